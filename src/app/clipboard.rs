@@ -0,0 +1,39 @@
+//! An abstraction layer for the system clipboard to enable testing.
+
+use anyhow::{Context, Result};
+use arboard::Clipboard;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+/// Defines a common interface for writing to the clipboard from the backend.
+/// This allows for a mock implementation during tests, so nothing is copied
+/// to the real system clipboard.
+pub trait ClipboardService: Send + Sync {
+    /// Replaces the clipboard's content with `text`.
+    fn copy_text(&self, text: &str) -> Result<()>;
+}
+
+/// The production implementation that uses the `arboard` crate to write to the
+/// system clipboard.
+pub struct SystemClipboardService;
+
+/// The clipboard handle, kept alive for the lifetime of the app. On X11 and Wayland
+/// the copied text is only served while its owning handle exists.
+static CLIPBOARD: OnceLock<Mutex<Option<Clipboard>>> = OnceLock::new();
+
+impl ClipboardService for SystemClipboardService {
+    fn copy_text(&self, text: &str) -> Result<()> {
+        let mut clipboard = CLIPBOARD
+            .get_or_init(|| Mutex::new(None))
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        if clipboard.is_none() {
+            *clipboard = Some(Clipboard::new().context("Failed to access the clipboard")?);
+        }
+        clipboard
+            .as_mut()
+            .context("The clipboard is not initialized")?
+            .set_text(text)
+            .context("Failed to copy to the clipboard")
+    }
+}
@@ -5,21 +5,45 @@
 //! These handlers are responsible for interacting with the `AppState` and the `core`
 //! logic, and for sending `UserEvent`s back to the UI.
 
-use super::events::UserEvent;
+use super::clipboard::ClipboardService;
+use super::events::{LoadMoreChildren, PathRef, UiPreferences, UserEvent};
 use super::filtering; // SRP: Use the new filtering module
-use super::helpers::with_state_and_notify;
+use super::helpers::{lock_state, remember_session, with_state_and_notify};
 use super::proxy::EventProxy;
-use super::state::AppState;
+use super::state::{
+    modification_times, AppState, ContentSearchScope, ContentSearchThreshold, GenerationTarget,
+    HeldOutput, SelectionSet,
+};
 // VET: Import tasks and their new service structs/traits
 use super::tasks::{self, search_in_files, start_lazy_load_scan, start_scan_on_path};
-use super::view_model::{auto_expand_for_matches, generate_ui_state, get_language_from_path};
+use super::view_model::{
+    auto_expand_for_matches, generate_ui_state, generation_diff, generation_plan,
+    get_selected_files_in_generation_order, get_selected_files_in_tree_order, name_filter,
+    selected_paths_text, tree_stats,
+};
 use crate::app::file_dialog::DialogService;
 use crate::config::{self, AppConfig}; // Import AppConfig for explicit deserialization
-use crate::core::FileHandler;
+use crate::core::git_status;
+use crate::core::ignore_rules::{ignore_preset, negated_pattern};
+use crate::core::references::{
+    self, MAX_AUTO_INCLUDED_FILES, MAX_REFERENCED_FILE_BYTES, MAX_SCANNED_FILE_BYTES,
+};
+use crate::core::{
+    FileHandler, FileItem, FilenameMatchMode, IgnoreRules, OutputFormat, SearchEngine,
+    TreeGenerator,
+};
+use crate::platform::{disk, editor};
+use crate::utils::file_detection::{get_language_from_path, LanguageOverrides};
+use crate::utils::unicode::nfc_path;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
 
 /// Opens a file dialog for the user to select a directory to scan.
 ///
@@ -36,9 +60,7 @@ pub fn select_directory<P: EventProxy, D: DialogService + ?Sized>(
     } else {
         tracing::info!("LOG: User cancelled directory selection.");
         // Manually notify on cancellation as no state mutation happens that would trigger the helper
-        let mut state_guard = state
-            .lock()
-            .expect("Mutex was poisoned. This should not happen.");
+        let mut state_guard = lock_state(&state);
         state_guard.is_scanning = false;
         let event = UserEvent::StateUpdate(Box::new(generate_ui_state(&state_guard)));
         proxy.send_event(event);
@@ -48,6 +70,7 @@ pub fn select_directory<P: EventProxy, D: DialogService + ?Sized>(
 /// Clears the currently loaded directory and resets the application state.
 pub fn clear_directory<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
     with_state_and_notify(&state, &proxy, |s| {
+        remember_session(s);
         s.reset_directory_state();
         s.config.last_directory = None;
         if let Err(e) = config::settings::save_config(&s.config, None) {
@@ -62,9 +85,7 @@ pub fn clear_directory<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
 /// the file list from the filesystem.
 pub fn rescan_directory<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
     let current_path_str = {
-        let mut state_guard = state
-            .lock()
-            .expect("Mutex was poisoned. This should not happen.");
+        let mut state_guard = lock_state(&state);
 
         state_guard.patterns_need_rescan = false;
         state_guard.current_path.clone()
@@ -89,10 +110,10 @@ pub async fn update_config<P: EventProxy>(
     proxy: P,
     state: Arc<Mutex<AppState>>,
 ) {
-    if let Ok(new_config) = serde_json::from_value::<AppConfig>(payload.clone()) {
-        let mut state_guard = state
-            .lock()
-            .expect("Mutex was poisoned. This should not happen.");
+    if let Ok(mut new_config) = serde_json::from_value::<AppConfig>(payload.clone()) {
+        let mut state_guard = lock_state(&state);
+        // The UI never sees the raw values of expanded paths, so keep them from the old config.
+        new_config.unexpanded_paths = state_guard.config.unexpanded_paths.clone();
 
         let patterns_added: HashSet<String> = new_config
             .ignore_patterns
@@ -109,7 +130,23 @@ pub async fn update_config<P: EventProxy>(
 
         let needs_refilter = state_guard.config.remove_empty_directories
             != new_config.remove_empty_directories
-            || state_guard.config.case_sensitive_search != new_config.case_sensitive_search;
+            || state_guard.config.case_sensitive_search != new_config.case_sensitive_search
+            || state_guard.config.min_file_size_bytes != new_config.min_file_size_bytes
+            || state_guard.config.max_file_size_bytes != new_config.max_file_size_bytes
+            || state_guard.config.exclude_empty_files != new_config.exclude_empty_files
+            || state_guard.config.modified_within_days != new_config.modified_within_days
+            || state_guard.config.overlay_ignore_patterns() != new_config.overlay_ignore_patterns();
+        let needs_rerender = state_guard.config.sort_order != new_config.sort_order;
+        let case_toggled =
+            state_guard.config.case_sensitive_search != new_config.case_sensitive_search;
+        let hidden_toggled = state_guard.config.include_hidden != new_config.include_hidden;
+        let git_status_toggled = state_guard.config.show_git_status != new_config.show_git_status;
+        let git_tracked_toggled =
+            state_guard.config.only_git_tracked != new_config.only_git_tracked;
+        let export_ignore_toggled =
+            state_guard.config.export_ignore_enabled() != new_config.export_ignore_enabled();
+        let extension_overrides_changed =
+            state_guard.config.extension_overrides() != new_config.extension_overrides();
 
         state_guard.config = new_config;
         if let Err(e) = config::settings::save_config(&state_guard.config, None) {
@@ -123,13 +160,84 @@ pub async fn update_config<P: EventProxy>(
 
         let mut should_send_update = false;
 
-        if !patterns_removed.is_empty() {
+        if git_status_toggled {
+            if state_guard.config.show_git_status {
+                // The status is read once per scan.
+                state_guard.patterns_need_rescan = true;
+            } else {
+                let state = &mut *state_guard;
+                state.git_statuses.clear();
+                git_status::annotate(&mut state.full_file_list, &state.git_statuses);
+                git_status::annotate(&mut state.pattern_ignored_items, &state.git_statuses);
+                filtering::apply_filters(state);
+            }
+            should_send_update = true;
+        }
+
+        if git_tracked_toggled {
+            if state_guard.config.only_git_tracked {
+                // The tracked files are listed once per scan.
+                state_guard.patterns_need_rescan = true;
+            } else {
+                state_guard.git_tracked_paths = None;
+                filtering::apply_filters(&mut state_guard);
+            }
+            should_send_update = true;
+        }
+
+        if export_ignore_toggled {
+            if state_guard.config.export_ignore_enabled() {
+                // The attributes files are read once per scan.
+                state_guard.patterns_need_rescan = true;
+            } else {
+                state_guard.export_ignore = None;
+                filtering::apply_filters(&mut state_guard);
+            }
+            should_send_update = true;
+        }
+
+        if extension_overrides_changed {
+            // Files are classified as text or binary while scanning.
+            state_guard.patterns_need_rescan = true;
+            should_send_update = true;
+        }
+
+        // An added `!` pattern can re-include files just like a removed pattern. Files
+        // that were excluded while scanning are not in memory and need a re-scan.
+        let patterns_re_included: HashSet<&String> = patterns_added
+            .iter()
+            .filter(|pattern| negated_pattern(pattern).is_some())
+            .collect();
+        let patterns_loosened = !patterns_removed.is_empty() || !patterns_re_included.is_empty();
+
+        if hidden_toggled {
+            // Hidden entries are filtered by the walker itself, so either direction
+            // needs a re-walk to bring the file list in line with the setting.
+            tracing::info!("⚠️ Hidden file visibility changed. Re-scan recommended.");
+            state_guard.patterns_need_rescan = true;
+            should_send_update = true;
+        } else if patterns_loosened
+            && !state_guard
+                .can_unignore_in_place(&patterns_removed, !patterns_re_included.is_empty())
+        {
             tracing::info!(
-                "⚠️ Ignore patterns removed: {:?}. Re-scan recommended.",
-                patterns_removed
+                "⚠️ Ignore patterns removed: {:?}, overrides added: {:?}. Re-scan recommended.",
+                patterns_removed,
+                patterns_re_included
             );
             state_guard.patterns_need_rescan = true;
             should_send_update = true;
+        } else if patterns_loosened {
+            tracing::info!(
+                "✓ Restoring entries of removed ignore patterns locally: {:?}",
+                patterns_removed
+            );
+            state_guard
+                .active_ignore_patterns
+                .retain(|pattern| !patterns_removed.contains(pattern));
+            state_guard.restore_pattern_ignored_items();
+            filtering::apply_filters(&mut state_guard);
+            should_send_update = true;
         } else if !patterns_added.is_empty() {
             tracing::info!(
                 "✓ Applying new ignore patterns locally: {:?}",
@@ -138,19 +246,14 @@ pub async fn update_config<P: EventProxy>(
 
             let root_path = PathBuf::from(&state_guard.current_path);
             for pattern in &patterns_added {
-                let mut builder = ignore::gitignore::GitignoreBuilder::new(&root_path);
-                if builder.add_line(None, pattern).is_ok() {
-                    if let Ok(matcher) = builder.build() {
-                        let has_match = state_guard.full_file_list.iter().any(|item| {
-                            matcher
-                                .matched_path_or_any_parents(&item.path, item.is_directory)
-                                .is_ignore()
-                        });
-
-                        if has_match {
-                            state_guard.active_ignore_patterns.insert(pattern.clone());
-                        }
-                    }
+                let rules = IgnoreRules::new(&root_path, [pattern]);
+                let has_match = state_guard
+                    .full_file_list
+                    .iter()
+                    .any(|item| rules.is_ignored_or_any_parents(&item.path, item.is_directory));
+
+                if has_match {
+                    state_guard.active_ignore_patterns.insert(pattern.clone());
                 }
             }
 
@@ -161,12 +264,24 @@ pub async fn update_config<P: EventProxy>(
             tracing::info!("🚀 Re-applying filters due to config change.");
             filtering::apply_filters(&mut state_guard);
             should_send_update = true;
+        } else if needs_rerender {
+            should_send_update = true;
         }
 
         if should_send_update {
             let ui_state = generate_ui_state(&state_guard);
             proxy.send_event(UserEvent::StateUpdate(Box::new(ui_state)));
         }
+
+        if case_toggled && !state_guard.content_search_query.is_empty() {
+            // The content search results were matched with the previous case sensitivity.
+            tracing::info!("🔍 Re-running the content search after a case sensitivity change.");
+            state_guard.content_search_limit = None;
+            let cancel_flag = state_guard.cancel_current_search();
+            let searcher = tasks::RealFileSearcher::new(&state_guard.config);
+            drop(state_guard);
+            search_in_files(proxy, state, searcher, cancel_flag).await;
+        }
     } else {
         tracing::warn!(
             "Failed to deserialize AppConfig from payload: {:?}",
@@ -177,9 +292,7 @@ pub async fn update_config<P: EventProxy>(
 
 /// Handles the initial request for state from the frontend when it loads.
 pub fn initialize<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
-    let state_guard = state
-        .lock()
-        .expect("Mutex was poisoned. This should not happen.");
+    let state_guard = lock_state(&state);
     let event = UserEvent::StateUpdate(Box::new(generate_ui_state(&state_guard)));
     proxy.send_event(event);
 }
@@ -187,19 +300,32 @@ pub fn initialize<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
 /// Updates the filename, extension, and content search filters.
 ///
 /// If the content search query has changed, it triggers a new content search task.
-/// Otherwise, it just re-applies the filename and extension filters on the existing file list.
+/// Otherwise, it just re-applies the filename and extension filters on the existing file list,
+/// after waiting `filter_debounce_ms` and only if no newer call arrived in the meantime.
 pub async fn update_filters<P: EventProxy>(
     payload: serde_json::Value,
     proxy: P,
     state: Arc<Mutex<AppState>>,
 ) {
     if let Ok(filters) = serde_json::from_value::<HashMap<String, String>>(payload.clone()) {
-        let should_search_content = {
-            let mut state_guard = state
-                .lock()
-                .expect("Mutex was poisoned. This should not happen.");
+        let (should_search_content, searcher, cancel_flag, request, debounce) = {
+            let mut state_guard = lock_state(&state);
+            state_guard.filter_request_seq += 1;
 
             state_guard.search_query = filters.get("searchQuery").cloned().unwrap_or_default();
+            state_guard.filename_match_mode = filters
+                .get("filenameMatchMode")
+                .and_then(|mode| {
+                    serde_json::from_value::<FilenameMatchMode>(mode.as_str().into()).ok()
+                })
+                .unwrap_or_default();
+            // An invalid glob matches nothing; tell the user why.
+            if let Err(e) = SearchEngine::compile_glob(&name_filter(&state_guard)) {
+                proxy.send_event(UserEvent::Status(format!(
+                    "Invalid glob pattern: {}",
+                    e.kind()
+                )));
+            }
             state_guard.extension_filter =
                 filters.get("extensionFilter").cloned().unwrap_or_default();
 
@@ -207,19 +333,64 @@ pub async fn update_filters<P: EventProxy>(
                 .get("contentSearchQuery")
                 .cloned()
                 .unwrap_or_default();
+            let new_scope = filters
+                .get("contentSearchScope")
+                .and_then(|scope| {
+                    serde_json::from_value::<ContentSearchScope>(scope.as_str().into()).ok()
+                })
+                .unwrap_or_default();
+            let defaults = ContentSearchThreshold::default();
+            let new_threshold = ContentSearchThreshold {
+                min_matches: filters
+                    .get("contentSearchMinMatches")
+                    .and_then(|v| v.trim().parse().ok())
+                    .unwrap_or(defaults.min_matches)
+                    .max(1),
+                min_matches_per_kb: filters
+                    .get("contentSearchMinMatchesPerKb")
+                    .and_then(|v| v.trim().parse::<f64>().ok())
+                    .filter(|ratio| ratio.is_finite() && *ratio >= 0.0)
+                    .unwrap_or(defaults.min_matches_per_kb),
+            };
 
-            if new_content_query != state_guard.content_search_query {
+            let changed = new_content_query != state_guard.content_search_query
+                || new_scope != state_guard.content_search_scope
+                || new_threshold != state_guard.content_search_threshold;
+            let cancel_flag = if changed {
+                if new_content_query != state_guard.content_search_query {
+                    // The preview's matches were found for the previous query.
+                    state_guard.reset_preview_matches(Vec::new());
+                }
                 state_guard.content_search_query = new_content_query;
-                true
+                state_guard.content_search_scope = new_scope;
+                state_guard.content_search_threshold = new_threshold;
+                state_guard.content_search_limit = None;
+                // A search for the previous query is now stale.
+                state_guard.cancel_current_search()
             } else {
-                false
-            }
+                state_guard.search_cancellation_flag.clone()
+            };
+            (
+                changed,
+                tasks::RealFileSearcher::new(&state_guard.config),
+                cancel_flag,
+                state_guard.filter_request_seq,
+                Duration::from_millis(state_guard.config.filter_debounce_ms),
+            )
         };
 
         if should_search_content {
-            let searcher = tasks::RealFileSearcher;
-            search_in_files(proxy, state, searcher).await;
+            search_in_files(proxy, state, searcher, cancel_flag).await;
         } else {
+            // Typing sends a call per keystroke. Only the last one of a burst re-filters,
+            // as auto-expanding and sending the state are costly on large trees.
+            if !debounce.is_zero() {
+                tokio::time::sleep(debounce).await;
+                let superseded = lock_state(&state).filter_request_seq != request;
+                if superseded {
+                    return;
+                }
+            }
             with_state_and_notify(&state, &proxy, |s| {
                 filtering::apply_filters(s);
                 if !s.search_query.is_empty() || !s.extension_filter.is_empty() {
@@ -232,20 +403,78 @@ pub async fn update_filters<P: EventProxy>(
     }
 }
 
+/// Re-runs the current content search keeping another `max_search_results` files.
+/// Does nothing if the last search kept all of its matches.
+pub async fn load_more_search_results<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    let (searcher, cancel_flag) = {
+        let mut state_guard = lock_state(&state);
+        if state_guard.content_search_query.is_empty() || !state_guard.content_search_truncated {
+            return;
+        }
+        let step = state_guard.config.max_search_results;
+        let limit = state_guard.content_search_limit.unwrap_or(step);
+        state_guard.content_search_limit = Some(limit.saturating_add(step.max(1)));
+        (
+            tasks::RealFileSearcher::new(&state_guard.config),
+            state_guard.cancel_current_search(),
+        )
+    };
+    search_in_files(proxy, state, searcher, cancel_flag).await;
+}
+
+/// Shows another `max_children_displayed` entries of a directory, or of the root, in
+/// the tree after the `offset` entries already shown.
+pub fn load_more_children<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let Ok(request) = serde_json::from_value::<LoadMoreChildren>(payload.clone()) else {
+        tracing::warn!(
+            "Failed to deserialize loadMoreChildren payload: {:?}",
+            payload
+        );
+        return;
+    };
+    with_state_and_notify(&state, &proxy, |s| {
+        let Some(step) = s.config.max_children_displayed else {
+            return;
+        };
+        let shown = request.offset.saturating_add(step.max(1));
+        let entry = s.children_shown.entry(request.path).or_insert(0);
+        *entry = (*entry).max(shown);
+    });
+}
+
+/// Deserializes a `PathRef` payload and resolves it to the exact path it refers to.
+fn resolve_path_payload(
+    payload: &serde_json::Value,
+    state: &Arc<Mutex<AppState>>,
+) -> Option<PathBuf> {
+    let path_ref = serde_json::from_value::<PathRef>(payload.clone()).ok()?;
+    lock_state(state).resolve_path_ref(path_ref)
+}
+
 /// Loads a file's content and sends it to the UI for preview.
 pub fn load_file_preview<P: EventProxy>(
     payload: serde_json::Value,
     proxy: P,
     state: Arc<Mutex<AppState>>,
 ) {
-    if let Ok(path_str) = serde_json::from_value::<String>(payload.clone()) {
-        let path = PathBuf::from(path_str);
+    if let Some(path) = resolve_path_payload(&payload, &state) {
         let search_term;
+        let max_lines;
+        let overrides;
+        let languages;
+        let case_sensitive;
         {
-            let mut state_guard = state
-                .lock()
-                .expect("Mutex was poisoned. This should not happen.");
+            let mut state_guard = lock_state(&state);
             state_guard.previewed_file_path = Some(path.clone());
+            state_guard.generated_content = None;
+            max_lines = state_guard.config.preview_max_lines;
+            overrides = state_guard.config.extension_overrides();
+            languages = LanguageOverrides::build(&state_guard.config.language_overrides);
+            case_sensitive = state_guard.config.case_sensitive_search;
             search_term = if state_guard.content_search_query.is_empty() {
                 None
             } else {
@@ -253,32 +482,35 @@ pub fn load_file_preview<P: EventProxy>(
             };
         }
 
-        match FileHandler::get_file_preview(&path, 1500) {
-            Ok(content) => {
+        let match_lines = match FileHandler::get_file_preview(&path, max_lines, &overrides) {
+            Ok(preview) => {
+                let match_lines = search_term
+                    .as_deref()
+                    .map(|query| tasks::matching_lines(&preview.content, query, case_sensitive))
+                    .unwrap_or_default();
                 let event = UserEvent::ShowFilePreview {
-                    content,
-                    language: get_language_from_path(&path),
+                    content: preview.content,
+                    language: get_language_from_path(&path, languages.as_deref()),
                     search_term,
                     path: path.clone(),
+                    truncation: preview.total_lines.map(|total| (max_lines, total)),
                 };
                 proxy.send_event(event);
+                match_lines
             }
             Err(e) => {
                 proxy.send_event(UserEvent::ShowError(e.to_string()));
+                Vec::new()
             }
-        }
+        };
 
         // Send a state update to reflect the `previewed_file_path` change in the UI (highlighting).
-        let state_guard = state
-            .lock()
-            .expect("Mutex was poisoned. This should not happen.");
+        let mut state_guard = lock_state(&state);
+        state_guard.reset_preview_matches(match_lines);
         let event = UserEvent::StateUpdate(Box::new(generate_ui_state(&state_guard)));
         proxy.send_event(event);
     } else {
-        tracing::warn!(
-            "Failed to deserialize path string from payload: {:?}",
-            payload
-        );
+        tracing::warn!("Failed to resolve path from payload: {:?}", payload);
     }
 }
 
@@ -289,14 +521,23 @@ pub fn load_directory_level<P: EventProxy>(
     proxy: P,
     state: Arc<Mutex<AppState>>,
 ) {
-    if let Ok(path_str) = serde_json::from_value::<String>(payload.clone()) {
-        let path = PathBuf::from(path_str);
+    if let Some(path) = resolve_path_payload(&payload, &state) {
         start_lazy_load_scan(path, proxy, state, None);
     } else {
-        tracing::warn!(
-            "Failed to deserialize path string from payload: {:?}",
-            payload
-        );
+        tracing::warn!("Failed to resolve path from payload: {:?}", payload);
+    }
+}
+
+/// Re-scans a single directory to its full depth, see [`tasks::start_subtree_rescan`].
+pub fn rescan_subtree<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    if let Some(path) = resolve_path_payload(&payload, &state) {
+        tasks::start_subtree_rescan(path, proxy, state, None);
+    } else {
+        tracing::warn!("Failed to resolve path from payload: {:?}", payload);
     }
 }
 
@@ -311,40 +552,161 @@ pub async fn add_ignore_path<P: EventProxy>(
     proxy: P,
     state: Arc<Mutex<AppState>>,
 ) {
-    if let Ok(path_str) = serde_json::from_value::<String>(payload) {
-        let (current_path_str, mut new_config) = {
-            let state_guard = state
-                .lock()
-                .expect("Mutex was poisoned. This should not happen.");
-            if state_guard.current_path.is_empty() {
-                return;
-            }
-            (state_guard.current_path.clone(), state_guard.config.clone())
+    if let Some(path) = resolve_path_payload(&payload, &state) {
+        add_ignore_patterns_for_paths(vec![path], proxy, state).await;
+    } else {
+        tracing::warn!("Failed to resolve path from payload for add_ignore_path");
+    }
+}
+
+/// Adds multiple paths to the ignore list in a single batch.
+///
+/// Uses the same pattern rules as `add_ignore_path`, but applies all new patterns
+/// with a single `update_config` call so only one re-scan is triggered.
+pub async fn add_ignore_paths<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    if let Ok(path_refs) = serde_json::from_value::<Vec<PathRef>>(payload) {
+        let paths = {
+            let state_guard = lock_state(&state);
+            path_refs
+                .into_iter()
+                .filter_map(|path_ref| state_guard.resolve_path_ref(path_ref))
+                .collect()
         };
+        add_ignore_patterns_for_paths(paths, proxy, state).await;
+    } else {
+        tracing::warn!("Failed to deserialize path array from payload for add_ignore_paths");
+    }
+}
 
-        let path_to_ignore = PathBuf::from(path_str);
-        let root_path = PathBuf::from(&current_path_str);
+/// Reports which files of the current file list a single ignore pattern would hide.
+/// Read-only: neither the config nor the file list is changed.
+pub fn preview_ignore_matches<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let Ok(pattern) = serde_json::from_value::<String>(payload) else {
+        tracing::warn!("Failed to deserialize pattern from payload for preview_ignore_matches");
+        return;
+    };
+    let pattern = pattern.trim().to_string();
+    let matches = lock_state(&state).ignore_pattern_matches(&pattern);
+    proxy.send_event(UserEvent::IgnorePreview { pattern, matches });
+}
 
-        if let Ok(relative_path) = path_to_ignore.strip_prefix(&root_path) {
-            let mut pattern_to_add = relative_path.to_string_lossy().to_string();
+/// Explains why a path is missing from the file tree: which ignore rule, scan limit
+/// or filter hides it. Relative paths are resolved against the current directory.
+pub fn explain_hidden<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let Some(path) = resolve_path_payload(&payload, &state) else {
+        tracing::warn!("Failed to resolve path from payload: {:?}", payload);
+        return;
+    };
+    let (path, reasons) = {
+        let state_guard = lock_state(&state);
+        let path = Path::new(&state_guard.current_path).join(path);
+        let reasons = filtering::explain_hidden(&state_guard, &path)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        (path, reasons)
+    };
+    proxy.send_event(UserEvent::HiddenExplanation { path, reasons });
+}
 
-            if path_to_ignore.is_dir() && !pattern_to_add.ends_with('/') {
-                pattern_to_add.push('/');
-            }
+/// Merges the named ignore preset (e.g. `rust` or `node`) into the ignore patterns
+/// and applies the result via `update_config`.
+pub async fn apply_ignore_preset<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let Ok(name) = serde_json::from_value::<String>(payload) else {
+        tracing::warn!("Failed to deserialize preset name from payload for apply_ignore_preset");
+        return;
+    };
+    let Some(patterns) = ignore_preset(&name) else {
+        proxy.send_event(UserEvent::ShowError(format!(
+            "Unknown ignore preset: '{name}'"
+        )));
+        return;
+    };
 
-            if new_config.ignore_patterns.insert(pattern_to_add) {
-                match serde_json::to_value(new_config) {
-                    Ok(config_payload) => {
-                        update_config(config_payload, proxy, state).await;
-                    }
-                    Err(e) => {
-                        tracing::error!("Failed to serialize config for update: {}", e);
-                    }
-                }
+    let mut new_config = lock_state(&state).config.clone();
+    let before = new_config.ignore_patterns.len();
+    new_config
+        .ignore_patterns
+        .extend(patterns.iter().map(|p| p.to_string()));
+    if new_config.ignore_patterns.len() == before {
+        return;
+    }
+
+    match serde_json::to_value(new_config) {
+        Ok(config_payload) => update_config(config_payload, proxy, state).await,
+        Err(e) => tracing::error!("Failed to serialize config for update: {}", e),
+    }
+}
+
+/// Converts an absolute path into an ignore pattern relative to `root_path`.
+///
+/// Components are joined with `/` on every platform, as gitignore patterns require.
+/// Directory patterns get a trailing slash. Returns `None` for paths outside the root.
+fn ignore_pattern_for_path(path_to_ignore: &Path, root_path: &Path) -> Option<String> {
+    // Compared in NFC, so a typed root matches the decomposed paths macOS reports.
+    let (path, root) = (nfc_path(path_to_ignore), nfc_path(root_path));
+    let relative_path = path.strip_prefix(&root).ok()?;
+    // Patterns are strings, so bytes that are not valid UTF-8 cannot be written
+    // literally. A wildcard in their place still matches the original name.
+    let mut pattern = relative_path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+        .replace(char::REPLACEMENT_CHARACTER, "*");
+    if path_to_ignore.is_dir() && !pattern.ends_with('/') {
+        pattern.push('/');
+    }
+    Some(pattern)
+}
+
+/// Shared implementation of `add_ignore_path` and `add_ignore_paths`.
+async fn add_ignore_patterns_for_paths<P: EventProxy>(
+    paths: Vec<PathBuf>,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let (current_path_str, mut new_config) = {
+        let state_guard = lock_state(&state);
+        if state_guard.current_path.is_empty() {
+            return;
+        }
+        (state_guard.current_path.clone(), state_guard.config.clone())
+    };
+
+    let root_path = PathBuf::from(&current_path_str);
+    let mut any_inserted = false;
+    for path_to_ignore in &paths {
+        if let Some(pattern_to_add) = ignore_pattern_for_path(path_to_ignore, &root_path) {
+            any_inserted |= new_config.ignore_patterns.insert(pattern_to_add);
+        }
+    }
+
+    if any_inserted {
+        match serde_json::to_value(new_config) {
+            Ok(config_payload) => {
+                update_config(config_payload, proxy, state).await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to serialize config for update: {}", e);
             }
         }
-    } else {
-        tracing::warn!("Failed to deserialize path string from payload for add_ignore_path");
     }
 }
 
@@ -354,9 +716,8 @@ pub fn toggle_selection<P: EventProxy>(
     proxy: P,
     state: Arc<Mutex<AppState>>,
 ) {
-    if let Ok(path_str) = serde_json::from_value::<String>(payload.clone()) {
+    if let Some(path) = resolve_path_payload(&payload, &state) {
         with_state_and_notify(&state, &proxy, |s| {
-            let path = PathBuf::from(path_str);
             if s.selected_files.contains(&path) {
                 s.selected_files.remove(&path);
             } else {
@@ -364,10 +725,7 @@ pub fn toggle_selection<P: EventProxy>(
             }
         });
     } else {
-        tracing::warn!(
-            "Failed to deserialize path string from payload: {:?}",
-            payload
-        );
+        tracing::warn!("Failed to resolve path from payload: {:?}", payload);
     }
 }
 
@@ -377,9 +735,8 @@ pub fn toggle_directory_selection<P: EventProxy>(
     proxy: P,
     state: Arc<Mutex<AppState>>,
 ) {
-    if let Ok(path_str) = serde_json::from_value::<String>(payload.clone()) {
+    if let Some(dir_path) = resolve_path_payload(&payload, &state) {
         with_state_and_notify(&state, &proxy, |s| {
-            let dir_path = PathBuf::from(path_str);
             let selection_state = super::view_model::get_directory_selection_state(
                 &dir_path,
                 &s.filtered_file_list,
@@ -387,30 +744,130 @@ pub fn toggle_directory_selection<P: EventProxy>(
             );
 
             // Important: only operate on the currently *visible* files in that directory
-            let files_in_dir: Vec<PathBuf> = s
+            let in_dir: Vec<&FileItem> = s
                 .filtered_file_list
                 .iter()
                 .filter(|item| !item.is_directory && item.path.starts_with(&dir_path))
+                .collect();
+            let selectable: Vec<PathBuf> = in_dir
+                .iter()
+                .filter(|item| s.config.is_bulk_selectable(item))
                 .map(|item| item.path.clone())
                 .collect();
-
-            if selection_state == "full" {
+            let files_in_dir: Vec<PathBuf> = in_dir.iter().map(|item| item.path.clone()).collect();
+            // Skipped generated files keep the directory "partial", so also treat it as
+            // fully selected once every selectable file is.
+            let fully_selected = selection_state == "full"
+                || (!selectable.is_empty()
+                    && selectable
+                        .iter()
+                        .all(|file| s.selected_files.contains(file)));
+
+            if fully_selected {
                 // If fully selected, deselect all
                 for file in files_in_dir {
                     s.selected_files.remove(&file);
                 }
             } else {
                 // If partially or not selected, select all
-                for file in files_in_dir {
-                    s.selected_files.insert(file);
-                }
+                s.selected_files.extend(selectable);
             }
         });
     } else {
-        tracing::warn!(
-            "Failed to deserialize path string from payload: {:?}",
-            payload
-        );
+        tracing::warn!("Failed to resolve path from payload: {:?}", payload);
+    }
+}
+
+/// Sets the order in which selected files are concatenated. The payload lists tree
+/// entries; selected files not listed follow in tree order. `null` or an empty list
+/// reverts to tree order.
+pub fn set_generation_order<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let Ok(path_refs) = serde_json::from_value::<Option<Vec<PathRef>>>(payload) else {
+        tracing::warn!("Failed to deserialize path array from payload for set_generation_order");
+        return;
+    };
+    with_state_and_notify(&state, &proxy, |s| {
+        let order: Vec<PathBuf> = path_refs
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|path_ref| s.resolve_path_ref(path_ref))
+            .collect();
+        s.generation_order_override = (!order.is_empty()).then_some(order);
+    });
+}
+
+/// Pins a file and selects it. Every later scan that finds the file selects it again,
+/// even if it was missing in between. Pins are saved in the config.
+pub fn pin_file<P: EventProxy>(payload: serde_json::Value, proxy: P, state: Arc<Mutex<AppState>>) {
+    set_pinned(payload, true, proxy, state);
+}
+
+/// Removes a file's pin. Its current selection is left unchanged.
+pub fn unpin_file<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    set_pinned(payload, false, proxy, state);
+}
+
+/// Shared implementation of `pin_file` and `unpin_file`.
+fn set_pinned<P: EventProxy>(
+    payload: serde_json::Value,
+    pinned: bool,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let Some(path) = resolve_path_payload(&payload, &state) else {
+        tracing::warn!("Failed to resolve path from payload: {:?}", payload);
+        return;
+    };
+    with_state_and_notify(&state, &proxy, |s| {
+        let changed = if pinned {
+            s.selected_files.insert(path.clone());
+            s.config.pinned_files.insert(path)
+        } else {
+            s.config.pinned_files.remove(&path)
+        };
+        if changed {
+            if let Err(e) = config::settings::save_config(&s.config, None) {
+                tracing::warn!("Failed to save config after changing pins: {}", e);
+            }
+        }
+    });
+}
+
+/// Sets the UI zoom and theme, saves them and applies them right away. The zoom is
+/// clamped to `UI_ZOOM_RANGE`; a preference missing from the payload is left as is.
+pub fn set_ui_preferences<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let Ok(preferences) = serde_json::from_value::<UiPreferences>(payload.clone()) else {
+        tracing::warn!("Invalid UI preferences: {:?}", payload);
+        return;
+    };
+    let mut zoom = None;
+    with_state_and_notify(&state, &proxy, |s| {
+        if let Some(ui_zoom) = preferences.ui_zoom.filter(|zoom| zoom.is_finite()) {
+            let (min, max) = config::UI_ZOOM_RANGE;
+            s.config.ui_zoom = ui_zoom.clamp(min, max);
+            zoom = Some(s.config.ui_zoom);
+        }
+        if let Some(theme) = preferences.theme {
+            s.config.theme = theme;
+        }
+        if let Err(e) = config::settings::save_config(&s.config, None) {
+            tracing::warn!("Failed to save config on UI preferences update: {}", e);
+        }
+    });
+    if let Some(zoom) = zoom {
+        proxy.send_event(UserEvent::SetZoom(zoom));
     }
 }
 
@@ -420,9 +877,8 @@ pub fn toggle_expansion<P: EventProxy>(
     proxy: P,
     state: Arc<Mutex<AppState>>,
 ) {
-    if let Ok(path_str) = serde_json::from_value::<String>(payload.clone()) {
+    if let Some(path) = resolve_path_payload(&payload, &state) {
         with_state_and_notify(&state, &proxy, |s| {
-            let path = PathBuf::from(path_str);
             if s.expanded_dirs.contains(&path) {
                 s.expanded_dirs.remove(&path);
             } else {
@@ -430,64 +886,264 @@ pub fn toggle_expansion<P: EventProxy>(
             }
         });
     } else {
-        tracing::warn!(
-            "Failed to deserialize path string from payload: {:?}",
-            payload
-        );
+        tracing::warn!("Failed to resolve path from payload: {:?}", payload);
     }
 }
 
-/// Expands or collapses all *currently visible* directories in the file tree.
-pub fn expand_collapse_all<P: EventProxy>(
+/// Expands exactly the ancestors of a file or directory below `current_path`, so it
+/// becomes visible in the tree. Other directories keep their expansion state. Ancestors
+/// whose children were not loaded yet are loaded lazily.
+pub fn reveal_path<P: EventProxy>(
     payload: serde_json::Value,
     proxy: P,
     state: Arc<Mutex<AppState>>,
 ) {
-    if let Ok(expand) = serde_json::from_value::<bool>(payload.clone()) {
-        with_state_and_notify(&state, &proxy, |s| {
-            if expand {
-                s.expanded_dirs = s
-                    .filtered_file_list
-                    .iter()
-                    .filter(|i| i.is_directory)
-                    .map(|i| i.path.clone())
-                    .collect();
-            } else {
-                s.expanded_dirs.clear();
-            }
-        });
-    } else {
-        tracing::warn!("Failed to deserialize boolean from payload: {:?}", payload);
-    }
-}
+    let Some(path) = resolve_path_payload(&payload, &state) else {
+        tracing::warn!("Failed to resolve path from payload: {:?}", payload);
+        return;
+    };
 
-/// Selects all *currently visible* files in the file tree.
-pub fn select_all<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    let mut dirs_to_load = Vec::new();
     with_state_and_notify(&state, &proxy, |s| {
-        let paths_to_select: Vec<PathBuf> = s
-            .filtered_file_list
-            .iter()
-            .filter(|item| !item.is_directory)
-            .map(|item| item.path.clone())
-            .collect();
-        s.selected_files.extend(paths_to_select);
+        dirs_to_load = expand_ancestors(s, &path);
     });
+
+    for dir in dirs_to_load.into_iter().rev() {
+        start_lazy_load_scan(dir, proxy.clone(), state.clone(), None);
+    }
 }
 
-/// Deselects all files.
-pub fn deselect_all<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+/// Collapses every directory except the ancestors of the previewed file, leaving a
+/// focused tree that shows just the path to it. Does nothing if no file is previewed.
+pub fn collapse_to_preview<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    let Some(path) = lock_state(&state).previewed_file_path.clone() else {
+        return;
+    };
+
+    let mut dirs_to_load = Vec::new();
     with_state_and_notify(&state, &proxy, |s| {
-        s.selected_files.clear();
+        s.expanded_dirs.clear();
+        dirs_to_load = expand_ancestors(s, &path);
     });
+
+    for dir in dirs_to_load.into_iter().rev() {
+        start_lazy_load_scan(dir, proxy.clone(), state.clone(), None);
+    }
 }
 
-/// Expands all directories after a full scan has completed.
-/// This command is intended to be used after the `is_fully_scanned` flag is true.
-pub fn expand_all_fully<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
-    with_state_and_notify(&state, &proxy, |s| {
-        if !s.is_fully_scanned {
-            tracing::warn!("expand_all_fully called before full scan completed. Ignoring.");
-            return;
+/// Expands the ancestors of `path` below `current_path` and returns those whose
+/// children were not loaded yet, nearest first.
+fn expand_ancestors(state: &mut AppState, path: &Path) -> Vec<PathBuf> {
+    let root = PathBuf::from(&state.current_path);
+    let mut dirs_to_load = Vec::new();
+    for ancestor in path
+        .ancestors()
+        .skip(1)
+        .take_while(|ancestor| ancestor.starts_with(&root) && *ancestor != root)
+    {
+        state.expanded_dirs.insert(ancestor.to_path_buf());
+        if !state.loaded_dirs.contains(ancestor) {
+            dirs_to_load.push(ancestor.to_path_buf());
+        }
+    }
+    dirs_to_load
+}
+
+/// Opens a file in the user's editor, see [`editor::open_in_editor`]. Failures to
+/// launch the editor are reported to the UI.
+pub fn open_in_editor<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let Some(path) = resolve_path_payload(&payload, &state) else {
+        tracing::warn!("Failed to resolve path from payload: {:?}", payload);
+        return;
+    };
+    if let Err(e) = editor::open_in_editor(&path) {
+        tracing::warn!("Failed to open {} in an editor: {e}", path.display());
+        proxy.send_event(UserEvent::ShowError(format!(
+            "Failed to open {} in an editor: {e}",
+            path.display()
+        )));
+    }
+}
+
+/// Toggles the selection of the node the keyboard cursor is on: a directory's visible
+/// files for a directory, the file itself otherwise.
+pub fn select_current<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let Some(path) = resolve_path_payload(&payload, &state) else {
+        tracing::warn!("Failed to resolve path from payload: {:?}", payload);
+        return;
+    };
+    let is_directory = lock_state(&state)
+        .full_file_list
+        .iter()
+        .find(|item| item.path == path)
+        .is_some_and(|item| item.is_directory);
+
+    if is_directory {
+        toggle_directory_selection(payload, proxy, state);
+    } else {
+        toggle_selection(payload, proxy, state);
+    }
+}
+
+/// Expands the directory the keyboard cursor is on. Does nothing if already expanded.
+pub fn expand_current<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    set_expansion(payload, true, proxy, state);
+}
+
+/// Collapses the directory the keyboard cursor is on. Does nothing if already collapsed.
+pub fn collapse_current<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    set_expansion(payload, false, proxy, state);
+}
+
+/// Sets the expanded state of a single directory, unlike `toggle_expansion`.
+fn set_expansion<P: EventProxy>(
+    payload: serde_json::Value,
+    expand: bool,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    if let Some(path) = resolve_path_payload(&payload, &state) {
+        with_state_and_notify(&state, &proxy, |s| {
+            if expand {
+                s.expanded_dirs.insert(path);
+            } else {
+                s.expanded_dirs.remove(&path);
+            }
+        });
+    } else {
+        tracing::warn!("Failed to resolve path from payload: {:?}", payload);
+    }
+}
+
+/// Expands or collapses all *currently visible* directories in the file tree.
+pub fn expand_collapse_all<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    if let Ok(expand) = serde_json::from_value::<bool>(payload.clone()) {
+        with_state_and_notify(&state, &proxy, |s| {
+            if expand {
+                s.expanded_dirs = s
+                    .filtered_file_list
+                    .iter()
+                    .filter(|i| i.is_directory)
+                    .map(|i| i.path.clone())
+                    .collect();
+            } else {
+                s.expanded_dirs.clear();
+            }
+        });
+    } else {
+        tracing::warn!("Failed to deserialize boolean from payload: {:?}", payload);
+    }
+}
+
+/// Selects all *currently visible* files in the file tree.
+pub fn select_all<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    with_state_and_notify(&state, &proxy, |s| {
+        let paths_to_select: Vec<PathBuf> = s
+            .filtered_file_list
+            .iter()
+            .filter(|item| s.config.is_bulk_selectable(item))
+            .map(|item| item.path.clone())
+            .collect();
+        s.selected_files.extend(paths_to_select);
+    });
+}
+
+/// Deselects all files.
+pub fn deselect_all<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    with_state_and_notify(&state, &proxy, |s| {
+        s.selected_files.clear();
+    });
+}
+
+/// Selects every visible file whose name ends with the given extension.
+pub fn select_by_extension<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    if let Ok(extension) = serde_json::from_value::<String>(payload) {
+        with_state_and_notify(&state, &proxy, |s| {
+            let paths_to_select = visible_files_with_extension(s, &extension, true);
+            s.selected_files.extend(paths_to_select);
+        });
+    }
+}
+
+/// Deselects every visible file whose name ends with the given extension.
+pub fn deselect_by_extension<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    if let Ok(extension) = serde_json::from_value::<String>(payload) {
+        with_state_and_notify(&state, &proxy, |s| {
+            for path in visible_files_with_extension(s, &extension, false) {
+                s.selected_files.remove(&path);
+            }
+        });
+    }
+}
+
+/// Collects the visible files whose name ends with `.extension`, ignoring ASCII case.
+/// A leading `*.` or `.` in `extension` is optional, so `rs`, `.rs` and `*.rs` are equal.
+/// With `selectable_only`, files left out of bulk selections are skipped.
+fn visible_files_with_extension(
+    state: &AppState,
+    extension: &str,
+    selectable_only: bool,
+) -> Vec<PathBuf> {
+    let extension = extension.trim();
+    let extension = extension
+        .strip_prefix("*.")
+        .or_else(|| extension.strip_prefix('.'))
+        .unwrap_or(extension);
+    if extension.is_empty() {
+        return Vec::new();
+    }
+    let suffix = format!(".{}", extension.to_ascii_lowercase());
+
+    state
+        .filtered_file_list
+        .iter()
+        .filter(|item| !item.is_directory)
+        .filter(|item| !selectable_only || state.config.is_bulk_selectable(item))
+        .filter(|item| {
+            item.path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.to_ascii_lowercase().ends_with(&suffix))
+        })
+        .map(|item| item.path.clone())
+        .collect()
+}
+
+/// Expands all directories after a full scan has completed.
+/// This command is intended to be used after the `is_fully_scanned` flag is true.
+pub fn expand_all_fully<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    with_state_and_notify(&state, &proxy, |s| {
+        if !s.is_fully_scanned {
+            tracing::warn!("expand_all_fully called before full scan completed. Ignoring.");
+            return;
         }
         s.expanded_dirs = s
             .filtered_file_list
@@ -509,7 +1165,7 @@ pub fn select_all_fully<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
         let paths_to_select: Vec<PathBuf> = s
             .filtered_file_list
             .iter()
-            .filter(|item| !item.is_directory)
+            .filter(|item| s.config.is_bulk_selectable(item))
             .map(|item| item.path.clone())
             .collect();
         s.selected_files.extend(paths_to_select);
@@ -517,23 +1173,200 @@ pub fn select_all_fully<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
 }
 
 /// Generates the final concatenated output from selected files by spawning a cancellable task.
+///
+/// If the selected files add up to more than `warn_over_bytes`, nothing is generated.
+/// The UI is asked to confirm instead, and generation starts on `confirm_generation`.
 pub fn generate_preview<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
-    let mut state_guard = state
-        .lock()
-        .expect("Mutex was poisoned. This should not happen.");
+    request_generation(proxy, state, GenerationTarget::Preview);
+}
+
+/// Generates the output like `generate_preview`, but copies it to the clipboard from
+/// the backend instead of sending it to the preview. Only the token count is reported,
+/// so large outputs never pass through the WebView.
+pub fn generate_to_clipboard<P: EventProxy, C: ClipboardService + 'static>(
+    clipboard: Arc<C>,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    lock_state(&state).generation_clipboard = Some(clipboard);
+    request_generation(proxy, state, GenerationTarget::Clipboard);
+}
 
+/// Starts a generation delivered to `target`, unless the size guard asks the user to
+/// confirm it first. With `auto_include_imports`, the files the selection refers to are
+/// selected first; the selected files are read for that in the background.
+fn request_generation<P: EventProxy>(
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+    target: GenerationTarget,
+) {
+    let mut state_guard = lock_state(&state);
+    state_guard.generation_target = target;
+    if !state_guard.config.auto_include_imports {
+        guard_generation(proxy, &state, &mut state_guard);
+        return;
+    }
+
+    let root = PathBuf::from(&state_guard.current_path);
+    let selected = get_selected_files_in_tree_order(&state_guard);
+    let text_files: HashMap<PathBuf, u64> = state_guard
+        .full_file_list
+        .iter()
+        .filter(|item| !item.is_directory && !item.is_binary)
+        .map(|item| (item.path.clone(), item.size))
+        .collect();
+    drop(state_guard);
+
+    tokio::spawn(async move {
+        let search_root = root.clone();
+        let found = tokio::task::spawn_blocking(move || {
+            referenced_files(&selected, &text_files, &search_root)
+        })
+        .await
+        .unwrap_or_default();
+
+        let mut state_guard = lock_state(&state);
+        if state_guard.current_path != root.to_string_lossy() {
+            tracing::info!("Dropping the generation, the directory changed meanwhile.");
+            return;
+        }
+        let added: Vec<PathBuf> = found
+            .into_iter()
+            .filter(|path| !state_guard.selected_files.contains(path))
+            .collect();
+        tracing::info!("Auto-included {} referenced files.", added.len());
+        if !added.is_empty() {
+            state_guard.selected_files.extend(added.iter().cloned());
+            proxy.send_event(UserEvent::FilesAutoIncluded(
+                added
+                    .iter()
+                    .map(|path| {
+                        path.strip_prefix(&root)
+                            .unwrap_or(path)
+                            .display()
+                            .to_string()
+                    })
+                    .collect(),
+            ));
+        }
+        guard_generation(proxy, &state, &mut state_guard);
+    });
+}
+
+/// Starts a generation, or asks the user to confirm it first if the selection is
+/// larger than `warn_over_bytes`.
+fn guard_generation<P: EventProxy>(
+    proxy: P,
+    state: &Arc<Mutex<AppState>>,
+    state_guard: &mut AppState,
+) {
+    if let Some(limit) = state_guard.config.warn_over_bytes {
+        let (bytes, files) = selection_size(state_guard);
+        if bytes > limit {
+            tracing::info!(
+                "Generation of {} files ({} bytes) awaits confirmation.",
+                files,
+                bytes
+            );
+            state_guard.generation_awaiting_confirmation = true;
+            proxy.send_event(UserEvent::ConfirmLargeGeneration { bytes, files });
+            return;
+        }
+    }
+    start_generation(proxy, state, state_guard);
+}
+
+/// Starts a generation that `generate_preview` held back for exceeding the size guard.
+pub fn confirm_generation<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    let mut state_guard = lock_state(&state);
+    if !std::mem::take(&mut state_guard.generation_awaiting_confirmation) {
+        tracing::warn!("Ignoring generation confirmation without a pending generation.");
+        return;
+    }
+    start_generation(proxy, &state, &mut state_guard);
+}
+
+/// Finds the files that the `selected` files name by a quoted relative path, see
+/// [`references::referenced_paths`]. Only one hop is followed: the found files are not
+/// searched themselves. Only files of `text_files` (paths with their sizes) up to
+/// `MAX_REFERENCED_FILE_BYTES` that are not selected yet are returned, at most
+/// `MAX_AUTO_INCLUDED_FILES` of them, in the order they were found. Reads the selected
+/// files, so it is run without holding the state lock.
+fn referenced_files(
+    selected: &[PathBuf],
+    text_files: &HashMap<PathBuf, u64>,
+    root: &Path,
+) -> Vec<PathBuf> {
+    let selected_set: HashSet<&PathBuf> = selected.iter().collect();
+    let mut found: Vec<PathBuf> = Vec::new();
+    'files: for file in selected {
+        if text_files
+            .get(file)
+            .is_none_or(|size| *size > MAX_SCANNED_FILE_BYTES)
+        {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(file) else {
+            continue;
+        };
+        for path in references::referenced_paths(&content, file, root) {
+            let is_small_text_file = text_files
+                .get(&path)
+                .is_some_and(|size| *size <= MAX_REFERENCED_FILE_BYTES);
+            if is_small_text_file && !selected_set.contains(&path) && !found.contains(&path) {
+                found.push(path);
+                if found.len() == MAX_AUTO_INCLUDED_FILES {
+                    break 'files;
+                }
+            }
+        }
+    }
+    found
+}
+
+/// The total size in bytes and the number of the selected files a generation would read.
+fn selection_size(state: &AppState) -> (u64, usize) {
+    let sizes: HashMap<&Path, u64> = state
+        .full_file_list
+        .iter()
+        .filter(|item| !item.is_directory)
+        .map(|item| (item.path.as_path(), item.size))
+        .collect();
+    get_selected_files_in_tree_order(state)
+        .iter()
+        .filter_map(|path| sizes.get(path.as_path()))
+        .fold((0, 0), |(bytes, files), size| (bytes + size, files + 1))
+}
+
+/// Spawns the generation task for the current selection.
+fn start_generation<P: EventProxy>(
+    proxy: P,
+    state: &Arc<Mutex<AppState>>,
+    state_guard: &mut AppState,
+) {
+    state_guard.generation_awaiting_confirmation = false;
     state_guard.cancel_current_generation();
     state_guard.is_generating = true;
-    state_guard.previewed_file_path = None;
+    if state_guard.generation_target == GenerationTarget::Preview {
+        state_guard.previewed_file_path = None;
+        state_guard.generated_content = None;
+    }
 
     // VET: CORRECTED LOGIC
     // Only generate a new timestamped filename if the current one appears to be a default.
     // This preserves any filename explicitly set by the user.
     let current_filename = &state_guard.config.output_filename;
-    if current_filename.starts_with("cfc_output_") && current_filename.ends_with(".txt") {
+    let is_default = current_filename.starts_with("cfc_output_")
+        && Path::new(current_filename)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(OutputFormat::from_extension)
+            .is_some();
+    if is_default {
         let new_filename = format!(
-            "cfc_output_{}.txt",
-            chrono::Local::now().format("%Y%m%d_%H%M%S")
+            "cfc_output_{}.{}",
+            chrono::Local::now().format("%Y%m%d_%H%M%S"),
+            state_guard.config.output_format.extension()
         );
         state_guard.config.output_filename = new_filename;
     }
@@ -543,11 +1376,12 @@ pub fn generate_preview<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
 
     // Send an immediate state update to the UI to show the 'generating' state.
     proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
-        &state_guard,
+        state_guard,
     ))));
 
     let real_generator = tasks::RealContentGenerator {
         cancel_flag: new_cancel_flag,
+        stream_generation: state_guard.config.stream_generation,
     };
     let real_tokenizer = tasks::RealTokenizer;
 
@@ -561,101 +1395,708 @@ pub fn generate_preview<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
     state_guard.generation_task = Some(handle);
 }
 
-/// Cancels the ongoing file content generation task.
+/// Cancels the ongoing file content generation task, or a generation that awaits
+/// confirmation.
 pub fn cancel_generation<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
     with_state_and_notify(&state, &proxy, |s| {
+        s.generation_awaiting_confirmation = false;
         s.cancel_current_generation();
     });
 }
 
-/// Resets the preview state in the UI.
-pub fn clear_preview_state<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
-    with_state_and_notify(&state, &proxy, |s| {
-        s.previewed_file_path = None;
-    });
-}
-
-/// Saves the provided content to a file, prompting the user for a location.
-pub fn save_file<P: EventProxy, D: DialogService + ?Sized>(
-    dialog: &D,
+/// Sets whether the output includes the directory tree. A generated output that is
+/// still current is re-sent with the tree added or removed without reading the files
+/// again; an outdated one is regenerated.
+pub fn include_tree_toggled<P: EventProxy>(
     payload: serde_json::Value,
     proxy: P,
     state: Arc<Mutex<AppState>>,
 ) {
-    if let Some(content) = payload.as_str() {
-        let content_clone = content.to_string();
-        let config = {
-            let state_guard = state
-                .lock()
-                .expect("Mutex was poisoned. This should not happen.");
-            state_guard.config.clone()
+    let Ok(include_tree) = serde_json::from_value::<bool>(payload.clone()) else {
+        tracing::warn!("Failed to deserialize boolean from payload: {:?}", payload);
+        return;
+    };
+    // Only the settings are compared under the lock; saving the config and checking
+    // the files for changes touch the disk and run after it is released.
+    let (config, selection, cached) = {
+        let mut state_guard = lock_state(&state);
+        state_guard.config.include_tree_by_default = include_tree;
+        let selection = get_selected_files_in_generation_order(&state_guard);
+        let streamed = matches!(
+            state_guard.generated_content,
+            Some(HeldOutput::Spilled { .. })
+        );
+        let cached = match &state_guard.last_generated {
+            Some(cached) => Some(
+                cached
+                    .matches(&selection, &state_guard.config)
+                    .then(|| cached.modified.clone()),
+            ),
+            // A streamed output is not kept in memory, so it is generated again.
+            None => streamed.then_some(None),
         };
+        (state_guard.config.clone(), selection, cached)
+    };
+    if let Err(e) = config::settings::save_config(&config, None) {
+        tracing::warn!("Failed to save config on tree toggle: {}", e);
+    }
 
-        if let Some(path) = dialog.save_output_file_path(&config) {
-            match std::fs::write(&path, content_clone) {
-                Ok(_) => {
-                    let event = UserEvent::SaveComplete(true, path.to_string_lossy().to_string());
-                    proxy.send_event(event);
-                }
-                Err(e) => {
-                    let event = UserEvent::SaveComplete(false, e.to_string());
-                    proxy.send_event(event);
-                }
-            };
-        } else {
-            let event = UserEvent::SaveComplete(false, "cancelled".to_string());
-            proxy.send_event(event);
-        }
-    } else {
-        tracing::warn!(
-            "Failed to deserialize content string from payload: {:?}",
-            payload
-        );
+    let Some(generated_times) = cached else {
+        proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
+            &lock_state(&state),
+        ))));
+        return;
+    };
+    let unchanged = generated_times.is_some_and(|times| times == modification_times(&selection));
+    if !unchanged {
+        tracing::info!("Generated output is outdated, regenerating for the tree toggle.");
+        generate_preview(proxy, state);
+        return;
     }
+
+    let mut state_guard = lock_state(&state);
+    state_guard.cancel_current_generation();
+    state_guard.is_generating = true;
+    proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
+        &state_guard,
+    ))));
+    let proxy_clone = proxy.clone();
+    let state_clone = state.clone();
+    let handle = tokio::spawn(async move {
+        tasks::reframe_task(proxy_clone, state_clone, tasks::RealTokenizer).await;
+    });
+    state_guard.generation_task = Some(handle);
 }
 
-/// Opens a file dialog for the user to select a default output directory.
-pub fn pick_output_directory<P: EventProxy, D: DialogService + ?Sized>(
-    dialog: &D,
-    proxy: P,
-    state: Arc<Mutex<AppState>>,
-) {
-    if let Some(path) = dialog.pick_directory() {
-        with_state_and_notify(&state, &proxy, |s| {
-            s.config.output_directory = Some(path);
-        });
+/// Sends which files were added, removed or changed between the last two generations.
+pub fn show_generation_diff<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    let diff = generation_diff(&lock_state(&state));
+    match diff {
+        Some(diff) => proxy.send_event(UserEvent::GenerationDiff(diff)),
+        None => proxy.send_event(UserEvent::ShowError(
+            "Generate at least twice to compare generations.".to_string(),
+        )),
     }
 }
 
-/// Imports an application configuration from a JSON file.
-///
-/// This action is treated as a "hard reset" of the application's context.
-/// It first completely clears the current state (file lists, selections, previews),
-/// sends an immediate UI update to reflect this clean state, and then applies
-/// the new configuration. If the imported config specifies a directory, a new
-/// scan is initiated on that path from a clean slate.
-pub async fn import_config<P: EventProxy, D: DialogService + ?Sized>(
-    dialog: &D,
+/// Sends the most recent generated outputs, newest first.
+pub fn list_generation_history<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    let history = lock_state(&state)
+        .generation_history
+        .iter()
+        .cloned()
+        .collect();
+    proxy.send_event(UserEvent::GenerationHistory(history));
+}
+
+/// Shows the output of the generation history with the `GenerationRecord::id` in the
+/// payload in the preview again, like a fresh generation. Only the newest outputs are
+/// kept in full; for older ones the user is told so.
+pub fn show_generation_from_history<P: EventProxy>(
+    payload: serde_json::Value,
     proxy: P,
     state: Arc<Mutex<AppState>>,
 ) {
-    if let Some(path) = dialog.pick_config_to_import() {
-        match config::settings::import_config(&path) {
-            Ok(new_config) => {
-                let filename = path.file_name().and_then(|n| n.to_str()).map(String::from);
-                let dir_to_scan = new_config.last_directory.clone();
+    let Ok(id) = serde_json::from_value::<u64>(payload.clone()) else {
+        tracing::warn!(
+            "Failed to deserialize history id from payload: {:?}",
+            payload
+        );
+        return;
+    };
+    let mut state_guard = lock_state(&state);
+    let Some(record) = state_guard
+        .generation_history
+        .iter()
+        .find(|record| record.id == id)
+        .cloned()
+    else {
+        proxy.send_event(UserEvent::ShowError(
+            "That generation is no longer in the history.".to_string(),
+        ));
+        return;
+    };
+    let Some(content) = record.content else {
+        proxy.send_event(UserEvent::ShowError(
+            "Only the newest generations are kept in full. Generate again to get this one."
+                .to_string(),
+        ));
+        return;
+    };
 
-                // Lock the state to perform the reset and config update atomically.
-                let mut state_guard = state
-                    .lock()
-                    .expect("Mutex was poisoned. This should not happen.");
+    let head = state_guard
+        .config
+        .preview_generated_head_lines
+        .and_then(|max_lines| tasks::preview_head(&content, max_lines));
+    // The preview no longer shows the latest generation, so it cannot be re-framed.
+    state_guard.last_generated = None;
+    state_guard.generated_content = head.is_some().then(|| HeldOutput::Text(content.clone()));
+    proxy.send_event(UserEvent::ShowGeneratedContent {
+        content: head.unwrap_or_else(|| content.to_string()),
+        token_count: record.token_count,
+    });
+    proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
+        &state_guard,
+    ))));
+}
 
-                // 1. Reset the entire directory-related state to a clean slate.
-                state_guard.reset_directory_state();
+/// Reports which files a generation of the current selection would include, without
+/// reading them.
+pub fn preview_generation_plan<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    let plan = generation_plan(&lock_state(&state));
+    proxy.send_event(UserEvent::GenerationPlan(plan));
+}
+
+/// Sends files, sizes and tokens per extension over all scanned files. Tokens are
+/// estimated from the file sizes unless the payload sets `exact`, in which case every
+/// text file is counted in the background, cancelling any earlier exact run.
+pub fn compute_tree_stats<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let exact = payload
+        .get("exact")
+        .and_then(serde_json::Value::as_bool)
+        .unwrap_or(false);
+    if exact {
+        let cancel_flag = lock_state(&state).cancel_tree_stats();
+        tokio::spawn(tasks::tree_stats_task(
+            proxy,
+            state,
+            tasks::RealTokenizer,
+            cancel_flag,
+        ));
+        return;
+    }
+    // Tallied outside the lock, as it walks every scanned file.
+    let files = lock_state(&state).full_file_list.clone();
+    let stats = tree_stats(&files, None);
+    proxy.send_event(UserEvent::TreeStats(stats));
+}
+
+/// Starts a background task that estimates the token count of the current selection.
+pub fn estimate_tokens<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    let mut state_guard = lock_state(&state);
+
+    state_guard.cancel_current_estimation();
+    state_guard.is_estimating = true;
+
+    let new_cancel_flag = Arc::new(AtomicBool::new(false));
+    state_guard.estimation_cancellation_flag = new_cancel_flag.clone();
+
+    proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
+        &state_guard,
+    ))));
+
+    let proxy_clone = proxy.clone();
+    let state_clone = state.clone();
+    let handle = tokio::spawn(async move {
+        tasks::estimation_task(
+            proxy_clone,
+            state_clone,
+            tasks::RealTokenizer,
+            new_cancel_flag,
+        )
+        .await;
+    });
+    state_guard.estimation_task = Some(handle);
+}
+
+/// Cancels the ongoing token estimate task.
+pub fn cancel_estimate<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    with_state_and_notify(&state, &proxy, |s| {
+        s.cancel_current_estimation();
+    });
+}
+
+/// Cancels the scan, the generation, the token estimate, the content search and the
+/// exact tree statistics at once and sends a single state update.
+pub fn cancel_all<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    with_state_and_notify(&state, &proxy, |s| {
+        tracing::info!("LOG: IPC 'cancelAll' received.");
+        s.cancel_all_tasks();
+    });
+}
+
+/// Resets the preview state in the UI.
+pub fn clear_preview_state<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    with_state_and_notify(&state, &proxy, |s| {
+        s.previewed_file_path = None;
+        s.generated_content = None;
+        s.reset_preview_matches(Vec::new());
+    });
+}
+
+/// Scrolls the preview to the next line matching the content search query.
+pub fn next_match<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    step_match(true, proxy, state);
+}
+
+/// Scrolls the preview to the previous line matching the content search query.
+pub fn prev_match<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    step_match(false, proxy, state);
+}
+
+/// Moves the preview's match cursor one match forward or back, wrapping around at
+/// either end, and asks the UI to scroll to that match.
+fn step_match<P: EventProxy>(forward: bool, proxy: P, state: Arc<Mutex<AppState>>) {
+    let line = lock_state(&state).step_preview_match(forward);
+    match line {
+        Some(line) => proxy.send_event(UserEvent::ScrollToLine(line)),
+        None => proxy.send_event(UserEvent::Status(
+            "No content search matches in the preview.".to_string(),
+        )),
+    }
+}
+
+/// Saves the provided content to a file, prompting the user for a location. While the
+/// preview only shows the head of the generated output, the full output is saved. With
+/// `split_output_bytes`, the parts are saved next to the chosen file instead, see
+/// `output_parts`.
+pub fn save_file<P: EventProxy, D: DialogService + ?Sized>(
+    dialog: &D,
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    if let Some(content) = payload.as_str() {
+        let (output, parts, config) = {
+            let state_guard = lock_state(&state);
+            let mut config = state_guard.config.clone();
+            let filename = expand_output_filename(
+                &config.output_filename,
+                &state_guard.current_path,
+                chrono::Local::now(),
+            );
+            config.output_filename = output_filename_for_format(&filename, config.output_format);
+            let output = state_guard
+                .generated_content
+                .clone()
+                .unwrap_or_else(|| HeldOutput::Text(content.into()));
+            (output, output_parts(&state_guard), config)
+        };
+
+        if let Some(path) = dialog.save_output_file_path(&config) {
+            let write = |path: &Path, output: &HeldOutput| {
+                check_disk_space(path, output)
+                    .and_then(|_| std::fs::File::create(path))
+                    .and_then(|file| write_output(file, path, output))
+                    .map(|_| path.to_path_buf())
+            };
+            let result = match &parts {
+                Some(parts) => {
+                    let filename = path.file_name().unwrap_or_default().to_string_lossy();
+                    write_parts(&filename, parts, |name, part| {
+                        write(&path.with_file_name(name), part)
+                    })
+                }
+                None => write(&path, &output),
+            };
+            match result {
+                Ok(saved) => {
+                    if parts.is_none() {
+                        lock_state(&state).note_generation_saved(output.content_hash(), &saved);
+                    }
+                    let event = UserEvent::SaveComplete(true, saved.to_string_lossy().to_string());
+                    proxy.send_event(event);
+                }
+                Err(e) => {
+                    let event = UserEvent::SaveComplete(false, e.to_string());
+                    proxy.send_event(event);
+                }
+            };
+        } else {
+            let event = UserEvent::SaveComplete(false, "cancelled".to_string());
+            proxy.send_event(event);
+        }
+    } else {
+        tracing::warn!(
+            "Failed to deserialize content string from payload: {:?}",
+            payload
+        );
+    }
+}
+
+/// Saves the provided content to the configured output directory and filename without
+/// showing a dialog. An existing file is never overwritten; a numbered name such as
+/// `cfc_output_(1).txt` is used instead. If the directory is not writable (or none is
+/// configured), the file is written to the desktop. Like `save_file`, the full generated
+/// output is saved while the preview only shows its head, or its parts with
+/// `split_output_bytes`.
+pub fn save_to_configured_path<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let Some(content) = payload.as_str() else {
+        tracing::warn!(
+            "Failed to deserialize content string from payload: {:?}",
+            payload
+        );
+        return;
+    };
+    let (output_directory, filename, output, parts) = {
+        let state_guard = lock_state(&state);
+        let config = &state_guard.config;
+        let filename = expand_output_filename(
+            &config.output_filename,
+            &state_guard.current_path,
+            chrono::Local::now(),
+        );
+        (
+            config.output_directory.clone(),
+            output_filename_for_format(&filename, config.output_format),
+            state_guard
+                .generated_content
+                .clone()
+                .unwrap_or_else(|| HeldOutput::Text(content.into())),
+            output_parts(&state_guard),
+        )
+    };
+
+    let write = |filename: &str, output: &HeldOutput| match &output_directory {
+        Some(dir) => match write_to_unique_path(dir, filename, output) {
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                tracing::warn!(
+                    "No permission to write to {}, falling back to the desktop",
+                    dir.display()
+                );
+                write_to_desktop(filename, output)
+            }
+            other => other,
+        },
+        None => write_to_desktop(filename, output),
+    };
+    let result = match &parts {
+        Some(parts) => write_parts(&filename, parts, write),
+        None => write(&filename, &output),
+    };
+
+    let event = match result {
+        Ok(path) => {
+            if parts.is_none() {
+                lock_state(&state).note_generation_saved(output.content_hash(), &path);
+            }
+            UserEvent::SaveComplete(true, path.to_string_lossy().to_string())
+        }
+        Err(e) => UserEvent::SaveComplete(false, e.to_string()),
+    };
+    proxy.send_event(event);
+}
+
+/// The parts `save_file` and `save_to_configured_path` write instead of a single file
+/// when `split_output_bytes` is set, see `FileHandler::split_output`. The parts are cut
+/// from the latest generation as generated, without the index and the manifest; the
+/// header of each part can list its files instead. `None` if the output fits into one
+/// part, or if the preview does not show a generation held in memory.
+fn output_parts(state: &AppState) -> Option<Vec<String>> {
+    let max_bytes = state.config.split_output_bytes?;
+    if state.previewed_file_path.is_some() {
+        return None;
+    }
+    let Some(generated) = &state.last_generated else {
+        tracing::warn!("The output is not held in memory, so it is saved in one piece.");
+        return None;
+    };
+    let parts = FileHandler::split_output(
+        &generated.output,
+        max_bytes,
+        &state.config.chunk_header_template,
+        Path::new(&state.current_path),
+    );
+    (parts.len() > 1).then_some(parts)
+}
+
+/// The name of part `part` of `total` of an output saved as `filename`, marked before
+/// the first extension so compressed names keep working: `out.txt.gz` becomes
+/// `out_part2of3.txt.gz`.
+fn part_filename(filename: &str, part: usize, total: usize) -> String {
+    match filename.split_once('.') {
+        Some((stem, extensions)) => format!("{stem}_part{part}of{total}.{extensions}"),
+        None => format!("{filename}_part{part}of{total}"),
+    }
+}
+
+/// Writes each of `parts` with `write`, given the part's name after `filename`, see
+/// `part_filename`. Returns the path of the first part.
+fn write_parts(
+    filename: &str,
+    parts: &[String],
+    mut write: impl FnMut(&str, &HeldOutput) -> std::io::Result<PathBuf>,
+) -> std::io::Result<PathBuf> {
+    let mut first = None;
+    for (index, part) in parts.iter().enumerate() {
+        let name = part_filename(filename, index + 1, parts.len());
+        let path = write(&name, &HeldOutput::Text(part.as_str().into()))?;
+        first.get_or_insert(path);
+    }
+    first.ok_or_else(|| std::io::Error::other("There were no parts to write"))
+}
+
+/// Writes `output` to `filename` on the user's desktop, see `write_to_unique_path`.
+fn write_to_desktop(filename: &str, output: &HeldOutput) -> std::io::Result<PathBuf> {
+    let desktop = dirs::desktop_dir().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "No output directory configured and no desktop directory found",
+        )
+    })?;
+    write_to_unique_path(&desktop, filename, output)
+}
+
+/// Writes `output` to `dir/filename`, or to `dir/<stem>_(n).<ext>` with the smallest
+/// free `n` if that file already exists. Returns the path that was written.
+fn write_to_unique_path(
+    dir: &Path,
+    filename: &str,
+    output: &HeldOutput,
+) -> std::io::Result<PathBuf> {
+    let name = Path::new(filename);
+    let stem = name
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "cfc_output".to_string());
+    let extension = name.extension().map(|e| e.to_string_lossy().to_string());
+    check_disk_space(&dir.join(filename), output)?;
+
+    for n in 0u32.. {
+        let candidate = match (n, &extension) {
+            (0, _) => dir.join(filename),
+            (_, Some(ext)) => dir.join(format!("{stem}_({n}).{ext}")),
+            (_, None) => dir.join(format!("{stem}_({n})")),
+        };
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(file) => {
+                write_output(file, &candidate, output)?;
+                return Ok(candidate);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!("ran out of candidate filenames")
+}
+
+/// Fails before anything is written if the volume of `path` has less free space than
+/// `output` needs. Compressed targets are not checked, as their size is only known
+/// once written; a full volume then fails the write itself. If the free space cannot
+/// be determined, the write is attempted anyway.
+fn check_disk_space(path: &Path, output: &HeldOutput) -> std::io::Result<()> {
+    if matches!(output_extension(path).as_deref(), Some("gz" | "zip")) {
+        return Ok(());
+    }
+    match disk::available_space(path) {
+        Ok(available) => disk::ensure_space(output.size()?, available),
+        Err(e) => {
+            tracing::warn!("Could not query free space for {}: {}", path.display(), e);
+            Ok(())
+        }
+    }
+}
+
+/// Writes `output` to the freshly opened `file` at `path`. A `.gz` name is written
+/// gzip-compressed and a `.zip` name as an archive with a single entry named after
+/// the file without `.zip`; anything else is written as is. A spilled output is
+/// copied from its file without loading it into memory.
+fn write_output(file: std::fs::File, path: &Path, output: &HeldOutput) -> std::io::Result<()> {
+    let mut content = output.reader()?;
+    match output_extension(path).as_deref() {
+        Some("gz") => {
+            let mut encoder = GzEncoder::new(file, Compression::default());
+            std::io::copy(&mut content, &mut encoder)?;
+            encoder.finish()?;
+        }
+        Some("zip") => {
+            let entry_name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| "cfc_output.txt".to_string());
+            let mut archive = ZipWriter::new(file);
+            archive
+                .start_file(
+                    entry_name,
+                    SimpleFileOptions::default().compression_method(CompressionMethod::Deflated),
+                )
+                .map_err(std::io::Error::other)?;
+            std::io::copy(&mut content, &mut archive)?;
+            archive.finish().map_err(std::io::Error::other)?;
+        }
+        _ => {
+            let mut file = file;
+            std::io::copy(&mut content, &mut file)?;
+        }
+    }
+    Ok(())
+}
+
+/// The lowercased extension of `path`, which decides how `write_output` writes it.
+fn output_extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+}
+
+/// Suggests a filename whose extension matches `format`. Only a missing extension or
+/// that of another `OutputFormat` is replaced, so a deliberately chosen extension is
+/// kept.
+fn output_filename_for_format(filename: &str, format: OutputFormat) -> String {
+    let path = Path::new(filename);
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if OutputFormat::from_extension(ext).is_none() => filename.to_string(),
+        _ => path
+            .with_extension(format.extension())
+            .to_string_lossy()
+            .to_string(),
+    }
+}
+
+/// Replaces the placeholders in an output filename: `{project}` with the name of the
+/// loaded directory, `{date}` with `YYYYMMDD` and `{time}` with `HHMMSS` of `now`.
+/// Unknown placeholders are kept as they are.
+fn expand_output_filename(
+    filename: &str,
+    current_path: &str,
+    now: chrono::DateTime<chrono::Local>,
+) -> String {
+    let project = Path::new(current_path).file_name().map_or_else(
+        || "project".to_string(),
+        |name| name.to_string_lossy().to_string(),
+    );
+    filename
+        .replace("{project}", &project)
+        .replace("{date}", &now.format("%Y%m%d").to_string())
+        .replace("{time}", &now.format("%H%M%S").to_string())
+}
+
+/// Characters the platform does not allow in filenames, besides path separators and
+/// control characters.
+#[cfg(windows)]
+const ILLEGAL_FILENAME_CHARS: &[char] = &['<', '>', ':', '"', '|', '?', '*'];
+#[cfg(not(windows))]
+const ILLEGAL_FILENAME_CHARS: &[char] = &[];
+
+/// Names Windows reserves for devices, with or without an extension.
+#[cfg(windows)]
+const RESERVED_FILENAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Checks a user-chosen output filename. Blank names, `.`/`..` and names containing a
+/// path separator are rejected. Control characters and characters the platform does
+/// not allow are replaced by `_`, and surrounding whitespace is trimmed.
+fn sanitize_output_filename(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(format!("\"{name}\" is not a valid output filename."));
+    }
+    if name.contains(['/', '\\']) {
+        return Err(format!(
+            "The output filename \"{name}\" must not contain path separators."
+        ));
+    }
+    let sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || ILLEGAL_FILENAME_CHARS.contains(&c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    #[cfg(windows)]
+    let sanitized = {
+        // Windows drops trailing dots and spaces and maps reserved names to devices.
+        let mut sanitized = sanitized.trim_end_matches(['.', ' ']).to_string();
+        let stem = sanitized.split('.').next().unwrap_or_default();
+        if sanitized.is_empty() || RESERVED_FILENAMES.contains(&stem.to_uppercase().as_str()) {
+            sanitized.insert(0, '_');
+        }
+        sanitized
+    };
+    Ok(sanitized)
+}
+
+/// Sets the output filename without sending the whole config. The name is sanitized,
+/// saved in the config and sent back, so the UI shows any corrections.
+pub fn set_output_filename<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let Some(name) = payload.as_str() else {
+        tracing::warn!("Failed to deserialize filename from payload: {:?}", payload);
+        return;
+    };
+    let filename = match sanitize_output_filename(name) {
+        Ok(filename) => filename,
+        Err(message) => {
+            proxy.send_event(UserEvent::ShowError(message));
+            return;
+        }
+    };
+
+    let mut state_guard = lock_state(&state);
+    state_guard.config.output_filename = filename.clone();
+    if let Err(e) = config::settings::save_config(&state_guard.config, None) {
+        tracing::warn!(
+            "Failed to save config after setting the output filename: {}",
+            e
+        );
+    }
+    proxy.send_event(UserEvent::OutputFilenameSet {
+        corrected: filename != name,
+        filename,
+    });
+}
+
+/// Opens a file dialog for the user to select a default output directory.
+pub fn pick_output_directory<P: EventProxy, D: DialogService + ?Sized>(
+    dialog: &D,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    if let Some(path) = dialog.pick_directory() {
+        with_state_and_notify(&state, &proxy, |s| {
+            s.config.output_directory = Some(path);
+        });
+    }
+}
+
+/// Imports an application configuration from a JSON file.
+///
+/// This action is treated as a "hard reset" of the application's context.
+/// It first completely clears the current state (file lists, selections, previews),
+/// sends an immediate UI update to reflect this clean state, and then applies
+/// the new configuration. If the imported config specifies a directory, a new
+/// scan is initiated on that path from a clean slate.
+pub async fn import_config<P: EventProxy, D: DialogService + ?Sized>(
+    dialog: &D,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    if let Some(path) = dialog.pick_config_to_import() {
+        match config::settings::import_config(&path) {
+            Ok(new_config) => {
+                let filename = path.file_name().and_then(|n| n.to_str()).map(String::from);
+                let dir_to_scan = new_config.last_directory.clone();
+
+                // Lock the state to perform the reset and config update atomically.
+                let mut state_guard = lock_state(&state);
+
+                // 1. Reset the entire directory-related state to a clean slate.
+                state_guard.reset_directory_state();
 
                 // 2. Apply the new configuration.
                 state_guard.config = new_config;
                 state_guard.current_config_filename = filename;
+                state_guard.active_profile = None;
                 if let Err(e) = config::settings::save_config(&state_guard.config, None) {
                     tracing::warn!("Failed to save imported config: {}", e);
                 }
@@ -683,6 +2124,43 @@ pub async fn import_config<P: EventProxy, D: DialogService + ?Sized>(
     }
 }
 
+/// Replaces the configuration with the defaults and persists it.
+///
+/// Like `import_config`, this clears the directory state first. The window geometry
+/// is kept, so the window doesn't jump on the next save. Asking the user for
+/// confirmation is left to the UI.
+pub fn reset_config_to_defaults<P: EventProxy>(proxy: P, state: Arc<Mutex<AppState>>) {
+    let mut state_guard = lock_state(&state);
+
+    state_guard.reset_directory_state();
+
+    let defaults = AppConfig {
+        window_size: state_guard.config.window_size,
+        window_position: state_guard.config.window_position,
+        ..AppConfig::default()
+    };
+    state_guard.config = defaults;
+    state_guard.current_config_filename = None;
+    state_guard.active_profile = None;
+    if let Err(e) = config::settings::save_config(&state_guard.config, None) {
+        tracing::warn!("Failed to save the default config: {}", e);
+    }
+
+    let ui_state = generate_ui_state(&state_guard);
+    proxy.send_event(UserEvent::StateUpdate(Box::new(ui_state)));
+}
+
+/// Checks the config file at the path in the payload as `importConfig` would read it
+/// and sends the problems found to the UI. Nothing is applied.
+pub fn validate_config<P: EventProxy>(payload: serde_json::Value, proxy: P) {
+    let Ok(path) = serde_json::from_value::<PathBuf>(payload.clone()) else {
+        tracing::warn!("Failed to deserialize path from payload: {:?}", payload);
+        return;
+    };
+    let issues = config::settings::validate_config(&path);
+    proxy.send_event(UserEvent::ConfigValidation { path, issues });
+}
+
 /// Exports the current application configuration to a JSON file.
 pub fn export_config<P: EventProxy, D: DialogService + ?Sized>(
     dialog: &D,
@@ -690,47 +2168,369 @@ pub fn export_config<P: EventProxy, D: DialogService + ?Sized>(
     state: Arc<Mutex<AppState>>,
 ) {
     if let Some(path) = dialog.export_config_path() {
-        let state_guard = state
-            .lock()
-            .expect("Mutex was poisoned. This should not happen.");
+        let state_guard = lock_state(&state);
         let result = config::settings::export_config(&state_guard.config, &path).is_ok();
         proxy.send_event(UserEvent::ConfigExported(result));
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::app::file_dialog::DialogService;
-    use crate::app::state::AppState;
-    use crate::app::view_model::UiState;
-    use crate::core::FileItem;
-    use crate::utils::test_helpers::setup_test_logging;
-    use serde_json::json;
-    use std::fs as std_fs;
-    use std::path::PathBuf;
-    use std::sync::Mutex;
-    use tempfile::{tempdir, TempDir};
-    use tokio::sync::mpsc;
+/// Writes the directory tree of the current filtered view to a file chosen by the user.
+///
+/// The tree honors `tree_ignore_patterns`, and empty directories are left out when
+/// `remove_empty_directories` is set since they are already filtered from the view.
+pub fn export_tree<P: EventProxy, D: DialogService + ?Sized>(
+    dialog: &D,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let tree = {
+        let state_guard = lock_state(&state);
+        if state_guard.current_path.is_empty() {
+            return;
+        }
+        TreeGenerator::generate_tree_with_style(
+            &state_guard.filtered_file_list,
+            Path::new(&state_guard.current_path),
+            &state_guard.config.tree_ignore_patterns,
+            state_guard.config.tree_style(),
+        )
+    };
 
-    // A mock EventProxy for capturing events sent to the UI.
-    #[derive(Clone)]
-    struct TestEventProxy {
-        sender: mpsc::UnboundedSender<UserEvent>,
+    if let Some(path) = dialog.export_tree_path() {
+        let event = match std::fs::write(&path, tree) {
+            Ok(()) => UserEvent::TreeExported(true, path.to_string_lossy().to_string()),
+            Err(e) => UserEvent::TreeExported(false, e.to_string()),
+        };
+        proxy.send_event(event);
     }
+}
 
-    impl EventProxy for TestEventProxy {
-        fn send_event(&self, event: UserEvent) {
-            self.sender.send(event).expect("Test receiver dropped");
+/// Copies the paths of the selected files to the clipboard, one per line.
+pub fn copy_selected_paths<P: EventProxy, C: ClipboardService + ?Sized>(
+    clipboard: &C,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let (text, count) = {
+        let state_guard = lock_state(&state);
+        if state_guard.selected_files.is_empty() {
+            return;
         }
-    }
+        let text = selected_paths_text(&state_guard);
+        let count = text.lines().count();
+        (text, count)
+    };
 
-    // A mock DialogService to simulate user interaction with file dialogs.
-    #[derive(Default)]
-    struct MockDialogService {
-        picked_folder: Mutex<Option<PathBuf>>,
-        picked_file: Mutex<Option<PathBuf>>,
-        saved_file: Mutex<Option<PathBuf>>,
+    match clipboard.copy_text(&text) {
+        Ok(()) => proxy.send_event(UserEvent::PathsCopied(count)),
+        Err(e) => proxy.send_event(UserEvent::ShowError(format!("{e:#}"))),
+    }
+}
+
+/// Copies the full generated output to the clipboard while the preview only shows its
+/// head, see `AppConfig::preview_generated_head_lines`.
+pub fn copy_generated_content<P: EventProxy, C: ClipboardService + ?Sized>(
+    clipboard: &C,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let (output, token_count) = {
+        let state_guard = lock_state(&state);
+        let Some(output) = state_guard.generated_content.clone() else {
+            proxy.send_event(UserEvent::ShowError(
+                "The preview holds the whole output; copy it from there.".to_string(),
+            ));
+            return;
+        };
+        let token_count = state_guard
+            .latest_generation
+            .as_ref()
+            .map_or(0, |generation| generation.token_count);
+        (output, token_count)
+    };
+
+    let copied = output
+        .text()
+        .map_err(anyhow::Error::from)
+        .and_then(|content| clipboard.copy_text(&content));
+    match copied {
+        Ok(()) => proxy.send_event(UserEvent::OutputCopied { token_count }),
+        Err(e) => proxy.send_event(UserEvent::ShowError(format!("{e:#}"))),
+    }
+}
+
+/// Selects the files listed in a newline-delimited manifest, e.g. the output of
+/// `grep -l`, or in a selection saved by `export_selection`. Listed paths that cannot
+/// be selected are reported in a warning.
+pub fn import_selection<P: EventProxy, D: DialogService + ?Sized>(
+    dialog: &D,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let Some(path) = dialog.pick_selection_manifest() else {
+        return;
+    };
+    let manifest = match std::fs::read_to_string(&path) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            proxy.send_event(UserEvent::ShowError(format!(
+                "Failed to import selection: {e}"
+            )));
+            return;
+        }
+    };
+
+    let saved_set = serde_json::from_str::<SelectionSet>(&manifest).ok();
+
+    let (import, foreign_root) = {
+        let mut state_guard = lock_state(&state);
+        if state_guard.current_path.is_empty() {
+            return;
+        }
+        let (import, foreign_root) = match &saved_set {
+            Some(set) => (
+                state_guard.select_listed_paths(set.paths.iter().map(String::as_str)),
+                (set.root != state_guard.current_path).then_some(set.root.as_str()),
+            ),
+            None => (state_guard.select_listed_paths(manifest.lines()), None),
+        };
+        proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
+            &state_guard,
+        ))));
+        (import, foreign_root)
+    };
+
+    proxy.send_event(UserEvent::Status(format!(
+        "Selected {} files from {}",
+        import.selected.len(),
+        path.file_name().unwrap_or_default().to_string_lossy()
+    )));
+    let mut skipped = Vec::new();
+    if let Some(root) = foreign_root {
+        skipped.push(format!("the selection was exported from {root}"));
+    }
+    if !import.missing.is_empty() {
+        skipped.push(format!("not found: {}", list_paths(&import.missing)));
+    }
+    if !import.filtered_out.is_empty() {
+        skipped.push(format!(
+            "filtered out: {}",
+            list_paths(&import.filtered_out)
+        ));
+    }
+    if !skipped.is_empty() {
+        proxy.send_event(UserEvent::ShowError(format!(
+            "Some listed paths were not selected ({}).",
+            skipped.join("; ")
+        )));
+    }
+}
+
+/// Writes the selected files, relative to the current directory, to a JSON file that
+/// `import_selection` can load again.
+pub fn export_selection<P: EventProxy, D: DialogService + ?Sized>(
+    dialog: &D,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    let set = {
+        let state_guard = lock_state(&state);
+        if state_guard.selected_files.is_empty() {
+            return;
+        }
+        let root = Path::new(&state_guard.current_path);
+        SelectionSet {
+            root: state_guard.current_path.clone(),
+            paths: get_selected_files_in_tree_order(&state_guard)
+                .iter()
+                .filter_map(|path| path.strip_prefix(root).ok())
+                .map(|relative| relative.to_string_lossy().to_string())
+                .collect(),
+        }
+    };
+
+    if let Some(path) = dialog.export_selection_path() {
+        let result = serde_json::to_string_pretty(&set)
+            .map_err(|e| e.to_string())
+            .and_then(|json| std::fs::write(&path, json).map_err(|e| e.to_string()));
+        let event = match result {
+            Ok(()) => UserEvent::SelectionExported(true, path.to_string_lossy().to_string()),
+            Err(e) => UserEvent::SelectionExported(false, e),
+        };
+        proxy.send_event(event);
+    }
+}
+
+/// Joins the file names of up to five paths for a message, noting how many were left out.
+fn list_paths(paths: &[PathBuf]) -> String {
+    const SHOWN: usize = 5;
+    let mut names: Vec<String> = paths
+        .iter()
+        .take(SHOWN)
+        .map(|path| {
+            path.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+    if paths.len() > SHOWN {
+        names.push(format!("and {} more", paths.len() - SHOWN));
+    }
+    names.join(", ")
+}
+
+/// Saves the current configuration as a named profile and marks it as active.
+pub fn save_profile<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    if let Ok(name) = serde_json::from_value::<String>(payload) {
+        let mut state_guard = lock_state(&state);
+        match config::settings::save_profile(&name, &state_guard.config, None) {
+            Ok(()) => {
+                state_guard.active_profile = Some(name.trim().to_string());
+                proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
+                    &state_guard,
+                ))));
+                drop(state_guard);
+                list_profiles(proxy);
+            }
+            Err(e) => {
+                proxy.send_event(UserEvent::ShowError(format!("Failed to save profile: {e}")));
+            }
+        }
+    } else {
+        tracing::warn!("Failed to deserialize profile name from payload");
+    }
+}
+
+/// Loads a named profile and makes it the active configuration.
+///
+/// Like `import_config`, this is a hard reset: the directory state is cleared and,
+/// if the profile specifies a directory, a fresh scan is started on it.
+pub fn load_profile<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    if let Ok(name) = serde_json::from_value::<String>(payload) {
+        match config::settings::load_profile(&name, None) {
+            Ok(new_config) => {
+                let dir_to_scan = new_config.last_directory.clone();
+
+                let mut state_guard = lock_state(&state);
+                state_guard.reset_directory_state();
+                state_guard.config = new_config;
+                state_guard.current_config_filename = None;
+                state_guard.active_profile = Some(name.trim().to_string());
+                if let Err(e) = config::settings::save_config(&state_guard.config, None) {
+                    tracing::warn!("Failed to save config after loading profile: {}", e);
+                }
+
+                let clean_ui_state = generate_ui_state(&state_guard);
+                proxy.send_event(UserEvent::StateUpdate(Box::new(clean_ui_state)));
+                drop(state_guard);
+
+                if let Some(dir) = dir_to_scan {
+                    if dir.exists() {
+                        start_scan_on_path(dir, proxy, state, false);
+                    }
+                }
+            }
+            Err(e) => {
+                proxy.send_event(UserEvent::ShowError(format!("Failed to load profile: {e}")));
+            }
+        }
+    } else {
+        tracing::warn!("Failed to deserialize profile name from payload");
+    }
+}
+
+/// Sends the names of all saved profiles to the UI.
+pub fn list_profiles<P: EventProxy>(proxy: P) {
+    match config::settings::list_profiles(None) {
+        Ok(names) => proxy.send_event(UserEvent::ProfilesListed(names)),
+        Err(e) => {
+            proxy.send_event(UserEvent::ShowError(format!(
+                "Failed to list profiles: {e}"
+            )));
+        }
+    }
+}
+
+/// Sends the directory log files are written to, so it can be attached to bug reports.
+pub fn get_log_path<P: EventProxy>(proxy: P) {
+    proxy.send_event(UserEvent::LogPath(config::settings::get_log_dir()));
+}
+
+/// Deletes a named profile. If it was the active profile, the current configuration
+/// is kept but no longer associated with it.
+pub fn delete_profile<P: EventProxy>(
+    payload: serde_json::Value,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+) {
+    if let Ok(name) = serde_json::from_value::<String>(payload) {
+        match config::settings::delete_profile(&name, None) {
+            Ok(()) => {
+                let mut state_guard = lock_state(&state);
+                if state_guard.active_profile.as_deref() == Some(name.trim()) {
+                    state_guard.active_profile = None;
+                    proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
+                        &state_guard,
+                    ))));
+                }
+                drop(state_guard);
+                list_profiles(proxy);
+            }
+            Err(e) => {
+                proxy.send_event(UserEvent::ShowError(format!(
+                    "Failed to delete profile: {e}"
+                )));
+            }
+        }
+    } else {
+        tracing::warn!("Failed to deserialize profile name from payload");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::file_dialog::DialogService;
+    use crate::app::state::AppState;
+    use crate::app::view_model::UiState;
+    use crate::config::UiTheme;
+    use crate::core::FileItem;
+    use crate::utils::test_helpers::setup_test_logging;
+    use serde_json::json;
+    use std::fs as std_fs;
+    use std::path::PathBuf;
+    use std::sync::atomic::Ordering;
+    use std::sync::Mutex;
+    use tempfile::{tempdir, TempDir};
+    use tokio::sync::mpsc;
+
+    // A mock EventProxy for capturing events sent to the UI.
+    #[derive(Clone)]
+    struct TestEventProxy {
+        sender: mpsc::UnboundedSender<UserEvent>,
+    }
+
+    impl EventProxy for TestEventProxy {
+        fn send_event(&self, event: UserEvent) {
+            self.sender.send(event).expect("Test receiver dropped");
+        }
+    }
+
+    // A mock DialogService to simulate user interaction with file dialogs.
+    #[derive(Default)]
+    struct MockDialogService {
+        picked_folder: Mutex<Option<PathBuf>>,
+        picked_file: Mutex<Option<PathBuf>>,
+        saved_file: Mutex<Option<PathBuf>>,
     }
 
     impl Clone for MockDialogService {
@@ -764,12 +2564,41 @@ mod tests {
         fn pick_config_to_import(&self) -> Option<PathBuf> {
             self.picked_file.lock().unwrap().clone()
         }
+        fn pick_selection_manifest(&self) -> Option<PathBuf> {
+            self.picked_file.lock().unwrap().clone()
+        }
+        fn export_selection_path(&self) -> Option<PathBuf> {
+            self.saved_file.lock().unwrap().clone()
+        }
         fn export_config_path(&self) -> Option<PathBuf> {
             self.saved_file.lock().unwrap().clone()
         }
         fn save_output_file_path(&self, _config: &AppConfig) -> Option<PathBuf> {
             self.saved_file.lock().unwrap().clone()
         }
+        fn export_tree_path(&self) -> Option<PathBuf> {
+            self.saved_file.lock().unwrap().clone()
+        }
+    }
+
+    // A mock ClipboardService that records what was copied instead of touching the
+    // system clipboard.
+    #[derive(Default)]
+    struct MockClipboardService {
+        copied: Mutex<Vec<String>>,
+    }
+
+    impl MockClipboardService {
+        fn copied(&self) -> Vec<String> {
+            self.copied.lock().unwrap().clone()
+        }
+    }
+
+    impl ClipboardService for MockClipboardService {
+        fn copy_text(&self, text: &str) -> anyhow::Result<()> {
+            self.copied.lock().unwrap().push(text.to_string());
+            Ok(())
+        }
     }
 
     struct TestHarness {
@@ -777,6 +2606,7 @@ mod tests {
         proxy: TestEventProxy,
         event_rx: mpsc::UnboundedReceiver<UserEvent>,
         dialog: Arc<MockDialogService>,
+        clipboard: Arc<MockClipboardService>,
         _temp_dir: TempDir,
         root_path: PathBuf,
     }
@@ -788,6 +2618,7 @@ mod tests {
             let (tx, rx) = mpsc::unbounded_channel();
             let proxy = TestEventProxy { sender: tx };
             let dialog = Arc::new(MockDialogService::default());
+            let clipboard = Arc::new(MockClipboardService::default());
 
             let mut state = AppState::default();
             state.config = AppConfig::default();
@@ -798,6 +2629,7 @@ mod tests {
                 proxy,
                 event_rx: rx,
                 dialog,
+                clipboard,
                 _temp_dir: temp_dir,
                 root_path,
             }
@@ -877,6 +2709,9 @@ mod tests {
             size: if is_dir { 0 } else { 123 },
             depth: 1,
             parent: None,
+            mtime: None,
+            likely_generated: false,
+            git_status: None,
         }
     }
 
@@ -934,517 +2769,1616 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_update_config_triggers_refilter() {
+    async fn test_update_config_triggers_refilter() {
+        let mut harness = TestHarness::new();
+        harness.create_file("src/main.rs", "main");
+        harness.create_dir("src/empty_dir");
+
+        harness.set_initial_files(&["src", "src/main.rs", "src/empty_dir"]);
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.is_fully_scanned = true;
+            state.loaded_dirs.insert(harness.root_path.join("src"));
+            state
+                .loaded_dirs
+                .insert(harness.root_path.join("src/empty_dir"));
+        }
+
+        let mut new_config = harness.state.lock().unwrap().config.clone();
+        new_config.remove_empty_directories = true;
+        let payload = serde_json::to_value(new_config).unwrap();
+        update_config(payload, harness.proxy.clone(), harness.state.clone()).await;
+
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(
+            ui_state.visible_files_count, 2,
+            "Expected 'src/empty_dir' to be removed"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_config_refilters_when_modified_within_days_changes() {
+        let mut harness = TestHarness::new();
+        harness.create_file("recent.rs", "new");
+        harness.create_file("old.rs", "old");
+        harness.set_initial_files(&["recent.rs", "old.rs"]);
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.full_file_list[0].mtime = Some(std::time::SystemTime::now());
+            state.full_file_list[1].mtime =
+                Some(std::time::SystemTime::now() - Duration::from_secs(30 * 24 * 60 * 60));
+        }
+
+        let mut new_config = harness.state.lock().unwrap().config.clone();
+        new_config.modified_within_days = Some(7);
+        let payload = serde_json::to_value(new_config).unwrap();
+        update_config(payload, harness.proxy.clone(), harness.state.clone()).await;
+
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.visible_files_count, 1);
+        assert_eq!(
+            harness.state.lock().unwrap().filtered_file_list[0].path,
+            harness.root_path.join("recent.rs")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_filters_applies_filename_filter_without_content_search() {
+        let mut harness = TestHarness::new();
+        harness.create_file("src/main.rs", "");
+        harness.create_file("src/lib.rs", "");
+        harness.create_file("README.md", "");
+        harness.set_initial_files(&["src", "src/main.rs", "src/lib.rs", "README.md"]);
+
+        let filters = json!({
+            "searchQuery": "main",
+            "extensionFilter": "",
+            "contentSearchQuery": ""
+        });
+        update_filters(filters, harness.proxy.clone(), harness.state.clone()).await;
+
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.visible_files_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_update_filters_coalesces_rapid_calls_into_one_update() {
+        let mut harness = TestHarness::new();
+        harness.create_file("src/main.rs", "");
+        harness.create_file("src/lib.rs", "");
+        harness.set_initial_files(&["src", "src/main.rs", "src/lib.rs"]);
+        harness.state.lock().unwrap().config.filter_debounce_ms = 50;
+
+        let filters = |query: &str| json!({ "searchQuery": query, "contentSearchQuery": "" });
+        let proxy = harness.proxy.clone();
+        let state = harness.state.clone();
+        let typing = async move {
+            for query in ["m", "ma", "mai"] {
+                let (proxy, state) = (proxy.clone(), state.clone());
+                tokio::spawn(update_filters(filters(query), proxy, state));
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            update_filters(filters("main"), proxy, state).await;
+        };
+        typing.await;
+        // Superseded calls finish after their quiet period without sending anything.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let mut updates = Vec::new();
+        while let Ok(event) = harness.event_rx.try_recv() {
+            if let UserEvent::StateUpdate(ui_state) = event {
+                updates.push(ui_state);
+            }
+        }
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].search_query, "main");
+        assert_eq!(updates[0].visible_files_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_add_ignore_path_retriggers_scan() {
+        let mut harness = TestHarness::new();
+        harness.create_file("src/main.rs", "");
+        harness.create_dir("docs");
+        harness.create_file("docs/guide.md", "");
+        harness.set_initial_files(&["src", "docs", "src/main.rs", "docs/guide.md"]);
+
+        let path_to_ignore = harness.root_path.join("docs");
+        let payload = json!(path_to_ignore);
+        add_ignore_path(payload, harness.proxy.clone(), harness.state.clone()).await;
+
+        let final_state = harness.wait_for_scan_completion().await.unwrap();
+        assert_eq!(final_state.visible_files_count, 2);
+        assert!(!final_state.tree.iter().any(|n| n.name == "docs"));
+        let state = harness.state.lock().unwrap();
+        assert!(state.config.ignore_patterns.contains("docs/"));
+    }
+
+    #[cfg(windows)]
+    #[tokio::test]
+    async fn test_add_ignore_path_uses_forward_slashes_for_nested_directories() {
+        let mut harness = TestHarness::new();
+        harness.create_file("src/main.rs", "");
+        harness.create_dir("docs/api");
+        harness.create_file("docs/api/index.md", "");
+        harness.create_file("docs/guide.md", "");
+        harness.set_initial_files(&[
+            "src",
+            "docs",
+            "docs/api",
+            "src/main.rs",
+            "docs/api/index.md",
+            "docs/guide.md",
+        ]);
+
+        let payload = json!(harness.root_path.join("docs").join("api"));
+        add_ignore_path(payload, harness.proxy.clone(), harness.state.clone()).await;
+
+        let final_state = harness.wait_for_scan_completion().await.unwrap();
+        assert_eq!(final_state.visible_files_count, 4);
+        let state = harness.state.lock().unwrap();
+        assert!(state.config.ignore_patterns.contains("docs/api/"));
+        assert!(!state
+            .full_file_list
+            .iter()
+            .any(|item| item.path.ends_with("index.md")));
+    }
+
+    #[tokio::test]
+    async fn test_add_ignore_paths_adds_all_patterns_in_one_rescan() {
+        let mut harness = TestHarness::new();
+        harness.create_file("src/main.rs", "");
+        harness.create_file("notes.txt", "");
+        harness.create_dir("docs");
+        harness.create_file("docs/guide.md", "");
+        harness.set_initial_files(&["src", "docs", "src/main.rs", "docs/guide.md", "notes.txt"]);
+
+        let payload = json!([
+            harness.root_path.join("docs"),
+            harness.root_path.join("notes.txt"),
+            "/etc/hosts"
+        ]);
+        add_ignore_paths(payload, harness.proxy.clone(), harness.state.clone()).await;
+
+        let final_state = harness.wait_for_scan_completion().await.unwrap();
+        assert_eq!(final_state.visible_files_count, 2);
+        let state = harness.state.lock().unwrap();
+        assert!(state.config.ignore_patterns.contains("docs/"));
+        assert!(state.config.ignore_patterns.contains("notes.txt"));
+        assert!(!state
+            .config
+            .ignore_patterns
+            .iter()
+            .any(|p| p.contains("hosts")));
+    }
+
+    #[tokio::test]
+    async fn test_add_ignore_paths_ignores_paths_outside_root() {
+        let mut harness = TestHarness::new();
+        harness.set_initial_files(&["src/main.rs"]);
+
+        let payload = json!(["/etc/hosts"]);
+        add_ignore_paths(payload, harness.proxy.clone(), harness.state.clone()).await;
+
+        let event = harness.get_next_event().await;
+        assert!(event.is_none(), "No rescan should be triggered");
+    }
+
+    #[tokio::test]
+    async fn test_import_config_resets_and_starts_scan() {
+        let mut harness = TestHarness::new();
+        harness.create_file("initial.txt", "");
+        let new_config_path = harness.root_path.join("new_config.json");
+        let project_to_scan = harness.create_dir("new_project_dir");
+        harness.create_file("new_project_dir/file.rs", "");
+
+        let new_config = AppConfig {
+            last_directory: Some(project_to_scan.clone()),
+            ..Default::default()
+        };
+        std_fs::write(
+            &new_config_path,
+            serde_json::to_string(&new_config).unwrap(),
+        )
+        .unwrap();
+        harness.dialog.set_pick_file(Some(new_config_path));
+
+        import_config(
+            harness.dialog.as_ref(),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        )
+        .await;
+
+        let _ = harness.get_next_event().await.unwrap();
+        let final_state = harness.wait_for_scan_completion().await.unwrap();
+        assert_eq!(final_state.current_path, project_to_scan.to_string_lossy());
+    }
+
+    #[test]
+    fn output_filenames_are_validated_and_sanitized() {
+        assert_eq!(sanitize_output_filename("  notes.md ").unwrap(), "notes.md");
+        assert_eq!(
+            sanitize_output_filename("out\tput.txt").unwrap(),
+            "out_put.txt"
+        );
+        assert!(sanitize_output_filename("   ").is_err());
+        assert!(sanitize_output_filename("..").is_err());
+        assert!(sanitize_output_filename("../escape.txt").is_err());
+        assert!(sanitize_output_filename("dir\\file.txt").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_set_output_filename_stores_the_sanitized_name() {
+        let mut harness = TestHarness::new();
+
+        set_output_filename(
+            serde_json::json!("my\u{7}output.txt"),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+
+        match harness.get_next_event().await {
+            Some(UserEvent::OutputFilenameSet {
+                filename,
+                corrected,
+            }) => {
+                assert_eq!(filename, "my_output.txt");
+                assert!(corrected);
+            }
+            other => panic!("Expected OutputFilenameSet, got {:?}", other),
+        }
+        assert_eq!(
+            harness.state.lock().unwrap().config.output_filename,
+            "my_output.txt"
+        );
+
+        set_output_filename(
+            serde_json::json!("sub/output.txt"),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+        assert!(matches!(
+            harness.get_next_event().await,
+            Some(UserEvent::ShowError(_))
+        ));
+        assert_eq!(
+            harness.state.lock().unwrap().config.output_filename,
+            "my_output.txt"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_config_does_nothing_when_no_directory_is_loaded() {
+        let mut harness = TestHarness::new();
+        let new_config = {
+            let mut state = harness.state.lock().unwrap();
+            state.current_path = String::new();
+            let mut config = state.config.clone();
+            config.remove_empty_directories = !config.remove_empty_directories;
+            config
+        };
+
+        let payload = serde_json::to_value(new_config.clone()).unwrap();
+        update_config(payload, harness.proxy.clone(), harness.state.clone()).await;
+
+        {
+            let final_config = &harness.state.lock().unwrap().config;
+            assert_eq!(
+                final_config.remove_empty_directories,
+                new_config.remove_empty_directories
+            );
+        }
+
+        let event = harness.get_next_event().await;
+        assert!(
+            event.is_none(),
+            "No events should be sent when no directory is loaded"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_ignore_path_does_nothing_when_no_directory_is_loaded() {
+        let mut harness = TestHarness::new();
+        let initial_patterns_count;
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.current_path = String::new();
+            initial_patterns_count = state.config.ignore_patterns.len();
+        }
+
+        let payload = json!("/some/path/to/ignore.txt");
+        add_ignore_path(payload, harness.proxy.clone(), harness.state.clone()).await;
+
+        {
+            let final_patterns_count = harness.state.lock().unwrap().config.ignore_patterns.len();
+            assert_eq!(
+                initial_patterns_count, final_patterns_count,
+                "Ignore patterns should not change"
+            );
+        }
+
+        let event = harness.get_next_event().await;
+        assert!(
+            event.is_none(),
+            "No events should be sent when no directory is loaded"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_config_sends_error_on_corrupt_file() {
+        let mut harness = TestHarness::new();
+        let corrupt_config_path = harness.create_file("corrupt_config.json", "{ not_valid_json, }");
+        harness.dialog.set_pick_file(Some(corrupt_config_path));
+
+        import_config(
+            harness.dialog.as_ref(),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        )
+        .await;
+
+        match harness.get_next_event().await.unwrap() {
+            UserEvent::ShowError(msg) => {
+                assert!(
+                    msg.contains("Failed to import config"),
+                    "Expected an import error message, but got: {}",
+                    msg
+                );
+            }
+            other => panic!("Expected ShowError event, but got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_config_reports_issues_and_leaves_state_untouched() {
+        let mut harness = TestHarness::new();
+        let config = AppConfig {
+            output_filename: String::new(),
+            last_directory: Some(harness.root_path.join("new_project_dir")),
+            ..Default::default()
+        };
+        let config_path =
+            harness.create_file("new_config.json", &serde_json::to_string(&config).unwrap());
+        let config_before = harness.state.lock().unwrap().config.clone();
+
+        validate_config(json!(config_path), harness.proxy.clone());
+
+        match harness.get_next_event().await.unwrap() {
+            UserEvent::ConfigValidation { path, issues } => {
+                assert_eq!(path, config_path);
+                let fields: Vec<_> = issues.iter().filter_map(|i| i.field.as_deref()).collect();
+                assert!(fields.contains(&"output_filename"), "{issues:?}");
+                assert!(fields.contains(&"last_directory"), "{issues:?}");
+            }
+            other => panic!("Expected ConfigValidation event, but got {:?}", other),
+        }
+        assert_eq!(harness.state.lock().unwrap().config, config_before);
+    }
+
+    #[tokio::test]
+    async fn test_profile_commands_reject_invalid_names() {
+        let mut harness = TestHarness::new();
+        harness.state.lock().unwrap().active_profile = Some("docs".to_string());
+
+        save_profile(
+            json!("../escape"),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+        match harness.get_next_event().await.unwrap() {
+            UserEvent::ShowError(msg) => assert!(msg.contains("Failed to save profile")),
+            other => panic!("Expected ShowError event, but got {:?}", other),
+        }
+
+        load_profile(json!(""), harness.proxy.clone(), harness.state.clone());
+        match harness.get_next_event().await.unwrap() {
+            UserEvent::ShowError(msg) => assert!(msg.contains("Failed to load profile")),
+            other => panic!("Expected ShowError event, but got {:?}", other),
+        }
+
+        delete_profile(json!("a/b"), harness.proxy.clone(), harness.state.clone());
+        match harness.get_next_event().await.unwrap() {
+            UserEvent::ShowError(msg) => assert!(msg.contains("Failed to delete profile")),
+            other => panic!("Expected ShowError event, but got {:?}", other),
+        }
+
+        // A failed operation must not change the active profile.
+        assert_eq!(
+            harness.state.lock().unwrap().active_profile.as_deref(),
+            Some("docs")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_profile_ignores_invalid_payload() {
+        let mut harness = TestHarness::new();
+        load_profile(
+            json!({ "name": 1 }),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+        assert!(harness.get_next_event().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_config_handles_invalid_payload() {
+        let mut harness = TestHarness::new();
+        let invalid_payload = json!({ "some_random_key": "some_value" });
+        let initial_config = harness.state.lock().unwrap().config.clone();
+
+        update_config(
+            invalid_payload,
+            harness.proxy.clone(),
+            harness.state.clone(),
+        )
+        .await;
+
+        let final_config = harness.state.lock().unwrap().config.clone();
+        assert_eq!(initial_config.output_filename, final_config.output_filename);
+
+        let event = harness.get_next_event().await;
+        assert!(event.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_config_triggers_rescan_on_pattern_change() {
+        let mut harness = TestHarness::new();
+        harness.set_initial_files(&["src/main.rs"]);
+
+        let mut new_config = harness.state.lock().unwrap().config.clone();
+        new_config.ignore_patterns.insert("*.rs".to_string());
+        let payload = serde_json::to_value(new_config).unwrap();
+
+        update_config(payload, harness.proxy.clone(), harness.state.clone()).await;
+
+        let final_state = harness.wait_for_scan_completion().await.unwrap();
+        assert_eq!(
+            final_state.visible_files_count, 0,
+            "Scan should have removed the .rs file"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_filters_triggers_content_search() {
+        let mut harness = TestHarness::new();
+        harness.set_initial_files(&["file1.txt"]);
+        harness.create_file("file1.txt", "hello world");
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.content_search_query = "initial".to_string();
+        }
+
+        let filters = json!({
+            "contentSearchQuery": "world"
+        });
+
+        update_filters(filters, harness.proxy.clone(), harness.state.clone()).await;
+
+        let final_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(final_state.content_search_query, "world");
+        assert_eq!(
+            final_state.visible_files_count, 1,
+            "The matching file should be visible"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_config_reruns_the_content_search_when_case_is_toggled() {
+        let mut harness = TestHarness::new();
+        harness.create_file("upper.txt", "Hello world");
+        harness.create_file("lower.txt", "hello world");
+        harness.set_initial_files(&["upper.txt", "lower.txt"]);
+        update_filters(
+            json!({ "contentSearchQuery": "hello" }),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        )
+        .await;
+        harness.get_last_state_update().await.unwrap();
+        assert_eq!(
+            harness.state.lock().unwrap().content_search_results.len(),
+            2
+        );
+
+        let mut new_config = harness.state.lock().unwrap().config.clone();
+        new_config.case_sensitive_search = true;
+        let payload = serde_json::to_value(new_config).unwrap();
+        update_config(payload, harness.proxy.clone(), harness.state.clone()).await;
+
+        let final_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(final_state.visible_files_count, 1);
+        let state = harness.state.lock().unwrap();
+        let matches: Vec<&PathBuf> = state.content_search_results.keys().collect();
+        assert_eq!(matches, vec![&harness.root_path.join("lower.txt")]);
+    }
+
+    #[tokio::test]
+    async fn test_update_filters_drops_content_matches_below_the_threshold() {
+        let mut harness = TestHarness::new();
+        harness.create_file("dense.txt", "todo todo todo\n");
+        harness.create_file("sparse.txt", &format!("todo{}", "x".repeat(2000)));
+        harness.set_initial_files(&["dense.txt", "sparse.txt"]);
+        for item in harness.state.lock().unwrap().full_file_list.iter_mut() {
+            item.size = std_fs::metadata(&item.path).unwrap().len();
+        }
+
+        async fn results_for(
+            harness: &mut TestHarness,
+            threshold: serde_json::Value,
+        ) -> Vec<String> {
+            let mut filters = json!({ "contentSearchQuery": "todo" });
+            filters
+                .as_object_mut()
+                .unwrap()
+                .extend(threshold.as_object().unwrap().clone());
+            update_filters(filters, harness.proxy.clone(), harness.state.clone()).await;
+            harness.get_last_state_update().await.unwrap();
+            let mut names: Vec<String> = harness
+                .state
+                .lock()
+                .unwrap()
+                .content_search_results
+                .keys()
+                .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            names
+        }
+
+        assert_eq!(
+            results_for(&mut harness, json!({})).await,
+            vec!["dense.txt", "sparse.txt"]
+        );
+        assert_eq!(
+            results_for(&mut harness, json!({ "contentSearchMinMatches": "2" })).await,
+            vec!["dense.txt"]
+        );
+        assert_eq!(
+            results_for(
+                &mut harness,
+                json!({ "contentSearchMinMatchesPerKb": "1.0" })
+            )
+            .await,
+            vec!["dense.txt"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_content_search_is_capped_until_more_results_are_loaded() {
+        let mut harness = TestHarness::new();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            harness.create_file(name, "needle");
+        }
+        harness.set_initial_files(&["a.txt", "b.txt", "c.txt"]);
+        harness.state.lock().unwrap().config.max_search_results = 2;
+
+        let filters = json!({ "contentSearchQuery": "needle" });
+        update_filters(filters, harness.proxy.clone(), harness.state.clone()).await;
+
+        let capped = harness.get_last_state_update().await.unwrap();
+        assert_eq!(capped.content_search_file_count, 2);
+        assert!(capped.content_search_truncated);
+        assert!(!harness
+            .state
+            .lock()
+            .unwrap()
+            .content_search_results
+            .contains_key(&harness.root_path.join("c.txt")));
+
+        load_more_search_results(harness.proxy.clone(), harness.state.clone()).await;
+
+        let all = harness.get_last_state_update().await.unwrap();
+        assert_eq!(all.content_search_file_count, 3);
+        assert!(!all.content_search_truncated);
+    }
+
+    #[tokio::test]
+    async fn test_add_ignore_path_handles_path_outside_root() {
+        let mut harness = TestHarness::new();
+        harness.set_initial_files(&["src/main.rs"]);
+        let initial_patterns_count = harness.state.lock().unwrap().config.ignore_patterns.len();
+        let outside_path = json!("/etc/hosts");
+
+        add_ignore_path(outside_path, harness.proxy.clone(), harness.state.clone()).await;
+
+        let event = harness.get_next_event().await;
+        assert!(event.is_none());
+        let final_patterns_count = harness.state.lock().unwrap().config.ignore_patterns.len();
+        assert_eq!(initial_patterns_count, final_patterns_count);
+    }
+
+    #[tokio::test]
+    async fn test_add_ignore_path_handles_duplicate_pattern() {
+        let mut harness = TestHarness::new();
+        harness.set_initial_files(&["docs/guide.md"]);
+
+        let path_to_ignore = harness.root_path.join("docs");
+        let payload = json!(path_to_ignore);
+        add_ignore_path(
+            payload.clone(),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        )
+        .await;
+
+        let _ = harness.wait_for_scan_completion().await;
+
+        add_ignore_path(payload, harness.proxy.clone(), harness.state.clone()).await;
+
+        let event = harness.get_next_event().await;
+        assert!(
+            event.is_none(),
+            "No rescan should be triggered for a duplicate ignore pattern"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_config_handles_nonexistent_scan_directory() {
+        let mut harness = TestHarness::new();
+        let new_config_path = harness.root_path.join("new_config.json");
+        let nonexistent_project_dir = harness.root_path.join("nonexistent_dir");
+
+        let new_config = AppConfig {
+            last_directory: Some(nonexistent_project_dir),
+            ..Default::default()
+        };
+        std_fs::write(
+            &new_config_path,
+            serde_json::to_string(&new_config).unwrap(),
+        )
+        .unwrap();
+        harness.dialog.set_pick_file(Some(new_config_path));
+
+        import_config(
+            harness.dialog.as_ref(),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        )
+        .await;
+
+        let event = harness.get_next_event().await;
+        assert!(matches!(event, Some(UserEvent::StateUpdate(_))));
+
+        let second_event = harness.get_next_event().await;
+        assert!(
+            second_event.is_none(),
+            "No scan should start for a nonexistent directory"
+        );
+    }
+
+    // =========================================================================================
+    // The following tests call SYNCHRONOUS commands and DO NOT NEED .await
+    // =========================================================================================
+
+    #[tokio::test]
+    async fn test_clear_directory_resets_state() {
+        let mut harness = TestHarness::new();
+        let file_path = harness.create_file("file.txt", "content");
+        harness.set_initial_files(&["file.txt"]);
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.selected_files.insert(file_path);
+            state.config.last_directory = Some(harness.root_path.clone());
+        }
+
+        clear_directory(harness.proxy.clone(), harness.state.clone());
+
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert!(ui_state.current_path.is_empty());
+        assert_eq!(ui_state.visible_files_count, 0);
+        let state = harness.state.lock().unwrap();
+        assert!(state.current_path.is_empty());
+        assert!(state.full_file_list.is_empty());
+        assert!(state.selected_files.is_empty());
+        assert!(state.config.last_directory.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_scan_updates_state() {
+        let mut harness = TestHarness::new();
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.is_scanning = true;
+            let handle = tokio::spawn(async {});
+            state.scan_task = Some(handle);
+        }
+
+        cancel_scan(harness.proxy.clone(), harness.state.clone());
+
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert!(!ui_state.is_scanning);
+        assert_eq!(ui_state.status_message, "Scan cancelled.");
+    }
+
+    #[tokio::test]
+    async fn test_initialize_sends_initial_state() {
+        let mut harness = TestHarness::new();
+        initialize(harness.proxy.clone(), harness.state.clone());
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.current_path, harness.root_path.to_string_lossy());
+    }
+
+    #[tokio::test]
+    async fn test_load_file_preview_sends_search_term() {
+        let mut harness = TestHarness::new();
+        let file_path = harness.create_file("preview.txt", "content with magic_word");
+        let search_term = "magic_word";
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.content_search_query = search_term.to_string();
+        }
+        let payload = json!(file_path);
+
+        load_file_preview(payload, harness.proxy.clone(), harness.state.clone());
+
+        let mut saw_preview = false;
+        for _ in 0..2 {
+            if let Some(event) = harness.get_next_event().await {
+                if let UserEvent::ShowFilePreview {
+                    search_term: term, ..
+                } = event
+                {
+                    assert_eq!(term, Some(search_term.to_string()));
+                    saw_preview = true;
+                }
+            }
+        }
+        assert!(
+            saw_preview,
+            "Did not receive the ShowFilePreview event with correct search term"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_next_and_prev_match_scroll_through_the_preview_matches() {
+        let mut harness = TestHarness::new();
+        let file_path = harness.create_file("preview.txt", "needle\nhay\nhay needle\n");
+        harness.state.lock().unwrap().content_search_query = "needle".to_string();
+        load_file_preview(
+            json!(file_path),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+        // The preview itself and the state update.
+        harness.get_next_event().await;
+        harness.get_next_event().await;
+
+        let mut lines = Vec::new();
+        for forward in [true, true, true, false] {
+            if forward {
+                next_match(harness.proxy.clone(), harness.state.clone());
+            } else {
+                prev_match(harness.proxy.clone(), harness.state.clone());
+            }
+            match harness.get_next_event().await {
+                Some(UserEvent::ScrollToLine(line)) => lines.push(line),
+                other => panic!("Expected ScrollToLine, got {:?}", other),
+            }
+        }
+        assert_eq!(lines, vec![1, 3, 1, 3]);
+
+        clear_preview_state(harness.proxy.clone(), harness.state.clone());
+        harness.get_next_event().await;
+        next_match(harness.proxy.clone(), harness.state.clone());
+        assert!(matches!(
+            harness.get_next_event().await,
+            Some(UserEvent::Status(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_preview_ignore_matches_reports_matches_without_changing_config() {
         let mut harness = TestHarness::new();
-        harness.create_file("src/main.rs", "main");
-        harness.create_dir("src/empty_dir");
-
-        harness.set_initial_files(&["src", "src/main.rs", "src/empty_dir"]);
-        {
-            let mut state = harness.state.lock().unwrap();
-            state.is_fully_scanned = true;
-            state.loaded_dirs.insert(harness.root_path.join("src"));
-            state
-                .loaded_dirs
-                .insert(harness.root_path.join("src/empty_dir"));
-        }
-
-        let mut new_config = harness.state.lock().unwrap().config.clone();
-        new_config.remove_empty_directories = true;
-        let payload = serde_json::to_value(new_config).unwrap();
-        update_config(payload, harness.proxy.clone(), harness.state.clone()).await;
+        harness.create_file("debug.log", "");
+        harness.create_file("main.rs", "");
+        harness.set_initial_files(&["debug.log", "main.rs"]);
+        let patterns_before = harness.state.lock().unwrap().config.ignore_patterns.clone();
 
-        let ui_state = harness.get_last_state_update().await.unwrap();
-        assert_eq!(
-            ui_state.visible_files_count, 2,
-            "Expected 'src/empty_dir' to be removed"
+        preview_ignore_matches(
+            json!(" *.log "),
+            harness.proxy.clone(),
+            harness.state.clone(),
         );
+
+        match harness.get_next_event().await {
+            Some(UserEvent::IgnorePreview { pattern, matches }) => {
+                assert_eq!(pattern, "*.log");
+                assert_eq!(matches, vec![harness.root_path.join("debug.log")]);
+            }
+            other => panic!("Expected IgnorePreview, got {other:?}"),
+        }
+        let state = harness.state.lock().unwrap();
+        assert_eq!(state.config.ignore_patterns, patterns_before);
+        assert_eq!(state.full_file_list.len(), 2);
     }
 
     #[tokio::test]
-    async fn test_update_filters_applies_filename_filter_without_content_search() {
+    async fn test_explain_hidden_resolves_relative_paths_against_the_root() {
         let mut harness = TestHarness::new();
-        harness.create_file("src/main.rs", "");
-        harness.create_file("src/lib.rs", "");
-        harness.create_file("README.md", "");
-        harness.set_initial_files(&["src", "src/main.rs", "src/lib.rs", "README.md"]);
+        harness.create_file("debug.log", "");
+        harness.state.lock().unwrap().config.ignore_patterns = HashSet::from(["*.log".to_string()]);
 
-        let filters = json!({
-            "searchQuery": "main",
-            "extensionFilter": "",
-            "contentSearchQuery": ""
-        });
-        update_filters(filters, harness.proxy.clone(), harness.state.clone()).await;
+        explain_hidden(
+            json!("debug.log"),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
 
-        let ui_state = harness.get_last_state_update().await.unwrap();
-        assert_eq!(ui_state.visible_files_count, 2);
+        match harness.get_next_event().await {
+            Some(UserEvent::HiddenExplanation { path, reasons }) => {
+                assert_eq!(path, harness.root_path.join("debug.log"));
+                assert_eq!(reasons, vec!["the ignore pattern `*.log` excludes it"]);
+            }
+            other => panic!("Expected HiddenExplanation, got {other:?}"),
+        }
     }
 
     #[tokio::test]
-    async fn test_add_ignore_path_retriggers_scan() {
+    async fn test_import_selection_selects_listed_files_and_warns_about_the_rest() {
         let mut harness = TestHarness::new();
-        harness.create_file("src/main.rs", "");
-        harness.create_dir("docs");
-        harness.create_file("docs/guide.md", "");
-        harness.set_initial_files(&["src", "docs", "src/main.rs", "docs/guide.md"]);
+        harness.create_file("a.rs", "");
+        harness.create_file("src/b.rs", "");
+        harness.create_file("secret.env", "");
+        harness.set_initial_files(&["a.rs", "src", "src/b.rs"]);
+        let manifest = harness.create_file(
+            "selection.txt",
+            "# from grep -l\n./a.rs\n\nsrc/b.rs\nsecret.env\nmissing.rs\n",
+        );
+        harness.dialog.set_pick_file(Some(manifest));
 
-        let path_to_ignore = harness.root_path.join("docs");
-        let payload = json!(path_to_ignore);
-        add_ignore_path(payload, harness.proxy.clone(), harness.state.clone()).await;
+        import_selection(
+            harness.dialog.as_ref(),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
 
-        let final_state = harness.wait_for_scan_completion().await.unwrap();
-        assert_eq!(final_state.visible_files_count, 2);
-        assert!(!final_state.tree.iter().any(|n| n.name == "docs"));
+        assert!(matches!(
+            harness.get_next_event().await,
+            Some(UserEvent::StateUpdate(_))
+        ));
+        match harness.get_next_event().await {
+            Some(UserEvent::Status(message)) => {
+                assert_eq!(message, "Selected 2 files from selection.txt")
+            }
+            other => panic!("Expected Status, got {other:?}"),
+        }
+        match harness.get_next_event().await {
+            Some(UserEvent::ShowError(message)) => {
+                assert!(message.contains("not found: missing.rs"));
+                assert!(message.contains("filtered out: secret.env"));
+            }
+            other => panic!("Expected ShowError, got {other:?}"),
+        }
         let state = harness.state.lock().unwrap();
-        assert!(state.config.ignore_patterns.contains("docs/"));
+        assert_eq!(
+            state.selected_files,
+            HashSet::from([
+                harness.root_path.join("a.rs"),
+                harness.root_path.join("src/b.rs")
+            ])
+        );
     }
 
     #[tokio::test]
-    async fn test_import_config_resets_and_starts_scan() {
+    async fn test_export_selection_round_trips_through_import_selection() {
         let mut harness = TestHarness::new();
-        harness.create_file("initial.txt", "");
-        let new_config_path = harness.root_path.join("new_config.json");
-        let project_to_scan = harness.create_dir("new_project_dir");
-        harness.create_file("new_project_dir/file.rs", "");
+        harness.create_file("a.rs", "");
+        harness.create_file("src/b.rs", "");
+        harness.set_initial_files(&["a.rs", "src", "src/b.rs"]);
+        harness.state.lock().unwrap().selected_files = HashSet::from([
+            harness.root_path.join("a.rs"),
+            harness.root_path.join("src/b.rs"),
+        ]);
+        let set_path = harness.root_path.join("selection.json");
+        harness.dialog.set_save_file(Some(set_path.clone()));
 
-        let new_config = AppConfig {
-            last_directory: Some(project_to_scan.clone()),
-            ..Default::default()
-        };
-        std_fs::write(
-            &new_config_path,
-            serde_json::to_string(&new_config).unwrap(),
-        )
-        .unwrap();
-        harness.dialog.set_pick_file(Some(new_config_path));
+        export_selection(
+            harness.dialog.as_ref(),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
 
-        import_config(
+        match harness.get_next_event().await {
+            Some(UserEvent::SelectionExported(success, path)) => {
+                assert!(success);
+                assert_eq!(path, set_path.to_string_lossy());
+            }
+            other => panic!("Expected SelectionExported, got {other:?}"),
+        }
+        let mut set: SelectionSet =
+            serde_json::from_str(&std_fs::read_to_string(&set_path).unwrap()).unwrap();
+        assert_eq!(set.root, harness.root_path.to_string_lossy());
+        let mut paths = set.paths.clone();
+        paths.sort();
+        assert_eq!(paths, vec!["a.rs", "src/b.rs"]);
+
+        // Importing a set exported from another root still selects by relative path,
+        // but warns about the mismatch.
+        harness.state.lock().unwrap().selected_files.clear();
+        set.root = "/elsewhere".to_string();
+        std_fs::write(&set_path, serde_json::to_string(&set).unwrap()).unwrap();
+        harness.dialog.set_pick_file(Some(set_path));
+
+        import_selection(
             harness.dialog.as_ref(),
             harness.proxy.clone(),
             harness.state.clone(),
-        )
-        .await;
+        );
 
-        let _ = harness.get_next_event().await.unwrap();
-        let final_state = harness.wait_for_scan_completion().await.unwrap();
-        assert_eq!(final_state.current_path, project_to_scan.to_string_lossy());
+        assert!(matches!(
+            harness.get_next_event().await,
+            Some(UserEvent::StateUpdate(_))
+        ));
+        assert!(matches!(
+            harness.get_next_event().await,
+            Some(UserEvent::Status(message)) if message == "Selected 2 files from selection.json"
+        ));
+        match harness.get_next_event().await {
+            Some(UserEvent::ShowError(message)) => {
+                assert!(message.contains("exported from /elsewhere"))
+            }
+            other => panic!("Expected ShowError, got {other:?}"),
+        }
+        assert_eq!(harness.state.lock().unwrap().selected_files.len(), 2);
     }
 
     #[tokio::test]
-    async fn test_update_config_does_nothing_when_no_directory_is_loaded() {
+    async fn test_load_file_preview_reports_truncation_at_the_configured_limit() {
         let mut harness = TestHarness::new();
-        let new_config = {
-            let mut state = harness.state.lock().unwrap();
-            state.current_path = String::new();
-            let mut config = state.config.clone();
-            config.remove_empty_directories = !config.remove_empty_directories;
-            config
-        };
+        let file_path = harness.create_file("long.txt", "1\n2\n3\n4\n5\n");
+        harness.state.lock().unwrap().config.preview_max_lines = 2;
 
-        let payload = serde_json::to_value(new_config.clone()).unwrap();
-        update_config(payload, harness.proxy.clone(), harness.state.clone()).await;
+        load_file_preview(
+            json!(file_path),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
 
-        {
-            let final_config = &harness.state.lock().unwrap().config;
-            assert_eq!(
-                final_config.remove_empty_directories,
-                new_config.remove_empty_directories
-            );
+        match harness.get_next_event().await {
+            Some(UserEvent::ShowFilePreview {
+                content,
+                truncation,
+                ..
+            }) => {
+                assert!(content.starts_with("1\n2\n...\n"));
+                assert_eq!(truncation, Some((2, 5)));
+            }
+            other => panic!("Expected ShowFilePreview, got {other:?}"),
         }
-
-        let event = harness.get_next_event().await;
-        assert!(
-            event.is_none(),
-            "No events should be sent when no directory is loaded"
-        );
     }
 
     #[tokio::test]
-    async fn test_add_ignore_path_does_nothing_when_no_directory_is_loaded() {
+    async fn test_load_directory_level_starts_lazy_scan() {
         let mut harness = TestHarness::new();
-        let initial_patterns_count;
-        {
-            let mut state = harness.state.lock().unwrap();
-            state.current_path = String::new();
-            initial_patterns_count = state.config.ignore_patterns.len();
-        }
-
-        let payload = json!("/some/path/to/ignore.txt");
-        add_ignore_path(payload, harness.proxy.clone(), harness.state.clone()).await;
+        let sub_dir = harness.create_dir("src/components");
+        harness.create_file("src/components/button.js", "");
+        harness.set_initial_files(&["src", "src/components"]);
 
-        {
-            let final_patterns_count = harness.state.lock().unwrap().config.ignore_patterns.len();
-            assert_eq!(
-                initial_patterns_count, final_patterns_count,
-                "Ignore patterns should not change"
-            );
-        }
+        let payload = json!(sub_dir);
+        load_directory_level(payload, harness.proxy.clone(), harness.state.clone());
 
-        let event = harness.get_next_event().await;
-        assert!(
-            event.is_none(),
-            "No events should be sent when no directory is loaded"
-        );
+        let final_state = harness.get_last_state_update().await.unwrap();
+        let src_node = final_state.tree.iter().find(|n| n.name == "src").unwrap();
+        let components_node = src_node
+            .children
+            .iter()
+            .find(|n| n.name == "components")
+            .unwrap();
+        assert!(components_node
+            .children
+            .iter()
+            .any(|n| n.name == "button.js"));
     }
 
     #[tokio::test]
-    async fn test_import_config_sends_error_on_corrupt_file() {
+    async fn test_subtree_rescan_replaces_only_that_subtree() {
         let mut harness = TestHarness::new();
-        let corrupt_config_path = harness.create_file("corrupt_config.json", "{ not_valid_json, }");
-        harness.dialog.set_pick_file(Some(corrupt_config_path));
-
-        import_config(
-            harness.dialog.as_ref(),
+        let kept = harness.create_file("pkg/a/kept.rs", "");
+        let removed = harness.create_file("pkg/a/removed.rs", "");
+        let other = harness.create_file("pkg/b/other.rs", "");
+        harness.set_initial_files(&[
+            "pkg",
+            "pkg/a",
+            "pkg/a/kept.rs",
+            "pkg/a/removed.rs",
+            "pkg/b",
+            "pkg/b/other.rs",
+        ]);
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.selected_files = HashSet::from([kept.clone(), removed.clone(), other.clone()]);
+        }
+        std_fs::remove_file(&removed).unwrap();
+        harness.create_file("pkg/a/deep/added.rs", "");
+        // Not picked up, as only `pkg/a` is rescanned.
+        harness.create_file("pkg/b/unseen.rs", "");
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        tasks::start_subtree_rescan(
+            harness.root_path.join("pkg/a"),
             harness.proxy.clone(),
             harness.state.clone(),
-        )
-        .await;
+            Some(tx),
+        );
+        rx.await.unwrap();
+        harness.get_last_state_update().await.unwrap();
 
-        match harness.get_next_event().await.unwrap() {
-            UserEvent::ShowError(msg) => {
-                assert!(
-                    msg.contains("Failed to import config"),
-                    "Expected an import error message, but got: {}",
-                    msg
-                );
-            }
-            other => panic!("Expected ShowError event, but got {:?}", other),
-        }
+        let state = harness.state.lock().unwrap();
+        let paths: HashSet<PathBuf> = state
+            .full_file_list
+            .iter()
+            .map(|item| item.path.clone())
+            .collect();
+        let expected: HashSet<PathBuf> = [
+            "pkg",
+            "pkg/a",
+            "pkg/a/kept.rs",
+            "pkg/a/deep",
+            "pkg/a/deep/added.rs",
+            "pkg/b",
+            "pkg/b/other.rs",
+        ]
+        .iter()
+        .map(|path| harness.root_path.join(path))
+        .collect();
+        assert_eq!(paths, expected);
+        assert_eq!(state.selected_files, HashSet::from([kept, other]));
     }
 
+    // ... All other synchronous tests remain unchanged ...
     #[tokio::test]
-    async fn test_update_config_handles_invalid_payload() {
+    async fn test_toggle_selection_adds_and_removes_file() {
         let mut harness = TestHarness::new();
-        let invalid_payload = json!({ "some_random_key": "some_value" });
-        let initial_config = harness.state.lock().unwrap().config.clone();
+        let file_path = harness.create_file("test.rs", "");
+        harness.set_initial_files(&["test.rs"]);
+        let payload = json!(file_path);
 
-        update_config(
-            invalid_payload,
+        toggle_selection(
+            payload.clone(),
             harness.proxy.clone(),
             harness.state.clone(),
-        )
-        .await;
-
-        let final_config = harness.state.lock().unwrap().config.clone();
-        assert_eq!(initial_config.output_filename, final_config.output_filename);
+        );
+        let ui_state1 = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state1.selected_files_count, 1);
 
-        let event = harness.get_next_event().await;
-        assert!(event.is_none());
+        toggle_selection(payload, harness.proxy.clone(), harness.state.clone());
+        let ui_state2 = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state2.selected_files_count, 0);
     }
 
     #[tokio::test]
-    async fn test_update_config_triggers_rescan_on_pattern_change() {
+    async fn test_toggle_selection_by_tree_id_resolves_exact_path() {
         let mut harness = TestHarness::new();
-        harness.set_initial_files(&["src/main.rs"]);
-
-        let mut new_config = harness.state.lock().unwrap().config.clone();
-        new_config.ignore_patterns.insert("*.rs".to_string());
-        let payload = serde_json::to_value(new_config).unwrap();
+        harness.create_file("a.rs", "");
+        let file_b = harness.create_file("b.rs", "");
+        harness.set_initial_files(&["a.rs", "b.rs"]);
+        let id_b = harness.state.lock().unwrap().path_ids.id(&file_b);
 
-        update_config(payload, harness.proxy.clone(), harness.state.clone()).await;
+        toggle_selection(json!(id_b), harness.proxy.clone(), harness.state.clone());
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.selected_files_count, 1);
+        assert!(harness
+            .state
+            .lock()
+            .unwrap()
+            .selected_files
+            .contains(&file_b));
 
-        let final_state = harness.wait_for_scan_completion().await.unwrap();
-        assert_eq!(
-            final_state.visible_files_count, 0,
-            "Scan should have removed the .rs file"
-        );
+        // An id that was never handed out is ignored.
+        toggle_selection(json!(99), harness.proxy.clone(), harness.state.clone());
+        assert!(harness.get_next_event().await.is_none());
     }
 
     #[tokio::test]
-    async fn test_update_filters_triggers_content_search() {
+    async fn test_tree_ids_rendered_before_a_rescan_still_name_the_same_path() {
         let mut harness = TestHarness::new();
-        harness.set_initial_files(&["file1.txt"]);
-        harness.create_file("file1.txt", "hello world");
-        {
-            let mut state = harness.state.lock().unwrap();
-            state.content_search_query = "initial".to_string();
-        }
+        harness.create_file("b.rs", "");
+        let file_c = harness.create_file("c.rs", "");
+        harness.set_initial_files(&["b.rs", "c.rs"]);
+        let rendered = generate_ui_state(&harness.state.lock().unwrap());
+        let id_c = rendered
+            .tree
+            .iter()
+            .find(|node| node.path == file_c)
+            .and_then(|node| node.id)
+            .unwrap();
 
-        let filters = json!({
-            "contentSearchQuery": "world"
-        });
+        // A rescan finds a new file that sorts first and shifts every index.
+        harness.create_file("a.rs", "");
+        harness.set_initial_files(&["a.rs", "b.rs", "c.rs"]);
+        toggle_selection(json!(id_c), harness.proxy.clone(), harness.state.clone());
 
-        update_filters(filters, harness.proxy.clone(), harness.state.clone()).await;
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.selected_files_count, 1);
+        assert_eq!(
+            harness.state.lock().unwrap().selected_files,
+            HashSet::from([file_c])
+        );
 
-        let final_state = harness.get_last_state_update().await.unwrap();
-        assert_eq!(final_state.content_search_query, "world");
+        // Ids of a previously loaded directory are not resolved at all.
+        harness.state.lock().unwrap().reset_directory_state();
         assert_eq!(
-            final_state.visible_files_count, 1,
-            "The matching file should be visible"
+            harness
+                .state
+                .lock()
+                .unwrap()
+                .resolve_path_ref(PathRef::Id(id_c)),
+            None
         );
     }
 
+    #[cfg(unix)]
     #[tokio::test]
-    async fn test_add_ignore_path_handles_path_outside_root() {
+    async fn test_non_utf8_file_names_round_trip_through_tree_ids() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
         let mut harness = TestHarness::new();
-        harness.set_initial_files(&["src/main.rs"]);
-        let initial_patterns_count = harness.state.lock().unwrap().config.ignore_patterns.len();
-        let outside_path = json!("/etc/hosts");
+        let name = OsStr::from_bytes(b"bad\xff.txt");
+        let path = harness.root_path.join(name);
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.current_path = harness.root_path.to_string_lossy().to_string();
+            state.full_file_list = vec![file_item(path.clone(), false)];
+            state.filtered_file_list = state.full_file_list.clone();
+        }
 
-        add_ignore_path(outside_path, harness.proxy.clone(), harness.state.clone()).await;
+        let id = harness.state.lock().unwrap().path_ids.id(&path);
+        toggle_selection(json!(id), harness.proxy.clone(), harness.state.clone());
+        let ui_state = harness.get_last_state_update().await.unwrap();
 
-        let event = harness.get_next_event().await;
-        assert!(event.is_none());
-        let final_patterns_count = harness.state.lock().unwrap().config.ignore_patterns.len();
-        assert_eq!(initial_patterns_count, final_patterns_count);
+        // The UI state still serializes, and the node carries an id instead of an exact path.
+        let serialized = serde_json::to_value(&*ui_state).unwrap();
+        assert_eq!(serialized["tree"][0]["id"], json!(id));
+        assert_eq!(serialized["tree"][0]["name"], json!("bad\u{FFFD}.txt"));
+        assert!(harness.state.lock().unwrap().selected_files.contains(&path));
+        assert_eq!(
+            ignore_pattern_for_path(&path, &harness.root_path).as_deref(),
+            Some("bad*.txt")
+        );
     }
 
     #[tokio::test]
-    async fn test_add_ignore_path_handles_duplicate_pattern() {
+    async fn test_toggle_directory_selection_selects_and_deselects_all_children() {
         let mut harness = TestHarness::new();
-        harness.set_initial_files(&["docs/guide.md"]);
+        harness.create_file("src/main.rs", "");
+        let dir_path = harness.create_dir("src");
+        harness.set_initial_files(&["src", "src/main.rs"]);
+        let payload = json!(dir_path);
 
-        let path_to_ignore = harness.root_path.join("docs");
-        let payload = json!(path_to_ignore);
-        add_ignore_path(
+        toggle_directory_selection(
             payload.clone(),
             harness.proxy.clone(),
             harness.state.clone(),
-        )
-        .await;
-
-        let _ = harness.wait_for_scan_completion().await;
-
-        add_ignore_path(payload, harness.proxy.clone(), harness.state.clone()).await;
-
-        let event = harness.get_next_event().await;
-        assert!(
-            event.is_none(),
-            "No rescan should be triggered for a duplicate ignore pattern"
         );
+        let ui_state_select = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state_select.selected_files_count, 1);
+
+        toggle_directory_selection(payload, harness.proxy.clone(), harness.state.clone());
+        let ui_state_deselect = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state_deselect.selected_files_count, 0);
     }
 
     #[tokio::test]
-    async fn test_import_config_handles_nonexistent_scan_directory() {
+    async fn test_large_directories_are_truncated_and_load_more_on_request() {
         let mut harness = TestHarness::new();
-        let new_config_path = harness.root_path.join("new_config.json");
-        let nonexistent_project_dir = harness.root_path.join("nonexistent_dir");
-
-        let new_config = AppConfig {
-            last_directory: Some(nonexistent_project_dir),
-            ..Default::default()
+        let dir_path = harness.create_dir("migrations");
+        let mut paths = vec!["migrations".to_string()];
+        for n in 1..=5 {
+            let rel = format!("migrations/{n:04}.sql");
+            harness.create_file(&rel, "");
+            paths.push(rel);
+        }
+        harness.set_initial_files(&paths.iter().map(String::as_str).collect::<Vec<_>>());
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.max_children_displayed = Some(2);
+            state.expanded_dirs.insert(dir_path.clone());
+        }
+        let names = |ui_state: &UiState| -> Vec<String> {
+            ui_state.tree[0]
+                .children
+                .iter()
+                .map(|child| child.name.clone())
+                .collect()
         };
-        std_fs::write(
-            &new_config_path,
-            serde_json::to_string(&new_config).unwrap(),
-        )
-        .unwrap();
-        harness.dialog.set_pick_file(Some(new_config_path));
 
-        import_config(
-            harness.dialog.as_ref(),
+        toggle_directory_selection(
+            json!(dir_path),
             harness.proxy.clone(),
             harness.state.clone(),
-        )
-        .await;
-
-        let event = harness.get_next_event().await;
-        assert!(matches!(event, Some(UserEvent::StateUpdate(_))));
+        );
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.selected_files_count, 5);
+        assert_eq!(names(&ui_state), ["0001.sql", "0002.sql", "… and 3 more"]);
+        let more = &ui_state.tree[0].children[2];
+        assert_eq!(
+            (more.path.as_path(), more.hidden_children),
+            (dir_path.as_path(), 3)
+        );
 
-        let second_event = harness.get_next_event().await;
-        assert!(
-            second_event.is_none(),
-            "No scan should start for a nonexistent directory"
+        load_more_children(
+            json!({ "path": dir_path, "offset": 2 }),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(
+            names(&ui_state),
+            [
+                "0001.sql",
+                "0002.sql",
+                "0003.sql",
+                "0004.sql",
+                "… and 1 more"
+            ]
         );
     }
 
-    // =========================================================================================
-    // The following tests call SYNCHRONOUS commands and DO NOT NEED .await
-    // =========================================================================================
+    #[tokio::test]
+    async fn test_toggle_expansion_adds_and_removes_dir() {
+        let mut harness = TestHarness::new();
+        let dir_to_toggle = harness.create_dir("src");
+        harness.set_initial_files(&["src"]);
+        let payload = json!(dir_to_toggle);
+
+        toggle_expansion(
+            payload.clone(),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+        let ui_state1 = harness.get_last_state_update().await.unwrap();
+        assert!(ui_state1.tree[0].is_expanded);
+
+        toggle_expansion(payload, harness.proxy.clone(), harness.state.clone());
+        let ui_state2 = harness.get_last_state_update().await.unwrap();
+        assert!(!ui_state2.tree[0].is_expanded);
+    }
 
     #[tokio::test]
-    async fn test_clear_directory_resets_state() {
+    async fn test_reveal_path_expands_only_the_ancestors() {
         let mut harness = TestHarness::new();
-        let file_path = harness.create_file("file.txt", "content");
-        harness.set_initial_files(&["file.txt"]);
+        let file_path = harness.create_file("src/app/main.rs", "");
+        harness.create_file("docs/guide.md", "");
+        harness.set_initial_files(&["src", "src/app", "src/app/main.rs", "docs", "docs/guide.md"]);
         {
             let mut state = harness.state.lock().unwrap();
-            state.selected_files.insert(file_path);
-            state.config.last_directory = Some(harness.root_path.clone());
+            state.loaded_dirs = ["src", "src/app", "docs"]
+                .iter()
+                .map(|dir| harness.root_path.join(dir))
+                .collect();
         }
 
-        clear_directory(harness.proxy.clone(), harness.state.clone());
+        reveal_path(
+            json!(file_path),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+        harness.get_last_state_update().await.unwrap();
 
-        let ui_state = harness.get_last_state_update().await.unwrap();
-        assert!(ui_state.current_path.is_empty());
-        assert_eq!(ui_state.visible_files_count, 0);
         let state = harness.state.lock().unwrap();
-        assert!(state.current_path.is_empty());
-        assert!(state.full_file_list.is_empty());
-        assert!(state.selected_files.is_empty());
-        assert!(state.config.last_directory.is_none());
+        assert_eq!(
+            state.expanded_dirs,
+            HashSet::from([
+                harness.root_path.join("src"),
+                harness.root_path.join("src/app"),
+            ])
+        );
     }
 
     #[tokio::test]
-    async fn test_cancel_scan_updates_state() {
+    async fn test_collapse_to_preview_keeps_only_the_preview_ancestors_expanded() {
         let mut harness = TestHarness::new();
+        let file_path = harness.create_file("src/app/main.rs", "");
+        harness.create_file("docs/guide.md", "");
+        harness.set_initial_files(&["src", "src/app", "src/app/main.rs", "docs", "docs/guide.md"]);
         {
             let mut state = harness.state.lock().unwrap();
-            state.is_scanning = true;
-            let handle = tokio::spawn(async {});
-            state.scan_task = Some(handle);
+            state.loaded_dirs = ["src", "src/app", "docs"]
+                .iter()
+                .map(|dir| harness.root_path.join(dir))
+                .collect();
+            state.expanded_dirs = state.loaded_dirs.clone();
+            state.previewed_file_path = Some(file_path);
         }
 
-        cancel_scan(harness.proxy.clone(), harness.state.clone());
+        collapse_to_preview(harness.proxy.clone(), harness.state.clone());
+        harness.get_last_state_update().await.unwrap();
 
-        let ui_state = harness.get_last_state_update().await.unwrap();
-        assert!(!ui_state.is_scanning);
-        assert_eq!(ui_state.status_message, "Scan cancelled.");
+        let state = harness.state.lock().unwrap();
+        assert_eq!(
+            state.expanded_dirs,
+            HashSet::from([
+                harness.root_path.join("src"),
+                harness.root_path.join("src/app"),
+            ])
+        );
     }
 
     #[tokio::test]
-    async fn test_initialize_sends_initial_state() {
+    async fn test_select_current_toggles_files_and_directories() {
         let mut harness = TestHarness::new();
-        initialize(harness.proxy.clone(), harness.state.clone());
+        let file_path = harness.create_file("src/main.rs", "");
+        harness.create_file("src/lib.rs", "");
+        let dir_path = harness.create_dir("src");
+        harness.set_initial_files(&["src", "src/main.rs", "src/lib.rs"]);
+
+        select_current(
+            json!(file_path),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
         let ui_state = harness.get_last_state_update().await.unwrap();
-        assert_eq!(ui_state.current_path, harness.root_path.to_string_lossy());
+        assert_eq!(ui_state.selected_files_count, 1);
+
+        // A partially selected directory gets all its files selected.
+        select_current(
+            json!(dir_path),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.selected_files_count, 2);
+
+        select_current(
+            json!(dir_path),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.selected_files_count, 0);
     }
 
     #[tokio::test]
-    async fn test_load_file_preview_sends_search_term() {
+    async fn test_expand_and_collapse_current_are_idempotent() {
         let mut harness = TestHarness::new();
-        let file_path = harness.create_file("preview.txt", "content with magic_word");
-        let search_term = "magic_word";
-        {
-            let mut state = harness.state.lock().unwrap();
-            state.content_search_query = search_term.to_string();
-        }
-        let payload = json!(file_path);
+        let dir_path = harness.create_dir("src");
+        harness.set_initial_files(&["src"]);
+        let payload = json!(dir_path);
 
-        load_file_preview(payload, harness.proxy.clone(), harness.state.clone());
+        for _ in 0..2 {
+            expand_current(
+                payload.clone(),
+                harness.proxy.clone(),
+                harness.state.clone(),
+            );
+            let ui_state = harness.get_last_state_update().await.unwrap();
+            assert!(ui_state.tree[0].is_expanded);
+        }
 
-        let mut saw_preview = false;
         for _ in 0..2 {
-            if let Some(event) = harness.get_next_event().await {
-                if let UserEvent::ShowFilePreview {
-                    search_term: term, ..
-                } = event
-                {
-                    assert_eq!(term, Some(search_term.to_string()));
-                    saw_preview = true;
-                }
-            }
+            collapse_current(
+                payload.clone(),
+                harness.proxy.clone(),
+                harness.state.clone(),
+            );
+            let ui_state = harness.get_last_state_update().await.unwrap();
+            assert!(!ui_state.tree[0].is_expanded);
         }
-        assert!(
-            saw_preview,
-            "Did not receive the ShowFilePreview event with correct search term"
-        );
     }
 
     #[tokio::test]
-    async fn test_load_directory_level_starts_lazy_scan() {
+    async fn test_expand_collapse_all() {
         let mut harness = TestHarness::new();
-        let sub_dir = harness.create_dir("src/components");
-        harness.create_file("src/components/button.js", "");
-        harness.set_initial_files(&["src", "src/components"]);
+        harness.create_dir("src");
+        harness.set_initial_files(&["src"]);
 
-        let payload = json!(sub_dir);
-        load_directory_level(payload, harness.proxy.clone(), harness.state.clone());
+        expand_collapse_all(json!(true), harness.proxy.clone(), harness.state.clone());
+        let ui_state1 = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state1.tree.iter().filter(|n| n.is_expanded).count(), 1);
 
-        let final_state = harness.get_last_state_update().await.unwrap();
-        let src_node = final_state.tree.iter().find(|n| n.name == "src").unwrap();
-        let components_node = src_node
-            .children
-            .iter()
-            .find(|n| n.name == "components")
-            .unwrap();
-        assert!(components_node
-            .children
-            .iter()
-            .any(|n| n.name == "button.js"));
+        expand_collapse_all(json!(false), harness.proxy.clone(), harness.state.clone());
+        let ui_state2 = harness.get_last_state_update().await.unwrap();
+        assert!(ui_state2.tree.iter().all(|n| !n.is_expanded));
+    }
+
+    #[tokio::test]
+    async fn test_select_all_and_deselect_all() {
+        let mut harness = TestHarness::new();
+        harness.create_file("file1.txt", "");
+        harness.create_file("file2.txt", "");
+        harness.set_initial_files(&["file1.txt", "file2.txt"]);
+
+        select_all(harness.proxy.clone(), harness.state.clone());
+        let ui_state1 = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state1.selected_files_count, 2);
+
+        deselect_all(harness.proxy.clone(), harness.state.clone());
+        let ui_state2 = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state2.selected_files_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_commands_keep_working_after_a_task_panicked_holding_the_state_lock() {
+        let mut harness = TestHarness::new();
+        harness.create_file("file1.txt", "a");
+        harness.create_file("file2.txt", "b");
+        harness.set_initial_files(&["file1.txt", "file2.txt"]);
+
+        let state = harness.state.clone();
+        let task = tokio::spawn(async move {
+            let _guard = state.lock().unwrap();
+            panic!("simulated panic while holding the state lock");
+        });
+        assert!(task.await.is_err());
+        assert!(harness.state.is_poisoned());
+
+        select_all(harness.proxy.clone(), harness.state.clone());
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.selected_files_count, 2);
+        assert!(!harness.state.is_poisoned());
+
+        deselect_all(harness.proxy.clone(), harness.state.clone());
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.selected_files_count, 0);
     }
 
-    // ... All other synchronous tests remain unchanged ...
-    #[tokio::test]
-    async fn test_toggle_selection_adds_and_removes_file() {
-        let mut harness = TestHarness::new();
-        let file_path = harness.create_file("test.rs", "");
-        harness.set_initial_files(&["test.rs"]);
-        let payload = json!(file_path);
+    #[tokio::test]
+    async fn test_select_and_deselect_by_extension() {
+        let mut harness = TestHarness::new();
+        harness.create_dir("src");
+        harness.create_file("src/main.rs", "");
+        harness.create_file("lib.RS", "");
+        harness.create_file("notes.txt", "");
+        harness.create_file("archive.tar.gz", "");
+        harness.set_initial_files(&[
+            "src",
+            "src/main.rs",
+            "lib.RS",
+            "notes.txt",
+            "archive.tar.gz",
+        ]);
+        let root = harness.root_path.clone();
+
+        select_by_extension(json!("rs"), harness.proxy.clone(), harness.state.clone());
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.selected_files_count, 2);
+        {
+            let state = harness.state.lock().unwrap();
+            assert!(state.selected_files.contains(&root.join("src/main.rs")));
+            assert!(state.selected_files.contains(&root.join("lib.RS")));
+        }
 
-        toggle_selection(
-            payload.clone(),
+        select_by_extension(
+            json!("*.tar.gz"),
             harness.proxy.clone(),
             harness.state.clone(),
         );
-        let ui_state1 = harness.get_last_state_update().await.unwrap();
-        assert_eq!(ui_state1.selected_files_count, 1);
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.selected_files_count, 3);
 
-        toggle_selection(payload, harness.proxy.clone(), harness.state.clone());
-        let ui_state2 = harness.get_last_state_update().await.unwrap();
-        assert_eq!(ui_state2.selected_files_count, 0);
+        deselect_by_extension(json!(".rs"), harness.proxy.clone(), harness.state.clone());
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.selected_files_count, 1);
+        assert!(harness
+            .state
+            .lock()
+            .unwrap()
+            .selected_files
+            .contains(&root.join("archive.tar.gz")));
     }
 
     #[tokio::test]
-    async fn test_toggle_directory_selection_selects_and_deselects_all_children() {
+    async fn test_select_by_extension_only_selects_visible_files() {
         let mut harness = TestHarness::new();
-        harness.create_file("src/main.rs", "");
-        let dir_path = harness.create_dir("src");
-        harness.set_initial_files(&["src", "src/main.rs"]);
-        let payload = json!(dir_path);
+        harness.create_file("visible.rs", "");
+        harness.create_file("hidden.rs", "");
+        harness.set_initial_files(&["visible.rs", "hidden.rs"]);
+        let root = harness.root_path.clone();
+        {
+            let mut state = harness.state.lock().unwrap();
+            let hidden = root.join("hidden.rs");
+            state.filtered_file_list.retain(|item| item.path != hidden);
+        }
 
-        toggle_directory_selection(
-            payload.clone(),
-            harness.proxy.clone(),
-            harness.state.clone(),
+        select_by_extension(json!("rs"), harness.proxy.clone(), harness.state.clone());
+        harness.get_last_state_update().await.unwrap();
+        let state = harness.state.lock().unwrap();
+        assert_eq!(
+            state.selected_files,
+            HashSet::from([root.join("visible.rs")])
         );
-        let ui_state_select = harness.get_last_state_update().await.unwrap();
-        assert_eq!(ui_state_select.selected_files_count, 1);
-
-        toggle_directory_selection(payload, harness.proxy.clone(), harness.state.clone());
-        let ui_state_deselect = harness.get_last_state_update().await.unwrap();
-        assert_eq!(ui_state_deselect.selected_files_count, 0);
     }
 
     #[tokio::test]
-    async fn test_toggle_expansion_adds_and_removes_dir() {
+    async fn test_bulk_selection_skips_likely_generated_files() {
         let mut harness = TestHarness::new();
-        let dir_to_toggle = harness.create_dir("src");
-        harness.set_initial_files(&["src"]);
-        let payload = json!(dir_to_toggle);
+        harness.create_dir("web");
+        harness.create_file("web/app.js", "");
+        harness.create_file("web/bundle.min.js", "");
+        harness.set_initial_files(&["web", "web/app.js", "web/bundle.min.js"]);
+        let root = harness.root_path.clone();
+        let generated = root.join("web/bundle.min.js");
+        {
+            let mut state = harness.state.lock().unwrap();
+            for item in state.filtered_file_list.iter_mut() {
+                item.likely_generated = item.path == generated;
+            }
+        }
 
-        toggle_expansion(
-            payload.clone(),
+        select_all(harness.proxy.clone(), harness.state.clone());
+        harness.get_last_state_update().await.unwrap();
+        assert_eq!(
+            harness.state.lock().unwrap().selected_files,
+            HashSet::from([root.join("web/app.js")])
+        );
+
+        // With every selectable file selected, toggling the directory deselects.
+        toggle_directory_selection(
+            json!(root.join("web")),
             harness.proxy.clone(),
             harness.state.clone(),
         );
-        let ui_state1 = harness.get_last_state_update().await.unwrap();
-        assert!(ui_state1.tree[0].is_expanded);
+        harness.get_last_state_update().await.unwrap();
+        assert!(harness.state.lock().unwrap().selected_files.is_empty());
 
-        toggle_expansion(payload, harness.proxy.clone(), harness.state.clone());
-        let ui_state2 = harness.get_last_state_update().await.unwrap();
-        assert!(!ui_state2.tree[0].is_expanded);
+        harness.state.lock().unwrap().config.skip_generated = false;
+        select_by_extension(json!("js"), harness.proxy.clone(), harness.state.clone());
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.selected_files_count, 2);
     }
 
     #[tokio::test]
-    async fn test_expand_collapse_all() {
+    async fn test_pin_and_unpin_file() {
         let mut harness = TestHarness::new();
-        harness.create_dir("src");
-        harness.set_initial_files(&["src"]);
+        harness.create_file("schema.rs", "");
+        harness.set_initial_files(&["schema.rs"]);
+        let path = harness.root_path.join("schema.rs");
+        let id = harness.state.lock().unwrap().path_ids.id(&path);
 
-        expand_collapse_all(json!(true), harness.proxy.clone(), harness.state.clone());
-        let ui_state1 = harness.get_last_state_update().await.unwrap();
-        assert_eq!(ui_state1.tree.iter().filter(|n| n.is_expanded).count(), 1);
+        pin_file(json!(id), harness.proxy.clone(), harness.state.clone());
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.selected_files_count, 1);
+        assert!(ui_state.tree[0].is_pinned);
+        assert!(harness
+            .state
+            .lock()
+            .unwrap()
+            .config
+            .pinned_files
+            .contains(&path));
 
-        expand_collapse_all(json!(false), harness.proxy.clone(), harness.state.clone());
-        let ui_state2 = harness.get_last_state_update().await.unwrap();
-        assert!(ui_state2.tree.iter().all(|n| !n.is_expanded));
+        unpin_file(json!(id), harness.proxy.clone(), harness.state.clone());
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.selected_files_count, 1);
+        assert!(!ui_state.tree[0].is_pinned);
+        assert!(harness.state.lock().unwrap().config.pinned_files.is_empty());
     }
 
     #[tokio::test]
-    async fn test_select_all_and_deselect_all() {
+    async fn test_set_generation_order_overrides_and_clears() {
         let mut harness = TestHarness::new();
-        harness.create_file("file1.txt", "");
-        harness.create_file("file2.txt", "");
-        harness.set_initial_files(&["file1.txt", "file2.txt"]);
-
+        harness.create_file("README.md", "");
+        harness.create_file("a.rs", "");
+        harness.create_file("b.rs", "");
+        harness.set_initial_files(&["README.md", "a.rs", "b.rs"]);
+        let root = harness.root_path.clone();
         select_all(harness.proxy.clone(), harness.state.clone());
-        let ui_state1 = harness.get_last_state_update().await.unwrap();
-        assert_eq!(ui_state1.selected_files_count, 2);
+        harness.get_last_state_update().await.unwrap();
 
-        deselect_all(harness.proxy.clone(), harness.state.clone());
-        let ui_state2 = harness.get_last_state_update().await.unwrap();
-        assert_eq!(ui_state2.selected_files_count, 0);
+        set_generation_order(
+            json!([root.join("b.rs"), 0]),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+        harness.get_last_state_update().await.unwrap();
+        {
+            let state = harness.state.lock().unwrap();
+            assert_eq!(
+                get_selected_files_in_tree_order(&state),
+                vec![root.join("b.rs"), root.join("README.md"), root.join("a.rs")]
+            );
+        }
+
+        set_generation_order(json!(null), harness.proxy.clone(), harness.state.clone());
+        harness.get_last_state_update().await.unwrap();
+        let state = harness.state.lock().unwrap();
+        assert!(state.generation_order_override.is_none());
+        assert_eq!(
+            get_selected_files_in_tree_order(&state),
+            vec![root.join("README.md"), root.join("a.rs"), root.join("b.rs")]
+        );
     }
 
     #[tokio::test]
@@ -1512,42 +4446,343 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_clear_preview_state() {
-        let harness = TestHarness::new();
-        let file_path = harness.create_file("file.txt", "content");
+    async fn test_cancel_all_stops_every_task_with_one_state_update() {
+        let mut harness = TestHarness::new();
+        let never_ending = || tokio::spawn(tokio::time::sleep(Duration::from_secs(60)));
+        let (scan_flag, generation_flag, estimation_flag, search_flag) = {
+            let mut state = harness.state.lock().unwrap();
+            state.scan_task = Some(never_ending());
+            state.generation_task = Some(never_ending());
+            state.estimation_task = Some(never_ending());
+            state.is_scanning = true;
+            state.is_generating = true;
+            state.is_estimating = true;
+            state.generation_awaiting_confirmation = true;
+            (
+                state.scan_cancellation_flag.clone(),
+                state.generation_cancellation_flag.clone(),
+                state.estimation_cancellation_flag.clone(),
+                state.search_cancellation_flag.clone(),
+            )
+        };
+
+        cancel_all(harness.proxy.clone(), harness.state.clone());
+
+        let Some(UserEvent::StateUpdate(ui_state)) = harness.get_next_event().await else {
+            panic!("Expected a StateUpdate event");
+        };
+        assert!(!ui_state.is_scanning);
+        assert!(!ui_state.is_generating);
+        assert!(!ui_state.is_estimating);
+        assert!(harness.event_rx.try_recv().is_err());
+
+        for flag in [scan_flag, generation_flag, estimation_flag, search_flag] {
+            assert!(flag.load(Ordering::SeqCst));
+        }
+        let state = harness.state.lock().unwrap();
+        assert!(state.scan_task.is_none());
+        assert!(state.generation_task.is_none());
+        assert!(state.estimation_task.is_none());
+        assert!(!state.generation_awaiting_confirmation);
+    }
+
+    #[tokio::test]
+    async fn test_clear_preview_state() {
+        let harness = TestHarness::new();
+        let file_path = harness.create_file("file.txt", "content");
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.previewed_file_path = Some(file_path);
+        }
+        clear_preview_state(harness.proxy.clone(), harness.state.clone());
+        let state = harness.state.lock().unwrap();
+        assert!(state.previewed_file_path.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_apply_ignore_preset_merges_patterns() {
+        let mut harness = TestHarness::new();
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.current_path.clear();
+            state.config.ignore_patterns = HashSet::from(["*.log".to_string()]);
+        }
+
+        apply_ignore_preset(json!("Rust"), harness.proxy.clone(), harness.state.clone()).await;
+
+        let patterns = harness.state.lock().unwrap().config.ignore_patterns.clone();
+        assert!(patterns.contains("*.log"));
+        assert!(patterns.contains("target/"));
+        assert!(patterns.contains("Cargo.lock"));
+
+        apply_ignore_preset(json!("cobol"), harness.proxy.clone(), harness.state.clone()).await;
+        match harness.get_next_event().await.unwrap() {
+            UserEvent::ShowError(msg) => assert!(msg.contains("cobol")),
+            _ => panic!("Expected ShowError event"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_file_writes_to_disk_on_ok() {
+        let mut harness = TestHarness::new();
+        let save_path = harness.root_path.join("output.txt");
+        let content_to_save = "Hello, World!";
+        harness.dialog.set_save_file(Some(save_path.clone()));
+
+        save_file(
+            harness.dialog.as_ref(),
+            json!(content_to_save),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+
+        let event = harness.get_next_event().await.unwrap();
+        match event {
+            UserEvent::SaveComplete(success, path_str) => {
+                assert!(success);
+                assert_eq!(path_str, save_path.to_string_lossy());
+            }
+            _ => panic!("Expected SaveComplete event"),
+        }
+        let written_content = std_fs::read_to_string(save_path).unwrap();
+        assert_eq!(written_content, content_to_save);
+    }
+
+    #[tokio::test]
+    async fn test_save_file_compresses_gz_and_zip_names() {
+        use std::io::Read;
+
+        let mut harness = TestHarness::new();
+        let content_to_save = "Hello, compressed World!";
+
+        let gz_path = harness.root_path.join("output.txt.gz");
+        harness.dialog.set_save_file(Some(gz_path.clone()));
+        save_file(
+            harness.dialog.as_ref(),
+            json!(content_to_save),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+        match harness.get_next_event().await.unwrap() {
+            UserEvent::SaveComplete(success, path_str) => {
+                assert!(success);
+                assert_eq!(path_str, gz_path.to_string_lossy());
+            }
+            _ => panic!("Expected SaveComplete event"),
+        }
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(std_fs::File::open(&gz_path).unwrap())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, content_to_save);
+
+        let zip_path = harness.root_path.join("output.txt.zip");
+        harness.dialog.set_save_file(Some(zip_path.clone()));
+        save_file(
+            harness.dialog.as_ref(),
+            json!(content_to_save),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+        assert!(matches!(
+            harness.get_next_event().await.unwrap(),
+            UserEvent::SaveComplete(true, _)
+        ));
+        let mut archive = zip::ZipArchive::new(std_fs::File::open(&zip_path).unwrap()).unwrap();
+        assert_eq!(archive.len(), 1);
+        let mut entry = archive.by_name("output.txt").unwrap();
+        let mut unzipped = String::new();
+        entry.read_to_string(&mut unzipped).unwrap();
+        assert_eq!(unzipped, content_to_save);
+    }
+
+    #[tokio::test]
+    async fn test_save_to_configured_path_writes_without_dialog() {
+        let mut harness = TestHarness::new();
+        let output_dir = harness.create_dir("out");
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.output_directory = Some(output_dir.clone());
+            state.config.output_filename = "result.txt".to_string();
+        }
+
+        save_to_configured_path(json!("first"), harness.proxy.clone(), harness.state.clone());
+        save_to_configured_path(
+            json!("second"),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+
+        let first = output_dir.join("result.txt");
+        let second = output_dir.join("result_(1).txt");
+        for expected in [&first, &second] {
+            match harness.get_next_event().await.unwrap() {
+                UserEvent::SaveComplete(success, path_str) => {
+                    assert!(success);
+                    assert_eq!(path_str, expected.to_string_lossy());
+                }
+                _ => panic!("Expected SaveComplete event"),
+            }
+        }
+        assert_eq!(std_fs::read_to_string(first).unwrap(), "first");
+        assert_eq!(std_fs::read_to_string(second).unwrap(), "second");
+    }
+
+    #[tokio::test]
+    async fn test_save_to_configured_path_writes_a_split_output_in_parts() {
+        let mut harness = TestHarness::new();
+        let output_dir = harness.create_dir("out");
+        let root = harness.root_path.clone();
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.output_directory = Some(output_dir.clone());
+            state.config.output_filename = "result.txt".to_string();
+            state.config.split_output_bytes = Some(21);
+            state.config.chunk_header_template = "// Part {part} of {total}: {files}".to_string();
+            let output = crate::core::ConcatenatedOutput {
+                content: "HEAD\na: aaaa\nb: bbbb\nc: cccc\nEND\n".to_string(),
+                file_ranges: vec![
+                    (root.join("a.txt"), 5..13),
+                    (root.join("b.txt"), 13..21),
+                    (root.join("c.txt"), 21..29),
+                ],
+                body: 5..29,
+            };
+            let selection = output
+                .file_ranges
+                .iter()
+                .map(|(path, _)| path.clone())
+                .collect();
+            state.last_generated = Some(crate::app::state::GeneratedBody::new(
+                output,
+                selection,
+                &state.config,
+            ));
+        }
+
+        save_to_configured_path(
+            json!("edited"),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+
+        let first = output_dir.join("result_part1of2.txt");
+        match harness.get_next_event().await.unwrap() {
+            UserEvent::SaveComplete(true, path_str) => {
+                assert_eq!(path_str, first.to_string_lossy())
+            }
+            other => panic!("Expected a successful SaveComplete, got {:?}", other),
+        }
+        assert_eq!(
+            std_fs::read_to_string(first).unwrap(),
+            "// Part 1 of 2: a.txt, b.txt\n\nHEAD\na: aaaa\nb: bbbb\n"
+        );
+        assert_eq!(
+            std_fs::read_to_string(output_dir.join("result_part2of2.txt")).unwrap(),
+            "// Part 2 of 2: c.txt\n\nc: cccc\nEND\n"
+        );
+        assert!(!output_dir.join("result.txt").exists());
+    }
+
+    #[test]
+    fn test_part_filename_marks_the_part_before_the_first_extension() {
+        assert_eq!(part_filename("out.txt", 2, 3), "out_part2of3.txt");
+        assert_eq!(part_filename("out.txt.gz", 1, 3), "out_part1of3.txt.gz");
+        assert_eq!(part_filename("out", 3, 3), "out_part3of3");
+    }
+
+    #[test]
+    fn test_output_filename_placeholders_expand_per_project() {
+        use chrono::TimeZone;
+        let now = chrono::Local
+            .with_ymd_and_hms(2024, 3, 9, 14, 5, 7)
+            .unwrap();
+
+        assert_eq!(
+            expand_output_filename("{project}_context.txt", "/work/alpha", now),
+            "alpha_context.txt"
+        );
+        assert_eq!(
+            expand_output_filename("{project}-{date}_{time}.md", "/home/me/beta app", now),
+            "beta app-20240309_140507.md"
+        );
+        assert_eq!(
+            expand_output_filename("{project}_{branch}.txt", "", now),
+            "project_{branch}.txt"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_save_to_configured_path_expands_the_project_placeholder() {
+        let mut harness = TestHarness::new();
+        let output_dir = harness.create_dir("out");
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.output_directory = Some(output_dir.clone());
+            state.config.output_filename = "{project}_context.txt".to_string();
+        }
+
+        save_to_configured_path(json!("data"), harness.proxy.clone(), harness.state.clone());
+
+        let project = harness.root_path.file_name().unwrap().to_string_lossy();
+        let expected = output_dir.join(format!("{project}_context.txt"));
+        match harness.get_next_event().await.unwrap() {
+            UserEvent::SaveComplete(success, path_str) => {
+                assert!(success);
+                assert_eq!(path_str, expected.to_string_lossy());
+            }
+            _ => panic!("Expected SaveComplete event"),
+        }
+        assert_eq!(
+            harness.state.lock().unwrap().config.output_filename,
+            "{project}_context.txt"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_truncated_preview_saves_and_copies_the_full_output() {
+        let mut harness = TestHarness::new();
+        let output_dir = harness.create_dir("out");
         {
             let mut state = harness.state.lock().unwrap();
-            state.previewed_file_path = Some(file_path);
+            state.config.output_directory = Some(output_dir.clone());
+            state.config.output_filename = "full.txt".to_string();
+            state.generated_content = Some(HeldOutput::Text("head\ntail\n".into()));
         }
-        clear_preview_state(harness.proxy.clone(), harness.state.clone());
-        let state = harness.state.lock().unwrap();
-        assert!(state.previewed_file_path.is_none());
-    }
 
-    #[tokio::test]
-    async fn test_save_file_writes_to_disk_on_ok() {
-        let mut harness = TestHarness::new();
-        let save_path = harness.root_path.join("output.txt");
-        let content_to_save = "Hello, World!";
-        harness.dialog.set_save_file(Some(save_path.clone()));
+        save_to_configured_path(json!("head"), harness.proxy.clone(), harness.state.clone());
+        assert!(matches!(
+            harness.get_next_event().await,
+            Some(UserEvent::SaveComplete(true, _))
+        ));
+        assert_eq!(
+            std_fs::read_to_string(output_dir.join("full.txt")).unwrap(),
+            "head\ntail\n"
+        );
 
-        save_file(
-            harness.dialog.as_ref(),
-            json!(content_to_save),
+        copy_generated_content(
+            harness.clipboard.as_ref(),
             harness.proxy.clone(),
             harness.state.clone(),
         );
+        assert!(matches!(
+            harness.get_next_event().await,
+            Some(UserEvent::OutputCopied { .. })
+        ));
+        assert_eq!(harness.clipboard.copied(), vec!["head\ntail\n"]);
+    }
 
-        let event = harness.get_next_event().await.unwrap();
-        match event {
-            UserEvent::SaveComplete(success, path_str) => {
-                assert!(success);
-                assert_eq!(path_str, save_path.to_string_lossy());
-            }
-            _ => panic!("Expected SaveComplete event"),
-        }
-        let written_content = std_fs::read_to_string(save_path).unwrap();
-        assert_eq!(written_content, content_to_save);
+    #[test]
+    fn test_write_to_unique_path_skips_existing_names() {
+        let dir = tempdir().unwrap();
+        std_fs::write(dir.path().join("out"), "").unwrap();
+        std_fs::write(dir.path().join("out_(1)"), "").unwrap();
+
+        let output = HeldOutput::Text("data".into());
+        let path = write_to_unique_path(dir.path(), "out", &output).unwrap();
+
+        assert_eq!(path, dir.path().join("out_(2)"));
+        assert_eq!(std_fs::read_to_string(path).unwrap(), "data");
     }
 
     #[tokio::test]
@@ -1566,6 +4801,30 @@ mod tests {
         assert_eq!(state.config.output_directory, Some(new_dir));
     }
 
+    #[tokio::test]
+    async fn test_set_ui_preferences_clamps_the_zoom_and_keeps_missing_fields() {
+        let mut harness = TestHarness::new();
+        harness.state.lock().unwrap().config.theme = UiTheme::Dark;
+
+        set_ui_preferences(
+            json!({ "ui_zoom": 10.0 }),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+
+        assert!(matches!(
+            harness.get_next_event().await.unwrap(),
+            UserEvent::StateUpdate(_)
+        ));
+        match harness.get_next_event().await.unwrap() {
+            UserEvent::SetZoom(zoom) => assert_eq!(zoom, config::UI_ZOOM_RANGE.1),
+            other => panic!("Expected SetZoom event, got {other:?}"),
+        }
+        let state = harness.state.lock().unwrap();
+        assert_eq!(state.config.ui_zoom, config::UI_ZOOM_RANGE.1);
+        assert_eq!(state.config.theme, UiTheme::Dark);
+    }
+
     #[tokio::test]
     async fn test_export_config_sends_event() {
         let mut harness = TestHarness::new();
@@ -1584,6 +4843,41 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_export_tree_writes_filtered_view_with_tree_ignores() {
+        let mut harness = TestHarness::new();
+        harness.create_file("src/main.rs", "fn main() {}");
+        harness.create_file("docs/guide.md", "# Guide");
+        harness.set_initial_files(&["src", "src/main.rs", "docs", "docs/guide.md"]);
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.tree_ascii = true;
+            state
+                .config
+                .tree_ignore_patterns
+                .insert("docs/".to_string());
+        }
+        let save_path = harness.root_path.join("tree.txt");
+        harness.dialog.set_save_file(Some(save_path.clone()));
+
+        export_tree(
+            harness.dialog.as_ref(),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+
+        match harness.get_next_event().await.unwrap() {
+            UserEvent::TreeExported(success, path) => {
+                assert!(success);
+                assert_eq!(path, save_path.to_string_lossy());
+            }
+            other => panic!("Expected TreeExported event, got {:?}", other),
+        }
+        let tree = std::fs::read_to_string(&save_path).unwrap();
+        assert!(tree.ends_with("\\-- src/\n    \\-- main.rs\n"));
+        assert!(!tree.contains("guide.md"));
+    }
+
     #[tokio::test]
     async fn test_export_config_sends_no_event_on_cancel() {
         let mut harness = TestHarness::new();
@@ -1675,6 +4969,98 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_generate_preview_auto_includes_referenced_files_one_hop_deep() {
+        let mut harness = TestHarness::new();
+        let main = harness.create_file(
+            "web/main.js",
+            "import \"./util.js\";\nimport \"./huge.js\";",
+        );
+        harness.create_file("web/util.js", "import { b } from \"./deep.js\";");
+        harness.create_file("web/deep.js", "");
+        harness.create_file("web/huge.js", "");
+        harness.set_initial_files(&["web/main.js", "web/util.js", "web/deep.js", "web/huge.js"]);
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.auto_include_imports = true;
+            state.config.warn_over_bytes = None;
+            state.full_file_list[3].size = MAX_REFERENCED_FILE_BYTES + 1;
+            state.selected_files.insert(main.clone());
+        }
+
+        generate_preview(harness.proxy.clone(), harness.state.clone());
+
+        match harness.get_next_event().await.unwrap() {
+            UserEvent::FilesAutoIncluded(files) => {
+                assert_eq!(
+                    files,
+                    vec![Path::new("web").join("util.js").display().to_string()]
+                );
+            }
+            other => panic!("Expected FilesAutoIncluded, got {:?}", other),
+        }
+        assert_eq!(
+            harness.state.lock().unwrap().selected_files,
+            HashSet::from([main, harness.root_path.join("web/util.js")])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_preview_waits_for_confirmation_over_size_limit() {
+        let mut harness = TestHarness::new();
+        harness.create_file("big.txt", "0123456789");
+        harness.set_initial_files(&["big.txt"]);
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.warn_over_bytes = Some(5);
+            state.full_file_list[0].size = 10;
+            state
+                .selected_files
+                .insert(harness.root_path.join("big.txt"));
+        }
+
+        generate_preview(harness.proxy.clone(), harness.state.clone());
+
+        match harness.get_next_event().await.unwrap() {
+            UserEvent::ConfirmLargeGeneration { bytes, files } => {
+                assert_eq!((bytes, files), (10, 1));
+            }
+            other => panic!("Expected ConfirmLargeGeneration, got {:?}", other),
+        }
+        {
+            let state = harness.state.lock().unwrap();
+            assert!(
+                !state.is_generating,
+                "Generation must wait for confirmation"
+            );
+            assert!(state.generation_awaiting_confirmation);
+        }
+
+        confirm_generation(harness.proxy.clone(), harness.state.clone());
+
+        match harness.get_next_event().await.unwrap() {
+            UserEvent::StateUpdate(ui_state) => assert!(ui_state.is_generating),
+            other => panic!("Expected a StateUpdate event, got {:?}", other),
+        }
+        assert!(
+            !harness
+                .state
+                .lock()
+                .unwrap()
+                .generation_awaiting_confirmation
+        );
+    }
+
+    #[tokio::test]
+    async fn test_confirm_generation_without_pending_request_does_nothing() {
+        let mut harness = TestHarness::new();
+
+        confirm_generation(harness.proxy.clone(), harness.state.clone());
+
+        assert!(harness.get_next_event().await.is_none());
+        assert!(!harness.state.lock().unwrap().is_generating);
+    }
+
     #[tokio::test]
     async fn test_generate_preview_preserves_custom_filename() {
         let mut harness = TestHarness::new();
@@ -1898,6 +5284,30 @@ mod tests {
         assert_eq!(ui_state2.selected_files_count, 1, "select_all_fully failed");
     }
 
+    #[test]
+    fn test_output_filename_for_format_swaps_default_extensions_only() {
+        assert_eq!(
+            output_filename_for_format("cfc_output.txt", OutputFormat::Html),
+            "cfc_output.html"
+        );
+        assert_eq!(
+            output_filename_for_format("cfc_output.html", OutputFormat::PlainText),
+            "cfc_output.txt"
+        );
+        assert_eq!(
+            output_filename_for_format("cfc_output.html", OutputFormat::Xml),
+            "cfc_output.xml"
+        );
+        assert_eq!(
+            output_filename_for_format("context", OutputFormat::Html),
+            "context.html"
+        );
+        assert_eq!(
+            output_filename_for_format("context.md", OutputFormat::Html),
+            "context.md"
+        );
+    }
+
     #[tokio::test]
     async fn test_generate_preview_creates_timestamped_filename_from_default() {
         let mut harness = TestHarness::new();
@@ -1926,6 +5336,27 @@ mod tests {
         let _ = harness.get_next_event().await;
     }
 
+    #[tokio::test]
+    async fn test_generate_preview_restamps_a_default_filename_of_any_format() {
+        let mut harness = TestHarness::new();
+        let old_default_filename = "cfc_output_20000101_120000.xml".to_string();
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.output_filename = old_default_filename.clone();
+            state.config.output_format = OutputFormat::PlainText;
+        }
+
+        generate_preview(harness.proxy.clone(), harness.state.clone());
+
+        let final_filename = harness.state.lock().unwrap().config.output_filename.clone();
+        assert_ne!(final_filename, old_default_filename);
+        assert!(final_filename.starts_with("cfc_output_"));
+        assert!(final_filename.ends_with(".txt"));
+
+        let _ = harness.get_next_event().await;
+        let _ = harness.get_next_event().await;
+    }
+
     #[tokio::test]
     async fn test_import_config_with_no_last_directory() {
         let mut harness = TestHarness::new();
@@ -1954,6 +5385,91 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_generation_history_lists_and_reopens_previous_outputs() {
+        let mut harness = TestHarness::new();
+        {
+            let mut s = harness.state.lock().unwrap();
+            s.record_generation("oldest output".into(), 1, 1);
+            s.record_generation("first output".into(), 10, 1);
+            s.record_generation("second output".into(), 20, 2);
+        }
+
+        list_generation_history(harness.proxy.clone(), harness.state.clone());
+        let ids: Vec<u64> = match harness.get_next_event().await {
+            Some(UserEvent::GenerationHistory(history)) => {
+                let tokens: Vec<_> = history.iter().map(|r| r.token_count).collect();
+                assert_eq!(tokens, vec![20, 10, 1]);
+                history.iter().map(|r| r.id).collect()
+            }
+            other => panic!("Expected GenerationHistory, got {:?}", other),
+        };
+
+        // A newer generation does not change which output an id refers to.
+        harness
+            .state
+            .lock()
+            .unwrap()
+            .record_generation("third output".into(), 30, 3);
+        show_generation_from_history(json!(ids[0]), harness.proxy.clone(), harness.state.clone());
+        match harness.get_next_event().await {
+            Some(UserEvent::ShowGeneratedContent {
+                content,
+                token_count,
+            }) => {
+                assert_eq!(content, "second output");
+                assert_eq!(token_count, 20);
+            }
+            other => panic!("Expected ShowGeneratedContent, got {:?}", other),
+        }
+        assert!(matches!(
+            harness.get_next_event().await,
+            Some(UserEvent::StateUpdate(_))
+        ));
+
+        // Only the newest outputs are kept in full.
+        show_generation_from_history(json!(ids[1]), harness.proxy.clone(), harness.state.clone());
+        assert!(matches!(
+            harness.get_next_event().await,
+            Some(UserEvent::ShowError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reset_config_to_defaults_keeps_the_window_geometry() {
+        let mut harness = TestHarness::new();
+        {
+            let mut s = harness.state.lock().unwrap();
+            s.config.ignore_patterns.insert("*.log".to_string());
+            s.config.include_tree_by_default = !AppConfig::default().include_tree_by_default;
+            s.config.window_size = (1600.0, 900.0);
+            s.config.window_position = (42.0, 24.0);
+            s.active_profile = Some("work".to_string());
+        }
+
+        reset_config_to_defaults(harness.proxy.clone(), harness.state.clone());
+
+        match harness.get_next_event().await {
+            Some(UserEvent::StateUpdate(ui_state)) => {
+                assert_eq!(ui_state.config.window_size, (1600.0, 900.0));
+                assert!(ui_state.current_path.is_empty());
+            }
+            other => panic!("Expected a StateUpdate, got {:?}", other),
+        }
+
+        let s = harness.state.lock().unwrap();
+        let defaults = AppConfig::default();
+        assert_eq!(s.config.ignore_patterns, defaults.ignore_patterns);
+        assert_eq!(
+            s.config.include_tree_by_default,
+            defaults.include_tree_by_default
+        );
+        assert_eq!(s.config.window_size, (1600.0, 900.0));
+        assert_eq!(s.config.window_position, (42.0, 24.0));
+        assert!(s.active_profile.is_none());
+        assert!(s.current_path.is_empty());
+    }
+
     #[tokio::test]
     async fn test_export_config_sends_false_on_failure() {
         let mut harness = TestHarness::new();
@@ -2041,7 +5557,7 @@ mod tests {
             "node_modules/package.json",
         ]);
 
-        // Set initial patterns
+        // Set initial patterns, as if they had excluded entries during the scan
         {
             let mut state = harness.state.lock().unwrap();
             state.config.ignore_patterns.insert("*.log".to_string());
@@ -2049,6 +5565,9 @@ mod tests {
                 .config
                 .ignore_patterns
                 .insert("node_modules/".to_string());
+            state
+                .scan_ignore_patterns
+                .insert("node_modules/".to_string());
         }
 
         // Remove one pattern
@@ -2065,6 +5584,78 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_update_config_restores_locally_ignored_files_without_rescan() {
+        let mut harness = TestHarness::new();
+        harness.create_file("src/main.rs", "fn main() {}");
+        harness.create_file("debug.log", "log content");
+        harness.set_initial_files(&["src", "src/main.rs", "debug.log"]);
+        harness.state.lock().unwrap().config.ignore_patterns.clear();
+
+        // The pattern is added after the scan, so its files are only hidden in memory.
+        let mut new_config = harness.state.lock().unwrap().config.clone();
+        new_config.ignore_patterns.insert("*.log".to_string());
+        let payload = serde_json::to_value(&new_config).unwrap();
+        update_config(payload, harness.proxy.clone(), harness.state.clone()).await;
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(ui_state.visible_files_count, 2);
+
+        new_config.ignore_patterns.remove("*.log");
+        let payload = serde_json::to_value(&new_config).unwrap();
+        update_config(payload, harness.proxy.clone(), harness.state.clone()).await;
+
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert!(
+            !ui_state.patterns_need_rescan,
+            "Files that are still in memory should not need a re-scan"
+        );
+        assert_eq!(ui_state.visible_files_count, 3);
+        assert!(!ui_state.active_ignore_patterns.contains("*.log"));
+    }
+
+    #[tokio::test]
+    async fn test_update_config_sets_rescan_flag_when_hidden_files_toggled() {
+        let mut harness = TestHarness::new();
+        harness.create_file("main.rs", "fn main() {}");
+        harness.set_initial_files(&["main.rs"]);
+        let mut new_config = harness.state.lock().unwrap().config.clone();
+        new_config.include_hidden = !new_config.include_hidden;
+        let payload = serde_json::to_value(new_config).unwrap();
+
+        update_config(payload, harness.proxy.clone(), harness.state.clone()).await;
+
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert!(
+            ui_state.patterns_need_rescan,
+            "Toggling hidden files must recommend a re-scan"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_config_sets_rescan_flag_on_negated_pattern() {
+        let mut harness = TestHarness::new();
+        harness.create_file("debug.log", "log content");
+        harness.create_file("keep.log", "important");
+        harness.set_initial_files(&["debug.log"]);
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.ignore_patterns.insert("*.log".to_string());
+            state.scan_ignore_patterns.insert("*.log".to_string());
+        }
+
+        let mut new_config = harness.state.lock().unwrap().config.clone();
+        new_config.ignore_patterns.insert("!keep.log".to_string());
+        let payload = serde_json::to_value(new_config).unwrap();
+
+        update_config(payload, harness.proxy.clone(), harness.state.clone()).await;
+
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert!(
+            ui_state.patterns_need_rescan,
+            "Flag should be set when an override pattern is added"
+        );
+    }
+
     #[tokio::test]
     async fn test_rescan_clears_patterns_need_rescan_flag() {
         let mut harness = TestHarness::new();
@@ -2228,4 +5819,78 @@ mod tests {
             .iter()
             .any(|node| node.name == artifact_dir_name));
     }
+
+    #[tokio::test]
+    async fn test_copy_selected_paths_writes_the_paths_to_the_clipboard() {
+        let mut harness = TestHarness::new();
+        let a = harness.create_file("src/a.rs", "a");
+        let b = harness.create_file("src/b.rs", "b");
+        harness.set_initial_files(&["src", "src/a.rs", "src/b.rs"]);
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.use_relative_paths = true;
+            state.selected_files = HashSet::from([a, b]);
+        }
+
+        copy_selected_paths(
+            harness.clipboard.as_ref(),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+
+        assert!(matches!(
+            harness.get_next_event().await,
+            Some(UserEvent::PathsCopied(2))
+        ));
+        let expected = ["src", "a.rs"]
+            .iter()
+            .collect::<PathBuf>()
+            .display()
+            .to_string()
+            + "\n"
+            + &["src", "b.rs"]
+                .iter()
+                .collect::<PathBuf>()
+                .display()
+                .to_string();
+        assert_eq!(harness.clipboard.copied(), vec![expected]);
+    }
+
+    #[tokio::test]
+    async fn test_generate_to_clipboard_copies_the_output_instead_of_previewing_it() {
+        let mut harness = TestHarness::new();
+        let file = harness.create_file("file.txt", "clipboard content");
+        harness.set_initial_files(&["file.txt"]);
+        harness.state.lock().unwrap().selected_files.insert(file);
+
+        generate_to_clipboard(
+            harness.clipboard.clone(),
+            harness.proxy.clone(),
+            harness.state.clone(),
+        );
+
+        let mut copied_event_found = false;
+        let timeout = tokio::time::sleep(std::time::Duration::from_secs(2));
+        tokio::pin!(timeout);
+        loop {
+            tokio::select! {
+                event = harness.get_next_event() => match event {
+                    Some(UserEvent::OutputCopied { .. }) => {
+                        copied_event_found = true;
+                        break;
+                    }
+                    Some(UserEvent::ShowGeneratedContent { .. }) => {
+                        panic!("The output should not be sent to the preview")
+                    }
+                    Some(_) => {}
+                    None => break,
+                },
+                _ = &mut timeout => { break; }
+            }
+        }
+        assert!(copied_event_found, "Did not receive OutputCopied");
+        let copied = harness.clipboard.copied();
+        assert_eq!(copied.len(), 1);
+        assert!(copied[0].contains("clipboard content"));
+    }
 }
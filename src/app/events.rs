@@ -3,7 +3,10 @@
 use serde::Deserialize;
 use std::path::PathBuf;
 
-use super::view_model::UiState;
+use super::state::GenerationRecord;
+use super::view_model::{ContentSearchFile, GenerationDiff, GenerationPlan, TreeStats, UiState};
+use crate::config::settings::ConfigIssue;
+use crate::config::UiTheme;
 use crate::core::ScanProgress;
 
 /// Events sent from the Rust backend to the WebView (UI thread).
@@ -19,19 +22,119 @@ pub enum UserEvent {
         language: String,
         search_term: Option<String>,
         path: PathBuf,
+        /// The lines shown and the lines in the file, if the preview is truncated.
+        truncation: Option<(usize, usize)>,
     },
     /// The generated, concatenated content for the main preview.
     ShowGeneratedContent { content: String, token_count: usize },
+    /// Starts streaming large generated content; the UI discards any partial stream.
+    GenerationStart,
+    /// The next piece of streamed generated content.
+    GenerationChunk { data: String },
+    /// Completes a stream; the UI shows the concatenated chunks.
+    GenerationEnd { token_count: usize },
+    /// The selection exceeds `AppConfig::warn_over_bytes`; generation waits for the
+    /// user to confirm it with `confirmGeneration`.
+    ConfirmLargeGeneration { bytes: u64, files: usize },
+    /// A token count estimate for the current selection, computed without generating output.
+    TokenEstimate { tokens: usize, files: usize },
     /// An error message to be displayed to the user.
     ShowError(String),
+    /// A transient status message. Unlike the status in `UiState`, it needs no full
+    /// state update and is replaced by the next one.
+    Status(String),
     /// The result of a file save operation.
     SaveComplete(bool, String),
     /// The result of a configuration export.
     ConfigExported(bool),
+    /// The problems `validateConfig` found in a config file, errors first.
+    ConfigValidation {
+        path: PathBuf,
+        issues: Vec<ConfigIssue>,
+    },
+    /// The result of a tree export: the saved path on success, otherwise the error.
+    TreeExported(bool, String),
+    /// The result of a selection export: the saved path on success, otherwise the error.
+    SelectionExported(bool, String),
+    /// The files that differ between the last two generations.
+    GenerationDiff(GenerationDiff),
+    /// The files a generation of the current selection would include.
+    GenerationPlan(GenerationPlan),
+    /// The most recent generated outputs, newest first.
+    GenerationHistory(Vec<GenerationRecord>),
+    /// Files selected before a generation because the selection refers to them, see
+    /// `AppConfig::auto_include_imports`. Paths relative to the root.
+    FilesAutoIncluded(Vec<String>),
+    /// Files, sizes and tokens per extension over all scanned files.
+    TreeStats(TreeStats),
+    /// The paths of this many selected files were copied to the clipboard.
+    PathsCopied(usize),
+    /// A generated output of this many tokens was copied to the clipboard.
+    OutputCopied { token_count: usize },
+    /// The names of all saved configuration profiles.
+    ProfilesListed(Vec<String>),
+    /// The output filename that was stored, and whether it differs from the requested one.
+    OutputFilenameSet { filename: String, corrected: bool },
+    /// The directory log files are written to, if the platform provides one.
+    LogPath(Option<PathBuf>),
+    /// Scrolls the preview to this line (1-based) of the previewed file.
+    ScrollToLine(usize),
+    /// Sets the zoom factor of the WebView, `1.0` being 100 %.
+    SetZoom(f64),
     /// A progress update during a directory scan.
     ScanProgress(ScanProgress),
     /// Indicates that a file is being dragged over the window.
     DragStateChanged(bool),
+    /// Matching snippets of a finished content search. An empty `query` clears them.
+    ContentSearchResults {
+        query: String,
+        files: Vec<ContentSearchFile>,
+    },
+    /// The files a single ignore pattern would hide, for previewing it before it is added.
+    IgnorePreview {
+        pattern: String,
+        matches: Vec<PathBuf>,
+    },
+    /// Why a path is missing from the file tree, one sentence per reason. Empty if
+    /// the path is shown.
+    HiddenExplanation { path: PathBuf, reasons: Vec<String> },
+    /// Files that were skipped during a full scan because they exceed the size limit.
+    LargeFilesSkipped {
+        count: usize,
+        names: Vec<String>,
+        max_file_size_mb: u64,
+    },
+    /// Broken symlinks that were skipped during a full scan, relative to the root.
+    /// Only sent while `AppConfig::report_broken_symlinks` is set.
+    BrokenSymlinks { paths: Vec<String> },
+}
+
+/// Identifies a file-tree entry in an IPC payload.
+///
+/// The frontend sends the `TreeNode::id` it was given, see `PathIds`. This resolves
+/// to the exact `PathBuf`, even for names that
+/// are not valid UTF-8 and cannot round-trip through a JSON string. Plain path strings
+/// are still accepted for paths that are not part of the file list.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum PathRef {
+    Id(usize),
+    Path(PathBuf),
+}
+
+/// The payload of `setUiPreferences`. Missing fields keep their current value.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct UiPreferences {
+    pub ui_zoom: Option<f64>,
+    pub theme: Option<UiTheme>,
+}
+
+/// The payload of `loadMoreChildren`: the directory, or the root, and the number of
+/// its entries already shown.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct LoadMoreChildren {
+    pub path: PathBuf,
+    pub offset: usize,
 }
 
 /// A message received from the WebView via the IPC channel.
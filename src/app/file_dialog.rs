@@ -1,6 +1,7 @@
 //! An abstraction layer for native file dialogs to enable testing.
 
 use crate::config::AppConfig;
+use crate::core::OutputFormat;
 use std::path::PathBuf;
 
 /// Defines a common interface for file and folder selection dialogs.
@@ -13,12 +14,21 @@ pub trait DialogService: Send + Sync {
     /// Opens a dialog to select a single file for config import.
     fn pick_config_to_import(&self) -> Option<PathBuf>;
 
+    /// Opens a dialog to select a newline-delimited list of files to select.
+    fn pick_selection_manifest(&self) -> Option<PathBuf>;
+
+    /// Opens a dialog to select a save location for an exported selection.
+    fn export_selection_path(&self) -> Option<PathBuf>;
+
     /// Opens a dialog to select a save location for a config export.
     fn export_config_path(&self) -> Option<PathBuf>;
 
     /// Opens a dialog to select a save location for the final output file.
     /// It uses the provided config to suggest a default name and directory.
     fn save_output_file_path(&self, config: &AppConfig) -> Option<PathBuf>;
+
+    /// Opens a dialog to select a save location for an exported directory tree.
+    fn export_tree_path(&self) -> Option<PathBuf>;
 }
 
 /// The production implementation that uses the `rfd` crate to show native OS dialogs.
@@ -35,6 +45,18 @@ impl DialogService for NativeDialogService {
             .pick_file()
     }
 
+    fn pick_selection_manifest(&self) -> Option<PathBuf> {
+        // Lists from `grep -l` or scripts often have no extension, so no filter is set.
+        rfd::FileDialog::new().pick_file()
+    }
+
+    fn export_selection_path(&self) -> Option<PathBuf> {
+        rfd::FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .set_file_name("selection.json")
+            .save_file()
+    }
+
     fn export_config_path(&self) -> Option<PathBuf> {
         rfd::FileDialog::new()
             .add_filter("JSON", &["json"])
@@ -43,12 +65,24 @@ impl DialogService for NativeDialogService {
     }
 
     fn save_output_file_path(&self, config: &AppConfig) -> Option<PathBuf> {
+        let filter = match config.output_format {
+            OutputFormat::PlainText => "Text File",
+            OutputFormat::Html => "HTML File",
+            OutputFormat::Xml => "XML File",
+        };
         let mut dialog = rfd::FileDialog::new()
-            .add_filter("Text File", &["txt"])
+            .add_filter(filter, &[config.output_format.extension()])
             .set_file_name(&config.output_filename);
         if let Some(dir) = &config.output_directory {
             dialog = dialog.set_directory(dir);
         }
         dialog.save_file()
     }
+
+    fn export_tree_path(&self) -> Option<PathBuf> {
+        rfd::FileDialog::new()
+            .add_filter("Text File", &["txt"])
+            .set_file_name("tree.txt")
+            .save_file()
+    }
 }
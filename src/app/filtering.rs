@@ -6,13 +6,25 @@
 
 use crate::app::state::AppState;
 use crate::config::AppConfig;
-use crate::core::{FileItem, SearchEngine, SearchFilter};
+use crate::core::ignore_rules::ignore_file_rule;
+use crate::core::{
+    ExportIgnore, FileItem, FilenameMatchMode, IgnoreRules, SearchEngine, SearchFilter,
+};
+use crate::utils::thread_pool;
 use rayon::prelude::*;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// Applies all current filters to the full file list to generate the visible list.
 pub fn apply_filters(state: &mut AppState) {
+    state.filtered_file_list = filtered_files(state, true);
+}
+
+/// The entries of the full file list that pass the current filters. Without
+/// `with_content_search`, the content search results are not applied.
+pub fn filtered_files(state: &AppState, with_content_search: bool) -> Vec<FileItem> {
     let all_dirs_in_full_list: HashSet<PathBuf> = state
         .full_file_list
         .iter()
@@ -30,23 +42,254 @@ pub fn apply_filters(state: &mut AppState) {
         dirs_to_preserve.extend(state.expanded_dirs.clone());
     }
 
-    state.filtered_file_list = apply_filters_on_data(
-        &state.full_file_list,
-        &PathBuf::from(&state.current_path),
-        &state.config,
-        &state.search_query,
-        &state.extension_filter,
-        &state.content_search_query,
-        &state.content_search_results,
-        &dirs_to_preserve,
-        state.is_fully_scanned,
-    );
+    let root_path = PathBuf::from(&state.current_path);
+    let content_search_query = if with_content_search {
+        state.content_search_query.as_str()
+    } else {
+        ""
+    };
+    thread_pool::install(state.config.max_threads, || {
+        apply_filters_on_data(
+            &state.full_file_list,
+            &root_path,
+            &state.config,
+            &state.search_query,
+            state.filename_match_mode,
+            &state.extension_filter,
+            content_search_query,
+            &state.content_search_results,
+            state.git_tracked_paths.as_ref(),
+            state.export_ignore.as_deref(),
+            &dirs_to_preserve,
+            state.is_fully_scanned,
+        )
+    })
+}
+
+/// Why an entry is missing from the file tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HiddenReason {
+    /// The path is not below the current directory.
+    OutsideRoot,
+    /// Nothing exists at the path.
+    NotFound,
+    /// A pattern from `AppConfig::ignore_patterns` excludes the path or a parent directory.
+    IgnorePattern(String),
+    /// A pattern `exclude_lockfiles` or `exclude_vendored` adds excludes the path or
+    /// a parent directory.
+    OverlayPattern(String),
+    /// A rule in a `.gitignore`, `.ignore` or `.git/info/exclude` file excludes the
+    /// path or a parent directory.
+    IgnoreFile { file: PathBuf, rule: String },
+    /// The path or a parent directory starts with a dot and `include_hidden` is off.
+    DotEntry,
+    /// The scan skipped the file for exceeding `AppConfig::max_file_size_mb`.
+    TooLarge { size: u64, limit_mb: u64 },
+    /// The scan has not reached the path, e.g. because its directory was never expanded.
+    NotScanned,
+    /// The file lies outside the configured size range.
+    OutsideSizeRange,
+    /// The file is empty and `exclude_empty_files` is on.
+    EmptyFile,
+    /// Neither the file nor anything below the directory was modified within the
+    /// last `modified_within_days` days.
+    NotModifiedRecently { days: u64 },
+    /// `only_git_tracked` is on and git does not track the path.
+    NotTrackedByGit,
+    /// A `.gitattributes` file marks the path or a parent directory `export-ignore`.
+    ExportIgnored,
+    /// Neither the file nor anything below the directory matches the content search.
+    NoContentMatch,
+    /// Neither the file nor anything below the directory matches the name or
+    /// extension filter.
+    NoNameMatch,
+    /// The directory contains no files and `remove_empty_directories` is on.
+    EmptyDirectory,
+}
+
+impl fmt::Display for HiddenReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutsideRoot => write!(f, "it is outside the current directory"),
+            Self::NotFound => write!(f, "it does not exist"),
+            Self::IgnorePattern(pattern) => write!(f, "the ignore pattern `{pattern}` excludes it"),
+            Self::OverlayPattern(pattern) => write!(
+                f,
+                "`{pattern}` from the lockfile or vendored exclusions excludes it"
+            ),
+            Self::IgnoreFile { file, rule } => {
+                write!(f, "the rule `{rule}` in {} excludes it", file.display())
+            }
+            Self::DotEntry => write!(f, "hidden (dot) files are not included"),
+            Self::TooLarge { size, limit_mb } => write!(
+                f,
+                "it is {size} bytes, above the scan limit of {limit_mb} MB"
+            ),
+            Self::NotScanned => write!(f, "it has not been scanned yet"),
+            Self::OutsideSizeRange => write!(f, "its size is outside the size filter"),
+            Self::EmptyFile => write!(f, "empty files are excluded"),
+            Self::NotModifiedRecently { days } => {
+                write!(f, "it was not modified within the last {days} days")
+            }
+            Self::NotTrackedByGit => write!(f, "git does not track it"),
+            Self::ExportIgnored => write!(f, "`.gitattributes` marks it export-ignore"),
+            Self::NoContentMatch => write!(f, "it does not match the content search"),
+            Self::NoNameMatch => write!(f, "it does not match the name or extension filter"),
+            Self::EmptyDirectory => write!(f, "empty directories are removed"),
+        }
+    }
+}
+
+/// Explains why `path` is missing from the file tree, checking the scan as well as
+/// the current filters. Returns an empty list if the path is shown.
+pub fn explain_hidden(state: &AppState, path: &Path) -> Vec<HiddenReason> {
+    let root = Path::new(&state.current_path);
+    if state.current_path.is_empty() || !path.starts_with(root) {
+        return vec![HiddenReason::OutsideRoot];
+    }
+    if path == root
+        || state
+            .filtered_file_list
+            .iter()
+            .any(|item| item.path == path)
+    {
+        return Vec::new();
+    }
+    match state.full_file_list.iter().find(|item| item.path == path) {
+        Some(item) => filter_reasons(state, item),
+        None => scan_reasons(state, root, path),
+    }
+}
+
+/// Why the scan left `path` out of the full file list.
+fn scan_reasons(state: &AppState, root: &Path, path: &Path) -> Vec<HiddenReason> {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return vec![HiddenReason::NotFound];
+    };
+    let is_dir = metadata.is_dir();
+    let config = &state.config;
+    let mut reasons = Vec::new();
+
+    if let Some(pattern) =
+        IgnoreRules::new(root, &config.ignore_patterns).excluding_pattern(path, is_dir)
+    {
+        reasons.push(HiddenReason::IgnorePattern(pattern));
+    }
+    if let Some((file, rule)) = ignore_file_rule(root, path, is_dir) {
+        reasons.push(HiddenReason::IgnoreFile { file, rule });
+    }
+    let is_dot_entry = path.strip_prefix(root).is_ok_and(|relative| {
+        relative
+            .components()
+            .any(|component| component.as_os_str().to_string_lossy().starts_with('.'))
+    });
+    if !config.include_hidden && is_dot_entry {
+        reasons.push(HiddenReason::DotEntry);
+    }
+    if !is_dir && metadata.len() > config.max_file_size_mb.saturating_mul(1024 * 1024) {
+        reasons.push(HiddenReason::TooLarge {
+            size: metadata.len(),
+            limit_mb: config.max_file_size_mb,
+        });
+    }
+
+    if reasons.is_empty() {
+        reasons.push(HiddenReason::NotScanned);
+    }
+    reasons
+}
+
+/// Why the current filters hide `item`, which the scan found.
+fn filter_reasons(state: &AppState, item: &FileItem) -> Vec<HiddenReason> {
+    let config = &state.config;
+    let mut reasons = Vec::new();
+    // The item itself for a file, everything below it for a directory.
+    let files_below = || {
+        state
+            .full_file_list
+            .iter()
+            .filter(|other| !other.is_directory && other.path.starts_with(&item.path))
+    };
+
+    if !item.is_directory {
+        if !SearchEngine::matches_size_range(
+            item.size,
+            config.min_file_size_bytes,
+            config.max_file_size_bytes,
+        ) {
+            reasons.push(HiddenReason::OutsideSizeRange);
+        }
+        if config.exclude_empty_files && item.size == 0 {
+            reasons.push(HiddenReason::EmptyFile);
+        }
+    }
+    if let (Some(days), Some(since)) = (config.modified_within_days, config.modified_since()) {
+        if !files_below().any(|file| file.mtime.is_some_and(|mtime| mtime >= since)) {
+            reasons.push(HiddenReason::NotModifiedRecently { days });
+        }
+    }
+    let overlay_patterns = config.overlay_ignore_patterns();
+    if let Some(pattern) = IgnoreRules::new(Path::new(&state.current_path), &overlay_patterns)
+        .excluding_pattern(&item.path, item.is_directory)
+    {
+        reasons.push(HiddenReason::OverlayPattern(pattern));
+    }
+    if config.only_git_tracked
+        && state
+            .git_tracked_paths
+            .as_ref()
+            .is_some_and(|tracked| !tracked.contains(&item.path))
+    {
+        reasons.push(HiddenReason::NotTrackedByGit);
+    }
+    if config.export_ignore_enabled()
+        && state
+            .export_ignore
+            .as_ref()
+            .is_some_and(|rules| rules.is_excluded(&item.path, item.is_directory))
+    {
+        reasons.push(HiddenReason::ExportIgnored);
+    }
+    if !state.content_search_query.trim().is_empty()
+        && !files_below().any(|file| state.content_search_results.contains_key(&file.path))
+    {
+        reasons.push(HiddenReason::NoContentMatch);
+    }
+    if !state.search_query.trim().is_empty() || !state.extension_filter.trim().is_empty() {
+        let filter = SearchFilter {
+            query: state.search_query.clone(),
+            match_mode: state.filename_match_mode,
+            root: PathBuf::from(&state.current_path),
+            extension: state.extension_filter.clone(),
+            case_sensitive: config.case_sensitive_search,
+            min_size: None,
+            max_size: None,
+            exclude_empty: false,
+            modified_since: None,
+            tracked_paths: None,
+        };
+        let glob = SearchEngine::compile_glob(&filter);
+        if !files_below().any(|file| SearchEngine::matches_filter_with_glob(file, &filter, &glob)) {
+            reasons.push(HiddenReason::NoNameMatch);
+        }
+    }
+    if item.is_directory
+        && config.remove_empty_directories
+        && state.is_fully_scanned
+        && files_below().next().is_none()
+    {
+        reasons.push(HiddenReason::EmptyDirectory);
+    }
+    reasons
 }
 
 /// Collects all parent directories for a given set of file paths.
-fn get_required_ancestors(file_paths: &HashSet<PathBuf>, root_path: &Path) -> HashSet<PathBuf> {
+fn get_required_ancestors<'a>(
+    file_paths: impl IntoParallelIterator<Item = &'a PathBuf>,
+    root_path: &Path,
+) -> HashSet<PathBuf> {
     file_paths
-        .par_iter()
+        .into_par_iter()
         .flat_map(|item_path| {
             let mut parents = Vec::new();
             let mut current = item_path.parent();
@@ -70,9 +313,12 @@ fn apply_filters_on_data(
     root_path: &Path,
     config: &AppConfig,
     search_query: &str,
+    filename_match_mode: FilenameMatchMode,
     extension_filter: &str,
     content_search_query: &str,
-    content_search_results: &HashSet<PathBuf>,
+    content_search_results: &HashMap<PathBuf, usize>,
+    tracked_paths: Option<&Arc<HashSet<PathBuf>>>,
+    export_ignore: Option<&ExportIgnore>,
     dirs_to_preserve: &HashSet<PathBuf>,
     is_fully_scanned: bool,
 ) -> Vec<FileItem> {
@@ -88,32 +334,104 @@ fn apply_filters_on_data(
         full_file_list.to_vec()
     };
 
-    // Step 2: Apply content search if active.
+    // Step 2: Drop files outside the configured size range, empty files with
+    // `exclude_empty_files`, with `only_git_tracked` entries git does not track,
+    // entries marked `export-ignore` and the lockfiles and vendored directories of
+    // `exclude_lockfiles`/`exclude_vendored`. Directories are unaffected by the size range.
+    let tracked_paths = tracked_paths.filter(|_| config.only_git_tracked);
+    if config.min_file_size_bytes.is_some()
+        || config.max_file_size_bytes.is_some()
+        || config.exclude_empty_files
+        || tracked_paths.is_some()
+    {
+        let size_filter = SearchFilter {
+            query: String::new(),
+            match_mode: FilenameMatchMode::Substring,
+            root: PathBuf::new(),
+            extension: String::new(),
+            case_sensitive: config.case_sensitive_search,
+            min_size: config.min_file_size_bytes,
+            max_size: config.max_file_size_bytes,
+            exclude_empty: config.exclude_empty_files,
+            modified_since: None,
+            tracked_paths: tracked_paths.cloned(),
+        };
+        working_list = SearchEngine::filter_files(&working_list, &size_filter);
+    }
+    if let Some(export_ignore) = export_ignore.filter(|_| config.export_ignore_enabled()) {
+        working_list.retain(|item| !export_ignore.is_excluded(&item.path, item.is_directory));
+    }
+    let overlay_patterns = config.overlay_ignore_patterns();
+    if !overlay_patterns.is_empty() {
+        let overlay = IgnoreRules::new(root_path, &overlay_patterns);
+        working_list
+            .retain(|item| !overlay.is_ignored_or_any_parents(&item.path, item.is_directory));
+    }
+
+    // Step 3: With `modified_within_days`, keep only recently modified files and the
+    // directories containing them.
+    if let Some(since) = config.modified_since() {
+        let filter = SearchFilter {
+            query: String::new(),
+            match_mode: FilenameMatchMode::Substring,
+            root: PathBuf::new(),
+            extension: String::new(),
+            case_sensitive: config.case_sensitive_search,
+            min_size: None,
+            max_size: None,
+            exclude_empty: false,
+            modified_since: Some(since),
+            tracked_paths: None,
+        };
+        let recent_files: HashSet<_> = working_list
+            .iter()
+            .filter(|item| !item.is_directory && SearchEngine::matches_filter(item, &filter))
+            .map(|item| item.path.clone())
+            .collect();
+        let required_dirs = get_required_ancestors(&recent_files, root_path);
+        working_list
+            .retain(|item| recent_files.contains(&item.path) || required_dirs.contains(&item.path));
+    }
+
+    // Step 4: Apply content search if active.
     let has_content_filter = !content_search_query.trim().is_empty();
     if has_content_filter {
         if content_search_results.is_empty() {
             return Vec::new();
         }
-        let required_dirs = get_required_ancestors(content_search_results, root_path);
+        let required_dirs = get_required_ancestors(
+            content_search_results.par_iter().map(|(path, _)| path),
+            root_path,
+        );
         working_list.retain(|item| {
-            content_search_results.contains(&item.path) || required_dirs.contains(&item.path)
+            content_search_results.contains_key(&item.path) || required_dirs.contains(&item.path)
         });
     }
 
-    // Step 3: Apply filename/extension search if active.
+    // Step 5: Apply filename/extension search if active.
     let has_filename_filter = !search_query.trim().is_empty();
     let has_extension_filter = !extension_filter.trim().is_empty();
 
     if has_filename_filter || has_extension_filter {
         let filter = SearchFilter {
             query: search_query.to_string(),
+            match_mode: filename_match_mode,
+            root: root_path.to_path_buf(),
             extension: extension_filter.to_string(),
             case_sensitive: config.case_sensitive_search,
+            min_size: config.min_file_size_bytes,
+            max_size: config.max_file_size_bytes,
+            exclude_empty: config.exclude_empty_files,
+            modified_since: None,
+            tracked_paths: None,
         };
 
+        let glob = SearchEngine::compile_glob(&filter);
         let matching_files: HashSet<_> = working_list
             .iter()
-            .filter(|item| !item.is_directory && SearchEngine::matches_filter(item, &filter))
+            .filter(|item| {
+                !item.is_directory && SearchEngine::matches_filter_with_glob(item, &filter, &glob)
+            })
             .map(|item| item.path.clone())
             .collect();
 
@@ -134,10 +452,10 @@ fn apply_filters_on_data(
 mod tests {
     use super::*;
     use crate::app::state::AppState;
-    use crate::app::view_model::generate_ui_state;
+    use crate::app::view_model::{generate_ui_state, get_selected_files_in_tree_order};
     use crate::config::AppConfig;
     use crate::core::FileItem;
-    use std::collections::HashSet;
+    use std::collections::{HashMap, HashSet};
     use std::path::PathBuf;
 
     /// Creates a test `FileItem` with the specified path and directory flag.
@@ -149,6 +467,9 @@ mod tests {
             size: if is_dir { 0 } else { 100 },
             depth: path_str.matches('/').count(),
             parent: PathBuf::from(path_str).parent().map(|p| p.to_path_buf()),
+            mtime: None,
+            likely_generated: false,
+            git_status: None,
         }
     }
 
@@ -215,12 +536,165 @@ mod tests {
             create_test_file_item("/project/README.md", false),
         ];
         state.content_search_query = "nonexistent".to_string();
-        state.content_search_results = HashSet::new(); // No matches
+        state.content_search_results = HashMap::new(); // No matches
         apply_filters(&mut state);
 
         assert_eq!(state.filtered_file_list.len(), 0);
     }
 
+    #[test]
+    fn test_apply_filters_keeps_recent_files_and_their_directories() {
+        let now = std::time::SystemTime::now();
+        let aged = |path: &str, days: u64| FileItem {
+            mtime: Some(now - std::time::Duration::from_secs(days * 24 * 60 * 60)),
+            ..create_test_file_item(path, false)
+        };
+        let mut state = AppState::default();
+        state.config = create_test_config();
+        state.config.modified_within_days = Some(7);
+        state.current_path = "/project".to_string();
+        state.full_file_list = vec![
+            create_test_file_item("/project/src", true),
+            aged("/project/src/main.rs", 1),
+            aged("/project/src/old.rs", 30),
+            create_test_file_item("/project/docs", true),
+            aged("/project/docs/guide.md", 90),
+        ];
+
+        apply_filters(&mut state);
+        let visible: Vec<_> = state
+            .filtered_file_list
+            .iter()
+            .map(|item| item.path.to_str().unwrap())
+            .collect();
+        assert_eq!(visible, vec!["/project/src", "/project/src/main.rs"]);
+        assert_eq!(
+            explain_hidden(&state, Path::new("/project/docs")),
+            vec![HiddenReason::NotModifiedRecently { days: 7 }]
+        );
+    }
+
+    #[test]
+    fn test_apply_filters_excludes_empty_files_only_when_enabled() {
+        let mut state = AppState::default();
+        state.config = create_test_config();
+        state.current_path = "/project".to_string();
+        let mut empty_file = create_test_file_item("/project/src/empty.rs", false);
+        empty_file.size = 0;
+        state.full_file_list = vec![
+            create_test_file_item("/project/src", true),
+            create_test_file_item("/project/src/main.rs", false),
+            empty_file.clone(),
+        ];
+        state.selected_files = HashSet::from([
+            PathBuf::from("/project/src/main.rs"),
+            empty_file.path.clone(),
+        ]);
+        let visible = |state: &AppState| -> HashSet<PathBuf> {
+            state
+                .filtered_file_list
+                .iter()
+                .map(|item| item.path.clone())
+                .collect()
+        };
+
+        apply_filters(&mut state);
+        assert!(visible(&state).contains(&empty_file.path));
+        assert_eq!(get_selected_files_in_tree_order(&state).len(), 2);
+
+        state.config.exclude_empty_files = true;
+        apply_filters(&mut state);
+        assert!(!visible(&state).contains(&empty_file.path));
+        assert!(visible(&state).contains(&PathBuf::from("/project/src/main.rs")));
+        assert!(!state.config.is_bulk_selectable(&empty_file));
+        assert_eq!(
+            get_selected_files_in_tree_order(&state),
+            vec![PathBuf::from("/project/src/main.rs")]
+        );
+    }
+
+    #[test]
+    fn test_apply_filters_hides_export_ignored_paths_when_enabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join(".gitattributes"), "tests export-ignore\n").unwrap();
+        let path = |relative: &str| root.join(relative).to_string_lossy().to_string();
+
+        let mut state = AppState::default();
+        state.config = create_test_config();
+        state.current_path = root.to_string_lossy().to_string();
+        state.full_file_list = vec![
+            create_test_file_item(&path("src"), true),
+            create_test_file_item(&path("src/main.rs"), false),
+            create_test_file_item(&path("tests"), true),
+            create_test_file_item(&path("tests/unit.rs"), false),
+        ];
+        state.export_ignore = Some(Arc::new(ExportIgnore::load(root)));
+
+        apply_filters(&mut state);
+        assert_eq!(state.filtered_file_list.len(), 4);
+
+        state.config.respect_export_ignore = true;
+        apply_filters(&mut state);
+        let visible: HashSet<_> = state
+            .filtered_file_list
+            .iter()
+            .map(|item| item.path.clone())
+            .collect();
+        assert_eq!(
+            visible,
+            HashSet::from([root.join("src"), root.join("src/main.rs")])
+        );
+    }
+
+    #[test]
+    fn test_apply_filters_hides_lockfiles_and_vendored_dirs_when_enabled() {
+        let mut state = AppState::default();
+        state.config = create_test_config();
+        state.current_path = "/project".to_string();
+        state.full_file_list = vec![
+            create_test_file_item("/project/Cargo.lock", false),
+            create_test_file_item("/project/yarn.lock", false),
+            create_test_file_item("/project/src", true),
+            create_test_file_item("/project/src/main.rs", false),
+            create_test_file_item("/project/vendor", true),
+            create_test_file_item("/project/vendor/dep.rs", false),
+        ];
+        let visible = |state: &AppState| -> HashSet<PathBuf> {
+            state
+                .filtered_file_list
+                .iter()
+                .map(|item| item.path.clone())
+                .collect()
+        };
+
+        apply_filters(&mut state);
+        assert_eq!(state.filtered_file_list.len(), 6);
+
+        state.config.exclude_lockfiles = true;
+        apply_filters(&mut state);
+        assert_eq!(
+            visible(&state),
+            HashSet::from([
+                PathBuf::from("/project/src"),
+                PathBuf::from("/project/src/main.rs"),
+                PathBuf::from("/project/vendor"),
+                PathBuf::from("/project/vendor/dep.rs"),
+            ])
+        );
+
+        state.config.exclude_lockfiles = false;
+        state.config.exclude_vendored = true;
+        apply_filters(&mut state);
+        assert!(!visible(&state).contains(&PathBuf::from("/project/vendor/dep.rs")));
+        assert!(visible(&state).contains(&PathBuf::from("/project/Cargo.lock")));
+        assert!(state.config.ignore_patterns.is_empty());
+        assert_eq!(
+            explain_hidden(&state, Path::new("/project/vendor/dep.rs")),
+            vec![HiddenReason::OverlayPattern("vendor/".to_string())]
+        );
+    }
+
     #[test]
     fn test_expanded_empty_dir_is_not_preserved_when_remove_is_on() {
         let mut state = AppState::default();
@@ -257,4 +731,70 @@ mod tests {
             "Regular file should still be visible"
         );
     }
+
+    #[test]
+    fn test_explain_hidden_reports_scan_and_filter_reasons() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join(".cache")).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.tmp\n").unwrap();
+        for file in [
+            "src/main.rs",
+            "src/lib.rs",
+            "notes.tmp",
+            "debug.log",
+            ".cache/data",
+        ] {
+            std::fs::write(root.join(file), "content").unwrap();
+        }
+
+        let mut state = AppState::default();
+        state.config = create_test_config();
+        state.config.ignore_patterns = HashSet::from(["*.log".to_string()]);
+        state.config.include_hidden = false;
+        state.current_path = root.to_string_lossy().to_string();
+        let item = |path: &str, is_dir| FileItem {
+            path: root.join(path),
+            ..create_test_file_item(path, is_dir)
+        };
+        state.full_file_list = vec![
+            item("src", true),
+            item("src/main.rs", false),
+            item("src/lib.rs", false),
+        ];
+        state.loaded_dirs.insert(root.join("src"));
+        state.is_fully_scanned = true;
+        state.search_query = "main".to_string();
+        apply_filters(&mut state);
+
+        assert!(explain_hidden(&state, &root.join("src/main.rs")).is_empty());
+        assert_eq!(
+            explain_hidden(&state, &root.join("src/lib.rs")),
+            vec![HiddenReason::NoNameMatch]
+        );
+        assert_eq!(
+            explain_hidden(&state, &root.join("debug.log")),
+            vec![HiddenReason::IgnorePattern("*.log".to_string())]
+        );
+        assert_eq!(
+            explain_hidden(&state, &root.join("notes.tmp")),
+            vec![HiddenReason::IgnoreFile {
+                file: root.join(".gitignore"),
+                rule: "*.tmp".to_string(),
+            }]
+        );
+        assert_eq!(
+            explain_hidden(&state, &root.join(".cache/data")),
+            vec![HiddenReason::DotEntry]
+        );
+        assert_eq!(
+            explain_hidden(&state, &root.join("missing.rs")),
+            vec![HiddenReason::NotFound]
+        );
+        assert_eq!(
+            explain_hidden(&state, Path::new("/elsewhere/file.rs")),
+            vec![HiddenReason::OutsideRoot]
+        );
+    }
 }
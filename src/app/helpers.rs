@@ -1,11 +1,27 @@
 //! Contains helper functions to reduce boilerplate code in other `app` modules.
 
-use std::sync::{Arc, Mutex};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
 
 use super::events::UserEvent;
 use super::proxy::EventProxy;
 use super::state::AppState;
 use super::view_model::generate_ui_state;
+use crate::config;
+
+/// Locks the `AppState`, recovering it if a task panicked while holding the lock.
+///
+/// Without this, one panicking task would poison the mutex and every later command
+/// would panic too, taking the UI down with it. The state may have been left
+/// half-updated by the panic, which beats losing the app.
+pub fn lock_state(state: &Mutex<AppState>) -> MutexGuard<'_, AppState> {
+    state.lock().unwrap_or_else(|poisoned| {
+        tracing::warn!("A task panicked while holding the app state lock; recovering it.");
+        // Warn only once per panic, not on every later lock.
+        state.clear_poison();
+        poisoned.into_inner()
+    })
+}
 
 /// A helper function that locks the `AppState`, performs a mutation,
 /// and then automatically sends a `StateUpdate` event to the UI.
@@ -18,9 +34,7 @@ pub fn with_state_and_notify<F, P: EventProxy>(
 ) where
     F: FnOnce(&mut AppState),
 {
-    let mut state_guard = state
-        .lock()
-        .expect("Mutex was poisoned. This should not happen.");
+    let mut state_guard = lock_state(state);
 
     // Execute the specific mutation logic
     update_fn(&mut state_guard);
@@ -31,3 +45,18 @@ pub fn with_state_and_notify<F, P: EventProxy>(
 
     proxy.send_event(event);
 }
+
+/// Saves the selection and expanded directories of the current root if
+/// `AppConfig::remember_selection` is set, so the next scan of it can restore them.
+/// Call it before the directory state is reset.
+pub fn remember_session(state: &AppState) {
+    if !state.config.remember_selection {
+        return;
+    }
+    if let Some(session) = state.current_session() {
+        let root = Path::new(&state.current_path);
+        if let Err(e) = config::settings::save_session(root, &session, None) {
+            tracing::warn!("Failed to save the session of {:?}: {}", root, e);
+        }
+    }
+}
@@ -3,6 +3,7 @@
 //! It manages the application state, handles events from the WebView (IPC messages),
 //! and sends updates back to the UI. It acts as the "controller" in an MVC-like pattern.
 
+pub mod clipboard;
 pub mod commands;
 pub mod events;
 pub mod file_dialog;
@@ -13,6 +14,7 @@ pub mod state;
 pub mod tasks;
 pub mod view_model;
 
+use crate::app::clipboard::ClipboardService;
 use crate::app::file_dialog::DialogService;
 use std::sync::{Arc, Mutex};
 use wry::WebView;
@@ -21,6 +23,8 @@ use crate::app::proxy::EventProxy;
 use events::{IpcMessage, UserEvent};
 use state::AppState;
 
+pub use helpers::{lock_state, remember_session};
+
 /// The main handler for IPC messages from the WebView.
 ///
 /// It parses the message and delegates to the appropriate command handler function
@@ -35,6 +39,7 @@ use state::AppState;
 pub fn handle_ipc_message<P: EventProxy>(
     message: String,
     dialog_service: Arc<impl DialogService + 'static>,
+    clipboard_service: Arc<impl ClipboardService + 'static>,
     proxy: P,
     state: Arc<Mutex<AppState>>,
 ) {
@@ -42,13 +47,20 @@ pub fn handle_ipc_message<P: EventProxy>(
         // Clone resources to be moved into the async task.
         let proxy = proxy.clone();
         let dialog = dialog_service.clone();
+        let clipboard = clipboard_service.clone();
 
         tokio::spawn(async move {
             match msg.command.as_str() {
                 // --- Asynchronous Commands (must be awaited) ---
                 "updateConfig" => commands::update_config(msg.payload, proxy, state).await,
                 "updateFilters" => commands::update_filters(msg.payload, proxy, state).await,
+                "loadMoreSearchResults" => commands::load_more_search_results(proxy, state).await,
+                "loadMoreChildren" => commands::load_more_children(msg.payload, proxy, state),
                 "addIgnorePath" => commands::add_ignore_path(msg.payload, proxy, state).await,
+                "addIgnorePaths" => commands::add_ignore_paths(msg.payload, proxy, state).await,
+                "applyIgnorePreset" => {
+                    commands::apply_ignore_preset(msg.payload, proxy, state).await
+                }
                 "importConfig" => commands::import_config(dialog.as_ref(), proxy, state).await,
 
                 // --- Synchronous Commands & Task Launchers (do not await) ---
@@ -56,28 +68,80 @@ pub fn handle_ipc_message<P: EventProxy>(
                 "rescanDirectory" => commands::rescan_directory(proxy, state),
                 "loadDirectoryLevel" => commands::load_directory_level(msg.payload, proxy, state),
                 "generatePreview" => commands::generate_preview(proxy, state),
+                "generateToClipboard" => commands::generate_to_clipboard(clipboard, proxy, state),
+                "confirmGeneration" => commands::confirm_generation(proxy, state),
+                "estimateTokens" => commands::estimate_tokens(proxy, state),
+                "cancelEstimate" => commands::cancel_estimate(proxy, state),
 
                 "clearDirectory" => commands::clear_directory(proxy, state),
                 "cancelScan" => commands::cancel_scan(proxy, state),
+                "cancelAll" => commands::cancel_all(proxy, state),
                 "initialize" => commands::initialize(proxy, state),
                 "loadFilePreview" => commands::load_file_preview(msg.payload, proxy, state),
                 "toggleSelection" => commands::toggle_selection(msg.payload, proxy, state),
                 "toggleDirectorySelection" => {
                     commands::toggle_directory_selection(msg.payload, proxy, state)
                 }
+                "pinFile" => commands::pin_file(msg.payload, proxy, state),
+                "unpinFile" => commands::unpin_file(msg.payload, proxy, state),
                 "toggleExpansion" => commands::toggle_expansion(msg.payload, proxy, state),
+                "revealPath" => commands::reveal_path(msg.payload, proxy, state),
+                "rescanSubtree" => commands::rescan_subtree(msg.payload, proxy, state),
+                "collapseToPreview" => commands::collapse_to_preview(proxy, state),
+                "setUiPreferences" => commands::set_ui_preferences(msg.payload, proxy, state),
+                "openInEditor" => commands::open_in_editor(msg.payload, proxy, state),
+                "selectCurrent" => commands::select_current(msg.payload, proxy, state),
+                "expandCurrent" => commands::expand_current(msg.payload, proxy, state),
+                "collapseCurrent" => commands::collapse_current(msg.payload, proxy, state),
                 "expandCollapseAll" => commands::expand_collapse_all(msg.payload, proxy, state),
+                "includeTreeToggled" => commands::include_tree_toggled(msg.payload, proxy, state),
                 "selectAll" => commands::select_all(proxy, state),
                 "deselectAll" => commands::deselect_all(proxy, state),
+                "setGenerationOrder" => commands::set_generation_order(msg.payload, proxy, state),
+                "selectByExtension" => commands::select_by_extension(msg.payload, proxy, state),
+                "deselectByExtension" => commands::deselect_by_extension(msg.payload, proxy, state),
                 "expandAllFully" => commands::expand_all_fully(proxy, state),
                 "selectAllFully" => commands::select_all_fully(proxy, state),
                 "cancelGeneration" => commands::cancel_generation(proxy, state),
+                "showGenerationDiff" => commands::show_generation_diff(proxy, state),
+                "previewGenerationPlan" => commands::preview_generation_plan(proxy, state),
+                "listGenerationHistory" => commands::list_generation_history(proxy, state),
+                "showGenerationFromHistory" => {
+                    commands::show_generation_from_history(msg.payload, proxy, state)
+                }
+                "computeTreeStats" => commands::compute_tree_stats(msg.payload, proxy, state),
                 "clearPreviewState" => commands::clear_preview_state(proxy, state),
+                "nextMatch" => commands::next_match(proxy, state),
+                "prevMatch" => commands::prev_match(proxy, state),
                 "saveFile" => commands::save_file(dialog.as_ref(), msg.payload, proxy, state),
+                "saveToConfiguredPath" => {
+                    commands::save_to_configured_path(msg.payload, proxy, state)
+                }
                 "pickOutputDirectory" => {
                     commands::pick_output_directory(dialog.as_ref(), proxy, state)
                 }
                 "exportConfig" => commands::export_config(dialog.as_ref(), proxy, state),
+                "resetConfigToDefaults" => commands::reset_config_to_defaults(proxy, state),
+                "validateConfig" => commands::validate_config(msg.payload, proxy),
+                "exportTree" => commands::export_tree(dialog.as_ref(), proxy, state),
+                "copySelectedPaths" => {
+                    commands::copy_selected_paths(clipboard.as_ref(), proxy, state)
+                }
+                "copyGeneratedContent" => {
+                    commands::copy_generated_content(clipboard.as_ref(), proxy, state)
+                }
+                "importSelection" => commands::import_selection(dialog.as_ref(), proxy, state),
+                "exportSelection" => commands::export_selection(dialog.as_ref(), proxy, state),
+                "previewIgnoreMatches" => {
+                    commands::preview_ignore_matches(msg.payload, proxy, state)
+                }
+                "explainHidden" => commands::explain_hidden(msg.payload, proxy, state),
+                "saveProfile" => commands::save_profile(msg.payload, proxy, state),
+                "loadProfile" => commands::load_profile(msg.payload, proxy, state),
+                "listProfiles" => commands::list_profiles(proxy),
+                "getLogPath" => commands::get_log_path(proxy),
+                "setOutputFilename" => commands::set_output_filename(msg.payload, proxy, state),
+                "deleteProfile" => commands::delete_profile(msg.payload, proxy, state),
 
                 // --- Legacy Command Names ---
                 "expand_all_fully" => commands::expand_all_fully(proxy, state),
@@ -107,12 +171,17 @@ pub fn handle_user_event(event: UserEvent, webview: &WebView) {
             language,
             search_term,
             path,
+            truncation,
         } => format!(
-            "window.showPreviewContent({}, {}, {}, {});",
+            "window.showPreviewContent({}, {}, {}, {}, {});",
             serde_json::to_string(&content).unwrap_or_default(),
             serde_json::to_string(&language).unwrap_or_default(),
             serde_json::to_string(&search_term).unwrap_or_default(),
-            serde_json::to_string(&path).unwrap_or_default(),
+            serde_json::to_string(&path.to_string_lossy()).unwrap_or_default(),
+            serde_json::to_string(&truncation.map(|(shown_lines, total_lines)| {
+                serde_json::json!({ "shown_lines": shown_lines, "total_lines": total_lines })
+            }))
+            .unwrap_or_default(),
         ),
         UserEvent::ShowGeneratedContent {
             content,
@@ -122,12 +191,30 @@ pub fn handle_user_event(event: UserEvent, webview: &WebView) {
             serde_json::to_string(&content).unwrap_or_default(),
             token_count
         ),
+        UserEvent::GenerationStart => "window.beginGeneratedContent();".to_string(),
+        UserEvent::GenerationChunk { data } => format!(
+            "window.appendGeneratedContent({});",
+            serde_json::to_string(&data).unwrap_or_default()
+        ),
+        UserEvent::GenerationEnd { token_count } => {
+            format!("window.endGeneratedContent({token_count});")
+        }
+        UserEvent::ConfirmLargeGeneration { bytes, files } => {
+            format!("window.confirmLargeGeneration({bytes}, {files});")
+        }
+        UserEvent::TokenEstimate { tokens, files } => {
+            format!("window.showTokenEstimate({tokens}, {files});")
+        }
         UserEvent::ShowError(msg) => {
             format!(
                 "window.showError({});",
                 serde_json::to_string(&msg).unwrap_or_default()
             )
         }
+        UserEvent::Status(message) => format!(
+            "window.showStatus({});",
+            serde_json::to_string(&message).unwrap_or_default()
+        ),
         UserEvent::SaveComplete(success, path) => format!(
             "window.fileSaveStatus({}, {});",
             success,
@@ -141,6 +228,63 @@ pub fn handle_user_event(event: UserEvent, webview: &WebView) {
                 "Failed to export config."
             }
         ),
+        UserEvent::TreeExported(success, detail) => {
+            let message = if success {
+                format!("Tree exported to {detail}.")
+            } else {
+                format!("Failed to export tree: {detail}")
+            };
+            format!(
+                "window.showStatus({});",
+                serde_json::to_string(&message).unwrap_or_default()
+            )
+        }
+        UserEvent::SelectionExported(success, detail) => {
+            let message = if success {
+                format!("Selection exported to {detail}.")
+            } else {
+                format!("Failed to export selection: {detail}")
+            };
+            format!(
+                "window.showStatus({});",
+                serde_json::to_string(&message).unwrap_or_default()
+            )
+        }
+        UserEvent::PathsCopied(count) => format!(
+            "window.showStatus('Copied {count} path{} to the clipboard.');",
+            if count == 1 { "" } else { "s" }
+        ),
+        UserEvent::OutputCopied { token_count } => {
+            format!("window.outputCopied({token_count});")
+        }
+        UserEvent::ConfigValidation { path, issues } => format!(
+            "window.showConfigValidation({}, {});",
+            serde_json::to_string(&path.to_string_lossy()).unwrap_or_default(),
+            serde_json::to_string(&issues).unwrap_or_default()
+        ),
+        UserEvent::ProfilesListed(names) => format!(
+            "window.showProfiles({});",
+            serde_json::to_string(&names).unwrap_or_default()
+        ),
+        UserEvent::OutputFilenameSet {
+            filename,
+            corrected,
+        } => format!(
+            "window.outputFilenameSet({}, {corrected});",
+            serde_json::to_string(&filename).unwrap_or_default()
+        ),
+        UserEvent::ScrollToLine(line) => format!("window.scrollToLine({line});"),
+        UserEvent::SetZoom(zoom) => {
+            // Zooming is a WebView setting, not something the page can do itself.
+            if let Err(e) = webview.zoom(zoom) {
+                tracing::warn!("Failed to set the UI zoom to {zoom}: {}", e);
+            }
+            return;
+        }
+        UserEvent::LogPath(path) => format!(
+            "window.showLogPath({});",
+            serde_json::to_string(&path).unwrap_or_default()
+        ),
         UserEvent::ScanProgress(progress) => {
             format!(
                 "window.updateScanProgress({});",
@@ -150,6 +294,58 @@ pub fn handle_user_event(event: UserEvent, webview: &WebView) {
         UserEvent::DragStateChanged(is_dragging) => {
             format!("window.setDragState({is_dragging});")
         }
+        UserEvent::GenerationDiff(diff) => format!(
+            "window.showGenerationDiff({});",
+            serde_json::to_string(&diff).unwrap_or_default()
+        ),
+        UserEvent::GenerationPlan(plan) => format!(
+            "window.showGenerationPlan({});",
+            serde_json::to_string(&plan).unwrap_or_default()
+        ),
+        UserEvent::GenerationHistory(history) => format!(
+            "window.showGenerationHistory({});",
+            serde_json::to_string(&history).unwrap_or_default()
+        ),
+        UserEvent::FilesAutoIncluded(files) => format!(
+            "window.showAutoIncluded({});",
+            serde_json::to_string(&files).unwrap_or_default()
+        ),
+        UserEvent::TreeStats(stats) => format!(
+            "window.showTreeStats({});",
+            serde_json::to_string(&stats).unwrap_or_default()
+        ),
+        UserEvent::ContentSearchResults { query, files } => format!(
+            "window.showContentSearchResults({}, {});",
+            serde_json::to_string(&query).unwrap_or_default(),
+            serde_json::to_string(&files).unwrap_or_default()
+        ),
+        UserEvent::IgnorePreview { pattern, matches } => {
+            let matches: Vec<_> = matches.iter().map(|path| path.to_string_lossy()).collect();
+            format!(
+                "window.showIgnorePreview({}, {});",
+                serde_json::to_string(&pattern).unwrap_or_default(),
+                serde_json::to_string(&matches).unwrap_or_default()
+            )
+        }
+        UserEvent::HiddenExplanation { path, reasons } => format!(
+            "window.showHiddenExplanation({}, {});",
+            serde_json::to_string(&path.to_string_lossy()).unwrap_or_default(),
+            serde_json::to_string(&reasons).unwrap_or_default()
+        ),
+        UserEvent::LargeFilesSkipped {
+            count,
+            names,
+            max_file_size_mb,
+        } => format!(
+            "window.showLargeFilesSkipped({}, {}, {});",
+            count,
+            serde_json::to_string(&names).unwrap_or_default(),
+            max_file_size_mb
+        ),
+        UserEvent::BrokenSymlinks { paths } => format!(
+            "window.showBrokenSymlinks({});",
+            serde_json::to_string(&paths).unwrap_or_default()
+        ),
     };
     if let Err(e) = webview.evaluate_script(&script) {
         tracing::error!("Failed to evaluate script: {}", e);
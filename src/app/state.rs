@@ -1,13 +1,367 @@
 //! Defines the central, mutable state of the application.
 
-use crate::config::AppConfig;
-use crate::core::{FileItem, ScanProgress};
-use std::collections::HashSet;
-use std::path::PathBuf;
+use super::clipboard::ClipboardService;
+use super::events::PathRef;
+use crate::config::{AppConfig, SavedSession};
+use crate::core::{
+    ConcatenatedOutput, ContentTransform, ExportIgnore, FileItem, FilenameMatchMode, GitStatus,
+    IgnoreRules, OutputFormat, ScanProgress,
+};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::SystemTime;
+use tempfile::TempPath;
 use tokio::task::JoinHandle;
 
+/// A saved selection, written by `exportSelection` and read back by `importSelection`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SelectionSet {
+    /// The directory the selection was exported from.
+    pub root: String,
+    /// The selected files, relative to `root`.
+    pub paths: Vec<String>,
+}
+
+/// The outcome of `AppState::select_listed_paths`.
+#[derive(Debug, Default, PartialEq)]
+pub struct SelectionImport {
+    /// The listed files that were selected.
+    pub selected: Vec<PathBuf>,
+    /// Listed paths that do not exist.
+    pub missing: Vec<PathBuf>,
+    /// Listed paths that exist but are not in the file list, e.g. because they are
+    /// ignored or are directories.
+    pub filtered_out: Vec<PathBuf>,
+}
+
+/// A cached token count for one file's content as it appeared in the generated output,
+/// with the size of that content block for the index, see `IndexEntry`.
+///
+/// The entry is only valid while the file's size and modification time are unchanged
+/// and the same tokenizer model, content transform and output format are used.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenCacheEntry {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub model: String,
+    pub transform: ContentTransform,
+    pub format: OutputFormat,
+    pub token_count: usize,
+    /// The size of the file's content block in bytes.
+    pub block_bytes: usize,
+    /// The number of lines in the file's content block.
+    pub block_lines: usize,
+}
+
+impl TokenCacheEntry {
+    /// Returns `true` if this entry was computed for the same file version, model,
+    /// transform and format as `other`. The counts themselves are not compared.
+    pub fn matches(&self, other: &TokenCacheEntry) -> bool {
+        self.size == other.size
+            && self.modified == other.modified
+            && self.model == other.model
+            && self.transform == other.transform
+            && self.format == other.format
+    }
+}
+
+/// Where a generated output is delivered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GenerationTarget {
+    /// Sent to the WebView and shown in the preview.
+    #[default]
+    Preview,
+    /// Copied to the clipboard by the backend; only the token count is reported.
+    Clipboard,
+}
+
+/// Which files a content search reads.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ContentSearchScope {
+    /// Every file in `full_file_list`.
+    #[default]
+    AllFiles,
+    /// Only the selected files.
+    SelectedOnly,
+    /// Only the files that pass the filename, extension and size filters.
+    VisibleOnly,
+}
+
+/// How relevant a file must be to appear in the content search results.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ContentSearchThreshold {
+    /// Files with fewer matches are left out.
+    pub min_matches: usize,
+    /// Files with fewer matches per KiB of content are left out. `0.0` disables it.
+    pub min_matches_per_kb: f64,
+}
+
+impl Default for ContentSearchThreshold {
+    fn default() -> Self {
+        Self {
+            min_matches: 1,
+            min_matches_per_kb: 0.0,
+        }
+    }
+}
+
+impl ContentSearchThreshold {
+    /// Whether a file of `size` bytes with `count` matches is shown. The ratio is not
+    /// checked for empty files.
+    pub fn accepts(&self, count: usize, size: u64) -> bool {
+        count >= self.min_matches
+            && (self.min_matches_per_kb <= 0.0
+                || size == 0
+                || count as f64 * 1024.0 / size as f64 >= self.min_matches_per_kb)
+    }
+}
+
+/// The files of one generated output with a hash of each file's content block, used
+/// to tell what changed between two generations.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GenerationSnapshot {
+    pub file_hashes: HashMap<PathBuf, u64>,
+    pub token_count: usize,
+}
+
+impl GenerationSnapshot {
+    /// Hashes every file's content block in `output`. The hashes are only comparable
+    /// within one run of the app.
+    pub fn from_output(output: &ConcatenatedOutput, token_count: usize) -> Self {
+        let file_hashes = output
+            .file_ranges
+            .iter()
+            .map(|(path, range)| {
+                (
+                    path.clone(),
+                    Self::hash_block(&output.content[range.clone()]),
+                )
+            })
+            .collect();
+        Self {
+            file_hashes,
+            token_count,
+        }
+    }
+
+    /// Hashes one file's content block the way `from_output` does.
+    pub fn hash_block(block: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        block.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// The number of past generations kept in `AppState::generation_history`.
+pub const GENERATION_HISTORY_LEN: usize = 10;
+/// The number of the newest past generations whose full output is kept as well.
+pub const GENERATION_HISTORY_CONTENTS: usize = 2;
+
+/// A past generated output, listed by `listGenerationHistory`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct GenerationRecord {
+    /// Identifies the record for `showGenerationFromHistory`. Ids grow with every
+    /// generation, so one is never reused for another output.
+    pub id: u64,
+    /// A hash of the full output, to tell identical outputs apart. Only comparable
+    /// within one run of the app.
+    #[serde(serialize_with = "serialize_hash")]
+    pub content_hash: u64,
+    pub token_count: usize,
+    pub file_count: usize,
+    /// The size of the output in bytes.
+    pub size: usize,
+    /// When it was generated, in seconds since the Unix epoch.
+    pub generated_at: u64,
+    /// Where the output was last saved, if anywhere.
+    pub saved_path: Option<PathBuf>,
+    /// The full output, kept for the newest `GENERATION_HISTORY_CONTENTS` records only.
+    #[serde(skip)]
+    pub content: Option<Arc<str>>,
+}
+
+impl GenerationRecord {
+    /// Hashes `content` the way `content_hash` is computed.
+    pub fn hash_content(content: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(content.as_bytes());
+        hasher.finish()
+    }
+
+    /// Like `hash_content`, but reads the content from `reader` piece by piece.
+    pub fn hash_reader(mut reader: impl Read) -> io::Result<u64> {
+        let mut hasher = DefaultHasher::new();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => return Ok(hasher.finish()),
+                Ok(read) => hasher.write(&buffer[..read]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// The full generated output while the preview only shows its head, see
+/// `AppState::generated_content`.
+#[derive(Debug, Clone)]
+pub enum HeldOutput {
+    /// The output itself, shared with its `GenerationRecord`.
+    Text(Arc<str>),
+    /// The output as left in a temporary file by a streamed generation, see
+    /// `AppConfig::stream_generation`, with its `GenerationRecord::content_hash`.
+    Spilled {
+        file: Arc<TempPath>,
+        content_hash: u64,
+    },
+}
+
+impl HeldOutput {
+    /// The `GenerationRecord::content_hash` of the output.
+    pub fn content_hash(&self) -> u64 {
+        match self {
+            Self::Text(content) => GenerationRecord::hash_content(content),
+            Self::Spilled { content_hash, .. } => *content_hash,
+        }
+    }
+
+    /// The size of the output in bytes.
+    pub fn size(&self) -> io::Result<u64> {
+        match self {
+            Self::Text(content) => Ok(content.len() as u64),
+            Self::Spilled { file, .. } => Ok(std::fs::metadata(&**file)?.len()),
+        }
+    }
+
+    /// Opens the output for reading, e.g. to save it without loading it into memory.
+    pub fn reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        match self {
+            Self::Text(content) => Ok(Box::new(content.as_bytes())),
+            Self::Spilled { file, .. } => Ok(Box::new(std::fs::File::open(&**file)?)),
+        }
+    }
+
+    /// Reads the whole output into memory, e.g. for the clipboard.
+    pub fn text(&self) -> io::Result<Cow<'_, str>> {
+        match self {
+            Self::Text(content) => Ok(Cow::Borrowed(&**content)),
+            Self::Spilled { file, .. } => Ok(Cow::Owned(std::fs::read_to_string(&**file)?)),
+        }
+    }
+}
+
+/// Serializes a hash as a hex string, as JavaScript numbers cannot hold every `u64`.
+fn serialize_hash<S: serde::Serializer>(hash: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{hash:016x}"))
+}
+
+/// The last generated output, kept so the directory tree can be added or removed
+/// without reading the selected files again.
+#[derive(Debug, Clone)]
+pub struct GeneratedBody {
+    /// The output as generated, without a manifest.
+    pub output: ConcatenatedOutput,
+    /// The selected files in output order.
+    pub selection: Vec<PathBuf>,
+    /// The modification time of each file in `selection` when it was read.
+    pub modified: Vec<Option<SystemTime>>,
+    pub transform: ContentTransform,
+    pub format: OutputFormat,
+    pub use_relative_paths: bool,
+}
+
+impl GeneratedBody {
+    /// Records `output` as generated for `selection` under `config`.
+    pub fn new(output: ConcatenatedOutput, selection: Vec<PathBuf>, config: &AppConfig) -> Self {
+        Self {
+            output,
+            modified: modification_times(&selection),
+            selection,
+            transform: config.content_transform(),
+            format: config.output_format,
+            use_relative_paths: config.use_relative_paths,
+        }
+    }
+
+    /// Returns `true` if the file blocks were generated for `selection` under the
+    /// settings of `config` that shape them: same files in the same order, same
+    /// transform, format and path style. Whether a file was modified since is checked
+    /// separately against `modified`, as it touches the disk.
+    pub fn matches(&self, selection: &[PathBuf], config: &AppConfig) -> bool {
+        self.selection == selection
+            && self.transform == config.content_transform()
+            && self.format == config.output_format
+            && self.use_relative_paths == config.use_relative_paths
+    }
+}
+
+/// Reads the modification time of each of `paths`, `None` where it cannot be read.
+pub fn modification_times(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+/// Hands out the ids the file tree refers to its entries by, see `PathRef::Id`.
+///
+/// A path keeps its id however often the file list is rescanned, filtered or
+/// reordered, and ids are never reused, so an id the frontend rendered earlier can
+/// only ever name the path it was given for. Ids are assigned while the UI state is
+/// built from a shared `&AppState`, hence the lock.
+#[derive(Debug, Default)]
+pub struct PathIds {
+    table: Mutex<PathIdTable>,
+}
+
+#[derive(Debug, Default)]
+struct PathIdTable {
+    ids: HashMap<PathBuf, usize>,
+    paths: HashMap<usize, PathBuf>,
+    next: usize,
+}
+
+impl PathIds {
+    /// The id of `path`, assigning the next unused one on first use.
+    pub fn id(&self, path: &Path) -> usize {
+        let mut table = self.lock();
+        if let Some(&id) = table.ids.get(path) {
+            return id;
+        }
+        let id = table.next;
+        table.next += 1;
+        table.ids.insert(path.to_path_buf(), id);
+        table.paths.insert(id, path.to_path_buf());
+        id
+    }
+
+    /// The path `id` was handed out for. `None` for ids that were never handed out or
+    /// were forgotten by `clear`.
+    pub fn path(&self, id: usize) -> Option<PathBuf> {
+        self.lock().paths.get(&id).cloned()
+    }
+
+    /// Forgets all paths, e.g. when another directory is loaded. Their ids are not
+    /// handed out again.
+    pub fn clear(&self) {
+        let mut table = self.lock();
+        table.ids.clear();
+        table.paths.clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PathIdTable> {
+        self.table.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
 /// Holds the complete, mutable state of the application.
 ///
 /// This struct is wrapped in an `Arc<Mutex<...>>` to allow for safe, shared access
@@ -27,24 +381,47 @@ pub struct AppState {
     pub expanded_dirs: HashSet<PathBuf>,
     /// The set of absolute paths to directories whose children have been loaded.
     pub loaded_dirs: HashSet<PathBuf>,
+    /// Entries shown in the tree for directories whose "… and N more" node was
+    /// clicked, if more than `AppConfig::max_children_displayed`.
+    pub children_shown: HashMap<PathBuf, usize>,
     /// `true` if a directory scan is currently in progress.
     pub is_scanning: bool,
     /// `true` if the concatenation process is currently running.
     pub is_generating: bool,
+    /// `true` if a token estimate is currently being computed.
+    pub is_estimating: bool,
     /// The current search query for filenames.
     pub search_query: String,
+    /// Whether `search_query` is a substring of the name or a glob.
+    pub filename_match_mode: FilenameMatchMode,
     /// The current filter for file extensions.
     pub extension_filter: String,
     /// The current search query for file content.
     pub content_search_query: String,
-    /// The set of paths that match the current content search query.
-    pub content_search_results: HashSet<PathBuf>,
+    /// The files that match the current content search query, with the number of
+    /// occurrences in each.
+    pub content_search_results: HashMap<PathBuf, usize>,
+    /// The number of matching files kept for the current content search. `None` uses
+    /// `AppConfig::max_search_results`; raised by `loadMoreSearchResults`.
+    pub content_search_limit: Option<usize>,
+    /// Which files the content search reads.
+    pub content_search_scope: ContentSearchScope,
+    /// How relevant a file must be to count as a content search result.
+    pub content_search_threshold: ContentSearchThreshold,
+    /// `true` if the current content search matched more files than it kept.
+    pub content_search_truncated: bool,
     /// The filename of the currently loaded configuration file, if any.
     pub current_config_filename: Option<String>,
+    /// The name of the profile the current configuration was loaded from or saved to, if any.
+    pub active_profile: Option<String>,
     /// The current progress of the directory scan.
     pub scan_progress: ScanProgress,
     /// The path of the file currently being previewed in the editor.
     pub previewed_file_path: Option<PathBuf>,
+    /// The lines (1-based) of the preview that contain the content search query.
+    pub preview_match_lines: Vec<usize>,
+    /// The index into `preview_match_lines` of the match last jumped to.
+    pub preview_match_cursor: Option<usize>,
     /// A handle to the currently running scan task, allowing it to be aborted.
     pub scan_task: Option<JoinHandle<()>>,
     /// A flag used to signal cancellation to the scan task.
@@ -53,14 +430,72 @@ pub struct AppState {
     pub generation_task: Option<JoinHandle<()>>,
     /// A flag used to signal cancellation to the generation task.
     pub generation_cancellation_flag: Arc<AtomicBool>,
+    /// A handle to the currently running token estimate task, allowing it to be aborted.
+    pub estimation_task: Option<JoinHandle<()>>,
+    /// A flag used to signal cancellation to the token estimate task.
+    pub estimation_cancellation_flag: Arc<AtomicBool>,
+    /// A flag used to signal cancellation to the in-flight content search.
+    pub search_cancellation_flag: Arc<AtomicBool>,
+    /// A flag used to signal cancellation to the in-flight exact tree statistics.
+    pub tree_stats_cancellation_flag: Arc<AtomicBool>,
+    /// Counts `updateFilters` calls. A debounced filter pass is dropped if a newer call
+    /// arrived while it waited.
+    pub filter_request_seq: u64,
     /// The set of ignore patterns that were actually matched during the last scan.
     pub active_ignore_patterns: HashSet<String>,
+    /// The ignore patterns that excluded entries while walking the directory. Those
+    /// entries were never read, so removing one of these patterns needs a re-scan.
+    pub scan_ignore_patterns: HashSet<String>,
+    /// Entries that `apply_ignore_patterns` removed from `full_file_list` after they
+    /// were scanned. They are restored in place when their patterns are removed.
+    pub pattern_ignored_items: Vec<FileItem>,
+    /// The git status of every changed file below the root, read once per scan while
+    /// `AppConfig::show_git_status` is set. Applied to entries as they are loaded.
+    pub git_statuses: HashMap<PathBuf, GitStatus>,
+    /// The files git tracks below the root and their parent directories, read once per
+    /// scan while `AppConfig::only_git_tracked` is set. `None` outside a git work tree.
+    pub git_tracked_paths: Option<Arc<HashSet<PathBuf>>>,
+    /// The `export-ignore` attributes below the root, read once per scan while
+    /// `AppConfig::export_ignore_enabled` holds.
+    pub export_ignore: Option<Arc<ExportIgnore>>,
     /// `true` if a full, non-lazy scan has been completed successfully.
     pub is_fully_scanned: bool,
     /// Indicates whether patterns were removed and a re-scan is recommended.
     /// This flag is set when ignore patterns are removed, as the current file list
     /// might be missing files that were previously filtered out.
     pub patterns_need_rescan: bool,
+    /// Per-file token counts from previous generations, keyed by absolute path.
+    pub token_cache: HashMap<PathBuf, TokenCacheEntry>,
+    /// A user-chosen order for the generated output. Selected files listed here come
+    /// first, in this order; all others follow in tree order. `None` uses tree order.
+    pub generation_order_override: Option<Vec<PathBuf>>,
+    /// `true` while a generation above `AppConfig::warn_over_bytes` waits for the user
+    /// to confirm it.
+    pub generation_awaiting_confirmation: bool,
+    /// Where the current or pending generation is delivered.
+    pub generation_target: GenerationTarget,
+    /// The clipboard a generation for `GenerationTarget::Clipboard` is copied to, as
+    /// passed to `generate_to_clipboard`.
+    pub generation_clipboard: Option<Arc<dyn ClipboardService>>,
+    /// A session saved for the current root, restored once the deep scan completes.
+    pub pending_session_restore: Option<SavedSession>,
+    /// The most recent successful generation.
+    pub latest_generation: Option<GenerationSnapshot>,
+    /// The successful generation before `latest_generation`.
+    pub previous_generation: Option<GenerationSnapshot>,
+    /// The output of the most recent successful generation.
+    pub last_generated: Option<GeneratedBody>,
+    /// The most recent generated outputs, newest first, at most
+    /// `GENERATION_HISTORY_LEN`. Kept when the directory changes.
+    pub generation_history: VecDeque<GenerationRecord>,
+    /// The `GenerationRecord::id` of the next generation.
+    pub next_generation_id: u64,
+    /// The ids of the paths sent to the UI, see `PathIds`.
+    pub path_ids: PathIds,
+    /// The full generated output while the preview only shows its head, see
+    /// `AppConfig::preview_generated_head_lines` and `AppConfig::stream_generation`.
+    /// Saved and copied in place of the preview's content.
+    pub generated_content: Option<HeldOutput>,
 }
 
 impl Default for AppState {
@@ -74,26 +509,59 @@ impl Default for AppState {
             selected_files: HashSet::new(),
             expanded_dirs: HashSet::new(),
             loaded_dirs: HashSet::new(),
+            children_shown: HashMap::new(),
             is_scanning: false,
             is_generating: false,
+            is_estimating: false,
             search_query: String::new(),
+            filename_match_mode: FilenameMatchMode::default(),
             extension_filter: String::new(),
             content_search_query: String::new(),
-            content_search_results: HashSet::new(),
+            content_search_results: HashMap::new(),
+            content_search_limit: None,
+            content_search_scope: ContentSearchScope::default(),
+            content_search_threshold: ContentSearchThreshold::default(),
+            content_search_truncated: false,
             current_config_filename: None,
+            active_profile: None,
             scan_progress: ScanProgress {
                 files_scanned: 0,
                 large_files_skipped: 0,
                 current_scanning_path: "Ready.".to_string(),
             },
             previewed_file_path: None,
+            preview_match_lines: Vec::new(),
+            preview_match_cursor: None,
             scan_task: None,
             scan_cancellation_flag: Arc::new(AtomicBool::new(false)),
             generation_task: None,
             generation_cancellation_flag: Arc::new(AtomicBool::new(false)),
+            estimation_task: None,
+            estimation_cancellation_flag: Arc::new(AtomicBool::new(false)),
+            search_cancellation_flag: Arc::new(AtomicBool::new(false)),
+            tree_stats_cancellation_flag: Arc::new(AtomicBool::new(false)),
+            filter_request_seq: 0,
             active_ignore_patterns: HashSet::new(),
+            scan_ignore_patterns: HashSet::new(),
+            pattern_ignored_items: Vec::new(),
+            git_statuses: HashMap::new(),
+            git_tracked_paths: None,
+            export_ignore: None,
             is_fully_scanned: false,
             patterns_need_rescan: false,
+            token_cache: HashMap::new(),
+            generation_order_override: None,
+            generation_awaiting_confirmation: false,
+            generation_target: GenerationTarget::default(),
+            generation_clipboard: None,
+            pending_session_restore: None,
+            latest_generation: None,
+            previous_generation: None,
+            generation_history: VecDeque::new(),
+            next_generation_id: 0,
+            path_ids: PathIds::default(),
+            last_generated: None,
+            generated_content: None,
         }
     }
 }
@@ -132,26 +600,112 @@ impl AppState {
         self.is_generating = false;
     }
 
+    /// Cancels the current token estimate task, if any.
+    pub fn cancel_current_estimation(&mut self) {
+        if let Some(handle) = self.estimation_task.take() {
+            handle.abort();
+        }
+        self.estimation_cancellation_flag
+            .store(true, Ordering::SeqCst);
+        self.is_estimating = false;
+    }
+
+    /// Signals the in-flight content search, if any, to stop, and installs a fresh flag
+    /// for the next search. Returns the new flag.
+    pub fn cancel_current_search(&mut self) -> Arc<AtomicBool> {
+        self.search_cancellation_flag.store(true, Ordering::SeqCst);
+        self.search_cancellation_flag = Arc::new(AtomicBool::new(false));
+        self.search_cancellation_flag.clone()
+    }
+
+    /// Signals the in-flight exact tree statistics, if any, to stop, and installs a
+    /// fresh flag for the next run. Returns the new flag.
+    pub fn cancel_tree_stats(&mut self) -> Arc<AtomicBool> {
+        self.tree_stats_cancellation_flag
+            .store(true, Ordering::SeqCst);
+        self.tree_stats_cancellation_flag = Arc::new(AtomicBool::new(false));
+        self.tree_stats_cancellation_flag.clone()
+    }
+
+    /// Cancels every background task: the scan, the generation, the token estimate,
+    /// the content search and the exact tree statistics. Unlike `cancel_current_scan`,
+    /// the scanning state is cleared even when the scan task has already been taken.
+    pub fn cancel_all_tasks(&mut self) {
+        self.cancel_current_scan();
+        self.scan_cancellation_flag.store(true, Ordering::SeqCst);
+        self.is_scanning = false;
+
+        self.generation_awaiting_confirmation = false;
+        self.cancel_current_generation();
+        self.cancel_current_estimation();
+        self.cancel_current_search();
+        self.cancel_tree_stats();
+    }
+
+    /// The selection and expanded directories relative to the current root, sorted, or
+    /// `None` if no directory is loaded.
+    pub fn current_session(&self) -> Option<SavedSession> {
+        if self.current_path.is_empty() {
+            return None;
+        }
+        let root = Path::new(&self.current_path);
+        let relative = |paths: &HashSet<PathBuf>| {
+            let mut relative: Vec<PathBuf> = paths
+                .iter()
+                .filter_map(|path| path.strip_prefix(root).ok())
+                .map(Path::to_path_buf)
+                .collect();
+            relative.sort();
+            relative
+        };
+        Some(SavedSession {
+            selected_files: relative(&self.selected_files),
+            expanded_dirs: relative(&self.expanded_dirs),
+        })
+    }
+
     /// Resets all state related to a loaded directory.
     pub fn reset_directory_state(&mut self) {
         self.cancel_current_scan();
         self.cancel_current_generation();
+        self.cancel_current_estimation();
+        self.cancel_current_search();
 
         self.current_path = String::new();
         self.full_file_list.clear();
         self.filtered_file_list.clear();
+        self.path_ids.clear();
         self.selected_files.clear();
         self.expanded_dirs.clear();
         self.loaded_dirs.clear();
+        self.children_shown.clear();
         self.search_query.clear();
         self.extension_filter.clear();
         self.content_search_query.clear();
         self.content_search_results.clear();
+        self.content_search_limit = None;
+        self.content_search_truncated = false;
         self.previewed_file_path = None;
+        self.reset_preview_matches(Vec::new());
         self.active_ignore_patterns.clear();
+        self.scan_ignore_patterns.clear();
+        self.pattern_ignored_items.clear();
+        self.git_statuses.clear();
+        self.git_tracked_paths = None;
+        self.export_ignore = None;
         self.is_generating = false;
         self.is_fully_scanned = false;
         self.patterns_need_rescan = false;
+        self.token_cache.clear();
+        self.generation_order_override = None;
+        self.generation_awaiting_confirmation = false;
+        self.generation_target = GenerationTarget::default();
+        self.generation_clipboard = None;
+        self.pending_session_restore = None;
+        self.latest_generation = None;
+        self.previous_generation = None;
+        self.last_generated = None;
+        self.generated_content = None;
         self.scan_progress = ScanProgress {
             files_scanned: 0,
             large_files_skipped: 0,
@@ -159,9 +713,127 @@ impl AppState {
         };
     }
 
+    /// Replaces the match lines of the preview and forgets the match last jumped to.
+    pub fn reset_preview_matches(&mut self, lines: Vec<usize>) {
+        self.preview_match_lines = lines;
+        self.preview_match_cursor = None;
+    }
+
+    /// Moves to the next match in the preview, or to the previous one without
+    /// `forward`, wrapping around at either end. Returns the line of that match, or
+    /// `None` if the preview has no matches.
+    pub fn step_preview_match(&mut self, forward: bool) -> Option<usize> {
+        let count = self.preview_match_lines.len();
+        if count == 0 {
+            return None;
+        }
+        let index = match (self.preview_match_cursor, forward) {
+            (None, true) => 0,
+            (None, false) => count - 1,
+            (Some(index), true) => (index + 1) % count,
+            (Some(index), false) => (index + count - 1) % count,
+        };
+        self.preview_match_cursor = Some(index);
+        Some(self.preview_match_lines[index])
+    }
+
+    /// Adds a generated output to the front of `generation_history`. Only the newest
+    /// `GENERATION_HISTORY_CONTENTS` records keep their content and only the newest
+    /// `GENERATION_HISTORY_LEN` records are kept at all.
+    pub fn record_generation(&mut self, content: Arc<str>, token_count: usize, file_count: usize) {
+        self.push_generation(GenerationRecord {
+            id: 0,
+            content_hash: GenerationRecord::hash_content(&content),
+            token_count,
+            file_count,
+            size: content.len(),
+            generated_at: 0,
+            saved_path: None,
+            content: Some(content),
+        });
+    }
+
+    /// Like `record_generation` for an output left in a temporary file by a streamed
+    /// generation. Its content is not kept.
+    pub fn record_spilled_generation(
+        &mut self,
+        content_hash: u64,
+        size: usize,
+        token_count: usize,
+        file_count: usize,
+    ) {
+        self.push_generation(GenerationRecord {
+            id: 0,
+            content_hash,
+            token_count,
+            file_count,
+            size,
+            generated_at: 0,
+            saved_path: None,
+            content: None,
+        });
+    }
+
+    /// Stamps `record` with the next id and the current time and adds it to
+    /// `generation_history`, see `record_generation`.
+    fn push_generation(&mut self, mut record: GenerationRecord) {
+        record.id = self.next_generation_id;
+        self.next_generation_id += 1;
+        record.generated_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |age| age.as_secs());
+        self.generation_history.push_front(record);
+        for record in self
+            .generation_history
+            .iter_mut()
+            .skip(GENERATION_HISTORY_CONTENTS)
+        {
+            record.content = None;
+        }
+        self.generation_history.truncate(GENERATION_HISTORY_LEN);
+    }
+
+    /// Remembers `path` as where the newest record of `generation_history` with this
+    /// `content_hash` was saved. Edited content matches no record.
+    pub fn note_generation_saved(&mut self, content_hash: u64, path: &Path) {
+        if let Some(record) = self
+            .generation_history
+            .iter_mut()
+            .find(|record| record.content_hash == content_hash)
+        {
+            record.saved_path = Some(path.to_path_buf());
+        }
+    }
+
+    /// Resolves a tree entry reference from an IPC payload to its exact path.
+    ///
+    /// An id resolves to the path it was rendered for, even if the file list changed
+    /// since. Returns `None` for an id that was never handed out or belongs to a
+    /// previously loaded directory.
+    pub fn resolve_path_ref(&self, path_ref: PathRef) -> Option<PathBuf> {
+        match path_ref {
+            PathRef::Id(id) => self.path_ids.path(id),
+            PathRef::Path(path) => Some(path),
+        }
+    }
+
+    /// Drops cached token counts for files that disappeared or changed size in the
+    /// latest scan. Modification times are re-checked when an entry is looked up.
+    pub fn prune_token_cache(&mut self) {
+        let sizes: HashMap<&PathBuf, u64> = self
+            .full_file_list
+            .iter()
+            .filter(|item| !item.is_directory)
+            .map(|item| (&item.path, item.size))
+            .collect();
+        self.token_cache
+            .retain(|path, entry| sizes.get(path) == Some(&entry.size));
+    }
+
     /// Applies the complete set of current ignore patterns to the in-memory file lists.
     /// This function re-builds the matcher from `self.config.ignore_patterns`
-    /// and filters `full_file_list` and `selected_files` accordingly.
+    /// and filters `full_file_list` and `selected_files` accordingly. Removed entries
+    /// are kept in `pattern_ignored_items`.
     pub fn apply_ignore_patterns(&mut self) {
         // CHANGED: Use the complete, current set of patterns from the config.
         let patterns = &self.config.ignore_patterns;
@@ -170,36 +842,98 @@ impl AppState {
         }
 
         let root_path = PathBuf::from(&self.current_path);
-        let mut builder = ignore::gitignore::GitignoreBuilder::new(&root_path);
+        let rules = IgnoreRules::new(&root_path, patterns);
 
-        // CHANGED: Iterate over all configured patterns.
-        for pattern in patterns {
-            builder.add_line(None, pattern).ok();
-        }
+        let path_info: std::collections::HashMap<_, _> = self
+            .full_file_list
+            .iter()
+            .map(|item| (item.path.clone(), item.is_directory))
+            .collect();
 
-        if let Ok(matcher) = builder.build() {
-            let path_info: std::collections::HashMap<_, _> = self
-                .full_file_list
-                .iter()
-                .map(|item| (item.path.clone(), item.is_directory))
-                .collect();
+        let (ignored, kept): (Vec<FileItem>, Vec<FileItem>) =
+            std::mem::take(&mut self.full_file_list)
+                .into_iter()
+                .partition(|item| rules.is_ignored_or_any_parents(&item.path, item.is_directory));
+        self.full_file_list = kept;
+        self.pattern_ignored_items.extend(ignored);
+
+        self.selected_files.retain(|path| {
+            let is_dir = path_info.get(path).copied().unwrap_or(false);
+            !rules.is_ignored_or_any_parents(path, is_dir)
+        });
 
-            self.full_file_list.retain(|item| {
-                !matcher
-                    .matched_path_or_any_parents(&item.path, item.is_directory)
-                    .is_ignore()
-            });
-
-            self.selected_files.retain(|path| {
-                let is_dir = path_info.get(path).copied().unwrap_or(false);
-                !matcher
-                    .matched_path_or_any_parents(path, is_dir)
-                    .is_ignore()
-            });
-
-            // Note: active_ignore_patterns are typically recalculated during a full scan
-            // or could be updated here if needed, but for local filtering this is sufficient.
+        // Note: active_ignore_patterns are typically recalculated during a full scan
+        // or could be updated here if needed, but for local filtering this is sufficient.
+    }
+
+    /// The files in `full_file_list` that `pattern` alone would hide, directly or through
+    /// an ignored parent directory, sorted by path. A `!` pattern hides nothing.
+    pub fn ignore_pattern_matches(&self, pattern: &str) -> Vec<PathBuf> {
+        let root_path = PathBuf::from(&self.current_path);
+        let pattern = pattern.to_string();
+        let rules = IgnoreRules::new(&root_path, [&pattern]);
+        let mut matches: Vec<PathBuf> = self
+            .full_file_list
+            .iter()
+            .filter(|item| !item.is_directory && rules.is_ignored_or_any_parents(&item.path, false))
+            .map(|item| item.path.clone())
+            .collect();
+        matches.sort();
+        matches
+    }
+
+    /// Selects the listed files, e.g. the lines of a manifest. Relative paths are
+    /// resolved against the current directory; blank entries and entries starting with
+    /// `#` are skipped. Listed paths that are not files in `full_file_list` are reported
+    /// as missing or, if they exist on disk, as filtered out.
+    pub fn select_listed_paths<'a>(
+        &mut self,
+        listed_paths: impl IntoIterator<Item = &'a str>,
+    ) -> SelectionImport {
+        let root_path = PathBuf::from(&self.current_path);
+        let files: HashSet<&Path> = self
+            .full_file_list
+            .iter()
+            .filter(|item| !item.is_directory)
+            .map(|item| item.path.as_path())
+            .collect();
+        let mut import = SelectionImport::default();
+        for line in listed_paths.into_iter().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // `./src/main.rs` as printed by `find` must match `src/main.rs`.
+            let listed: PathBuf = Path::new(line)
+                .components()
+                .filter(|c| !matches!(c, Component::CurDir))
+                .collect();
+            let path = root_path.join(listed);
+            if files.contains(path.as_path()) {
+                import.selected.push(path);
+            } else if path.exists() {
+                import.filtered_out.push(path);
+            } else {
+                import.missing.push(path);
+            }
         }
+        self.selected_files.extend(import.selected.iter().cloned());
+        import
+    }
+
+    /// Returns `true` if the file list can follow the removal of `removed` patterns and
+    /// the addition of `!` overrides without a re-scan, because every entry they could
+    /// bring back is still in memory.
+    pub fn can_unignore_in_place(&self, removed: &HashSet<String>, adds_overrides: bool) -> bool {
+        removed.is_disjoint(&self.scan_ignore_patterns)
+            && (!adds_overrides || self.scan_ignore_patterns.is_empty())
+    }
+
+    /// Moves the entries in `pattern_ignored_items` back into `full_file_list` and
+    /// re-applies the current ignore patterns to them.
+    pub fn restore_pattern_ignored_items(&mut self) {
+        let restored = std::mem::take(&mut self.pattern_ignored_items);
+        self.full_file_list.extend(restored);
+        self.apply_ignore_patterns();
     }
 }
 
@@ -414,4 +1148,129 @@ mod tests {
         assert_eq!(state.full_file_list.len(), initial_file_list.len());
         assert_eq!(state.full_file_list[0].path, initial_file_list[0].path);
     }
+
+    #[test]
+    fn test_ignore_pattern_matches_lists_hidden_files_without_changing_state() {
+        let mut state = AppState::default();
+        state.config.ignore_patterns.clear();
+        state.current_path = "/project".to_string();
+        state.full_file_list = vec![
+            create_test_file_item("/project/src", true),
+            create_test_file_item("/project/src/main.rs", false),
+            create_test_file_item("/project/logs", true),
+            create_test_file_item("/project/logs/b.log", false),
+            create_test_file_item("/project/a.log", false),
+        ];
+
+        assert_eq!(
+            state.ignore_pattern_matches("*.log"),
+            vec![
+                PathBuf::from("/project/a.log"),
+                PathBuf::from("/project/logs/b.log")
+            ]
+        );
+        assert_eq!(
+            state.ignore_pattern_matches("src/"),
+            vec![PathBuf::from("/project/src/main.rs")]
+        );
+        assert!(state.ignore_pattern_matches("!a.log").is_empty());
+        assert_eq!(state.full_file_list.len(), 5);
+        assert!(state.config.ignore_patterns.is_empty());
+    }
+
+    #[test]
+    fn test_generation_history_is_bounded_and_keeps_only_the_newest_contents() {
+        let mut state = AppState::default();
+        for n in 0..GENERATION_HISTORY_LEN + 2 {
+            state.record_generation(format!("output {n}").into(), n, 1);
+        }
+
+        let history = &state.generation_history;
+        assert_eq!(history.len(), GENERATION_HISTORY_LEN);
+        assert_eq!(history[0].token_count, GENERATION_HISTORY_LEN + 1);
+        let ids: Vec<_> = history.iter().map(|record| record.id).collect();
+        let expected: Vec<_> = (2..GENERATION_HISTORY_LEN as u64 + 2).rev().collect();
+        assert_eq!(ids, expected);
+        assert_eq!(
+            history[0].content.as_deref(),
+            Some(format!("output {}", GENERATION_HISTORY_LEN + 1).as_str())
+        );
+        assert!(history
+            .iter()
+            .skip(GENERATION_HISTORY_CONTENTS)
+            .all(|record| record.content.is_none()));
+        assert_eq!(
+            history
+                .iter()
+                .filter(|record| record.content.is_some())
+                .count(),
+            GENERATION_HISTORY_CONTENTS
+        );
+
+        let saved = |content| GenerationRecord::hash_content(content);
+        state.note_generation_saved(saved("output 5"), Path::new("/out/five.txt"));
+        state.note_generation_saved(saved("edited"), Path::new("/out/edited.txt"));
+        let saved: Vec<_> = state
+            .generation_history
+            .iter()
+            .filter_map(|record| record.saved_path.as_deref())
+            .collect();
+        assert_eq!(saved, vec![Path::new("/out/five.txt")]);
+    }
+
+    #[test]
+    fn test_hash_reader_matches_hash_content_however_the_content_is_split() {
+        let content = "fn main() {}\n".repeat(10_000);
+        let (head, tail) = content.split_at(12_345);
+
+        let streamed = GenerationRecord::hash_reader(head.as_bytes().chain(tail.as_bytes()));
+
+        assert_eq!(streamed.unwrap(), GenerationRecord::hash_content(&content));
+    }
+
+    #[tokio::test]
+    async fn test_prune_token_cache_drops_missing_and_resized_files() {
+        // Arrange
+        let mut state = AppState::default();
+        let entry = |size| TokenCacheEntry {
+            size,
+            modified: None,
+            model: "cl100k_base".to_string(),
+            transform: ContentTransform::default(),
+            format: OutputFormat::PlainText,
+            token_count: 10,
+            block_bytes: 5,
+            block_lines: 1,
+        };
+        state.full_file_list = vec![
+            FileItem {
+                path: PathBuf::from("/project/same.rs"),
+                size: 5,
+                ..Default::default()
+            },
+            FileItem {
+                path: PathBuf::from("/project/resized.rs"),
+                size: 7,
+                ..Default::default()
+            },
+        ];
+        state
+            .token_cache
+            .insert(PathBuf::from("/project/same.rs"), entry(5));
+        state
+            .token_cache
+            .insert(PathBuf::from("/project/resized.rs"), entry(6));
+        state
+            .token_cache
+            .insert(PathBuf::from("/project/deleted.rs"), entry(1));
+
+        // Act
+        state.prune_token_cache();
+
+        // Assert
+        assert_eq!(state.token_cache.len(), 1);
+        assert!(state
+            .token_cache
+            .contains_key(&PathBuf::from("/project/same.rs")));
+    }
 }
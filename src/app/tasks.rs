@@ -10,23 +10,49 @@
 
 use async_trait::async_trait;
 use rayon::prelude::*;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, BufRead, BufReader};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::oneshot;
 
+use super::clipboard::ClipboardService;
 use super::events::UserEvent;
 use super::filtering;
+use super::helpers::{lock_state, remember_session};
 use super::proxy::EventProxy;
-use super::state::AppState;
+use super::state::{
+    AppState, ContentSearchScope, ContentSearchThreshold, GeneratedBody, GenerationRecord,
+    GenerationSnapshot, GenerationTarget, HeldOutput, TokenCacheEntry,
+};
 use super::view_model::{
-    auto_expand_for_matches, generate_ui_state, get_selected_files_in_tree_order,
+    auto_expand_for_matches, content_search_files, generate_ui_state,
+    get_selected_files_in_generation_order, get_selected_files_in_tree_order, items_for_tree,
+    tree_stats,
 };
 
-use crate::core::{CoreError, DirectoryScanner, FileHandler, FileItem, ScanProgress, SearchEngine};
+use crate::config::AppConfig;
+use crate::core::file_handler::MAX_CONTENT_BYTES;
+use crate::core::{
+    git_status, ConcatenatedOutput, ContentTransform, CoreError, DirectoryScanner, ExportIgnore,
+    FileHandler, FileItem, FileMatches, GeneratedOutput, IndexEntry, MatchSnippet, OutputFormat,
+    ScanProgress, SearchEngine, SpilledOutput, TreePosition, TreeStyle,
+};
+use crate::utils::file_detection::{get_language_from_path, ExtensionOverrides};
+use crate::utils::thread_pool;
 use tiktoken_rs::cl100k_base;
 
+/// Generated outputs larger than this are streamed to the WebView in chunks of at most
+/// this many bytes, keeping each evaluated script small.
+const GENERATION_CHUNK_BYTES: usize = 256 * 1024;
+
+/// The lines of a streamed output sent to the preview when
+/// `AppConfig::preview_generated_head_lines` is not set, as a streamed output is not
+/// loaded into memory as a whole.
+const SPILLED_PREVIEW_HEAD_LINES: usize = 2_000;
+
 //================================================================================================//
 //|                                     SERVICE TRAITS                                           |//
 //================================================================================================//
@@ -34,17 +60,27 @@ use tiktoken_rs::cl100k_base;
 /// A trait abstracting the generation of concatenated file content.
 #[async_trait]
 pub trait ContentGenerator: Send + Sync {
+    #[allow(clippy::too_many_arguments)] // Mirrors `FileHandler::generate_concatenated_content_simple`
     async fn generate(
         &self,
         selected_files: &[PathBuf],
         root_path: &Path,
-        include_tree: bool,
+        tree_position: TreePosition,
         items_for_tree: Vec<FileItem>,
         tree_ignore_patterns: HashSet<String>,
+        tree_style: TreeStyle,
         use_relative_paths: bool,
-    ) -> Result<String, CoreError>;
+        transform: ContentTransform,
+        format: OutputFormat,
+        skip_unreadable: bool,
+    ) -> Result<GeneratedOutput, CoreError>;
 }
 
+/// The outcome of a scan: the discovered items, the custom ignore patterns that were
+/// actively used, the paths of files skipped for exceeding the size limit, and the
+/// paths of skipped broken symlinks.
+pub type ScanOutput = (Vec<FileItem>, HashSet<String>, Vec<PathBuf>, Vec<PathBuf>);
+
 /// A trait abstracting the directory scanning functionality.
 #[async_trait]
 pub trait Scanner: Send + Sync {
@@ -53,24 +89,32 @@ pub trait Scanner: Send + Sync {
         root_path: &Path,
         max_depth: Option<usize>,
         progress_callback: Box<dyn Fn(ScanProgress) + Send + Sync>,
-    ) -> Result<(Vec<FileItem>, HashSet<String>), CoreError>;
+    ) -> Result<ScanOutput, CoreError>;
 }
 
 /// A trait abstracting the token counting functionality.
 #[async_trait]
 pub trait Tokenizer: Send + Sync {
     async fn count_tokens(&self, text: &str) -> usize;
+    /// The name of the encoding used, so cached counts are not reused across models.
+    fn model(&self) -> &str;
 }
 
 /// A trait abstracting the file content search functionality.
 #[async_trait]
 pub trait FileSearcher: Send + Sync {
+    /// Returns the matches per matching file. With `context_lines` set, up to
+    /// `MAX_SNIPPETS_PER_FILE` matching lines are captured per file together with that
+    /// many lines before and after them. Once `cancel_flag` is set the search may stop
+    /// early and return partial results, which callers discard.
     async fn search(
         &self,
         files_to_search: Vec<FileItem>,
         query: &str,
         case_sensitive: bool,
-    ) -> HashSet<PathBuf>;
+        context_lines: Option<usize>,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> HashMap<PathBuf, FileMatches>;
 }
 
 //================================================================================================//
@@ -79,6 +123,8 @@ pub trait FileSearcher: Send + Sync {
 
 pub struct RealContentGenerator {
     pub cancel_flag: Arc<AtomicBool>,
+    /// See `AppConfig::stream_generation`.
+    pub stream_generation: bool,
 }
 #[async_trait]
 impl ContentGenerator for RealContentGenerator {
@@ -86,28 +132,57 @@ impl ContentGenerator for RealContentGenerator {
         &self,
         selected_files: &[PathBuf],
         root_path: &Path,
-        include_tree: bool,
+        tree_position: TreePosition,
         items_for_tree: Vec<FileItem>,
         tree_ignore_patterns: HashSet<String>,
+        tree_style: TreeStyle,
         use_relative_paths: bool,
-    ) -> Result<String, CoreError> {
-        FileHandler::generate_concatenated_content_simple(
+        transform: ContentTransform,
+        format: OutputFormat,
+        skip_unreadable: bool,
+    ) -> Result<GeneratedOutput, CoreError> {
+        if self.stream_generation {
+            return FileHandler::generate_spilled_output(
+                selected_files,
+                root_path,
+                tree_position,
+                items_for_tree,
+                tree_ignore_patterns,
+                tree_style,
+                use_relative_paths,
+                transform,
+                format,
+                skip_unreadable,
+                self.cancel_flag.clone(),
+            )
+            .await
+            .map(GeneratedOutput::Spilled);
+        }
+        FileHandler::generate_concatenated_output(
             selected_files,
             root_path,
-            include_tree,
+            tree_position,
             items_for_tree,
             tree_ignore_patterns,
+            tree_style,
             use_relative_paths,
+            transform,
+            format,
+            skip_unreadable,
             self.cancel_flag.clone(),
             #[cfg(test)]
             None,
         )
         .await
+        .map(GeneratedOutput::InMemory)
     }
 }
 
 pub struct RealScanner {
     pub ignore_patterns: HashSet<String>,
+    pub max_file_size_mb: u64,
+    pub include_hidden: bool,
+    pub extension_overrides: ExtensionOverrides,
     pub cancel_flag: Arc<AtomicBool>,
 }
 #[async_trait]
@@ -117,8 +192,11 @@ impl Scanner for RealScanner {
         root_path: &Path,
         max_depth: Option<usize>,
         progress_callback: Box<dyn Fn(ScanProgress) + Send + Sync>,
-    ) -> Result<(Vec<FileItem>, HashSet<String>), CoreError> {
-        let scanner = DirectoryScanner::new(self.ignore_patterns.clone());
+    ) -> Result<ScanOutput, CoreError> {
+        let scanner = DirectoryScanner::new(self.ignore_patterns.clone())
+            .with_max_file_size_mb(self.max_file_size_mb)
+            .with_include_hidden(self.include_hidden)
+            .with_extension_overrides(self.extension_overrides.clone());
         scanner
             .scan_directory_with_progress(
                 root_path,
@@ -143,10 +221,32 @@ impl Tokenizer for RealTokenizer {
         .await
         .unwrap_or(0)
     }
+
+    fn model(&self) -> &str {
+        "cl100k_base"
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct RealFileSearcher {
+    /// See `AppConfig::max_threads`.
+    pub max_threads: Option<usize>,
+    /// See `AppConfig::search_read_buffer_kb`. `0` uses the default buffer size.
+    pub read_buffer_kb: usize,
+}
+
+impl RealFileSearcher {
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            max_threads: config.max_threads,
+            read_buffer_kb: config.search_read_buffer_kb,
+        }
+    }
 }
 
-#[derive(Copy, Clone)]
-pub struct RealFileSearcher;
+/// The read buffer of a `RealFileSearcher` with `read_buffer_kb` unset.
+const DEFAULT_SEARCH_BUFFER_KB: usize = 64;
+
 #[async_trait]
 impl FileSearcher for RealFileSearcher {
     async fn search(
@@ -154,37 +254,157 @@ impl FileSearcher for RealFileSearcher {
         files_to_search: Vec<FileItem>,
         query: &str,
         case_sensitive: bool,
-    ) -> HashSet<PathBuf> {
-        let query_clone = query.to_string();
+        context_lines: Option<usize>,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> HashMap<PathBuf, FileMatches> {
+        let query_clone = if case_sensitive {
+            query.to_string()
+        } else {
+            query.to_lowercase()
+        };
+        let max_threads = self.max_threads;
+        let buffer_size = match self.read_buffer_kb {
+            0 => DEFAULT_SEARCH_BUFFER_KB,
+            kb => kb,
+        } * 1024;
         tokio::task::spawn_blocking(move || {
-            files_to_search
-                .into_par_iter()
-                .filter_map(|item| {
-                    if item.is_directory || item.is_binary {
-                        return None;
-                    }
-                    if let Ok(content) = std::fs::read_to_string(&item.path) {
-                        let found = if case_sensitive {
-                            content.contains(&query_clone)
-                        } else {
-                            content.to_lowercase().contains(&query_clone.to_lowercase())
-                        };
-                        if found {
-                            Some(item.path)
-                        } else {
-                            None
+            thread_pool::install(max_threads, || {
+                files_to_search
+                    .into_par_iter()
+                    .filter_map(|item| {
+                        if item.is_directory
+                            || item.is_binary
+                            || cancel_flag.load(Ordering::Relaxed)
+                        {
+                            return None;
                         }
-                    } else {
-                        None
-                    }
-                })
-                .collect()
+                        let file = std::fs::File::open(&item.path).ok()?;
+                        let matches = search_lines(
+                            BufReader::with_capacity(buffer_size, file),
+                            &query_clone,
+                            case_sensitive,
+                            context_lines,
+                            &cancel_flag,
+                        )
+                        .ok()?;
+                        (matches.count > 0).then_some((item.path, matches))
+                    })
+                    .collect()
+            })
         })
         .await
         .unwrap_or_default()
     }
 }
 
+/// Returns the numbers (1-based) of the lines of `content` that contain `query`.
+pub fn matching_lines(content: &str, query: &str, case_sensitive: bool) -> Vec<usize> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let query = if case_sensitive {
+        query.to_string()
+    } else {
+        query.to_lowercase()
+    };
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            if case_sensitive {
+                line.contains(&query)
+            } else {
+                line.to_lowercase().contains(&query)
+            }
+        })
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// The maximum number of snippets captured per file, to bound memory on large result sets.
+pub const MAX_SNIPPETS_PER_FILE: usize = 5;
+
+/// Snippet lines longer than this many characters are cut off.
+const MAX_SNIPPET_LINE_CHARS: usize = 200;
+
+/// Searches `reader` for `query` line by line, so only the current line and the
+/// `context_lines` lines before it are held in memory. Counts every non-overlapping
+/// occurrence; occurrences are not counted across line breaks. With `context_lines`
+/// set, the first `MAX_SNIPPETS_PER_FILE` matching lines are captured with that many
+/// lines before and after them. For a case-insensitive search, `query` must already
+/// be lowercase.
+///
+/// Fails with `InvalidData` on content that is not UTF-8, and stops with `Interrupted`
+/// as soon as `cancel_flag` is set, even in the middle of a large file.
+fn search_lines<R: BufRead>(
+    mut reader: R,
+    query: &str,
+    case_sensitive: bool,
+    context_lines: Option<usize>,
+    cancel_flag: &AtomicBool,
+) -> io::Result<FileMatches> {
+    let mut matches = FileMatches::default();
+    if query.is_empty() {
+        return Ok(matches);
+    }
+    let context = context_lines.unwrap_or(0);
+    let truncate = |line: &str| match line.char_indices().nth(MAX_SNIPPET_LINE_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_string(),
+    };
+    let mut before: VecDeque<String> = VecDeque::with_capacity(context);
+    let mut buffer = String::new();
+    let mut line_number = 0;
+
+    loop {
+        if cancel_flag.load(Ordering::Relaxed) {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        buffer.clear();
+        if reader.read_line(&mut buffer)? == 0 {
+            break;
+        }
+        line_number += 1;
+        let line = match buffer.strip_suffix('\n') {
+            Some(line) => line.strip_suffix('\r').unwrap_or(line),
+            None => &buffer,
+        };
+
+        // Snippets still short of their context take this line as context after them.
+        for snippet in matches.snippets.iter_mut().rev() {
+            if snippet.context_after.len() == context {
+                break;
+            }
+            snippet.context_after.push(truncate(line));
+        }
+
+        let occurrences = if case_sensitive {
+            line.matches(query).count()
+        } else {
+            line.to_lowercase().matches(query).count()
+        };
+        if occurrences > 0 {
+            matches.count += occurrences;
+            if context_lines.is_some() && matches.snippets.len() < MAX_SNIPPETS_PER_FILE {
+                matches.snippets.push(MatchSnippet {
+                    line_number,
+                    line: truncate(line),
+                    context_before: before.iter().cloned().collect(),
+                    context_after: Vec::new(),
+                });
+            }
+        }
+
+        if context > 0 {
+            if before.len() == context {
+                before.pop_front();
+            }
+            before.push_back(truncate(line));
+        }
+    }
+    Ok(matches)
+}
+
 //================================================================================================//
 //|                                   TASK IMPLEMENTATIONS                                       |//
 //================================================================================================//
@@ -200,39 +420,32 @@ pub async fn generation_task<P, G, T>(
     G: ContentGenerator + 'static,
     T: Tokenizer + 'static,
 {
-    let (selected, root, config, files_for_tree, is_fully_scanned) = {
-        let state_guard = state
-            .lock()
-            .expect("Mutex was poisoned. This should not happen.");
+    let (selected, root, config, files_for_tree, is_fully_scanned, target, clipboard) = {
+        let state_guard = lock_state(&state);
         (
-            get_selected_files_in_tree_order(&state_guard),
+            get_selected_files_in_generation_order(&state_guard),
             PathBuf::from(&state_guard.current_path),
             state_guard.config.clone(),
             state_guard.full_file_list.clone(),
             state_guard.is_fully_scanned,
+            state_guard.generation_target,
+            state_guard.generation_clipboard.clone(),
         )
     };
 
-    let items_for_tree = if config.remove_empty_directories && is_fully_scanned {
-        tracing::info!("🌳 Pruning empty directories from the generated tree.");
-        SearchEngine::remove_empty_directories(
-            files_for_tree.clone(),
-            &files_for_tree,
-            &HashSet::new(),
-        )
-        .0
-    } else {
-        files_for_tree
-    };
-
+    let items_for_tree = items_for_tree(&config, files_for_tree, is_fully_scanned);
     let result = content_generator
         .generate(
             &selected,
             &root,
-            config.include_tree_by_default,
+            config.output_tree_position(),
             items_for_tree,
-            config.tree_ignore_patterns,
+            config.tree_ignore_patterns.clone(),
+            config.tree_style(),
             config.use_relative_paths,
+            config.content_transform(),
+            config.output_format,
+            config.skip_unreadable_files,
         )
         .await;
 
@@ -242,573 +455,2840 @@ pub async fn generation_task<P, G, T>(
     };
 
     match result {
-        Ok(content) => {
-            let token_count = tokenizer.count_tokens(&content).await;
-            proxy.send_event(UserEvent::ShowGeneratedContent {
-                content,
-                token_count,
-            });
-            let mut state_guard = state.lock().expect("Mutex poisoned");
-            finalize_state(&mut state_guard);
+        Ok(GeneratedOutput::InMemory(output)) if target == GenerationTarget::Clipboard => {
+            copy_generated_output(
+                &proxy,
+                &state,
+                output,
+                selected,
+                &root,
+                &config,
+                &tokenizer,
+                clipboard.as_deref(),
+            )
+            .await;
+        }
+        Ok(GeneratedOutput::InMemory(output)) => {
+            publish_generated_output(&proxy, &state, output, selected, &root, &config, &tokenizer)
+                .await;
+        }
+        Ok(GeneratedOutput::Spilled(output)) => {
+            let delivered = deliver_spilled_output(
+                &proxy,
+                &state,
+                output,
+                selected,
+                &root,
+                &config,
+                &tokenizer,
+                target,
+                clipboard.as_deref(),
+            )
+            .await;
+            if let Err(e) = delivered {
+                tracing::error!("LOG: Finishing the streamed output failed: {}", e);
+                proxy.send_event(UserEvent::ShowError(e.to_string()));
+                let mut state_guard = lock_state(&state);
+                finalize_state(&mut state_guard);
+            }
         }
         Err(CoreError::Cancelled) => {
             tracing::info!("LOG: Generation task gracefully cancelled.");
-            let mut state_guard = state.lock().expect("Mutex poisoned");
-            state_guard.scan_progress.current_scanning_path = "Generation cancelled.".to_string();
+            let mut state_guard = lock_state(&state);
             finalize_state(&mut state_guard);
+            proxy.send_event(UserEvent::Status("Generation cancelled.".to_string()));
         }
         Err(e) => {
             tracing::error!("LOG: Generation task failed: {}", e);
             proxy.send_event(UserEvent::ShowError(e.to_string()));
-            let mut state_guard = state.lock().expect("Mutex poisoned");
+            let mut state_guard = lock_state(&state);
             finalize_state(&mut state_guard);
         }
     }
 }
 
-/// The core orchestration logic for the proactive, two-phase scan.
-pub async fn proactive_scan_task<P: EventProxy, S: Scanner>(
-    proxy: P,
-    state: Arc<Mutex<AppState>>,
-    path: PathBuf,
-    scanner: S,
+/// Re-sends the last generated output with the directory tree placed as currently
+/// configured, reusing its file blocks instead of reading the files again. The caller
+/// checks that the cached output is still current.
+pub async fn reframe_task<P, T>(proxy: P, state: Arc<Mutex<AppState>>, tokenizer: T)
+where
+    P: EventProxy,
+    T: Tokenizer + 'static,
+{
+    let (cached, root, config, items_for_tree) = {
+        let state_guard = lock_state(&state);
+        let config = state_guard.config.clone();
+        let items_for_tree = items_for_tree(
+            &config,
+            state_guard.full_file_list.clone(),
+            state_guard.is_fully_scanned,
+        );
+        (
+            state_guard.last_generated.clone(),
+            PathBuf::from(&state_guard.current_path),
+            config,
+            items_for_tree,
+        )
+    };
+    let Some(cached) = cached else {
+        tracing::warn!("No generated output to re-frame.");
+        let mut state_guard = lock_state(&state);
+        state_guard.is_generating = false;
+        proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
+            &state_guard,
+        ))));
+        return;
+    };
+
+    let output = FileHandler::reframe_output(
+        &cached.output,
+        cached.selection.len(),
+        config.output_tree_position(),
+        &items_for_tree,
+        &root,
+        &config.tree_ignore_patterns,
+        config.tree_style(),
+        config.output_format,
+    );
+    publish_generated_output(
+        &proxy,
+        &state,
+        output,
+        cached.selection,
+        &root,
+        &config,
+        &tokenizer,
+    )
+    .await;
+}
+
+/// Counts the tokens of a successfully generated `output`, adds the index and manifest
+/// if configured and sends the result to the UI. Records the output as the latest
+/// generation and in the generation history, and ends the generating state.
+async fn publish_generated_output<P: EventProxy, T: Tokenizer>(
+    proxy: &P,
+    state: &Arc<Mutex<AppState>>,
+    output: ConcatenatedOutput,
+    selected: Vec<PathBuf>,
+    root: &Path,
+    config: &AppConfig,
+    tokenizer: &T,
 ) {
-    // VET: RAII Guard now also takes the proxy to send a final state update.
-    struct ScanGuard<'a, P: EventProxy> {
-        state: &'a Arc<Mutex<AppState>>,
-        proxy: &'a P,
-    }
-    impl<'a, P: EventProxy> Drop for ScanGuard<'a, P> {
-        fn drop(&mut self) {
-            let mut state = self.state.lock().unwrap();
-            // Only update and notify if the scan was actually running.
-            // This prevents sending a redundant event if the scan finished cleanly.
-            if state.is_scanning {
-                state.is_scanning = false;
-                state.scan_task = None;
-                self.proxy
-                    .send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(&state))));
-            }
+    let (content, snapshot) =
+        finish_generated_output(state, &output, &selected, root, config, tokenizer).await;
+    let chunk_bytes = config
+        .stream_generated_content
+        .then_some(GENERATION_CHUNK_BYTES);
+    let head = config
+        .preview_generated_head_lines
+        .and_then(|max_lines| preview_head(&content, max_lines));
+    let content: Arc<str> = content.into();
+    lock_state(state).record_generation(content.clone(), snapshot.token_count, selected.len());
+
+    let full_content = match head {
+        Some(head) => {
+            send_generated_content(proxy, head, snapshot.token_count, chunk_bytes);
+            Some(content)
+        }
+        None => {
+            send_generated_content(
+                proxy,
+                content.to_string(),
+                snapshot.token_count,
+                chunk_bytes,
+            );
+            None
         }
-    }
-    let _scan_guard = ScanGuard {
-        state: &state,
-        proxy: &proxy,
     };
 
-    // --- Phase 1: Shallow Scan ---
-    let progress_proxy_shallow = proxy.clone();
-    let scan_result_shallow = scanner
-        .scan(
-            &path,
-            Some(1),
-            Box::new(move |p| progress_proxy_shallow.send_event(UserEvent::ScanProgress(p))),
-        )
-        .await;
+    let mut state_guard = lock_state(state);
+    state_guard.previous_generation = state_guard.latest_generation.take();
+    state_guard.latest_generation = Some(snapshot);
+    state_guard.last_generated = Some(GeneratedBody::new(output, selected, config));
+    state_guard.generated_content = full_content.map(HeldOutput::Text);
+    state_guard.is_generating = false;
+    proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
+        &state_guard,
+    ))));
+}
 
-    if state
-        .lock()
-        .unwrap()
-        .scan_cancellation_flag
-        .load(Ordering::SeqCst)
-    {
-        tracing::info!("Scan cancelled after shallow scan phase.");
-        return; // _scan_guard is dropped here, cleaning up the state and notifying.
+/// Counts the tokens of a successfully generated `output`, adds the index and manifest
+/// if configured and copies the result to the clipboard. Only the token count is sent to
+/// the UI. The preview is left alone, so the output is not kept for re-framing, but it
+/// is added to the generation history.
+#[allow(clippy::too_many_arguments)] // Mirrors `publish_generated_output`, plus the clipboard
+async fn copy_generated_output<P: EventProxy, T: Tokenizer>(
+    proxy: &P,
+    state: &Arc<Mutex<AppState>>,
+    output: ConcatenatedOutput,
+    selected: Vec<PathBuf>,
+    root: &Path,
+    config: &AppConfig,
+    tokenizer: &T,
+    clipboard: Option<&dyn ClipboardService>,
+) {
+    let (content, snapshot) =
+        finish_generated_output(state, &output, &selected, root, config, tokenizer).await;
+    copy_finished_output(proxy, state, &content, snapshot, selected.len(), clipboard);
+}
+
+/// Copies a finished `content` to the clipboard and records it, see
+/// `copy_generated_output`.
+fn copy_finished_output<P: EventProxy>(
+    proxy: &P,
+    state: &Arc<Mutex<AppState>>,
+    content: &str,
+    snapshot: GenerationSnapshot,
+    file_count: usize,
+    clipboard: Option<&dyn ClipboardService>,
+) {
+    let token_count = snapshot.token_count;
+    let copied = match clipboard {
+        Some(clipboard) => clipboard.copy_text(content),
+        None => Err(anyhow::anyhow!(
+            "No clipboard was given to copy the output to"
+        )),
+    };
+    match copied {
+        Ok(()) => proxy.send_event(UserEvent::OutputCopied { token_count }),
+        Err(e) => proxy.send_event(UserEvent::ShowError(format!("{e:#}"))),
     }
 
-    match scan_result_shallow {
-        Ok((files, patterns)) => {
-            let mut s = state.lock().unwrap();
-            s.full_file_list = files;
-            s.active_ignore_patterns = patterns;
-            s.loaded_dirs.insert(path.clone());
-            filtering::apply_filters(&mut s);
-            proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(&s))));
+    let mut state_guard = lock_state(state);
+    state_guard.record_generation(content.into(), token_count, file_count);
+    state_guard.previous_generation = state_guard.latest_generation.take();
+    state_guard.latest_generation = Some(snapshot);
+    state_guard.is_generating = false;
+    proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
+        &state_guard,
+    ))));
+}
+
+/// Finishes a streamed generation, see `AppConfig::stream_generation`. The output stays
+/// in its temporary file: only its head is sent to the preview, and saving or copying
+/// reads the file. An output that fits in the preview is read back and kept like a
+/// generated one, so the directory tree can still be toggled without reading the
+/// selected files again. With the clipboard as target, the output is read into memory
+/// to copy it.
+#[allow(clippy::too_many_arguments)] // Mirrors `copy_generated_output`, plus the target
+async fn deliver_spilled_output<P: EventProxy, T: Tokenizer>(
+    proxy: &P,
+    state: &Arc<Mutex<AppState>>,
+    output: SpilledOutput,
+    selected: Vec<PathBuf>,
+    root: &Path,
+    config: &AppConfig,
+    tokenizer: &T,
+    target: GenerationTarget,
+    clipboard: Option<&dyn ClipboardService>,
+) -> Result<(), CoreError> {
+    let (finished, snapshot) =
+        finish_spilled_output(state, &output, &selected, root, config, tokenizer).await?;
+    let document = finished.as_ref().unwrap_or(&output);
+    if target == GenerationTarget::Clipboard {
+        let content = document.read_all()?;
+        copy_finished_output(proxy, state, &content, snapshot, selected.len(), clipboard);
+        return Ok(());
+    }
+
+    let io_error = |e: std::io::Error| CoreError::Io(e.to_string(), document.file.to_path_buf());
+    let max_lines = config
+        .preview_generated_head_lines
+        .unwrap_or(SPILLED_PREVIEW_HEAD_LINES);
+    let head = preview_head_of_file(&document.file, max_lines).map_err(io_error)?;
+    let chunk_bytes = config
+        .stream_generated_content
+        .then_some(GENERATION_CHUNK_BYTES);
+    let token_count = snapshot.token_count;
+    let (last_generated, held) = match head {
+        Some(head) => {
+            let content_hash = std::fs::File::open(&document.file)
+                .and_then(GenerationRecord::hash_reader)
+                .map_err(io_error)?;
+            lock_state(state).record_spilled_generation(
+                content_hash,
+                document.length,
+                token_count,
+                selected.len(),
+            );
+            send_generated_content(proxy, head, token_count, chunk_bytes);
+            let file = finished.map_or(output.file, |finished| finished.file);
+            let held = HeldOutput::Spilled {
+                file: Arc::new(file),
+                content_hash,
+            };
+            (None, Some(held))
         }
-        Err(e) => {
-            handle_scan_error(e, &state, &proxy);
-            return; // _scan_guard is dropped here.
+        None => {
+            let content = document.read_all()?;
+            // The cached output is the one without the index and the manifest.
+            let generated = ConcatenatedOutput {
+                content: match finished {
+                    Some(_) => output.read_all()?,
+                    None => content.clone(),
+                },
+                file_ranges: output.file_ranges,
+                body: output.body,
+            };
+            let content: Arc<str> = content.into();
+            lock_state(state).record_generation(content.clone(), token_count, selected.len());
+            send_generated_content(proxy, content.to_string(), token_count, chunk_bytes);
+            (Some(GeneratedBody::new(generated, selected, config)), None)
         }
+    };
+
+    let mut state_guard = lock_state(state);
+    state_guard.previous_generation = state_guard.latest_generation.take();
+    state_guard.latest_generation = Some(snapshot);
+    state_guard.last_generated = last_generated;
+    state_guard.generated_content = held;
+    state_guard.is_generating = false;
+    proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
+        &state_guard,
+    ))));
+    Ok(())
+}
+
+/// Counts the tokens of `output` and adds the index and the manifest if configured.
+/// Both are counted in the token total. Returns the final content together with its
+/// snapshot for `latest_generation`.
+async fn finish_generated_output<T: Tokenizer>(
+    state: &Arc<Mutex<AppState>>,
+    output: &ConcatenatedOutput,
+    selected: &[PathBuf],
+    root: &Path,
+    config: &AppConfig,
+    tokenizer: &T,
+) -> (String, GenerationSnapshot) {
+    let format = config.output_format;
+    let (mut token_count, file_tokens) = count_tokens_cached(
+        output,
+        &config.content_transform(),
+        format,
+        state,
+        tokenizer,
+    )
+    .await;
+    // Hashed before the index and the manifest shift the file blocks' ranges.
+    let mut snapshot = GenerationSnapshot::from_output(output, token_count);
+    let mut content = output.content.clone();
+    if config.include_index {
+        let index = FileHandler::index(
+            output,
+            root,
+            config.use_relative_paths,
+            &file_tokens,
+            format,
+        );
+        token_count += tokenizer.count_tokens(&index).await;
+        // Inserted first, as the manifest moves the start of the body.
+        content.insert_str(output.body.start, &index);
+    }
+    if config.include_manifest {
+        let manifest = FileHandler::manifest(selected, root, token_count, format);
+        token_count += tokenizer.count_tokens(&manifest).await;
+        FileHandler::insert_manifest(&mut content, &manifest, format);
     }
+    snapshot.token_count = token_count;
+    (content, snapshot)
+}
 
-    // --- Phase 2: Deep Background Scan (Indexing) ---
-    let progress_proxy_deep = proxy.clone();
-    let scan_result_deep = scanner
-        .scan(
-            &path,
-            None,
-            Box::new(move |p| progress_proxy_deep.send_event(UserEvent::ScanProgress(p))),
-        )
-        .await;
+/// Like `finish_generated_output` for a streamed output. The file blocks are read one
+/// at a time to count and hash them, and the index and the manifest are written into
+/// a copy of the file, which is returned if either is configured.
+async fn finish_spilled_output<T: Tokenizer>(
+    state: &Arc<Mutex<AppState>>,
+    output: &SpilledOutput,
+    selected: &[PathBuf],
+    root: &Path,
+    config: &AppConfig,
+    tokenizer: &T,
+) -> Result<(Option<SpilledOutput>, GenerationSnapshot), CoreError> {
+    let format = config.output_format;
+    let transform = config.content_transform();
+    let model = tokenizer.model().to_string();
+    let mut reader = output.reader()?;
+    let mut skeleton = String::new();
+    let mut snapshot = GenerationSnapshot::default();
+    let mut index_entries = Vec::new();
+
+    for (path, range) in &output.file_ranges {
+        skeleton.push_str(&reader.read_to(range.start)?);
+        let block = reader.read_to(range.end)?;
+
+        let probe = token_cache_probe(path, &model, &transform, format);
+        let token_count = match cached_token_count(state, path, probe.as_ref()) {
+            Some(count) => count,
+            None => {
+                let count = tokenizer.count_tokens(&block).await;
+                store_token_count(state, path, probe, &block, count);
+                count
+            }
+        };
+        snapshot.token_count += token_count;
+        snapshot
+            .file_hashes
+            .insert(path.clone(), GenerationSnapshot::hash_block(&block));
+        if config.include_index {
+            let display_path = FileHandler::display_path(path, root, config.use_relative_paths)
+                .unwrap_or_else(|_| path.display().to_string());
+            index_entries.push(IndexEntry::new(display_path, &block, token_count));
+        }
+    }
+    skeleton.push_str(&reader.read_to(output.length)?);
+    snapshot.token_count += tokenizer.count_tokens(&skeleton).await;
 
-    if state
-        .lock()
-        .unwrap()
-        .scan_cancellation_flag
-        .load(Ordering::SeqCst)
-    {
-        tracing::info!("Scan cancelled during deep scan phase.");
-        return; // _scan_guard is dropped here.
+    let index = config.include_index.then(|| format.index(&index_entries));
+    if let Some(index) = &index {
+        snapshot.token_count += tokenizer.count_tokens(index).await;
     }
+    let manifest = if config.include_manifest {
+        let manifest = FileHandler::manifest(selected, root, snapshot.token_count, format);
+        snapshot.token_count += tokenizer.count_tokens(&manifest).await;
+        Some(manifest)
+    } else {
+        None
+    };
 
-    match scan_result_deep {
-        Ok((files, patterns)) => {
-            let mut s = state.lock().unwrap();
-            let new_file_paths: HashSet<_> = files.iter().map(|f| f.path.clone()).collect();
-            s.selected_files.retain(|p| new_file_paths.contains(p));
-            s.full_file_list = files;
-            s.active_ignore_patterns = patterns;
-            s.is_fully_scanned = true;
-            s.loaded_dirs = s
-                .full_file_list
-                .iter()
-                .filter(|i| i.is_directory)
-                .map(|i| i.path.clone())
-                .collect();
-            filtering::apply_filters(&mut s);
+    // The manifest goes first, as `insert_manifest` places it after the document start.
+    let manifest_offset = format.document_start().len().min(output.length);
+    let insertions: Vec<(usize, &str)> = [
+        manifest
+            .as_deref()
+            .map(|manifest| (manifest_offset, manifest)),
+        index.as_deref().map(|index| (output.body.start, index)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let finished = if insertions.is_empty() {
+        None
+    } else {
+        Some(output.with_insertions(&insertions)?)
+    };
+    Ok((finished, snapshot))
+}
 
-            // VET: We now set the final state here and the guard is just for cleanup on panics/cancellations.
-            s.is_scanning = false;
-            s.scan_task = None;
-            s.scan_progress.current_scanning_path = format!(
-                "Indexing complete. Found {} visible items.",
-                s.filtered_file_list.len()
-            );
+/// Returns the first `max_lines` lines of `content` followed by a note on how many
+/// lines were left out, or `None` if `content` is not longer than that.
+pub(crate) fn preview_head(content: &str, max_lines: usize) -> Option<String> {
+    let (end, _) = content.match_indices('\n').nth(max_lines.checked_sub(1)?)?;
+    let hidden_lines = content[end + 1..].lines().count();
+    (hidden_lines > 0).then(|| head_with_note(&content[..end], hidden_lines))
+}
 
-            proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(&s))));
-        }
-        Err(e) => {
-            handle_scan_error(e, &state, &proxy);
-        }
+/// Like `preview_head`, but reads the output from the file at `path`, holding no more
+/// than the head in memory.
+fn preview_head_of_file(path: &Path, max_lines: usize) -> io::Result<Option<String>> {
+    if max_lines == 0 {
+        return Ok(None);
+    }
+    let mut reader = BufReader::new(std::fs::File::open(path)?);
+    let mut head = Vec::new();
+    for _ in 0..max_lines {
+        reader.read_until(b'\n', &mut head)?;
+    }
+    if head.pop() != Some(b'\n') {
+        return Ok(None);
+    }
+    let mut hidden_lines = 0;
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        hidden_lines += 1;
+        line.clear();
     }
+    if hidden_lines == 0 {
+        return Ok(None);
+    }
+    let head =
+        String::from_utf8(head).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(Some(head_with_note(&head, hidden_lines)))
 }
 
-/// Initiates a proactive, two-phase directory scan.
-pub fn start_scan_on_path<P: EventProxy>(
-    path: PathBuf,
-    proxy: P,
-    state: Arc<Mutex<AppState>>,
-    preserve_state: bool,
+/// `head` followed by a note that `hidden_lines` more lines are not shown.
+fn head_with_note(head: &str, hidden_lines: usize) -> String {
+    format!(
+        "{head}\n\n[… {hidden_lines} more lines not shown. Save or copy to get the full output.]\n"
+    )
+}
+
+/// Sends the generated content to the UI. With `chunk_bytes` set and content larger
+/// than that, it is streamed as `GenerationStart`, one `GenerationChunk` per piece and
+/// `GenerationEnd`; otherwise a single `ShowGeneratedContent` event is sent.
+fn send_generated_content<P: EventProxy>(
+    proxy: &P,
+    content: String,
+    token_count: usize,
+    chunk_bytes: Option<usize>,
 ) {
-    let proxy_clone = proxy.clone();
-    let state_clone = state.clone();
-    tokio::spawn(async move {
-        // 1. First, check if the path exists at all.
-        if !path.exists() {
-            proxy.send_event(UserEvent::ShowError(format!(
-                "Path does not exist: {}",
-                path.display()
-            )));
-            return;
+    let Some(chunk_bytes) = chunk_bytes.filter(|&max| content.len() > max) else {
+        proxy.send_event(UserEvent::ShowGeneratedContent {
+            content,
+            token_count,
+        });
+        return;
+    };
+
+    proxy.send_event(UserEvent::GenerationStart);
+    let mut rest = content.as_str();
+    while !rest.is_empty() {
+        // Never split inside a UTF-8 sequence; a chunk holds at least one character.
+        let mut end = chunk_bytes.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end += 1;
         }
+        let (chunk, tail) = rest.split_at(end);
+        proxy.send_event(UserEvent::GenerationChunk {
+            data: chunk.to_string(),
+        });
+        rest = tail;
+    }
+    proxy.send_event(UserEvent::GenerationEnd { token_count });
+}
 
-        // 2. If it exists, determine the correct directory to scan.
-        let directory_path = if path.is_dir() {
-            path
-        } else {
-            // It's a file, so we take its parent. This is safe now because we know the path exists.
-            path.parent().map(|p| p.to_path_buf()).unwrap_or(path) // Fallback just in case of root paths like "/"
+/// Counts the tokens of a generated output, reusing cached per-file counts.
+///
+/// Each file's content block is counted separately and cached by path together with
+/// its size, modification time, tokenizer model, content transform and output format.
+/// Everything outside the file blocks (headers, tree, separators) is re-counted on
+/// every call. Returns the total and the count of every file block, in output order.
+async fn count_tokens_cached<T: Tokenizer>(
+    output: &ConcatenatedOutput,
+    transform: &ContentTransform,
+    format: OutputFormat,
+    state: &Arc<Mutex<AppState>>,
+    tokenizer: &T,
+) -> (usize, Vec<usize>) {
+    let model = tokenizer.model().to_string();
+    let mut skeleton = String::new();
+    let mut cursor = 0;
+    let mut total = 0;
+    let mut file_tokens = Vec::with_capacity(output.file_ranges.len());
+
+    for (path, range) in &output.file_ranges {
+        skeleton.push_str(&output.content[cursor..range.start]);
+        cursor = range.end;
+
+        let probe = token_cache_probe(path, &model, transform, format);
+        let token_count = match cached_token_count(state, path, probe.as_ref()) {
+            Some(count) => count,
+            None => {
+                let block = &output.content[range.clone()];
+                let count = tokenizer.count_tokens(block).await;
+                store_token_count(state, path, probe, block, count);
+                count
+            }
         };
+        total += token_count;
+        file_tokens.push(token_count);
+    }
 
-        let new_cancel_flag = {
-            let mut state_guard = state.lock().expect("Mutex was poisoned");
-            if !preserve_state {
-                state_guard.reset_directory_state();
-            } else {
-                state_guard.cancel_current_scan();
-            }
-            state_guard.current_path = directory_path.to_string_lossy().to_string();
-            state_guard.config.last_directory = Some(directory_path.clone());
-            crate::config::settings::save_config(&state_guard.config, None).ok();
-            state_guard.is_scanning = true;
-            state_guard.is_fully_scanned = false;
-            let flag = Arc::new(AtomicBool::new(false));
-            state_guard.scan_cancellation_flag = flag.clone();
-            flag
-        };
-        proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
-            &state.lock().unwrap(),
-        ))));
-        let ignore_patterns = state.lock().unwrap().config.ignore_patterns.clone();
-        let scanner = RealScanner {
-            ignore_patterns,
-            cancel_flag: new_cancel_flag,
-        };
-        let handle = tokio::spawn(async move {
-            proactive_scan_task(proxy_clone, state_clone, directory_path, scanner).await;
-        });
-        let mut state_guard = state.lock().expect("Mutex was poisoned");
-        state_guard.scan_task = Some(handle);
-    });
+    skeleton.push_str(&output.content[cursor..]);
+    (total + tokenizer.count_tokens(&skeleton).await, file_tokens)
 }
 
-/// Helper to handle scan errors consistently.
-fn handle_scan_error<P: EventProxy>(error: CoreError, state: &Arc<Mutex<AppState>>, proxy: &P) {
-    tracing::error!("LOG: TASK:: Scan finished with error: {}", error);
-    let mut state_lock = state.lock().expect("Mutex was poisoned");
-    if !state_lock.is_scanning {
-        return;
-    }
-    state_lock.scan_progress.current_scanning_path = format!("Scan failed: {error}");
-    state_lock.is_scanning = false;
-    state_lock.scan_task = None;
-    let event = UserEvent::StateUpdate(Box::new(generate_ui_state(&state_lock)));
-    proxy.send_event(event);
+/// Builds the cache entry `path` would have under `model`, `transform` and `format`,
+/// with placeholder counts. Returns `None` if the file cannot be inspected.
+fn token_cache_probe(
+    path: &Path,
+    model: &str,
+    transform: &ContentTransform,
+    format: OutputFormat,
+) -> Option<TokenCacheEntry> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some(TokenCacheEntry {
+        size: metadata.len(),
+        modified: metadata.modified().ok(),
+        model: model.to_string(),
+        transform: transform.clone(),
+        format,
+        token_count: 0,
+        block_bytes: 0,
+        block_lines: 0,
+    })
 }
 
-/// Initiates a scan for a specific subdirectory level (lazy loading).
-pub fn start_lazy_load_scan<P: EventProxy>(
-    path: PathBuf,
-    proxy: P,
-    state: Arc<Mutex<AppState>>,
-    completion_signal: Option<oneshot::Sender<()>>,
+/// Returns the cache entry for `path` if it is still valid for `probe`.
+fn cached_token_entry(
+    state: &Arc<Mutex<AppState>>,
+    path: &Path,
+    probe: Option<&TokenCacheEntry>,
+) -> Option<TokenCacheEntry> {
+    let probe = probe?;
+    let state_guard = lock_state(state);
+    state_guard
+        .token_cache
+        .get(path)
+        .filter(|entry| entry.matches(probe))
+        .cloned()
+}
+
+/// Returns the cached token count for `path` if it is still valid for `probe`.
+fn cached_token_count(
+    state: &Arc<Mutex<AppState>>,
+    path: &Path,
+    probe: Option<&TokenCacheEntry>,
+) -> Option<usize> {
+    cached_token_entry(state, path, probe).map(|entry| entry.token_count)
+}
+
+/// Caches `token_count` for `path`, whose content block is `block`, under the file
+/// version described by `probe`.
+fn store_token_count(
+    state: &Arc<Mutex<AppState>>,
+    path: &Path,
+    probe: Option<TokenCacheEntry>,
+    block: &str,
+    token_count: usize,
 ) {
-    tokio::spawn(async move {
-        let (ignore_patterns, is_scanning) = {
-            let state_guard = state
-                .lock()
-                .expect("Mutex was poisoned. This should not happen.");
-            (
-                state_guard.config.ignore_patterns.clone(),
-                state_guard.is_scanning,
-            )
-        };
-        if is_scanning {
-            tracing::warn!("Attempted to lazy load while a full scan was in progress. Ignoring.");
-            return;
-        }
-        let new_cancel_flag = Arc::new(AtomicBool::new(false));
-        let scanner = RealScanner {
-            ignore_patterns,
-            cancel_flag: new_cancel_flag.clone(),
-        };
-        let proxy_clone = proxy.clone();
-        let state_clone = state.clone();
-        tracing::info!("LOG: Spawning new lazy_load_task for path: {:?}", path);
-        tokio::spawn(async move {
-            lazy_load_task(path, proxy_clone, state_clone, scanner, completion_signal).await;
-        });
-    });
+    if let Some(entry) = probe {
+        lock_state(state).token_cache.insert(
+            path.to_path_buf(),
+            TokenCacheEntry {
+                token_count,
+                block_bytes: block.len(),
+                block_lines: block.lines().count(),
+                ..entry
+            },
+        );
+    }
 }
 
-/// The asynchronous task for scanning a single directory level and appending the results.
-async fn lazy_load_task<P: EventProxy, S: Scanner>(
-    path_to_load: PathBuf,
+/// Estimates the token count of the current selection without building the output.
+///
+/// File contents are read and counted one at a time (or taken from the token cache),
+/// so at most one file is held in memory. Headers and the optional tree are counted
+/// from the same layout `FileHandler` uses, so the result closely matches a real
+/// generation. The task checks `cancel_flag` before each file.
+pub async fn estimation_task<P, T>(
     proxy: P,
     state: Arc<Mutex<AppState>>,
-    scanner: S,
-    completion_signal: Option<oneshot::Sender<()>>,
-) {
-    // The scan call remains the same.
-    let scan_result = scanner.scan(&path_to_load, Some(1), Box::new(|_| {})).await;
-
-    match scan_result {
-        Ok((new_items, new_active_patterns)) => {
-            tracing::info!(
-                "LOG: TASK:: Lazy load successful. {} new items found for {:?}.",
-                new_items.len(),
-                path_to_load
-            );
-
-            // Instead of using the `with_state_and_notify` helper, we now manually
-            // lock the state and explicitly send the notification event.
-            // This aligns with the pattern used in other modern tasks in this file.
-            let mut state_guard = state.lock().expect("Mutex was poisoned");
-
-            state_guard.loaded_dirs.insert(path_to_load.clone());
-            state_guard.expanded_dirs.insert(path_to_load);
-            state_guard
-                .active_ignore_patterns
-                .extend(new_active_patterns);
-
-            let existing_paths: HashSet<PathBuf> = state_guard
-                .full_file_list
-                .iter()
-                .map(|item| item.path.clone())
-                .collect();
-
-            for item in new_items {
-                if !existing_paths.contains(&item.path) {
-                    state_guard.full_file_list.push(item);
-                }
-            }
+    tokenizer: T,
+    cancel_flag: Arc<AtomicBool>,
+) where
+    P: EventProxy,
+    T: Tokenizer + 'static,
+{
+    let (selected, root, config, files_for_tree, is_fully_scanned) = {
+        let state_guard = lock_state(&state);
+        (
+            get_selected_files_in_tree_order(&state_guard),
+            PathBuf::from(&state_guard.current_path),
+            state_guard.config.clone(),
+            state_guard.full_file_list.clone(),
+            state_guard.is_fully_scanned,
+        )
+    };
 
-            // Apply other filters to the now-updated list.
-            filtering::apply_filters(&mut state_guard);
+    let transform = config.content_transform();
+    let items_for_tree = if config.remove_empty_directories && is_fully_scanned {
+        SearchEngine::remove_empty_directories(
+            files_for_tree.clone(),
+            &files_for_tree,
+            &HashSet::new(),
+        )
+        .0
+    } else {
+        files_for_tree
+    };
 
-            // Explicitly send the state update event. This fixes the test panic.
-            proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
-                &state_guard,
-            ))));
+    let result = estimate_selection_tokens(
+        &selected,
+        &root,
+        &config,
+        &items_for_tree,
+        transform,
+        &state,
+        &tokenizer,
+        &cancel_flag,
+    )
+    .await;
+
+    let mut state_guard = lock_state(&state);
+    let mut cancelled = false;
+    match result {
+        Ok((tokens, files)) => {
+            proxy.send_event(UserEvent::TokenEstimate { tokens, files });
+        }
+        Err(CoreError::Cancelled) => {
+            tracing::info!("LOG: Token estimate cancelled.");
+            cancelled = true;
         }
         Err(e) => {
-            // Error handling remains the same.
-            tracing::error!("LOG: TASK:: Lazy load failed for {:?}: {}", path_to_load, e);
-            proxy.send_event(UserEvent::ShowError(format!(
-                "Failed to load directory {}: {}",
-                path_to_load.display(),
-                e
-            )));
+            tracing::error!("LOG: Token estimate failed: {}", e);
+            proxy.send_event(UserEvent::ShowError(e.to_string()));
         }
     }
-
-    // After all work is done, send the completion signal if one was provided.
-    if let Some(signal) = completion_signal {
-        let _ = signal.send(());
+    state_guard.is_estimating = false;
+    state_guard.estimation_task = None;
+    proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
+        &state_guard,
+    ))));
+    if cancelled {
+        proxy.send_event(UserEvent::Status("Estimate cancelled.".to_string()));
     }
 }
 
-/// Performs a content search across all non-binary files.
-pub async fn search_in_files<P: EventProxy, S: FileSearcher>(
+/// How many uncached files [`tree_stats_task`] reads in parallel between checks of
+/// its cancel flag.
+const TREE_STATS_READ_BATCH: usize = 64;
+
+/// Sends [`tree_stats`] for all scanned files with the tokens of every text file
+/// counted by `tokenizer`, as the file would be rendered into the output. Counts come
+/// from the token cache where they can; the other files are read in parallel batches
+/// and their counts cached. Files over [`MAX_CONTENT_BYTES`] and files that cannot be
+/// read count as zero tokens. Nothing is sent once `cancel_flag` is set.
+pub async fn tree_stats_task<P, T>(
     proxy: P,
     state: Arc<Mutex<AppState>>,
-    searcher: S,
-) {
-    let (files_to_search, query, case_sensitive) = {
-        let mut state_guard = state
-            .lock()
-            .expect("Mutex was poisoned. This should not happen.");
-        if state_guard.content_search_query.is_empty() {
-            state_guard.content_search_results.clear();
-            filtering::apply_filters(&mut state_guard);
-            let event = UserEvent::StateUpdate(Box::new(generate_ui_state(&state_guard)));
-            proxy.send_event(event);
-            return;
-        }
+    tokenizer: T,
+    cancel_flag: Arc<AtomicBool>,
+) where
+    P: EventProxy,
+    T: Tokenizer + 'static,
+{
+    let (files, config) = {
+        let state_guard = lock_state(&state);
         (
             state_guard.full_file_list.clone(),
-            state_guard.content_search_query.clone(),
-            state_guard.config.case_sensitive_search,
+            state_guard.config.clone(),
         )
     };
-    let matching_paths = searcher
-        .search(files_to_search, &query, case_sensitive)
-        .await;
-    let mut state_guard = state
-        .lock()
-        .expect("Mutex was poisoned. This should not happen.");
-    state_guard.content_search_results = matching_paths;
-    filtering::apply_filters(&mut state_guard);
-    auto_expand_for_matches(&mut state_guard);
-    let event = UserEvent::StateUpdate(Box::new(generate_ui_state(&state_guard)));
-    proxy.send_event(event);
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::app::view_model::UiState;
-    use crate::config::AppConfig;
-    use crate::utils::test_helpers::running_as_root;
-    use std::time::Duration;
-    use tempfile::{tempdir, TempDir};
-    use tokio::sync::{mpsc, oneshot};
-
-    //============================================================================================//
-    //|                                       TEST HARNESS                                       |//
-    //============================================================================================//
-    #[derive(Clone)]
-    struct TestEventProxy {
-        sender: mpsc::UnboundedSender<UserEvent>,
+    let transform = config.content_transform();
+    let format = config.output_format;
+    let model = tokenizer.model().to_string();
+
+    let mut token_counts = HashMap::new();
+    let mut uncached = Vec::new();
+    for item in files
+        .iter()
+        .filter(|item| !item.is_directory && !item.is_binary && item.size <= MAX_CONTENT_BYTES)
+    {
+        let probe = token_cache_probe(&item.path, &model, &transform, format);
+        match cached_token_count(&state, &item.path, probe.as_ref()) {
+            Some(count) => {
+                token_counts.insert(item.path.clone(), count);
+            }
+            None => uncached.push((item.path.clone(), probe)),
+        }
     }
-    impl EventProxy for TestEventProxy {
-        fn send_event(&self, event: UserEvent) {
-            self.sender.send(event).expect("Test receiver dropped");
+
+    while !uncached.is_empty() {
+        if cancel_flag.load(Ordering::SeqCst) {
+            return;
+        }
+        let batch: Vec<_> = uncached
+            .drain(..uncached.len().min(TREE_STATS_READ_BATCH))
+            .collect();
+        let paths: Vec<PathBuf> = batch.iter().map(|(path, _)| path.clone()).collect();
+        let read_transform = transform.clone();
+        let max_threads = config.max_threads;
+        let contents: Vec<Option<String>> = tokio::task::spawn_blocking(move || {
+            thread_pool::install(max_threads, || {
+                paths
+                    .par_iter()
+                    .map(|path| {
+                        FileHandler::read_rendered_content(path, &read_transform, format)
+                            .ok()
+                            .map(|(content, _)| content)
+                    })
+                    .collect()
+            })
+        })
+        .await
+        .unwrap_or_default();
+        for ((path, probe), content) in batch.into_iter().zip(contents) {
+            let Some(content) = content else {
+                continue;
+            };
+            let count = tokenizer.count_tokens(&content).await;
+            store_token_count(&state, &path, probe, &content, count);
+            token_counts.insert(path, count);
         }
     }
-    struct TestHarness {
-        state: Arc<Mutex<AppState>>,
-        proxy: TestEventProxy,
-        event_rx: mpsc::UnboundedReceiver<UserEvent>,
-        _temp_dir: TempDir,
-        root_path: PathBuf,
+
+    if cancel_flag.load(Ordering::SeqCst) {
+        return;
     }
-    impl TestHarness {
-        fn new() -> Self {
-            let temp_dir = tempdir().expect("Failed to create temp dir");
-            let root_path = temp_dir.path().to_path_buf();
-            let (tx, rx) = mpsc::unbounded_channel();
-            let proxy = TestEventProxy { sender: tx };
-            let mut state = AppState::default();
-            state.config = AppConfig::default();
-            state.current_path = root_path.to_string_lossy().to_string();
-            Self {
-                state: Arc::new(Mutex::new(state)),
-                proxy,
-                event_rx: rx,
-                _temp_dir: temp_dir,
-                root_path,
-            }
+    proxy.send_event(UserEvent::TreeStats(tree_stats(
+        &files,
+        Some(&token_counts),
+    )));
+}
+
+/// Sums the per-file and layout token counts for `selected`. Returns the total and
+/// the number of files counted.
+#[allow(clippy::too_many_arguments)] // Mirrors the inputs of a real generation
+async fn estimate_selection_tokens<T: Tokenizer>(
+    selected: &[PathBuf],
+    root: &Path,
+    config: &AppConfig,
+    items_for_tree: &[FileItem],
+    transform: ContentTransform,
+    state: &Arc<Mutex<AppState>>,
+    tokenizer: &T,
+    cancel_flag: &AtomicBool,
+) -> Result<(usize, usize), CoreError> {
+    let model = tokenizer.model().to_string();
+    let format = config.output_format;
+    let mut skeleton = FileHandler::output_preamble(
+        selected.len(),
+        config.output_tree_position(),
+        items_for_tree,
+        root,
+        &config.tree_ignore_patterns,
+        config.tree_style(),
+        format,
+    );
+    let mut total = 0;
+    let mut files = 0;
+    let mut index_entries = Vec::new();
+
+    for path in selected {
+        if cancel_flag.load(Ordering::SeqCst) {
+            return Err(CoreError::Cancelled);
         }
-        async fn get_n_events(&mut self, n: usize) -> Vec<UserEvent> {
-            let mut events = Vec::with_capacity(n);
-            for _ in 0..n {
-                match tokio::time::timeout(Duration::from_secs(2), self.event_rx.recv()).await {
-                    Ok(Some(event)) => events.push(event),
-                    _ => break,
-                }
-            }
-            events
+        if path.is_dir() {
+            continue;
         }
-        async fn get_last_state_update(&mut self) -> Option<UiState> {
-            let mut last_state = None;
-            while let Ok(Some(event)) =
-                tokio::time::timeout(Duration::from_millis(100), self.event_rx.recv()).await
-            {
-                if let UserEvent::StateUpdate(state) = event {
-                    last_state = Some(*state);
+
+        let display_path = FileHandler::display_path(path, root, config.use_relative_paths)?;
+        let mode = FileHandler::file_mode(path, &transform);
+
+        let probe = token_cache_probe(path, &model, &transform, format);
+        let (counts, checksum, missing_newline) =
+            match cached_token_entry(state, path, probe.as_ref()) {
+                Some(entry) => {
+                    let checksum = transform
+                        .include_checksum
+                        .then(|| FileHandler::file_checksum(path))
+                        .flatten();
+                    let counts = (entry.token_count, entry.block_bytes, entry.block_lines);
+                    (counts, checksum, false)
                 }
-            }
-            last_state
+                None => {
+                    let (content, checksum) = FileHandler::load_output_content(
+                        path,
+                        transform.clone(),
+                        format,
+                        config.skip_unreadable_files,
+                    )
+                    .await?;
+                    let missing_newline =
+                        format.needs_trailing_newline() && !content.ends_with('\n');
+                    let count = tokenizer.count_tokens(&content).await;
+                    store_token_count(state, path, probe, &content, count);
+                    let counts = (count, content.len(), content.lines().count());
+                    (counts, checksum, missing_newline)
+                }
+            };
+        let (token_count, block_bytes, block_lines) = counts;
+        skeleton.push_str(&format.file_header(
+            &display_path,
+            &get_language_from_path(path, transform.language_overrides.as_deref()),
+            mode.as_deref(),
+            checksum.as_deref(),
+        ));
+        if missing_newline {
+            skeleton.push('\n');
         }
+        skeleton.push_str(format.file_footer());
+        if config.include_index {
+            index_entries.push(IndexEntry {
+                path: display_path,
+                bytes: block_bytes,
+                lines: block_lines,
+                tokens: token_count,
+            });
+        }
+        total += token_count;
+        files += 1;
+    }
+    skeleton.push_str(&FileHandler::output_epilogue(
+        config.output_tree_position(),
+        items_for_tree,
+        root,
+        &config.tree_ignore_patterns,
+        config.tree_style(),
+        format,
+    ));
+    total += tokenizer.count_tokens(&skeleton).await;
+
+    if config.include_index {
+        total += tokenizer.count_tokens(&format.index(&index_entries)).await;
+    }
+    if config.include_manifest {
+        let manifest = FileHandler::manifest(selected, root, total, format);
+        total += tokenizer.count_tokens(&manifest).await;
     }
 
-    //============================================================================================//
-    //|                                        MOCK SERVICES                                     |//
-    //============================================================================================//
+    Ok((total, files))
+}
+
+/// The minimum time between two scan progress events sent to the UI.
+const SCAN_PROGRESS_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Forwards scan progress to the UI at most once per `interval`, whatever rate the
+/// `Scanner` reports it at. The latest update held back is sent by `flush`.
+struct ProgressThrottle<P: EventProxy> {
+    proxy: P,
+    interval: Duration,
+    /// When the last update was sent, and the latest update held back since then.
+    inner: Mutex<(Option<Instant>, Option<ScanProgress>)>,
+}
+
+impl<P: EventProxy> ProgressThrottle<P> {
+    fn new(proxy: P, interval: Duration) -> Arc<Self> {
+        Arc::new(Self {
+            proxy,
+            interval,
+            inner: Mutex::new((None, None)),
+        })
+    }
+
+    /// A progress callback for `Scanner::scan` that feeds this throttle.
+    fn callback(self: &Arc<Self>) -> Box<dyn Fn(ScanProgress) + Send + Sync> {
+        let throttle = self.clone();
+        Box::new(move |progress| throttle.offer(progress))
+    }
+
+    fn offer(&self, progress: ScanProgress) {
+        let mut inner = self.inner.lock().expect("Mutex poisoned");
+        let due = match inner.0 {
+            Some(last_sent) => last_sent.elapsed() >= self.interval,
+            None => true,
+        };
+        if due {
+            *inner = (Some(Instant::now()), None);
+            drop(inner);
+            self.proxy.send_event(UserEvent::ScanProgress(progress));
+        } else {
+            inner.1 = Some(progress);
+        }
+    }
+
+    /// Sends the latest update that was held back, if any.
+    fn flush(&self) {
+        let pending = self.inner.lock().expect("Mutex poisoned").1.take();
+        if let Some(progress) = pending {
+            self.proxy.send_event(UserEvent::ScanProgress(progress));
+        }
+    }
+}
+
+/// The core orchestration logic for the proactive, two-phase scan.
+pub async fn proactive_scan_task<P: EventProxy, S: Scanner>(
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+    path: PathBuf,
+    scanner: S,
+) {
+    // VET: RAII Guard now also takes the proxy to send a final state update.
+    struct ScanGuard<'a, P: EventProxy> {
+        state: &'a Arc<Mutex<AppState>>,
+        proxy: &'a P,
+    }
+    impl<'a, P: EventProxy> Drop for ScanGuard<'a, P> {
+        fn drop(&mut self) {
+            let mut state = lock_state(&self.state);
+            // Only update and notify if the scan was actually running.
+            // This prevents sending a redundant event if the scan finished cleanly.
+            if state.is_scanning {
+                state.is_scanning = false;
+                state.scan_task = None;
+                self.proxy
+                    .send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(&state))));
+            }
+        }
+    }
+    let _scan_guard = ScanGuard {
+        state: &state,
+        proxy: &proxy,
+    };
+
+    let progress = ProgressThrottle::new(proxy.clone(), SCAN_PROGRESS_INTERVAL);
+
+    // --- Phase 1: Shallow Scan ---
+    let scan_result_shallow = scanner.scan(&path, Some(1), progress.callback()).await;
+    progress.flush();
+
+    if lock_state(&state)
+        .scan_cancellation_flag
+        .load(Ordering::SeqCst)
+    {
+        tracing::info!("Scan cancelled after shallow scan phase.");
+        return; // _scan_guard is dropped here, cleaning up the state and notifying.
+    }
+
+    let (show_git_status, only_git_tracked, export_ignore_enabled) = {
+        let s = lock_state(&state);
+        (
+            s.config.show_git_status,
+            s.config.only_git_tracked,
+            s.config.export_ignore_enabled(),
+        )
+    };
+    let git_statuses = if show_git_status {
+        let root = path.clone();
+        tokio::task::spawn_blocking(move || git_status::read_git_statuses(&root))
+            .await
+            .unwrap_or_default()
+    } else {
+        HashMap::new()
+    };
+    let git_tracked_paths = if only_git_tracked {
+        let root = path.clone();
+        let tracked = tokio::task::spawn_blocking(move || git_status::read_tracked_paths(&root))
+            .await
+            .ok()
+            .flatten();
+        if tracked.is_none() {
+            tracing::warn!(
+                "{} is not a git work tree; showing all files despite only_git_tracked",
+                path.display()
+            );
+        }
+        tracked.map(Arc::new)
+    } else {
+        None
+    };
+    let export_ignore = if export_ignore_enabled {
+        let root = path.clone();
+        tokio::task::spawn_blocking(move || ExportIgnore::load(&root))
+            .await
+            .ok()
+            .map(Arc::new)
+    } else {
+        None
+    };
+
+    match scan_result_shallow {
+        Ok((mut files, patterns, _, _)) => {
+            let mut s = lock_state(&state);
+            git_status::annotate(&mut files, &git_statuses);
+            s.git_statuses = git_statuses;
+            s.git_tracked_paths = git_tracked_paths;
+            s.export_ignore = export_ignore;
+            s.full_file_list = files;
+            s.scan_ignore_patterns = patterns.clone();
+            s.pattern_ignored_items.clear();
+            s.active_ignore_patterns = patterns;
+            s.loaded_dirs.insert(path.clone());
+            filtering::apply_filters(&mut s);
+            proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(&s))));
+        }
+        Err(e) => {
+            handle_scan_error(e, &state, &proxy);
+            return; // _scan_guard is dropped here.
+        }
+    }
+
+    // --- Phase 2: Deep Background Scan (Indexing) ---
+    let scan_result_deep = scanner.scan(&path, None, progress.callback()).await;
+    progress.flush();
+
+    if lock_state(&state)
+        .scan_cancellation_flag
+        .load(Ordering::SeqCst)
+    {
+        tracing::info!("Scan cancelled during deep scan phase.");
+        return; // _scan_guard is dropped here.
+    }
+
+    match scan_result_deep {
+        Ok((mut files, patterns, large_files, broken_symlinks)) => {
+            let mut s = lock_state(&state);
+            git_status::annotate(&mut files, &s.git_statuses);
+            let new_file_paths: HashSet<_> = files.iter().map(|f| f.path.clone()).collect();
+            s.selected_files.retain(|p| new_file_paths.contains(p));
+            // Pinned files are re-selected whenever they exist, even if they were
+            // missing from an earlier scan.
+            let pinned: Vec<PathBuf> = s
+                .config
+                .pinned_files
+                .iter()
+                .filter(|p| new_file_paths.contains(*p))
+                .cloned()
+                .collect();
+            s.selected_files.extend(pinned);
+            // A session saved when this root was last left is restored once, dropping
+            // paths that no longer exist.
+            if let Some(session) = s.pending_session_restore.take() {
+                let dirs: HashSet<&Path> = files
+                    .iter()
+                    .filter(|f| f.is_directory)
+                    .map(|f| f.path.as_path())
+                    .collect();
+                let restored_files: Vec<PathBuf> = session
+                    .selected_files
+                    .iter()
+                    .map(|p| path.join(p))
+                    .filter(|p| new_file_paths.contains(p) && !dirs.contains(p.as_path()))
+                    .collect();
+                let restored_dirs: Vec<PathBuf> = session
+                    .expanded_dirs
+                    .iter()
+                    .map(|p| path.join(p))
+                    .filter(|p| dirs.contains(p.as_path()))
+                    .collect();
+                s.selected_files.extend(restored_files);
+                s.expanded_dirs.extend(restored_dirs);
+            }
+            s.full_file_list = files;
+            s.scan_ignore_patterns = patterns.clone();
+            s.pattern_ignored_items.clear();
+            s.active_ignore_patterns = patterns;
+            s.is_fully_scanned = true;
+            s.prune_token_cache();
+            s.loaded_dirs = s
+                .full_file_list
+                .iter()
+                .filter(|i| i.is_directory)
+                .map(|i| i.path.clone())
+                .collect();
+            filtering::apply_filters(&mut s);
+            let auto_selected = s.config.auto_selected_files(&s.filtered_file_list, &path);
+            s.selected_files.extend(auto_selected);
+
+            // VET: We now set the final state here and the guard is just for cleanup on panics/cancellations.
+            s.is_scanning = false;
+            s.scan_task = None;
+            s.scan_progress.current_scanning_path = format!(
+                "Indexing complete. Found {} visible items.",
+                s.filtered_file_list.len()
+            );
+
+            proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(&s))));
+
+            // Large files are dropped silently by the scanner, so tell the user about them.
+            if !large_files.is_empty() {
+                let names = large_files
+                    .iter()
+                    .map(|p| p.strip_prefix(&path).unwrap_or(p).display().to_string())
+                    .collect();
+                proxy.send_event(UserEvent::LargeFilesSkipped {
+                    count: large_files.len(),
+                    names,
+                    max_file_size_mb: s.config.max_file_size_mb,
+                });
+            }
+            if s.config.report_broken_symlinks && !broken_symlinks.is_empty() {
+                let paths = broken_symlinks
+                    .iter()
+                    .map(|p| p.strip_prefix(&path).unwrap_or(p).display().to_string())
+                    .collect();
+                proxy.send_event(UserEvent::BrokenSymlinks { paths });
+            }
+        }
+        Err(e) => {
+            handle_scan_error(e, &state, &proxy);
+        }
+    }
+}
+
+/// Initiates a proactive, two-phase directory scan.
+pub fn start_scan_on_path<P: EventProxy>(
+    path: PathBuf,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+    preserve_state: bool,
+) {
+    let proxy_clone = proxy.clone();
+    let state_clone = state.clone();
+    tokio::spawn(async move {
+        // 1. First, check if the path exists at all.
+        if !path.exists() {
+            proxy.send_event(UserEvent::ShowError(format!(
+                "Path does not exist: {}",
+                path.display()
+            )));
+            return;
+        }
+
+        // 2. If it exists, determine the correct directory to scan.
+        let directory_path = if path.is_dir() {
+            path
+        } else {
+            // It's a file, so we take its parent. This is safe now because we know the path exists.
+            path.parent().map(|p| p.to_path_buf()).unwrap_or(path) // Fallback just in case of root paths like "/"
+        };
+
+        let new_cancel_flag = {
+            let mut state_guard = lock_state(&state);
+            if !preserve_state {
+                remember_session(&state_guard);
+                state_guard.reset_directory_state();
+            } else {
+                state_guard.cancel_current_scan();
+            }
+            state_guard.current_path = directory_path.to_string_lossy().to_string();
+            state_guard.config.last_directory = Some(directory_path.clone());
+            if !preserve_state && state_guard.config.remember_selection {
+                state_guard.pending_session_restore =
+                    crate::config::settings::load_session(&directory_path, None).unwrap_or_else(
+                        |e| {
+                            tracing::warn!("Failed to load the saved session: {}", e);
+                            None
+                        },
+                    );
+            }
+            crate::config::settings::save_config(&state_guard.config, None).ok();
+            state_guard.is_scanning = true;
+            state_guard.is_fully_scanned = false;
+            let flag = Arc::new(AtomicBool::new(false));
+            state_guard.scan_cancellation_flag = flag.clone();
+            flag
+        };
+        proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
+            &lock_state(&state),
+        ))));
+        let (ignore_patterns, max_file_size_mb, include_hidden, extension_overrides) = {
+            let state_guard = lock_state(&state);
+            (
+                state_guard.config.ignore_patterns.clone(),
+                state_guard.config.max_file_size_mb,
+                state_guard.config.include_hidden,
+                state_guard.config.extension_overrides(),
+            )
+        };
+        let scanner = RealScanner {
+            ignore_patterns,
+            max_file_size_mb,
+            include_hidden,
+            extension_overrides,
+            cancel_flag: new_cancel_flag,
+        };
+        let handle = tokio::spawn(async move {
+            proactive_scan_task(proxy_clone, state_clone, directory_path, scanner).await;
+        });
+        let mut state_guard = lock_state(&state);
+        state_guard.scan_task = Some(handle);
+    });
+}
+
+/// Helper to handle scan errors consistently.
+fn handle_scan_error<P: EventProxy>(error: CoreError, state: &Arc<Mutex<AppState>>, proxy: &P) {
+    tracing::error!("LOG: TASK:: Scan finished with error: {}", error);
+    let mut state_lock = lock_state(state);
+    if !state_lock.is_scanning {
+        return;
+    }
+    state_lock.scan_progress.current_scanning_path = format!("Scan failed: {error}");
+    state_lock.is_scanning = false;
+    state_lock.scan_task = None;
+    let event = UserEvent::StateUpdate(Box::new(generate_ui_state(&state_lock)));
+    proxy.send_event(event);
+}
+
+/// Initiates a scan for a specific subdirectory level (lazy loading).
+pub fn start_lazy_load_scan<P: EventProxy>(
+    path: PathBuf,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+    completion_signal: Option<oneshot::Sender<()>>,
+) {
+    tokio::spawn(async move {
+        let Some(scanner) = scanner_unless_scanning(&state) else {
+            tracing::warn!("Attempted to lazy load while a full scan was in progress. Ignoring.");
+            return;
+        };
+        let proxy_clone = proxy.clone();
+        let state_clone = state.clone();
+        tracing::info!("LOG: Spawning new lazy_load_task for path: {:?}", path);
+        tokio::spawn(async move {
+            lazy_load_task(path, proxy_clone, state_clone, scanner, completion_signal).await;
+        });
+    });
+}
+
+/// Re-walks the directory `path` to its full depth and replaces the entries below it
+/// with the result, e.g. after editing one package of a monorepo. Entries elsewhere,
+/// and the selection and expansion of paths that still exist, are kept.
+pub fn start_subtree_rescan<P: EventProxy>(
+    path: PathBuf,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+    completion_signal: Option<oneshot::Sender<()>>,
+) {
+    tokio::spawn(async move {
+        let Some(scanner) = scanner_unless_scanning(&state) else {
+            tracing::warn!(
+                "Attempted to rescan a subtree while a full scan was in progress. Ignoring."
+            );
+            return;
+        };
+        subtree_rescan_task(path, proxy, state, scanner, completion_signal).await;
+    });
+}
+
+/// A scanner with the current settings, or `None` while a full scan is running.
+fn scanner_unless_scanning(state: &Mutex<AppState>) -> Option<RealScanner> {
+    let state_guard = lock_state(state);
+    if state_guard.is_scanning {
+        return None;
+    }
+    Some(RealScanner {
+        ignore_patterns: state_guard.config.ignore_patterns.clone(),
+        max_file_size_mb: state_guard.config.max_file_size_mb,
+        include_hidden: state_guard.config.include_hidden,
+        extension_overrides: state_guard.config.extension_overrides(),
+        cancel_flag: Arc::new(AtomicBool::new(false)),
+    })
+}
+
+/// The asynchronous task behind `start_subtree_rescan`.
+async fn subtree_rescan_task<P: EventProxy, S: Scanner>(
+    subtree: PathBuf,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+    scanner: S,
+    completion_signal: Option<oneshot::Sender<()>>,
+) {
+    match scanner.scan(&subtree, None, Box::new(|_| {})).await {
+        Ok((mut new_items, new_active_patterns, _, _)) => {
+            let mut state_guard = lock_state(&state);
+            let state = &mut *state_guard;
+            let below = |path: &Path| path.starts_with(&subtree) && path != subtree;
+            new_items.retain(|item| below(&item.path));
+            git_status::annotate(&mut new_items, &state.git_statuses);
+
+            let present: HashSet<PathBuf> =
+                new_items.iter().map(|item| item.path.clone()).collect();
+            let still_present = |path: &PathBuf| !below(path) || present.contains(path);
+            state.selected_files.retain(&still_present);
+            state.expanded_dirs.retain(&still_present);
+            state.loaded_dirs.retain(&still_present);
+            // Everything below the subtree has been walked now.
+            state.loaded_dirs.insert(subtree.clone());
+            state.loaded_dirs.extend(
+                new_items
+                    .iter()
+                    .filter(|item| item.is_directory)
+                    .map(|item| item.path.clone()),
+            );
+
+            state.full_file_list.retain(|item| !below(&item.path));
+            state.full_file_list.extend(new_items);
+            state
+                .scan_ignore_patterns
+                .extend(new_active_patterns.iter().cloned());
+            state.active_ignore_patterns.extend(new_active_patterns);
+
+            filtering::apply_filters(state);
+            proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(state))));
+        }
+        Err(e) => {
+            tracing::error!("Rescan failed for {:?}: {}", subtree, e);
+            proxy.send_event(UserEvent::ShowError(format!(
+                "Failed to rescan {}: {}",
+                subtree.display(),
+                e
+            )));
+        }
+    }
+
+    if let Some(signal) = completion_signal {
+        let _ = signal.send(());
+    }
+}
+
+/// The asynchronous task for scanning a single directory level and appending the results.
+async fn lazy_load_task<P: EventProxy, S: Scanner>(
+    path_to_load: PathBuf,
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+    scanner: S,
+    completion_signal: Option<oneshot::Sender<()>>,
+) {
+    // The scan call remains the same.
+    let scan_result = scanner.scan(&path_to_load, Some(1), Box::new(|_| {})).await;
+
+    match scan_result {
+        Ok((mut new_items, new_active_patterns, _, _)) => {
+            tracing::info!(
+                "LOG: TASK:: Lazy load successful. {} new items found for {:?}.",
+                new_items.len(),
+                path_to_load
+            );
+
+            // Instead of using the `with_state_and_notify` helper, we now manually
+            // lock the state and explicitly send the notification event.
+            // This aligns with the pattern used in other modern tasks in this file.
+            let mut state_guard = lock_state(&state);
+            git_status::annotate(&mut new_items, &state_guard.git_statuses);
+
+            state_guard.loaded_dirs.insert(path_to_load.clone());
+            state_guard.expanded_dirs.insert(path_to_load);
+            state_guard
+                .scan_ignore_patterns
+                .extend(new_active_patterns.iter().cloned());
+            state_guard
+                .active_ignore_patterns
+                .extend(new_active_patterns);
+
+            let existing_paths: HashSet<PathBuf> = state_guard
+                .full_file_list
+                .iter()
+                .map(|item| item.path.clone())
+                .collect();
+
+            for item in new_items {
+                if !existing_paths.contains(&item.path) {
+                    state_guard.full_file_list.push(item);
+                }
+            }
+
+            // Apply other filters to the now-updated list.
+            filtering::apply_filters(&mut state_guard);
+
+            // Explicitly send the state update event. This fixes the test panic.
+            proxy.send_event(UserEvent::StateUpdate(Box::new(generate_ui_state(
+                &state_guard,
+            ))));
+        }
+        Err(e) => {
+            // Error handling remains the same.
+            tracing::error!("LOG: TASK:: Lazy load failed for {:?}: {}", path_to_load, e);
+            proxy.send_event(UserEvent::ShowError(format!(
+                "Failed to load directory {}: {}",
+                path_to_load.display(),
+                e
+            )));
+        }
+    }
+
+    // After all work is done, send the completion signal if one was provided.
+    if let Some(signal) = completion_signal {
+        let _ = signal.send(());
+    }
+}
+
+/// Keeps the matches of the first `limit` files by path. Returns the kept matches and
+/// whether any were dropped.
+fn cap_search_results(
+    matches: HashMap<PathBuf, FileMatches>,
+    limit: usize,
+) -> (HashMap<PathBuf, FileMatches>, bool) {
+    if matches.len() <= limit {
+        return (matches, false);
+    }
+    let mut entries: Vec<_> = matches.into_iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries.truncate(limit);
+    (entries.into_iter().collect(), true)
+}
+
+/// Performs a content search across all non-binary files in the search scope. Files
+/// below the relevance threshold are dropped, and at most the configured number of
+/// matching files is kept, see `cap_search_results`.
+///
+/// If `cancel_flag` is set while the search runs, its results are discarded and the
+/// state is left to the search that superseded it.
+pub async fn search_in_files<P: EventProxy, S: FileSearcher>(
+    proxy: P,
+    state: Arc<Mutex<AppState>>,
+    searcher: S,
+    cancel_flag: Arc<AtomicBool>,
+) {
+    let (files_to_search, query, case_sensitive, context_lines, limit, threshold) = {
+        let mut state_guard = lock_state(&state);
+        if state_guard.content_search_query.is_empty() {
+            state_guard.content_search_results.clear();
+            state_guard.content_search_truncated = false;
+            filtering::apply_filters(&mut state_guard);
+            let event = UserEvent::StateUpdate(Box::new(generate_ui_state(&state_guard)));
+            proxy.send_event(event);
+            proxy.send_event(UserEvent::ContentSearchResults {
+                query: String::new(),
+                files: Vec::new(),
+            });
+            return;
+        }
+        let files_to_search = match state_guard.content_search_scope {
+            ContentSearchScope::AllFiles => state_guard.full_file_list.clone(),
+            ContentSearchScope::SelectedOnly => state_guard
+                .full_file_list
+                .iter()
+                .filter(|item| state_guard.selected_files.contains(&item.path))
+                .cloned()
+                .collect(),
+            ContentSearchScope::VisibleOnly => filtering::filtered_files(&state_guard, false),
+        };
+        (
+            files_to_search,
+            state_guard.content_search_query.clone(),
+            state_guard.config.case_sensitive_search,
+            state_guard.config.content_search_context_lines,
+            state_guard
+                .content_search_limit
+                .unwrap_or(state_guard.config.max_search_results),
+            state_guard.content_search_threshold,
+        )
+    };
+    let sizes: HashMap<PathBuf, u64> = files_to_search
+        .iter()
+        .map(|item| (item.path.clone(), item.size))
+        .collect();
+    let mut matches = searcher
+        .search(
+            files_to_search,
+            &query,
+            case_sensitive,
+            context_lines,
+            cancel_flag.clone(),
+        )
+        .await;
+    if cancel_flag.load(Ordering::SeqCst) {
+        tracing::info!("LOG: Content search for '{}' cancelled.", query);
+        return;
+    }
+    matches.retain(|path, file_matches| {
+        threshold.accepts(file_matches.count, sizes.get(path).copied().unwrap_or(0))
+    });
+    let (matches, truncated) = cap_search_results(matches, limit);
+    let mut state_guard = lock_state(&state);
+    state_guard.content_search_truncated = truncated;
+    state_guard.content_search_results = matches
+        .iter()
+        .map(|(path, file_matches)| (path.clone(), file_matches.count))
+        .collect();
+    filtering::apply_filters(&mut state_guard);
+    auto_expand_for_matches(&mut state_guard);
+    let event = UserEvent::StateUpdate(Box::new(generate_ui_state(&state_guard)));
+    proxy.send_event(event);
+    if context_lines.is_some() {
+        proxy.send_event(UserEvent::ContentSearchResults {
+            files: content_search_files(&state_guard, matches),
+            query,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::view_model::UiState;
+    use crate::config::{AppConfig, AutoSelect, SavedSession};
+    use crate::core::GitStatus;
+    use crate::utils::test_helpers::running_as_root;
+    use std::time::Duration;
+    use tempfile::{tempdir, TempDir};
+    use tokio::sync::{mpsc, oneshot};
+
+    //============================================================================================//
+    //|                                       TEST HARNESS                                       |//
+    //============================================================================================//
+    #[derive(Clone)]
+    struct TestEventProxy {
+        sender: mpsc::UnboundedSender<UserEvent>,
+    }
+    impl EventProxy for TestEventProxy {
+        fn send_event(&self, event: UserEvent) {
+            self.sender.send(event).expect("Test receiver dropped");
+        }
+    }
+    struct TestHarness {
+        state: Arc<Mutex<AppState>>,
+        proxy: TestEventProxy,
+        event_rx: mpsc::UnboundedReceiver<UserEvent>,
+        _temp_dir: TempDir,
+        root_path: PathBuf,
+    }
+    impl TestHarness {
+        fn new() -> Self {
+            let temp_dir = tempdir().expect("Failed to create temp dir");
+            let root_path = temp_dir.path().to_path_buf();
+            let (tx, rx) = mpsc::unbounded_channel();
+            let proxy = TestEventProxy { sender: tx };
+            let mut state = AppState::default();
+            state.config = AppConfig::default();
+            state.current_path = root_path.to_string_lossy().to_string();
+            Self {
+                state: Arc::new(Mutex::new(state)),
+                proxy,
+                event_rx: rx,
+                _temp_dir: temp_dir,
+                root_path,
+            }
+        }
+        async fn get_n_events(&mut self, n: usize) -> Vec<UserEvent> {
+            let mut events = Vec::with_capacity(n);
+            for _ in 0..n {
+                match tokio::time::timeout(Duration::from_secs(2), self.event_rx.recv()).await {
+                    Ok(Some(event)) => events.push(event),
+                    _ => break,
+                }
+            }
+            events
+        }
+        async fn get_last_state_update(&mut self) -> Option<UiState> {
+            let mut last_state = None;
+            while let Ok(Some(event)) =
+                tokio::time::timeout(Duration::from_millis(100), self.event_rx.recv()).await
+            {
+                if let UserEvent::StateUpdate(state) = event {
+                    last_state = Some(*state);
+                }
+            }
+            last_state
+        }
+    }
+
+    //============================================================================================//
+    //|                                        MOCK SERVICES                                     |//
+    //============================================================================================//
+
+    #[derive(Clone)]
+    struct MockContentGenerator {
+        result: Arc<Mutex<Result<String, CoreError>>>,
+        cancellation_check: Arc<Mutex<Option<oneshot::Receiver<()>>>>,
+        start_notifier: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+    }
+
+    impl MockContentGenerator {
+        fn new() -> Self {
+            Self {
+                result: Arc::new(Mutex::new(Ok(String::new()))),
+                cancellation_check: Arc::new(Mutex::new(None)),
+                start_notifier: Arc::new(Mutex::new(None)),
+            }
+        }
+        fn set_result(&self, result: Result<String, CoreError>) {
+            *self.result.lock().unwrap() = result;
+        }
+        fn expect_cancellation(&mut self) -> (oneshot::Receiver<()>, oneshot::Sender<()>) {
+            let (tx_start, rx_start) = oneshot::channel();
+            *self.start_notifier.lock().unwrap() = Some(tx_start);
+            let (tx_wait, rx_wait) = oneshot::channel();
+            *self.cancellation_check.lock().unwrap() = Some(rx_wait);
+            (rx_start, tx_wait)
+        }
+    }
+
+    #[async_trait]
+    impl ContentGenerator for MockContentGenerator {
+        async fn generate(
+            &self,
+            _: &[PathBuf],
+            _: &Path,
+            _: TreePosition,
+            _: Vec<FileItem>,
+            _: HashSet<String>,
+            _: TreeStyle,
+            _: bool,
+            _: ContentTransform,
+            _: OutputFormat,
+            _: bool,
+        ) -> Result<GeneratedOutput, CoreError> {
+            if let Some(notifier) = self.start_notifier.lock().unwrap().take() {
+                let _ = notifier.send(());
+            }
+            // VET: Release lock before await to fix !Send error
+            let receiver = self.cancellation_check.lock().unwrap().take();
+            if let Some(receiver) = receiver {
+                let _ = receiver.await;
+            }
+            self.result.lock().unwrap().clone().map(|content| {
+                GeneratedOutput::InMemory(ConcatenatedOutput {
+                    content,
+                    ..Default::default()
+                })
+            })
+        }
+    }
+
+    #[derive(Clone)]
+    struct MockTokenizer {
+        token_count: usize,
+    }
+
+    #[async_trait]
+    impl Tokenizer for MockTokenizer {
+        async fn count_tokens(&self, _: &str) -> usize {
+            self.token_count
+        }
+
+        fn model(&self) -> &str {
+            "mock"
+        }
+    }
+
+    /// A tokenizer that counts whitespace-separated words and records how often it was called.
+    #[derive(Clone, Default)]
+    struct CountingTokenizer {
+        calls: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Tokenizer for CountingTokenizer {
+        async fn count_tokens(&self, text: &str) -> usize {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            text.split_whitespace().count()
+        }
+
+        fn model(&self) -> &str {
+            "words"
+        }
+    }
+
+    #[derive(Clone)]
+    struct MockScanner {
+        shallow_result: Arc<Mutex<Result<ScanOutput, CoreError>>>,
+        deep_result: Arc<Mutex<Result<ScanOutput, CoreError>>>,
+        cancellation_trigger: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+        wait_for_cancel: Arc<Mutex<Option<oneshot::Receiver<()>>>>,
+    }
+
+    impl MockScanner {
+        fn new() -> Self {
+            Self {
+                shallow_result: Arc::new(Mutex::new(Ok((vec![], HashSet::new(), vec![], vec![])))),
+                deep_result: Arc::new(Mutex::new(Ok((vec![], HashSet::new(), vec![], vec![])))),
+                cancellation_trigger: Arc::new(Mutex::new(None)),
+                wait_for_cancel: Arc::new(Mutex::new(None)),
+            }
+        }
+        fn set_results(&mut self, shallow: Vec<FileItem>, deep: Vec<FileItem>) {
+            *self.shallow_result.lock().unwrap() = Ok((shallow, HashSet::new(), vec![], vec![]));
+            *self.deep_result.lock().unwrap() = Ok((deep, HashSet::new(), vec![], vec![]));
+        }
+        fn prepare_for_cancellation(&mut self) -> (oneshot::Receiver<()>, oneshot::Sender<()>) {
+            let (tx_trigger, rx_trigger) = oneshot::channel();
+            let (tx_wait, rx_wait) = oneshot::channel();
+            *self.cancellation_trigger.lock().unwrap() = Some(tx_trigger);
+            *self.wait_for_cancel.lock().unwrap() = Some(rx_wait);
+            (rx_trigger, tx_wait)
+        }
+    }
+
+    #[async_trait]
+    impl Scanner for MockScanner {
+        async fn scan(
+            &self,
+            _: &Path,
+            depth: Option<usize>,
+            _: Box<dyn Fn(ScanProgress) + Send + Sync>,
+        ) -> Result<ScanOutput, CoreError> {
+            if depth == Some(1) {
+                if let Some(trigger) = self.cancellation_trigger.lock().unwrap().take() {
+                    trigger.send(()).ok();
+                }
+                self.shallow_result.lock().unwrap().clone()
+            } else {
+                let waiter = self.wait_for_cancel.lock().unwrap().take();
+                if let Some(waiter) = waiter {
+                    waiter.await.ok();
+                }
+                self.deep_result.lock().unwrap().clone()
+            }
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct MockFileSearcher {
+        results: Arc<Mutex<HashMap<PathBuf, FileMatches>>>,
+    }
+    impl MockFileSearcher {
+        fn set_results(&self, results: HashMap<PathBuf, FileMatches>) {
+            *self.results.lock().unwrap() = results;
+        }
+    }
+    #[async_trait]
+    impl FileSearcher for MockFileSearcher {
+        async fn search(
+            &self,
+            _: Vec<FileItem>,
+            _: &str,
+            _: bool,
+            _: Option<usize>,
+            _: Arc<AtomicBool>,
+        ) -> HashMap<PathBuf, FileMatches> {
+            self.results.lock().unwrap().clone()
+        }
+    }
+
+    /// A searcher that records which files it was asked to read and finds nothing.
+    #[derive(Clone, Default)]
+    struct RecordingFileSearcher {
+        searched: Arc<Mutex<Vec<PathBuf>>>,
+    }
+    #[async_trait]
+    impl FileSearcher for RecordingFileSearcher {
+        async fn search(
+            &self,
+            files: Vec<FileItem>,
+            _: &str,
+            _: bool,
+            _: Option<usize>,
+            _: Arc<AtomicBool>,
+        ) -> HashMap<PathBuf, FileMatches> {
+            *self.searched.lock().unwrap() = files.into_iter().map(|item| item.path).collect();
+            HashMap::new()
+        }
+    }
+
+    /// A searcher that blocks until its cancellation flag is set, reporting when it
+    /// has started.
+    struct BlockingFileSearcher {
+        start_notifier: Mutex<Option<oneshot::Sender<()>>>,
+    }
+    #[async_trait]
+    impl FileSearcher for BlockingFileSearcher {
+        async fn search(
+            &self,
+            files: Vec<FileItem>,
+            _: &str,
+            _: bool,
+            _: Option<usize>,
+            cancel_flag: Arc<AtomicBool>,
+        ) -> HashMap<PathBuf, FileMatches> {
+            if let Some(notifier) = self.start_notifier.lock().unwrap().take() {
+                let _ = notifier.send(());
+            }
+            while !cancel_flag.load(Ordering::SeqCst) {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            // Partial results of a cancelled search must never reach the state.
+            files
+                .into_iter()
+                .map(|item| {
+                    let matches = FileMatches {
+                        count: 1,
+                        snippets: Vec::new(),
+                    };
+                    (item.path, matches)
+                })
+                .collect()
+        }
+    }
+
+    //============================================================================================//
+    //|                                        TEST CASES                                        |//
+    //============================================================================================//
+
+    #[tokio::test]
+    async fn generation_task_happy_path_sends_content_and_resets_state() {
+        // Arrange
+        let mut harness = TestHarness::new();
+        let generator = MockContentGenerator::new();
+        generator.set_result(Ok("Generated Content".to_string()));
+        let tokenizer = MockTokenizer { token_count: 2 };
+        harness.state.lock().unwrap().is_generating = true;
+
+        // Act
+        generation_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            generator,
+            tokenizer,
+        )
+        .await;
+
+        // Assert
+        let events = harness.get_n_events(2).await;
+        assert_eq!(events.len(), 2, "Expected exactly two events");
+
+        assert!(matches!(events[0], UserEvent::ShowGeneratedContent { .. }));
+        if let UserEvent::ShowGeneratedContent {
+            content,
+            token_count,
+        } = &events[0]
+        {
+            assert_eq!(content, "Generated Content");
+            assert_eq!(*token_count, 2);
+        }
+
+        // VET: Fix - Assert the event we already captured, don't try to fetch a new one.
+        assert!(matches!(events[1], UserEvent::StateUpdate(_)));
+        if let UserEvent::StateUpdate(final_state_in_event) = &events[1] {
+            assert!(!final_state_in_event.is_generating);
+        }
+
+        assert!(
+            !harness.state.lock().unwrap().is_generating,
+            "is_generating should be reset in AppState"
+        );
+    }
+
+    #[tokio::test]
+    async fn generation_task_prepends_manifest_and_counts_its_tokens() {
+        let mut harness = TestHarness::new();
+        let generator = MockContentGenerator::new();
+        generator.set_result(Ok("Generated Content".to_string()));
+        let tokenizer = MockTokenizer { token_count: 2 };
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.is_generating = true;
+            state.config.include_manifest = true;
+            state.config.output_format = OutputFormat::PlainText;
+        }
+
+        generation_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            generator,
+            tokenizer,
+        )
+        .await;
+
+        let events = harness.get_n_events(2).await;
+        match &events[0] {
+            UserEvent::ShowGeneratedContent {
+                content,
+                token_count,
+            } => {
+                assert!(content.starts_with("# MANIFEST\n"));
+                assert!(content.contains("# Total tokens (excluding manifest): 2\n"));
+                assert!(content.ends_with("Generated Content"));
+                // The mock tokenizer reports 2 tokens for the body and for the manifest.
+                assert_eq!(*token_count, 4);
+            }
+            _ => panic!("Expected ShowGeneratedContent event"),
+        }
+    }
+
+    #[tokio::test]
+    async fn large_generated_content_is_streamed_in_char_aligned_chunks() {
+        let mut harness = TestHarness::new();
+        let content = "aä€😀".repeat(5);
+
+        send_generated_content(&harness.proxy, content.clone(), 7, Some(4));
+
+        let mut events = Vec::new();
+        while let Ok(Some(event)) =
+            tokio::time::timeout(Duration::from_millis(100), harness.event_rx.recv()).await
+        {
+            events.push(event);
+        }
+        assert!(matches!(events.first(), Some(UserEvent::GenerationStart)));
+        assert!(matches!(
+            events.last(),
+            Some(UserEvent::GenerationEnd { token_count: 7 })
+        ));
+        let chunks: Vec<&str> = events[1..events.len() - 1]
+            .iter()
+            .map(|event| match event {
+                UserEvent::GenerationChunk { data } => data.as_str(),
+                other => panic!("Expected GenerationChunk, got {:?}", other),
+            })
+            .collect();
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| !chunk.is_empty()));
+        assert_eq!(chunks.concat(), content);
+    }
+
+    #[tokio::test]
+    async fn generation_task_previews_only_the_head_and_keeps_the_full_output() {
+        let mut harness = TestHarness::new();
+        let generator = MockContentGenerator::new();
+        generator.set_result(Ok("one\ntwo\nthree\nfour\n".to_string()));
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.is_generating = true;
+            state.config.preview_generated_head_lines = Some(2);
+        }
+
+        generation_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            generator,
+            MockTokenizer { token_count: 4 },
+        )
+        .await;
+
+        let events = harness.get_n_events(2).await;
+        match &events[0] {
+            UserEvent::ShowGeneratedContent { content, .. } => assert_eq!(
+                content,
+                "one\ntwo\n\n[… 2 more lines not shown. Save or copy to get the full output.]\n"
+            ),
+            other => panic!("Expected ShowGeneratedContent, got {:?}", other),
+        }
+        match &events[1] {
+            UserEvent::StateUpdate(ui_state) => assert!(ui_state.generated_preview_truncated),
+            other => panic!("Expected StateUpdate, got {:?}", other),
+        }
+        assert!(matches!(
+            harness.state.lock().unwrap().generated_content.as_ref(),
+            Some(HeldOutput::Text(content)) if &**content == "one\ntwo\nthree\nfour\n"
+        ));
+    }
+
+    #[test]
+    fn preview_head_leaves_short_outputs_alone() {
+        assert_eq!(preview_head("one\ntwo\n", 2), None);
+        assert_eq!(preview_head("one\ntwo", 2), None);
+        assert_eq!(preview_head("one\ntwo\nthree", 0), None);
+        assert_eq!(
+            preview_head("one\ntwo\nthree", 1).as_deref(),
+            Some("one\n\n[… 2 more lines not shown. Save or copy to get the full output.]\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn small_or_unstreamed_content_is_sent_in_one_event() {
+        let mut harness = TestHarness::new();
+
+        send_generated_content(&harness.proxy, "short".to_string(), 1, Some(1024));
+        send_generated_content(&harness.proxy, "x".repeat(64), 1, None);
+
+        let events = harness.get_n_events(2).await;
+        assert!(events
+            .iter()
+            .all(|event| matches!(event, UserEvent::ShowGeneratedContent { .. })));
+    }
+
+    #[tokio::test]
+    async fn generation_task_cancellation_is_handled_gracefully() {
+        let mut harness = TestHarness::new();
+        let mut generator = MockContentGenerator::new();
+        let (has_started_receiver, unblock_sender) = generator.expect_cancellation();
+        generator.set_result(Err(CoreError::Cancelled));
+        harness.state.lock().unwrap().is_generating = true;
+
+        let proxy_clone = harness.proxy.clone();
+        let task_state = harness.state.clone();
+
+        let task_handle = tokio::spawn(async move {
+            generation_task(
+                proxy_clone,
+                task_state,
+                generator,
+                MockTokenizer { token_count: 0 },
+            )
+            .await;
+        });
+        has_started_receiver
+            .await
+            .expect("Mock did not signal start");
+        unblock_sender.send(()).unwrap();
+        task_handle.await.unwrap();
+        let events = harness.get_n_events(2).await;
+        match &events[..] {
+            [UserEvent::StateUpdate(final_state), UserEvent::Status(message)] => {
+                assert!(!final_state.is_generating);
+                assert_eq!(message, "Generation cancelled.");
+            }
+            other => panic!("Expected a StateUpdate and a Status, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn generation_task_io_error_sends_error_event_and_resets_state() {
+        // Arrange
+        let mut harness = TestHarness::new();
+        let generator = MockContentGenerator::new();
+        let io_error = CoreError::Io("File not found".to_string(), PathBuf::from("a/b/c.txt"));
+        generator.set_result(Err(io_error));
+        harness.state.lock().unwrap().is_generating = true;
+
+        // Act
+        generation_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            generator,
+            MockTokenizer { token_count: 0 },
+        )
+        .await;
+
+        // Assert
+        let events = harness.get_n_events(2).await;
+        assert_eq!(events.len(), 2);
+
+        assert!(matches!(events[0], UserEvent::ShowError(_)));
+        if let UserEvent::ShowError(msg) = &events[0] {
+            assert!(msg.contains("I/O error"));
+            assert!(msg.contains("a/b/c.txt"));
+        }
+
+        // VET: Fix - Assert the event we already captured.
+        assert!(matches!(events[1], UserEvent::StateUpdate(_)));
+        if let UserEvent::StateUpdate(final_state_in_event) = &events[1] {
+            assert!(!final_state_in_event.is_generating);
+        }
+
+        assert!(
+            !harness.state.lock().unwrap().is_generating,
+            "is_generating should be reset in AppState"
+        );
+    }
+
+    #[tokio::test]
+    async fn proactive_scan_task_verifies_two_phase_events() {
+        // Arrange
+        let mut harness = TestHarness::new();
+        let mut scanner = MockScanner::new();
+
+        let shallow_files = vec![FileItem {
+            path: harness.root_path.join("src"),
+            is_directory: true,
+            ..Default::default()
+        }];
+        let deep_files = vec![
+            FileItem {
+                path: harness.root_path.join("src"),
+                is_directory: true,
+                ..Default::default()
+            },
+            FileItem {
+                path: harness.root_path.join("src/main.rs"),
+                is_directory: false,
+                ..Default::default()
+            },
+        ];
+        scanner.set_results(shallow_files.clone(), deep_files.clone());
+
+        harness.state.lock().unwrap().is_scanning = true;
+
+        // Act
+        proactive_scan_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            harness.root_path.clone(),
+            scanner,
+        )
+        .await;
+
+        // Assert
+        // VET: Fix - Expect 2 events now. The success path sets the final state itself.
+        // The guard is now only for panics or cancellations.
+        let events = harness.get_n_events(2).await;
+        assert_eq!(events.len(), 2, "Expected two StateUpdate events");
+
+        // Phase 1: Shallow scan update
+        let state1 = match &events[0] {
+            UserEvent::StateUpdate(s) => s,
+            _ => panic!("Expected StateUpdate"),
+        };
+        assert_eq!(state1.visible_files_count, 1);
+        assert!(
+            !state1.is_fully_scanned,
+            "Should not be fully scanned after phase 1"
+        );
+        assert!(state1.is_scanning, "Should still be scanning after phase 1");
+
+        // Phase 2: Deep scan update (this is the final event in the success case)
+        let state2 = match &events[1] {
+            UserEvent::StateUpdate(s) => s,
+            _ => panic!("Expected StateUpdate"),
+        };
+        assert_eq!(state2.visible_files_count, 2);
+        assert!(
+            state2.is_fully_scanned,
+            "Should be fully scanned after phase 2"
+        );
+        assert!(
+            !state2.is_scanning,
+            "Scanning should be complete in final state"
+        );
+        assert!(state2.status_message.contains("Indexing complete"));
+    }
+
+    #[tokio::test]
+    async fn proactive_scan_task_annotates_git_status_when_enabled() {
+        let harness = TestHarness::new();
+        let git_init = std::process::Command::new("git")
+            .args(["init", "--quiet"])
+            .current_dir(&harness.root_path)
+            .status();
+        if !git_init.is_ok_and(|status| status.success()) {
+            eprintln!("git is not available, skipping");
+            return;
+        }
+        let untracked = harness.root_path.join("new.rs");
+        std::fs::write(&untracked, "fn new() {}").unwrap();
+        let mut scanner = MockScanner::new();
+        let files = vec![FileItem {
+            path: untracked.clone(),
+            ..Default::default()
+        }];
+        scanner.set_results(files.clone(), files);
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.show_git_status = true;
+            state.is_scanning = true;
+        }
+
+        proactive_scan_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            harness.root_path.clone(),
+            scanner,
+        )
+        .await;
+
+        let state = harness.state.lock().unwrap();
+        assert_eq!(
+            state.full_file_list[0].git_status,
+            Some(GitStatus::Untracked)
+        );
+        let ui_state = generate_ui_state(&state);
+        assert_eq!(ui_state.tree[0].git_status, Some(GitStatus::Untracked));
+    }
+
+    #[tokio::test]
+    async fn proactive_scan_task_reselects_pinned_files_that_reappear() {
+        let mut harness = TestHarness::new();
+        let mut scanner = MockScanner::new();
+        let pinned = harness.root_path.join("schema.rs");
+        let missing_pin = harness.root_path.join("gone.rs");
+        let file = |path: &PathBuf| FileItem {
+            path: path.clone(),
+            ..Default::default()
+        };
+        scanner.set_results(vec![], vec![file(&pinned)]);
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.is_scanning = true;
+            // The pinned file was missing before, so it is no longer selected.
+            state.selected_files.clear();
+            state.config.pinned_files = HashSet::from([pinned.clone(), missing_pin.clone()]);
+        }
+
+        proactive_scan_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            harness.root_path.clone(),
+            scanner,
+        )
+        .await;
+
+        let final_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(final_state.selected_files_count, 1);
+        let state = harness.state.lock().unwrap();
+        assert!(state.selected_files.contains(&pinned));
+        assert!(!state.selected_files.contains(&missing_pin));
+    }
+
+    #[tokio::test]
+    async fn proactive_scan_task_restores_the_saved_session_without_stale_paths() {
+        let mut harness = TestHarness::new();
+        let mut scanner = MockScanner::new();
+        let root = harness.root_path.clone();
+        let item = |relative: &str, is_directory: bool| FileItem {
+            path: root.join(relative),
+            is_directory,
+            ..Default::default()
+        };
+        scanner.set_results(
+            vec![],
+            vec![
+                item("src", true),
+                item("src/main.rs", false),
+                item("README.md", false),
+            ],
+        );
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.is_scanning = true;
+            state.selected_files.clear();
+            state.expanded_dirs.clear();
+            state.pending_session_restore = Some(SavedSession {
+                selected_files: vec![PathBuf::from("src/main.rs"), PathBuf::from("gone.rs")],
+                expanded_dirs: vec![PathBuf::from("src"), PathBuf::from("old")],
+            });
+        }
+
+        proactive_scan_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            root.clone(),
+            scanner,
+        )
+        .await;
+
+        harness.get_last_state_update().await.unwrap();
+        let state = harness.state.lock().unwrap();
+        assert_eq!(
+            state.selected_files,
+            HashSet::from([root.join("src/main.rs")])
+        );
+        assert_eq!(state.expanded_dirs, HashSet::from([root.join("src")]));
+        assert!(state.pending_session_restore.is_none());
+    }
+
+    #[tokio::test]
+    async fn proactive_scan_task_auto_selects_files_after_the_deep_scan() {
+        let harness = TestHarness::new();
+        let root = harness.root_path.clone();
+        let files = vec![
+            FileItem {
+                path: root.join("src"),
+                is_directory: true,
+                ..Default::default()
+            },
+            FileItem {
+                path: root.join("src/main.rs"),
+                ..Default::default()
+            },
+            FileItem {
+                path: root.join("src/bundle.min.js"),
+                likely_generated: true,
+                ..Default::default()
+            },
+            FileItem {
+                path: root.join("logo.png"),
+                is_binary: true,
+                ..Default::default()
+            },
+            FileItem {
+                path: root.join("huge.sql"),
+                size: 2 * 1024 * 1024,
+                ..Default::default()
+            },
+            FileItem {
+                path: root.join("README.md"),
+                ..Default::default()
+            },
+        ];
+
+        let scan_with = |auto_select| {
+            {
+                let mut state = harness.state.lock().unwrap();
+                state.is_scanning = true;
+                state.selected_files.clear();
+                state.config.max_file_size_mb = 1;
+                state.config.auto_select_on_scan = auto_select;
+                state.config.auto_select_patterns = HashSet::from(["src/".to_string()]);
+            }
+            let mut scanner = MockScanner::new();
+            scanner.set_results(vec![], files.clone());
+            proactive_scan_task(
+                harness.proxy.clone(),
+                harness.state.clone(),
+                root.clone(),
+                scanner,
+            )
+        };
+
+        scan_with(AutoSelect::None).await;
+        assert!(harness.state.lock().unwrap().selected_files.is_empty());
+
+        scan_with(AutoSelect::AllText).await;
+        assert_eq!(
+            harness.state.lock().unwrap().selected_files,
+            HashSet::from([root.join("src/main.rs"), root.join("README.md")])
+        );
+
+        scan_with(AutoSelect::FromPreset).await;
+        assert_eq!(
+            harness.state.lock().unwrap().selected_files,
+            HashSet::from([root.join("src/main.rs")])
+        );
+    }
+
+    #[tokio::test]
+    async fn proactive_scan_task_reports_skipped_large_files() {
+        // Arrange
+        let mut harness = TestHarness::new();
+        let scanner = MockScanner::new();
+        *scanner.deep_result.lock().unwrap() = Ok((
+            vec![],
+            HashSet::new(),
+            vec![harness.root_path.join("assets/huge.bin")],
+            vec![],
+        ));
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.is_scanning = true;
+            state.config.max_file_size_mb = 5;
+        }
+
+        // Act
+        proactive_scan_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            harness.root_path.clone(),
+            scanner,
+        )
+        .await;
+
+        // Assert: the report follows the two regular state updates.
+        let events = harness.get_n_events(3).await;
+        assert_eq!(events.len(), 3);
+        match &events[2] {
+            UserEvent::LargeFilesSkipped {
+                count,
+                names,
+                max_file_size_mb,
+            } => {
+                assert_eq!(*count, 1);
+                assert_eq!(
+                    names,
+                    &vec![Path::new("assets").join("huge.bin").display().to_string()]
+                );
+                assert_eq!(*max_file_size_mb, 5);
+            }
+            other => panic!("Expected LargeFilesSkipped, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn proactive_scan_task_reports_broken_symlinks_only_when_configured() {
+        for report in [false, true] {
+            let mut harness = TestHarness::new();
+            let scanner = MockScanner::new();
+            *scanner.deep_result.lock().unwrap() = Ok((
+                vec![],
+                HashSet::new(),
+                vec![],
+                vec![harness.root_path.join("docs/dangling.md")],
+            ));
+            {
+                let mut state = harness.state.lock().unwrap();
+                state.is_scanning = true;
+                state.config.report_broken_symlinks = report;
+            }
+
+            proactive_scan_task(
+                harness.proxy.clone(),
+                harness.state.clone(),
+                harness.root_path.clone(),
+                scanner,
+            )
+            .await;
+
+            let events = harness.get_n_events(3).await;
+            let reported = events.iter().find_map(|event| match event {
+                UserEvent::BrokenSymlinks { paths } => Some(paths.clone()),
+                _ => None,
+            });
+            if report {
+                assert_eq!(
+                    reported,
+                    Some(vec![Path::new("docs")
+                        .join("dangling.md")
+                        .display()
+                        .to_string()])
+                );
+            } else {
+                assert_eq!(reported, None);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn real_scanner_applies_the_configured_size_limit() {
+        let dir = tempdir().unwrap();
+        let big = dir.path().join("big.txt");
+        std::fs::File::create(&big)
+            .unwrap()
+            .set_len(2 * 1024 * 1024)
+            .unwrap();
+        std::fs::write(dir.path().join("small.txt"), "small").unwrap();
+
+        for (max_file_size_mb, expect_skipped) in [(1, true), (3, false)] {
+            for max_depth in [None, Some(1)] {
+                let scanner = RealScanner {
+                    ignore_patterns: HashSet::new(),
+                    max_file_size_mb,
+                    include_hidden: false,
+                    extension_overrides: ExtensionOverrides::default(),
+                    cancel_flag: Arc::new(AtomicBool::new(false)),
+                };
+                let (items, _, skipped, _) = scanner
+                    .scan(dir.path(), max_depth, Box::new(|_| {}))
+                    .await
+                    .unwrap();
+
+                assert_eq!(skipped.contains(&big), expect_skipped);
+                assert_eq!(items.iter().any(|item| item.path == big), !expect_skipped);
+                assert!(items.iter().any(|item| item.path.ends_with("small.txt")));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn real_scanner_skips_hidden_entries_unless_included() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir(dir.path().join(".github")).unwrap();
+        std::fs::write(dir.path().join(".github/ci.yml"), "on: push").unwrap();
+        std::fs::write(dir.path().join(".env.example"), "KEY=").unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+
+        for include_hidden in [false, true] {
+            let scanner = RealScanner {
+                ignore_patterns: HashSet::new(),
+                max_file_size_mb: 20,
+                include_hidden,
+                extension_overrides: ExtensionOverrides::default(),
+                cancel_flag: Arc::new(AtomicBool::new(false)),
+            };
+            let (items, _, _, _) = scanner
+                .scan(dir.path(), None, Box::new(|_| {}))
+                .await
+                .unwrap();
+            let has = |name: &str| items.iter().any(|item| item.path.ends_with(name));
+
+            assert!(has("main.rs"));
+            assert_eq!(has(".env.example"), include_hidden);
+            assert_eq!(has(".github/ci.yml"), include_hidden);
+        }
+    }
+
+    #[tokio::test]
+    async fn proactive_scan_cancellation_during_deep_scan_aborts_task() {
+        let harness = TestHarness::new();
+        let mut scanner = MockScanner::new();
+        scanner.set_results(vec![FileItem::default()], vec![]);
+        let (has_started_deep_scan, unblock_deep_scan) = scanner.prepare_for_cancellation();
+        harness.state.lock().unwrap().is_scanning = true;
+        let cancel_flag = harness.state.lock().unwrap().scan_cancellation_flag.clone();
+
+        let proxy_clone = harness.proxy.clone();
+        let task_state = harness.state.clone();
+        let task_path = harness.root_path.clone();
+
+        let task_handle = tokio::spawn(async move {
+            proactive_scan_task(proxy_clone, task_state, task_path, scanner).await;
+        });
+        has_started_deep_scan.await.unwrap();
+        cancel_flag.store(true, Ordering::SeqCst);
+        unblock_deep_scan.send(()).unwrap();
+        let _ = task_handle.await;
+
+        // The task now cleans up after itself via the RAII guard.
+        // We can now confidently check the final state.
+        let final_app_state = harness.state.lock().unwrap();
+        assert!(!final_app_state.is_scanning, "is_scanning should be false");
+        assert!(
+            !final_app_state.is_fully_scanned,
+            "is_fully_scanned should be false"
+        );
+    }
+
+    #[tokio::test]
+    async fn search_in_files_updates_state_with_mock_results() {
+        let mut harness = TestHarness::new();
+        let searcher = MockFileSearcher::default();
+        let match1 = harness.root_path.join("match1.txt");
+        let match2 = harness.root_path.join("match2.txt");
+        let mut mock_results = HashMap::new();
+        let matches = |count| FileMatches {
+            count,
+            snippets: Vec::new(),
+        };
+        mock_results.insert(match1.clone(), matches(3));
+        mock_results.insert(match2.clone(), matches(1));
+        searcher.set_results(mock_results);
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.content_search_query = "magic".to_string();
+            state.full_file_list.push(FileItem {
+                path: match1.clone(),
+                ..Default::default()
+            });
+            state.full_file_list.push(FileItem {
+                path: match2.clone(),
+                ..Default::default()
+            });
+            state.full_file_list.push(FileItem {
+                path: harness.root_path.join("nomatch.txt"),
+                ..Default::default()
+            });
+        }
+        search_in_files(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            searcher,
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await;
+        {
+            let final_state = harness.state.lock().unwrap();
+            assert_eq!(final_state.content_search_results.len(), 2);
+            assert_eq!(final_state.content_search_results.get(&match1), Some(&3));
+            assert_eq!(final_state.content_search_results.get(&match2), Some(&1));
+        }
+        let ui_state = harness.get_last_state_update().await.unwrap();
+        assert_eq!(
+            ui_state.visible_files_count, 2,
+            "Filtered list should only contain the 2 matches"
+        );
+    }
+
+    #[tokio::test]
+    async fn search_in_files_only_reads_files_in_the_search_scope() {
+        let harness = TestHarness::new();
+        let root = harness.root_path.clone();
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.content_search_query = "magic".to_string();
+            state.extension_filter = "rs".to_string();
+            for name in ["a.rs", "b.rs", "c.txt"] {
+                state.full_file_list.push(FileItem {
+                    path: root.join(name),
+                    ..Default::default()
+                });
+            }
+            state.selected_files.insert(root.join("a.rs"));
+        }
+
+        let searched_in = |scope| {
+            harness.state.lock().unwrap().content_search_scope = scope;
+            let searcher = RecordingFileSearcher::default();
+            let searched = searcher.searched.clone();
+            let search = search_in_files(
+                harness.proxy.clone(),
+                harness.state.clone(),
+                searcher,
+                Arc::new(AtomicBool::new(false)),
+            );
+            async move {
+                search.await;
+                let mut paths = searched.lock().unwrap().clone();
+                paths.sort();
+                paths
+            }
+        };
+
+        assert_eq!(
+            searched_in(ContentSearchScope::AllFiles).await,
+            vec![root.join("a.rs"), root.join("b.rs"), root.join("c.txt")]
+        );
+        assert_eq!(
+            searched_in(ContentSearchScope::SelectedOnly).await,
+            vec![root.join("a.rs")]
+        );
+        assert_eq!(
+            searched_in(ContentSearchScope::VisibleOnly).await,
+            vec![root.join("a.rs"), root.join("b.rs")]
+        );
+    }
+
+    #[tokio::test]
+    async fn search_in_files_sends_snippets_with_file_ids() {
+        let mut harness = TestHarness::new();
+        let path = harness.root_path.join("notes.txt");
+        std::fs::write(&path, "intro\nthe magic line\noutro\n").unwrap();
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.content_search_query = "magic".to_string();
+            state.config.content_search_context_lines = Some(1);
+            state.full_file_list.push(FileItem {
+                path: path.clone(),
+                ..Default::default()
+            });
+        }
+
+        search_in_files(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            RealFileSearcher::default(),
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await;
+
+        let events = harness.get_n_events(2).await;
+        match &events[1] {
+            UserEvent::ContentSearchResults { query, files } => {
+                assert_eq!(query, "magic");
+                assert_eq!(files.len(), 1);
+                assert_eq!(
+                    files[0].id,
+                    Some(harness.state.lock().unwrap().path_ids.id(&path))
+                );
+                assert_eq!(files[0].path, path);
+                assert_eq!(files[0].match_count, 1);
+                assert_eq!(
+                    files[0].snippets,
+                    vec![MatchSnippet {
+                        line_number: 2,
+                        line: "the magic line".to_string(),
+                        context_before: vec!["intro".to_string()],
+                        context_after: vec!["outro".to_string()],
+                    }]
+                );
+            }
+            other => panic!("Expected ContentSearchResults, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn search_in_files_discards_results_when_cancelled_mid_search() {
+        let mut harness = TestHarness::new();
+        let (start_tx, start_rx) = oneshot::channel();
+        let searcher = BlockingFileSearcher {
+            start_notifier: Mutex::new(Some(start_tx)),
+        };
+        let cancel_flag = {
+            let mut state = harness.state.lock().unwrap();
+            state.content_search_query = "stale".to_string();
+            state.full_file_list.push(FileItem {
+                path: harness.root_path.join("file.txt"),
+                ..Default::default()
+            });
+            state.search_cancellation_flag.clone()
+        };
+
+        let search = tokio::spawn(search_in_files(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            searcher,
+            cancel_flag,
+        ));
+        start_rx.await.unwrap();
+        harness.state.lock().unwrap().cancel_current_search();
+        tokio::time::timeout(Duration::from_secs(2), search)
+            .await
+            .expect("Cancelled search should finish promptly")
+            .unwrap();
+
+        assert!(harness
+            .state
+            .lock()
+            .unwrap()
+            .content_search_results
+            .is_empty());
+        assert!(
+            harness.get_last_state_update().await.is_none(),
+            "A cancelled search should not send a state update"
+        );
+    }
+
+    #[tokio::test]
+    async fn search_in_files_clears_results_on_empty_query() {
+        let mut harness = TestHarness::new();
+        let searcher = MockFileSearcher::default();
+        {
+            let mut state = harness.state.lock().unwrap();
+            state
+                .content_search_results
+                .insert(PathBuf::from("previous_match.txt"), 1);
+            state.content_search_query = "".to_string();
+        }
+        search_in_files(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            searcher,
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await;
+        {
+            let final_state = harness.state.lock().unwrap();
+            assert!(
+                final_state.content_search_results.is_empty(),
+                "Search results should be cleared"
+            );
+        }
+        assert!(
+            harness.get_last_state_update().await.is_some(),
+            "Should have received a state update"
+        );
+    }
+
+    #[tokio::test]
+    async fn proactive_scan_task_handles_shallow_scan_error() {
+        // Arrange
+        let mut harness = TestHarness::new();
+        let scanner = MockScanner::new();
+        let scan_error = CoreError::Io("Permission denied".to_string(), harness.root_path.clone());
+
+        // Configure the mock scanner to fail the shallow scan
+        *scanner.shallow_result.lock().unwrap() = Err(scan_error.clone());
+
+        harness.state.lock().unwrap().is_scanning = true;
+
+        // Act
+        proactive_scan_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            harness.root_path.clone(),
+            scanner,
+        )
+        .await;
+
+        // Assert
+        // The RAII guard will send a final StateUpdate on drop because of the error.
+        let events = harness.get_n_events(1).await;
+        assert_eq!(events.len(), 1, "Expected one final StateUpdate event");
 
-    #[derive(Clone)]
-    struct MockContentGenerator {
-        result: Arc<Mutex<Result<String, CoreError>>>,
-        cancellation_check: Arc<Mutex<Option<oneshot::Receiver<()>>>>,
-        start_notifier: Arc<Mutex<Option<oneshot::Sender<()>>>>,
-    }
+        let final_state = match &events[0] {
+            UserEvent::StateUpdate(s) => s,
+            _ => panic!("Expected a StateUpdate event."),
+        };
 
-    impl MockContentGenerator {
-        fn new() -> Self {
-            Self {
-                result: Arc::new(Mutex::new(Ok(String::new()))),
-                cancellation_check: Arc::new(Mutex::new(None)),
-                start_notifier: Arc::new(Mutex::new(None)),
-            }
-        }
-        fn set_result(&self, result: Result<String, CoreError>) {
-            *self.result.lock().unwrap() = result;
-        }
-        fn expect_cancellation(&mut self) -> (oneshot::Receiver<()>, oneshot::Sender<()>) {
-            let (tx_start, rx_start) = oneshot::channel();
-            *self.start_notifier.lock().unwrap() = Some(tx_start);
-            let (tx_wait, rx_wait) = oneshot::channel();
-            *self.cancellation_check.lock().unwrap() = Some(rx_wait);
-            (rx_start, tx_wait)
-        }
+        assert!(
+            !final_state.is_scanning,
+            "Scanning should be stopped on error."
+        );
+        assert!(
+            final_state
+                .status_message
+                .contains("Scan failed: I/O error"),
+            "Status message should reflect the scan failure."
+        );
     }
 
-    #[async_trait]
-    impl ContentGenerator for MockContentGenerator {
-        async fn generate(
-            &self,
-            _: &[PathBuf],
-            _: &Path,
-            _: bool,
-            _: Vec<FileItem>,
-            _: HashSet<String>,
-            _: bool,
-        ) -> Result<String, CoreError> {
-            if let Some(notifier) = self.start_notifier.lock().unwrap().take() {
-                let _ = notifier.send(());
-            }
-            // VET: Release lock before await to fix !Send error
-            let receiver = self.cancellation_check.lock().unwrap().take();
-            if let Some(receiver) = receiver {
-                let _ = receiver.await;
-            }
-            self.result.lock().unwrap().clone()
-        }
-    }
+    #[tokio::test]
+    async fn proactive_scan_task_handles_deep_scan_error() {
+        // Arrange
+        let mut harness = TestHarness::new();
+        let scanner = MockScanner::new();
+        let scan_error = CoreError::Io("Disk full".to_string(), harness.root_path.clone());
 
-    #[derive(Clone)]
-    struct MockTokenizer {
-        token_count: usize,
-    }
+        // Shallow scan succeeds, deep scan fails
+        let shallow_files = vec![FileItem {
+            path: harness.root_path.join("file.txt"),
+            ..Default::default()
+        }];
+        *scanner.shallow_result.lock().unwrap() =
+            Ok((shallow_files.clone(), HashSet::new(), vec![], vec![]));
+        *scanner.deep_result.lock().unwrap() = Err(scan_error.clone());
 
-    #[async_trait]
-    impl Tokenizer for MockTokenizer {
-        async fn count_tokens(&self, _: &str) -> usize {
-            self.token_count
-        }
-    }
+        harness.state.lock().unwrap().is_scanning = true;
 
-    #[derive(Clone)]
-    struct MockScanner {
-        shallow_result: Arc<Mutex<Result<(Vec<FileItem>, HashSet<String>), CoreError>>>,
-        deep_result: Arc<Mutex<Result<(Vec<FileItem>, HashSet<String>), CoreError>>>,
-        cancellation_trigger: Arc<Mutex<Option<oneshot::Sender<()>>>>,
-        wait_for_cancel: Arc<Mutex<Option<oneshot::Receiver<()>>>>,
-    }
+        // Act
+        proactive_scan_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            harness.root_path.clone(),
+            scanner,
+        )
+        .await;
 
-    impl MockScanner {
-        fn new() -> Self {
-            Self {
-                shallow_result: Arc::new(Mutex::new(Ok((vec![], HashSet::new())))),
-                deep_result: Arc::new(Mutex::new(Ok((vec![], HashSet::new())))),
-                cancellation_trigger: Arc::new(Mutex::new(None)),
-                wait_for_cancel: Arc::new(Mutex::new(None)),
-            }
-        }
-        fn set_results(&mut self, shallow: Vec<FileItem>, deep: Vec<FileItem>) {
-            *self.shallow_result.lock().unwrap() = Ok((shallow, HashSet::new()));
-            *self.deep_result.lock().unwrap() = Ok((deep, HashSet::new()));
-        }
-        fn prepare_for_cancellation(&mut self) -> (oneshot::Receiver<()>, oneshot::Sender<()>) {
-            let (tx_trigger, rx_trigger) = oneshot::channel();
-            let (tx_wait, rx_wait) = oneshot::channel();
-            *self.cancellation_trigger.lock().unwrap() = Some(tx_trigger);
-            *self.wait_for_cancel.lock().unwrap() = Some(rx_wait);
-            (rx_trigger, tx_wait)
-        }
-    }
+        // Assert
+        // Expect two StateUpdates: one after the successful shallow scan, one for the final error state.
+        let events = harness.get_n_events(2).await;
+        assert_eq!(events.len(), 2, "Expected two StateUpdate events");
 
-    #[async_trait]
-    impl Scanner for MockScanner {
-        async fn scan(
-            &self,
-            _: &Path,
-            depth: Option<usize>,
-            _: Box<dyn Fn(ScanProgress) + Send + Sync>,
-        ) -> Result<(Vec<FileItem>, HashSet<String>), CoreError> {
-            if depth == Some(1) {
-                if let Some(trigger) = self.cancellation_trigger.lock().unwrap().take() {
-                    trigger.send(()).ok();
-                }
-                self.shallow_result.lock().unwrap().clone()
-            } else {
-                let waiter = self.wait_for_cancel.lock().unwrap().take();
-                if let Some(waiter) = waiter {
-                    waiter.await.ok();
-                }
-                self.deep_result.lock().unwrap().clone()
-            }
-        }
-    }
+        // Check state after shallow scan
+        let state1 = match &events[0] {
+            UserEvent::StateUpdate(s) => s,
+            _ => panic!("Expected StateUpdate for shallow scan"),
+        };
+        assert!(
+            state1.is_scanning,
+            "Should still be scanning after shallow scan."
+        );
+        assert_eq!(
+            state1.visible_files_count, 1,
+            "Shallow scan results should be visible."
+        );
 
-    #[derive(Clone, Default)]
-    struct MockFileSearcher {
-        results: Arc<Mutex<HashSet<PathBuf>>>,
-    }
-    impl MockFileSearcher {
-        fn set_results(&self, results: HashSet<PathBuf>) {
-            *self.results.lock().unwrap() = results;
-        }
-    }
-    #[async_trait]
-    impl FileSearcher for MockFileSearcher {
-        async fn search(&self, _: Vec<FileItem>, _: &str, _: bool) -> HashSet<PathBuf> {
-            self.results.lock().unwrap().clone()
-        }
+        // Check final state after deep scan error
+        let state2 = match &events[1] {
+            UserEvent::StateUpdate(s) => s,
+            _ => panic!("Expected StateUpdate for deep scan failure"),
+        };
+        assert!(
+            !state2.is_scanning,
+            "Scanning should be stopped after error."
+        );
+        assert!(
+            state2.status_message.contains("Scan failed: I/O error"),
+            "Status message should reflect the deep scan failure."
+        );
     }
 
-    //============================================================================================//
-    //|                                        TEST CASES                                        |//
-    //============================================================================================//
-
     #[tokio::test]
-    async fn generation_task_happy_path_sends_content_and_resets_state() {
+    async fn generation_task_handles_generator_error() {
         // Arrange
         let mut harness = TestHarness::new();
         let generator = MockContentGenerator::new();
-        generator.set_result(Ok("Generated Content".to_string()));
-        let tokenizer = MockTokenizer { token_count: 2 };
+        let gen_error = CoreError::Io("Failed to read file".to_string(), PathBuf::from("test.txt"));
+        generator.set_result(Err(gen_error.clone()));
+        let tokenizer = MockTokenizer { token_count: 0 };
         harness.state.lock().unwrap().is_generating = true;
 
         // Act
@@ -821,807 +3301,1049 @@ mod tests {
         .await;
 
         // Assert
+        // Expect a ShowError event, followed by a StateUpdate.
         let events = harness.get_n_events(2).await;
-        assert_eq!(events.len(), 2, "Expected exactly two events");
+        assert_eq!(events.len(), 2, "Expected ShowError and StateUpdate events");
 
-        assert!(matches!(events[0], UserEvent::ShowGeneratedContent { .. }));
-        if let UserEvent::ShowGeneratedContent {
-            content,
-            token_count,
-        } = &events[0]
-        {
-            assert_eq!(content, "Generated Content");
-            assert_eq!(*token_count, 2);
+        // Check the ShowError event
+        match &events[0] {
+            UserEvent::ShowError(msg) => {
+                assert!(
+                    msg.contains("Failed to read file"),
+                    "Error message content is incorrect."
+                );
+            }
+            _ => panic!("Expected a ShowError event first."),
         }
 
-        // VET: Fix - Assert the event we already captured, don't try to fetch a new one.
-        assert!(matches!(events[1], UserEvent::StateUpdate(_)));
-        if let UserEvent::StateUpdate(final_state_in_event) = &events[1] {
-            assert!(!final_state_in_event.is_generating);
+        // Check the final StateUpdate
+        match &events[1] {
+            UserEvent::StateUpdate(s) => {
+                assert!(!s.is_generating, "is_generating flag should be reset.");
+            }
+            _ => panic!("Expected a StateUpdate event second."),
         }
 
+        // Double-check the final state directly
         assert!(
             !harness.state.lock().unwrap().is_generating,
-            "is_generating should be reset in AppState"
+            "is_generating should be false in final AppState."
         );
     }
 
     #[tokio::test]
-    async fn generation_task_cancellation_is_handled_gracefully() {
-        let mut harness = TestHarness::new();
-        let mut generator = MockContentGenerator::new();
-        let (has_started_receiver, unblock_sender) = generator.expect_cancellation();
-        generator.set_result(Err(CoreError::Cancelled));
-        harness.state.lock().unwrap().is_generating = true;
-
-        let proxy_clone = harness.proxy.clone();
-        let task_state = harness.state.clone();
-
-        let task_handle = tokio::spawn(async move {
-            generation_task(
-                proxy_clone,
-                task_state,
-                generator,
-                MockTokenizer { token_count: 0 },
-            )
-            .await;
-        });
-        has_started_receiver
-            .await
-            .expect("Mock did not signal start");
-        unblock_sender.send(()).unwrap();
-        task_handle.await.unwrap();
-        let final_state = harness.get_last_state_update().await.unwrap();
-        assert!(!final_state.is_generating);
-        assert!(final_state.status_message.contains("Generation cancelled"));
-        let events = harness.get_n_events(1).await;
-        assert!(!matches!(events.get(0), Some(UserEvent::ShowError(_))));
-    }
-
-    #[tokio::test]
-    async fn generation_task_io_error_sends_error_event_and_resets_state() {
+    async fn lazy_load_task_handles_scan_error() {
         // Arrange
         let mut harness = TestHarness::new();
-        let generator = MockContentGenerator::new();
-        let io_error = CoreError::Io("File not found".to_string(), PathBuf::from("a/b/c.txt"));
-        generator.set_result(Err(io_error));
-        harness.state.lock().unwrap().is_generating = true;
+        let scanner = MockScanner::new();
+        let path_to_load = harness.root_path.join("lazy");
+
+        // Configure scanner to fail for the shallow scan, which is what lazy_load uses
+        let scan_error = CoreError::NotADirectory(path_to_load.clone());
+        *scanner.shallow_result.lock().unwrap() = Err(scan_error.clone());
 
         // Act
-        generation_task(
+        lazy_load_task(
+            path_to_load.clone(),
             harness.proxy.clone(),
             harness.state.clone(),
-            generator,
-            MockTokenizer { token_count: 0 },
+            scanner,
+            None,
         )
         .await;
 
         // Assert
-        let events = harness.get_n_events(2).await;
-        assert_eq!(events.len(), 2);
-
-        assert!(matches!(events[0], UserEvent::ShowError(_)));
-        if let UserEvent::ShowError(msg) = &events[0] {
-            assert!(msg.contains("I/O error"));
-            assert!(msg.contains("a/b/c.txt"));
-        }
+        // Expect a ShowError event.
+        let events = harness.get_n_events(1).await;
+        assert_eq!(events.len(), 1, "Expected one ShowError event");
 
-        // VET: Fix - Assert the event we already captured.
-        assert!(matches!(events[1], UserEvent::StateUpdate(_)));
-        if let UserEvent::StateUpdate(final_state_in_event) = &events[1] {
-            assert!(!final_state_in_event.is_generating);
+        match &events[0] {
+            UserEvent::ShowError(msg) => {
+                assert!(
+                    msg.contains("Failed to load directory"),
+                    "Error message is incorrect"
+                );
+                assert!(
+                    msg.contains("lazy"),
+                    "Error message should contain the path"
+                );
+                assert!(
+                    msg.contains("not a valid directory"),
+                    "Error message should contain the error detail"
+                );
+            }
+            _ => panic!("Expected a ShowError event."),
         }
 
+        // Assert that state was not left inconsistent
+        let state = harness.state.lock().unwrap();
         assert!(
-            !harness.state.lock().unwrap().is_generating,
-            "is_generating should be reset in AppState"
+            !state.loaded_dirs.contains(&path_to_load),
+            "Failed path should not be marked as loaded."
         );
     }
 
     #[tokio::test]
-    async fn proactive_scan_task_verifies_two_phase_events() {
+    async fn proactive_scan_task_cancels_between_phases() {
         // Arrange
         let mut harness = TestHarness::new();
         let mut scanner = MockScanner::new();
 
+        // Use the cancellation helper to pause the task between scan phases
+        let (shallow_scan_finished, allow_deep_scan_to_proceed) =
+            scanner.prepare_for_cancellation();
+
+        // Shallow scan succeeds.
         let shallow_files = vec![FileItem {
-            path: harness.root_path.join("src"),
-            is_directory: true,
+            path: harness.root_path.join("file.txt"),
             ..Default::default()
         }];
-        let deep_files = vec![
-            FileItem {
-                path: harness.root_path.join("src"),
-                is_directory: true,
-                ..Default::default()
-            },
-            FileItem {
-                path: harness.root_path.join("src/main.rs"),
-                is_directory: false,
-                ..Default::default()
-            },
-        ];
-        scanner.set_results(shallow_files.clone(), deep_files.clone());
+        *scanner.shallow_result.lock().unwrap() =
+            Ok((shallow_files.clone(), HashSet::new(), vec![], vec![]));
+        *scanner.deep_result.lock().unwrap() = Ok((vec![], HashSet::new(), vec![], vec![]));
 
         harness.state.lock().unwrap().is_scanning = true;
+        let cancel_flag = harness.state.lock().unwrap().scan_cancellation_flag.clone();
+        let proxy_clone = harness.proxy.clone();
+        let state_clone = harness.state.clone();
+        let path_clone = harness.root_path.clone();
 
         // Act
-        proactive_scan_task(
-            harness.proxy.clone(),
-            harness.state.clone(),
-            harness.root_path.clone(),
-            scanner,
-        )
-        .await;
+        let task_handle = tokio::spawn(async move {
+            proactive_scan_task(proxy_clone, state_clone, path_clone, scanner).await
+        });
 
-        // Assert
-        // VET: Fix - Expect 2 events now. The success path sets the final state itself.
-        // The guard is now only for panics or cancellations.
-        let events = harness.get_n_events(2).await;
-        assert_eq!(events.len(), 2, "Expected two StateUpdate events");
+        // 1. Wait for the signal from the mock that the shallow scan is complete.
+        shallow_scan_finished
+            .await
+            .expect("Mock scanner should signal that shallow scan finished");
 
-        // Phase 1: Shallow scan update
-        let state1 = match &events[0] {
-            UserEvent::StateUpdate(s) => s,
-            _ => panic!("Expected StateUpdate"),
-        };
-        assert_eq!(state1.visible_files_count, 1);
-        assert!(
-            !state1.is_fully_scanned,
-            "Should not be fully scanned after phase 1"
-        );
-        assert!(state1.is_scanning, "Should still be scanning after phase 1");
+        // 2. Now that the task is paused, set the cancellation flag.
+        cancel_flag.store(true, Ordering::SeqCst);
+
+        // 3. Unblock the task, which will now immediately see the cancel flag and exit.
+        allow_deep_scan_to_proceed.send(()).unwrap();
+
+        // 4. Wait for the task to fully terminate.
+        task_handle.await.unwrap();
+
+        // Assert
+        // We need to drain the initial shallow update event first.
+        let _ = harness.get_n_events(1).await;
+        // Now, the ScanGuard will have fired a final state update on drop. Get that one.
+        let final_state_update = harness
+            .get_last_state_update()
+            .await
+            .expect("ScanGuard should send a final update on cancellation");
 
-        // Phase 2: Deep scan update (this is the final event in the success case)
-        let state2 = match &events[1] {
-            UserEvent::StateUpdate(s) => s,
-            _ => panic!("Expected StateUpdate"),
-        };
-        assert_eq!(state2.visible_files_count, 2);
         assert!(
-            state2.is_fully_scanned,
-            "Should be fully scanned after phase 2"
+            !final_state_update.is_scanning,
+            "Scanning should be false in the final state."
         );
         assert!(
-            !state2.is_scanning,
-            "Scanning should be complete in final state"
+            !final_state_update.is_fully_scanned,
+            "Scan should not be marked as full."
         );
-        assert!(state2.status_message.contains("Indexing complete"));
+
+        // The final AppState should be consistent
+        let final_app_state = harness.state.lock().unwrap();
+        assert!(!final_app_state.is_scanning);
+        assert!(final_app_state.scan_task.is_none());
     }
 
     #[tokio::test]
-    async fn proactive_scan_cancellation_during_deep_scan_aborts_task() {
-        let harness = TestHarness::new();
-        let mut scanner = MockScanner::new();
-        scanner.set_results(vec![FileItem::default()], vec![]);
-        let (has_started_deep_scan, unblock_deep_scan) = scanner.prepare_for_cancellation();
-        harness.state.lock().unwrap().is_scanning = true;
-        let cancel_flag = harness.state.lock().unwrap().scan_cancellation_flag.clone();
+    async fn start_scan_on_path_handles_nonexistent_path() {
+        // Arrange
+        let mut harness = TestHarness::new();
+        let nonexistent_path = harness.root_path.join("nonexistent");
 
-        let proxy_clone = harness.proxy.clone();
-        let task_state = harness.state.clone();
-        let task_path = harness.root_path.clone();
+        // Act
+        start_scan_on_path(
+            nonexistent_path,
+            harness.proxy.clone(),
+            harness.state.clone(),
+            false,
+        );
 
-        let task_handle = tokio::spawn(async move {
-            proactive_scan_task(proxy_clone, task_state, task_path, scanner).await;
-        });
-        has_started_deep_scan.await.unwrap();
-        cancel_flag.store(true, Ordering::SeqCst);
-        unblock_deep_scan.send(()).unwrap();
-        let _ = task_handle.await;
+        // Assert
+        // We must await the event, as it's sent from a spawned task.
+        let event = harness
+            .get_n_events(1)
+            .await
+            .pop()
+            .expect("Should have received one event");
 
-        // The task now cleans up after itself via the RAII guard.
-        // We can now confidently check the final state.
-        let final_app_state = harness.state.lock().unwrap();
-        assert!(!final_app_state.is_scanning, "is_scanning should be false");
-        assert!(
-            !final_app_state.is_fully_scanned,
-            "is_fully_scanned should be false"
-        );
+        match event {
+            UserEvent::ShowError(msg) => {
+                // Check for the more specific error message from our fix.
+                assert!(msg.contains("Path does not exist"));
+            }
+            _ => panic!("Expected a ShowError event."),
+        }
+
+        let state = harness.state.lock().unwrap();
+        assert!(!state.is_scanning, "is_scanning should remain false.");
     }
 
     #[tokio::test]
-    async fn search_in_files_updates_state_with_mock_results() {
+    async fn start_scan_on_path_handles_path_is_file() {
+        // Arrange
         let mut harness = TestHarness::new();
-        let searcher = MockFileSearcher::default();
-        let match1 = harness.root_path.join("match1.txt");
-        let match2 = harness.root_path.join("match2.txt");
-        let mut mock_results = HashSet::new();
-        mock_results.insert(match1.clone());
-        mock_results.insert(match2.clone());
-        searcher.set_results(mock_results);
-        {
-            let mut state = harness.state.lock().unwrap();
-            state.content_search_query = "magic".to_string();
-            state.full_file_list.push(FileItem {
-                path: match1.clone(),
-                ..Default::default()
-            });
-            state.full_file_list.push(FileItem {
-                path: match2.clone(),
-                ..Default::default()
-            });
-            state.full_file_list.push(FileItem {
-                path: harness.root_path.join("nomatch.txt"),
-                ..Default::default()
-            });
-        }
-        search_in_files(harness.proxy.clone(), harness.state.clone(), searcher).await;
-        {
-            let final_state = harness.state.lock().unwrap();
-            assert_eq!(final_state.content_search_results.len(), 2);
-            assert!(final_state.content_search_results.contains(&match1));
-            assert!(final_state.content_search_results.contains(&match2));
-        }
-        let ui_state = harness.get_last_state_update().await.unwrap();
-        assert_eq!(
-            ui_state.visible_files_count, 2,
-            "Filtered list should only contain the 2 matches"
+        let file_path = harness.root_path.join("some_file.txt");
+        std::fs::write(&file_path, "content").unwrap();
+
+        // Act
+        // We expect this to run the scan on the parent directory.
+        start_scan_on_path(
+            file_path,
+            harness.proxy.clone(),
+            harness.state.clone(),
+            false,
         );
+
+        // Assert
+        // It should NOT send an error. It should start scanning the parent.
+        // It sends an initial state update setting is_scanning to true.
+        let events = harness.get_n_events(1).await;
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            UserEvent::StateUpdate(s) => {
+                assert!(s.is_scanning);
+                assert_eq!(s.current_path, harness.root_path.to_string_lossy());
+            }
+            _ => panic!("Expected a StateUpdate event."),
+        }
+
+        // Lock the state mutably to check and then cancel.
+        let mut state = harness.state.lock().unwrap();
+        assert!(state.is_scanning, "is_scanning should be true.");
+        assert_eq!(state.current_path, harness.root_path.to_string_lossy());
+
+        // Cancel the scan to clean up the task and prevent test runner warnings.
+        state.cancel_current_scan();
     }
 
     #[tokio::test]
-    async fn search_in_files_clears_results_on_empty_query() {
+    async fn start_scan_on_path_preserves_state() {
+        // Arrange
         let mut harness = TestHarness::new();
-        let searcher = MockFileSearcher::default();
+        let dir_to_expand = harness.root_path.join("src");
+        let new_scan_path = harness.root_path.join("new_project");
+        std::fs::create_dir_all(&new_scan_path).unwrap();
+
+        // Set initial state
         {
             let mut state = harness.state.lock().unwrap();
-            state
-                .content_search_results
-                .insert(PathBuf::from("previous_match.txt"));
-            state.content_search_query = "".to_string();
+            state.expanded_dirs.insert(dir_to_expand.clone());
         }
-        search_in_files(harness.proxy.clone(), harness.state.clone(), searcher).await;
+
+        // Act
+        // Call with preserve_state = true
+        start_scan_on_path(
+            new_scan_path.clone(),
+            harness.proxy.clone(),
+            harness.state.clone(),
+            true,
+        );
+
+        // Assert
+        // Wait for the initial StateUpdate event which confirms the task has started and set the initial state.
+        // This resolves the race condition.
+        let initial_update = harness
+            .get_n_events(1)
+            .await
+            .pop()
+            .expect("Task should send an initial StateUpdate");
+        assert!(matches!(initial_update, UserEvent::StateUpdate(_)));
+
+        // Now it's safe to check the state.
         {
-            let final_state = harness.state.lock().unwrap();
+            let state = harness.state.lock().unwrap();
+            assert!(state.is_scanning, "Scanning should have started.");
+            assert_eq!(state.current_path, new_scan_path.to_string_lossy());
             assert!(
-                final_state.content_search_results.is_empty(),
-                "Search results should be cleared"
+                state.expanded_dirs.contains(&dir_to_expand),
+                "expanded_dirs should have been preserved."
             );
         }
-        assert!(
-            harness.get_last_state_update().await.is_some(),
-            "Should have received a state update"
-        );
+
+        // We need to wait for the scan to finish to avoid panics on drop.
+        // Let's get all events to clear the channel.
+        while harness.get_n_events(1).await.len() > 0 {}
     }
 
+    /// Comprehensive test for the RealFileSearcher implementation via the search_in_files task.
+    /// This test covers:
+    /// 1. Case-sensitive matching.
+    /// 2. Case-insensitive matching (default).
+    /// 3. Correctly skipping binary files and directories.
+    /// 4. Handling files where the search term is not found.
     #[tokio::test]
-    async fn proactive_scan_task_handles_shallow_scan_error() {
+    async fn search_in_files_with_real_searcher_covers_edge_cases() {
         // Arrange
         let mut harness = TestHarness::new();
-        let scanner = MockScanner::new();
-        let scan_error = CoreError::Io("Permission denied".to_string(), harness.root_path.clone());
+        let searcher = RealFileSearcher::default(); // Use the real implementation
 
-        // Configure the mock scanner to fail the shallow scan
-        *scanner.shallow_result.lock().unwrap() = Err(scan_error.clone());
+        // Create a diverse set of files
+        let text_file_path = harness.root_path.join("file.txt");
+        std::fs::write(&text_file_path, "find the MagicWord here").unwrap();
 
-        harness.state.lock().unwrap().is_scanning = true;
+        let binary_file_path = harness.root_path.join("app.exe");
+        std::fs::write(&binary_file_path, &[0, 1, 2, 3, 255]).unwrap(); // A binary file
 
-        // Act
-        proactive_scan_task(
+        let no_match_path = harness.root_path.join("another.txt");
+        std::fs::write(&no_match_path, "nothing to see").unwrap();
+
+        let dir_path = harness.root_path.join("subfolder");
+        std::fs::create_dir(&dir_path).unwrap();
+
+        // Populate state
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.full_file_list = vec![
+                FileItem {
+                    path: text_file_path.clone(),
+                    is_binary: false,
+                    ..Default::default()
+                },
+                FileItem {
+                    path: binary_file_path,
+                    is_binary: true,
+                    ..Default::default()
+                },
+                FileItem {
+                    path: no_match_path,
+                    is_binary: false,
+                    ..Default::default()
+                },
+                FileItem {
+                    path: dir_path,
+                    is_directory: true,
+                    ..Default::default()
+                },
+            ];
+        }
+
+        // --- SCENARIO 1: Case-sensitive search (no match) ---
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.case_sensitive_search = true;
+            state.content_search_query = "magicword".to_string(); // Lowercase
+        }
+        // Pass 'searcher' by value (it's Copy)
+        search_in_files(
             harness.proxy.clone(),
             harness.state.clone(),
-            harness.root_path.clone(),
-            scanner,
+            searcher,
+            Arc::new(AtomicBool::new(false)),
         )
         .await;
-
-        // Assert
-        // The RAII guard will send a final StateUpdate on drop because of the error.
-        let events = harness.get_n_events(1).await;
-        assert_eq!(events.len(), 1, "Expected one final StateUpdate event");
-
-        let final_state = match &events[0] {
-            UserEvent::StateUpdate(s) => s,
-            _ => panic!("Expected a StateUpdate event."),
-        };
-
-        assert!(
-            !final_state.is_scanning,
-            "Scanning should be stopped on error."
-        );
+        // Assert against the AppState, not the UiState
         assert!(
-            final_state
-                .status_message
-                .contains("Scan failed: I/O error"),
-            "Status message should reflect the scan failure."
+            harness
+                .state
+                .lock()
+                .unwrap()
+                .content_search_results
+                .is_empty(),
+            "Case-sensitive search should not find 'magicword'"
         );
-    }
-
-    #[tokio::test]
-    async fn proactive_scan_task_handles_deep_scan_error() {
-        // Arrange
-        let mut harness = TestHarness::new();
-        let scanner = MockScanner::new();
-        let scan_error = CoreError::Io("Disk full".to_string(), harness.root_path.clone());
-
-        // Shallow scan succeeds, deep scan fails
-        let shallow_files = vec![FileItem {
-            path: harness.root_path.join("file.txt"),
-            ..Default::default()
-        }];
-        *scanner.shallow_result.lock().unwrap() = Ok((shallow_files.clone(), HashSet::new()));
-        *scanner.deep_result.lock().unwrap() = Err(scan_error.clone());
-
-        harness.state.lock().unwrap().is_scanning = true;
+        // Drain the event queue
+        let _ = harness.get_last_state_update().await;
 
-        // Act
-        proactive_scan_task(
+        // --- SCENARIO 2: Case-sensitive search (exact match) ---
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.case_sensitive_search = true;
+            state.content_search_query = "MagicWord".to_string(); // Exact case
+        }
+        search_in_files(
             harness.proxy.clone(),
             harness.state.clone(),
-            harness.root_path.clone(),
-            scanner,
+            searcher,
+            Arc::new(AtomicBool::new(false)),
         )
         .await;
-
-        // Assert
-        // Expect two StateUpdates: one after the successful shallow scan, one for the final error state.
-        let events = harness.get_n_events(2).await;
-        assert_eq!(events.len(), 2, "Expected two StateUpdate events");
-
-        // Check state after shallow scan
-        let state1 = match &events[0] {
-            UserEvent::StateUpdate(s) => s,
-            _ => panic!("Expected StateUpdate for shallow scan"),
-        };
-        assert!(
-            state1.is_scanning,
-            "Should still be scanning after shallow scan."
+        // Assert against the AppState for correctness
+        let final_state = harness.state.lock().unwrap();
+        assert_eq!(
+            final_state.content_search_results.len(),
+            1,
+            "Case-sensitive search should find 'MagicWord'"
         );
         assert_eq!(
-            state1.visible_files_count, 1,
-            "Shallow scan results should be visible."
+            final_state.content_search_results.get(&text_file_path),
+            Some(&1)
         );
+    }
 
-        // Check final state after deep scan error
-        let state2 = match &events[1] {
-            UserEvent::StateUpdate(s) => s,
-            _ => panic!("Expected StateUpdate for deep scan failure"),
-        };
-        assert!(
-            !state2.is_scanning,
-            "Scanning should be stopped after error."
-        );
-        assert!(
-            state2.status_message.contains("Scan failed: I/O error"),
-            "Status message should reflect the deep scan failure."
-        );
+    /// Counts the occurrences of `query` in `content` with `search_lines`.
+    fn count_matches(content: &str, query: &str, case_sensitive: bool) -> usize {
+        search_lines(
+            content.as_bytes(),
+            query,
+            case_sensitive,
+            None,
+            &AtomicBool::new(false),
+        )
+        .unwrap()
+        .count
     }
 
-    #[tokio::test]
-    async fn generation_task_handles_generator_error() {
-        // Arrange
-        let mut harness = TestHarness::new();
-        let generator = MockContentGenerator::new();
-        let gen_error = CoreError::Io("Failed to read file".to_string(), PathBuf::from("test.txt"));
-        generator.set_result(Err(gen_error.clone()));
-        let tokenizer = MockTokenizer { token_count: 0 };
-        harness.state.lock().unwrap().is_generating = true;
+    /// The snippets `search_lines` captures for `query` in `content`.
+    fn collect_snippets(
+        content: &str,
+        query: &str,
+        case_sensitive: bool,
+        context_lines: usize,
+    ) -> Vec<MatchSnippet> {
+        search_lines(
+            content.as_bytes(),
+            query,
+            case_sensitive,
+            Some(context_lines),
+            &AtomicBool::new(false),
+        )
+        .unwrap()
+        .snippets
+    }
 
-        // Act
-        generation_task(
-            harness.proxy.clone(),
-            harness.state.clone(),
-            generator,
-            tokenizer,
+    #[test]
+    fn search_lines_streams_large_files_through_a_small_buffer() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("big.log");
+        // Multi-byte characters regularly straddle the 1 KiB buffer boundaries.
+        let filler = "größe ünd 日本語 log line without the needle\n".repeat(50_000);
+        std::fs::write(
+            &path,
+            format!("first Nadel line\n{filler}last NADEL ü\r\ntrailing"),
         )
-        .await;
+        .unwrap();
+        let open = || BufReader::with_capacity(1024, std::fs::File::open(&path).unwrap());
+        let not_cancelled = AtomicBool::new(false);
 
-        // Assert
-        // Expect a ShowError event, followed by a StateUpdate.
-        let events = harness.get_n_events(2).await;
-        assert_eq!(events.len(), 2, "Expected ShowError and StateUpdate events");
+        let matches = search_lines(open(), "nadel", false, Some(1), &not_cancelled).unwrap();
+        assert_eq!(matches.count, 2);
+        assert_eq!(
+            matches.snippets,
+            vec![
+                MatchSnippet {
+                    line_number: 1,
+                    line: "first Nadel line".to_string(),
+                    context_before: vec![],
+                    context_after: vec!["größe ünd 日本語 log line without the needle".to_string()],
+                },
+                MatchSnippet {
+                    line_number: 50_002,
+                    line: "last NADEL ü".to_string(),
+                    context_before: vec!["größe ünd 日本語 log line without the needle".to_string()],
+                    context_after: vec!["trailing".to_string()],
+                },
+            ]
+        );
 
-        // Check the ShowError event
-        match &events[0] {
-            UserEvent::ShowError(msg) => {
-                assert!(
-                    msg.contains("Failed to read file"),
-                    "Error message content is incorrect."
-                );
-            }
-            _ => panic!("Expected a ShowError event first."),
-        }
+        let misses = search_lines(open(), "absent", true, Some(2), &not_cancelled).unwrap();
+        assert_eq!(misses, FileMatches::default());
 
-        // Check the final StateUpdate
-        match &events[1] {
-            UserEvent::StateUpdate(s) => {
-                assert!(!s.is_generating, "is_generating flag should be reset.");
-            }
-            _ => panic!("Expected a StateUpdate event second."),
-        }
+        let cancelled = AtomicBool::new(true);
+        let error = search_lines(open(), "nadel", false, None, &cancelled).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Interrupted);
 
-        // Double-check the final state directly
-        assert!(
-            !harness.state.lock().unwrap().is_generating,
-            "is_generating should be false in final AppState."
+        std::fs::write(&path, b"needle \xff\xfe").unwrap();
+        let error = search_lines(open(), "needle", true, None, &not_cancelled).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn collect_snippets_captures_bounded_matches_with_context() {
+        let content = "a\nfoo 1\nb\nc\nFOO 2\n";
+        let snippets = collect_snippets(content, "foo", false, 1);
+        assert_eq!(
+            snippets,
+            vec![
+                MatchSnippet {
+                    line_number: 2,
+                    line: "foo 1".to_string(),
+                    context_before: vec!["a".to_string()],
+                    context_after: vec!["b".to_string()],
+                },
+                MatchSnippet {
+                    line_number: 5,
+                    line: "FOO 2".to_string(),
+                    context_before: vec!["c".to_string()],
+                    context_after: vec![],
+                },
+            ]
+        );
+        assert_eq!(collect_snippets(content, "foo", true, 0).len(), 1);
+
+        let many = "hit\n".repeat(20);
+        assert_eq!(
+            collect_snippets(&many, "hit", true, 2).len(),
+            MAX_SNIPPETS_PER_FILE
         );
+
+        let long = "x".repeat(500) + "hit";
+        let snippet = &collect_snippets(&long, "hit", true, 0)[0];
+        assert_eq!(snippet.line.chars().count(), MAX_SNIPPET_LINE_CHARS + 1);
+    }
+
+    #[test]
+    fn count_matches_counts_every_occurrence_on_each_line() {
+        let content = "foo foo\nbar\nFOO and foo\n";
+        assert_eq!(count_matches(content, "foo", true), 3);
+        assert_eq!(count_matches(content, "foo", false), 4);
+        assert_eq!(count_matches(content, "baz", false), 0);
+        // Occurrences are not counted across line breaks.
+        assert_eq!(count_matches("ab\ncd", "b\nc", true), 0);
+    }
+
+    #[test]
+    fn matching_lines_are_numbered_from_one() {
+        let content = "foo\nbar\nFOO and foo\nbaz\n";
+        assert_eq!(matching_lines(content, "foo", true), vec![1, 3]);
+        assert_eq!(matching_lines(content, "Foo", false), vec![1, 3]);
+        assert_eq!(matching_lines(content, "qux", false), Vec::<usize>::new());
+        assert!(matching_lines(content, "", false).is_empty());
     }
 
+    /// Tests that the generation_task correctly prunes empty directories from the
+    /// generated tree view when the corresponding config flags are set.
     #[tokio::test]
-    async fn lazy_load_task_handles_scan_error() {
+    async fn generation_task_prunes_empty_directories_from_tree() {
         // Arrange
         let mut harness = TestHarness::new();
-        let scanner = MockScanner::new();
-        let path_to_load = harness.root_path.join("lazy");
+        let generator = MockContentGenerator::new();
+        // The generator will include a tree. We check if the input it receives is correct.
+        generator.set_result(Ok("Success".to_string()));
+        let tokenizer = MockTokenizer { token_count: 1 };
 
-        // Configure scanner to fail for the shallow scan, which is what lazy_load uses
-        let scan_error = CoreError::NotADirectory(path_to_load.clone());
-        *scanner.shallow_result.lock().unwrap() = Err(scan_error.clone());
+        let file_path = harness.root_path.join("src/main.rs");
+        let empty_dir_path = harness.root_path.join("src/empty");
+
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.is_fully_scanned = true; // Required for pruning
+            state.config.remove_empty_directories = true; // Enable feature
+            state.config.include_tree_by_default = true;
+            state.selected_files.insert(file_path.clone());
+            state.full_file_list = vec![
+                FileItem {
+                    path: harness.root_path.join("src"),
+                    is_directory: true,
+                    ..Default::default()
+                },
+                FileItem {
+                    path: file_path,
+                    ..Default::default()
+                },
+                FileItem {
+                    path: empty_dir_path,
+                    is_directory: true,
+                    ..Default::default()
+                },
+            ];
+        }
 
         // Act
-        lazy_load_task(
-            path_to_load.clone(),
+        generation_task(
             harness.proxy.clone(),
             harness.state.clone(),
-            scanner,
-            None,
+            generator,
+            tokenizer,
         )
         .await;
 
         // Assert
-        // Expect a ShowError event.
-        let events = harness.get_n_events(1).await;
-        assert_eq!(events.len(), 1, "Expected one ShowError event");
-
-        match &events[0] {
-            UserEvent::ShowError(msg) => {
-                assert!(
-                    msg.contains("Failed to load directory"),
-                    "Error message is incorrect"
-                );
-                assert!(
-                    msg.contains("lazy"),
-                    "Error message should contain the path"
-                );
-                assert!(
-                    msg.contains("not a valid directory"),
-                    "Error message should contain the error detail"
-                );
-            }
-            _ => panic!("Expected a ShowError event."),
-        }
-
-        // Assert that state was not left inconsistent
-        let state = harness.state.lock().unwrap();
-        assert!(
-            !state.loaded_dirs.contains(&path_to_load),
-            "Failed path should not be marked as loaded."
-        );
+        // This test is implicitly asserting that the `remove_empty_directories` logic in `generation_task`
+        // is called and doesn't panic. A more advanced mock could capture the `items_for_tree` argument
+        // and assert its contents, but for coverage, simply executing the path is sufficient.
+        let events = harness.get_n_events(2).await;
+        assert!(matches!(events[0], UserEvent::ShowGeneratedContent { .. }));
+        assert!(matches!(events[1], UserEvent::StateUpdate(_)));
     }
 
     #[tokio::test]
-    async fn proactive_scan_task_cancels_between_phases() {
+    async fn generation_task_reuses_cached_token_counts_for_unchanged_files() {
         // Arrange
         let mut harness = TestHarness::new();
-        let mut scanner = MockScanner::new();
-
-        // Use the cancellation helper to pause the task between scan phases
-        let (shallow_scan_finished, allow_deep_scan_to_proceed) =
-            scanner.prepare_for_cancellation();
-
-        // Shallow scan succeeds.
-        let shallow_files = vec![FileItem {
-            path: harness.root_path.join("file.txt"),
-            ..Default::default()
-        }];
-        *scanner.shallow_result.lock().unwrap() = Ok((shallow_files.clone(), HashSet::new()));
-        *scanner.deep_result.lock().unwrap() = Ok((vec![], HashSet::new()));
-
-        harness.state.lock().unwrap().is_scanning = true;
-        let cancel_flag = harness.state.lock().unwrap().scan_cancellation_flag.clone();
-        let proxy_clone = harness.proxy.clone();
-        let state_clone = harness.state.clone();
-        let path_clone = harness.root_path.clone();
-
-        // Act
-        let task_handle = tokio::spawn(async move {
-            proactive_scan_task(proxy_clone, state_clone, path_clone, scanner).await
-        });
-
-        // 1. Wait for the signal from the mock that the shallow scan is complete.
-        shallow_scan_finished
-            .await
-            .expect("Mock scanner should signal that shallow scan finished");
-
-        // 2. Now that the task is paused, set the cancellation flag.
-        cancel_flag.store(true, Ordering::SeqCst);
-
-        // 3. Unblock the task, which will now immediately see the cancel flag and exit.
-        allow_deep_scan_to_proceed.send(()).unwrap();
-
-        // 4. Wait for the task to fully terminate.
-        task_handle.await.unwrap();
+        let file_a = harness.root_path.join("a.rs");
+        let file_b = harness.root_path.join("b.rs");
+        std::fs::write(&file_a, "fn a() {}").unwrap();
+        std::fs::write(&file_b, "fn b() {}").unwrap();
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.include_tree_by_default = false;
+            state.selected_files = [file_a.clone(), file_b.clone()].into_iter().collect();
+            state.full_file_list = vec![
+                FileItem {
+                    path: file_a.clone(),
+                    ..Default::default()
+                },
+                FileItem {
+                    path: file_b.clone(),
+                    ..Default::default()
+                },
+            ];
+        }
+        let tokenizer = CountingTokenizer::default();
+        let generator = || RealContentGenerator {
+            cancel_flag: Arc::new(AtomicBool::new(false)),
+            stream_generation: false,
+        };
+        let token_count_of = |event: &UserEvent| match event {
+            UserEvent::ShowGeneratedContent { token_count, .. } => *token_count,
+            other => panic!("Expected ShowGeneratedContent, got {:?}", other),
+        };
 
-        // Assert
-        // We need to drain the initial shallow update event first.
-        let _ = harness.get_n_events(1).await;
-        // Now, the ScanGuard will have fired a final state update on drop. Get that one.
-        let final_state_update = harness
-            .get_last_state_update()
-            .await
-            .expect("ScanGuard should send a final update on cancellation");
+        // Act 1: The first run counts both files plus the surrounding text.
+        generation_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            generator(),
+            tokenizer.clone(),
+        )
+        .await;
+        let first = token_count_of(&harness.get_n_events(2).await[0]);
+        assert_eq!(tokenizer.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(harness.state.lock().unwrap().token_cache.len(), 2);
 
-        assert!(
-            !final_state_update.is_scanning,
-            "Scanning should be false in the final state."
-        );
-        assert!(
-            !final_state_update.is_fully_scanned,
-            "Scan should not be marked as full."
-        );
+        // Act 2: Unchanged files are served from the cache.
+        generation_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            generator(),
+            tokenizer.clone(),
+        )
+        .await;
+        let second = token_count_of(&harness.get_n_events(2).await[0]);
+        assert_eq!(tokenizer.calls.load(Ordering::SeqCst), 4);
+        assert_eq!(first, second);
 
-        // The final AppState should be consistent
-        let final_app_state = harness.state.lock().unwrap();
-        assert!(!final_app_state.is_scanning);
-        assert!(final_app_state.scan_task.is_none());
+        // Act 3: A modified file is re-counted.
+        std::fs::write(&file_b, "fn b() { let x = 1; }").unwrap();
+        generation_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            generator(),
+            tokenizer.clone(),
+        )
+        .await;
+        let third = token_count_of(&harness.get_n_events(2).await[0]);
+        assert_eq!(tokenizer.calls.load(Ordering::SeqCst), 6);
+        assert_eq!(third, first + 5);
     }
 
     #[tokio::test]
-    async fn start_scan_on_path_handles_nonexistent_path() {
-        // Arrange
+    async fn reframe_task_adds_the_tree_without_re_counting_file_blocks() {
+        // Arrange: a generation without a tree.
         let mut harness = TestHarness::new();
-        let nonexistent_path = harness.root_path.join("nonexistent");
+        let file_a = harness.root_path.join("a.rs");
+        std::fs::write(&file_a, "fn a() {}").unwrap();
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.include_tree_by_default = false;
+            state.selected_files.insert(file_a.clone());
+            state.full_file_list = vec![FileItem {
+                path: file_a.clone(),
+                ..Default::default()
+            }];
+        }
+        let tokenizer = CountingTokenizer::default();
+        generation_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            RealContentGenerator {
+                cancel_flag: Arc::new(AtomicBool::new(false)),
+                stream_generation: false,
+            },
+            tokenizer.clone(),
+        )
+        .await;
+        harness.get_n_events(2).await;
+        let calls_after_generation = tokenizer.calls.load(Ordering::SeqCst);
 
         // Act
-        start_scan_on_path(
-            nonexistent_path,
+        harness.state.lock().unwrap().config.include_tree_by_default = true;
+        reframe_task(
             harness.proxy.clone(),
             harness.state.clone(),
-            false,
-        );
-
-        // Assert
-        // We must await the event, as it's sent from a spawned task.
-        let event = harness
-            .get_n_events(1)
-            .await
-            .pop()
-            .expect("Should have received one event");
+            tokenizer.clone(),
+        )
+        .await;
 
-        match event {
-            UserEvent::ShowError(msg) => {
-                // Check for the more specific error message from our fix.
-                assert!(msg.contains("Path does not exist"));
+        // Assert: only the text around the cached file block is counted again.
+        let events = harness.get_n_events(2).await;
+        match &events[0] {
+            UserEvent::ShowGeneratedContent { content, .. } => {
+                assert!(content.contains("# DIRECTORY TREE"));
+                assert!(content.contains("fn a() {}"));
             }
-            _ => panic!("Expected a ShowError event."),
+            other => panic!("Expected ShowGeneratedContent, got {:?}", other),
         }
-
+        assert!(matches!(events[1], UserEvent::StateUpdate(_)));
+        assert_eq!(
+            tokenizer.calls.load(Ordering::SeqCst),
+            calls_after_generation + 1
+        );
         let state = harness.state.lock().unwrap();
-        assert!(!state.is_scanning, "is_scanning should remain false.");
+        assert!(!state.is_generating);
+        assert!(state
+            .last_generated
+            .as_ref()
+            .unwrap()
+            .output
+            .content
+            .contains("# DIRECTORY TREE"));
     }
 
     #[tokio::test]
-    async fn start_scan_on_path_handles_path_is_file() {
-        // Arrange
+    async fn streamed_generation_previews_the_head_and_keeps_the_output_in_its_file() {
         let mut harness = TestHarness::new();
-        let file_path = harness.root_path.join("some_file.txt");
-        std::fs::write(&file_path, "content").unwrap();
-
-        // Act
-        // We expect this to run the scan on the parent directory.
-        start_scan_on_path(
-            file_path,
-            harness.proxy.clone(),
-            harness.state.clone(),
-            false,
-        );
+        let file_a = harness.root_path.join("a.rs");
+        std::fs::write(&file_a, "fn a() {}\n".repeat(20)).unwrap();
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.include_index = true;
+            state.config.include_manifest = true;
+            state.config.preview_generated_head_lines = Some(3);
+            state.selected_files.insert(file_a.clone());
+            state.full_file_list = vec![FileItem {
+                path: file_a.clone(),
+                ..Default::default()
+            }];
+        }
 
-        // Assert
-        // It should NOT send an error. It should start scanning the parent.
-        // It sends an initial state update setting is_scanning to true.
-        let events = harness.get_n_events(1).await;
-        assert_eq!(events.len(), 1);
-        match &events[0] {
-            UserEvent::StateUpdate(s) => {
-                assert!(s.is_scanning);
-                assert_eq!(s.current_path, harness.root_path.to_string_lossy());
+        let mut full_outputs = Vec::new();
+        for stream_generation in [false, true] {
+            generation_task(
+                harness.proxy.clone(),
+                harness.state.clone(),
+                RealContentGenerator {
+                    cancel_flag: Arc::new(AtomicBool::new(false)),
+                    stream_generation,
+                },
+                CountingTokenizer::default(),
+            )
+            .await;
+            match &harness.get_n_events(2).await[0] {
+                UserEvent::ShowGeneratedContent { content, .. } => {
+                    assert!(content.contains("more lines not shown"))
+                }
+                other => panic!("Expected ShowGeneratedContent, got {:?}", other),
             }
-            _ => panic!("Expected a StateUpdate event."),
+            let state = harness.state.lock().unwrap();
+            let held = state.generated_content.as_ref().unwrap();
+            assert_eq!(
+                matches!(held, HeldOutput::Spilled { .. }),
+                stream_generation
+            );
+            assert_eq!(
+                state.generation_history[0].content_hash,
+                held.content_hash()
+            );
+            full_outputs.push(held.text().unwrap().into_owned());
         }
 
-        // Lock the state mutably to check and then cancel.
-        let mut state = harness.state.lock().unwrap();
-        assert!(state.is_scanning, "is_scanning should be true.");
-        assert_eq!(state.current_path, harness.root_path.to_string_lossy());
-
-        // Cancel the scan to clean up the task and prevent test runner warnings.
-        state.cancel_current_scan();
+        // The timestamps may tick between the two runs.
+        let without_timestamp = |content: &str| -> Vec<String> {
+            content
+                .lines()
+                .filter(|line| !line.contains("Generated"))
+                .map(str::to_string)
+                .collect()
+        };
+        assert_eq!(
+            without_timestamp(&full_outputs[1]),
+            without_timestamp(&full_outputs[0])
+        );
+        assert!(full_outputs[1].contains("# INDEX"));
     }
 
     #[tokio::test]
-    async fn start_scan_on_path_preserves_state() {
-        // Arrange
+    async fn streamed_output_that_fits_the_preview_is_kept_for_reframing() {
         let mut harness = TestHarness::new();
-        let dir_to_expand = harness.root_path.join("src");
-        let new_scan_path = harness.root_path.join("new_project");
-        std::fs::create_dir_all(&new_scan_path).unwrap();
-
-        // Set initial state
+        let file_a = harness.root_path.join("a.rs");
+        std::fs::write(&file_a, "fn a() {}").unwrap();
         {
             let mut state = harness.state.lock().unwrap();
-            state.expanded_dirs.insert(dir_to_expand.clone());
+            state.config.include_index = true;
+            state.selected_files.insert(file_a.clone());
         }
 
-        // Act
-        // Call with preserve_state = true
-        start_scan_on_path(
-            new_scan_path.clone(),
+        generation_task(
             harness.proxy.clone(),
             harness.state.clone(),
-            true,
+            RealContentGenerator {
+                cancel_flag: Arc::new(AtomicBool::new(false)),
+                stream_generation: true,
+            },
+            CountingTokenizer::default(),
+        )
+        .await;
+
+        let shown = match &harness.get_n_events(2).await[0] {
+            UserEvent::ShowGeneratedContent { content, .. } => content.clone(),
+            other => panic!("Expected ShowGeneratedContent, got {:?}", other),
+        };
+        assert!(shown.contains("# INDEX"));
+        let state = harness.state.lock().unwrap();
+        assert!(state.generated_content.is_none());
+        let cached = state.last_generated.as_ref().unwrap();
+        assert!(!cached.output.content.contains("# INDEX"));
+        assert_eq!(
+            &cached.output.content[cached.output.file_ranges[0].1.clone()],
+            "fn a() {}"
         );
+    }
 
-        // Assert
-        // Wait for the initial StateUpdate event which confirms the task has started and set the initial state.
-        // This resolves the race condition.
-        let initial_update = harness
-            .get_n_events(1)
-            .await
-            .pop()
-            .expect("Task should send an initial StateUpdate");
-        assert!(matches!(initial_update, UserEvent::StateUpdate(_)));
+    #[tokio::test]
+    async fn estimate_builds_the_index_from_the_token_cache() {
+        let mut harness = TestHarness::new();
+        let file_a = harness.root_path.join("a.rs");
+        std::fs::write(&file_a, "fn a() {}\nrun();\n").unwrap();
+        {
+            let mut state = harness.state.lock().unwrap();
+            state.config.include_tree_by_default = false;
+            state.config.include_index = true;
+            state.selected_files.insert(file_a.clone());
+            state.full_file_list = vec![FileItem {
+                path: file_a.clone(),
+                ..Default::default()
+            }];
+        }
+        let tokenizer = CountingTokenizer::default();
 
-        // Now it's safe to check the state.
+        generation_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            RealContentGenerator {
+                cancel_flag: Arc::new(AtomicBool::new(false)),
+                stream_generation: false,
+            },
+            tokenizer.clone(),
+        )
+        .await;
+        let generated = match &harness.get_n_events(2).await[0] {
+            UserEvent::ShowGeneratedContent { token_count, .. } => *token_count,
+            other => panic!("Expected ShowGeneratedContent, got {:?}", other),
+        };
         {
             let state = harness.state.lock().unwrap();
-            assert!(state.is_scanning, "Scanning should have started.");
-            assert_eq!(state.current_path, new_scan_path.to_string_lossy());
-            assert!(
-                state.expanded_dirs.contains(&dir_to_expand),
-                "expanded_dirs should have been preserved."
-            );
+            let entry = &state.token_cache[&file_a];
+            assert_eq!((entry.block_bytes, entry.block_lines), (17, 2));
         }
 
-        // We need to wait for the scan to finish to avoid panics on drop.
-        // Let's get all events to clear the channel.
-        while harness.get_n_events(1).await.len() > 0 {}
+        estimation_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            tokenizer.clone(),
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await;
+        match &harness.get_n_events(2).await[0] {
+            UserEvent::TokenEstimate { tokens, .. } => assert_eq!(*tokens, generated),
+            other => panic!("Expected TokenEstimate, got {:?}", other),
+        }
     }
 
-    /// Comprehensive test for the RealFileSearcher implementation via the search_in_files task.
-    /// This test covers:
-    /// 1. Case-sensitive matching.
-    /// 2. Case-insensitive matching (default).
-    /// 3. Correctly skipping binary files and directories.
-    /// 4. Handling files where the search term is not found.
     #[tokio::test]
-    async fn search_in_files_with_real_searcher_covers_edge_cases() {
-        // Arrange
+    async fn tree_stats_task_counts_the_tokens_of_text_files() {
         let mut harness = TestHarness::new();
-        let searcher = RealFileSearcher; // Use the real implementation
+        let source = harness.root_path.join("main.rs");
+        let image = harness.root_path.join("logo.png");
+        std::fs::write(&source, "fn main() { run(); }").unwrap();
+        std::fs::write(&image, "not really a png").unwrap();
+        harness.state.lock().unwrap().full_file_list = vec![
+            FileItem {
+                path: source,
+                size: 20,
+                ..Default::default()
+            },
+            FileItem {
+                path: image,
+                size: 16,
+                is_binary: true,
+                ..Default::default()
+            },
+        ];
+        let tokenizer = CountingTokenizer::default();
 
-        // Create a diverse set of files
-        let text_file_path = harness.root_path.join("file.txt");
-        std::fs::write(&text_file_path, "find the MagicWord here").unwrap();
+        tree_stats_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            tokenizer.clone(),
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await;
 
-        let binary_file_path = harness.root_path.join("app.exe");
-        std::fs::write(&binary_file_path, &[0, 1, 2, 3, 255]).unwrap(); // A binary file
+        let stats = match harness.get_n_events(1).await.remove(0) {
+            UserEvent::TreeStats(stats) => stats,
+            other => panic!("Expected TreeStats, got {:?}", other),
+        };
+        assert!(stats.exact_tokens);
+        assert_eq!(stats.total_files, 2);
+        assert_eq!(stats.total_size, 36);
+        assert_eq!(stats.total_tokens, 5);
+        assert_eq!(tokenizer.calls.load(Ordering::SeqCst), 1);
+    }
 
-        let no_match_path = harness.root_path.join("another.txt");
-        std::fs::write(&no_match_path, "nothing to see").unwrap();
+    #[tokio::test]
+    async fn tree_stats_task_reuses_the_token_cache_and_skips_oversized_files() {
+        let mut harness = TestHarness::new();
+        let source = harness.root_path.join("main.rs");
+        let dump = harness.root_path.join("dump.sql");
+        std::fs::write(&source, "fn main() { run(); }").unwrap();
+        std::fs::write(&dump, "insert into t values (1);").unwrap();
+        harness.state.lock().unwrap().full_file_list = vec![
+            FileItem {
+                path: source,
+                size: 20,
+                ..Default::default()
+            },
+            FileItem {
+                path: dump,
+                size: MAX_CONTENT_BYTES + 1,
+                ..Default::default()
+            },
+        ];
+        let tokenizer = CountingTokenizer::default();
+
+        for _ in 0..2 {
+            tree_stats_task(
+                harness.proxy.clone(),
+                harness.state.clone(),
+                tokenizer.clone(),
+                Arc::new(AtomicBool::new(false)),
+            )
+            .await;
+            match harness.get_n_events(1).await.remove(0) {
+                UserEvent::TreeStats(stats) => assert_eq!(stats.total_tokens, 5),
+                other => panic!("Expected TreeStats, got {:?}", other),
+            }
+        }
+        assert_eq!(tokenizer.calls.load(Ordering::SeqCst), 1);
+    }
 
-        let dir_path = harness.root_path.join("subfolder");
-        std::fs::create_dir(&dir_path).unwrap();
+    #[tokio::test]
+    async fn cancelled_tree_stats_task_sends_nothing() {
+        let mut harness = TestHarness::new();
+        let source = harness.root_path.join("main.rs");
+        std::fs::write(&source, "fn main() { run(); }").unwrap();
+        harness.state.lock().unwrap().full_file_list = vec![FileItem {
+            path: source,
+            size: 20,
+            ..Default::default()
+        }];
+        let cancel_flag = harness.state.lock().unwrap().cancel_tree_stats();
+        harness.state.lock().unwrap().cancel_all_tasks();
 
-        // Populate state
+        tree_stats_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            CountingTokenizer::default(),
+            cancel_flag,
+        )
+        .await;
+
+        assert!(harness.event_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn estimation_task_matches_generation_and_uses_token_cache() {
+        // Arrange
+        let mut harness = TestHarness::new();
+        let file_a = harness.root_path.join("a.rs");
+        let file_b = harness.root_path.join("b.rs");
+        std::fs::write(&file_a, "fn a() {}").unwrap();
+        std::fs::write(&file_b, "fn b() {}\n").unwrap();
         {
             let mut state = harness.state.lock().unwrap();
+            state.config.include_tree_by_default = false;
+            state.selected_files = [file_a.clone(), file_b.clone()].into_iter().collect();
             state.full_file_list = vec![
                 FileItem {
-                    path: text_file_path.clone(),
-                    is_binary: false,
-                    ..Default::default()
-                },
-                FileItem {
-                    path: binary_file_path,
-                    is_binary: true,
-                    ..Default::default()
-                },
-                FileItem {
-                    path: no_match_path,
-                    is_binary: false,
+                    path: file_a.clone(),
                     ..Default::default()
                 },
                 FileItem {
-                    path: dir_path,
-                    is_directory: true,
+                    path: file_b.clone(),
                     ..Default::default()
                 },
             ];
+            state.is_estimating = true;
         }
+        let tokenizer = CountingTokenizer::default();
 
-        // --- SCENARIO 1: Case-sensitive search (no match) ---
-        {
-            let mut state = harness.state.lock().unwrap();
-            state.config.case_sensitive_search = true;
-            state.content_search_query = "magicword".to_string(); // Lowercase
-        }
-        // Pass 'searcher' by value (it's Copy)
-        search_in_files(harness.proxy.clone(), harness.state.clone(), searcher).await;
-        // Assert against the AppState, not the UiState
-        assert!(
-            harness
-                .state
-                .lock()
-                .unwrap()
-                .content_search_results
-                .is_empty(),
-            "Case-sensitive search should not find 'magicword'"
-        );
-        // Drain the event queue
-        let _ = harness.get_last_state_update().await;
+        // Act 1: A cold estimate counts each file plus the layout.
+        estimation_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            tokenizer.clone(),
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await;
+        let events = harness.get_n_events(2).await;
+        let (estimate, files) = match &events[0] {
+            UserEvent::TokenEstimate { tokens, files } => (*tokens, *files),
+            other => panic!("Expected TokenEstimate, got {:?}", other),
+        };
+        assert_eq!(files, 2);
+        assert_eq!(tokenizer.calls.load(Ordering::SeqCst), 3);
+        assert!(!harness.state.lock().unwrap().is_estimating);
 
-        // --- SCENARIO 2: Case-sensitive search (exact match) ---
-        {
-            let mut state = harness.state.lock().unwrap();
-            state.config.case_sensitive_search = true;
-            state.content_search_query = "MagicWord".to_string(); // Exact case
-        }
-        search_in_files(harness.proxy.clone(), harness.state.clone(), searcher).await;
-        // Assert against the AppState for correctness
-        let final_state = harness.state.lock().unwrap();
-        assert_eq!(
-            final_state.content_search_results.len(),
-            1,
-            "Case-sensitive search should find 'MagicWord'"
-        );
-        assert!(final_state.content_search_results.contains(&text_file_path));
+        // Act 2: A real generation reuses the estimate's cache and agrees with it.
+        generation_task(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            RealContentGenerator {
+                cancel_flag: Arc::new(AtomicBool::new(false)),
+                stream_generation: false,
+            },
+            tokenizer.clone(),
+        )
+        .await;
+        let generated = match &harness.get_n_events(2).await[0] {
+            UserEvent::ShowGeneratedContent { token_count, .. } => *token_count,
+            other => panic!("Expected ShowGeneratedContent, got {:?}", other),
+        };
+        assert_eq!(tokenizer.calls.load(Ordering::SeqCst), 4);
+        assert_eq!(generated, estimate);
     }
 
-    /// Tests that the generation_task correctly prunes empty directories from the
-    /// generated tree view when the corresponding config flags are set.
     #[tokio::test]
-    async fn generation_task_prunes_empty_directories_from_tree() {
+    async fn estimation_task_stops_when_cancelled() {
         // Arrange
         let mut harness = TestHarness::new();
-        let generator = MockContentGenerator::new();
-        // The generator will include a tree. We check if the input it receives is correct.
-        generator.set_result(Ok("Success".to_string()));
-        let tokenizer = MockTokenizer { token_count: 1 };
-
-        let file_path = harness.root_path.join("src/main.rs");
-        let empty_dir_path = harness.root_path.join("src/empty");
-
+        let file_a = harness.root_path.join("a.rs");
+        std::fs::write(&file_a, "fn a() {}").unwrap();
         {
             let mut state = harness.state.lock().unwrap();
-            state.is_fully_scanned = true; // Required for pruning
-            state.config.remove_empty_directories = true; // Enable feature
-            state.config.include_tree_by_default = true;
-            state.selected_files.insert(file_path.clone());
-            state.full_file_list = vec![
-                FileItem {
-                    path: harness.root_path.join("src"),
-                    is_directory: true,
-                    ..Default::default()
-                },
-                FileItem {
-                    path: file_path,
-                    ..Default::default()
-                },
-                FileItem {
-                    path: empty_dir_path,
-                    is_directory: true,
-                    ..Default::default()
-                },
-            ];
+            state.selected_files = [file_a.clone()].into_iter().collect();
+            state.full_file_list = vec![FileItem {
+                path: file_a,
+                ..Default::default()
+            }];
+            state.is_estimating = true;
         }
 
         // Act
-        generation_task(
+        estimation_task(
             harness.proxy.clone(),
             harness.state.clone(),
-            generator,
-            tokenizer,
+            CountingTokenizer::default(),
+            Arc::new(AtomicBool::new(true)),
         )
         .await;
 
-        // Assert
-        // This test is implicitly asserting that the `remove_empty_directories` logic in `generation_task`
-        // is called and doesn't panic. A more advanced mock could capture the `items_for_tree` argument
-        // and assert its contents, but for coverage, simply executing the path is sufficient.
+        // Assert: only the final state update and the status message are sent.
         let events = harness.get_n_events(2).await;
-        assert!(matches!(events[0], UserEvent::ShowGeneratedContent { .. }));
-        assert!(matches!(events[1], UserEvent::StateUpdate(_)));
+        assert!(matches!(events[0], UserEvent::StateUpdate(_)));
+        assert!(
+            matches!(&events[1], UserEvent::Status(message) if message == "Estimate cancelled.")
+        );
+        let state = harness.state.lock().unwrap();
+        assert!(!state.is_estimating);
     }
 
     /// Test for the lazy load happy path, using the proper entry point.
@@ -1725,7 +4447,7 @@ mod tests {
         use std::os::unix::fs::PermissionsExt;
 
         let harness = TestHarness::new();
-        let searcher = RealFileSearcher;
+        let searcher = RealFileSearcher::default();
 
         let unreadable_file_path = harness.root_path.join("unreadable.txt");
         std::fs::write(&unreadable_file_path, "you can't read me").unwrap();
@@ -1747,7 +4469,13 @@ mod tests {
         }
 
         // Act
-        search_in_files(harness.proxy.clone(), harness.state.clone(), searcher).await;
+        search_in_files(
+            harness.proxy.clone(),
+            harness.state.clone(),
+            searcher,
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await;
 
         // Assert
         // The main assertion is that the task completes without panicking.
@@ -1768,6 +4496,7 @@ mod tests {
         let cancel_flag = Arc::new(AtomicBool::new(false));
         let generator = RealContentGenerator {
             cancel_flag: cancel_flag.clone(),
+            stream_generation: false,
         };
         let tokenizer = MockTokenizer { token_count: 0 };
 
@@ -1796,13 +4525,13 @@ mod tests {
         task_handle.await.unwrap();
 
         // Assert
-        let events = harness.get_n_events(2).await;
-        // We expect ONLY a StateUpdate event that resets the is_generating flag.
-        // We should NOT receive a ShowGeneratedContent or ShowError event.
+        let events = harness.get_n_events(3).await;
+        // We expect ONLY a StateUpdate event that resets the is_generating flag, followed
+        // by the status message. We should NOT receive a ShowGeneratedContent or ShowError event.
         assert_eq!(
             events.len(),
-            1,
-            "Only one final StateUpdate event should be sent on cancellation."
+            2,
+            "Only a final StateUpdate and a Status event should be sent on cancellation."
         );
 
         let final_state = match &events[0] {
@@ -1812,7 +4541,7 @@ mod tests {
 
         assert!(!final_state.is_generating, "is_generating should be reset.");
         assert!(
-            final_state.status_message.contains("Generation cancelled"),
+            matches!(&events[1], UserEvent::Status(message) if message == "Generation cancelled."),
             "Status message should indicate cancellation."
         );
     }
@@ -1837,4 +4566,31 @@ mod tests {
             "No events should be sent if not scanning."
         );
     }
+
+    #[tokio::test]
+    async fn progress_throttle_holds_back_updates_and_flushes_the_latest() {
+        let mut harness = TestHarness::new();
+        let throttle = ProgressThrottle::new(harness.proxy.clone(), Duration::from_secs(3600));
+        let callback = throttle.callback();
+        for files_scanned in 1..=100 {
+            callback(ScanProgress {
+                files_scanned,
+                large_files_skipped: 0,
+                current_scanning_path: String::new(),
+            });
+        }
+        throttle.flush();
+        throttle.flush();
+
+        let scanned: Vec<usize> = harness
+            .get_n_events(3)
+            .await
+            .into_iter()
+            .filter_map(|event| match event {
+                UserEvent::ScanProgress(progress) => Some(progress.files_scanned),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(scanned, vec![1, 100]);
+    }
 }
@@ -5,12 +5,18 @@
 //! display-related properties. It is purely for data transformation and does not
 //! mutate the application state.
 
-use crate::app::state::AppState;
-use crate::config::AppConfig;
-use crate::core::FileItem;
-use serde::Serialize;
+use crate::app::state::{AppState, ContentSearchScope, ContentSearchThreshold, PathIds};
+use crate::config::{AppConfig, ConcatOrder, SortOrder};
+use crate::core::{
+    FileItem, FileMatches, FilenameMatchMode, GitStatus, MatchSnippet, SearchEngine, SearchFilter,
+    TreeGenerator, TreePosition,
+};
+use globset::GlobMatcher;
+use rayon::prelude::*;
+use serde::{Serialize, Serializer};
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// A serializable representation of the application state for the UI.
 #[derive(Serialize, Clone, Debug)]
@@ -23,12 +29,25 @@ pub struct UiState {
     pub selected_files_count: usize,
     pub is_scanning: bool,
     pub is_generating: bool,
+    pub is_estimating: bool,
     pub is_fully_scanned: bool,
+    /// `true` while the preview shows only the head of the generated output.
+    pub generated_preview_truncated: bool,
     pub status_message: String,
     pub search_query: String,
+    pub filename_match_mode: FilenameMatchMode,
     pub extension_filter: String,
     pub content_search_query: String,
+    pub content_search_scope: ContentSearchScope,
+    pub content_search_threshold: ContentSearchThreshold,
+    /// Number of files matching the content search.
+    pub content_search_file_count: usize,
+    /// Total number of occurrences of the content search query across all files.
+    pub content_search_match_count: usize,
+    /// `true` if the content search matched more files than are shown.
+    pub content_search_truncated: bool,
     pub current_config_filename: Option<String>,
+    pub active_profile: Option<String>,
     pub scan_progress: crate::core::ScanProgress,
     pub active_ignore_patterns: HashSet<String>,
     pub patterns_need_rescan: bool,
@@ -37,19 +56,307 @@ pub struct UiState {
 /// A serializable representation of a single node in the file tree for the UI.
 #[derive(Serialize, Clone, Debug, PartialEq)]
 pub struct TreeNode {
+    /// The entry's id from `AppState::path_ids`, used by the frontend to refer back to
+    /// this exact path in commands.
+    pub id: Option<usize>,
     pub name: String,
+    /// Serialized lossily for display. Commands should use `id` instead.
+    #[serde(serialize_with = "serialize_path_lossy")]
     pub path: PathBuf,
     pub is_directory: bool,
     pub is_binary: bool,
+    /// `true` if the file looks minified or generated, see `FileItem::likely_generated`.
+    pub likely_generated: bool,
+    /// The file's git status, see `FileItem::git_status`.
+    pub git_status: Option<GitStatus>,
     pub size: u64,
+    /// Last modification time in seconds since the Unix epoch. `None` for directories
+    /// and for files whose platform does not report one.
+    pub mtime: Option<u64>,
+    /// Occurrences of the content search query in this file, or in the visible files
+    /// below this directory.
+    pub content_match_count: usize,
     pub children: Vec<TreeNode>,
     pub selection_state: String,
     pub is_expanded: bool,
     pub is_match: bool,
     pub is_previewed: bool,
+    /// `true` for files that are pinned, i.e. re-selected by every scan.
+    pub is_pinned: bool,
     /// Indicates if the children of this directory have been loaded.
     /// This is used for the lazy-loading UI.
     pub children_loaded: bool,
+    /// Non-zero only for the "… and N more" node that ends a directory's children when
+    /// more than `AppConfig::max_children_displayed` are visible: the number of entries
+    /// left out. Its `path` is the directory's.
+    pub hidden_children: usize,
+}
+
+impl TreeNode {
+    /// The "… and `hidden` more" node that stands in for the entries of `directory`
+    /// that are not shown.
+    fn more_children(directory: &Path, hidden: usize) -> Self {
+        TreeNode {
+            id: None,
+            name: format!("… and {hidden} more"),
+            path: directory.to_path_buf(),
+            is_directory: false,
+            is_binary: false,
+            likely_generated: false,
+            git_status: None,
+            size: 0,
+            mtime: None,
+            content_match_count: 0,
+            children: Vec::new(),
+            selection_state: "none".to_string(),
+            is_expanded: false,
+            is_match: false,
+            is_previewed: false,
+            is_pinned: false,
+            children_loaded: true,
+            hidden_children: hidden,
+        }
+    }
+}
+
+/// The content search snippets of one matching file.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ContentSearchFile {
+    /// The file's id from `AppState::path_ids`, see `TreeNode::id`.
+    pub id: Option<usize>,
+    #[serde(serialize_with = "serialize_path_lossy")]
+    pub path: PathBuf,
+    pub match_count: usize,
+    pub snippets: Vec<MatchSnippet>,
+}
+
+/// Converts content search results into the per-file snippet lists shown by the UI,
+/// sorted by path.
+pub fn content_search_files(
+    state: &AppState,
+    matches: HashMap<PathBuf, FileMatches>,
+) -> Vec<ContentSearchFile> {
+    let mut files: Vec<ContentSearchFile> = matches
+        .into_iter()
+        .map(|(path, file_matches)| ContentSearchFile {
+            id: Some(state.path_ids.id(&path)),
+            path,
+            match_count: file_matches.count,
+            snippets: file_matches.snippets,
+        })
+        .collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+/// The files that differ between the last two generations, relative to `current_path`
+/// and sorted, and how the token count changed.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct GenerationDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    pub token_delta: i64,
+}
+
+/// What a generation of the current selection would include: the files in output
+/// order and relative to `current_path`, their total size in bytes and the number of
+/// entries in the directory tree, or `None` if no tree is included.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct GenerationPlan {
+    pub files: Vec<String>,
+    pub total_size: u64,
+    pub tree_entries: Option<usize>,
+}
+
+/// Works out what `generation_task` would include for the current selection, with the
+/// same selection order and tree pruning, without reading any file.
+pub fn generation_plan(state: &AppState) -> GenerationPlan {
+    let root = Path::new(&state.current_path);
+    let sizes: HashMap<&Path, u64> = state
+        .full_file_list
+        .iter()
+        .filter(|item| !item.is_directory)
+        .map(|item| (item.path.as_path(), item.size))
+        .collect();
+    let selected = get_selected_files_in_generation_order(state);
+    let total_size = selected
+        .iter()
+        .filter_map(|path| sizes.get(path.as_path()))
+        .sum();
+    let tree_entries = (state.config.output_tree_position() != TreePosition::None).then(|| {
+        let items = items_for_tree(
+            &state.config,
+            state.full_file_list.clone(),
+            state.is_fully_scanned,
+        );
+        TreeGenerator::count_entries(&items, root, &state.config.tree_ignore_patterns)
+    });
+    GenerationPlan {
+        files: selected
+            .iter()
+            .map(|path| {
+                path.strip_prefix(root)
+                    .unwrap_or(path)
+                    .display()
+                    .to_string()
+            })
+            .collect(),
+        total_size,
+        tree_entries,
+    }
+}
+
+/// The entries the directory tree of an output is drawn from. Empty directories are
+/// pruned if configured and the whole directory has been scanned.
+pub fn items_for_tree(
+    config: &AppConfig,
+    files_for_tree: Vec<FileItem>,
+    is_fully_scanned: bool,
+) -> Vec<FileItem> {
+    if config.remove_empty_directories && is_fully_scanned {
+        tracing::info!("🌳 Pruning empty directories from the generated tree.");
+        SearchEngine::remove_empty_directories(
+            files_for_tree.clone(),
+            &files_for_tree,
+            &HashSet::new(),
+        )
+        .0
+    } else {
+        files_for_tree
+    }
+}
+
+/// Compares `AppState::previous_generation` with `latest_generation`, or returns
+/// `None` if there have not been two generations yet.
+pub fn generation_diff(state: &AppState) -> Option<GenerationDiff> {
+    let previous = state.previous_generation.as_ref()?;
+    let latest = state.latest_generation.as_ref()?;
+    let root = Path::new(&state.current_path);
+    let display = |path: &PathBuf| {
+        path.strip_prefix(root)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+    let sorted = |mut paths: Vec<String>| {
+        paths.sort();
+        paths
+    };
+
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    for (path, hash) in &latest.file_hashes {
+        match previous.file_hashes.get(path) {
+            None => added.push(display(path)),
+            Some(previous_hash) if previous_hash != hash => changed.push(display(path)),
+            Some(_) => {}
+        }
+    }
+    let removed = previous
+        .file_hashes
+        .keys()
+        .filter(|path| !latest.file_hashes.contains_key(*path))
+        .map(display)
+        .collect();
+
+    Some(GenerationDiff {
+        added: sorted(added),
+        removed: sorted(removed),
+        changed: sorted(changed),
+        token_delta: latest.token_count as i64 - previous.token_count as i64,
+    })
+}
+
+/// The files, bytes and tokens of one file extension in [`TreeStats`]. Files without an
+/// extension are tallied under an empty `extension`.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExtensionStats {
+    pub extension: String,
+    pub files: usize,
+    pub size: u64,
+    pub tokens: usize,
+}
+
+/// Aggregate statistics over all scanned files, by extension sorted by size (largest
+/// first). `exact_tokens` tells whether the token counts came from the tokenizer or
+/// were estimated from the file sizes.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct TreeStats {
+    pub extensions: Vec<ExtensionStats>,
+    pub total_files: usize,
+    pub total_size: u64,
+    pub total_tokens: usize,
+    pub exact_tokens: bool,
+}
+
+/// Tallies the files in `files` per extension. Token counts are looked up in
+/// `exact_tokens` if given, otherwise estimated as a quarter of the file size. Binary
+/// files are counted with zero tokens, as they are never concatenated.
+pub fn tree_stats(files: &[FileItem], exact_tokens: Option<&HashMap<PathBuf, usize>>) -> TreeStats {
+    let tokens_of = |item: &FileItem| match exact_tokens {
+        _ if item.is_binary => 0,
+        Some(counts) => counts.get(&item.path).copied().unwrap_or(0),
+        None => item.size.div_ceil(4) as usize,
+    };
+    let by_extension = files
+        .par_iter()
+        .filter(|item| !item.is_directory)
+        .fold(HashMap::<String, ExtensionStats>::new, |mut tally, item| {
+            let extension = item
+                .path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let entry = tally
+                .entry(extension.clone())
+                .or_insert_with(|| ExtensionStats {
+                    extension,
+                    ..Default::default()
+                });
+            entry.files += 1;
+            entry.size += item.size;
+            entry.tokens += tokens_of(item);
+            tally
+        })
+        .reduce(HashMap::new, |mut merged, tally| {
+            for (extension, stats) in tally {
+                merged
+                    .entry(extension)
+                    .and_modify(|entry: &mut ExtensionStats| {
+                        entry.files += stats.files;
+                        entry.size += stats.size;
+                        entry.tokens += stats.tokens;
+                    })
+                    .or_insert(stats);
+            }
+            merged
+        });
+
+    let mut extensions: Vec<ExtensionStats> = by_extension.into_values().collect();
+    extensions.sort_by(|a, b| {
+        b.size
+            .cmp(&a.size)
+            .then_with(|| a.extension.cmp(&b.extension))
+    });
+    TreeStats {
+        total_files: extensions.iter().map(|stats| stats.files).sum(),
+        total_size: extensions.iter().map(|stats| stats.size).sum(),
+        total_tokens: extensions.iter().map(|stats| stats.tokens).sum(),
+        exact_tokens: exact_tokens.is_some(),
+        extensions,
+    }
+}
+
+/// Serializes a path as a (possibly lossy) UTF-8 string, so that file names that
+/// are not valid UTF-8 never make the whole `UiState` fail to serialize.
+fn serialize_path_lossy<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&path.to_string_lossy())
+}
+
+/// Converts a timestamp to whole seconds since the Unix epoch, if it is after it.
+fn unix_seconds(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
 }
 
 /// Creates the complete `UiState` from the current `AppState`.
@@ -60,17 +367,24 @@ pub fn generate_ui_state(state: &AppState) -> UiState {
     {
         Vec::new()
     } else {
+        let name_filter = name_filter(state);
+        let name_glob = SearchEngine::compile_glob(&name_filter);
         let args = BuildTreeArgs {
             items: &state.filtered_file_list,
+            ids: &state.path_ids,
             root_path: &PathBuf::from(&state.current_path),
             selected: &state.selected_files,
+            pinned: &state.config.pinned_files,
             expanded: &state.expanded_dirs,
             loaded_dirs: &state.loaded_dirs,
             content_search_matches: &state.content_search_results,
-            filename_query: &state.search_query,
+            name_filter: &name_filter,
+            name_glob: &name_glob,
             extension_filter: &state.extension_filter,
-            case_sensitive: state.config.case_sensitive_search,
             previewed_path: &state.previewed_file_path,
+            sort_order: state.config.sort_order,
+            max_children: state.config.max_children_displayed,
+            children_shown: &state.children_shown,
         };
         build_tree_nodes(args)
     };
@@ -95,12 +409,21 @@ pub fn generate_ui_state(state: &AppState) -> UiState {
         selected_files_count: state.selected_files.len(),
         is_scanning: state.is_scanning,
         is_generating: state.is_generating,
+        is_estimating: state.is_estimating,
         is_fully_scanned: state.is_fully_scanned,
+        generated_preview_truncated: state.generated_content.is_some(),
         status_message,
         search_query: state.search_query.clone(),
+        filename_match_mode: state.filename_match_mode,
         extension_filter: state.extension_filter.clone(),
         content_search_query: state.content_search_query.clone(),
+        content_search_scope: state.content_search_scope,
+        content_search_threshold: state.content_search_threshold,
+        content_search_file_count: state.content_search_results.len(),
+        content_search_match_count: state.content_search_results.values().sum(),
+        content_search_truncated: state.content_search_truncated,
         current_config_filename: state.current_config_filename.clone(),
+        active_profile: state.active_profile.clone(),
         scan_progress: state.scan_progress.clone(),
         active_ignore_patterns: state.active_ignore_patterns.clone(),
         patterns_need_rescan: state.patterns_need_rescan,
@@ -114,6 +437,8 @@ pub fn generate_ui_state(state: &AppState) -> UiState {
 /// ensuring that search results are visible in the file tree.
 pub fn auto_expand_for_matches(state: &mut AppState) {
     let root_path = PathBuf::from(&state.current_path);
+    let name_filter = name_filter(state);
+    let name_glob = SearchEngine::compile_glob(&name_filter);
     let matches: Vec<PathBuf> = state
         .filtered_file_list
         .iter()
@@ -122,19 +447,8 @@ pub fn auto_expand_for_matches(state: &mut AppState) {
                 return false;
             }
 
-            let file_name = item.path.file_name().unwrap_or_default().to_string_lossy();
-
-            let name_match = if !state.search_query.is_empty() {
-                if state.config.case_sensitive_search {
-                    file_name.contains(&state.search_query)
-                } else {
-                    file_name
-                        .to_lowercase()
-                        .contains(&state.search_query.to_lowercase())
-                }
-            } else {
-                false
-            };
+            let name_match = !name_filter.query.is_empty()
+                && SearchEngine::matches_query(&item.path, &name_filter, &name_glob);
 
             let extension_match = if !state.extension_filter.is_empty() {
                 matches_extension(&item.path, &state.extension_filter)
@@ -142,7 +456,7 @@ pub fn auto_expand_for_matches(state: &mut AppState) {
                 false
             };
 
-            let content_match = state.content_search_results.contains(&item.path);
+            let content_match = state.content_search_results.contains_key(&item.path);
 
             name_match || extension_match || content_match
         })
@@ -162,18 +476,62 @@ pub fn auto_expand_for_matches(state: &mut AppState) {
     }
 }
 
+/// The filename query of `state` as a filter, matched the same way `SearchEngine`
+/// matches it when filtering the file list.
+pub fn name_filter(state: &AppState) -> SearchFilter {
+    SearchFilter {
+        query: state.search_query.clone(),
+        match_mode: state.filename_match_mode,
+        root: PathBuf::from(&state.current_path),
+        extension: String::new(),
+        case_sensitive: state.config.case_sensitive_search,
+        min_size: None,
+        max_size: None,
+        exclude_empty: false,
+        modified_since: None,
+        tracked_paths: None,
+    }
+}
+
 /// Arguments for the `build_tree_nodes` function.
 struct BuildTreeArgs<'a> {
     items: &'a [FileItem],
+    ids: &'a PathIds,
     root_path: &'a Path,
     selected: &'a HashSet<PathBuf>,
+    pinned: &'a HashSet<PathBuf>,
     expanded: &'a HashSet<PathBuf>,
     loaded_dirs: &'a HashSet<PathBuf>,
-    content_search_matches: &'a HashSet<PathBuf>,
-    filename_query: &'a str,
+    content_search_matches: &'a HashMap<PathBuf, usize>,
+    /// The filename filter, to mark the entries matching it, see `name_filter`.
+    name_filter: &'a SearchFilter,
+    name_glob: &'a Result<Option<GlobMatcher>, globset::Error>,
     extension_filter: &'a str,
-    case_sensitive: bool,
     previewed_path: &'a Option<PathBuf>,
+    sort_order: SortOrder,
+    max_children: Option<usize>,
+    children_shown: &'a HashMap<PathBuf, usize>,
+}
+
+impl BuildTreeArgs<'_> {
+    /// Cuts the sorted `nodes` of `directory` down to the entries shown and appends an
+    /// "… and N more" node for the rest.
+    fn limit_children(&self, directory: &Path, nodes: &mut Vec<TreeNode>) {
+        let Some(max) = self.max_children else {
+            return;
+        };
+        let shown = self
+            .children_shown
+            .get(directory)
+            .copied()
+            .unwrap_or(0)
+            .max(max);
+        if nodes.len() > shown {
+            let hidden = nodes.len() - shown;
+            nodes.truncate(shown);
+            nodes.push(TreeNode::more_children(directory, hidden));
+        }
+    }
 }
 
 /// A transient struct used during tree construction for memoizing selection counts.
@@ -228,12 +586,23 @@ fn get_recursive_selection_counts(
     counts
 }
 
-/// Sorts a list of TreeNodes: directories first, then alphabetically.
-fn sort_tree_nodes(nodes: &mut [TreeNode]) {
+/// Sorts a list of TreeNodes: directories first, then by the configured sort order.
+/// Ties are broken alphabetically so the order is always deterministic.
+fn sort_tree_nodes(
+    nodes: &mut [TreeNode],
+    sort_order: SortOrder,
+    item_map: &HashMap<PathBuf, &FileItem>,
+) {
+    let mtime = |node: &TreeNode| item_map.get(&node.path).and_then(|item| item.mtime);
     nodes.sort_by(|a, b| match (a.is_directory, b.is_directory) {
         (true, false) => std::cmp::Ordering::Less,
         (false, true) => std::cmp::Ordering::Greater,
-        _ => a.name.cmp(&b.name),
+        _ => match sort_order {
+            SortOrder::NameAsc => a.name.cmp(&b.name),
+            SortOrder::NameDesc => b.name.cmp(&a.name),
+            SortOrder::SizeDesc => b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)),
+            SortOrder::ModifiedDesc => mtime(b).cmp(&mtime(a)).then_with(|| a.name.cmp(&b.name)),
+        },
     });
 }
 
@@ -270,17 +639,8 @@ fn build_node_recursive(
         "none".to_string()
     };
 
-    let name_match = if !args.filename_query.is_empty() {
-        if args.case_sensitive {
-            file_name_str.contains(args.filename_query)
-        } else {
-            file_name_str
-                .to_lowercase()
-                .contains(&args.filename_query.to_lowercase())
-        }
-    } else {
-        false
-    };
+    let name_match = !args.name_filter.query.is_empty()
+        && SearchEngine::matches_query(&item.path, args.name_filter, args.name_glob);
 
     let extension_match = if !args.extension_filter.is_empty() {
         matches_extension(&item.path, args.extension_filter)
@@ -288,7 +648,12 @@ fn build_node_recursive(
         false
     };
 
-    let content_match = args.content_search_matches.contains(&item.path);
+    let file_match_count = args
+        .content_search_matches
+        .get(&item.path)
+        .copied()
+        .unwrap_or(0);
+    let content_match = file_match_count > 0;
     let is_previewed = args.previewed_path.as_ref() == Some(&item.path);
 
     let mut children_nodes = Vec::new();
@@ -301,22 +666,43 @@ fn build_node_recursive(
                 })
                 .collect();
 
-            sort_tree_nodes(&mut children_nodes);
+            sort_tree_nodes(&mut children_nodes, args.sort_order, item_map);
         }
     }
 
+    let content_match_count = if item.is_directory {
+        children_nodes
+            .iter()
+            .map(|child| child.content_match_count)
+            .sum()
+    } else {
+        file_match_count
+    };
+    args.limit_children(path, &mut children_nodes);
+
     TreeNode {
+        id: Some(args.ids.id(&item.path)),
         name: file_name_str.to_string(),
         path: item.path.clone(),
         is_directory: item.is_directory,
         is_binary: item.is_binary,
+        likely_generated: item.likely_generated,
+        git_status: item.git_status,
         size: item.size,
+        mtime: if item.is_directory {
+            None
+        } else {
+            item.mtime.and_then(unix_seconds)
+        },
+        content_match_count,
         children: children_nodes,
         selection_state,
         is_expanded: args.expanded.contains(&item.path),
         is_match: name_match || extension_match || content_match,
         is_previewed,
+        is_pinned: args.pinned.contains(&item.path),
         children_loaded: !item.is_directory || args.loaded_dirs.contains(&item.path),
+        hidden_children: 0,
     }
 }
 
@@ -361,7 +747,8 @@ fn build_tree_nodes(args: BuildTreeArgs) -> Vec<TreeNode> {
         .collect();
 
     // Step 4: Sort the final root nodes.
-    sort_tree_nodes(&mut root_nodes);
+    sort_tree_nodes(&mut root_nodes, args.sort_order, &item_map);
+    args.limit_children(args.root_path, &mut root_nodes);
 
     root_nodes
 }
@@ -407,52 +794,94 @@ pub fn get_directory_selection_state(
     }
 }
 
-/// Returns a list of the selected file paths in natural tree order.
+/// Returns a list of the selected file paths in natural tree order, or in the order of
+/// `AppState::generation_order_override` for the files it lists.
 pub fn get_selected_files_in_tree_order(state: &AppState) -> Vec<PathBuf> {
+    get_selected_files_in_order(state, ConcatOrder::TreeOrder)
+}
+
+/// Returns a list of the selected file paths in the order they are concatenated into
+/// the output: sorted by `AppConfig::concat_order`, except for the files listed in
+/// `AppState::generation_order_override`, which come first in that order.
+pub fn get_selected_files_in_generation_order(state: &AppState) -> Vec<PathBuf> {
+    get_selected_files_in_order(state, state.config.concat_order)
+}
+
+fn get_selected_files_in_order(state: &AppState, concat_order: ConcatOrder) -> Vec<PathBuf> {
     // Use full_file_list to ensure all selected files are included,
     // regardless of the current search filter. This list already respects ignore patterns.
     let mut selected_file_items: Vec<&FileItem> = state
         .full_file_list
         .iter()
-        .filter(|item| !item.is_directory && state.selected_files.contains(&item.path))
+        .filter(|item| {
+            !item.is_directory
+                && state.selected_files.contains(&item.path)
+                && !state.config.excludes_as_empty(item)
+        })
         .collect();
 
-    selected_file_items.sort_by_key(|a| a.path.clone());
+    match concat_order {
+        ConcatOrder::TreeOrder => selected_file_items.sort_by(|a, b| a.path.cmp(&b.path)),
+        ConcatOrder::ExtensionThenName => selected_file_items.sort_by_cached_key(|item| {
+            let extension = item
+                .path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase());
+            (
+                extension,
+                item.path.file_name().map(|name| name.to_owned()),
+                item.path.clone(),
+            )
+        }),
+        ConcatOrder::SizeAsc => selected_file_items
+            .sort_by(|a, b| a.size.cmp(&b.size).then_with(|| a.path.cmp(&b.path))),
+        ConcatOrder::SizeDesc => selected_file_items
+            .sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path))),
+    }
 
-    selected_file_items
+    let sorted: Vec<PathBuf> = selected_file_items
         .into_iter()
         .map(|item| item.path.clone())
-        .collect()
-}
-
-/// Determines the programming language from a file path for syntax highlighting.
-pub fn get_language_from_path(path: &Path) -> String {
-    match path.extension().and_then(|s| s.to_str()) {
-        Some("rs") => "rust",
-        Some("js") | Some("mjs") | Some("cjs") => "javascript",
-        Some("ts") | Some("tsx") => "typescript",
-        Some("py") => "python",
-        Some("html") | Some("htm") => "html",
-        Some("css") => "css",
-        Some("json") => "json",
-        Some("md") => "markdown",
-        Some("toml") => "toml",
-        Some("yaml") | Some("yml") => "yaml",
-        Some("sh") => "shell",
-        Some("go") => "go",
-        Some("java") => "java",
-        Some("c") | Some("h") => "c",
-        Some("cpp") | Some("hpp") | Some("cxx") | Some("hxx") => "cpp",
-        _ => "plaintext",
-    }
-    .to_string()
+        .collect();
+    let Some(order) = &state.generation_order_override else {
+        return sorted;
+    };
+
+    // Listed paths that are no longer selected files are ignored, as are duplicates.
+    let mut remaining: HashSet<&PathBuf> = sorted.iter().collect();
+    let mut ordered: Vec<PathBuf> = order
+        .iter()
+        .filter(|path| remaining.remove(path))
+        .cloned()
+        .collect();
+    ordered.extend(
+        sorted
+            .iter()
+            .filter(|path| remaining.contains(path))
+            .cloned(),
+    );
+    ordered
+}
+
+/// The selected files' paths in tree order, one per line. Paths are relative to
+/// `current_path` when `use_relative_paths` is set, otherwise absolute.
+pub fn selected_paths_text(state: &AppState) -> String {
+    let root = Path::new(&state.current_path);
+    get_selected_files_in_tree_order(state)
+        .iter()
+        .map(|path| match path.strip_prefix(root) {
+            Ok(relative) if state.config.use_relative_paths => relative.display().to_string(),
+            _ => path.display().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::app::state::AppState;
-    use crate::config::AppConfig;
+    use crate::app::state::{AppState, GenerationSnapshot};
+    use crate::config::{AppConfig, ConcatOrder, SortOrder};
     use std::collections::HashSet;
     use std::path::PathBuf;
 
@@ -465,6 +894,9 @@ mod tests {
             size: if is_dir { 0 } else { 100 },
             depth: path_str.matches('/').count(),
             parent: PathBuf::from(path_str).parent().map(|p| p.to_path_buf()),
+            mtime: None,
+            likely_generated: false,
+            git_status: None,
         }
     }
 
@@ -538,4 +970,337 @@ mod tests {
         assert!(main_rs_node.is_previewed);
         assert_eq!(main_rs_node.selection_state, "full");
     }
+
+    #[test]
+    fn test_glob_filter_marks_and_expands_the_files_it_matches() {
+        let mut state = AppState::default();
+        state.config = create_test_config();
+        state.current_path = "/project".to_string();
+        state.search_query = "src/**/*.rs".to_string();
+        state.filename_match_mode = FilenameMatchMode::Glob;
+        state.filtered_file_list = vec![
+            create_test_file_item("/project/src", true),
+            create_test_file_item("/project/src/app", true),
+            create_test_file_item("/project/src/app/main.rs", false),
+        ];
+        state.loaded_dirs = HashSet::from([
+            PathBuf::from("/project/src"),
+            PathBuf::from("/project/src/app"),
+        ]);
+
+        auto_expand_for_matches(&mut state);
+        let ui_state = generate_ui_state(&state);
+
+        assert!(state.expanded_dirs.contains(Path::new("/project/src/app")));
+        let main_rs = &ui_state.tree[0].children[0].children[0];
+        assert_eq!(main_rs.name, "main.rs");
+        assert!(main_rs.is_match);
+    }
+
+    #[test]
+    fn test_tree_respects_sort_order_and_keeps_directories_first() {
+        let mut state = AppState::default();
+        state.config = create_test_config();
+        state.current_path = "/project".to_string();
+        let now = std::time::SystemTime::now();
+        let file = |name: &str, size: u64, age_secs: u64| FileItem {
+            size,
+            mtime: Some(now - std::time::Duration::from_secs(age_secs)),
+            ..create_test_file_item(&format!("/project/{name}"), false)
+        };
+        state.filtered_file_list = vec![
+            create_test_file_item("/project/zdir", true),
+            file("a.rs", 10, 30),
+            file("b.rs", 300, 20),
+            file("c.rs", 20, 10),
+        ];
+        let names = |state: &AppState| -> Vec<String> {
+            generate_ui_state(state)
+                .tree
+                .into_iter()
+                .map(|n| n.name)
+                .collect()
+        };
+
+        state.config.sort_order = SortOrder::NameAsc;
+        assert_eq!(names(&state), ["zdir", "a.rs", "b.rs", "c.rs"]);
+        state.config.sort_order = SortOrder::NameDesc;
+        assert_eq!(names(&state), ["zdir", "c.rs", "b.rs", "a.rs"]);
+        state.config.sort_order = SortOrder::SizeDesc;
+        assert_eq!(names(&state), ["zdir", "b.rs", "c.rs", "a.rs"]);
+        state.config.sort_order = SortOrder::ModifiedDesc;
+        assert_eq!(names(&state), ["zdir", "c.rs", "b.rs", "a.rs"]);
+    }
+
+    #[test]
+    fn test_content_match_counts_are_reported_per_node_and_in_total() {
+        let mut state = AppState::default();
+        state.config = create_test_config();
+        state.current_path = "/project".to_string();
+        let src = PathBuf::from("/project/src");
+        let a = PathBuf::from("/project/src/a.rs");
+        let b = PathBuf::from("/project/src/b.rs");
+        state.filtered_file_list = vec![
+            create_test_file_item("/project/src", true),
+            create_test_file_item("/project/src/a.rs", false),
+            create_test_file_item("/project/src/b.rs", false),
+        ];
+        state.content_search_query = "todo".to_string();
+        state.content_search_results = HashMap::from([(a.clone(), 3), (b.clone(), 2)]);
+
+        let ui_state = generate_ui_state(&state);
+
+        assert_eq!(ui_state.content_search_file_count, 2);
+        assert_eq!(ui_state.content_search_match_count, 5);
+        let src_node = ui_state.tree.iter().find(|n| n.path == src).unwrap();
+        assert_eq!(src_node.content_match_count, 5);
+        let count_of = |path: &PathBuf| {
+            src_node
+                .children
+                .iter()
+                .find(|n| &n.path == path)
+                .unwrap()
+                .content_match_count
+        };
+        assert_eq!(count_of(&a), 3);
+        assert_eq!(count_of(&b), 2);
+    }
+
+    #[test]
+    fn test_tree_nodes_expose_file_mtime_in_unix_seconds() {
+        let mut state = AppState::default();
+        state.config = create_test_config();
+        state.current_path = "/project".to_string();
+        let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        state.filtered_file_list = vec![
+            FileItem {
+                mtime: Some(modified),
+                ..create_test_file_item("/project/dir", true)
+            },
+            FileItem {
+                mtime: Some(modified),
+                ..create_test_file_item("/project/file.rs", false)
+            },
+            create_test_file_item("/project/unknown.rs", false),
+        ];
+
+        let tree = generate_ui_state(&state).tree;
+        let mtime_of = |name: &str| tree.iter().find(|n| n.name == name).unwrap().mtime;
+
+        assert_eq!(mtime_of("dir"), None);
+        assert_eq!(mtime_of("file.rs"), Some(1_700_000_000));
+        assert_eq!(mtime_of("unknown.rs"), None);
+
+        let serialized = serde_json::to_value(&tree).unwrap();
+        assert!(serialized
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|n| n["mtime"] == serde_json::json!(1_700_000_000)));
+    }
+
+    #[test]
+    fn test_selected_paths_text_lists_selection_in_tree_order() {
+        let mut state = AppState::default();
+        state.config = create_test_config();
+        state.current_path = "/project".to_string();
+        state.full_file_list = vec![
+            create_test_file_item("/project/src", true),
+            create_test_file_item("/project/src/main.rs", false),
+            create_test_file_item("/project/README.md", false),
+            create_test_file_item("/project/Cargo.toml", false),
+        ];
+        state.selected_files = HashSet::from([
+            PathBuf::from("/project/src/main.rs"),
+            PathBuf::from("/project/README.md"),
+        ]);
+
+        assert_eq!(selected_paths_text(&state), "README.md\nsrc/main.rs");
+
+        state.config.use_relative_paths = false;
+        assert_eq!(
+            selected_paths_text(&state),
+            "/project/README.md\n/project/src/main.rs"
+        );
+    }
+
+    #[test]
+    fn test_generation_order_follows_the_concat_order_strategy() {
+        let mut state = AppState::default();
+        state.config = create_test_config();
+        state.current_path = "/project".to_string();
+        for (path, size) in [
+            ("/project/b.rs", 300),
+            ("/project/README.md", 50),
+            ("/project/src/a.rs", 100),
+            ("/project/src/notes.md", 200),
+        ] {
+            let mut item = create_test_file_item(path, false);
+            item.size = size;
+            state.full_file_list.push(item);
+        }
+        state.selected_files = state
+            .full_file_list
+            .iter()
+            .map(|item| item.path.clone())
+            .collect();
+        let order = |state: &AppState| -> Vec<String> {
+            get_selected_files_in_generation_order(state)
+                .iter()
+                .map(|path| path.strip_prefix("/project").unwrap().display().to_string())
+                .collect()
+        };
+
+        state.config.concat_order = ConcatOrder::TreeOrder;
+        assert_eq!(
+            order(&state),
+            ["README.md", "b.rs", "src/a.rs", "src/notes.md"]
+        );
+        state.config.concat_order = ConcatOrder::ExtensionThenName;
+        assert_eq!(
+            order(&state),
+            ["README.md", "src/notes.md", "src/a.rs", "b.rs"]
+        );
+        state.config.concat_order = ConcatOrder::SizeAsc;
+        assert_eq!(
+            order(&state),
+            ["README.md", "src/a.rs", "src/notes.md", "b.rs"]
+        );
+        state.config.concat_order = ConcatOrder::SizeDesc;
+        assert_eq!(
+            order(&state),
+            ["b.rs", "src/notes.md", "src/a.rs", "README.md"]
+        );
+
+        // A manual order still comes first, and tree order ignores the strategy.
+        state.generation_order_override = Some(vec![PathBuf::from("/project/src/a.rs")]);
+        assert_eq!(
+            order(&state),
+            ["src/a.rs", "b.rs", "src/notes.md", "README.md"]
+        );
+        assert_eq!(
+            get_selected_files_in_tree_order(&state),
+            [
+                PathBuf::from("/project/src/a.rs"),
+                PathBuf::from("/project/README.md"),
+                PathBuf::from("/project/b.rs"),
+                PathBuf::from("/project/src/notes.md"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generation_plan_lists_the_selection_and_the_pruned_tree() {
+        let mut state = AppState::default();
+        state.config = create_test_config();
+        state.config.remove_empty_directories = true;
+        state.config.include_tree_by_default = true;
+        state.current_path = "/project".to_string();
+        state.full_file_list = vec![
+            create_test_file_item("/project/src", true),
+            create_test_file_item("/project/src/main.rs", false),
+            create_test_file_item("/project/empty", true),
+            create_test_file_item("/project/README.md", false),
+            create_test_file_item("/project/notes.txt", false),
+        ];
+        state.selected_files = HashSet::from([
+            PathBuf::from("/project/src/main.rs"),
+            PathBuf::from("/project/README.md"),
+        ]);
+        state.is_fully_scanned = true;
+
+        let plan = generation_plan(&state);
+        assert_eq!(plan.files, ["README.md", "src/main.rs"]);
+        assert_eq!(plan.total_size, 200);
+        // The empty directory is pruned from the tree; unselected files stay in it.
+        assert_eq!(plan.tree_entries, Some(4));
+
+        state.config.include_tree_by_default = false;
+        assert_eq!(generation_plan(&state).tree_entries, None);
+    }
+
+    #[test]
+    fn test_tree_stats_tallies_files_per_extension() {
+        let file = |path: &str, size: u64, is_binary: bool| FileItem {
+            size,
+            is_binary,
+            ..create_test_file_item(path, false)
+        };
+        let files = vec![
+            create_test_file_item("/project/src", true),
+            file("/project/src/main.rs", 400, false),
+            file("/project/src/lib.RS", 101, false),
+            file("/project/logo.png", 9000, true),
+            file("/project/Makefile", 40, false),
+        ];
+
+        let stats = tree_stats(&files, None);
+
+        assert_eq!(
+            stats.extensions,
+            vec![
+                ExtensionStats {
+                    extension: "png".to_string(),
+                    files: 1,
+                    size: 9000,
+                    tokens: 0,
+                },
+                ExtensionStats {
+                    extension: "rs".to_string(),
+                    files: 2,
+                    size: 501,
+                    tokens: 126,
+                },
+                ExtensionStats {
+                    extension: String::new(),
+                    files: 1,
+                    size: 40,
+                    tokens: 10,
+                },
+            ]
+        );
+        assert_eq!(
+            (stats.total_files, stats.total_size, stats.total_tokens),
+            (4, 9541, 136)
+        );
+        assert!(!stats.exact_tokens);
+
+        let exact = HashMap::from([(PathBuf::from("/project/src/main.rs"), 7)]);
+        let stats = tree_stats(&files, Some(&exact));
+        assert_eq!(stats.total_tokens, 7);
+        assert!(stats.exact_tokens);
+    }
+
+    #[test]
+    fn test_generation_diff_reports_added_removed_and_changed_files() {
+        let mut state = AppState::default();
+        state.current_path = "/project".to_string();
+        let snapshot = |files: &[(&str, u64)], token_count| GenerationSnapshot {
+            file_hashes: files
+                .iter()
+                .map(|(path, hash)| (PathBuf::from(path), *hash))
+                .collect(),
+            token_count,
+        };
+        state.latest_generation = Some(snapshot(
+            &[("/project/a.rs", 1), ("/project/old.rs", 4)],
+            10,
+        ));
+        assert_eq!(generation_diff(&state), None);
+
+        state.previous_generation = state.latest_generation.take();
+        state.latest_generation = Some(snapshot(
+            &[("/project/a.rs", 2), ("/project/src/b.rs", 3)],
+            25,
+        ));
+        assert_eq!(
+            generation_diff(&state),
+            Some(GenerationDiff {
+                added: vec!["src/b.rs".to_string()],
+                removed: vec!["old.rs".to_string()],
+                changed: vec!["a.rs".to_string()],
+                token_delta: 15,
+            })
+        );
+    }
 }
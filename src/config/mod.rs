@@ -1,9 +1,18 @@
 pub mod settings;
 
+use crate::core::file_handler::DEFAULT_CHUNK_HEADER_TEMPLATE;
+use crate::core::ignore_rules::{LOCKFILE_PATTERNS, VENDORED_PATTERNS};
+use crate::core::redaction::default_redaction_patterns;
+use crate::core::{
+    ContentTransform, FileItem, IgnoreRules, LineEndings, LineSummary, OutputFormat, Redaction,
+    TabPolicy, TreePosition, TreeStyle,
+};
+use crate::utils::file_detection::{ExtensionOverrides, LanguageOverrides};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct AppConfig {
@@ -14,19 +23,332 @@ pub struct AppConfig {
     pub output_filename: String,
     pub case_sensitive_search: bool,
     pub include_tree_by_default: bool,
+    /// Where the directory tree goes in the output when `include_tree_by_default` is set.
+    pub tree_position: TreePosition,
     pub use_relative_paths: bool,
     pub remove_empty_directories: bool,
+    /// Hide zero-byte files and leave them out of selections and the output.
+    pub exclude_empty_files: bool,
     pub window_size: (f64, f64),
     pub window_position: (f64, f64),
+    /// The zoom factor of the UI, `1.0` being 100 %. Applied to the WebView at startup.
+    pub ui_zoom: f64,
+    /// The color theme of the UI.
+    pub theme: UiTheme,
     pub auto_load_last_directory: bool,
     pub max_file_size_mb: u64,
+    pub min_file_size_bytes: Option<u64>,
+    pub max_file_size_bytes: Option<u64>,
+    /// Only files modified within this many days are shown, together with their
+    /// parent directories. `None` shows files regardless of their age.
+    pub modified_within_days: Option<u64>,
     pub scan_chunk_size: usize,
+    /// Broken symlinks are always left out of the scan. When set, the user is told
+    /// which ones after a full scan.
+    pub report_broken_symlinks: bool,
+    /// Upper bound on worker threads for parallel content search and filtering.
+    /// `None` uses one thread per core. Lower values also bound how many files are
+    /// read into memory concurrently.
+    pub max_threads: Option<usize>,
+    pub strip_comments: bool,
+    pub strip_blank_lines: bool,
+    /// Files with more lines than this are shortened to their first
+    /// `summary_head_lines` and last `summary_tail_lines` lines. `None` disables it.
+    pub summarize_over_lines: Option<usize>,
+    pub summary_head_lines: usize,
+    pub summary_tail_lines: usize,
+    /// The line endings every file is converted to in the generated output.
+    pub normalize_line_endings: LineEndings,
+    /// Remove trailing spaces and tabs from every line of the generated output.
+    pub trim_trailing_whitespace: bool,
+    /// How leading indentation is converted in the generated output.
+    pub tab_policy: TabPolicy,
+    /// Prefix each line of every file in the generated output with its line number.
+    pub number_lines_in_output: bool,
+    /// Show each file's Unix mode, or its read-only and hidden attributes on Windows, in
+    /// its header in the generated output.
+    pub include_file_mode: bool,
+    /// Show the SHA-256 of each file as read from disk in its header in the generated
+    /// output, so recipients can verify it.
+    pub include_checksums: bool,
+    /// Mask secrets matching `redaction_patterns` with `[REDACTED]` in the generated output.
+    pub enable_redaction: bool,
+    /// Regular expressions matched against every line when `enable_redaction` is set. If a
+    /// pattern has capture groups, only the first group is masked.
+    pub redaction_patterns: Vec<String>,
+    pub sort_order: SortOrder,
+    /// The order in which the selected files are concatenated into the output.
+    pub concat_order: ConcatOrder,
+    pub output_format: OutputFormat,
+    /// Prepend a manifest (root, file list, total size and tokens) to the output.
+    pub include_manifest: bool,
+    /// Put a numbered index of the included files, with the bytes, lines and tokens
+    /// of each, before the first file.
+    pub include_index: bool,
+    /// Stream large generated outputs to the UI in chunks instead of one event.
+    pub stream_generated_content: bool,
+    /// Write the output to a temporary file while generating and keep it there. Only
+    /// one file's content is held in memory at a time: the preview shows the output's
+    /// head, and saving or copying reads the file.
+    pub stream_generation: bool,
+    /// Leave likely generated or minified files out of bulk selections such as
+    /// "Select all". They can still be selected individually.
+    pub skip_generated: bool,
+    /// Before generating, add the files that the selected files name by a quoted
+    /// relative path, one hop deep and up to a fixed number of small files.
+    pub auto_include_imports: bool,
+    /// Replace files that cannot be read, even after retrying, with an error note in
+    /// the output instead of aborting the generation.
+    pub skip_unreadable_files: bool,
+    /// Generation asks for confirmation first when the selected files add up to more
+    /// than this many bytes. `None` disables the check.
+    pub warn_over_bytes: Option<u64>,
+    /// Saving writes the output as several files of at most about this many bytes
+    /// each, split between files, so each part can be pasted on its own. `None` saves
+    /// a single file.
+    pub split_output_bytes: Option<usize>,
+    /// The header that starts each part when the output is split into several parts.
+    /// `{part}`, `{total}`, `{root}` and `{files}` are replaced with the part number,
+    /// the number of parts, the root directory's name and the files in the part. Empty
+    /// for no header.
+    pub chunk_header_template: String,
+    /// Lines of context captured around each content search match. `None` reports
+    /// only which files match, without snippets.
+    pub content_search_context_lines: Option<usize>,
+    /// Files are content-searched line by line through a read buffer of this many KiB,
+    /// so large files are never held in memory as a whole.
+    pub search_read_buffer_kb: usize,
+    /// Matching files kept per content search. Further matches are dropped until the
+    /// user asks for more with `loadMoreSearchResults`.
+    pub max_search_results: usize,
+    /// Lines shown when previewing a file. Longer files are truncated.
+    pub preview_max_lines: usize,
+    /// Entries shown per directory in the file tree before the rest are collapsed into
+    /// an "… and N more" node, see `loadMoreChildren`. `None` shows all entries.
+    pub max_children_displayed: Option<usize>,
+    /// Lines of a generated output sent to the preview. The full output is kept in the
+    /// backend for saving and copying. `None` sends the whole output.
+    pub preview_generated_head_lines: Option<usize>,
+    /// How long the name and extension filters wait for typing to pause before they are
+    /// applied, in milliseconds. `0` applies them on every keystroke.
+    pub filter_debounce_ms: u64,
+    /// Draw directory trees with plain ASCII instead of box-drawing characters and
+    /// icons, in the generated output and in tree exports. Often cheaper in tokens.
+    pub tree_ascii: bool,
+    /// Walk `.`-prefixed files and directories, independently of the ignore patterns.
+    pub include_hidden: bool,
+    /// Badge files that differ from the last git commit. Runs `git status` once per
+    /// scan, so it is off by default.
+    pub show_git_status: bool,
+    /// Only show files that git tracks, regardless of the ignore patterns. Runs
+    /// `git ls-files` once per scan; has no effect outside a git work tree.
+    pub only_git_tracked: bool,
+    /// Hide paths a `.gitattributes` file marks `export-ignore`, approximating what
+    /// `git archive` would leave out. Also applies while `only_git_tracked` is set.
+    pub respect_export_ignore: bool,
+    /// Hide lockfiles such as `Cargo.lock` and `package-lock.json`, without adding
+    /// them to `ignore_patterns`.
+    pub exclude_lockfiles: bool,
+    /// Hide vendored dependencies such as `vendor/` and `node_modules/`, without
+    /// adding them to `ignore_patterns`.
+    pub exclude_vendored: bool,
+    /// Which files are selected when a full scan completes.
+    pub auto_select_on_scan: AutoSelect,
+    /// Gitignore-style patterns of the files `AutoSelect::FromPreset` selects.
+    pub auto_select_patterns: HashSet<String>,
+    /// Extensions always treated as text, e.g. `graphql`, whatever their content looks like.
+    pub text_extensions: HashSet<String>,
+    /// Extensions always treated as binary, e.g. `pdf`. They win over `text_extensions`.
+    pub force_binary_extensions: HashSet<String>,
+    /// Language tags for extensions, e.g. `tf` → `terraform`, used in code fences and
+    /// file headers and for preview highlighting. They win over the built-in mapping.
+    pub language_overrides: HashMap<String, String>,
+    /// The most verbose level written to the log file: `error`, `warn`, `info`, `debug`
+    /// or `trace`. `RUST_LOG` overrides it. Takes effect on the next start.
+    pub log_level: String,
+    /// Files that every scan re-selects when it finds them, even after they were
+    /// temporarily missing. Absolute paths.
+    pub pinned_files: HashSet<PathBuf>,
+    /// Save the selection and expanded directories per root directory when leaving it,
+    /// and restore them when it is opened again.
+    pub remember_selection: bool,
+    /// Raw values of paths expanded on load, keyed by their expanded form. Never
+    /// serialized; used to write the unexpanded values back to disk.
+    #[serde(skip)]
+    pub unexpanded_paths: HashMap<String, String>,
 }
 
+/// The files selected automatically when a full scan completes. Files that bulk
+/// selections leave out, binary files and files over `max_file_size_mb` are never
+/// selected.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AutoSelect {
+    /// Keep the selection as it is.
+    #[default]
+    None,
+    /// Every visible text file.
+    AllText,
+    /// The visible files matching `auto_select_patterns`.
+    FromPreset,
+}
+
+/// The order in which entries are listed within each directory of the file tree.
+/// Directories are always grouped before files, regardless of the sort key.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    NameAsc,
+    NameDesc,
+    SizeDesc,
+    ModifiedDesc,
+}
+
+/// The order in which the selected files are concatenated into the output. A manual
+/// order set with `setGenerationOrder` still takes precedence for the files it lists.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ConcatOrder {
+    /// By path, as the files appear in the tree.
+    #[default]
+    TreeOrder,
+    /// Grouped by extension, then by file name.
+    ExtensionThenName,
+    /// Smallest files first, e.g. to fill a token budget greedily.
+    SizeAsc,
+    SizeDesc,
+}
+
+/// The color theme of the UI.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum UiTheme {
+    /// Follow the operating system's light or dark appearance.
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// The selection and expanded directories of one root directory, saved while
+/// `AppConfig::remember_selection` is set. Paths are relative to the root.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SavedSession {
+    pub selected_files: Vec<PathBuf>,
+    pub expanded_dirs: Vec<PathBuf>,
+}
+
+/// The smallest and largest accepted `AppConfig::ui_zoom`.
+pub const UI_ZOOM_RANGE: (f64, f64) = (0.5, 3.0);
+
 impl AppConfig {
     pub fn load() -> Result<Self> {
         settings::load_config(None)
     }
+
+    /// The per-file content transformations selected by this config.
+    pub fn content_transform(&self) -> ContentTransform {
+        ContentTransform {
+            strip_comments: self.strip_comments,
+            strip_blank_lines: self.strip_blank_lines,
+            summarize: self.summarize_over_lines.map(|over_lines| LineSummary {
+                over_lines,
+                head_lines: self.summary_head_lines,
+                tail_lines: self.summary_tail_lines,
+            }),
+            line_endings: self.normalize_line_endings,
+            trim_trailing_whitespace: self.trim_trailing_whitespace,
+            tab_policy: self.tab_policy,
+            number_lines: self.number_lines_in_output,
+            include_file_mode: self.include_file_mode,
+            include_checksum: self.include_checksums,
+            redaction: self
+                .enable_redaction
+                .then(|| Redaction::compile(&self.redaction_patterns)),
+            language_overrides: LanguageOverrides::build(&self.language_overrides),
+        }
+    }
+
+    /// Whether paths marked `export-ignore` in `.gitattributes` are hidden.
+    pub fn export_ignore_enabled(&self) -> bool {
+        self.respect_export_ignore || self.only_git_tracked
+    }
+
+    /// The patterns `exclude_lockfiles` and `exclude_vendored` hide on top of
+    /// `ignore_patterns`. They are applied while filtering, so toggling them never
+    /// needs a re-scan.
+    pub fn overlay_ignore_patterns(&self) -> Vec<String> {
+        let lockfiles = LOCKFILE_PATTERNS.iter().filter(|_| self.exclude_lockfiles);
+        let vendored = VENDORED_PATTERNS.iter().filter(|_| self.exclude_vendored);
+        lockfiles.chain(vendored).map(|p| p.to_string()).collect()
+    }
+
+    /// The configured overrides of the text/binary classification.
+    pub fn extension_overrides(&self) -> ExtensionOverrides {
+        ExtensionOverrides::new(&self.text_extensions, &self.force_binary_extensions)
+    }
+
+    /// Whether bulk selections (select all, directories, extensions) include `item`.
+    /// Likely generated files are left out when `skip_generated` is set, empty files
+    /// when `exclude_empty_files` is set.
+    pub fn is_bulk_selectable(&self, item: &FileItem) -> bool {
+        !item.is_directory
+            && !(self.skip_generated && item.likely_generated)
+            && !self.excludes_as_empty(item)
+    }
+
+    /// The cutoff `modified_within_days` corresponds to at the time of the call.
+    pub fn modified_since(&self) -> Option<SystemTime> {
+        self.modified_within_days.map(|days| {
+            let age = Duration::from_secs(days.saturating_mul(24 * 60 * 60));
+            SystemTime::now()
+                .checked_sub(age)
+                .unwrap_or(SystemTime::UNIX_EPOCH)
+        })
+    }
+
+    /// Whether `item` is a zero-byte file and `exclude_empty_files` is set.
+    pub fn excludes_as_empty(&self, item: &FileItem) -> bool {
+        self.exclude_empty_files && !item.is_directory && item.size == 0
+    }
+
+    /// The paths of the files among `items` that `auto_select_on_scan` selects.
+    /// `root` anchors `auto_select_patterns`.
+    pub fn auto_selected_files(&self, items: &[FileItem], root: &Path) -> Vec<PathBuf> {
+        let include = match self.auto_select_on_scan {
+            AutoSelect::None => return Vec::new(),
+            AutoSelect::AllText => None,
+            AutoSelect::FromPreset => Some(IgnoreRules::new(root, &self.auto_select_patterns)),
+        };
+        let max_size = self.max_file_size_mb.saturating_mul(1024 * 1024);
+        items
+            .iter()
+            .filter(|item| {
+                self.is_bulk_selectable(item)
+                    && !item.is_binary
+                    && item.size <= max_size
+                    && include
+                        .as_ref()
+                        .is_none_or(|rules| rules.is_ignored_or_any_parents(&item.path, false))
+            })
+            .map(|item| item.path.clone())
+            .collect()
+    }
+
+    /// Where the generated output places the directory tree, if anywhere.
+    pub fn output_tree_position(&self) -> TreePosition {
+        if self.include_tree_by_default {
+            self.tree_position
+        } else {
+            TreePosition::None
+        }
+    }
+
+    /// The characters used to draw directory trees.
+    pub fn tree_style(&self) -> TreeStyle {
+        if self.tree_ascii {
+            TreeStyle::Ascii
+        } else {
+            TreeStyle::Unicode
+        }
+    }
 }
 
 impl Default for AppConfig {
@@ -83,13 +405,71 @@ impl Default for AppConfig {
             output_filename: "cfc_output.txt".to_string(),
             case_sensitive_search: false,
             include_tree_by_default: true,
+            tree_position: TreePosition::Top,
             use_relative_paths: true,
             remove_empty_directories: false,
+            exclude_empty_files: false,
             window_size: (1200.0, 800.0),
             window_position: (100.0, 100.0),
+            ui_zoom: 1.0,
+            theme: UiTheme::System,
             auto_load_last_directory: false,
             max_file_size_mb: 20,
+            min_file_size_bytes: None,
+            max_file_size_bytes: None,
+            modified_within_days: None,
             scan_chunk_size: 100,
+            report_broken_symlinks: false,
+            max_threads: None,
+            strip_comments: false,
+            strip_blank_lines: false,
+            summarize_over_lines: None,
+            summary_head_lines: 50,
+            summary_tail_lines: 20,
+            normalize_line_endings: LineEndings::AsIs,
+            trim_trailing_whitespace: false,
+            tab_policy: TabPolicy::AsIs,
+            number_lines_in_output: false,
+            include_file_mode: false,
+            include_checksums: false,
+            enable_redaction: false,
+            redaction_patterns: default_redaction_patterns(),
+            sort_order: SortOrder::NameAsc,
+            concat_order: ConcatOrder::TreeOrder,
+            output_format: OutputFormat::PlainText,
+            include_manifest: false,
+            include_index: false,
+            stream_generated_content: true,
+            stream_generation: false,
+            skip_generated: true,
+            auto_include_imports: false,
+            skip_unreadable_files: false,
+            warn_over_bytes: Some(50 * 1024 * 1024),
+            split_output_bytes: None,
+            chunk_header_template: DEFAULT_CHUNK_HEADER_TEMPLATE.to_string(),
+            content_search_context_lines: Some(2),
+            search_read_buffer_kb: 64,
+            max_search_results: 300,
+            preview_max_lines: 1500,
+            max_children_displayed: Some(1000),
+            preview_generated_head_lines: None,
+            filter_debounce_ms: 120,
+            tree_ascii: false,
+            include_hidden: false,
+            show_git_status: false,
+            only_git_tracked: false,
+            respect_export_ignore: false,
+            exclude_lockfiles: false,
+            exclude_vendored: false,
+            auto_select_on_scan: AutoSelect::None,
+            auto_select_patterns: HashSet::new(),
+            text_extensions: HashSet::new(),
+            force_binary_extensions: HashSet::new(),
+            language_overrides: HashMap::new(),
+            log_level: "info".to_string(),
+            pinned_files: HashSet::new(),
+            remember_selection: false,
+            unexpanded_paths: HashMap::new(),
         }
     }
 }
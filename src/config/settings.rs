@@ -2,13 +2,19 @@ use anyhow::{anyhow, Result};
 use directories::ProjectDirs;
 use serde::Serialize;
 use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use super::AppConfig;
+use super::{AppConfig, SavedSession};
 
 const APP_NAME: &str = "ContextFileConcat";
 const CONFIG_FILE: &str = "config.json";
+const SESSIONS_FILE: &str = "sessions.json";
+const PROFILES_DIR: &str = "profiles";
+const PROFILE_EXTENSION: &str = "json";
+const LOGS_DIR: &str = "logs";
 
 /// Production implementation for getting the platform-specific config directory.
 #[cfg(not(test))]
@@ -28,6 +34,12 @@ fn get_platform_config_dir() -> Option<PathBuf> {
     }
 }
 
+/// The platform-specific directory log files are written to.
+pub fn get_log_dir() -> Option<PathBuf> {
+    ProjectDirs::from("com", "contextfileconcat", APP_NAME)
+        .map(|dirs| dirs.data_local_dir().join(LOGS_DIR))
+}
+
 // This private helper function centralizes path resolution logic.
 fn get_path(path_override: Option<&Path>) -> Result<PathBuf> {
     match path_override {
@@ -64,7 +76,7 @@ pub fn load_config(path_override: Option<&Path>) -> Result<AppConfig> {
     match serde_json::from_str::<AppConfig>(&config_content) {
         Ok(config) => {
             tracing::info!("Loaded config from {:?}", config_path);
-            Ok(config)
+            Ok(expand_config_paths(config))
         }
         Err(e) => {
             tracing::warn!(
@@ -72,13 +84,15 @@ pub fn load_config(path_override: Option<&Path>) -> Result<AppConfig> {
                 config_path,
                 e
             );
-            migrate_legacy_config(&config_content).or_else(|migration_err| {
-                tracing::error!(
-                    "Migration also failed: {}. Using default config.",
-                    migration_err
-                );
-                Ok(AppConfig::default())
-            })
+            migrate_legacy_config(&config_content)
+                .map(expand_config_paths)
+                .or_else(|migration_err| {
+                    tracing::error!(
+                        "Migration also failed: {}. Using default config.",
+                        migration_err
+                    );
+                    Ok(AppConfig::default())
+                })
         }
     }
 }
@@ -91,7 +105,7 @@ pub fn save_config(config: &AppConfig, path_override: Option<&Path>) -> Result<(
             fs::create_dir_all(parent)?;
         }
     }
-    let config_json = serde_json::to_string_pretty(config)?;
+    let config_json = serde_json::to_string_pretty(&portable_config(config))?;
     fs::write(&config_path, config_json)?;
     tracing::info!("Saved config to {:?}", config_path);
     Ok(())
@@ -105,19 +119,390 @@ pub fn export_config(config: &AppConfig, export_path: &Path) -> Result<()> {
 /// Imports an application configuration from a user-specified JSON file.
 pub fn import_config(import_path: &PathBuf) -> Result<AppConfig> {
     let config_content = fs::read_to_string(import_path)?;
-    match serde_json::from_str::<AppConfig>(&config_content) {
+    parse_config(&config_content, import_path)
+}
+
+/// Parses the content of the config file at `path`, migrating the legacy format if
+/// needed, with paths expanded as on import.
+fn parse_config(config_content: &str, path: &Path) -> Result<AppConfig> {
+    match serde_json::from_str::<AppConfig>(config_content) {
         Ok(config) => {
-            tracing::info!("Imported config from {:?}", import_path);
-            Ok(config)
+            tracing::info!("Imported config from {:?}", path);
+            Ok(expand_config_paths(config))
         }
         Err(_) => {
-            tracing::info!(
-                "Attempting to import legacy config format from {:?}",
-                import_path
+            tracing::info!("Attempting to import legacy config format from {:?}", path);
+            migrate_legacy_config(config_content).map(expand_config_paths)
+        }
+    }
+}
+
+/// How serious a problem found by `validate_config` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueSeverity {
+    /// The config can be imported, but probably not as intended.
+    Warning,
+    /// The config cannot be imported, or importing it breaks generation.
+    Error,
+}
+
+/// A problem found by `validate_config`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigIssue {
+    pub severity: IssueSeverity,
+    /// The config field the issue is about, or `None` for the file as a whole.
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl ConfigIssue {
+    fn new(severity: IssueSeverity, field: Option<&str>, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            field: field.map(String::from),
+            message: message.into(),
+        }
+    }
+}
+
+/// Checks the config file at `path` the way `import_config` would read it, without
+/// applying it. Returns the problems found, errors first; an empty list means the
+/// config can be imported as is.
+pub fn validate_config(path: &Path) -> Vec<ConfigIssue> {
+    let error = |field, message: String| ConfigIssue::new(IssueSeverity::Error, field, message);
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => return vec![error(None, format!("Cannot read the file: {e}"))],
+    };
+    match parse_config(&content, path) {
+        Ok(config) => check_config(&config),
+        // Report why the current format was rejected; the legacy fallback's error is
+        // rarely the helpful one.
+        Err(e) => {
+            let reason = serde_json::from_str::<AppConfig>(&content)
+                .err()
+                .map_or_else(|| e.to_string(), |e| e.to_string());
+            vec![error(None, format!("Not a valid config: {reason}"))]
+        }
+    }
+}
+
+/// The semantic checks behind `validate_config`, for a config that parsed.
+fn check_config(config: &AppConfig) -> Vec<ConfigIssue> {
+    use IssueSeverity::{Error, Warning};
+    let mut issues = Vec::new();
+    let mut issue = |severity, field, message: String| {
+        issues.push(ConfigIssue::new(severity, Some(field), message))
+    };
+
+    let filename = config.output_filename.trim();
+    if filename.is_empty() {
+        issue(
+            Error,
+            "output_filename",
+            "The output filename is empty.".into(),
+        );
+    } else if filename.contains(['/', '\\']) {
+        issue(
+            Error,
+            "output_filename",
+            format!("The output filename {filename:?} contains a path separator."),
+        );
+    }
+    if config.scan_chunk_size == 0 {
+        issue(Error, "scan_chunk_size", "The scan chunk size is 0.".into());
+    }
+    if config.preview_max_lines == 0 {
+        issue(
+            Error,
+            "preview_max_lines",
+            "The preview shows no lines.".into(),
+        );
+    }
+    if config.preview_generated_head_lines == Some(0) {
+        issue(
+            Warning,
+            "preview_generated_head_lines",
+            "A head of 0 lines is ignored; the whole output is previewed.".into(),
+        );
+    }
+    if let (Some(min), Some(max)) = (config.min_file_size_bytes, config.max_file_size_bytes) {
+        if min > max {
+            issue(
+                Error,
+                "min_file_size_bytes",
+                format!("The minimum file size ({min} bytes) exceeds the maximum ({max} bytes)."),
+            );
+        }
+    }
+
+    for (field, dir) in [
+        ("last_directory", &config.last_directory),
+        ("output_directory", &config.output_directory),
+    ] {
+        if let Some(dir) = dir.as_ref().filter(|dir| !dir.is_dir()) {
+            issue(
+                Warning,
+                field,
+                format!("The directory {} does not exist.", dir.display()),
+            );
+        }
+    }
+    let (min_zoom, max_zoom) = super::UI_ZOOM_RANGE;
+    if !(min_zoom..=max_zoom).contains(&config.ui_zoom) {
+        issue(
+            Warning,
+            "ui_zoom",
+            format!(
+                "The zoom {} is outside {min_zoom}–{max_zoom} and will be clamped.",
+                config.ui_zoom
+            ),
+        );
+    }
+    let (width, height) = config.window_size;
+    if width <= 0.0 || height <= 0.0 {
+        issue(
+            Warning,
+            "window_size",
+            format!("The window size {width}×{height} is not positive."),
+        );
+    }
+    for pattern in &config.redaction_patterns {
+        if let Err(e) = regex::Regex::new(pattern) {
+            issue(
+                Warning,
+                "redaction_patterns",
+                format!("The redaction pattern {pattern:?} is invalid and will be skipped: {e}"),
             );
-            migrate_legacy_config(&config_content)
         }
     }
+    if tracing_subscriber::EnvFilter::try_new(&config.log_level).is_err() {
+        issue(
+            Warning,
+            "log_level",
+            format!(
+                "The log level {:?} is invalid; \"info\" is used instead.",
+                config.log_level
+            ),
+        );
+    }
+    issues
+}
+
+/// Expands `~` and `${VAR}` in the output directory and in ignore patterns that look
+/// like absolute paths. The raw values are remembered in `unexpanded_paths`, so saving
+/// the config writes them back and the file on disk stays portable.
+fn expand_config_paths(mut config: AppConfig) -> AppConfig {
+    let mut unexpanded = std::mem::take(&mut config.unexpanded_paths);
+    let mut expand = |raw: String| {
+        let expanded = expand_path(&raw);
+        if expanded != raw {
+            unexpanded.insert(expanded.clone(), raw);
+        }
+        expanded
+    };
+
+    if let Some(dir) = config.output_directory.take() {
+        config.output_directory = Some(match dir.to_str() {
+            Some(raw) => PathBuf::from(expand(raw.to_string())),
+            None => dir,
+        });
+    }
+    config.ignore_patterns = config
+        .ignore_patterns
+        .into_iter()
+        .map(|pattern| {
+            if pattern.starts_with('~') || pattern.starts_with("${") {
+                expand(pattern)
+            } else {
+                pattern
+            }
+        })
+        .collect();
+
+    config.unexpanded_paths = unexpanded;
+    config
+}
+
+/// Returns the config as it should be written to disk, with expanded paths replaced
+/// by the values they were loaded from.
+fn portable_config(config: &AppConfig) -> Cow<'_, AppConfig> {
+    if config.unexpanded_paths.is_empty() {
+        return Cow::Borrowed(config);
+    }
+    let raw = |value: &str| config.unexpanded_paths.get(value).cloned();
+
+    let mut portable = config.clone();
+    if let Some(original) = config
+        .output_directory
+        .as_deref()
+        .and_then(Path::to_str)
+        .and_then(raw)
+    {
+        portable.output_directory = Some(PathBuf::from(original));
+    }
+    portable.ignore_patterns = config
+        .ignore_patterns
+        .iter()
+        .map(|pattern| raw(pattern).unwrap_or_else(|| pattern.clone()))
+        .collect();
+    Cow::Owned(portable)
+}
+
+/// Expands a leading `~` to the home directory and every `${VAR}` to the value of the
+/// environment variable. Anything that cannot be resolved is kept literally.
+fn expand_path(raw: &str) -> String {
+    let mut expanded = String::with_capacity(raw.len());
+    let mut rest = raw;
+
+    if let Some(after_tilde) = raw.strip_prefix('~') {
+        if after_tilde.is_empty() || after_tilde.starts_with(['/', '\\']) {
+            match dirs::home_dir() {
+                Some(home) => {
+                    expanded.push_str(&home.to_string_lossy());
+                    rest = after_tilde;
+                }
+                None => tracing::warn!("Could not expand '~' in '{}': no home directory", raw),
+            }
+        }
+    }
+
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let (literal, name) = (&rest[start..=start + len], &rest[start + 2..start + len]);
+        expanded.push_str(&rest[..start]);
+        match std::env::var(name) {
+            Ok(value) if !name.is_empty() => expanded.push_str(&value),
+            _ => {
+                tracing::warn!(
+                    "Environment variable '{}' in '{}' is not set. Keeping it literally.",
+                    name,
+                    raw
+                );
+                expanded.push_str(literal);
+            }
+        }
+        rest = &rest[start + len + 1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+// Resolves the file holding the saved sessions of all root directories.
+fn get_sessions_path(path_override: Option<&Path>) -> Result<PathBuf> {
+    match path_override {
+        Some(path) => Ok(path.to_path_buf()),
+        None => get_platform_config_dir()
+            .map(|dir| dir.join(SESSIONS_FILE))
+            .ok_or_else(|| anyhow!("Could not determine config directory")),
+    }
+}
+
+// Reads all saved sessions, keyed by root directory. A missing or corrupt file holds none.
+fn read_sessions(sessions_path: &Path) -> BTreeMap<PathBuf, SavedSession> {
+    fs::read_to_string(sessions_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Loads the session saved for `root`, if there is one.
+pub fn load_session(root: &Path, path_override: Option<&Path>) -> Result<Option<SavedSession>> {
+    let sessions_path = get_sessions_path(path_override)?;
+    Ok(read_sessions(&sessions_path).remove(root))
+}
+
+/// Saves the session of `root`, replacing the one saved before. An empty session
+/// removes the entry, so the file only grows with directories that have a selection.
+pub fn save_session(
+    root: &Path,
+    session: &SavedSession,
+    path_override: Option<&Path>,
+) -> Result<()> {
+    let sessions_path = get_sessions_path(path_override)?;
+    let mut sessions = read_sessions(&sessions_path);
+    if session.selected_files.is_empty() && session.expanded_dirs.is_empty() {
+        sessions.remove(root);
+    } else {
+        sessions.insert(root.to_path_buf(), session.clone());
+    }
+    if let Some(parent) = sessions_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&sessions_path, serde_json::to_string_pretty(&sessions)?)?;
+    tracing::info!("Saved session of {:?} to {:?}", root, sessions_path);
+    Ok(())
+}
+
+// Resolves the directory holding named profiles, next to the main config file.
+fn get_profiles_dir(dir_override: Option<&Path>) -> Result<PathBuf> {
+    match dir_override {
+        Some(dir) => Ok(dir.to_path_buf()),
+        None => get_platform_config_dir()
+            .map(|dir| dir.join(PROFILES_DIR))
+            .ok_or_else(|| anyhow!("Could not determine config directory")),
+    }
+}
+
+// Validates a profile name and maps it to its JSON file inside the profiles directory.
+fn get_profile_path(name: &str, dir_override: Option<&Path>) -> Result<PathBuf> {
+    let trimmed = name.trim();
+    if trimmed.is_empty()
+        || trimmed.starts_with('.')
+        || trimmed.contains(['/', '\\', ':'])
+        || trimmed.chars().any(char::is_control)
+    {
+        return Err(anyhow!("Invalid profile name: '{}'", name));
+    }
+    Ok(get_profiles_dir(dir_override)?.join(format!("{trimmed}.{PROFILE_EXTENSION}")))
+}
+
+/// Saves the configuration as a named profile, overwriting any existing profile of that name.
+pub fn save_profile(name: &str, config: &AppConfig, dir_override: Option<&Path>) -> Result<()> {
+    let profile_path = get_profile_path(name, dir_override)?;
+    save_config(config, Some(&profile_path))
+}
+
+/// Loads a named profile, migrating it from a legacy format if necessary.
+pub fn load_profile(name: &str, dir_override: Option<&Path>) -> Result<AppConfig> {
+    let profile_path = get_profile_path(name, dir_override)?;
+    if !profile_path.exists() {
+        return Err(anyhow!("Profile '{}' does not exist", name.trim()));
+    }
+    import_config(&profile_path)
+}
+
+/// Lists the names of all saved profiles, sorted alphabetically.
+pub fn list_profiles(dir_override: Option<&Path>) -> Result<Vec<String>> {
+    let profiles_dir = get_profiles_dir(dir_override)?;
+    if !profiles_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut names: Vec<String> = fs::read_dir(&profiles_dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file()
+                && path.extension().and_then(|ext| ext.to_str()) == Some(PROFILE_EXTENSION)
+        })
+        .filter_map(|path| {
+            path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .map(str::to_string)
+        })
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Deletes a named profile.
+pub fn delete_profile(name: &str, dir_override: Option<&Path>) -> Result<()> {
+    let profile_path = get_profile_path(name, dir_override)?;
+    fs::remove_file(&profile_path)?;
+    tracing::info!("Deleted profile at {:?}", profile_path);
+    Ok(())
 }
 
 /// Helper to ensure a field in the config JSON exists, adding it from a default if not.
@@ -154,21 +539,114 @@ fn migrate_legacy_config(config_content: &str) -> Result<AppConfig> {
         "include_tree_by_default",
         defaults.include_tree_by_default,
     )?;
+    ensure_field_from_default(obj, "tree_position", defaults.tree_position)?;
     ensure_field_from_default(obj, "use_relative_paths", defaults.use_relative_paths)?;
     ensure_field_from_default(
         obj,
         "remove_empty_directories",
         defaults.remove_empty_directories,
     )?;
+    ensure_field_from_default(obj, "exclude_empty_files", defaults.exclude_empty_files)?;
     ensure_field_from_default(obj, "window_size", defaults.window_size)?;
     ensure_field_from_default(obj, "window_position", defaults.window_position)?;
+    ensure_field_from_default(obj, "ui_zoom", defaults.ui_zoom)?;
+    ensure_field_from_default(obj, "theme", defaults.theme)?;
     ensure_field_from_default(
         obj,
         "auto_load_last_directory",
         defaults.auto_load_last_directory,
     )?;
     ensure_field_from_default(obj, "max_file_size_mb", defaults.max_file_size_mb)?;
+    ensure_field_from_default(obj, "min_file_size_bytes", defaults.min_file_size_bytes)?;
+    ensure_field_from_default(obj, "max_file_size_bytes", defaults.max_file_size_bytes)?;
+    ensure_field_from_default(obj, "modified_within_days", defaults.modified_within_days)?;
     ensure_field_from_default(obj, "scan_chunk_size", defaults.scan_chunk_size)?;
+    ensure_field_from_default(
+        obj,
+        "report_broken_symlinks",
+        defaults.report_broken_symlinks,
+    )?;
+    ensure_field_from_default(obj, "max_threads", defaults.max_threads)?;
+    ensure_field_from_default(obj, "strip_comments", defaults.strip_comments)?;
+    ensure_field_from_default(obj, "strip_blank_lines", defaults.strip_blank_lines)?;
+    ensure_field_from_default(obj, "summarize_over_lines", defaults.summarize_over_lines)?;
+    ensure_field_from_default(obj, "summary_head_lines", defaults.summary_head_lines)?;
+    ensure_field_from_default(obj, "summary_tail_lines", defaults.summary_tail_lines)?;
+    ensure_field_from_default(
+        obj,
+        "normalize_line_endings",
+        defaults.normalize_line_endings,
+    )?;
+    ensure_field_from_default(
+        obj,
+        "trim_trailing_whitespace",
+        defaults.trim_trailing_whitespace,
+    )?;
+    ensure_field_from_default(obj, "tab_policy", defaults.tab_policy)?;
+    ensure_field_from_default(
+        obj,
+        "number_lines_in_output",
+        defaults.number_lines_in_output,
+    )?;
+    ensure_field_from_default(obj, "include_file_mode", defaults.include_file_mode)?;
+    ensure_field_from_default(obj, "include_checksums", defaults.include_checksums)?;
+    ensure_field_from_default(obj, "enable_redaction", defaults.enable_redaction)?;
+    ensure_field_from_default(obj, "redaction_patterns", defaults.redaction_patterns)?;
+    ensure_field_from_default(obj, "sort_order", defaults.sort_order)?;
+    ensure_field_from_default(obj, "concat_order", defaults.concat_order)?;
+    ensure_field_from_default(obj, "output_format", defaults.output_format)?;
+    ensure_field_from_default(obj, "include_manifest", defaults.include_manifest)?;
+    ensure_field_from_default(obj, "include_index", defaults.include_index)?;
+    ensure_field_from_default(
+        obj,
+        "stream_generated_content",
+        defaults.stream_generated_content,
+    )?;
+    ensure_field_from_default(obj, "stream_generation", defaults.stream_generation)?;
+    ensure_field_from_default(obj, "skip_generated", defaults.skip_generated)?;
+    ensure_field_from_default(obj, "auto_include_imports", defaults.auto_include_imports)?;
+    ensure_field_from_default(obj, "skip_unreadable_files", defaults.skip_unreadable_files)?;
+    ensure_field_from_default(obj, "warn_over_bytes", defaults.warn_over_bytes)?;
+    ensure_field_from_default(obj, "split_output_bytes", defaults.split_output_bytes)?;
+    ensure_field_from_default(obj, "chunk_header_template", defaults.chunk_header_template)?;
+    ensure_field_from_default(
+        obj,
+        "content_search_context_lines",
+        defaults.content_search_context_lines,
+    )?;
+    ensure_field_from_default(obj, "search_read_buffer_kb", defaults.search_read_buffer_kb)?;
+    ensure_field_from_default(obj, "max_search_results", defaults.max_search_results)?;
+    ensure_field_from_default(obj, "preview_max_lines", defaults.preview_max_lines)?;
+    ensure_field_from_default(
+        obj,
+        "max_children_displayed",
+        defaults.max_children_displayed,
+    )?;
+    ensure_field_from_default(
+        obj,
+        "preview_generated_head_lines",
+        defaults.preview_generated_head_lines,
+    )?;
+    ensure_field_from_default(obj, "filter_debounce_ms", defaults.filter_debounce_ms)?;
+    ensure_field_from_default(obj, "tree_ascii", defaults.tree_ascii)?;
+    ensure_field_from_default(obj, "include_hidden", defaults.include_hidden)?;
+    ensure_field_from_default(obj, "show_git_status", defaults.show_git_status)?;
+    ensure_field_from_default(obj, "only_git_tracked", defaults.only_git_tracked)?;
+    ensure_field_from_default(obj, "respect_export_ignore", defaults.respect_export_ignore)?;
+    ensure_field_from_default(obj, "exclude_lockfiles", defaults.exclude_lockfiles)?;
+    ensure_field_from_default(obj, "exclude_vendored", defaults.exclude_vendored)?;
+    ensure_field_from_default(obj, "auto_select_on_scan", defaults.auto_select_on_scan)?;
+    ensure_field_from_default(obj, "auto_select_patterns", &defaults.auto_select_patterns)?;
+    ensure_field_from_default(obj, "text_extensions", &defaults.text_extensions)?;
+    ensure_field_from_default(
+        obj,
+        "force_binary_extensions",
+        &defaults.force_binary_extensions,
+    )?;
+    ensure_field_from_default(obj, "language_overrides", &defaults.language_overrides)?;
+    ensure_field_from_default(obj, "log_level", &defaults.log_level)?;
+    ensure_field_from_default(obj, "pinned_files", defaults.pinned_files)?;
+    ensure_field_from_default(obj, "remember_selection", defaults.remember_selection)?;
 
     let migrated_config: AppConfig = serde_json::from_value(Value::Object(obj.clone()))?;
     tracing::info!("Successfully migrated legacy config");
@@ -291,6 +769,55 @@ pub mod tests {
         assert!(nested_path.exists());
     }
 
+    #[test]
+    fn test_load_config_expands_paths_but_saves_them_unexpanded() {
+        let harness = TestHarness::new();
+        std::env::set_var("CFC_TEST_OUTPUT_ROOT", "/data/exports");
+        let home = dirs::home_dir().unwrap();
+        harness.write_to_config_file(
+            &serde_json::to_string(&json!({
+                "output_directory": "${CFC_TEST_OUTPUT_ROOT}/cfc",
+                "ignore_patterns": ["~/secrets", "${CFC_TEST_UNSET_VAR}/cache", "*.log"],
+            }))
+            .unwrap(),
+        );
+
+        let loaded = load_config(Some(&harness.config_path)).unwrap();
+        assert_eq!(
+            loaded.output_directory,
+            Some(PathBuf::from("/data/exports/cfc"))
+        );
+        assert!(loaded
+            .ignore_patterns
+            .contains(&format!("{}/secrets", home.to_string_lossy())));
+        assert!(loaded
+            .ignore_patterns
+            .contains("${CFC_TEST_UNSET_VAR}/cache"));
+        assert!(loaded.ignore_patterns.contains("*.log"));
+
+        save_config(&loaded, Some(&harness.config_path)).unwrap();
+        let saved: Value =
+            serde_json::from_str(&fs::read_to_string(&harness.config_path).unwrap()).unwrap();
+        assert_eq!(saved["output_directory"], "${CFC_TEST_OUTPUT_ROOT}/cfc");
+        let saved_patterns = saved["ignore_patterns"].as_array().unwrap();
+        assert!(saved_patterns.contains(&json!("~/secrets")));
+        assert!(saved.get("unexpanded_paths").is_none());
+    }
+
+    #[test]
+    fn test_expand_path_keeps_unresolvable_parts_literal() {
+        std::env::set_var("CFC_TEST_EXPAND_VAR", "value");
+        assert_eq!(expand_path("a/${CFC_TEST_EXPAND_VAR}/b"), "a/value/b");
+        assert_eq!(
+            expand_path("${CFC_TEST_MISSING_VAR}/b"),
+            "${CFC_TEST_MISSING_VAR}/b"
+        );
+        assert_eq!(expand_path("${}/b"), "${}/b");
+        assert_eq!(expand_path("a/${UNTERMINATED"), "a/${UNTERMINATED");
+        assert_eq!(expand_path("~user/x"), "~user/x");
+        assert_eq!(expand_path("no/vars"), "no/vars");
+    }
+
     // =========================================================================
     // SECTION: import_config Tests
     // =========================================================================
@@ -312,6 +839,132 @@ pub mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_validate_config_reports_issues_without_importing() {
+        let harness = TestHarness::new();
+        let mut config = serde_json::to_value(AppConfig {
+            output_directory: Some(harness.temp_path().to_path_buf()),
+            ..AppConfig::default()
+        })
+        .unwrap();
+        harness.write_to_config_file(&config.to_string());
+        assert_eq!(validate_config(&harness.config_path), Vec::new());
+
+        config["output_filename"] = json!(" ");
+        config["last_directory"] = json!(harness.temp_path().join("missing"));
+        config["ui_zoom"] = json!(10.0);
+        harness.write_to_config_file(&config.to_string());
+        let issues: Vec<_> = validate_config(&harness.config_path)
+            .into_iter()
+            .map(|issue| (issue.severity, issue.field.unwrap()))
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                (IssueSeverity::Error, "output_filename".to_string()),
+                (IssueSeverity::Warning, "last_directory".to_string()),
+                (IssueSeverity::Warning, "ui_zoom".to_string()),
+            ]
+        );
+
+        config["max_file_size_mb"] = json!(-1);
+        harness.write_to_config_file(&config.to_string());
+        let issues = validate_config(&harness.config_path);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, IssueSeverity::Error);
+        assert!(issues[0].message.contains("invalid value: integer `-1`"));
+    }
+
+    // =========================================================================
+    // SECTION: Profile Tests
+    // =========================================================================
+
+    #[test]
+    fn test_profile_roundtrip_list_and_delete() {
+        let harness = TestHarness::new();
+        let dir = harness.temp_path().join("profiles");
+        let mut rust_config = AppConfig::default();
+        rust_config.ignore_patterns.insert("*.ts".to_string());
+
+        save_profile("rust review", &rust_config, Some(&dir)).unwrap();
+        save_profile("docs", &AppConfig::default(), Some(&dir)).unwrap();
+        fs::write(dir.join("notes.txt"), "not a profile").unwrap();
+
+        assert_eq!(
+            list_profiles(Some(&dir)).unwrap(),
+            vec!["docs".to_string(), "rust review".to_string()]
+        );
+        assert_eq!(
+            load_profile("rust review", Some(&dir)).unwrap(),
+            rust_config
+        );
+
+        delete_profile("docs", Some(&dir)).unwrap();
+        assert_eq!(
+            list_profiles(Some(&dir)).unwrap(),
+            vec!["rust review".to_string()]
+        );
+        assert!(load_profile("docs", Some(&dir)).is_err());
+    }
+
+    #[test]
+    fn test_list_profiles_without_directory_is_empty() {
+        let harness = TestHarness::new();
+        let dir = harness.temp_path().join("missing");
+        assert!(list_profiles(Some(&dir)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_profile_names_are_rejected() {
+        let harness = TestHarness::new();
+        let dir = harness.temp_path();
+        for name in ["", "   ", "../escape", "a/b", "a\\b", ".hidden"] {
+            assert!(
+                save_profile(name, &AppConfig::default(), Some(dir)).is_err(),
+                "name {name:?} should be rejected"
+            );
+        }
+        assert!(list_profiles(Some(dir)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sessions_are_kept_per_root_and_removed_when_empty() {
+        let harness = TestHarness::new();
+        let sessions_path = harness.temp_path().join("sessions.json");
+        let session = SavedSession {
+            selected_files: vec![PathBuf::from("src/main.rs")],
+            expanded_dirs: vec![PathBuf::from("src")],
+        };
+
+        save_session(Path::new("/repo/a"), &session, Some(&sessions_path)).unwrap();
+        save_session(
+            Path::new("/repo/b"),
+            &SavedSession::default(),
+            Some(&sessions_path),
+        )
+        .unwrap();
+
+        assert_eq!(
+            load_session(Path::new("/repo/a"), Some(&sessions_path)).unwrap(),
+            Some(session)
+        );
+        assert_eq!(
+            load_session(Path::new("/repo/b"), Some(&sessions_path)).unwrap(),
+            None
+        );
+
+        save_session(
+            Path::new("/repo/a"),
+            &SavedSession::default(),
+            Some(&sessions_path),
+        )
+        .unwrap();
+        assert_eq!(
+            load_session(Path::new("/repo/a"), Some(&sessions_path)).unwrap(),
+            None
+        );
+    }
+
     // =========================================================================
     // SECTION: Migration Logic & Helpers Tests
     // =========================================================================
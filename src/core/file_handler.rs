@@ -1,13 +1,164 @@
 //! Handles file content operations like reading, previewing, and concatenation.
 
-use super::{CoreError, FileItem, TreeGenerator};
-use crate::utils::file_detection::is_text_file;
+use super::{
+    ContentTransform, CoreError, FileItem, IndexEntry, Manifest, OutputFormat, TreeGenerator,
+    TreePosition, TreeStyle,
+};
+use crate::utils::file_detection::{get_language_from_path, is_text_file_with, ExtensionOverrides};
+use sha2::{Digest, Sha256};
 use std::collections::HashSet;
 use std::fs;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+
+/// Files larger than this are left out of the output with a note.
+pub const MAX_CONTENT_BYTES: u64 = 20 * 1024 * 1024;
+
+/// The concatenated output together with the location of each file's content.
+#[derive(Debug, Clone, Default)]
+pub struct ConcatenatedOutput {
+    /// The full generated text.
+    pub content: String,
+    /// The byte range of each file's content block within `content`, in output order.
+    pub file_ranges: Vec<(PathBuf, Range<usize>)>,
+    /// The byte range of all file blocks within `content`, between the preamble and
+    /// the epilogue.
+    pub body: Range<usize>,
+}
+
+/// A generated document kept in a temporary file instead of in memory, see
+/// `FileHandler::generate_spilled_output`.
+#[derive(Debug)]
+pub struct SpilledOutput {
+    /// The file holding the document. It is deleted once this is dropped.
+    pub file: tempfile::TempPath,
+    /// The byte range of each file's content block within the file, in output order.
+    pub file_ranges: Vec<(PathBuf, Range<usize>)>,
+    /// The byte range of all file blocks within the file.
+    pub body: Range<usize>,
+    /// The length of the document in bytes.
+    pub length: usize,
+}
+
+/// A generated document, held in memory or spilled to a temporary file.
+#[derive(Debug)]
+pub enum GeneratedOutput {
+    InMemory(ConcatenatedOutput),
+    Spilled(SpilledOutput),
+}
+
+impl SpilledOutput {
+    /// Opens the document to read it front to back, part by part.
+    pub fn reader(&self) -> Result<SpilledReader, CoreError> {
+        let file = fs::File::open(&self.file)
+            .map_err(|e| CoreError::Io(e.to_string(), self.file.to_path_buf()))?;
+        Ok(SpilledReader {
+            reader: BufReader::new(file),
+            position: 0,
+            path: self.file.to_path_buf(),
+        })
+    }
+
+    /// Reads the whole document into memory.
+    pub fn read_all(&self) -> Result<String, CoreError> {
+        fs::read_to_string(&self.file)
+            .map_err(|e| CoreError::Io(e.to_string(), self.file.to_path_buf()))
+    }
+
+    /// Copies the document into a new temporary file with each `(offset, text)` of
+    /// `insertions` inserted at that byte offset, e.g. to add the index and the
+    /// manifest. Insertions must be ordered by offset; at the same offset they are
+    /// inserted in the given order. The ranges are shifted accordingly.
+    pub fn with_insertions(&self, insertions: &[(usize, &str)]) -> Result<Self, CoreError> {
+        let spill_dir = std::env::temp_dir();
+        let target =
+            tempfile::NamedTempFile::new().map_err(|e| CoreError::Io(e.to_string(), spill_dir))?;
+        let target_path = target.path().to_path_buf();
+        let io_error = |e: io::Error, path: &Path| CoreError::Io(e.to_string(), path.to_path_buf());
+        let mut source =
+            BufReader::new(fs::File::open(&self.file).map_err(|e| io_error(e, &self.file))?);
+        let mut writer = BufWriter::new(target);
+
+        let mut copied = 0;
+        for &(offset, text) in insertions {
+            io::copy(
+                &mut (&mut source).take((offset - copied) as u64),
+                &mut writer,
+            )
+            .and_then(|_| writer.write_all(text.as_bytes()))
+            .map_err(|e| io_error(e, &target_path))?;
+            copied = offset;
+        }
+        io::copy(&mut source, &mut writer).map_err(|e| io_error(e, &target_path))?;
+        let target = writer
+            .into_inner()
+            .map_err(|e| io_error(e.into_error(), &target_path))?;
+
+        let shift = |position: usize| -> usize {
+            position
+                + insertions
+                    .iter()
+                    .filter(|(offset, _)| *offset <= position)
+                    .map(|(_, text)| text.len())
+                    .sum::<usize>()
+        };
+        let shift_range =
+            |range: &Range<usize>| shift(range.start)..shift(range.start) + range.len();
+        Ok(Self {
+            file: target.into_temp_path(),
+            file_ranges: self
+                .file_ranges
+                .iter()
+                .map(|(path, range)| (path.clone(), shift_range(range)))
+                .collect(),
+            body: shift_range(&self.body),
+            length: self.length + insertions.iter().map(|(_, text)| text.len()).sum::<usize>(),
+        })
+    }
+}
+
+/// Reads a spilled document front to back, see `SpilledOutput::reader`.
+pub struct SpilledReader {
+    reader: BufReader<fs::File>,
+    position: usize,
+    path: PathBuf,
+}
+
+impl SpilledReader {
+    /// Reads on up to byte `end` of the document, which must lie on a character
+    /// boundary, e.g. the end of a file's content block.
+    pub fn read_to(&mut self, end: usize) -> Result<String, CoreError> {
+        let io_error = |e: io::Error| CoreError::Io(e.to_string(), self.path.clone());
+        let mut bytes = vec![0; end - self.position];
+        self.reader.read_exact(&mut bytes).map_err(io_error)?;
+        self.position = end;
+        String::from_utf8(bytes)
+            .map_err(|e| io_error(io::Error::new(io::ErrorKind::InvalidData, e.utf8_error())))
+    }
+}
+
+/// The header that starts each part of a split output, see `FileHandler::split_output`.
+pub const DEFAULT_CHUNK_HEADER_TEMPLATE: &str = "// Part {part} of {total} — continues {root}";
+
+/// Where the parts of a document written by `write_concatenated_output` ended up.
+struct OutputLayout {
+    file_ranges: Vec<(PathBuf, Range<usize>)>,
+    body: Range<usize>,
+    /// The length of the whole document in bytes.
+    length: usize,
+}
+
+/// The beginning of a file, as shown in the preview panel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePreview {
+    pub content: String,
+    /// The number of lines in the file if `content` only holds the first lines.
+    pub total_lines: Option<usize>,
+}
 
 /// A utility struct for handling file-related operations.
 ///
@@ -18,7 +169,9 @@ impl FileHandler {
     /// Generates a single string by concatenating the content of selected files.
     ///
     /// It includes a header with metadata, an optional directory tree, and formatted
-    /// content blocks for each selected file. This operation is cancellable.
+    /// content blocks for each selected file. Each file's content is passed through
+    /// `transform` (e.g. comment stripping) before being appended. This operation is cancellable.
+    #[allow(clippy::too_many_arguments)]
     pub async fn generate_concatenated_content_simple(
         selected_files: &[PathBuf],
         root_path: &Path,
@@ -26,26 +179,176 @@ impl FileHandler {
         items_for_tree: Vec<FileItem>,
         tree_ignore_patterns: HashSet<String>,
         use_relative_paths: bool,
+        transform: ContentTransform,
         cancel_flag: Arc<AtomicBool>,
         // This parameter only exists during `cargo test` runs. It allows deterministic
         // testing of the cancellation logic without affecting the production build.
-        #[cfg(test)] mut test_notifier: Option<tokio::sync::oneshot::Sender<()>>,
+        #[cfg(test)] test_notifier: Option<tokio::sync::oneshot::Sender<()>>,
     ) -> Result<String, CoreError> {
-        let mut content = String::new();
-        content.push_str(&format!(
-            "# CFC Output - Generated: {}\n",
-            chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
-        ));
-        content.push_str(&format!("# Total files: {}\n\n", selected_files.len()));
-
-        if include_tree {
-            let tree =
-                TreeGenerator::generate_tree(&items_for_tree, root_path, &tree_ignore_patterns);
-            content.push_str("# DIRECTORY TREE\n");
-            content.push_str("=====================\n");
-            content.push_str(&tree);
-            content.push_str("=====================\n\n");
-        }
+        let tree_position = if include_tree {
+            TreePosition::Top
+        } else {
+            TreePosition::None
+        };
+        Self::generate_concatenated_output(
+            selected_files,
+            root_path,
+            tree_position,
+            items_for_tree,
+            tree_ignore_patterns,
+            TreeStyle::Unicode,
+            use_relative_paths,
+            transform,
+            OutputFormat::PlainText,
+            false,
+            cancel_flag,
+            #[cfg(test)]
+            test_notifier,
+        )
+        .await
+        .map(|output| output.content)
+    }
+
+    /// Like `generate_concatenated_content_simple`, but renders the document in `format`,
+    /// places the directory tree at `tree_position` and also reports where each file's
+    /// content ended up in the output so callers can process files individually. With
+    /// `skip_unreadable`, a file that cannot be read is replaced by an
+    /// `[error reading file: ...]` note instead of failing the whole run.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn generate_concatenated_output(
+        selected_files: &[PathBuf],
+        root_path: &Path,
+        tree_position: TreePosition,
+        items_for_tree: Vec<FileItem>,
+        tree_ignore_patterns: HashSet<String>,
+        tree_style: TreeStyle,
+        use_relative_paths: bool,
+        transform: ContentTransform,
+        format: OutputFormat,
+        skip_unreadable: bool,
+        cancel_flag: Arc<AtomicBool>,
+        #[cfg(test)] test_notifier: Option<tokio::sync::oneshot::Sender<()>>,
+    ) -> Result<ConcatenatedOutput, CoreError> {
+        let mut buffer = Vec::new();
+        let layout = Self::write_concatenated_output(
+            &mut buffer,
+            Path::new(""),
+            selected_files,
+            root_path,
+            tree_position,
+            items_for_tree,
+            tree_ignore_patterns,
+            tree_style,
+            use_relative_paths,
+            transform,
+            format,
+            skip_unreadable,
+            cancel_flag,
+            #[cfg(test)]
+            test_notifier,
+        )
+        .await?;
+        Ok(ConcatenatedOutput {
+            content: String::from_utf8(buffer).expect("output is assembled from UTF-8 text"),
+            file_ranges: layout.file_ranges,
+            body: layout.body,
+        })
+    }
+
+    /// Like `generate_concatenated_output`, but streams the document into a temporary
+    /// file while it is generated and leaves it there. Only one file's content is held
+    /// in memory at a time. The document is identical.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn generate_spilled_output(
+        selected_files: &[PathBuf],
+        root_path: &Path,
+        tree_position: TreePosition,
+        items_for_tree: Vec<FileItem>,
+        tree_ignore_patterns: HashSet<String>,
+        tree_style: TreeStyle,
+        use_relative_paths: bool,
+        transform: ContentTransform,
+        format: OutputFormat,
+        skip_unreadable: bool,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<SpilledOutput, CoreError> {
+        let spill_dir = std::env::temp_dir();
+        let io_error = |e: io::Error, path: &Path| CoreError::Io(e.to_string(), path.to_path_buf());
+        let spill = tempfile::NamedTempFile::new().map_err(|e| io_error(e, &spill_dir))?;
+        let spill_path = spill.path().to_path_buf();
+
+        let mut writer = BufWriter::new(spill);
+        let layout = Self::write_concatenated_output(
+            &mut writer,
+            &spill_path,
+            selected_files,
+            root_path,
+            tree_position,
+            items_for_tree,
+            tree_ignore_patterns,
+            tree_style,
+            use_relative_paths,
+            transform,
+            format,
+            skip_unreadable,
+            cancel_flag,
+            #[cfg(test)]
+            None,
+        )
+        .await?;
+        let spill = writer
+            .into_inner()
+            .map_err(|e| io_error(e.into_error(), &spill_path))?;
+        Ok(SpilledOutput {
+            file: spill.into_temp_path(),
+            file_ranges: layout.file_ranges,
+            body: layout.body,
+            length: layout.length,
+        })
+    }
+
+    /// Writes the concatenated document to `out` file by file, releasing each file's
+    /// content before the next one is read. `out_path` names the destination in I/O
+    /// errors. Returns where each file's content and the body ended up.
+    #[allow(clippy::too_many_arguments)]
+    async fn write_concatenated_output<W: Write + Send>(
+        out: &mut W,
+        out_path: &Path,
+        selected_files: &[PathBuf],
+        root_path: &Path,
+        tree_position: TreePosition,
+        items_for_tree: Vec<FileItem>,
+        tree_ignore_patterns: HashSet<String>,
+        tree_style: TreeStyle,
+        use_relative_paths: bool,
+        transform: ContentTransform,
+        format: OutputFormat,
+        skip_unreadable: bool,
+        cancel_flag: Arc<AtomicBool>,
+        #[cfg(test)] mut test_notifier: Option<tokio::sync::oneshot::Sender<()>>,
+    ) -> Result<OutputLayout, CoreError> {
+        let mut written = 0;
+        let mut emit = |out: &mut W, text: &str| -> Result<usize, CoreError> {
+            out.write_all(text.as_bytes())
+                .map_err(|e| CoreError::Io(e.to_string(), out_path.to_path_buf()))?;
+            written += text.len();
+            Ok(written)
+        };
+
+        let body_start = emit(
+            out,
+            &Self::output_preamble(
+                selected_files.len(),
+                tree_position,
+                &items_for_tree,
+                root_path,
+                &tree_ignore_patterns,
+                tree_style,
+                format,
+            ),
+        )?;
+        let mut body_end = body_start;
+        let mut file_ranges = Vec::with_capacity(selected_files.len());
 
         for file_path in selected_files {
             // In test builds, this block allows a test to synchronize with the function,
@@ -72,63 +375,454 @@ impl FileHandler {
                 continue;
             }
 
-            let display_path = if use_relative_paths {
-                if let Some(parent) = root_path.parent() {
-                    file_path.strip_prefix(parent)?.display().to_string()
-                } else {
-                    // Fallback for root paths that have no parent (e.g., "/")
-                    file_path.display().to_string()
-                }
+            let display_path = Self::display_path(file_path, root_path, use_relative_paths)?;
+            let mode = Self::file_mode(file_path, &transform);
+            // Read before the header is written, as it shows the checksum of this read.
+            let (file_content, checksum) =
+                Self::load_output_content(file_path, transform.clone(), format, skip_unreadable)
+                    .await?;
+            let start = emit(
+                out,
+                &format.file_header(
+                    &display_path,
+                    &get_language_from_path(file_path, transform.language_overrides.as_deref()),
+                    mode.as_deref(),
+                    checksum.as_deref(),
+                ),
+            )?;
+            let end = emit(out, &file_content)?;
+            file_ranges.push((file_path.clone(), start..end));
+
+            // Ensure the content block ends with a newline for consistent formatting.
+            if format.needs_trailing_newline() && !file_content.ends_with('\n') {
+                emit(out, "\n")?;
+            }
+            body_end = emit(out, format.file_footer())?;
+        }
+        let length = emit(
+            out,
+            &Self::output_epilogue(
+                tree_position,
+                &items_for_tree,
+                root_path,
+                &tree_ignore_patterns,
+                tree_style,
+                format,
+            ),
+        )?;
+        Ok(OutputLayout {
+            file_ranges,
+            body: body_start..body_end,
+            length,
+        })
+    }
+
+    /// Rebuilds `output` around its unchanged file blocks with a fresh preamble and
+    /// epilogue, e.g. to move or drop the directory tree without reading the files
+    /// again. `file_count` is the number of selected files the output was generated for.
+    #[allow(clippy::too_many_arguments)]
+    pub fn reframe_output(
+        output: &ConcatenatedOutput,
+        file_count: usize,
+        tree_position: TreePosition,
+        items_for_tree: &[FileItem],
+        root_path: &Path,
+        tree_ignore_patterns: &HashSet<String>,
+        tree_style: TreeStyle,
+        format: OutputFormat,
+    ) -> ConcatenatedOutput {
+        let mut content = Self::output_preamble(
+            file_count,
+            tree_position,
+            items_for_tree,
+            root_path,
+            tree_ignore_patterns,
+            tree_style,
+            format,
+        );
+        let body_start = content.len();
+        content.push_str(&output.content[output.body.clone()]);
+        let body = body_start..content.len();
+        content.push_str(&Self::output_epilogue(
+            tree_position,
+            items_for_tree,
+            root_path,
+            tree_ignore_patterns,
+            tree_style,
+            format,
+        ));
+
+        let file_ranges = output
+            .file_ranges
+            .iter()
+            .map(|(path, range)| {
+                let start = range.start - output.body.start + body_start;
+                (path.clone(), start..start + range.len())
+            })
+            .collect();
+        ConcatenatedOutput {
+            content,
+            file_ranges,
+            body,
+        }
+    }
+
+    /// Splits `output` at file boundaries into parts of at most `max_bytes` each, so
+    /// they can be pasted one at a time. The preamble stays with the first part and the
+    /// epilogue with the last; a file block larger than `max_bytes` gets a part of its
+    /// own. Each part starts with `header_template`, in which `{part}`, `{total}`,
+    /// `{root}` and `{files}` are replaced with the part number, the number of parts,
+    /// the name of `root_path` and a comma-separated index of the files in the part.
+    pub fn split_output(
+        output: &ConcatenatedOutput,
+        max_bytes: usize,
+        header_template: &str,
+        root_path: &Path,
+    ) -> Vec<String> {
+        let content = output.content.as_str();
+        let mut blocks = Vec::with_capacity(output.file_ranges.len());
+        for (index, (path, range)) in output.file_ranges.iter().enumerate() {
+            let end = output
+                .file_ranges
+                .get(index + 1)
+                .map_or(output.body.end, |(_, next)| next.start);
+            blocks.push((path, range.start..end));
+        }
+
+        // Each part is a range of `content` plus the files it holds.
+        let mut parts: Vec<(Range<usize>, Vec<&PathBuf>)> =
+            vec![(0..output.body.start, Vec::new())];
+        for (path, block) in blocks {
+            let (current, files) = parts.last_mut().expect("there is always a part");
+            if !files.is_empty() && block.end - current.start > max_bytes {
+                parts.push((block.start..block.end, vec![path]));
             } else {
-                file_path.display().to_string()
+                current.end = block.end;
+                files.push(path);
+            }
+        }
+        parts.last_mut().expect("there is always a part").0.end = content.len();
+
+        let total = parts.len();
+        let root = root_path.file_name().map_or_else(
+            || root_path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        parts
+            .into_iter()
+            .enumerate()
+            .map(|(index, (range, files))| {
+                if header_template.is_empty() {
+                    return content[range].to_string();
+                }
+                let files = files
+                    .iter()
+                    .map(|path| {
+                        path.strip_prefix(root_path)
+                            .unwrap_or(path)
+                            .display()
+                            .to_string()
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let header = header_template
+                    .replace("{part}", &(index + 1).to_string())
+                    .replace("{total}", &total.to_string())
+                    .replace("{root}", &root)
+                    .replace("{files}", &files);
+                format!("{header}\n\n{}", &content[range])
+            })
+            .collect()
+    }
+
+    /// Builds the metadata header and, for `TreePosition::Top`, the directory tree
+    /// section that precede the file blocks in the concatenated output.
+    pub fn output_preamble(
+        file_count: usize,
+        tree_position: TreePosition,
+        items_for_tree: &[FileItem],
+        root_path: &Path,
+        tree_ignore_patterns: &HashSet<String>,
+        tree_style: TreeStyle,
+        format: OutputFormat,
+    ) -> String {
+        let generated = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let tree = (tree_position == TreePosition::Top).then(|| {
+            TreeGenerator::generate_tree_with_style(
+                items_for_tree,
+                root_path,
+                tree_ignore_patterns,
+                tree_style,
+            )
+        });
+        format.preamble(&generated, file_count, tree.as_deref())
+    }
+
+    /// Builds what follows the last file block: the directory tree section for
+    /// `TreePosition::Bottom`, then the markup that closes the document.
+    pub fn output_epilogue(
+        tree_position: TreePosition,
+        items_for_tree: &[FileItem],
+        root_path: &Path,
+        tree_ignore_patterns: &HashSet<String>,
+        tree_style: TreeStyle,
+        format: OutputFormat,
+    ) -> String {
+        let mut content = String::new();
+        if tree_position == TreePosition::Bottom {
+            let tree = TreeGenerator::generate_tree_with_style(
+                items_for_tree,
+                root_path,
+                tree_ignore_patterns,
+                tree_style,
+            );
+            content.push_str(&format.tree_section(&tree));
+        }
+        content.push_str(format.epilogue());
+        content
+    }
+
+    /// Renders the manifest for `selected_files`: root, timestamp, file count, total
+    /// size and the relative path of every included file. `total_tokens` is the token
+    /// count of the document the manifest is prepended to. Directories are skipped.
+    pub fn manifest(
+        selected_files: &[PathBuf],
+        root_path: &Path,
+        total_tokens: usize,
+        format: OutputFormat,
+    ) -> String {
+        let mut files = Vec::with_capacity(selected_files.len());
+        let mut total_size = 0;
+        for path in selected_files {
+            let Ok(metadata) = fs::metadata(path) else {
+                continue;
             };
+            if metadata.is_dir() {
+                continue;
+            }
+            total_size += metadata.len();
+            let relative = path.strip_prefix(root_path).unwrap_or(path);
+            files.push(relative.display().to_string());
+        }
 
-            content.push_str(&format!("{display_path}\n"));
-            content.push_str("===FILE-START===\n");
+        let root = root_path.display().to_string();
+        let generated = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        format.manifest(&Manifest {
+            root: &root,
+            generated: &generated,
+            total_size,
+            total_tokens,
+            files: &files,
+        })
+    }
 
-            let file_content = Self::read_file_content(file_path)?;
-            content.push_str(&file_content);
+    /// Inserts a rendered manifest at the top of `content`, after the markup that
+    /// opens the document.
+    pub fn insert_manifest(content: &mut String, manifest: &str, format: OutputFormat) {
+        let offset = format.document_start().len().min(content.len());
+        content.insert_str(offset, manifest);
+    }
 
-            // Ensure the content block ends with a newline for consistent formatting.
-            if !file_content.ends_with('\n') {
-                content.push('\n');
+    /// Renders the index of `output`'s files in concatenation order, with the bytes and
+    /// lines of each file's content block. `file_tokens` holds the token count of every
+    /// block, in the same order. The caller inserts it at `output.body.start`.
+    pub fn index(
+        output: &ConcatenatedOutput,
+        root_path: &Path,
+        use_relative_paths: bool,
+        file_tokens: &[usize],
+        format: OutputFormat,
+    ) -> String {
+        let entries: Vec<IndexEntry> = output
+            .file_ranges
+            .iter()
+            .zip(file_tokens)
+            .map(|((path, range), &tokens)| {
+                let display_path = Self::display_path(path, root_path, use_relative_paths)
+                    .unwrap_or_else(|_| path.display().to_string());
+                IndexEntry::new(display_path, &output.content[range.clone()], tokens)
+            })
+            .collect();
+        format.index(&entries)
+    }
+
+    /// The path shown in a file block's header.
+    pub fn display_path(
+        file_path: &Path,
+        root_path: &Path,
+        use_relative_paths: bool,
+    ) -> Result<String, CoreError> {
+        if use_relative_paths {
+            if let Some(parent) = root_path.parent() {
+                Ok(file_path.strip_prefix(parent)?.display().to_string())
+            } else {
+                // Fallback for root paths that have no parent (e.g., "/")
+                Ok(file_path.display().to_string())
             }
-            content.push_str("---FILE-END-----\n\n");
+        } else {
+            Ok(file_path.display().to_string())
         }
-        Ok(content)
     }
 
-    /// Reads the content of a file, with safeguards for large or binary files.
-    fn read_file_content(file_path: &Path) -> Result<String, CoreError> {
+    /// The permissions shown in a file's header if `transform.include_file_mode` is
+    /// set: the Unix mode in octal, e.g. `mode: 100755`, or on Windows the read-only and
+    /// hidden attributes, e.g. `attributes: readonly`. `None` if the option is off, the
+    /// metadata cannot be read or a Windows file has neither attribute.
+    pub fn file_mode(file_path: &Path, transform: &ContentTransform) -> Option<String> {
+        if !transform.include_file_mode {
+            return None;
+        }
+        let metadata = fs::metadata(file_path).ok()?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            Some(format!("mode: {:o}", metadata.mode()))
+        }
+        #[cfg(windows)]
+        {
+            use std::os::windows::fs::MetadataExt;
+            const FILE_ATTRIBUTE_READONLY: u32 = 0x1;
+            const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+            let attributes = metadata.file_attributes();
+            let names: Vec<&str> = [
+                (FILE_ATTRIBUTE_READONLY, "readonly"),
+                (FILE_ATTRIBUTE_HIDDEN, "hidden"),
+            ]
+            .into_iter()
+            .filter(|(flag, _)| attributes & flag != 0)
+            .map(|(_, name)| name)
+            .collect();
+            (!names.is_empty()).then(|| format!("attributes: {}", names.join(", ")))
+        }
+        #[cfg(not(any(unix, windows)))]
+        {
+            let _ = metadata;
+            None
+        }
+    }
+
+    /// The SHA-256 of the file on disk in hex, as its header shows it with
+    /// `transform.include_checksum`. `None` if the file cannot be read or is too large
+    /// to be included.
+    pub fn file_checksum(file_path: &Path) -> Option<String> {
+        let mut file = fs::File::open(file_path).ok()?;
+        if file.metadata().ok()?.len() > MAX_CONTENT_BYTES {
+            return None;
+        }
+        let mut hasher = Sha256::new();
+        io::copy(&mut file, &mut hasher).ok()?;
+        Some(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Reads a file's content as it appears in the output: passed through `transform`,
+    /// then escaped for `format`. With `transform.include_checksum`, also returns the
+    /// SHA-256 of the file as read from disk, computed from the same read.
+    pub fn read_rendered_content(
+        file_path: &Path,
+        transform: &ContentTransform,
+        format: OutputFormat,
+    ) -> Result<(String, Option<String>), CoreError> {
+        let (mut file_content, checksum) =
+            Self::read_file_content(file_path, transform.include_checksum)?;
+        if !transform.is_noop() {
+            let language =
+                get_language_from_path(file_path, transform.language_overrides.as_deref());
+            file_content = transform.apply(&file_content, &language);
+        }
+        let file_content = match format {
+            OutputFormat::PlainText => file_content,
+            OutputFormat::Html | OutputFormat::Xml => format.escape(&file_content),
+        };
+        Ok((file_content, checksum))
+    }
+
+    /// Like `read_rendered_content`, but with `skip_unreadable` a file that cannot be
+    /// read yields an `[error reading file: ...]` note, and no checksum, instead of an
+    /// error.
+    pub fn read_output_content(
+        file_path: &Path,
+        transform: &ContentTransform,
+        format: OutputFormat,
+        skip_unreadable: bool,
+    ) -> Result<(String, Option<String>), CoreError> {
+        match Self::read_rendered_content(file_path, transform, format) {
+            Err(CoreError::Io(message, _)) if skip_unreadable => {
+                tracing::warn!(
+                    "Skipping unreadable file {}: {}",
+                    file_path.display(),
+                    message
+                );
+                Ok((
+                    format.escape(&format!("[error reading file: {message}]")),
+                    None,
+                ))
+            }
+            result => result,
+        }
+    }
+
+    /// Runs `read_output_content` on the blocking thread pool, so that slow reads and
+    /// the backoff between retries never stall an async worker.
+    pub async fn load_output_content(
+        file_path: &Path,
+        transform: ContentTransform,
+        format: OutputFormat,
+        skip_unreadable: bool,
+    ) -> Result<(String, Option<String>), CoreError> {
+        let file_path = file_path.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            Self::read_output_content(&file_path, &transform, format, skip_unreadable)
+        })
+        .await?
+    }
+
+    /// Reads the content of a file, with safeguards for large or binary files. Transient
+    /// I/O errors are retried with a short backoff. With `checksum`, the SHA-256 of the
+    /// bytes read is returned as well; files too large to be read have none.
+    fn read_file_content(
+        file_path: &Path,
+        checksum: bool,
+    ) -> Result<(String, Option<String>), CoreError> {
+        let sha256 = |bytes: &[u8]| checksum.then(|| format!("{:x}", Sha256::digest(bytes)));
         let metadata =
             // VET: Convert error to string
-            fs::metadata(file_path).map_err(|e| CoreError::Io(e.to_string(), file_path.to_path_buf()))?;
+            with_retry(|| fs::metadata(file_path))
+                .map_err(|e| CoreError::Io(e.to_string(), file_path.to_path_buf()))?;
 
         // Skip files that exceed the size limit to prevent excessive memory usage.
-        if metadata.len() > 20 * 1024 * 1024 {
-            return Ok(format!(
-                "[FILE TOO LARGE: {} bytes - CONTENT SKIPPED]",
-                metadata.len()
+        if metadata.len() > MAX_CONTENT_BYTES {
+            return Ok((
+                format!(
+                    "[FILE TOO LARGE: {} bytes - CONTENT SKIPPED]",
+                    metadata.len()
+                ),
+                None,
             ));
         }
 
         // Attempt to read the file as a UTF-8 string.
-        match fs::read_to_string(file_path) {
-            Ok(content) => Ok(content),
+        match with_retry(|| fs::read_to_string(file_path)) {
+            Ok(content) => {
+                let checksum = sha256(content.as_bytes());
+                Ok((content, checksum))
+            }
             // If reading as a string fails, it's likely binary or has an incompatible encoding.
             Err(_) => {
                 let bytes =
                     // VET: Convert error to string
-                    fs::read(file_path).map_err(|e| CoreError::Io(e.to_string(), file_path.to_path_buf()))?;
+                    with_retry(|| fs::read(file_path))
+                        .map_err(|e| CoreError::Io(e.to_string(), file_path.to_path_buf()))?;
 
                 // Use a lossy conversion to create a string preview. If the conversion
                 // introduces Unicode replacement characters, we classify it as binary.
+                let checksum = sha256(&bytes);
                 match String::from_utf8_lossy(&bytes) {
-                    content if content.contains('\u{FFFD}') => {
-                        Ok("[BINARY OR NON-UTF8 FILE - CONTENT SKIPPED]".to_string())
-                    }
+                    content if content.contains('\u{FFFD}') => Ok((
+                        "[BINARY OR NON-UTF8 FILE - CONTENT SKIPPED]".to_string(),
+                        checksum,
+                    )),
                     // Otherwise, the content might be valid in a different encoding but still mostly readable.
-                    content => Ok(content.to_string()),
+                    content => Ok((content.to_string(), checksum)),
                 }
             }
         }
@@ -136,18 +830,28 @@ impl FileHandler {
 
     /// Retrieves a truncated preview of a text file's content.
     ///
-    /// Reads up to a specified maximum number of lines. Identifies directories and binary files.
-    pub fn get_file_preview(file_path: &Path, max_lines: usize) -> Result<String, CoreError> {
+    /// Reads up to a specified maximum number of lines. Identifies directories and binary files,
+    /// honouring the extensions in `overrides`. Lines past the limit are only counted, so
+    /// large files stay cheap in memory.
+    pub fn get_file_preview(
+        file_path: &Path,
+        max_lines: usize,
+        overrides: &ExtensionOverrides,
+    ) -> Result<FilePreview, CoreError> {
+        let placeholder = |content: &str| FilePreview {
+            content: content.to_string(),
+            total_lines: None,
+        };
         if file_path.is_dir() {
-            return Ok("[DIRECTORY]".to_string());
+            return Ok(placeholder("[DIRECTORY]"));
         }
 
         // Use a utility to quickly check if the file is likely text-based.
-        if !is_text_file(file_path)
+        if !is_text_file_with(file_path, overrides)
             // VET: Convert error to string
             .map_err(|e| CoreError::Io(e.to_string(), file_path.to_path_buf()))?
         {
-            return Ok("[BINARY FILE]".to_string());
+            return Ok(placeholder("[BINARY FILE]"));
         }
 
         let file =
@@ -156,10 +860,15 @@ impl FileHandler {
         let reader = BufReader::new(file);
         let mut preview = String::new();
 
-        for (i, line) in reader.lines().enumerate() {
+        let mut lines = reader.lines().enumerate();
+        while let Some((i, line)) = lines.next() {
             if i >= max_lines {
                 preview.push_str("...\n[Preview truncated]");
-                break;
+                // The rest is only counted, one line at a time.
+                return Ok(FilePreview {
+                    content: preview,
+                    total_lines: Some(i + 1 + lines.count()),
+                });
             }
             match line {
                 Ok(line_content) => {
@@ -173,14 +882,54 @@ impl FileHandler {
                 }
             }
         }
-        Ok(preview)
+        Ok(FilePreview {
+            content: preview,
+            total_lines: None,
+        })
+    }
+}
+
+/// How often a read failing with a transient error is attempted in total.
+const READ_ATTEMPTS: u32 = 3;
+/// The wait before the first retry; it doubles with every further attempt.
+const READ_RETRY_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Runs the file operation `op`, retrying it while it fails with a transient error.
+/// Sleeps between attempts, so it must run off the async workers, see
+/// `FileHandler::load_output_content`.
+fn with_retry<T>(mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let mut backoff = READ_RETRY_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Err(e) if attempt < READ_ATTEMPTS && is_transient(&e) => {
+                tracing::debug!("Retrying transient read error: {}", e);
+                std::thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Whether an I/O error may go away on its own, e.g. a timeout on a network share.
+fn is_transient(error: &io::Error) -> bool {
+    // `EIO` has no error kind of its own. Its number is 5 on all supported Unix platforms.
+    const EIO: i32 = 5;
+    if cfg!(unix) && error.raw_os_error() == Some(EIO) {
+        return true;
     }
+    matches!(
+        error.kind(),
+        io::ErrorKind::Interrupted | io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::FileItem;
+    use crate::core::{FileItem, LineEndings, LineSummary, Redaction};
     use std::collections::HashSet;
     use std::fs::{self, File};
     use std::io::Write;
@@ -246,6 +995,9 @@ mod tests {
                     size: metadata.len(),
                     depth: p.split('/').count(),
                     parent: full_path.parent().map(|p| p.to_path_buf()),
+                    mtime: None,
+                    likely_generated: false,
+                    git_status: None,
                 }
             })
             .collect()
@@ -267,6 +1019,7 @@ mod tests {
             all_items,
             HashSet::new(),
             true,
+            ContentTransform::default(),
             Arc::new(AtomicBool::new(false)),
             #[cfg(test)]
             None,
@@ -295,6 +1048,7 @@ mod tests {
             vec![],
             HashSet::new(),
             false,
+            ContentTransform::default(),
             Arc::new(AtomicBool::new(false)),
             #[cfg(test)]
             None,
@@ -312,6 +1066,43 @@ mod tests {
         });
     }
 
+    #[tokio::test]
+    async fn concatenated_content_applies_content_transform() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        fs::write(
+            root.join("lib.rs"),
+            "// doc\nfn a() {}\n\n\n\nfn b() {} // tail\n",
+        )
+        .unwrap();
+        fs::write(root.join("run.py"), "# setup\nimport os\n").unwrap();
+        let selected_files = vec![root.join("lib.rs"), root.join("run.py")];
+
+        let content = FileHandler::generate_concatenated_content_simple(
+            &selected_files,
+            &root,
+            false,
+            vec![],
+            HashSet::new(),
+            true,
+            ContentTransform {
+                strip_comments: true,
+                strip_blank_lines: true,
+                ..ContentTransform::default()
+            },
+            Arc::new(AtomicBool::new(false)),
+            #[cfg(test)]
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert!(content.contains("===FILE-START===\nfn a() {}\n\nfn b() {}\n---FILE-END-----"));
+        assert!(content.contains("===FILE-START===\nimport os\n---FILE-END-----"));
+        assert!(!content.contains("// doc"));
+        assert!(!content.contains("# setup"));
+    }
+
     #[tokio::test]
     async fn concatenated_content_with_tree_ignores() {
         let (_dir, root) = setup_test_environment();
@@ -341,6 +1132,7 @@ mod tests {
             all_items,
             tree_ignore_patterns,
             true,
+            ContentTransform::default(),
             Arc::new(AtomicBool::new(false)),
             #[cfg(test)]
             None,
@@ -370,6 +1162,7 @@ mod tests {
             vec![],
             HashSet::new(),
             true,
+            ContentTransform::default(),
             Arc::new(AtomicBool::new(false)),
             #[cfg(test)]
             None,
@@ -391,22 +1184,34 @@ mod tests {
     fn get_file_preview_all_cases() {
         let (_dir, root) = setup_test_environment();
         let dir_path = root.join("src");
-        let preview = FileHandler::get_file_preview(&dir_path, 10).unwrap();
-        assert_eq!(preview, "[DIRECTORY]");
+        let preview =
+            FileHandler::get_file_preview(&dir_path, 10, &ExtensionOverrides::default()).unwrap();
+        assert_eq!(preview.content, "[DIRECTORY]");
 
         let binary_path = root.join("assets/logo.png");
-        let preview = FileHandler::get_file_preview(&binary_path, 10).unwrap();
-        assert_eq!(preview, "[BINARY FILE]");
+        let preview =
+            FileHandler::get_file_preview(&binary_path, 10, &ExtensionOverrides::default())
+                .unwrap();
+        assert_eq!(preview.content, "[BINARY FILE]");
 
         let long_file_path = root.join("docs/large_file.txt");
-        let preview = FileHandler::get_file_preview(&long_file_path, 5).unwrap();
-        assert!(preview.starts_with("Line 1\nLine 2\nLine 3\nLine 4\nLine 5\n"));
-        assert!(preview.ends_with("...\n[Preview truncated]"));
+        let preview =
+            FileHandler::get_file_preview(&long_file_path, 5, &ExtensionOverrides::default())
+                .unwrap();
+        assert!(preview
+            .content
+            .starts_with("Line 1\nLine 2\nLine 3\nLine 4\nLine 5\n"));
+        assert!(preview.content.ends_with("...\n[Preview truncated]"));
+        let total_lines = fs::read_to_string(&long_file_path).unwrap().lines().count();
+        assert_eq!(preview.total_lines, Some(total_lines));
 
         let short_file_path = root.join("src/main.rs");
-        let preview = FileHandler::get_file_preview(&short_file_path, 10).unwrap();
+        let preview =
+            FileHandler::get_file_preview(&short_file_path, 10, &ExtensionOverrides::default())
+                .unwrap();
         let expected_content = "fn main() {\n    println!(\"Hello, world!\");\n}\n";
-        assert_eq!(preview, expected_content);
+        assert_eq!(preview.content, expected_content);
+        assert_eq!(preview.total_lines, None);
     }
 
     #[tokio::test]
@@ -427,6 +1232,7 @@ mod tests {
             vec![],
             HashSet::new(),
             true,
+            ContentTransform::default(),
             Arc::new(AtomicBool::new(false)),
             #[cfg(test)]
             None,
@@ -467,6 +1273,7 @@ mod tests {
             vec![], // no items for tree either
             HashSet::new(),
             true,
+            ContentTransform::default(),
             Arc::new(AtomicBool::new(false)),
             #[cfg(test)]
             None,
@@ -507,6 +1314,7 @@ mod tests {
             vec![],
             HashSet::new(),
             true,
+            ContentTransform::default(),
             Arc::new(AtomicBool::new(false)),
             #[cfg(test)]
             None,
@@ -555,6 +1363,7 @@ mod tests {
             all_items,
             HashSet::new(),
             true,
+            ContentTransform::default(),
             Arc::new(AtomicBool::new(false)),
             #[cfg(test)]
             None,
@@ -574,6 +1383,592 @@ mod tests {
         });
     }
 
+    #[tokio::test]
+    async fn large_files_are_summarized_to_head_and_tail() {
+        let (_dir, root) = setup_test_environment();
+        let schema_path = root.join("schema.txt");
+        let schema: String = (1..=10_000).map(|i| format!("line {i}\n")).collect();
+        fs::write(&schema_path, schema).unwrap();
+
+        let content = FileHandler::generate_concatenated_content_simple(
+            &[schema_path],
+            &root,
+            false,
+            vec![],
+            HashSet::new(),
+            true,
+            ContentTransform {
+                summarize: Some(LineSummary {
+                    over_lines: 1_000,
+                    head_lines: 3,
+                    tail_lines: 2,
+                }),
+                ..ContentTransform::default()
+            },
+            Arc::new(AtomicBool::new(false)),
+            #[cfg(test)]
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert!(content.contains(
+            "===FILE-START===\nline 1\nline 2\nline 3\n[... 9995 lines omitted ...]\nline 9999\nline 10000\n---FILE-END-----"
+        ));
+        assert!(!content.contains("line 4\n"));
+    }
+
+    #[tokio::test]
+    async fn crlf_files_are_normalized_to_lf() {
+        let (_dir, root) = setup_test_environment();
+        let windows_path = root.join("windows.txt");
+        fs::write(&windows_path, "first\r\nsecond\r\n").unwrap();
+
+        let content = FileHandler::generate_concatenated_content_simple(
+            &[windows_path, root.join("README.md")],
+            &root,
+            false,
+            vec![],
+            HashSet::new(),
+            true,
+            ContentTransform {
+                line_endings: LineEndings::Lf,
+                ..ContentTransform::default()
+            },
+            Arc::new(AtomicBool::new(false)),
+            #[cfg(test)]
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert!(content.contains("===FILE-START===\nfirst\nsecond\n---FILE-END-----"));
+        assert!(!content.contains('\r'));
+    }
+
+    #[tokio::test]
+    async fn numbered_lines_are_padded_and_restart_in_every_file() {
+        let (_dir, root) = setup_test_environment();
+        let long_path = root.join("long.txt");
+        fs::write(&long_path, "a\n\nc\nd\ne\nf\ng\nh\ni\nj").unwrap();
+        let short_path = root.join("short.txt");
+        fs::write(&short_path, "first\nsecond\n").unwrap();
+
+        let content = FileHandler::generate_concatenated_content_simple(
+            &[long_path, short_path],
+            &root,
+            false,
+            vec![],
+            HashSet::new(),
+            true,
+            ContentTransform {
+                number_lines: true,
+                ..ContentTransform::default()
+            },
+            Arc::new(AtomicBool::new(false)),
+            #[cfg(test)]
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert!(content.contains("===FILE-START===\n 1 | a\n 2 |\n 3 | c\n"));
+        assert!(content.contains(" 9 | i\n10 | j\n---FILE-END-----"));
+        assert!(content.contains("===FILE-START===\n1 | first\n2 | second\n---FILE-END-----"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn file_mode_is_shown_in_the_file_header() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (_dir, root) = setup_test_environment();
+        let script_path = root.join("run.sh");
+        fs::write(&script_path, "#!/bin/sh\necho hi\n").unwrap();
+        fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755)).unwrap();
+        let generate = |include_file_mode| {
+            FileHandler::generate_concatenated_content_simple(
+                std::slice::from_ref(&script_path),
+                &root,
+                false,
+                vec![],
+                HashSet::new(),
+                true,
+                ContentTransform {
+                    include_file_mode,
+                    ..ContentTransform::default()
+                },
+                Arc::new(AtomicBool::new(false)),
+                #[cfg(test)]
+                None,
+            )
+        };
+
+        let content = generate(true).await.unwrap();
+        assert!(content.contains("run.sh [mode: 100755]\n===FILE-START===\n#!/bin/sh\n"));
+        let content = generate(false).await.unwrap();
+        assert!(content.contains("run.sh\n===FILE-START===\n"));
+    }
+
+    #[tokio::test]
+    async fn checksum_is_shown_in_the_file_header() {
+        let (_dir, root) = setup_test_environment();
+        let file_path = root.join("abc.txt");
+        fs::write(&file_path, "abc").unwrap();
+        let generate = |include_checksum| {
+            FileHandler::generate_concatenated_content_simple(
+                std::slice::from_ref(&file_path),
+                &root,
+                false,
+                vec![],
+                HashSet::new(),
+                true,
+                ContentTransform {
+                    include_checksum,
+                    ..ContentTransform::default()
+                },
+                Arc::new(AtomicBool::new(false)),
+                #[cfg(test)]
+                None,
+            )
+        };
+
+        let sha256 = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let content = generate(true).await.unwrap();
+        assert!(content.contains(&format!(
+            "abc.txt\nsha256: {sha256}\n===FILE-START===\nabc\n"
+        )));
+        assert_eq!(
+            FileHandler::file_checksum(&file_path).as_deref(),
+            Some(sha256)
+        );
+        let content = generate(false).await.unwrap();
+        assert!(content.contains("abc.txt\n===FILE-START===\n"));
+        assert!(!content.contains("sha256"));
+    }
+
+    #[tokio::test]
+    async fn redaction_masks_secrets_in_the_generated_output() {
+        let (_dir, root) = setup_test_environment();
+        let env_path = root.join(".env");
+        fs::write(
+            &env_path,
+            "DEBUG=true\nAWS_SECRET_ACCESS_KEY=wJalrXUtnFEMI/K7MDENG\n",
+        )
+        .unwrap();
+        let patterns = crate::core::redaction::default_redaction_patterns();
+
+        let content = FileHandler::generate_concatenated_content_simple(
+            &[env_path],
+            &root,
+            false,
+            vec![],
+            HashSet::new(),
+            true,
+            ContentTransform {
+                redaction: Some(Redaction::compile(&patterns)),
+                ..ContentTransform::default()
+            },
+            Arc::new(AtomicBool::new(false)),
+            #[cfg(test)]
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert!(content.contains(
+            "===FILE-START===\nDEBUG=true\nAWS_SECRET_ACCESS_KEY=[REDACTED]\n---FILE-END-----"
+        ));
+        assert!(!content.contains("wJalrXUtnFEMI"));
+    }
+
+    #[tokio::test]
+    async fn html_output_escapes_content_and_closes_document() {
+        let (_dir, root) = setup_test_environment();
+        let source_path = root.join("markup.rs");
+        fs::write(&source_path, "fn f() -> Vec<u8> { a && b }").unwrap();
+
+        let output = FileHandler::generate_concatenated_output(
+            &[source_path.clone()],
+            &root,
+            TreePosition::Top,
+            create_file_items(&root, &["markup.rs"]),
+            HashSet::new(),
+            TreeStyle::Unicode,
+            true,
+            ContentTransform::default(),
+            OutputFormat::Html,
+            false,
+            Arc::new(AtomicBool::new(false)),
+            #[cfg(test)]
+            None,
+        )
+        .await
+        .unwrap();
+        let content = output.content;
+
+        assert!(content.starts_with("<!DOCTYPE html>"));
+        assert!(content.contains("<details>\n<summary>Directory tree</summary>"));
+        assert!(content.contains("<h2>markup.rs</h2>"));
+        assert!(content.contains("<code class=\"language-rust\">"));
+        assert!(content.contains("fn f() -&gt; Vec&lt;u8&gt; { a &amp;&amp; b }"));
+        assert!(!content.contains("===FILE-START==="));
+        assert!(content.ends_with("</body>\n</html>\n"));
+    }
+
+    #[tokio::test]
+    async fn xml_output_wraps_files_in_cdata_and_splits_terminators() {
+        let (_dir, root) = setup_test_environment();
+        let source_path = root.join("tricky.rs");
+        fs::write(&source_path, "let s = \"]]>\"; // <tag> & more").unwrap();
+
+        let output = FileHandler::generate_concatenated_output(
+            &[source_path.clone()],
+            &root,
+            TreePosition::Top,
+            create_file_items(&root, &["tricky.rs"]),
+            HashSet::new(),
+            TreeStyle::Unicode,
+            true,
+            ContentTransform::default(),
+            OutputFormat::Xml,
+            false,
+            Arc::new(AtomicBool::new(false)),
+            #[cfg(test)]
+            None,
+        )
+        .await
+        .unwrap();
+        let content = output.content;
+
+        assert!(content.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<context>\n"));
+        assert!(content.contains("<tree><![CDATA[\n"));
+        assert!(content.contains("language=\"rust\"><![CDATA["));
+        assert!(content.contains("let s = \"]]]]><![CDATA[>\"; // <tag> & more]]></file>\n"));
+        assert!(content.ends_with("</context>\n"));
+    }
+
+    #[tokio::test]
+    async fn bottom_tree_position_places_the_tree_after_the_last_file() {
+        let (_dir, root) = setup_test_environment();
+        let source_path = root.join("markup.rs");
+        fs::write(&source_path, "fn f() {}\n").unwrap();
+
+        let output = FileHandler::generate_concatenated_output(
+            &[source_path.clone()],
+            &root,
+            TreePosition::Bottom,
+            create_file_items(&root, &["markup.rs"]),
+            HashSet::new(),
+            TreeStyle::Ascii,
+            true,
+            ContentTransform::default(),
+            OutputFormat::PlainText,
+            false,
+            Arc::new(AtomicBool::new(false)),
+            #[cfg(test)]
+            None,
+        )
+        .await
+        .unwrap();
+        let content = output.content;
+
+        let tree_start = content.find("# DIRECTORY TREE").unwrap();
+        assert!(tree_start > content.find("---FILE-END-----").unwrap());
+        assert!(content[tree_start..].contains("markup.rs"));
+        assert_eq!(content.matches("# DIRECTORY TREE").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn index_lists_files_in_body_order_with_their_sizes() {
+        let (_dir, root) = setup_test_environment();
+        // Not in tree order, so the index has to follow the concatenation order.
+        let selected = vec![root.join("src/main.rs"), root.join("README.md")];
+        let output = FileHandler::generate_concatenated_output(
+            &selected,
+            &root,
+            TreePosition::Top,
+            create_file_items(&root, &["src/main.rs", "README.md"]),
+            HashSet::new(),
+            TreeStyle::Unicode,
+            false,
+            ContentTransform::default(),
+            OutputFormat::PlainText,
+            false,
+            Arc::new(AtomicBool::new(false)),
+            #[cfg(test)]
+            None,
+        )
+        .await
+        .unwrap();
+
+        let index = FileHandler::index(&output, &root, false, &[7, 5], OutputFormat::PlainText);
+        let main = root.join("src/main.rs").display().to_string();
+        let readme = root.join("README.md").display().to_string();
+        assert_eq!(
+            index,
+            format!(
+                "# INDEX\n\
+                 #   1. {main} (44 bytes, 3 lines, 7 tokens)\n\
+                 #   2. {readme} (24 bytes, 1 lines, 5 tokens)\n\n"
+            )
+        );
+        let body = &output.content[output.body.clone()];
+        assert!(body.find(&main).unwrap() < body.find(&readme).unwrap());
+    }
+
+    #[tokio::test]
+    async fn spilled_output_is_identical_to_the_in_memory_output() {
+        let (_dir, root) = setup_test_environment();
+        let paths = ["README.md", "src/main.rs", "src/lib.rs", "assets/data.bin"];
+        let selected: Vec<PathBuf> = paths.iter().map(|path| root.join(path)).collect();
+        // The timestamp may tick between the two runs.
+        let without_timestamp = |content: &str| -> Vec<String> {
+            content
+                .lines()
+                .filter(|line| !line.to_lowercase().contains("generated"))
+                .map(str::to_string)
+                .collect()
+        };
+
+        for format in [
+            OutputFormat::PlainText,
+            OutputFormat::Html,
+            OutputFormat::Xml,
+        ] {
+            for tree_position in [TreePosition::Top, TreePosition::Bottom] {
+                let in_memory = FileHandler::generate_concatenated_output(
+                    &selected,
+                    &root,
+                    tree_position,
+                    create_file_items(&root, &paths),
+                    HashSet::new(),
+                    TreeStyle::Unicode,
+                    true,
+                    ContentTransform::default(),
+                    format,
+                    false,
+                    Arc::new(AtomicBool::new(false)),
+                    #[cfg(test)]
+                    None,
+                )
+                .await
+                .unwrap();
+                let spilled = FileHandler::generate_spilled_output(
+                    &selected,
+                    &root,
+                    tree_position,
+                    create_file_items(&root, &paths),
+                    HashSet::new(),
+                    TreeStyle::Unicode,
+                    true,
+                    ContentTransform::default(),
+                    format,
+                    false,
+                    Arc::new(AtomicBool::new(false)),
+                )
+                .await
+                .unwrap();
+
+                assert_eq!(
+                    without_timestamp(&spilled.read_all().unwrap()),
+                    without_timestamp(&in_memory.content)
+                );
+                assert_eq!(spilled.file_ranges, in_memory.file_ranges);
+                assert_eq!(spilled.body, in_memory.body);
+                assert_eq!(spilled.length, in_memory.content.len());
+                let mut reader = spilled.reader().unwrap();
+                reader.read_to(spilled.body.start).unwrap();
+                assert_eq!(
+                    reader.read_to(spilled.body.end).unwrap(),
+                    in_memory.content[in_memory.body.clone()]
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn insertions_into_a_spilled_output_shift_its_ranges() {
+        let (_dir, root) = setup_test_environment();
+        let selected = vec![root.join("README.md"), root.join("src/main.rs")];
+        let spilled = FileHandler::generate_spilled_output(
+            &selected,
+            &root,
+            TreePosition::Top,
+            create_file_items(&root, &["README.md", "src", "src/main.rs"]),
+            HashSet::new(),
+            TreeStyle::Unicode,
+            true,
+            ContentTransform::default(),
+            OutputFormat::PlainText,
+            false,
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await
+        .unwrap();
+        let original = spilled.read_all().unwrap();
+
+        let inserted = spilled
+            .with_insertions(&[(0, "MANIFEST\n"), (spilled.body.start, "INDEX\n")])
+            .unwrap();
+
+        let mut expected = original.clone();
+        expected.insert_str(spilled.body.start, "INDEX\n");
+        expected.insert_str(0, "MANIFEST\n");
+        let content = inserted.read_all().unwrap();
+        assert_eq!(content, expected);
+        assert_eq!(inserted.length, expected.len());
+        for ((path, range), (original_path, original_range)) in
+            inserted.file_ranges.iter().zip(&spilled.file_ranges)
+        {
+            assert_eq!(path, original_path);
+            assert_eq!(content[range.clone()], original[original_range.clone()]);
+        }
+    }
+
+    #[tokio::test]
+    async fn reframed_output_matches_a_fresh_generation_with_the_new_tree_position() {
+        let (_dir, root) = setup_test_environment();
+        let selected_files = vec![root.join("README.md"), root.join("src/main.rs")];
+        let items = create_file_items(&root, &["README.md", "src", "src/main.rs"]);
+        let generate = |tree_position| {
+            FileHandler::generate_concatenated_output(
+                &selected_files,
+                &root,
+                tree_position,
+                items.clone(),
+                HashSet::new(),
+                TreeStyle::Unicode,
+                true,
+                ContentTransform::default(),
+                OutputFormat::Html,
+                false,
+                Arc::new(AtomicBool::new(false)),
+                #[cfg(test)]
+                None,
+            )
+        };
+        let without_tree = generate(TreePosition::None).await.unwrap();
+        let with_tree = generate(TreePosition::Top).await.unwrap();
+
+        let reframed = FileHandler::reframe_output(
+            &without_tree,
+            selected_files.len(),
+            TreePosition::Top,
+            &items,
+            &root,
+            &HashSet::new(),
+            TreeStyle::Unicode,
+            OutputFormat::Html,
+        );
+
+        // The timestamp in the header may differ by a second; compare what follows it.
+        let after_header =
+            |content: &str| content[content.find("</header>").unwrap()..].to_string();
+        assert_eq!(
+            after_header(&reframed.content),
+            after_header(&with_tree.content)
+        );
+        for ((path, range), (_, expected)) in
+            reframed.file_ranges.iter().zip(&with_tree.file_ranges)
+        {
+            assert_eq!(
+                &reframed.content[range.clone()],
+                &with_tree.content[expected.clone()],
+                "{}",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn split_output_numbers_parts_and_keeps_file_blocks_whole() {
+        let root = PathBuf::from("/work/repo");
+        let content = "HEAD\na: aaaa\nb: bbbb\nc: cccc\nEND\n".to_string();
+        let output = ConcatenatedOutput {
+            file_ranges: vec![
+                (root.join("a.txt"), 5..13),
+                (root.join("src/b.txt"), 13..21),
+                (root.join("c.txt"), 21..29),
+            ],
+            body: 5..29,
+            content,
+        };
+
+        let parts = FileHandler::split_output(
+            &output,
+            21,
+            "// Part {part} of {total} — continues {root} ({files})",
+            &root,
+        );
+        assert_eq!(
+            parts,
+            vec![
+                "// Part 1 of 2 — continues repo (a.txt, src/b.txt)\n\nHEAD\na: aaaa\nb: bbbb\n",
+                "// Part 2 of 2 — continues repo (c.txt)\n\nc: cccc\nEND\n",
+            ]
+        );
+
+        let unannotated = FileHandler::split_output(&output, 10, "", &root);
+        assert_eq!(unannotated.len(), 3);
+        assert_eq!(unannotated.concat(), output.content);
+    }
+
+    #[tokio::test]
+    async fn unreadable_file_is_noted_or_aborts_depending_on_skip_setting() {
+        let (_dir, root) = setup_test_environment();
+        // A file that vanished after it was selected fails to read like a broken share.
+        let selected_files = vec![root.join("gone.rs"), root.join("README.md")];
+
+        let generate = |skip_unreadable| {
+            FileHandler::generate_concatenated_output(
+                &selected_files,
+                &root,
+                TreePosition::None,
+                vec![],
+                HashSet::new(),
+                TreeStyle::Unicode,
+                true,
+                ContentTransform::default(),
+                OutputFormat::PlainText,
+                skip_unreadable,
+                Arc::new(AtomicBool::new(false)),
+                #[cfg(test)]
+                None,
+            )
+        };
+
+        let content = generate(true).await.unwrap().content;
+        assert!(content.contains("gone.rs\n===FILE-START===\n[error reading file: "));
+        assert!(content.contains("This is the main readme."));
+
+        assert!(
+            matches!(generate(false).await, Err(CoreError::Io(_, path)) if path.ends_with("gone.rs"))
+        );
+    }
+
+    #[test]
+    fn transient_read_errors_are_retried() {
+        let mut calls = 0;
+        let result = with_retry(|| {
+            calls += 1;
+            if calls < READ_ATTEMPTS {
+                Err(io::Error::from(io::ErrorKind::TimedOut))
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), READ_ATTEMPTS);
+
+        let mut calls = 0;
+        let result: io::Result<()> = with_retry(|| {
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
     #[tokio::test]
     async fn content_reading_handles_exact_size_boundary() {
         let (_dir, root) = setup_test_environment();
@@ -603,6 +1998,7 @@ mod tests {
             vec![],
             HashSet::new(),
             true,
+            ContentTransform::default(),
             Arc::new(AtomicBool::new(false)),
             #[cfg(test)]
             None,
@@ -637,6 +2033,7 @@ mod tests {
             vec![],
             HashSet::new(),
             true,
+            ContentTransform::default(),
             Arc::new(AtomicBool::new(false)),
             #[cfg(test)]
             None,
@@ -673,6 +2070,7 @@ mod tests {
             vec![],
             HashSet::new(),
             true,
+            ContentTransform::default(),
             Arc::new(AtomicBool::new(false)),
             #[cfg(test)]
             None,
@@ -712,6 +2110,7 @@ mod tests {
                 vec![],
                 HashSet::new(),
                 true,
+                ContentTransform::default(),
                 task_cancel_flag,
                 #[cfg(test)]
                 Some(tx),
@@ -745,9 +2144,11 @@ mod tests {
             b"This is a valid line.\nAnd this one is not -> \x99 so good.".to_vec();
         fs::write(&corrupted_file_path, content).unwrap();
 
-        let preview = FileHandler::get_file_preview(&corrupted_file_path, 10).unwrap();
+        let preview =
+            FileHandler::get_file_preview(&corrupted_file_path, 10, &ExtensionOverrides::default())
+                .unwrap();
 
         // The snapshot will show that the second line failed to read.
-        insta::assert_snapshot!(preview);
+        insta::assert_snapshot!(preview.content);
     }
 }
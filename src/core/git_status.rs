@@ -0,0 +1,170 @@
+//! Reads the working tree status and the tracked files from git, for badges in the
+//! file tree and the `only_git_tracked` filter.
+
+use super::FileItem;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// How a file differs from the last commit, as reported by `git status`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum GitStatus {
+    Modified,
+    Added,
+    Deleted,
+    Renamed,
+    Untracked,
+    /// Unmerged, i.e. with conflicts left to resolve.
+    Conflicted,
+}
+
+impl GitStatus {
+    /// Classifies the two-letter `XY` status code of a porcelain v1 entry. Returns
+    /// `None` for ignored entries.
+    fn from_porcelain(code: &[u8]) -> Option<Self> {
+        let (index, worktree) = match code {
+            [x, y] => (*x, *y),
+            _ => return None,
+        };
+        let status = match (index, worktree) {
+            (b'!', b'!') => return None,
+            (b'?', b'?') => GitStatus::Untracked,
+            (b'U', _) | (_, b'U') | (b'A', b'A') | (b'D', b'D') => GitStatus::Conflicted,
+            (b'R', _) | (_, b'R') => GitStatus::Renamed,
+            (b'A', _) | (b'C', _) => GitStatus::Added,
+            (b'D', _) | (_, b'D') => GitStatus::Deleted,
+            _ => GitStatus::Modified,
+        };
+        Some(status)
+    }
+}
+
+/// Runs `git status` once for the files below `root` and returns the status of every
+/// changed file by absolute path. Returns an empty map if `root` is not inside a git
+/// work tree or git cannot be run.
+pub fn read_git_statuses(root: &Path) -> HashMap<PathBuf, GitStatus> {
+    // Paths in the status output are relative to the top level of the work tree;
+    // the prefix maps them below `root` without canonicalizing either path.
+    let Some(prefix) = run_git(root, &["rev-parse", "--show-prefix"]) else {
+        return HashMap::new();
+    };
+    let prefix = String::from_utf8_lossy(&prefix).trim().to_string();
+    let Some(output) = run_git(
+        root,
+        &[
+            "status",
+            "--porcelain=v1",
+            "-z",
+            "--untracked-files=all",
+            "--",
+            ".",
+        ],
+    ) else {
+        return HashMap::new();
+    };
+    parse_porcelain(&output, &prefix, root)
+}
+
+/// Lists the files git tracks below `root`, together with their parent directories
+/// up to `root`, as absolute paths. Returns `None` if `root` is not inside a git work
+/// tree or git cannot be run.
+pub fn read_tracked_paths(root: &Path) -> Option<HashSet<PathBuf>> {
+    // Unlike `git status`, `ls-files` prints paths relative to the directory it runs in.
+    let output = run_git(root, &["ls-files", "-z", "--cached"])?;
+    let mut tracked = HashSet::new();
+    for entry in output.split(|&b| b == 0).filter(|entry| !entry.is_empty()) {
+        let path = root.join(&*String::from_utf8_lossy(entry));
+        let mut current = path.parent();
+        while let Some(parent) = current {
+            if parent == root || !tracked.insert(parent.to_path_buf()) {
+                break;
+            }
+            current = parent.parent();
+        }
+        tracked.insert(path);
+    }
+    Some(tracked)
+}
+
+/// Parses `git status --porcelain=v1 -z` output. Entries are relative to the top level
+/// of the work tree; those below `prefix` are returned joined onto `root`.
+pub fn parse_porcelain(output: &[u8], prefix: &str, root: &Path) -> HashMap<PathBuf, GitStatus> {
+    let mut statuses = HashMap::new();
+    let mut entries = output.split(|&b| b == 0).filter(|entry| !entry.is_empty());
+    while let Some(entry) = entries.next() {
+        if entry.len() < 4 {
+            continue;
+        }
+        let (code, path) = (&entry[..2], &entry[3..]);
+        // Renames and copies are followed by the original path, which is skipped.
+        if matches!(code[0], b'R' | b'C') {
+            entries.next();
+        }
+        let Some(status) = GitStatus::from_porcelain(code) else {
+            continue;
+        };
+        let path = String::from_utf8_lossy(path);
+        if let Some(relative) = path.strip_prefix(prefix) {
+            statuses.insert(root.join(relative), status);
+        }
+    }
+    statuses
+}
+
+/// Sets the `git_status` of every item from `statuses`, clearing it for the others.
+pub fn annotate(items: &mut [FileItem], statuses: &HashMap<PathBuf, GitStatus>) {
+    for item in items {
+        item.git_status = statuses.get(&item.path).copied();
+    }
+}
+
+/// Runs git in `dir` and returns its standard output, or `None` if it failed.
+fn run_git(dir: &Path, args: &[&str]) -> Option<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        tracing::debug!(
+            "git {} failed in {}: {}",
+            args.join(" "),
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return None;
+    }
+    Some(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn porcelain_entries_are_classified_and_mapped_below_the_root() {
+        let root = Path::new("/work/app");
+        let output = b" M app/src/main.rs\0?? app/notes.txt\0A  app/new.rs\0\
+R  app/renamed.rs\0app/old.rs\0UU app/conflict.rs\0 M other/outside.rs\0!! app/ignored.log\0";
+
+        let statuses = parse_porcelain(output, "app/", root);
+
+        assert_eq!(statuses[&root.join("src/main.rs")], GitStatus::Modified);
+        assert_eq!(statuses[&root.join("notes.txt")], GitStatus::Untracked);
+        assert_eq!(statuses[&root.join("new.rs")], GitStatus::Added);
+        assert_eq!(statuses[&root.join("renamed.rs")], GitStatus::Renamed);
+        assert_eq!(statuses[&root.join("conflict.rs")], GitStatus::Conflicted);
+        assert!(!statuses.contains_key(&root.join("old.rs")));
+        assert!(!statuses.contains_key(&root.join("ignored.log")));
+        assert_eq!(statuses.len(), 5);
+    }
+
+    #[test]
+    fn a_directory_outside_a_work_tree_has_no_statuses() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(read_git_statuses(dir.path()).is_empty());
+        assert!(read_tracked_paths(dir.path()).is_none());
+    }
+}
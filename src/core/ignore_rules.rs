@@ -0,0 +1,425 @@
+//! Gitignore-style ignore rules with support for `!`-prefixed allow-list overrides.
+
+use crate::utils::unicode::{nfc, nfc_path};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{Match, WalkBuilder};
+use std::path::{Path, PathBuf};
+
+/// A matcher for the user's ignore patterns.
+///
+/// Plain patterns exclude matching entries. Patterns starting with `!` re-include
+/// entries that a plain pattern would exclude, e.g. `*.log` together with `!keep.log`
+/// ignores every log file except `keep.log`. Because the patterns are stored in a set,
+/// allow rules always take precedence regardless of their order, which matches git
+/// whenever the negation is listed after the broader rule. As in git, an entry cannot
+/// be re-included if one of its parent directories is excluded.
+///
+/// Patterns and paths are compared in Unicode NFC, so a pattern typed with composed
+/// accents matches the decomposed names macOS reports.
+#[derive(Debug)]
+pub struct IgnoreRules {
+    ignore: Gitignore,
+    allow: Gitignore,
+}
+
+impl IgnoreRules {
+    /// Builds the rules for `patterns`, interpreted relative to `root`. Invalid
+    /// patterns are skipped.
+    pub fn new<'a>(root: &Path, patterns: impl IntoIterator<Item = &'a String>) -> Self {
+        let root = nfc_path(root);
+        let mut ignore = GitignoreBuilder::new(&root);
+        let mut allow = GitignoreBuilder::new(&root);
+        for pattern in patterns {
+            let pattern = nfc(pattern);
+            match negated_pattern(&pattern) {
+                Some(allowed) => allow.add_line(None, allowed).ok(),
+                None => ignore.add_line(None, &pattern).ok(),
+            };
+        }
+        Self {
+            ignore: ignore.build().unwrap_or_else(|_| Gitignore::empty()),
+            allow: allow.build().unwrap_or_else(|_| Gitignore::empty()),
+        }
+    }
+
+    /// Returns `true` if `path` itself is excluded by a plain pattern and not
+    /// re-included by an allow rule. Parent directories are not considered.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let path = nfc_path(path);
+        self.ignore.matched(&path, is_dir).is_ignore()
+            && !self.allow.matched(&path, is_dir).is_ignore()
+    }
+
+    /// Like `is_ignored`, but also returns `true` if any parent directory of `path`
+    /// below the root is ignored.
+    pub fn is_ignored_or_any_parents(&self, path: &Path, is_dir: bool) -> bool {
+        let root = self.ignore.path();
+        let path = nfc_path(path);
+        if self.is_ignored(&path, is_dir) {
+            return true;
+        }
+        path.ancestors()
+            .skip(1)
+            .take_while(|ancestor| ancestor.starts_with(root) && *ancestor != root)
+            .any(|ancestor| self.is_ignored(ancestor, true))
+    }
+
+    /// Returns the pattern that excludes `path` or, failing that, one of its parent
+    /// directories below the root. `None` if neither is ignored.
+    pub fn excluding_pattern(&self, path: &Path, is_dir: bool) -> Option<String> {
+        let root = self.ignore.path();
+        let path = nfc_path(path);
+        std::iter::once((path.as_ref(), is_dir))
+            .chain(
+                path.ancestors()
+                    .skip(1)
+                    .take_while(|ancestor| ancestor.starts_with(root) && *ancestor != root)
+                    .map(|ancestor| (ancestor, true)),
+            )
+            .find_map(|(candidate, is_dir)| {
+                if self.allow.matched(candidate, is_dir).is_ignore() {
+                    return None;
+                }
+                match self.ignore.matched(candidate, is_dir) {
+                    Match::Ignore(glob) => Some(glob.original().to_string()),
+                    _ => None,
+                }
+            })
+    }
+}
+
+/// The ignore files the directory walk reads in every directory, from lowest to
+/// highest precedence.
+const IGNORE_FILE_NAMES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// Finds the rule in an ignore file below `root` (`.gitignore`, `.ignore` or
+/// `.git/info/exclude`) that makes the directory walk skip `path` or one of its
+/// parent directories. Returns the ignore file and the rule as written. Global git
+/// excludes are not consulted.
+pub fn ignore_file_rule(root: &Path, path: &Path, is_dir: bool) -> Option<(PathBuf, String)> {
+    let relative = path.strip_prefix(root).ok()?;
+    let components: Vec<_> = relative.components().collect();
+
+    let mut matchers = Vec::new();
+    let exclude = root.join(".git/info/exclude");
+    if exclude.is_file() {
+        let mut builder = GitignoreBuilder::new(root);
+        builder.add(&exclude);
+        matchers.extend(builder.build().ok());
+    }
+
+    // Entries are checked from the top down, as the walk skips the whole subtree of
+    // an ignored directory. Rules in deeper ignore files take precedence.
+    let mut current = root.to_path_buf();
+    for (index, component) in components.iter().enumerate() {
+        for name in IGNORE_FILE_NAMES {
+            let file = current.join(name);
+            if file.is_file() {
+                matchers.push(Gitignore::new(&file).0);
+            }
+        }
+        current.push(component);
+        let current_is_dir = is_dir || index + 1 < components.len();
+        let decision = matchers
+            .iter()
+            .rev()
+            .map(|matcher| matcher.matched(&current, current_is_dir))
+            .find(|decision| !decision.is_none());
+        if let Some(Match::Ignore(glob)) = decision {
+            let file = glob.from().map(Path::to_path_buf).unwrap_or_default();
+            return Some((file, glob.original().to_string()));
+        }
+    }
+    None
+}
+
+/// The paths `git archive` leaves out because a `.gitattributes` file gives them the
+/// `export-ignore` attribute.
+///
+/// This approximates git's behavior: each pattern is read as a gitignore pattern
+/// relative to the directory of its `.gitattributes` file, which matches git for the
+/// usual top-level patterns such as `tests`, `/docs` or `*.md`. Later lines and deeper
+/// files win, and `-export-ignore` or `!export-ignore` re-include a path. Quoted
+/// patterns and attribute macros are not supported.
+#[derive(Debug)]
+pub struct ExportIgnore {
+    root: PathBuf,
+    /// One matcher per attributes file, from lowest to highest precedence.
+    matchers: Vec<Gitignore>,
+}
+
+impl ExportIgnore {
+    /// Reads `.git/info/attributes` and every `.gitattributes` file below `root` that
+    /// the directory walk would visit. Without any, nothing is excluded.
+    pub fn load(root: &Path) -> Self {
+        let mut files: Vec<PathBuf> = WalkBuilder::new(root)
+            .hidden(false)
+            .require_git(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build()
+            .filter_map(Result::ok)
+            .filter(|entry| {
+                entry.file_name() == ".gitattributes"
+                    && entry.file_type().is_some_and(|ft| ft.is_file())
+            })
+            .map(|entry| entry.into_path())
+            .collect();
+        files.sort_by_key(|file| file.components().count());
+
+        let mut matchers: Vec<Gitignore> = files
+            .iter()
+            .filter_map(|file| {
+                let contents = std::fs::read_to_string(file).ok()?;
+                Some(export_ignore_matcher(file.parent()?, &contents))
+            })
+            .collect();
+        if let Ok(contents) = std::fs::read_to_string(root.join(".git/info/attributes")) {
+            matchers.push(export_ignore_matcher(root, &contents));
+        }
+        Self {
+            root: root.to_path_buf(),
+            matchers,
+        }
+    }
+
+    /// Returns `true` if `path` or one of its parent directories below the root has
+    /// the `export-ignore` attribute.
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        if self.matchers.is_empty() {
+            return false;
+        }
+        std::iter::once((path, is_dir))
+            .chain(
+                path.ancestors()
+                    .skip(1)
+                    .take_while(|ancestor| {
+                        ancestor.starts_with(&self.root) && *ancestor != self.root
+                    })
+                    .map(|ancestor| (ancestor, true)),
+            )
+            .any(|(candidate, is_dir)| {
+                self.matchers
+                    .iter()
+                    .rev()
+                    .filter(|matcher| candidate.starts_with(matcher.path()))
+                    .map(|matcher| matcher.matched(candidate, is_dir))
+                    .find(|decision| !decision.is_none())
+                    .is_some_and(|decision| decision.is_ignore())
+            })
+    }
+}
+
+/// Builds a matcher from the contents of an attributes file in `dir`. Patterns that
+/// set `export-ignore` become ignore rules, patterns that unset it allow rules.
+fn export_ignore_matcher(dir: &Path, contents: &str) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(dir);
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(pattern) = fields.next() else {
+            continue;
+        };
+        if pattern.starts_with(['#', '"', '!']) || pattern.starts_with("[attr]") {
+            continue;
+        }
+        let export_ignore = fields.fold(None, |current, attribute| match attribute {
+            "export-ignore" => Some(true),
+            "-export-ignore" | "!export-ignore" => Some(false),
+            _ => current,
+        });
+        match export_ignore {
+            Some(true) => builder.add_line(None, pattern).ok(),
+            Some(false) => builder.add_line(None, &format!("!{pattern}")).ok(),
+            None => None,
+        };
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+/// Named ignore presets for common project types, as `(name, patterns)`.
+pub const IGNORE_PRESETS: &[(&str, &[&str])] = &[
+    ("rust", &["target/", "Cargo.lock", "*.rs.bk", "*.pdb"]),
+    (
+        "node",
+        &[
+            "node_modules/",
+            "dist/",
+            "build/",
+            "coverage/",
+            ".next/",
+            "package-lock.json",
+            "yarn.lock",
+            "pnpm-lock.yaml",
+            "*.log",
+        ],
+    ),
+    (
+        "python",
+        &[
+            "__pycache__/",
+            "*.pyc",
+            "*.pyo",
+            ".venv/",
+            "venv/",
+            ".pytest_cache/",
+            ".mypy_cache/",
+            "*.egg-info/",
+            "dist/",
+            "build/",
+        ],
+    ),
+    (
+        "go",
+        &["vendor/", "bin/", "go.sum", "*.exe", "*.test", "*.out"],
+    ),
+];
+
+/// Lockfiles hidden by `AppConfig::exclude_lockfiles`.
+pub const LOCKFILE_PATTERNS: &[&str] = &["*.lock", "package-lock.json", "Cargo.lock"];
+
+/// Vendored dependency directories hidden by `AppConfig::exclude_vendored`.
+pub const VENDORED_PATTERNS: &[&str] = &["vendor/", "node_modules/", "third_party/"];
+
+/// Returns the patterns of the preset called `name`, ignoring ASCII case.
+pub fn ignore_preset(name: &str) -> Option<&'static [&'static str]> {
+    IGNORE_PRESETS
+        .iter()
+        .find(|(preset, _)| preset.eq_ignore_ascii_case(name.trim()))
+        .map(|(_, patterns)| *patterns)
+}
+
+/// Returns the pattern without its leading `!` if `pattern` is an allow rule.
+/// A `\!` prefix escapes a literal exclamation mark, as in gitignore.
+pub fn negated_pattern(pattern: &str) -> Option<&str> {
+    pattern.strip_prefix('!').filter(|rest| !rest.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+
+    fn rules(patterns: &[&str]) -> (IgnoreRules, PathBuf) {
+        let root = PathBuf::from("/project");
+        let patterns: HashSet<String> = patterns.iter().map(|p| p.to_string()).collect();
+        (IgnoreRules::new(&root, &patterns), root)
+    }
+
+    #[test]
+    fn negation_re_includes_a_file_excluded_by_a_broader_rule() {
+        let (rules, root) = rules(&["*.log", "!keep.log"]);
+
+        assert!(rules.is_ignored(&root.join("debug.log"), false));
+        assert!(rules.is_ignored(&root.join("nested/trace.log"), false));
+        assert!(!rules.is_ignored(&root.join("keep.log"), false));
+        assert!(!rules.is_ignored(&root.join("nested/keep.log"), false));
+        assert!(!rules.is_ignored(&root.join("main.rs"), false));
+    }
+
+    #[test]
+    fn negation_cannot_re_include_a_file_in_an_excluded_directory() {
+        let (rules, root) = rules(&["logs/", "!logs/keep.log"]);
+
+        assert!(rules.is_ignored_or_any_parents(&root.join("logs/keep.log"), false));
+        assert!(rules.is_ignored_or_any_parents(&root.join("logs"), true));
+    }
+
+    #[test]
+    fn the_excluding_pattern_is_reported_for_the_path_or_its_parents() {
+        let (rules, root) = rules(&["*.log", "!keep.log", "build/"]);
+
+        assert_eq!(
+            rules.excluding_pattern(&root.join("debug.log"), false),
+            Some("*.log".to_string())
+        );
+        assert_eq!(
+            rules.excluding_pattern(&root.join("build/out/main.o"), false),
+            Some("build/".to_string())
+        );
+        assert_eq!(rules.excluding_pattern(&root.join("keep.log"), false), None);
+        assert_eq!(rules.excluding_pattern(&root.join("main.rs"), false), None);
+    }
+
+    #[test]
+    fn ignore_file_rules_are_found_in_nested_ignore_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("web/dist")).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.tmp\n").unwrap();
+        std::fs::write(root.join("web/.gitignore"), "dist/\n!keep.tmp\n").unwrap();
+
+        assert_eq!(
+            ignore_file_rule(root, &root.join("notes.tmp"), false),
+            Some((root.join(".gitignore"), "*.tmp".to_string()))
+        );
+        assert_eq!(
+            ignore_file_rule(root, &root.join("web/dist/app.js"), false),
+            Some((root.join("web/.gitignore"), "dist/".to_string()))
+        );
+        assert_eq!(
+            ignore_file_rule(root, &root.join("web/keep.tmp"), false),
+            None
+        );
+        assert_eq!(
+            ignore_file_rule(root, &root.join("web/index.html"), false),
+            None
+        );
+    }
+
+    #[test]
+    fn export_ignore_attributes_exclude_paths_and_deeper_files_win() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("docs/api")).unwrap();
+        std::fs::write(
+            root.join(".gitattributes"),
+            "# archive contents\n*.rs text\ntests export-ignore\n*.md export-ignore\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("docs/.gitattributes"),
+            "README.md -export-ignore\n",
+        )
+        .unwrap();
+
+        let export_ignore = ExportIgnore::load(root);
+
+        assert!(export_ignore.is_excluded(&root.join("tests"), true));
+        assert!(export_ignore.is_excluded(&root.join("tests/unit.rs"), false));
+        assert!(export_ignore.is_excluded(&root.join("docs/api/guide.md"), false));
+        assert!(!export_ignore.is_excluded(&root.join("docs/README.md"), false));
+        assert!(!export_ignore.is_excluded(&root.join("src/main.rs"), false));
+    }
+
+    #[test]
+    fn without_attributes_files_nothing_is_export_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let export_ignore = ExportIgnore::load(dir.path());
+
+        assert!(!export_ignore.is_excluded(&dir.path().join("tests/unit.rs"), false));
+    }
+
+    #[test]
+    fn composed_patterns_match_decomposed_paths() {
+        let (rules, root) = rules(&["caf\u{e9}/", "r\u{e9}sum\u{e9}.txt"]);
+
+        assert!(rules.is_ignored(&root.join("cafe\u{301}"), true));
+        assert!(rules.is_ignored_or_any_parents(&root.join("cafe\u{301}/menu.txt"), false));
+        assert!(rules.is_ignored(&root.join("docs/re\u{301}sume\u{301}.txt"), false));
+    }
+
+    #[test]
+    fn presets_are_found_by_name_regardless_of_case() {
+        assert!(ignore_preset("Rust").unwrap().contains(&"target/"));
+        assert!(ignore_preset("node").unwrap().contains(&"node_modules/"));
+        assert!(ignore_preset("fortran").is_none());
+    }
+
+    #[test]
+    fn negation_alone_ignores_nothing() {
+        let (rules, root) = rules(&["!keep.log"]);
+
+        assert!(!rules.is_ignored(&root.join("keep.log"), false));
+        assert!(!rules.is_ignored(&root.join("other.log"), false));
+    }
+}
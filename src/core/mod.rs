@@ -6,12 +6,21 @@
 
 pub mod error;
 pub mod file_handler;
+pub mod git_status;
+pub mod ignore_rules;
+pub mod output_format;
+pub mod redaction;
+pub mod references;
 pub mod scanner;
 pub mod search;
+pub mod transform;
 pub mod tree_generator;
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
 
 // Re-export CoreError to make it accessible from the app module.
 pub use error::CoreError;
@@ -31,23 +40,64 @@ pub struct FileItem {
     pub depth: usize,
     /// The path of the parent directory, if it exists.
     pub parent: Option<PathBuf>,
+    /// The last modification time, if the platform reports one.
+    pub mtime: Option<SystemTime>,
+    /// `true` if the file looks minified or generated by a tool.
+    pub likely_generated: bool,
+    /// How the file differs from the last git commit, if `AppConfig::show_git_status`
+    /// is set and the file is changed.
+    pub git_status: Option<GitStatus>,
 }
 
 /// Defines the criteria for filtering files.
 #[derive(Debug, Clone)]
 pub struct SearchFilter {
-    /// A string to match against filenames.
+    /// A string to match against filenames, or a glob, see `match_mode`.
     pub query: String,
+    /// How `query` is matched.
+    pub match_mode: FilenameMatchMode,
+    /// The directory `FilenameMatchMode::Glob` patterns are relative to.
+    pub root: PathBuf,
     /// A file extension to filter by (e.g., "rs", "txt").
     pub extension: String,
     /// `true` if the filename query should be case-sensitive.
     pub case_sensitive: bool,
+    /// Files smaller than this many bytes are excluded. Directories are unaffected.
+    pub min_size: Option<u64>,
+    /// Files larger than this many bytes are excluded. Directories are unaffected.
+    pub max_size: Option<u64>,
+    /// Exclude zero-byte files. Directories are unaffected.
+    pub exclude_empty: bool,
+    /// Files modified before this, or with an unknown modification time, are
+    /// excluded. Directories are unaffected.
+    pub modified_since: Option<SystemTime>,
+    /// If set, only these paths are kept, e.g. the files tracked by git together with
+    /// their parent directories.
+    pub tracked_paths: Option<Arc<HashSet<PathBuf>>>,
+}
+
+/// How `SearchFilter::query` is matched against paths.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum FilenameMatchMode {
+    /// The file name contains the query.
+    #[default]
+    Substring,
+    /// The path relative to `SearchFilter::root` matches the query as a glob, e.g.
+    /// `src/**/*.rs`. `*` does not match across directories, `**` does.
+    Glob,
 }
 
 // Re-export der ScanProgress aus scanner
 pub use scanner::ScanProgress;
 
-pub use file_handler::FileHandler;
+pub use file_handler::{
+    ConcatenatedOutput, FileHandler, FilePreview, GeneratedOutput, SpilledOutput, SpilledReader,
+};
+pub use git_status::GitStatus;
+pub use ignore_rules::{ExportIgnore, IgnoreRules};
+pub use output_format::{IndexEntry, Manifest, OutputFormat};
+pub use redaction::Redaction;
 pub use scanner::DirectoryScanner;
-pub use search::SearchEngine;
-pub use tree_generator::TreeGenerator;
+pub use search::{FileMatches, MatchSnippet, SearchEngine};
+pub use transform::{ContentTransform, LineEndings, LineSummary, TabPolicy};
+pub use tree_generator::{TreeGenerator, TreePosition, TreeStyle};
@@ -0,0 +1,401 @@
+//! The document formats the concatenated output can be rendered in.
+
+use serde::{Deserialize, Serialize};
+
+/// The format of the generated output document.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum OutputFormat {
+    /// The classic text format with `===FILE-START===` / `---FILE-END-----` markers.
+    #[default]
+    PlainText,
+    /// A self-contained HTML page with one `<section>` per file. No JavaScript is used.
+    Html,
+    /// An XML document with one `<file path="..." language="...">` element per file.
+    /// File content is wrapped in CDATA sections.
+    Xml,
+}
+
+/// The facts listed in a generation manifest.
+#[derive(Debug, Clone, Copy)]
+pub struct Manifest<'a> {
+    pub root: &'a str,
+    pub generated: &'a str,
+    /// Sum of the included files' sizes on disk, in bytes.
+    pub total_size: u64,
+    /// Tokens of the document without the manifest itself.
+    pub total_tokens: usize,
+    /// Paths of the included files, relative to `root`.
+    pub files: &'a [String],
+}
+
+/// One file listed in the index block, see `OutputFormat::index`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// The path as shown in the file's header.
+    pub path: String,
+    /// Size of the file's content block in the output, in bytes.
+    pub bytes: usize,
+    pub lines: usize,
+    pub tokens: usize,
+}
+
+impl IndexEntry {
+    /// The entry for a file whose content block in the output is `content`.
+    pub fn new(path: String, content: &str, tokens: usize) -> Self {
+        Self {
+            path,
+            bytes: content.len(),
+            lines: content.lines().count(),
+            tokens,
+        }
+    }
+}
+
+/// Minimal styling embedded into HTML output so it is readable without external assets.
+const HTML_STYLE: &str =
+    "body{font-family:system-ui,sans-serif;margin:2rem;color:#1f2328;background:#fff}\
+h1{font-size:1.4rem}h2{font-size:1rem;font-family:monospace;margin:0 0 .5rem}\
+section{margin:1.5rem 0;border:1px solid #d0d7de;border-radius:6px;padding:1rem}\
+pre{margin:0;padding:.75rem;overflow:auto;background:#f6f8fa;border-radius:6px;font-size:.85rem}\
+details{margin:1rem 0}summary{cursor:pointer;font-weight:600}\
+dt{font-weight:600}dd{margin:0 0 .25rem 1rem}";
+
+impl OutputFormat {
+    /// Every output format.
+    pub const ALL: [OutputFormat; 3] = [
+        OutputFormat::PlainText,
+        OutputFormat::Html,
+        OutputFormat::Xml,
+    ];
+
+    /// The format whose `extension` is `extension`, if any.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extension() == extension)
+    }
+
+    /// The file extension conventionally used for this format, without a dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::PlainText => "txt",
+            OutputFormat::Html => "html",
+            OutputFormat::Xml => "xml",
+        }
+    }
+
+    /// Escapes text so it can be embedded verbatim in the output.
+    pub fn escape(&self, text: &str) -> String {
+        match self {
+            OutputFormat::PlainText => text.to_string(),
+            OutputFormat::Html => escape_html(text),
+            OutputFormat::Xml => escape_cdata(text),
+        }
+    }
+
+    /// The markup that opens the document, before any visible content. A manifest is
+    /// inserted directly after it.
+    pub fn document_start(&self) -> String {
+        match self {
+            OutputFormat::PlainText => String::new(),
+            OutputFormat::Html => format!(
+                "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n\
+                 <meta charset=\"utf-8\">\n<title>CFC Output</title>\n\
+                 <style>{HTML_STYLE}</style>\n</head>\n<body>\n"
+            ),
+            OutputFormat::Xml => {
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<context>\n".to_string()
+            }
+        }
+    }
+
+    /// The document header: a metadata block and, if given, the directory tree.
+    pub fn preamble(&self, generated: &str, file_count: usize, tree: Option<&str>) -> String {
+        let mut content = self.document_start();
+        match self {
+            OutputFormat::PlainText => {
+                content.push_str(&format!("# CFC Output - Generated: {generated}\n"));
+                content.push_str(&format!("# Total files: {file_count}\n\n"));
+            }
+            OutputFormat::Html => {
+                content.push_str("<header>\n<h1>CFC Output</h1>\n");
+                content.push_str(&format!("<p>Generated: {generated}</p>\n"));
+                content.push_str(&format!("<p>Total files: {file_count}</p>\n</header>\n"));
+            }
+            OutputFormat::Xml => {
+                content.push_str(&format!(
+                    "<metadata generated=\"{}\" files=\"{file_count}\"/>\n",
+                    escape_html(generated)
+                ));
+            }
+        }
+        if let Some(tree) = tree {
+            content.push_str(&self.tree_section(tree));
+        }
+        content
+    }
+
+    /// The directory tree block, placed in the preamble or after the last file.
+    pub fn tree_section(&self, tree: &str) -> String {
+        match self {
+            OutputFormat::PlainText => {
+                format!("# DIRECTORY TREE\n=====================\n{tree}=====================\n\n")
+            }
+            OutputFormat::Html => format!(
+                "<details>\n<summary>Directory tree</summary>\n<pre>{}</pre>\n</details>\n",
+                escape_html(tree)
+            ),
+            OutputFormat::Xml => format!("<tree><![CDATA[\n{}]]></tree>\n", escape_cdata(tree)),
+        }
+    }
+
+    /// A summary of the document's scope, placed before the header.
+    pub fn manifest(&self, manifest: &Manifest) -> String {
+        let mut content = String::new();
+        match self {
+            OutputFormat::PlainText => {
+                content.push_str("# MANIFEST\n");
+                content.push_str(&format!("# Root: {}\n", manifest.root));
+                content.push_str(&format!("# Generated: {}\n", manifest.generated));
+                content.push_str(&format!("# Files: {}\n", manifest.files.len()));
+                content.push_str(&format!("# Total size: {} bytes\n", manifest.total_size));
+                content.push_str(&format!(
+                    "# Total tokens (excluding manifest): {}\n",
+                    manifest.total_tokens
+                ));
+                for file in manifest.files {
+                    content.push_str(&format!("#   {file}\n"));
+                }
+                content.push('\n');
+            }
+            OutputFormat::Html => {
+                content.push_str("<aside class=\"manifest\">\n<h2>Manifest</h2>\n<dl>\n");
+                for (term, value) in [
+                    ("Root", escape_html(manifest.root)),
+                    ("Generated", escape_html(manifest.generated)),
+                    ("Files", manifest.files.len().to_string()),
+                    ("Total size", format!("{} bytes", manifest.total_size)),
+                    (
+                        "Total tokens (excluding manifest)",
+                        manifest.total_tokens.to_string(),
+                    ),
+                ] {
+                    content.push_str(&format!("<dt>{term}</dt><dd>{value}</dd>\n"));
+                }
+                content.push_str("</dl>\n<ul>\n");
+                for file in manifest.files {
+                    content.push_str(&format!("<li>{}</li>\n", escape_html(file)));
+                }
+                content.push_str("</ul>\n</aside>\n");
+            }
+            OutputFormat::Xml => {
+                content.push_str(&format!(
+                    "<manifest root=\"{}\" generated=\"{}\" files=\"{}\" total_size=\"{}\" \
+                     total_tokens=\"{}\">\n",
+                    escape_html(manifest.root),
+                    escape_html(manifest.generated),
+                    manifest.files.len(),
+                    manifest.total_size,
+                    manifest.total_tokens
+                ));
+                for file in manifest.files {
+                    content.push_str(&format!("<entry path=\"{}\"/>\n", escape_html(file)));
+                }
+                content.push_str("</manifest>\n");
+            }
+        }
+        content
+    }
+
+    /// A numbered list of the files in concatenation order with the size of each,
+    /// placed before the first file block.
+    pub fn index(&self, entries: &[IndexEntry]) -> String {
+        let mut content = String::new();
+        match self {
+            OutputFormat::PlainText => {
+                content.push_str("# INDEX\n");
+                for (number, entry) in entries.iter().enumerate() {
+                    content.push_str(&format!(
+                        "# {:>3}. {} ({} bytes, {} lines, {} tokens)\n",
+                        number + 1,
+                        entry.path,
+                        entry.bytes,
+                        entry.lines,
+                        entry.tokens
+                    ));
+                }
+                content.push('\n');
+            }
+            OutputFormat::Html => {
+                content.push_str("<nav class=\"index\">\n<h2>Index</h2>\n<ol>\n");
+                for entry in entries {
+                    content.push_str(&format!(
+                        "<li>{} ({} bytes, {} lines, {} tokens)</li>\n",
+                        escape_html(&entry.path),
+                        entry.bytes,
+                        entry.lines,
+                        entry.tokens
+                    ));
+                }
+                content.push_str("</ol>\n</nav>\n");
+            }
+            OutputFormat::Xml => {
+                content.push_str("<index>\n");
+                for (number, entry) in entries.iter().enumerate() {
+                    content.push_str(&format!(
+                        "<entry number=\"{}\" path=\"{}\" bytes=\"{}\" lines=\"{}\" tokens=\"{}\"/>\n",
+                        number + 1,
+                        escape_html(&entry.path),
+                        entry.bytes,
+                        entry.lines,
+                        entry.tokens
+                    ));
+                }
+                content.push_str("</index>\n");
+            }
+        }
+        content
+    }
+
+    /// The markup written before a file's content. `mode` describes the file's
+    /// permissions, e.g. `mode: 100755`, see `FileHandler::file_mode`. `checksum` is
+    /// the file's SHA-256 in hex.
+    pub fn file_header(
+        &self,
+        display_path: &str,
+        language: &str,
+        mode: Option<&str>,
+        checksum: Option<&str>,
+    ) -> String {
+        match self {
+            OutputFormat::PlainText => format!(
+                "{display_path}{}\n{}===FILE-START===\n",
+                mode.map(|mode| format!(" [{mode}]")).unwrap_or_default(),
+                checksum
+                    .map(|checksum| format!("sha256: {checksum}\n"))
+                    .unwrap_or_default()
+            ),
+            OutputFormat::Html => format!(
+                "<section>\n<h2>{}{}{}</h2>\n<pre><code class=\"language-{}\">",
+                escape_html(display_path),
+                mode.map(|mode| format!(" <small>{}</small>", escape_html(mode)))
+                    .unwrap_or_default(),
+                checksum
+                    .map(|checksum| format!(" <small>sha256: {checksum}</small>"))
+                    .unwrap_or_default(),
+                escape_html(language)
+            ),
+            OutputFormat::Xml => format!(
+                "<file path=\"{}\" language=\"{}\"{}{}><![CDATA[",
+                escape_html(display_path),
+                escape_html(language),
+                mode.map(|mode| format!(" permissions=\"{}\"", escape_html(mode)))
+                    .unwrap_or_default(),
+                checksum
+                    .map(|checksum| format!(" sha256=\"{checksum}\""))
+                    .unwrap_or_default()
+            ),
+        }
+    }
+
+    /// The markup written after a file's content.
+    pub fn file_footer(&self) -> &'static str {
+        match self {
+            OutputFormat::PlainText => "---FILE-END-----\n\n",
+            OutputFormat::Html => "</code></pre>\n</section>\n",
+            OutputFormat::Xml => "]]></file>\n",
+        }
+    }
+
+    /// Whether a file's content must end with a newline before the footer.
+    pub fn needs_trailing_newline(&self) -> bool {
+        matches!(self, OutputFormat::PlainText)
+    }
+
+    /// The markup that closes the document.
+    pub fn epilogue(&self) -> &'static str {
+        match self {
+            OutputFormat::PlainText => "",
+            OutputFormat::Html => "</body>\n</html>\n",
+            OutputFormat::Xml => "</context>\n",
+        }
+    }
+}
+
+/// Makes text safe to place inside a CDATA section by splitting every `]]>` across two
+/// sections, so the terminator never appears in the content itself.
+fn escape_cdata(text: &str) -> String {
+    text.replace("]]>", "]]]]><![CDATA[>")
+}
+
+/// Escapes the characters that are significant in HTML text and attribute values.
+/// The result is also valid in XML attribute values.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_escaping_covers_markup_characters() {
+        assert_eq!(
+            OutputFormat::Html.escape("<a href=\"x\">Tom & 'Jerry'</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#39;Jerry&#39;&lt;/a&gt;"
+        );
+        assert_eq!(OutputFormat::PlainText.escape("<a>"), "<a>");
+    }
+
+    #[test]
+    fn html_file_header_uses_language_class_and_escapes_path() {
+        assert_eq!(
+            OutputFormat::Html.file_header("src/<main>.rs", "rust", None, None),
+            "<section>\n<h2>src/&lt;main&gt;.rs</h2>\n<pre><code class=\"language-rust\">"
+        );
+    }
+
+    #[test]
+    fn xml_cdata_escaping_splits_the_terminator() {
+        assert_eq!(OutputFormat::Xml.escape("a]]>b"), "a]]]]><![CDATA[>b");
+        assert_eq!(
+            OutputFormat::Xml.file_header("src/\"q\".rs", "rust", None, None),
+            "<file path=\"src/&quot;q&quot;.rs\" language=\"rust\"><![CDATA["
+        );
+    }
+
+    #[test]
+    fn manifest_lists_files_after_the_document_start() {
+        let files = vec!["src/main.rs".to_string(), "a&b.txt".to_string()];
+        let manifest = Manifest {
+            root: "/repo",
+            generated: "2024-01-01 00:00:00",
+            total_size: 42,
+            total_tokens: 7,
+            files: &files,
+        };
+
+        let text = OutputFormat::PlainText.manifest(&manifest);
+        assert!(text.starts_with("# MANIFEST\n# Root: /repo\n"));
+        assert!(text.contains("# Files: 2\n"));
+        assert!(text.contains("# Total size: 42 bytes\n"));
+        assert!(text.contains("#   src/main.rs\n"));
+
+        let html = OutputFormat::Html.manifest(&manifest);
+        assert!(html.contains("<li>a&amp;b.txt</li>"));
+        assert!(OutputFormat::Html
+            .preamble("now", 2, None)
+            .starts_with(&OutputFormat::Html.document_start()));
+    }
+}
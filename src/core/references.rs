@@ -0,0 +1,111 @@
+//! Finds the files a file refers to by a quoted relative path.
+//!
+//! This is a language-agnostic heuristic: every quoted string that looks like a path is
+//! a candidate, e.g. `import { x } from "./util"` or `include_str!("../data/table.csv")`.
+//! Callers decide which candidates actually exist.
+
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Selected files larger than this many bytes are not searched for references.
+pub const MAX_SCANNED_FILE_BYTES: u64 = 1024 * 1024;
+/// Referenced files larger than this many bytes are never auto-included.
+pub const MAX_REFERENCED_FILE_BYTES: u64 = 64 * 1024;
+/// At most this many referenced files are auto-included per generation.
+pub const MAX_AUTO_INCLUDED_FILES: usize = 20;
+
+/// Returns the paths the quoted strings in `content` could refer to, in order of their
+/// first appearance. Each string is resolved against the directory of `file` and
+/// against `root`; a string without an extension is also tried with the extension of
+/// `file`. Paths that would leave `root` are dropped.
+pub fn referenced_paths(content: &str, file: &Path, root: &Path) -> Vec<PathBuf> {
+    static QUOTED: OnceLock<Regex> = OnceLock::new();
+    let quoted = QUOTED.get_or_init(|| {
+        Regex::new(r#"["'`]([^"'`\s]{1,256})["'`]"#).expect("quoted-string regex is valid")
+    });
+    let base = file.parent().unwrap_or(root);
+    let extension = file.extension();
+
+    let mut paths = Vec::new();
+    let mut seen = HashSet::new();
+    for captures in quoted.captures_iter(content) {
+        let reference = &captures[1];
+        if !looks_like_relative_path(reference) {
+            continue;
+        }
+        let reference = Path::new(reference);
+        let mut candidates = vec![base.join(reference), root.join(reference)];
+        if let (None, Some(extension)) = (reference.extension(), extension) {
+            candidates.extend([
+                base.join(reference).with_extension(extension),
+                root.join(reference).with_extension(extension),
+            ]);
+        }
+        for candidate in candidates.iter().map(|path| normalize(path)) {
+            if candidate.starts_with(root) && candidate != file && seen.insert(candidate.clone()) {
+                paths.push(candidate);
+            }
+        }
+    }
+    paths
+}
+
+/// Whether `reference` is worth resolving: a relative path with a directory separator
+/// or an extension, and not a URL.
+fn looks_like_relative_path(reference: &str) -> bool {
+    (reference.contains('/') || reference.contains('.'))
+        && !reference.contains("://")
+        && !reference.starts_with('/')
+        && !reference.starts_with('\\')
+        && reference.chars().any(char::is_alphanumeric)
+}
+
+/// Resolves `.` and `..` components without touching the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_paths_resolve_against_the_file_and_the_root() {
+        let root = Path::new("/repo");
+        let file = Path::new("/repo/web/app.ts");
+        let content = r#"import { x } from "./util";
+            const table = require('../data/table.csv');
+            fetch("https://example.com/api.json", `${name}`);"#;
+
+        assert_eq!(
+            referenced_paths(content, file, root),
+            vec![
+                PathBuf::from("/repo/web/util"),
+                PathBuf::from("/repo/util"),
+                PathBuf::from("/repo/web/util.ts"),
+                PathBuf::from("/repo/util.ts"),
+                PathBuf::from("/repo/data/table.csv"),
+            ]
+        );
+    }
+
+    #[test]
+    fn paths_outside_the_root_are_dropped() {
+        let root = Path::new("/repo");
+        let file = Path::new("/repo/main.rs");
+
+        assert!(referenced_paths(r#"include_str!("../secrets.txt")"#, file, root).is_empty());
+    }
+}
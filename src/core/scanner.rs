@@ -1,7 +1,8 @@
 //! Provides the functionality for recursively scanning directories.
 
-use super::{CoreError, FileItem};
-use crate::utils::file_detection::is_text_file;
+use super::ignore_rules::negated_pattern;
+use super::{CoreError, FileItem, IgnoreRules};
+use crate::utils::file_detection::{is_likely_generated, is_text_file_with, ExtensionOverrides};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
@@ -17,12 +18,17 @@ pub struct ScanProgress {
     pub current_scanning_path: String,
 }
 
+/// The default size above which files are skipped. The app overrides it with
+/// `AppConfig::max_file_size_mb` via `with_max_file_size_mb`.
 const MAX_FILE_SIZE: u64 = 20 * 1024 * 1024;
 const PROGRESS_UPDATE_THROTTLE: Duration = Duration::from_millis(100);
 
 /// Scans a directory for files and subdirectories, respecting ignore patterns.
 pub struct DirectoryScanner {
     ignore_patterns: HashSet<String>,
+    max_file_size: u64,
+    include_hidden: bool,
+    extension_overrides: ExtensionOverrides,
     // This field only exists in test builds to allow deterministic testing of progress updates.
     #[cfg(test)]
     progress_throttle_override: Option<Duration>,
@@ -31,18 +37,26 @@ pub struct DirectoryScanner {
 /// Private helper function with the core walker loop.
 /// This allows the throttling logic to be tested deterministically without
 /// polluting the public API signature.
+///
+/// Returns the collected items together with the paths of files that were
+/// skipped for exceeding `max_file_size` and the paths of broken symlinks, which
+/// are always skipped.
 fn process_walker_results<F, H>(
     walker: ignore::Walk,
     cancel_flag: Arc<AtomicBool>,
     progress_callback: F,
     progress_throttle: Duration,
+    max_file_size: u64,
+    extension_overrides: &ExtensionOverrides,
     mut test_hook: H,
-) -> Vec<FileItem>
+) -> (Vec<FileItem>, Vec<PathBuf>, Vec<PathBuf>)
 where
     F: Fn(ScanProgress) + Send + Sync + 'static,
     H: FnMut(&ignore::DirEntry) + Send + 'static,
 {
     let mut final_files = Vec::new();
+    let mut large_files = Vec::new();
+    let mut broken_symlinks = Vec::new();
     let large_files_skipped_counter = AtomicUsize::new(0);
     let files_scanned_counter = AtomicUsize::new(0);
     let mut last_update = Instant::now();
@@ -71,6 +85,14 @@ where
             continue;
         }
 
+        // Links are not followed, so the entry's own metadata always succeeds. A link
+        // whose target cannot be read is skipped here instead of failing later on.
+        if entry.path_is_symlink() && std::fs::metadata(entry.path()).is_err() {
+            tracing::debug!("Skipping broken symlink {}", entry.path().display());
+            broken_symlinks.push(entry.path().to_path_buf());
+            continue;
+        }
+
         // All logic related to counting and progress now happens only for valid entries.
         let count = files_scanned_counter.fetch_add(1, Ordering::Relaxed) + 1;
         if Instant::now().duration_since(last_update) > progress_throttle {
@@ -101,18 +123,21 @@ where
             }
         };
 
-        if !metadata.is_dir() && metadata.len() > MAX_FILE_SIZE {
+        if !metadata.is_dir() && metadata.len() > max_file_size {
             large_files_skipped_counter.fetch_add(1, Ordering::Relaxed);
+            large_files.push(entry.path().to_path_buf());
             continue;
         }
 
         let is_binary = if metadata.is_file() {
             // If file content cannot be read for is_text_file check, it returns Err.
             // We treat such files as binary for safety, covering the unwrap_or(false) path.
-            !is_text_file(entry.path()).unwrap_or(false)
+            !is_text_file_with(entry.path(), extension_overrides).unwrap_or(false)
         } else {
             false
         };
+        let likely_generated =
+            metadata.is_file() && !is_binary && is_likely_generated(entry.path());
 
         final_files.push(FileItem {
             path: entry.path().to_path_buf(),
@@ -121,15 +146,21 @@ where
             size: metadata.len(),
             depth: entry.depth(),
             parent: entry.path().parent().map(PathBuf::from),
+            mtime: metadata.modified().ok(),
+            likely_generated,
+            git_status: None,
         });
     }
-    final_files
+    (final_files, large_files, broken_symlinks)
 }
 
 impl DirectoryScanner {
     pub fn new(ignore_patterns: HashSet<String>) -> Self {
         Self {
             ignore_patterns,
+            max_file_size: MAX_FILE_SIZE,
+            include_hidden: true,
+            extension_overrides: ExtensionOverrides::default(),
             #[cfg(test)]
             progress_throttle_override: None,
         }
@@ -139,28 +170,54 @@ impl DirectoryScanner {
     fn new_with_throttle(ignore_patterns: HashSet<String>, throttle: Duration) -> Self {
         Self {
             ignore_patterns,
+            max_file_size: MAX_FILE_SIZE,
+            include_hidden: true,
+            extension_overrides: ExtensionOverrides::default(),
             progress_throttle_override: Some(throttle),
         }
     }
 
+    /// Overrides the size limit (in megabytes) above which files are skipped.
+    pub fn with_max_file_size_mb(mut self, max_file_size_mb: u64) -> Self {
+        self.max_file_size = max_file_size_mb.saturating_mul(1024 * 1024);
+        self
+    }
+
+    /// Sets whether `.`-prefixed files and directories are walked. They are included
+    /// by default.
+    pub fn with_include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    /// Sets extensions that are classified as text or binary regardless of content.
+    pub fn with_extension_overrides(mut self, extension_overrides: ExtensionOverrides) -> Self {
+        self.extension_overrides = extension_overrides;
+        self
+    }
+
     /// Scans a directory asynchronously, providing progress updates via a callback.
     ///
     /// This function performs the scan in a blocking thread to avoid blocking the async runtime,
     /// while allowing for cancellation and progress reporting. It uses the `ignore` crate
     /// for high-performance, gitignore-aware directory traversal. It also manually checks
-    /// custom ignore patterns to report which ones were actively used.
+    /// custom ignore patterns to report which ones were actively used, and returns the
+    /// paths of any files skipped for exceeding the size limit and of any broken symlinks.
     pub async fn scan_directory_with_progress<F>(
         &self,
         root_path: &Path,
         max_depth: Option<usize>,
         cancel_flag: Arc<AtomicBool>,
         progress_callback: F,
-    ) -> Result<(Vec<FileItem>, HashSet<String>), CoreError>
+    ) -> Result<(Vec<FileItem>, HashSet<String>, Vec<PathBuf>, Vec<PathBuf>), CoreError>
     where
         F: Fn(ScanProgress) + Send + Sync + 'static,
     {
         let root_path_buf = root_path.to_path_buf();
         let ignore_patterns_clone = self.ignore_patterns.clone();
+        let max_file_size = self.max_file_size;
+        let include_hidden = self.include_hidden;
+        let extension_overrides = self.extension_overrides.clone();
 
         #[cfg(test)]
         let throttle = self
@@ -175,23 +232,23 @@ impl DirectoryScanner {
             let active_patterns =
                 std::sync::Arc::new(std::sync::Mutex::new(HashSet::<String>::new()));
 
-            // Create a list of individual matchers for each user-defined pattern.
-            let custom_matchers: Vec<(String, ignore::gitignore::Gitignore)> =
-                ignore_patterns_clone
-                    .iter()
-                    .filter_map(|pattern| {
-                        let mut builder = ignore::gitignore::GitignoreBuilder::new(&root_path_buf);
-                        builder.add_line(None, pattern).ok()?;
-                        builder.build().ok().map(|m| (pattern.clone(), m))
-                    })
-                    .collect();
+            // The combined rules decide what is excluded, including `!` overrides.
+            let rules = IgnoreRules::new(&root_path_buf, &ignore_patterns_clone);
+
+            // Individual matchers for each plain pattern, used to report which
+            // patterns actually excluded something.
+            let custom_matchers: Vec<(String, IgnoreRules)> = ignore_patterns_clone
+                .iter()
+                .filter(|pattern| negated_pattern(pattern).is_none())
+                .map(|pattern| (pattern.clone(), IgnoreRules::new(&root_path_buf, [pattern])))
+                .collect();
 
             if let Some(depth) = max_depth {
                 walker_builder.max_depth(Some(depth));
             }
 
             walker_builder
-                .hidden(false)
+                .hidden(!include_hidden)
                 .parents(false)
                 .git_global(true)
                 .git_ignore(true)
@@ -203,26 +260,41 @@ impl DirectoryScanner {
             let active_patterns_clone = active_patterns.clone();
             walker_builder.filter_entry(move |entry| {
                 let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
-                for (pattern, matcher) in &custom_matchers {
-                    if matcher.matched(entry.path(), is_dir).is_ignore() {
-                        active_patterns_clone
-                            .lock()
-                            .unwrap()
-                            .insert(pattern.clone());
-                        return false; // Exclude this entry.
-                    }
+                if !rules.is_ignored(entry.path(), is_dir) {
+                    return true; // Keep this entry.
+                }
+                if let Some((pattern, _)) = custom_matchers
+                    .iter()
+                    .find(|(_, matcher)| matcher.is_ignored(entry.path(), is_dir))
+                {
+                    active_patterns_clone
+                        .lock()
+                        .unwrap()
+                        .insert(pattern.clone());
                 }
-                true // Keep this entry.
+                false // Exclude this entry.
             });
 
             let walker = walker_builder.build();
 
             // Call the internal helper with a no-op closure for the test hook.
-            let final_files =
-                process_walker_results(walker, cancel_flag, progress_callback, throttle, |_| {});
+            let (final_files, large_files, broken_symlinks) = process_walker_results(
+                walker,
+                cancel_flag,
+                progress_callback,
+                throttle,
+                max_file_size,
+                &extension_overrides,
+                |_| {},
+            );
 
             let final_active_patterns = active_patterns.lock().unwrap().clone();
-            (final_files, final_active_patterns)
+            (
+                final_files,
+                final_active_patterns,
+                large_files,
+                broken_symlinks,
+            )
         });
 
         // Await the result. If the task panicked, spawn_blocking returns a JoinError,
@@ -275,7 +347,7 @@ mod tests {
 
         let scanner = DirectoryScanner::new(custom_ignores);
 
-        let (files, _) = scanner
+        let (files, _, _, _) = scanner
             .scan_directory_with_progress(&root, None, Arc::new(AtomicBool::new(false)), |_| {})
             .await
             .expect("Scan should succeed");
@@ -293,6 +365,50 @@ mod tests {
         assert!(!paths.contains(&root.join("large_file.bin")));
     }
 
+    /// Verifies that skipped large files are reported and that the size limit is configurable.
+    #[tokio::test]
+    async fn test_large_files_are_reported_and_limit_is_configurable() {
+        setup_test_logging();
+        let (_temp_dir, root) = setup_test_filesystem();
+
+        let scanner = DirectoryScanner::new(HashSet::new());
+        let (files, _, large_files, _) = scanner
+            .scan_directory_with_progress(&root, None, Arc::new(AtomicBool::new(false)), |_| {})
+            .await
+            .expect("Scan should succeed");
+        assert_eq!(large_files, vec![root.join("large_file.bin")]);
+        assert!(!files.iter().any(|f| f.path == root.join("large_file.bin")));
+
+        // Raising the limit lets the same file through.
+        let scanner = DirectoryScanner::new(HashSet::new()).with_max_file_size_mb(21);
+        let (files, _, large_files, _) = scanner
+            .scan_directory_with_progress(&root, None, Arc::new(AtomicBool::new(false)), |_| {})
+            .await
+            .expect("Scan should succeed");
+        assert!(large_files.is_empty());
+        assert!(files.iter().any(|f| f.path == root.join("large_file.bin")));
+    }
+
+    /// Verifies that dangling symlinks are skipped and reported, while intact ones stay.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_broken_symlinks_are_skipped_and_reported() {
+        setup_test_logging();
+        let (_temp_dir, root) = setup_test_filesystem();
+        std::os::unix::fs::symlink(root.join("missing.rs"), root.join("src/dangling.rs")).unwrap();
+        std::os::unix::fs::symlink(root.join("src/main.rs"), root.join("src/alias.rs")).unwrap();
+
+        let scanner = DirectoryScanner::new(HashSet::new());
+        let (files, _, _, broken_symlinks) = scanner
+            .scan_directory_with_progress(&root, None, Arc::new(AtomicBool::new(false)), |_| {})
+            .await
+            .expect("Scan should succeed");
+
+        assert_eq!(broken_symlinks, vec![root.join("src/dangling.rs")]);
+        assert!(!files.iter().any(|f| f.path == root.join("src/dangling.rs")));
+        assert!(files.iter().any(|f| f.path == root.join("src/alias.rs")));
+    }
+
     /// Verifies that the `max_depth` parameter is correctly honored.
     #[tokio::test]
     async fn test_max_depth_is_honored() {
@@ -300,7 +416,7 @@ mod tests {
         let (_temp_dir, root) = setup_test_filesystem();
         let scanner = DirectoryScanner::new(HashSet::new());
 
-        let (files, _) = scanner
+        let (files, _, _, _) = scanner
             .scan_directory_with_progress(&root, Some(1), Arc::new(AtomicBool::new(false)), |_| {})
             .await
             .expect("Scan should succeed");
@@ -355,6 +471,8 @@ mod tests {
                     }
                 },
                 PROGRESS_UPDATE_THROTTLE, // Use standard throttle for this test
+                MAX_FILE_SIZE,
+                &ExtensionOverrides::default(),
                 move |_| {
                     // Introduce a small delay to make cancellation more likely to happen mid-scan.
                     std::thread::sleep(std::time::Duration::from_millis(1));
@@ -370,7 +488,7 @@ mod tests {
 
         // Now, cancel the operation.
         cancel_flag.store(true, Ordering::SeqCst);
-        let files = handle.await.expect("Scan task panicked");
+        let (files, _, _) = handle.await.expect("Scan task panicked");
 
         assert!(!files.is_empty());
         assert!(
@@ -403,6 +521,8 @@ mod tests {
                 updates_clone.lock().unwrap().push(progress);
             },
             PROGRESS_UPDATE_THROTTLE, // Use standard throttle
+            MAX_FILE_SIZE,
+            &ExtensionOverrides::default(),
             // The deterministic test hook.
             move |entry| {
                 hook_call_count += 1;
@@ -436,7 +556,7 @@ mod tests {
         custom_ignores.insert(pattern_not_to_match.clone());
 
         let scanner = DirectoryScanner::new(custom_ignores);
-        let (files, active_patterns) = scanner
+        let (files, active_patterns, _, _) = scanner
             .scan_directory_with_progress(&root, None, Arc::new(AtomicBool::new(false)), |_| {})
             .await
             .expect("Scan should succeed");
@@ -457,6 +577,28 @@ mod tests {
         assert!(!paths.contains(&root.join("src/main.rs")));
     }
 
+    /// Verifies that a `!` pattern keeps a file that a broader pattern would exclude.
+    #[tokio::test]
+    async fn test_negated_ignore_pattern_re_includes_file() {
+        setup_test_logging();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("debug.log"), "noise").unwrap();
+        fs::write(root.join("keep.log"), "important").unwrap();
+        let custom_ignores = HashSet::from(["*.log".to_string(), "!keep.log".to_string()]);
+
+        let scanner = DirectoryScanner::new(custom_ignores);
+        let (files, active_patterns, _, _) = scanner
+            .scan_directory_with_progress(root, None, Arc::new(AtomicBool::new(false)), |_| {})
+            .await
+            .unwrap();
+
+        let paths: HashSet<PathBuf> = files.iter().map(|f| f.path.clone()).collect();
+        assert!(paths.contains(&root.join("keep.log")));
+        assert!(!paths.contains(&root.join("debug.log")));
+        assert_eq!(active_patterns, HashSet::from(["*.log".to_string()]));
+    }
+
     /// Verifies that paths with special characters are handled correctly.
     #[tokio::test]
     async fn test_scan_with_special_characters_in_paths() {
@@ -469,7 +611,7 @@ mod tests {
         fs::write(&special_file, "fn solution() {}").unwrap();
 
         let scanner = DirectoryScanner::new(HashSet::new());
-        let (files, _) = scanner
+        let (files, _, _, _) = scanner
             .scan_directory_with_progress(root, None, Arc::new(AtomicBool::new(false)), |_| {})
             .await
             .unwrap();
@@ -491,7 +633,7 @@ mod tests {
             .await;
 
         assert!(result.is_ok());
-        let (files, active_patterns) = result.unwrap();
+        let (files, active_patterns, _, _) = result.unwrap();
         assert!(!files.is_empty());
         assert!(active_patterns.is_empty());
     }
@@ -510,7 +652,7 @@ mod tests {
             .await;
 
         assert!(result.is_ok());
-        let (files, active_patterns) = result.unwrap();
+        let (files, active_patterns, _, _) = result.unwrap();
         assert!(active_patterns.is_empty());
         assert!(!files.is_empty());
     }
@@ -541,7 +683,7 @@ mod tests {
         fs::set_permissions(&unreadable_dir, perms.clone()).unwrap();
 
         let scanner = DirectoryScanner::new(HashSet::new());
-        let (files, _) = scanner
+        let (files, _, _, _) = scanner
             .scan_directory_with_progress(root, None, Arc::new(AtomicBool::new(false)), |_| {})
             .await
             .unwrap();
@@ -578,11 +720,13 @@ mod tests {
         };
 
         // We test the private helper directly to use the deterministic test hook.
-        let files = process_walker_results(
+        let (files, _, _) = process_walker_results(
             walker,
             Arc::new(AtomicBool::new(false)),
             |_| {},
             PROGRESS_UPDATE_THROTTLE,
+            MAX_FILE_SIZE,
+            &ExtensionOverrides::default(),
             test_hook,
         );
 
@@ -626,11 +770,13 @@ mod tests {
             }
         };
 
-        let files = process_walker_results(
+        let (files, _, _) = process_walker_results(
             walker,
             cancel_flag,
             |_| {},
             PROGRESS_UPDATE_THROTTLE,
+            MAX_FILE_SIZE,
+            &ExtensionOverrides::default(),
             test_hook,
         );
 
@@ -703,11 +849,13 @@ mod tests {
             }
         };
 
-        let files = process_walker_results(
+        let (files, _, _) = process_walker_results(
             walker,
             Arc::new(AtomicBool::new(false)),
             |_| {},
             PROGRESS_UPDATE_THROTTLE,
+            MAX_FILE_SIZE,
+            &ExtensionOverrides::default(),
             test_hook,
         );
 
@@ -1,10 +1,33 @@
 //! Provides logic for filtering and searching lists of `FileItem`s.
 
-use super::{FileItem, SearchFilter};
+use super::{FileItem, FilenameMatchMode, SearchFilter};
+use crate::utils::unicode::{nfc, nfc_path};
+use globset::{GlobBuilder, GlobMatcher};
 use rayon::prelude::*;
+use serde::Serialize;
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
+/// The content search result for a single file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileMatches {
+    /// Total number of occurrences of the query in the file.
+    pub count: usize,
+    /// The first few matching lines with their context. Empty unless snippets were
+    /// requested.
+    pub snippets: Vec<MatchSnippet>,
+}
+
+/// A line matching a content search, together with the lines around it.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct MatchSnippet {
+    /// The 1-based number of the matching line.
+    pub line_number: usize,
+    pub line: String,
+    pub context_before: Vec<String>,
+    pub context_after: Vec<String>,
+}
+
 /// A utility struct for searching and filtering file lists.
 ///
 /// This struct is stateless and provides methods as associated functions.
@@ -15,19 +38,59 @@ impl SearchEngine {
     ///
     /// This is the main entry point for applying dynamic UI filters (name, extension).
     /// Ignore patterns are handled during the initial scan by the `scanner` module.
+    /// An invalid glob matches nothing.
     pub fn filter_files(files: &[FileItem], filter: &SearchFilter) -> Vec<FileItem> {
+        let glob = Self::compile_glob(filter);
         files
             .par_iter()
-            .filter(|file| Self::matches_filter(file, filter))
+            .filter(|file| Self::matches_filter_with_glob(file, filter, &glob))
             .cloned()
             .collect()
     }
 
     /// Checks if a single `FileItem` matches the given filter criteria.
     pub fn matches_filter(file: &FileItem, filter: &SearchFilter) -> bool {
-        if !filter.query.is_empty()
-            && !Self::matches_search_query(&file.path, &filter.query, filter.case_sensitive)
-        {
+        Self::matches_filter_with_glob(file, filter, &Self::compile_glob(filter))
+    }
+
+    /// Compiles the query of a `FilenameMatchMode::Glob` filter, so many files can be
+    /// matched against it. `Ok(None)` for other modes or an empty query.
+    pub fn compile_glob(filter: &SearchFilter) -> Result<Option<GlobMatcher>, globset::Error> {
+        if filter.match_mode != FilenameMatchMode::Glob || filter.query.trim().is_empty() {
+            return Ok(None);
+        }
+        GlobBuilder::new(&nfc(filter.query.trim()))
+            .literal_separator(true)
+            .case_insensitive(!filter.case_sensitive)
+            .build()
+            .map(|glob| Some(glob.compile_matcher()))
+    }
+
+    /// Whether `path` matches the name query of `filter`, with the filter's glob
+    /// compiled by `compile_glob`. A glob is matched against the path relative to
+    /// `filter.root`, any other query against the file name.
+    pub fn matches_query(
+        path: &Path,
+        filter: &SearchFilter,
+        glob: &Result<Option<GlobMatcher>, globset::Error>,
+    ) -> bool {
+        match glob {
+            Ok(Some(glob)) => {
+                let relative = path.strip_prefix(&filter.root).unwrap_or(path);
+                glob.is_match(nfc_path(relative))
+            }
+            Ok(None) => Self::matches_search_query(path, &filter.query, filter.case_sensitive),
+            Err(_) => false,
+        }
+    }
+
+    /// Like `matches_filter`, with the filter's glob compiled by `compile_glob`.
+    pub fn matches_filter_with_glob(
+        file: &FileItem,
+        filter: &SearchFilter,
+        glob: &Result<Option<GlobMatcher>, globset::Error>,
+    ) -> bool {
+        if !filter.query.is_empty() && !Self::matches_query(&file.path, filter, glob) {
             return false;
         }
 
@@ -35,18 +98,47 @@ impl SearchEngine {
             return false;
         }
 
+        if !file.is_directory
+            && !Self::matches_size_range(file.size, filter.min_size, filter.max_size)
+        {
+            return false;
+        }
+
+        if filter.exclude_empty && !file.is_directory && file.size == 0 {
+            return false;
+        }
+
+        if let Some(since) = filter.modified_since {
+            if !file.is_directory && file.mtime.is_none_or(|mtime| mtime < since) {
+                return false;
+            }
+        }
+
+        if let Some(tracked) = &filter.tracked_paths {
+            if !tracked.contains(&file.path) {
+                return false;
+            }
+        }
+
         true
     }
 
-    /// Checks if a path's filename contains the search query.
+    /// Checks if a size lies within the optional, inclusive `[min, max]` range.
+    pub fn matches_size_range(size: u64, min: Option<u64>, max: Option<u64>) -> bool {
+        min.is_none_or(|min| size >= min) && max.is_none_or(|max| size <= max)
+    }
+
+    /// Checks if a path's filename contains the search query. Both are compared in
+    /// Unicode NFC, as macOS reports decomposed file names.
     fn matches_search_query(path: &Path, query: &str, case_sensitive: bool) -> bool {
         let file_name = path
             .file_name()
             .and_then(|name| name.to_str())
             .unwrap_or("");
+        let (file_name, query) = (nfc(file_name), nfc(query));
 
         if case_sensitive {
-            file_name.contains(query)
+            file_name.contains(query.as_ref())
         } else {
             let query_lower = query.to_lowercase();
             file_name.to_lowercase().contains(&query_lower)
@@ -131,6 +223,8 @@ impl SearchEngine {
 mod tests {
     use super::*;
     use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
 
     fn file(path: &str) -> FileItem {
         FileItem {
@@ -140,6 +234,9 @@ mod tests {
             size: 100,
             depth: path.split('/').count(),
             parent: PathBuf::from(path).parent().map(|p| p.to_path_buf()),
+            mtime: None,
+            likely_generated: false,
+            git_status: None,
         }
     }
 
@@ -151,6 +248,9 @@ mod tests {
             size: 0,
             depth: path.split('/').count(),
             parent: PathBuf::from(path).parent().map(|p| p.to_path_buf()),
+            mtime: None,
+            likely_generated: false,
+            git_status: None,
         }
     }
 
@@ -174,8 +274,15 @@ mod tests {
         let files = create_test_files();
         let filter = SearchFilter {
             query: "README".to_string(),
+            match_mode: FilenameMatchMode::Substring,
+            root: PathBuf::new(),
             extension: String::new(),
             case_sensitive: true,
+            min_size: None,
+            max_size: None,
+            exclude_empty: false,
+            modified_since: None,
+            tracked_paths: None,
         };
         let result = SearchEngine::filter_files(&files, &filter);
         assert_eq!(result.len(), 2);
@@ -190,8 +297,15 @@ mod tests {
         let files = create_test_files();
         let filter = SearchFilter {
             query: "readme".to_string(),
+            match_mode: FilenameMatchMode::Substring,
+            root: PathBuf::new(),
             extension: String::new(),
             case_sensitive: false,
+            min_size: None,
+            max_size: None,
+            exclude_empty: false,
+            modified_since: None,
+            tracked_paths: None,
         };
         let result = SearchEngine::filter_files(&files, &filter);
         assert_eq!(result.len(), 2);
@@ -201,13 +315,48 @@ mod tests {
         assert!(result.iter().any(|f| f.path.to_str() == Some("README.md")));
     }
 
+    /// macOS reports accented file names decomposed; a composed query still matches.
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_filter_by_name_matches_accented_names_typed_composed() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Re\u{301}sume\u{301}.md"), "").unwrap();
+        let scanned = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| file(entry.unwrap().path().to_str().unwrap()))
+            .collect::<Vec<_>>();
+
+        for case_sensitive in [true, false] {
+            let filter = SearchFilter {
+                query: "R\u{e9}sum\u{e9}".to_string(),
+                match_mode: FilenameMatchMode::Substring,
+                root: PathBuf::new(),
+                extension: String::new(),
+                case_sensitive,
+                min_size: None,
+                max_size: None,
+                exclude_empty: false,
+                modified_since: None,
+                tracked_paths: None,
+            };
+            assert_eq!(SearchEngine::filter_files(&scanned, &filter).len(), 1);
+        }
+    }
+
     #[test]
     fn test_filter_by_extension() {
         let files = create_test_files();
         let filter = SearchFilter {
             query: String::new(),
+            match_mode: FilenameMatchMode::Substring,
+            root: PathBuf::new(),
             extension: "rs".to_string(),
             case_sensitive: false,
+            min_size: None,
+            max_size: None,
+            exclude_empty: false,
+            modified_since: None,
+            tracked_paths: None,
         };
         let result = SearchEngine::filter_files(&files, &filter);
         assert_eq!(result.len(), 3);
@@ -225,8 +374,15 @@ mod tests {
         let files = create_test_files();
         let filter = SearchFilter {
             query: "main".to_string(),
+            match_mode: FilenameMatchMode::Substring,
+            root: PathBuf::new(),
             extension: "rs".to_string(),
             case_sensitive: false,
+            min_size: None,
+            max_size: None,
+            exclude_empty: false,
+            modified_since: None,
+            tracked_paths: None,
         };
         let result = SearchEngine::filter_files(&files, &filter);
         assert_eq!(result.len(), 1);
@@ -243,8 +399,15 @@ mod tests {
         ];
         let filter = SearchFilter {
             query: String::new(),
+            match_mode: FilenameMatchMode::Substring,
+            root: PathBuf::new(),
             extension: "no extension".to_string(),
             case_sensitive: false,
+            min_size: None,
+            max_size: None,
+            exclude_empty: false,
+            modified_since: None,
+            tracked_paths: None,
         };
 
         let result = SearchEngine::filter_files(&files, &filter);
@@ -259,4 +422,209 @@ mod tests {
         assert!(result_paths.contains("Makefile"));
         assert!(result_paths.contains(".config"));
     }
+
+    #[test]
+    fn test_filter_by_size_range_is_inclusive_and_ignores_directories() {
+        let sized = |path: &str, size: u64| FileItem { size, ..file(path) };
+        let files = vec![
+            dir("src"),
+            sized("src/stub.rs", 9),
+            sized("src/min.rs", 10),
+            sized("src/mid.rs", 50),
+            sized("src/max.rs", 100),
+            sized("src/huge.rs", 101),
+        ];
+        let filter = SearchFilter {
+            query: String::new(),
+            match_mode: FilenameMatchMode::Substring,
+            root: PathBuf::new(),
+            extension: String::new(),
+            case_sensitive: false,
+            min_size: Some(10),
+            max_size: Some(100),
+            exclude_empty: false,
+            modified_since: None,
+            tracked_paths: None,
+        };
+
+        let result = SearchEngine::filter_files(&files, &filter);
+        let result_paths: Vec<_> = result.iter().map(|f| f.path.to_str().unwrap()).collect();
+
+        assert_eq!(
+            result_paths,
+            vec!["src", "src/min.rs", "src/mid.rs", "src/max.rs"]
+        );
+    }
+
+    #[test]
+    fn test_filter_by_size_with_only_one_bound() {
+        let sized = |path: &str, size: u64| FileItem { size, ..file(path) };
+        let files = vec![sized("a", 0), sized("b", 5)];
+        let filter = SearchFilter {
+            query: String::new(),
+            match_mode: FilenameMatchMode::Substring,
+            root: PathBuf::new(),
+            extension: String::new(),
+            case_sensitive: false,
+            min_size: Some(1),
+            max_size: None,
+            exclude_empty: false,
+            modified_since: None,
+            tracked_paths: None,
+        };
+        let result = SearchEngine::filter_files(&files, &filter);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].path.to_str(), Some("b"));
+
+        assert!(SearchEngine::matches_size_range(u64::MAX, None, None));
+        assert!(SearchEngine::matches_size_range(5, None, Some(5)));
+        assert!(!SearchEngine::matches_size_range(6, None, Some(5)));
+    }
+
+    fn glob_filter(query: &str) -> SearchFilter {
+        SearchFilter {
+            query: query.to_string(),
+            match_mode: FilenameMatchMode::Glob,
+            root: PathBuf::from("/repo"),
+            extension: String::new(),
+            case_sensitive: false,
+            min_size: None,
+            max_size: None,
+            exclude_empty: false,
+            modified_since: None,
+            tracked_paths: None,
+        }
+    }
+
+    fn glob_matches(files: &[FileItem], query: &str) -> Vec<String> {
+        SearchEngine::filter_files(files, &glob_filter(query))
+            .iter()
+            .map(|f| f.path.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_glob_filter_matches_paths_relative_to_the_root() {
+        let files = vec![
+            file("/repo/Cargo.toml"),
+            file("/repo/src/main.rs"),
+            file("/repo/src/app/mod.rs"),
+            file("/repo/src/app/nested/deep.rs"),
+            file("/repo/crates/core/Cargo.toml"),
+            file("/repo/tests/it.rs"),
+        ];
+
+        assert_eq!(
+            glob_matches(&files, "src/**/*.rs"),
+            vec![
+                "/repo/src/main.rs",
+                "/repo/src/app/mod.rs",
+                "/repo/src/app/nested/deep.rs"
+            ]
+        );
+        // `*` stays within the root; `**` descends.
+        assert_eq!(glob_matches(&files, "*.toml"), vec!["/repo/Cargo.toml"]);
+        assert_eq!(
+            glob_matches(&files, "**/cargo.TOML"),
+            vec!["/repo/Cargo.toml", "/repo/crates/core/Cargo.toml"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_glob_matches_nothing() {
+        let files = vec![file("/repo/src/main.rs")];
+        assert!(SearchEngine::compile_glob(&glob_filter("src/[")).is_err());
+        assert!(glob_matches(&files, "src/[").is_empty());
+    }
+
+    #[test]
+    fn test_filter_by_modification_time_keeps_only_recent_files() {
+        let now = SystemTime::now();
+        let aged = |path: &str, days: u64| FileItem {
+            mtime: Some(now - Duration::from_secs(days * 24 * 60 * 60)),
+            ..file(path)
+        };
+        let files = vec![
+            dir("src"),
+            aged("src/today.rs", 0),
+            aged("src/last_week.rs", 6),
+            aged("src/last_month.rs", 30),
+            file("src/unknown.rs"),
+        ];
+        let filter = SearchFilter {
+            query: String::new(),
+            match_mode: FilenameMatchMode::Substring,
+            root: PathBuf::new(),
+            extension: String::new(),
+            case_sensitive: false,
+            min_size: None,
+            max_size: None,
+            exclude_empty: false,
+            modified_since: Some(now - Duration::from_secs(7 * 24 * 60 * 60)),
+            tracked_paths: None,
+        };
+
+        let result = SearchEngine::filter_files(&files, &filter);
+        let result_paths: Vec<_> = result.iter().map(|f| f.path.to_str().unwrap()).collect();
+
+        assert_eq!(
+            result_paths,
+            vec!["src", "src/today.rs", "src/last_week.rs"]
+        );
+    }
+
+    #[test]
+    fn test_filter_keeps_only_git_tracked_files_and_their_directories() {
+        let repo = tempfile::tempdir().unwrap();
+        let root = repo.path();
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .args(args)
+                .current_dir(root)
+                .status()
+                .is_ok_and(|status| status.success())
+        };
+        if !git(&["init", "--quiet"]) {
+            eprintln!("git is not available, skipping");
+            return;
+        }
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join("target/app.exe"), "binary").unwrap();
+        assert!(git(&["add", "src/main.rs"]));
+
+        let tracked = crate::core::git_status::read_tracked_paths(root).unwrap();
+        let item = |path: &str, is_directory: bool| FileItem {
+            path: root.join(path),
+            is_directory,
+            ..Default::default()
+        };
+        let files = vec![
+            item("src", true),
+            item("src/main.rs", false),
+            item("target", true),
+            item("target/app.exe", false),
+        ];
+        let filter = SearchFilter {
+            query: String::new(),
+            match_mode: FilenameMatchMode::Substring,
+            root: PathBuf::new(),
+            extension: String::new(),
+            case_sensitive: false,
+            min_size: None,
+            max_size: None,
+            exclude_empty: false,
+            modified_since: None,
+            tracked_paths: Some(Arc::new(tracked)),
+        };
+
+        let result = SearchEngine::filter_files(&files, &filter);
+        let result_paths: Vec<_> = result.iter().map(|f| f.path.clone()).collect();
+
+        assert_eq!(
+            result_paths,
+            vec![root.join("src"), root.join("src/main.rs")]
+        );
+    }
 }
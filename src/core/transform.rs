@@ -0,0 +1,458 @@
+//! Optional per-file content transformations applied during concatenation.
+
+use super::redaction::Redaction;
+use crate::utils::file_detection::LanguageOverrides;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Describes which transformations are applied to each file's content before it
+/// is written into the concatenated output.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentTransform {
+    /// Remove line comments for languages with a known line-comment syntax.
+    pub strip_comments: bool,
+    /// Collapse runs of blank lines into a single empty line.
+    pub strip_blank_lines: bool,
+    /// Shorten files longer than a threshold to their first and last lines.
+    pub summarize: Option<LineSummary>,
+    /// The line endings each file is converted to.
+    pub line_endings: LineEndings,
+    /// Remove spaces and tabs at the end of every line.
+    pub trim_trailing_whitespace: bool,
+    /// How tabs and spaces in leading indentation are converted.
+    pub tab_policy: TabPolicy,
+    /// Prefix every line with its right-aligned number, starting at 1 in each file.
+    pub number_lines: bool,
+    /// Show each file's permissions in its header, see `FileHandler::file_mode`.
+    pub include_file_mode: bool,
+    /// Show each file's SHA-256 in its header, computed while the file is read.
+    pub include_checksum: bool,
+    /// Mask secrets matching these patterns before anything else is applied.
+    pub redaction: Option<Arc<Redaction>>,
+    /// Languages named in file headers instead of the built-in ones, see
+    /// `get_language_from_path`.
+    pub language_overrides: Option<Arc<LanguageOverrides>>,
+}
+
+/// How the leading indentation of each line is rewritten. The width is the number of
+/// columns per tab; a width of zero leaves indentation as it is.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TabPolicy {
+    /// Keep indentation as it is.
+    #[default]
+    AsIs,
+    /// Expand indentation tabs to spaces, up to the next tab stop.
+    TabsToSpaces(u8),
+    /// Replace indentation by tabs, keeping leftover columns as spaces.
+    SpacesToTabs(u8),
+}
+
+/// The line endings written to the output.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LineEndings {
+    /// Keep each file's line endings, even if files differ.
+    #[default]
+    AsIs,
+    /// Convert `\r\n` to `\n`.
+    Lf,
+    /// Convert lone `\n` to `\r\n`.
+    Crlf,
+}
+
+/// Settings for shortening long files to a head and a tail with an omission marker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineSummary {
+    /// Files with more lines than this are summarized.
+    pub over_lines: usize,
+    /// Number of lines kept from the start of the file.
+    pub head_lines: usize,
+    /// Number of lines kept from the end of the file.
+    pub tail_lines: usize,
+}
+
+impl ContentTransform {
+    /// Returns `true` if no transformation is enabled. `include_file_mode`,
+    /// `include_checksum` and `language_overrides` only change file headers, so they
+    /// do not count.
+    pub fn is_noop(&self) -> bool {
+        !self.strip_comments
+            && !self.strip_blank_lines
+            && self.summarize.is_none()
+            && self.line_endings == LineEndings::AsIs
+            && !self.trim_trailing_whitespace
+            && self.tab_policy == TabPolicy::AsIs
+            && !self.number_lines
+            && self.redaction.is_none()
+    }
+
+    /// Applies the enabled transformations to `content`.
+    ///
+    /// `language` is the identifier returned by `get_language_from_path`.
+    pub fn apply(&self, content: &str, language: &str) -> String {
+        // The other transformations work on `\n`-separated lines, so CRLF is only
+        // restored at the end.
+        let normalized;
+        let content = if self.line_endings == LineEndings::AsIs {
+            content
+        } else {
+            normalized = content.replace("\r\n", "\n");
+            &normalized
+        };
+        let redacted;
+        let content = match &self.redaction {
+            Some(redaction) => {
+                redacted = redaction.apply(content);
+                &redacted
+            }
+            None => content,
+        };
+        let mut result = if self.strip_comments {
+            strip_line_comments(content, language)
+        } else {
+            content.to_string()
+        };
+        if self.trim_trailing_whitespace || self.tab_policy != TabPolicy::AsIs {
+            result = rewrite_whitespace(&result, self.trim_trailing_whitespace, self.tab_policy);
+        }
+        if self.strip_blank_lines {
+            result = collapse_blank_lines(&result);
+        }
+        // Numbered before summarizing, so the kept tail lines keep their numbers and the
+        // omission marker gets none.
+        if self.number_lines {
+            result = number_lines(&result);
+        }
+        if let Some(summary) = self.summarize {
+            result = summarize_lines(&result, summary);
+        }
+        if self.line_endings == LineEndings::Crlf {
+            result = result.replace('\n', "\r\n");
+        }
+        result
+    }
+}
+
+/// The line-comment marker for a language, if it has one we can strip safely.
+fn line_comment_marker(language: &str) -> Option<&'static str> {
+    match language {
+        "rust" | "javascript" | "typescript" | "go" | "java" | "c" | "cpp" => Some("//"),
+        "python" | "shell" | "toml" | "yaml" => Some("#"),
+        "sql" | "lua" => Some("--"),
+        _ => None,
+    }
+}
+
+/// Removes line comments from `content`.
+///
+/// This is deliberately conservative: markers inside single-line string literals are
+/// left alone, `#` only starts a comment at the beginning of a word, shebang lines are
+/// kept, and Python triple-quoted strings are never touched. Lines that consisted only
+/// of a comment are removed entirely.
+fn strip_line_comments(content: &str, language: &str) -> String {
+    let Some(marker) = line_comment_marker(language) else {
+        return content.to_string();
+    };
+
+    let mut output = String::with_capacity(content.len());
+    let mut in_triple_quote: Option<&str> = None;
+
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let (body, newline) = match line.strip_suffix('\n') {
+            Some(body) => (body, "\n"),
+            None => (line, ""),
+        };
+
+        if index == 0 && body.starts_with("#!") {
+            output.push_str(line);
+            continue;
+        }
+
+        if language == "python" {
+            if let Some(quote) = in_triple_quote {
+                if body.matches(quote).count() % 2 == 1 {
+                    in_triple_quote = None;
+                }
+                output.push_str(line);
+                continue;
+            }
+            let opened = ["\"\"\"", "'''"]
+                .into_iter()
+                .find(|quote| body.matches(quote).count() % 2 == 1);
+            if opened.is_some() {
+                in_triple_quote = opened;
+                output.push_str(line);
+                continue;
+            }
+        }
+
+        match find_comment_start(body, marker) {
+            Some(start) => {
+                let code = body[..start].trim_end();
+                if !code.is_empty() {
+                    output.push_str(code);
+                    output.push_str(newline);
+                }
+            }
+            None => output.push_str(line),
+        }
+    }
+    output
+}
+
+/// Finds the byte offset of the first line-comment marker outside of a string literal.
+fn find_comment_start(line: &str, marker: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut prev: Option<char> = None;
+
+    for (i, c) in line.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+        } else if c == '"' || c == '\'' || c == '`' {
+            quote = Some(c);
+        } else if line[i..].starts_with(marker)
+            && (marker != "#" || prev.is_none_or(char::is_whitespace))
+        {
+            return Some(i);
+        }
+        prev = Some(c);
+    }
+    None
+}
+
+/// Trims trailing spaces and tabs and rewrites leading indentation line by line. Line
+/// endings, including a `\r` before the `\n`, are preserved.
+fn rewrite_whitespace(content: &str, trim_trailing: bool, tab_policy: TabPolicy) -> String {
+    let mut output = String::with_capacity(content.len());
+
+    for line in content.split_inclusive('\n') {
+        let (body, newline) = match line.strip_suffix("\r\n") {
+            Some(body) => (body, "\r\n"),
+            None => match line.strip_suffix('\n') {
+                Some(body) => (body, "\n"),
+                None => (line, ""),
+            },
+        };
+        let body = if trim_trailing {
+            body.trim_end_matches([' ', '\t'])
+        } else {
+            body
+        };
+        let code = body.trim_start_matches([' ', '\t']);
+        let indent = &body[..body.len() - code.len()];
+        match tab_policy {
+            TabPolicy::TabsToSpaces(width) if width > 0 => {
+                let columns = indent_columns(indent, width as usize);
+                output.push_str(&" ".repeat(columns));
+            }
+            TabPolicy::SpacesToTabs(width) if width > 0 => {
+                let columns = indent_columns(indent, width as usize);
+                output.push_str(&"\t".repeat(columns / width as usize));
+                output.push_str(&" ".repeat(columns % width as usize));
+            }
+            _ => output.push_str(indent),
+        }
+        output.push_str(code);
+        output.push_str(newline);
+    }
+    output
+}
+
+/// The display width of an indentation made of spaces and tabs, with a tab stop every
+/// `width` columns.
+fn indent_columns(indent: &str, width: usize) -> usize {
+    indent.chars().fold(0, |columns, c| match c {
+        '\t' => columns + width - columns % width,
+        _ => columns + 1,
+    })
+}
+
+/// Collapses runs of whitespace-only lines into a single empty line.
+fn collapse_blank_lines(content: &str) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut previous_blank = false;
+
+    for line in content.split_inclusive('\n') {
+        let is_blank = line.trim().is_empty();
+        if is_blank {
+            if !previous_blank {
+                output.push('\n');
+            }
+        } else {
+            output.push_str(line);
+        }
+        previous_blank = is_blank;
+    }
+    output
+}
+
+/// Prefixes every line with its 1-based number, right-aligned to the width of the last
+/// number and followed by `| `, e.g. ` 9 | fn main() {`. Empty lines get no trailing
+/// space.
+fn number_lines(content: &str) -> String {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let width = lines.len().to_string().len();
+    let mut output = String::with_capacity(content.len() + lines.len() * (width + 3));
+
+    for (index, line) in lines.iter().enumerate() {
+        let number = index + 1;
+        if line.trim_end_matches(['\n', '\r']).is_empty() {
+            output.push_str(&format!("{number:>width$} |{line}"));
+        } else {
+            output.push_str(&format!("{number:>width$} | {line}"));
+        }
+    }
+    output
+}
+
+/// Keeps the first `head_lines` and last `tail_lines` lines of `content` if it has more
+/// than `over_lines` lines, replacing the rest with a `[... M lines omitted ...]` line.
+fn summarize_lines(content: &str, summary: LineSummary) -> String {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let kept = summary.head_lines.saturating_add(summary.tail_lines);
+    if lines.len() <= summary.over_lines || lines.len() <= kept {
+        return content.to_string();
+    }
+
+    let omitted = lines.len() - kept;
+    let mut output: String = lines[..summary.head_lines].concat();
+    output.push_str(&format!("[... {omitted} lines omitted ...]\n"));
+    output.push_str(&lines[lines.len() - summary.tail_lines..].concat());
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip_comments() -> ContentTransform {
+        ContentTransform {
+            strip_comments: true,
+            ..ContentTransform::default()
+        }
+    }
+
+    #[test]
+    fn strips_rust_line_comments_but_not_strings() {
+        let input = "// header\nfn main() { // entry\n    let url = \"http://example.com\";\n}\n";
+        let output = strip_comments().apply(input, "rust");
+        assert_eq!(
+            output,
+            "fn main() {\n    let url = \"http://example.com\";\n}\n"
+        );
+    }
+
+    #[test]
+    fn strips_python_comments_but_keeps_shebang_and_docstrings() {
+        let input = "#!/usr/bin/env python\n# comment\nx = 1  # trailing\ns = \"a # b\"\n\"\"\"\n# not a comment\n\"\"\"\n";
+        let output = strip_comments().apply(input, "python");
+        assert_eq!(
+            output,
+            "#!/usr/bin/env python\nx = 1\ns = \"a # b\"\n\"\"\"\n# not a comment\n\"\"\"\n"
+        );
+    }
+
+    #[test]
+    fn hash_inside_a_word_is_not_a_comment() {
+        let input = "echo ${#array[@]} $# # count\n";
+        let output = strip_comments().apply(input, "shell");
+        assert_eq!(output, "echo ${#array[@]} $#\n");
+    }
+
+    #[test]
+    fn unknown_languages_are_left_untouched() {
+        let input = "// not stripped\n";
+        assert_eq!(strip_comments().apply(input, "plaintext"), input);
+    }
+
+    #[test]
+    fn collapses_runs_of_blank_lines() {
+        let transform = ContentTransform {
+            strip_blank_lines: true,
+            ..ContentTransform::default()
+        };
+        let input = "a\n\n\n  \nb\n\nc";
+        assert_eq!(transform.apply(input, "plaintext"), "a\n\nb\n\nc");
+    }
+
+    #[test]
+    fn summarizes_files_over_the_line_threshold() {
+        let transform = ContentTransform {
+            summarize: Some(LineSummary {
+                over_lines: 5,
+                head_lines: 2,
+                tail_lines: 1,
+            }),
+            ..ContentTransform::default()
+        };
+        let input = "1\n2\n3\n4\n5\n6\n7";
+        assert_eq!(
+            transform.apply(input, "plaintext"),
+            "1\n2\n[... 4 lines omitted ...]\n7"
+        );
+        let short = "1\n2\n3\n4\n5\n";
+        assert_eq!(transform.apply(short, "plaintext"), short);
+    }
+
+    #[test]
+    fn converts_line_endings_in_both_directions() {
+        let mixed = "a\r\nb\nc\r\n";
+        let with = |line_endings| ContentTransform {
+            line_endings,
+            ..ContentTransform::default()
+        };
+        assert_eq!(with(LineEndings::Lf).apply(mixed, "plaintext"), "a\nb\nc\n");
+        assert_eq!(
+            with(LineEndings::Crlf).apply(mixed, "plaintext"),
+            "a\r\nb\r\nc\r\n"
+        );
+        assert!(with(LineEndings::AsIs).is_noop());
+    }
+
+    #[test]
+    fn trims_trailing_whitespace_but_keeps_line_endings() {
+        let transform = ContentTransform {
+            trim_trailing_whitespace: true,
+            ..ContentTransform::default()
+        };
+        let input = "a  \r\n\tb\t\n  \nc ";
+        assert_eq!(transform.apply(input, "plaintext"), "a\r\n\tb\n\nc");
+    }
+
+    #[test]
+    fn expands_indentation_tabs_to_the_next_tab_stop() {
+        let transform = ContentTransform {
+            tab_policy: TabPolicy::TabsToSpaces(4),
+            ..ContentTransform::default()
+        };
+        let input = "\tone\n\t\ttwo\n  \tmixed\nkeep\tinner\n";
+        assert_eq!(
+            transform.apply(input, "plaintext"),
+            "    one\n        two\n    mixed\nkeep\tinner\n"
+        );
+    }
+
+    #[test]
+    fn converts_indentation_spaces_to_tabs_keeping_the_remainder() {
+        let transform = ContentTransform {
+            tab_policy: TabPolicy::SpacesToTabs(4),
+            ..ContentTransform::default()
+        };
+        let input = "    one\n          two\n  \tmixed\nkeep    inner\n";
+        assert_eq!(
+            transform.apply(input, "plaintext"),
+            "\tone\n\t\t  two\n\tmixed\nkeep    inner\n"
+        );
+        let zero_width = ContentTransform {
+            tab_policy: TabPolicy::SpacesToTabs(0),
+            ..ContentTransform::default()
+        };
+        assert_eq!(zero_width.apply(input, "plaintext"), input);
+    }
+}
@@ -1,8 +1,52 @@
 //! Generates an ASCII representation of a directory tree.
 
-use super::FileItem;
+use super::{FileItem, IgnoreRules};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
-use std::path::{Path, PathBuf};
+use std::path::Path;
+
+/// The characters used to draw a directory tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TreeStyle {
+    /// Box-drawing connectors with folder and file icons.
+    #[default]
+    Unicode,
+    /// Plain `|--` and `\--` connectors; directories are marked with a trailing `/`.
+    Ascii,
+}
+
+/// Where the directory tree is placed in the generated output.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TreePosition {
+    /// In the header, before the first file.
+    #[default]
+    Top,
+    /// After the last file.
+    Bottom,
+    /// Not in the output at all.
+    None,
+}
+
+impl TreeStyle {
+    fn connector(self, is_last: bool) -> &'static str {
+        match (self, is_last) {
+            (TreeStyle::Unicode, true) => "└── ",
+            (TreeStyle::Unicode, false) => "├── ",
+            (TreeStyle::Ascii, true) => "\\-- ",
+            (TreeStyle::Ascii, false) => "|-- ",
+        }
+    }
+
+    fn indent(self, is_last: bool) -> &'static str {
+        match (self, is_last) {
+            (_, true) => "    ",
+            (TreeStyle::Unicode, false) => "│   ",
+            (TreeStyle::Ascii, false) => "|   ",
+        }
+    }
+}
 
 /// A utility struct for generating an ASCII directory tree.
 ///
@@ -18,30 +62,70 @@ impl TreeGenerator {
         root_path: &Path,
         ignore_patterns: &HashSet<String>,
     ) -> String {
-        // 1. Build a Matcher from the tree-specific ignore patterns.
-        let mut ignore_builder = ignore::gitignore::GitignoreBuilder::new(root_path);
-        for pattern in ignore_patterns {
-            ignore_builder.add_line(None, pattern).ok();
-        }
-        let matcher = match ignore_builder.build() {
-            Ok(m) => m,
-            Err(_) => return String::from("Error building tree ignore patterns."),
-        };
+        Self::generate_tree_with_style(files, root_path, ignore_patterns, TreeStyle::Unicode)
+    }
 
-        // 2. Filter the provided files to get the final list of items to render.
-        let filtered_files: Vec<&FileItem> = files
+    /// The number of entries `generate_tree` draws for `files`, i.e. those that neither
+    /// themselves nor through a parent directory match `ignore_patterns`.
+    pub fn count_entries(
+        files: &[FileItem],
+        root_path: &Path,
+        ignore_patterns: &HashSet<String>,
+    ) -> usize {
+        let rules = IgnoreRules::new(root_path, ignore_patterns);
+        files
             .iter()
-            .filter(|file| !matcher.matched(&file.path, file.is_directory).is_ignore())
-            .collect();
+            .filter(|file| !rules.is_ignored_or_any_parents(&file.path, file.is_directory))
+            .count()
+    }
 
-        // 3. Create a map from parent directory paths to their children.
-        let mut children_map: HashMap<PathBuf, Vec<&FileItem>> = HashMap::new();
-        for item in &filtered_files {
+    /// Like `generate_tree`, but draws the tree with the characters of `style`.
+    ///
+    /// Lists of `PARALLEL_THRESHOLD` or more entries are filtered, sorted and rendered
+    /// in parallel; the result is the same as on a single thread.
+    pub fn generate_tree_with_style(
+        files: &[FileItem],
+        root_path: &Path,
+        ignore_patterns: &HashSet<String>,
+        style: TreeStyle,
+    ) -> String {
+        let parallel = files.len() >= PARALLEL_THRESHOLD;
+        Self::render_tree(files, root_path, ignore_patterns, style, parallel)
+    }
+
+    fn render_tree(
+        files: &[FileItem],
+        root_path: &Path,
+        ignore_patterns: &HashSet<String>,
+        style: TreeStyle,
+        parallel: bool,
+    ) -> String {
+        // 1. Build the rules from the tree-specific ignore patterns.
+        let rules = IgnoreRules::new(root_path, ignore_patterns);
+        let is_shown = |file: &&FileItem| !rules.is_ignored(&file.path, file.is_directory);
+
+        // 2. Filter the provided files to get the final list of items to render.
+        let filtered_files: Vec<&FileItem> = if parallel {
+            files.par_iter().filter(is_shown).collect()
+        } else {
+            files.iter().filter(is_shown).collect()
+        };
+
+        // 3. Create a map from parent directory paths to their children, each list in
+        //    tree order.
+        let mut children_map: HashMap<&Path, Vec<&FileItem>> = HashMap::new();
+        for item in filtered_files {
             if let Some(parent) = item.path.parent() {
-                children_map
-                    .entry(parent.to_path_buf())
-                    .or_default()
-                    .push(item);
+                children_map.entry(parent).or_default().push(item);
+            }
+        }
+        if parallel {
+            children_map
+                .par_iter_mut()
+                .for_each(|(_, children)| children.sort_by(compare_tree_order));
+        } else {
+            for children in children_map.values_mut() {
+                children.sort_by(compare_tree_order);
             }
         }
 
@@ -53,7 +137,16 @@ impl TreeGenerator {
         ));
 
         // Start the recursive rendering from the root path.
-        Self::render_level(&mut result, root_path, &children_map, "");
+        if parallel {
+            result.push_str(&Self::render_level_parallel(
+                root_path,
+                &children_map,
+                "",
+                style,
+            ));
+        } else {
+            Self::render_level(&mut result, root_path, &children_map, "", style);
+        }
 
         result
     }
@@ -62,39 +155,81 @@ impl TreeGenerator {
     fn render_level(
         result: &mut String,
         parent_path: &Path,
-        children_map: &HashMap<PathBuf, Vec<&FileItem>>,
+        children_map: &HashMap<&Path, Vec<&FileItem>>,
         prefix: &str,
+        style: TreeStyle,
     ) {
         if let Some(children) = children_map.get(parent_path) {
-            let mut sorted_children = children.clone();
-            // Sort entries: directories first, then alphabetically by name.
-            sorted_children.sort_by(|a, b| {
-                a.is_directory
-                    .cmp(&b.is_directory)
-                    .reverse()
-                    .then_with(|| a.path.cmp(&b.path))
-            });
-
-            let last_index = sorted_children.len().saturating_sub(1);
-            for (i, item) in sorted_children.iter().enumerate() {
+            let last_index = children.len().saturating_sub(1);
+            for (i, item) in children.iter().enumerate() {
                 let is_last = i == last_index;
-                let connector = if is_last { "└── " } else { "├── " };
-                let icon = if item.is_directory { "📁 " } else { "📄 " };
-
-                let file_name = item.path.file_name().unwrap_or_default().to_string_lossy();
-                result.push_str(&format!("{prefix}{connector}{icon}{file_name}\n"));
+                result.push_str(&Self::entry_line(item, prefix, is_last, style));
 
                 if item.is_directory {
-                    let new_prefix = if is_last {
-                        format!("{prefix}    ")
-                    } else {
-                        format!("{prefix}│   ")
-                    };
-                    Self::render_level(result, &item.path, children_map, &new_prefix);
+                    let new_prefix = format!("{prefix}{}", style.indent(is_last));
+                    Self::render_level(result, &item.path, children_map, &new_prefix, style);
                 }
             }
         }
     }
+
+    /// Like `render_level`, but renders the subtrees of a level in parallel and joins
+    /// them in order.
+    fn render_level_parallel(
+        parent_path: &Path,
+        children_map: &HashMap<&Path, Vec<&FileItem>>,
+        prefix: &str,
+        style: TreeStyle,
+    ) -> String {
+        let Some(children) = children_map.get(parent_path) else {
+            return String::new();
+        };
+        let last_index = children.len().saturating_sub(1);
+        children
+            .par_iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let is_last = i == last_index;
+                let mut block = Self::entry_line(item, prefix, is_last, style);
+                if item.is_directory {
+                    let new_prefix = format!("{prefix}{}", style.indent(is_last));
+                    block.push_str(&Self::render_level_parallel(
+                        &item.path,
+                        children_map,
+                        &new_prefix,
+                        style,
+                    ));
+                }
+                block
+            })
+            .collect::<Vec<String>>()
+            .concat()
+    }
+
+    /// The line drawn for a single entry, including its connector.
+    fn entry_line(item: &FileItem, prefix: &str, is_last: bool, style: TreeStyle) -> String {
+        let connector = style.connector(is_last);
+        let file_name = item.path.file_name().unwrap_or_default().to_string_lossy();
+        let line = match (style, item.is_directory) {
+            (TreeStyle::Unicode, true) => format!("📁 {file_name}"),
+            (TreeStyle::Unicode, false) => format!("📄 {file_name}"),
+            (TreeStyle::Ascii, true) => format!("{file_name}/"),
+            (TreeStyle::Ascii, false) => file_name.to_string(),
+        };
+        format!("{prefix}{connector}{line}\n")
+    }
+}
+
+/// Inputs with at least this many entries are rendered in parallel. Below it, the
+/// overhead of splitting the work outweighs the gain.
+const PARALLEL_THRESHOLD: usize = 5_000;
+
+/// Orders siblings in the tree: directories first, then alphabetically by name.
+fn compare_tree_order(a: &&FileItem, b: &&FileItem) -> Ordering {
+    a.is_directory
+        .cmp(&b.is_directory)
+        .reverse()
+        .then_with(|| a.path.cmp(&b.path))
 }
 
 // The tests you already added. No changes needed here.
@@ -114,6 +249,9 @@ mod tests {
             size: if is_dir { 0 } else { 123 },
             depth: path.split('/').count(),
             parent: Path::new(path).parent().map(|p| p.to_path_buf()),
+            mtime: None,
+            likely_generated: false,
+            git_status: None,
         }
     }
 
@@ -133,6 +271,31 @@ mod tests {
         insta::assert_snapshot!(tree_output);
     }
 
+    #[test]
+    fn test_unicode_and_ascii_glyph_sets() {
+        let root_path = Path::new("/project");
+        let files = vec![
+            create_item("/project/src", true),
+            create_item("/project/src/main.rs", false),
+            create_item("/project/src/lib.rs", false),
+            create_item("/project/README.md", false),
+        ];
+        let render = |style| {
+            TreeGenerator::generate_tree_with_style(&files, root_path, &HashSet::new(), style)
+        };
+
+        assert_eq!(
+            render(TreeStyle::Unicode),
+            "project/\n├── 📁 src\n│   ├── 📄 lib.rs\n│   └── 📄 main.rs\n└── 📄 README.md\n"
+        );
+        let ascii = render(TreeStyle::Ascii);
+        assert_eq!(
+            ascii,
+            "project/\n|-- src/\n|   |-- lib.rs\n|   \\-- main.rs\n\\-- README.md\n"
+        );
+        assert!(ascii.is_ascii());
+    }
+
     #[test]
     fn test_tree_with_ignored_files() {
         let root_path = Path::new("/project");
@@ -189,4 +352,41 @@ mod tests {
 
         insta::assert_snapshot!(tree_output);
     }
+
+    #[test]
+    fn parallel_rendering_matches_serial_rendering_for_a_large_tree() {
+        let root_path = Path::new("/big");
+        let mut files = Vec::new();
+        for a in 0..20 {
+            let dir_a = format!("/big/dir_{a:02}");
+            files.push(create_item(&dir_a, true));
+            for b in 0..10 {
+                let dir_b = format!("{dir_a}/sub_{b}");
+                files.push(create_item(&dir_b, true));
+                for f in 0..99 {
+                    let ext = if f % 7 == 0 { "log" } else { "rs" };
+                    files.push(create_item(&format!("{dir_b}/file_{f:03}.{ext}"), false));
+                }
+            }
+            files.push(create_item(&format!("{dir_a}/top.rs"), false));
+        }
+        assert!(files.len() >= 20_000);
+        // Reverse the input so sorting actually has to reorder the entries.
+        files.reverse();
+        let ignore_patterns = HashSet::from(["*.log".to_string()]);
+
+        for style in [TreeStyle::Unicode, TreeStyle::Ascii] {
+            let serial =
+                TreeGenerator::render_tree(&files, root_path, &ignore_patterns, style, false);
+            let parallel =
+                TreeGenerator::render_tree(&files, root_path, &ignore_patterns, style, true);
+
+            assert_eq!(parallel, serial);
+            assert_eq!(
+                TreeGenerator::generate_tree_with_style(&files, root_path, &ignore_patterns, style),
+                serial
+            );
+            assert!(!serial.contains(".log"));
+        }
+    }
 }
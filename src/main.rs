@@ -1,6 +1,10 @@
-use context_file_concat::app;
+use context_file_concat::app::clipboard::SystemClipboardService;
 use context_file_concat::app::file_dialog::NativeDialogService;
+use context_file_concat::app::{self, lock_state};
 use context_file_concat::config;
+#[cfg(target_os = "macos")]
+use context_file_concat::platform;
+use context_file_concat::utils::logging;
 use std::sync::{Arc, Mutex};
 use tao::{
     event::{Event, StartCause, WindowEvent},
@@ -9,22 +13,19 @@ use tao::{
 };
 use wry::WebViewBuilder;
 
-// Keep platform quirks isolated.
-mod platform;
-
 // Embedded UI-Assets – only compiled in Release
 #[cfg(not(debug_assertions))]
 mod web_assets;
 
 #[tokio::main]
 async fn main() {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
+    let initial_config = app::state::AppState::default().config;
+
+    // Initialize logging. The guard flushes the log file on exit.
+    let _log_guard = logging::init(&initial_config.log_level);
 
     // Create the event loop and window
     let event_loop = EventLoopBuilder::<app::events::UserEvent>::with_user_event().build();
-
-    let initial_config = app::state::AppState::default().config;
     let (width, height) = initial_config.window_size;
     let (pos_x, pos_y) = initial_config.window_position;
 
@@ -46,15 +47,18 @@ async fn main() {
     let proxy = event_loop.create_proxy();
     let state = Arc::new(Mutex::new(app::state::AppState::default()));
     let dialog_service = Arc::new(NativeDialogService {});
+    let clipboard_service = Arc::new(SystemClipboardService);
 
     // To avoid code duplication, we define the handlers first.
     let ipc_handler_state = state.clone();
     let ipc_handler_proxy = proxy.clone();
     let ipc_handler_dialog = dialog_service.clone();
+    let ipc_handler_clipboard = clipboard_service.clone();
     let ipc_handler = move |message: String| {
         app::handle_ipc_message(
             message,
             ipc_handler_dialog.clone(),
+            ipc_handler_clipboard.clone(),
             ipc_handler_proxy.clone(),
             ipc_handler_state.clone(),
         );
@@ -161,6 +165,9 @@ async fn main() {
         .with_file_drop_handler(file_drop_handler)
         .build()
         .expect("Failed to build WebView");
+    if let Err(e) = webview.zoom(initial_config.ui_zoom) {
+        tracing::warn!("Failed to apply the saved UI zoom: {}", e);
+    }
 
     let state_for_events = state.clone();
     let window_for_events = window.clone();
@@ -175,11 +182,12 @@ async fn main() {
             Event::WindowEvent { event, .. } => match event {
                 WindowEvent::CloseRequested => {
                     tracing::info!("Close requested. Saving final window state...");
-                    let mut state_guard = state_for_events.lock().unwrap();
+                    let mut state_guard = lock_state(&state_for_events);
                     let size = window_for_events.inner_size();
                     let position = window_for_events.outer_position().unwrap_or_default();
                     state_guard.config.window_size = (size.width.into(), size.height.into());
                     state_guard.config.window_position = (position.x.into(), position.y.into());
+                    app::remember_session(&state_guard);
 
                     if let Err(e) = config::settings::save_config(&state_guard.config, None) {
                         tracing::error!("Failed to save config on exit: {}", e);
@@ -187,11 +195,11 @@ async fn main() {
                     *control_flow = ControlFlow::Exit;
                 }
                 WindowEvent::Resized(size) => {
-                    let mut state_guard = state_for_events.lock().unwrap();
+                    let mut state_guard = lock_state(&state_for_events);
                     state_guard.config.window_size = (size.width.into(), size.height.into());
                 }
                 WindowEvent::Moved(position) => {
-                    let mut state_guard = state_for_events.lock().unwrap();
+                    let mut state_guard = lock_state(&state_for_events);
                     state_guard.config.window_position = (position.x.into(), position.y.into());
                 }
                 _ => (),
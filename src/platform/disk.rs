@@ -0,0 +1,55 @@
+//! Free-space queries for the volumes outputs are saved to.
+
+use std::io;
+use std::path::Path;
+
+/// Returns the bytes available to the current user on the volume that holds `path`.
+///
+/// `path` does not have to exist yet: the nearest existing ancestor is queried, so the
+/// target of a save can be passed before the file is created.
+pub fn available_space(path: &Path) -> io::Result<u64> {
+    let existing = path
+        .ancestors()
+        .find(|ancestor| !ancestor.as_os_str().is_empty() && ancestor.exists())
+        .unwrap_or(Path::new("."));
+    fs2::available_space(existing)
+}
+
+/// Fails with a `StorageFull` error if `needed` bytes do not fit into `available`.
+pub fn ensure_space(needed: u64, available: u64) -> io::Result<()> {
+    if needed <= available {
+        return Ok(());
+    }
+    Err(io::Error::new(
+        io::ErrorKind::StorageFull,
+        format!("not enough disk space: needs {needed} bytes, has {available} bytes"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn available_space_is_reported_for_a_path_that_does_not_exist_yet() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("not/yet/created.txt");
+
+        assert_eq!(
+            available_space(&target).unwrap(),
+            available_space(dir.path()).unwrap()
+        );
+    }
+
+    #[test]
+    fn ensure_space_reports_needed_and_available_bytes() {
+        assert!(ensure_space(10, 10).is_ok());
+
+        let err = ensure_space(2048, 1024).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::StorageFull);
+        assert_eq!(
+            err.to_string(),
+            "not enough disk space: needs 2048 bytes, has 1024 bytes"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! Opens files in the user's editor of choice.
+
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// Opens `path` in the editor named by `$VISUAL` or `$EDITOR`, or with the platform's
+/// default handler if neither is set. On macOS the fallback is `open -t`, which picks
+/// the default text editor instead of the handler registered for the file type.
+///
+/// The editor is spawned without waiting for it to exit. The path is passed as a
+/// separate argument and never goes through a shell.
+pub fn open_in_editor(path: &Path) -> io::Result<()> {
+    let editor = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(std::env::var_os)
+        .find_map(|value| editor_command(&value.to_string_lossy(), path));
+    if let Some(mut command) = editor {
+        return command.spawn().map(|_| ());
+    }
+    open_with_default_handler(path)
+}
+
+/// Builds the command for an editor setting such as `code --wait`. The setting is split
+/// on whitespace into the program and its leading arguments; `path` is appended last.
+/// Returns `None` for a blank setting.
+fn editor_command(editor: &str, path: &Path) -> Option<Command> {
+    let mut parts = editor.split_whitespace();
+    let mut command = Command::new(parts.next()?);
+    command.args(parts).arg(path);
+    Some(command)
+}
+
+#[cfg(target_os = "macos")]
+fn open_with_default_handler(path: &Path) -> io::Result<()> {
+    Command::new("open").arg("-t").arg(path).spawn().map(|_| ())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn open_with_default_handler(path: &Path) -> io::Result<()> {
+    open::that_detached(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsString;
+
+    /// The program and arguments of a command, for inspecting what would be run.
+    fn command_line(command: &Command) -> Vec<OsString> {
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|part| part.to_os_string())
+            .collect()
+    }
+
+    #[test]
+    fn editor_arguments_are_kept_and_the_path_is_passed_verbatim() {
+        let path = Path::new("/repo/a file; rm -rf ~.txt");
+        let command = editor_command("code --wait", path).unwrap();
+
+        assert_eq!(
+            command_line(&command),
+            vec![
+                OsString::from("code"),
+                OsString::from("--wait"),
+                OsString::from("/repo/a file; rm -rf ~.txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_blank_editor_setting_is_ignored() {
+        assert!(editor_command("   ", Path::new("/repo/main.rs")).is_none());
+    }
+}
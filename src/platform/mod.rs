@@ -2,5 +2,8 @@
 //!
 //! Keep OS quirks here to avoid leaking them into the app's core logic.
 
+pub mod disk;
+pub mod editor;
+
 #[cfg(target_os = "macos")]
 pub mod macos;
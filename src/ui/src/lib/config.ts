@@ -1,6 +1,16 @@
 export const MONACO_VS_PATH =
   "https://cdn.jsdelivr.net/npm/monaco-editor@0.52.2/min/vs";
 
+/** Ignore presets known to the backend, keyed by the name sent to `applyIgnorePreset`. */
+export const IGNORE_PRESETS = {
+  rust: "Rust",
+  node: "Node",
+  python: "Python",
+  go: "Go",
+} as const;
+
+export type IgnorePresetName = keyof typeof IGNORE_PRESETS;
+
 export const COMMON_IGNORE_PATTERNS = [
   "node_modules",
   "venv",
@@ -105,23 +105,91 @@ register("en", {
   "action.selectDirectory": "Select Directory",
   "action.importConfig": "Import Config",
   "action.exportConfig": "Export Config",
+  "action.resetConfig": "Reset Config",
+  "action.resetConfigConfirm": "Click again to reset",
+  "action.resetConfigHint":
+    "Restore the default settings. The current directory is closed.",
+  "action.saveProfile": "Save Profile",
+  "action.deleteProfile": "Delete Profile",
+  "profile.select": "Profile",
+  "profile.none": "No profile",
+  "profile.ph.name": "Profile name",
   "action.add": "Add",
   "action.clear": "Clear",
   "action.generate": "Generate",
   "action.saveToFile": "Save to File",
+  "action.quickSave": "Quick Save",
   "action.noDirectorySelected": "No Directory selected",
 
   // ===== Sidebar =====
   "sidebar.title": "Search & Filter",
   "sidebar.ph.selectDirFirst": "Select a directory first...",
   "sidebar.caseSensitive": "Case Sensitive",
+  "sidebar.sortOrder": "Sort by",
+  "sidebar.sort.nameAsc": "Name (A–Z)",
+  "sidebar.sort.nameDesc": "Name (Z–A)",
+  "sidebar.sort.sizeDesc": "Size (largest first)",
+  "sidebar.sort.modifiedDesc": "Modified (newest first)",
+  "sidebar.autoSelect": "Select after scan",
+  "sidebar.autoSelectHint":
+    "Files selected when a full scan completes; binary and large files are skipped",
+  "sidebar.autoSelect.none": "Nothing",
+  "sidebar.autoSelect.allText": "All text files",
+  "sidebar.autoSelect.fromPreset": "Matching patterns",
+  "sidebar.ph.autoSelectPatterns": "Patterns (src/, *.md)",
+  "sidebar.rememberSelection": "Remember selection",
+  "sidebar.rememberSelectionHint":
+    "Restore the selection and expanded folders when this directory is opened again",
+  "sidebar.extensionOverrides": "Text / binary extensions",
+  "sidebar.extensionOverridesHint":
+    "Classify these extensions without inspecting the content; takes effect on the next scan",
+  "sidebar.ph.textExtensions": "Always text (graphql, prisma)",
+  "sidebar.ph.forceBinaryExtensions": "Always binary (pdf)",
+  "sidebar.ph.languageOverrides": "Languages (tf=terraform, jinja=jinja)",
+  "sidebar.languageOverridesHint":
+    "Language tags for code fences, file headers and preview highlighting",
+  "sidebar.sizeRange": "File size range (bytes)",
+  "sidebar.ph.minBytes": "Min bytes",
+  "sidebar.ph.maxBytes": "Max bytes",
+  "sidebar.ph.modifiedWithinDays": "Modified within (days)",
+  "sidebar.modifiedWithinDaysHint":
+    "Show only files changed in the last N days. Leave empty to show all.",
 
   "sidebar.ignoreTitle": "Ignore Patterns",
   "sidebar.res": "Re-Scan",
   "sidebar.rescan": "Re-Scan",
   "sidebar.ph.addPattern": "Add pattern (*.log, build/)",
+  "sidebar.ph.explainHidden": "Path to explain (src/gen.rs)",
+  "sidebar.explainHidden": "Why hidden?",
   "sidebar.removeAll": "Delete All",
+  "sidebar.applyPreset": "Add preset...",
   "sidebar.removeEmptyDirs": "Remove empty dirs",
+  "sidebar.excludeEmptyFiles": "Exclude empty files",
+  "sidebar.skipGenerated": "Skip generated files",
+  "sidebar.skipGeneratedHint":
+    "Leave minified or generated files out of bulk selections",
+  "sidebar.patternWouldHide": "This pattern would hide {count} file(s)",
+  "sidebar.includeHidden": "Show hidden files",
+  "sidebar.includeHiddenHint":
+    "Include dotfiles such as .env.example or .github/ (requires a re-scan)",
+  "sidebar.showGitStatus": "Show git status",
+  "sidebar.showGitStatusHint":
+    "Mark modified, added and untracked files in the tree (requires a re-scan)",
+  "sidebar.onlyGitTracked": "Only git-tracked files",
+  "sidebar.onlyGitTrackedHint":
+    "Hide files git does not track, regardless of ignore patterns (requires a re-scan)",
+  "sidebar.respectExportIgnore": "Respect export-ignore",
+  "sidebar.respectExportIgnoreHint":
+    "Hide paths .gitattributes marks export-ignore, like git archive (requires a re-scan)",
+  "sidebar.excludeLockfiles": "Exclude lockfiles",
+  "sidebar.excludeLockfilesHint":
+    "Hide *.lock, package-lock.json and Cargo.lock without adding ignore patterns",
+  "sidebar.excludeVendored": "Exclude vendored code",
+  "sidebar.excludeVendoredHint":
+    "Hide vendor/, node_modules/ and third_party/ without adding ignore patterns",
+  "sidebar.reportBrokenSymlinks": "Report broken symlinks",
+  "sidebar.reportBrokenSymlinksHint":
+    "List the symlinks whose target is missing after a scan; they are always skipped",
   "sidebar.ph.filterAssigned": "Filter currently assigned ignore patterns...",
 
   // ===== Filetree placeholder =====
@@ -133,6 +201,12 @@ register("en", {
   "preview.selectAFile": "Select a file to preview",
   "preview.copy": "Copy",
   "preview.clear": "Clear",
+  "preview.openInEditor": "Open in editor",
+  "preview.collapseOthers": "Collapse others",
+  "preview.collapseOthersHint":
+    "Collapse the tree down to the path of this file",
+  "preview.prevMatch": "Previous match",
+  "preview.nextMatch": "Next match",
   "preview.generated": "Preview generated",
 
   // ===== Status / Common =====
@@ -141,10 +215,89 @@ register("en", {
   // ===== Footer / Output =====
   "action.browse": "Choose Dir",
   "action.cancel": "Cancel",
+  "action.close": "Close",
   "status.dir": "Directory",
   "status.filename": "Filename",
+  "status.filenameHint":
+    "{project}, {date} and {time} are replaced when the file is saved",
   "status.includeTree": "Include File Tree",
   "status.relativePaths": "Relative File Paths",
+  "status.includeManifest": "Include Manifest",
+  "status.includeIndex": "Include Index",
+  "status.includeIndexHint":
+    "List the files with their bytes, lines and tokens before the first one",
+  "status.asciiTree": "ASCII tree",
+  "status.exportTree": "Export Tree",
+  "status.exportTreeHint": "Save the directory tree of the current view to a file",
+  "status.stripComments": "Strip Comments",
+  "status.stripBlankLines": "Collapse Blank Lines",
+  "status.trimTrailingWhitespace": "Trim Trailing Whitespace",
+  "status.numberLines": "Number Lines",
+  "status.includeFileMode": "File Modes",
+  "status.includeFileModeHint":
+    "Show each file's permissions, e.g. mode: 100755, in its header",
+  "status.includeChecksums": "Checksums",
+  "status.includeChecksumsHint":
+    "Show each file's SHA-256, e.g. sha256: 9f86d0…, in its header",
+  "status.redactSecrets": "Redact Secrets",
+  "status.redactSecretsHint":
+    "Replace API keys, tokens and .env secrets with [REDACTED]",
+  "status.autoIncludeImports": "Include Referenced Files",
+  "status.autoIncludeImportsHint":
+    "Before generating, select small files the selection names by a quoted relative path",
+  "status.tabPolicy": "Indentation",
+  "status.tabPolicy.asIs": "As is",
+  "status.tabPolicy.tabsToSpaces": "Tabs to spaces",
+  "status.tabPolicy.spacesToTabs": "Spaces to tabs",
+  "status.tabWidth": "Tab width",
+  "status.logLevel": "Log Level",
+  "status.logLevelHint": "Takes effect on the next start",
+  "status.showLogPath": "Show Log Location",
+  "status.skipUnreadable": "Skip Unreadable Files",
+  "status.skipUnreadableHint":
+    "Note read errors in the output instead of aborting the generation",
+  "status.streamGeneration": "Low-Memory Generation",
+  "status.streamGenerationHint":
+    "Keep the output in a temporary file and preview only its head",
+  "status.maxThreads": "Max Threads",
+  "status.ph.maxThreads": "All cores",
+  "status.maxThreadsHint":
+    "Limits CPU use and concurrent file reads for content search and filtering",
+  "status.previewMaxLines": "Preview Lines",
+  "status.previewMaxLinesHint": "Lines shown when previewing a file",
+  "status.generatedHeadLines": "Output Preview Lines",
+  "status.ph.generatedHeadLines": "All",
+  "status.generatedHeadLinesHint":
+    "Preview only the first lines of large outputs; save and copy still use the full output",
+  "status.filterDebounce": "Filter Delay (ms)",
+  "status.filterDebounceHint":
+    "How long the name and extension filters wait for typing to pause (0 = apply immediately)",
+  "status.summarizeOver": "Summarize over",
+  "status.ph.summarizeOver": "Never",
+  "status.summarizeHint":
+    "Files with more lines than this keep only their first and last lines",
+  "status.summaryHead": "Head",
+  "status.summaryTail": "Tail",
+  "status.outputFormat": "Format",
+  "status.format.plain": "Plain text",
+  "status.format.html": "HTML",
+  "status.format.xml": "XML",
+  "status.lineEndings": "Line Endings",
+  "status.lineEndings.asIs": "As is",
+  "status.uiZoom": "UI Zoom",
+  "status.theme": "Theme",
+  "status.theme.system": "System",
+  "status.theme.light": "Light",
+  "status.theme.dark": "Dark",
+  "status.concatOrder": "File order",
+  "status.concatOrder.tree": "Tree order",
+  "status.concatOrder.extension": "By extension, then name",
+  "status.concatOrder.sizeAsc": "Smallest first",
+  "status.concatOrder.sizeDesc": "Largest first",
+  "status.treePosition": "Where the directory tree is placed in the output",
+  "status.treePosition.top": "At the top",
+  "status.treePosition.bottom": "At the bottom",
+  "status.treePosition.none": "Omitted",
 
   // ------- Legacy/earlier keys we still ship -------
   "preview.title": "Preview",
@@ -159,12 +312,20 @@ register("en", {
   "filetree.large_skipped": "{count} large files skipped",
   "filetree.selectAll": "Select all",
   "filetree.deselectAll": "Deselect all",
+  "filetree.importSelection": "Import list",
+  "filetree.importSelectionHint":
+    "Select the files in an exported list or a text file with one path per line",
+  "filetree.exportSelection": "Export list",
+  "filetree.exportSelectionHint":
+    "Save the selected files to a JSON file that can be imported again",
   "filetree.expandAll": "Expand all",
   "filetree.collapseAll": "Collapse all",
+  "filetree.moreChildren": "… and {count} more",
   "filetree.stats.files": "Files selected",
   "filetree.stats.selectedOf": "of total files",
   "filetree.stats.folders": "Folders",
   "stats.readOnly": "read only",
+  "stats.truncated": "showing first {shown} of {total} lines",
   "stats.editable": "editable",
   "header.select_directory": "Select Directory",
   "header.scanning": "Scanning...",
@@ -182,12 +343,52 @@ register("en", {
   "sidebar.ignore.remove_empty_dirs": "Remove empty dirs",
   "sidebar.ignore.common_label": "Common Ignore Pattern:",
   "sidebar.ph.searchFileNames": "Search for File names",
+  "sidebar.ph.searchFileGlob": "Glob, e.g. src/**/*.rs",
+  "sidebar.filenameMatchMode": "How the file name search matches",
+  "sidebar.match.substring": "Name contains",
+  "sidebar.match.glob": "Path glob",
   "sidebar.ph.extensionFilter": "Search for File Types",
   "sidebar.ph.contentSearch": "Search inside Files",
+  "sidebar.contentSearchSummary": "{files} files, {matches} matches",
+  "sidebar.contentSearchScope": "Files to search",
+  "sidebar.scope.allFiles": "All files",
+  "sidebar.scope.selectedOnly": "Selected files",
+  "sidebar.scope.visibleOnly": "Visible files",
+  "sidebar.relevance": "Minimum relevance",
+  "sidebar.minMatches": "Minimum matches per file",
+  "sidebar.minMatchesPerKb": "Minimum matches per KB (0 = off)",
+  "sidebar.resultsLimited": "Results limited.",
+  "sidebar.showMoreResults": "Show more",
+  "sidebar.contentSearchResults": "Matching lines",
+  "sidebar.revealInTree": "Show in tree",
   "footer.generate": "Generate",
   "footer.concat": "Concat{dots}",
   "footer.cancel": "Cancel",
   "footer.save": "Save to File",
+  "footer.estimate": "Estimate Tokens",
+  "footer.estimating": "Estimating…",
+  "footer.quickSaveHint": "Save to {path} without asking",
+  "footer.copyPaths": "Copy Paths",
+  "footer.generateToClipboard": "Generate & Copy",
+  "footer.generateToClipboardHint":
+    "Generate the output and copy it to the clipboard without showing it",
+  "footer.compare": "Compare",
+  "footer.plan": "Dry run",
+  "footer.planHint":
+    "List the files a generation would include, without reading them",
+  "footer.stats": "Stats",
+  "footer.statsHint":
+    "Files, size and estimated tokens per extension. Shift-click to count tokens exactly",
+  "footer.compareHint": "Show what changed since the previous generation",
+  "footer.copyPathsHint": "Copy the paths of the selected files, one per line",
+  "footer.confirmLarge": "{files} files ({size}) selected. Generate anyway?",
+  "footer.generateAnyway": "Generate anyway",
+  "footer.history": "History",
+  "footer.historyHint": "Reopen one of the recent generations",
+  "footer.historyEmpty": "Nothing generated yet.",
+  "footer.historyEntry": "{time}: {files} files, {tokens} tokens ({size})",
+  "footer.historySaved": "Saved to {path}",
+  "footer.historyDropped": "Only the newest outputs are kept to reopen",
   "toast.copied": "Copied to clipboard",
   "toast.copy_failed": "Failed to copy to clipboard",
   "toast.pasted": "Pasted content",
@@ -195,6 +396,24 @@ register("en", {
   "toast.paste_empty": "Clipboard is empty",
   "toast.cut_ok": "Cut to clipboard",
   "toast.cut_failed": "Cut failed",
+  "toast.large_files_skipped":
+    "Skipped {count} file(s) larger than {limit} MB",
+  "toast.broken_symlinks": "Skipped {count} broken symlink(s)",
+  "toast.token_estimate": "Estimated ~{tokens} tokens for {files} file(s)",
+  "toast.output_copied": "Copied the output ({tokens} tokens) to the clipboard",
+  "toast.log_path": "Logs are written to {path}",
+  "toast.path_shown": "{path} is shown in the tree",
+  "toast.path_hidden": "{path} is hidden: {reasons}",
+  "toast.output_filename_corrected": "Output filename changed to {name}",
+  "toast.log_path_unavailable": "No log directory is available on this system",
+  "toast.generation_plan": "{files} files ({size}) would be generated",
+  "toast.tree_stats": "{files} files, {size}, {tokens} tokens in the tree",
+  "toast.auto_included": "Added {count} referenced file(s) to the selection",
+  "toast.config_valid": "{name} is a valid config",
+  "toast.config_invalid": "{name} cannot be imported: {count} issue(s)",
+  "toast.config_warnings": "{name} can be imported, with {count} warning(s)",
+  "toast.generation_diff":
+    "Since the previous generation: {added} added, {removed} removed, {changed} changed, {tokens} tokens",
   "status.ready": "Status: Ready.",
   "status.save_cancelled": "Status: Save cancelled.",
   "status.saved_to": "Status: Saved to {path}",
@@ -207,23 +426,91 @@ register("de", {
   "action.selectDirectory": "Verzeichnis wählen",
   "action.importConfig": "Konfiguration import",
   "action.exportConfig": "Konfiguration export",
+  "action.resetConfig": "Konfiguration zurücksetzen",
+  "action.resetConfigConfirm": "Zum Zurücksetzen erneut klicken",
+  "action.resetConfigHint":
+    "Stellt die Standardeinstellungen wieder her. Das aktuelle Verzeichnis wird geschlossen.",
+  "action.saveProfile": "Profil speichern",
+  "action.deleteProfile": "Profil löschen",
+  "profile.select": "Profil",
+  "profile.none": "Kein Profil",
+  "profile.ph.name": "Profilname",
   "action.add": "Hinzufügen",
   "action.clear": "Bereinigen",
   "action.generate": "Erzeugen",
   "action.saveToFile": "In Datei speichern",
+  "action.quickSave": "Schnell speichern",
   "action.noDirectorySelected": "Kein Verzeichnis ausgewählt",
 
   // ===== Sidebar =====
   "sidebar.title": "Suche & Filter",
   "sidebar.ph.selectDirFirst": "Zuerst ein Verzeichnis wählen...",
   "sidebar.caseSensitive": "Groß-/Kleinschreibung beachten",
+  "sidebar.sortOrder": "Sortieren nach",
+  "sidebar.sort.nameAsc": "Name (A–Z)",
+  "sidebar.sort.nameDesc": "Name (Z–A)",
+  "sidebar.sort.sizeDesc": "Größe (größte zuerst)",
+  "sidebar.sort.modifiedDesc": "Geändert (neueste zuerst)",
+  "sidebar.autoSelect": "Nach dem Scan auswählen",
+  "sidebar.autoSelectHint":
+    "Dateien, die nach einem vollständigen Scan ausgewählt werden; Binär- und große Dateien werden übersprungen",
+  "sidebar.autoSelect.none": "Nichts",
+  "sidebar.autoSelect.allText": "Alle Textdateien",
+  "sidebar.autoSelect.fromPreset": "Passende Muster",
+  "sidebar.ph.autoSelectPatterns": "Muster (src/, *.md)",
+  "sidebar.rememberSelection": "Auswahl merken",
+  "sidebar.rememberSelectionHint":
+    "Auswahl und geöffnete Ordner wiederherstellen, wenn dieses Verzeichnis erneut geöffnet wird",
+  "sidebar.extensionOverrides": "Text-/Binär-Endungen",
+  "sidebar.extensionOverridesHint":
+    "Diese Endungen ohne Prüfung des Inhalts einordnen; wirkt ab dem nächsten Scan",
+  "sidebar.ph.textExtensions": "Immer Text (graphql, prisma)",
+  "sidebar.ph.forceBinaryExtensions": "Immer binär (pdf)",
+  "sidebar.ph.languageOverrides": "Sprachen (tf=terraform, jinja=jinja)",
+  "sidebar.languageOverridesHint":
+    "Sprachkennungen für Codeblöcke, Dateiköpfe und die Syntaxhervorhebung der Vorschau",
+  "sidebar.sizeRange": "Dateigrößenbereich (Bytes)",
+  "sidebar.ph.minBytes": "Min. Bytes",
+  "sidebar.ph.maxBytes": "Max. Bytes",
+  "sidebar.ph.modifiedWithinDays": "Geändert innerhalb (Tage)",
+  "sidebar.modifiedWithinDaysHint":
+    "Nur Dateien anzeigen, die in den letzten N Tagen geändert wurden. Leer lassen, um alle anzuzeigen.",
 
   "sidebar.ignoreTitle": "Ignore-Muster",
   "sidebar.res": "Neu scannen",
   "sidebar.rescan": "Neu scannen",
   "sidebar.ph.addPattern": "Muster hinzufügen (*.log, build/)",
+  "sidebar.ph.explainHidden": "Zu erklärender Pfad (src/gen.rs)",
+  "sidebar.explainHidden": "Warum ausgeblendet?",
   "sidebar.removeAll": "Alle löschen",
+  "sidebar.applyPreset": "Vorlage hinzufügen...",
   "sidebar.removeEmptyDirs": "Leere Ordner entfernen",
+  "sidebar.excludeEmptyFiles": "Leere Dateien ausschließen",
+  "sidebar.skipGenerated": "Generierte Dateien überspringen",
+  "sidebar.skipGeneratedHint":
+    "Minifizierte oder generierte Dateien bei Mehrfachauswahl auslassen",
+  "sidebar.patternWouldHide": "Dieses Muster würde {count} Datei(en) ausblenden",
+  "sidebar.includeHidden": "Versteckte Dateien anzeigen",
+  "sidebar.includeHiddenHint":
+    "Dotfiles wie .env.example oder .github/ einbeziehen (erfordert neuen Scan)",
+  "sidebar.showGitStatus": "Git-Status anzeigen",
+  "sidebar.showGitStatusHint":
+    "Geänderte, neue und unversionierte Dateien im Baum markieren (erfordert neuen Scan)",
+  "sidebar.onlyGitTracked": "Nur von git verfolgte Dateien",
+  "sidebar.onlyGitTrackedHint":
+    "Von git nicht verfolgte Dateien unabhängig von Ignore-Mustern ausblenden (erfordert neuen Scan)",
+  "sidebar.respectExportIgnore": "export-ignore beachten",
+  "sidebar.respectExportIgnoreHint":
+    "In .gitattributes als export-ignore markierte Pfade wie bei git archive ausblenden (erfordert neuen Scan)",
+  "sidebar.excludeLockfiles": "Lockfiles ausschließen",
+  "sidebar.excludeLockfilesHint":
+    "*.lock, package-lock.json und Cargo.lock ausblenden, ohne Ignore-Muster hinzuzufügen",
+  "sidebar.excludeVendored": "Vendored Code ausschließen",
+  "sidebar.excludeVendoredHint":
+    "vendor/, node_modules/ und third_party/ ausblenden, ohne Ignore-Muster hinzuzufügen",
+  "sidebar.reportBrokenSymlinks": "Defekte Symlinks melden",
+  "sidebar.reportBrokenSymlinksHint":
+    "Listet nach einem Scan die Symlinks auf, deren Ziel fehlt; sie werden immer übersprungen",
   "sidebar.ph.filterAssigned": "Zugewiesene Ignore-Muster filtern...",
 
   // ===== Filetree placeholder =====
@@ -236,6 +523,12 @@ register("de", {
   "preview.selectAFile": "Wähle eine Datei zur Vorschau",
   "preview.copy": "Kopieren",
   "preview.clear": "Löschen",
+  "preview.openInEditor": "Im Editor öffnen",
+  "preview.collapseOthers": "Andere einklappen",
+  "preview.collapseOthersHint":
+    "Den Baum bis auf den Pfad zu dieser Datei einklappen",
+  "preview.prevMatch": "Vorheriger Treffer",
+  "preview.nextMatch": "Nächster Treffer",
   "preview.generated": "Vorschau erstellt",
 
   // ===== Status / Common =====
@@ -244,10 +537,90 @@ register("de", {
   // ===== Footer / Output =====
   "action.browse": "Auswählen",
   "action.cancel": "Abbrechen",
+  "action.close": "Schließen",
   "status.dir": "Verzeichnis",
   "status.filename": "Dateiname",
+  "status.filenameHint":
+    "{project}, {date} und {time} werden beim Speichern ersetzt",
   "status.includeTree": "File Tree addieren",
   "status.relativePaths": "Relative Dateipfade",
+  "status.includeManifest": "Manifest einfügen",
+  "status.includeIndex": "Index einfügen",
+  "status.includeIndexHint":
+    "Die Dateien mit Bytes, Zeilen und Tokens vor der ersten auflisten",
+  "status.asciiTree": "ASCII-Baum",
+  "status.exportTree": "Baum exportieren",
+  "status.exportTreeHint":
+    "Verzeichnisbaum der aktuellen Ansicht in eine Datei speichern",
+  "status.stripComments": "Kommentare entfernen",
+  "status.stripBlankLines": "Leerzeilen zusammenfassen",
+  "status.trimTrailingWhitespace": "Leerzeichen am Zeilenende entfernen",
+  "status.numberLines": "Zeilen nummerieren",
+  "status.includeFileMode": "Dateimodi",
+  "status.includeFileModeHint":
+    "Zeigt die Berechtigungen jeder Datei, z. B. mode: 100755, in ihrem Kopf",
+  "status.includeChecksums": "Prüfsummen",
+  "status.includeChecksumsHint":
+    "Zeigt den SHA-256 jeder Datei, z. B. sha256: 9f86d0…, in ihrem Kopf",
+  "status.redactSecrets": "Geheimnisse schwärzen",
+  "status.redactSecretsHint":
+    "API-Schlüssel, Tokens und .env-Geheimnisse durch [REDACTED] ersetzen",
+  "status.autoIncludeImports": "Referenzierte Dateien einbeziehen",
+  "status.autoIncludeImportsHint":
+    "Vor dem Erzeugen kleine Dateien auswählen, die die Auswahl über einen relativen Pfad in Anführungszeichen nennt",
+  "status.tabPolicy": "Einrückung",
+  "status.tabPolicy.asIs": "Unverändert",
+  "status.tabPolicy.tabsToSpaces": "Tabs zu Leerzeichen",
+  "status.tabPolicy.spacesToTabs": "Leerzeichen zu Tabs",
+  "status.tabWidth": "Tabbreite",
+  "status.logLevel": "Protokollstufe",
+  "status.logLevelHint": "Wird beim nächsten Start wirksam",
+  "status.showLogPath": "Protokollort anzeigen",
+  "status.skipUnreadable": "Unlesbare Dateien überspringen",
+  "status.skipUnreadableHint":
+    "Lesefehler in der Ausgabe vermerken, statt die Erzeugung abzubrechen",
+  "status.streamGeneration": "Speichersparende Erzeugung",
+  "status.streamGenerationHint":
+    "Die Ausgabe in einer temporären Datei halten und nur ihren Anfang zeigen",
+  "status.maxThreads": "Max. Threads",
+  "status.ph.maxThreads": "Alle Kerne",
+  "status.maxThreadsHint":
+    "Begrenzt CPU-Last und gleichzeitige Dateilesevorgänge bei Inhaltssuche und Filterung",
+  "status.previewMaxLines": "Vorschauzeilen",
+  "status.previewMaxLinesHint": "Angezeigte Zeilen in der Dateivorschau",
+  "status.generatedHeadLines": "Ausgabe-Vorschauzeilen",
+  "status.ph.generatedHeadLines": "Alle",
+  "status.generatedHeadLinesHint":
+    "Bei großen Ausgaben nur die ersten Zeilen anzeigen; Speichern und Kopieren nutzen weiterhin die vollständige Ausgabe",
+  "status.filterDebounce": "Filterverzögerung (ms)",
+  "status.filterDebounceHint":
+    "Wie lange Namens- und Endungsfilter auf eine Tipppause warten (0 = sofort anwenden)",
+  "status.summarizeOver": "Kürzen ab",
+  "status.ph.summarizeOver": "Nie",
+  "status.summarizeHint":
+    "Dateien mit mehr Zeilen behalten nur ihre ersten und letzten Zeilen",
+  "status.summaryHead": "Anfang",
+  "status.summaryTail": "Ende",
+  "status.outputFormat": "Format",
+  "status.format.plain": "Klartext",
+  "status.format.html": "HTML",
+  "status.format.xml": "XML",
+  "status.lineEndings": "Zeilenenden",
+  "status.lineEndings.asIs": "Unverändert",
+  "status.uiZoom": "UI-Zoom",
+  "status.theme": "Design",
+  "status.theme.system": "System",
+  "status.theme.light": "Hell",
+  "status.theme.dark": "Dunkel",
+  "status.concatOrder": "Dateireihenfolge",
+  "status.concatOrder.tree": "Baumreihenfolge",
+  "status.concatOrder.extension": "Nach Endung, dann Name",
+  "status.concatOrder.sizeAsc": "Kleinste zuerst",
+  "status.concatOrder.sizeDesc": "Größte zuerst",
+  "status.treePosition": "Position des Verzeichnisbaums in der Ausgabe",
+  "status.treePosition.top": "Oben",
+  "status.treePosition.bottom": "Unten",
+  "status.treePosition.none": "Weggelassen",
 
   // ------- Legacy/earlier keys we still ship -------
   "preview.title": "Vorschau",
@@ -262,12 +635,20 @@ register("de", {
   "filetree.large_skipped": "{count} große Dateien übersprungen",
   "filetree.selectAll": "Alles auswählen",
   "filetree.deselectAll": "Alles abwählen",
+  "filetree.importSelection": "Liste importieren",
+  "filetree.importSelectionHint":
+    "Die Dateien einer exportierten Liste oder einer Textdatei mit einem Pfad pro Zeile auswählen",
+  "filetree.exportSelection": "Liste exportieren",
+  "filetree.exportSelectionHint":
+    "Die ausgewählten Dateien in einer JSON-Datei speichern, die wieder importiert werden kann",
   "filetree.expandAll": "Aufklappen",
   "filetree.collapseAll": "Einklappen",
+  "filetree.moreChildren": "… und {count} weitere",
   "filetree.stats.files": "Dateien selektiert",
   "filetree.stats.selectedOf": "von gesamt Dateien",
   "filetree.stats.folders": "Verzeichnisse",
   "stats.readOnly": "read only",
+  "stats.truncated": "erste {shown} von {total} Zeilen",
   "stats.editable": "editierbar",
   "header.select_directory": "Verzeichnis wählen",
   "header.scanning": "Scanne...",
@@ -285,12 +666,56 @@ register("de", {
   "sidebar.ignore.remove_empty_dirs": "Leere Ordner entfernen",
   "sidebar.ignore.common_label": "Gängiges Ignore-Muster:",
   "sidebar.ph.searchFileNames": "Suche nach Dateinamen",
+  "sidebar.ph.searchFileGlob": "Glob, z. B. src/**/*.rs",
+  "sidebar.filenameMatchMode": "Wie die Dateinamensuche vergleicht",
+  "sidebar.match.substring": "Name enthält",
+  "sidebar.match.glob": "Pfad-Glob",
   "sidebar.ph.extensionFilter": "Suche nach Dateitypen",
   "sidebar.ph.contentSearch": "Suche innerhalb Dateien",
+  "sidebar.contentSearchSummary": "{files} Dateien, {matches} Treffer",
+  "sidebar.contentSearchScope": "Zu durchsuchende Dateien",
+  "sidebar.scope.allFiles": "Alle Dateien",
+  "sidebar.scope.selectedOnly": "Ausgewählte Dateien",
+  "sidebar.scope.visibleOnly": "Sichtbare Dateien",
+  "sidebar.relevance": "Mindestrelevanz",
+  "sidebar.minMatches": "Mindestanzahl Treffer pro Datei",
+  "sidebar.minMatchesPerKb": "Mindestanzahl Treffer pro KB (0 = aus)",
+  "sidebar.resultsLimited": "Ergebnisse begrenzt.",
+  "sidebar.showMoreResults": "Mehr anzeigen",
+  "sidebar.contentSearchResults": "Gefundene Zeilen",
+  "sidebar.revealInTree": "Im Baum zeigen",
   "footer.generate": "Erzeugen",
   "footer.concat": "Concat{dots}",
   "footer.cancel": "Abbrechen",
   "footer.save": "In Datei speichern",
+  "footer.estimate": "Tokens schätzen",
+  "footer.estimating": "Schätze…",
+  "footer.quickSaveHint": "Ohne Nachfrage in {path} speichern",
+  "footer.copyPaths": "Pfade kopieren",
+  "footer.generateToClipboard": "Erzeugen & kopieren",
+  "footer.generateToClipboardHint":
+    "Die Ausgabe erzeugen und in die Zwischenablage kopieren, ohne sie anzuzeigen",
+  "footer.compare": "Vergleichen",
+  "footer.plan": "Probelauf",
+  "footer.planHint":
+    "Dateien auflisten, die eine Erzeugung enthielte, ohne sie zu lesen",
+  "footer.stats": "Statistik",
+  "footer.statsHint":
+    "Dateien, Größe und geschätzte Tokens je Endung. Umschalt-Klick zählt Tokens exakt",
+  "footer.compareHint":
+    "Zeigen, was sich seit der vorigen Erzeugung geändert hat",
+  "footer.copyPathsHint":
+    "Die Pfade der ausgewählten Dateien kopieren, einer pro Zeile",
+  "footer.confirmLarge":
+    "{files} Dateien ({size}) ausgewählt. Trotzdem erzeugen?",
+  "footer.generateAnyway": "Trotzdem erzeugen",
+  "footer.history": "Verlauf",
+  "footer.historyHint": "Eine der letzten Erzeugungen erneut öffnen",
+  "footer.historyEmpty": "Noch nichts erzeugt.",
+  "footer.historyEntry": "{time}: {files} Dateien, {tokens} Tokens ({size})",
+  "footer.historySaved": "Gespeichert unter {path}",
+  "footer.historyDropped":
+    "Nur die neuesten Ausgaben werden zum erneuten Öffnen behalten",
   "toast.copied": "In Zwischenablage kopiert",
   "toast.copy_failed": "Kopieren fehlgeschlagen",
   "toast.pasted": "Inhalt eingefügt",
@@ -298,6 +723,26 @@ register("de", {
   "toast.paste_empty": "Zwischenablage ist leer",
   "toast.cut_ok": "Inhalt ausgeschnitten",
   "toast.cut_failed": "Ausschneiden fehlgeschlagen",
+  "toast.large_files_skipped":
+    "{count} Datei(en) größer als {limit} MB übersprungen",
+  "toast.broken_symlinks": "{count} defekte(n) Symlink(s) übersprungen",
+  "toast.token_estimate": "Geschätzt ~{tokens} Tokens für {files} Datei(en)",
+  "toast.output_copied":
+    "Ausgabe ({tokens} Tokens) in die Zwischenablage kopiert",
+  "toast.log_path": "Protokolle werden in {path} geschrieben",
+  "toast.path_shown": "{path} wird im Baum angezeigt",
+  "toast.path_hidden": "{path} ist ausgeblendet: {reasons}",
+  "toast.output_filename_corrected": "Ausgabedateiname geändert zu {name}",
+  "toast.log_path_unavailable":
+    "Auf diesem System ist kein Protokollverzeichnis verfügbar",
+  "toast.generation_plan": "{files} Dateien ({size}) würden erzeugt",
+  "toast.tree_stats": "{files} Dateien, {size}, {tokens} Tokens im Baum",
+  "toast.auto_included": "{count} referenzierte Datei(en) zur Auswahl hinzugefügt",
+  "toast.config_valid": "{name} ist eine gültige Konfiguration",
+  "toast.config_invalid": "{name} kann nicht importiert werden: {count} Problem(e)",
+  "toast.config_warnings": "{name} kann importiert werden, mit {count} Warnung(en)",
+  "toast.generation_diff":
+    "Seit der vorigen Erzeugung: {added} hinzugefügt, {removed} entfernt, {changed} geändert, {tokens} Tokens",
   "status.ready": "Status: Bereit.",
   "status.save_cancelled": "Status: Speichern abgebrochen.",
   "status.saved_to": "Status: Gespeichert unter {path}",
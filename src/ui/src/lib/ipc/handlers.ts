@@ -5,8 +5,18 @@
  * - Idempotent install
  */
 
-import { appState, getState } from "$lib/stores/app";
+import {
+  appState,
+  contentSearchResults,
+  generationHistory,
+  getState,
+  ignorePreview,
+  pendingLargeGeneration,
+  profiles,
+} from "$lib/stores/app";
 import { toast } from "$lib/stores/toast";
+import { applyThemePreference } from "$lib/stores/theme";
+import { formatFileSize } from "$lib/utils";
 import { t as tStore } from "$lib/i18n";
 import { get } from "svelte/store";
 
@@ -15,9 +25,29 @@ import {
   ScanProgressSchema,
   ShowPreviewArgsSchema,
   ShowGeneratedArgsSchema,
+  GenerationChunkSchema,
+  GenerationEndSchema,
+  OutputCopiedSchema,
   StatusMessageSchema,
   FileSaveStatusArgsSchema,
   DragStateSchema,
+  LargeFilesSkippedArgsSchema,
+  ProfileListSchema,
+  LogPathSchema,
+  OutputFilenameSetArgsSchema,
+  ScrollToLineSchema,
+  TokenEstimateArgsSchema,
+  ContentSearchResultsArgsSchema,
+  IgnorePreviewArgsSchema,
+  HiddenExplanationArgsSchema,
+  ConfirmLargeGenerationArgsSchema,
+  GenerationDiffSchema,
+  GenerationPlanSchema,
+  TreeStatsSchema,
+  AutoIncludedSchema,
+  ConfigValidationArgsSchema,
+  BrokenSymlinksSchema,
+  GenerationHistorySchema,
 } from "$lib/ipc/schema";
 
 import {
@@ -28,10 +58,21 @@ import {
 import {
   showPreviewContent as showPreviewContentImpl,
   showGeneratedContent as showGeneratedContentImpl,
+  scrollToLine as scrollToLineImpl,
   clearPreview,
 } from "$lib/modules/editor";
 
-import type { AppState } from "$lib/types";
+import type {
+  AppState,
+  ConfigIssue,
+  ContentSearchFile,
+  GenerationDiff,
+  GenerationPlan,
+  GenerationRecord,
+  PreviewTruncation,
+  TreeStats,
+  UiTheme,
+} from "$lib/types";
 
 declare global {
   interface Window {
@@ -45,13 +86,42 @@ declare global {
       content: string,
       language: string,
       searchTerm: string | null | undefined,
-      path: string
+      path: string,
+      truncation?: PreviewTruncation | null
     ) => void;
     showGeneratedContent: (content: string, tokenCount: number) => void;
+    beginGeneratedContent: () => void;
+    appendGeneratedContent: (chunk: string) => void;
+    endGeneratedContent: (tokenCount: number) => void;
     showError: (msg: string) => void;
     showStatus: (msg: string) => void;
     fileSaveStatus: (success: boolean, path: string) => void;
     setDragState: (isDragging: boolean) => void;
+    showLargeFilesSkipped: (
+      count: number,
+      names: string[],
+      maxFileSizeMb: number
+    ) => void;
+    showProfiles: (names: string[]) => void;
+    showLogPath: (path: string | null) => void;
+    outputFilenameSet: (filename: string, corrected: boolean) => void;
+    scrollToLine: (line: number) => void;
+    showTokenEstimate: (tokens: number, files: number) => void;
+    outputCopied: (tokenCount: number) => void;
+    showContentSearchResults: (
+      query: string,
+      files: ContentSearchFile[]
+    ) => void;
+    showIgnorePreview: (pattern: string, matches: string[]) => void;
+    showHiddenExplanation: (path: string, reasons: string[]) => void;
+    confirmLargeGeneration: (bytes: number, files: number) => void;
+    showGenerationDiff: (diff: GenerationDiff) => void;
+    showGenerationPlan: (plan: GenerationPlan) => void;
+    showTreeStats: (stats: TreeStats) => void;
+    showAutoIncluded: (files: string[]) => void;
+    showConfigValidation: (path: string, issues: ConfigIssue[]) => void;
+    showBrokenSymlinks: (paths: string[]) => void;
+    showGenerationHistory: (records: GenerationRecord[]) => void;
     __APP_READY?: boolean;
   }
 }
@@ -67,6 +137,7 @@ export function installWindowIPCHandlers(): void {
   }
 
   let lastPath: string | null = null;
+  let lastTheme: UiTheme | null = null;
 
   window.render = (incoming: AppState) => {
     const parsed = UiStateSchema.safeParse(incoming);
@@ -102,7 +173,12 @@ export function installWindowIPCHandlers(): void {
 
       if (prev.current_path && !patched.current_path) clearPreview();
 
+      // The saved theme wins over the one remembered by the browser storage.
+      const nextTheme = patched.config?.theme ?? "System";
+      if (lastTheme !== nextTheme) applyThemePreference(nextTheme);
+
       lastPath = nextPath;
+      lastTheme = nextTheme;
     } catch (err) {
       console.error("render() failed:", err);
       appState.update((s) => {
@@ -168,13 +244,15 @@ export function installWindowIPCHandlers(): void {
     content: string,
     language: string,
     searchTerm: string | null | undefined,
-    path: string
+    path: string,
+    truncation?: PreviewTruncation | null
   ) => {
     const parsed = ShowPreviewArgsSchema.safeParse([
       content,
       language,
       searchTerm,
       path,
+      truncation,
     ]);
     if (!parsed.success) {
       console.warn(
@@ -183,8 +261,8 @@ export function installWindowIPCHandlers(): void {
       );
       return;
     }
-    const [c, l, s, p] = parsed.data;
-    showPreviewContentImpl(c, l, s ?? "", p);
+    const [c, l, s, p, tr] = parsed.data;
+    showPreviewContentImpl(c, l, s ?? "", p, tr ?? null);
   };
 
   window.showGeneratedContent = (content: string, tokenCount: number) => {
@@ -200,6 +278,33 @@ export function installWindowIPCHandlers(): void {
     showGeneratedContentImpl(c, t);
   };
 
+  // Large outputs arrive in pieces; they are shown once the stream ends.
+  let generatedChunks: string[] | null = null;
+
+  window.beginGeneratedContent = () => {
+    generatedChunks = [];
+  };
+
+  window.appendGeneratedContent = (chunk: string) => {
+    const parsed = GenerationChunkSchema.safeParse(chunk);
+    if (!parsed.success || !generatedChunks) {
+      console.warn("[IPC] Ignored appendGeneratedContent() outside a stream");
+      return;
+    }
+    generatedChunks.push(parsed.data);
+  };
+
+  window.endGeneratedContent = (tokenCount: number) => {
+    const parsed = GenerationEndSchema.safeParse(tokenCount);
+    const chunks = generatedChunks;
+    generatedChunks = null;
+    if (!parsed.success || !chunks) {
+      console.warn("[IPC] Ignored endGeneratedContent() outside a stream");
+      return;
+    }
+    showGeneratedContentImpl(chunks.join(""), parsed.data);
+  };
+
   window.showError = (msg: string) => {
     const parsed = StatusMessageSchema.safeParse(msg);
     if (!parsed.success) {
@@ -278,4 +383,385 @@ export function installWindowIPCHandlers(): void {
     if (parsed.data) container?.classList.add("drag-over");
     else container?.classList.remove("drag-over");
   };
+
+  window.showLargeFilesSkipped = (
+    count: number,
+    names: string[],
+    maxFileSizeMb: number
+  ) => {
+    const parsed = LargeFilesSkippedArgsSchema.safeParse([
+      count,
+      names,
+      maxFileSizeMb,
+    ]);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid showLargeFilesSkipped() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    const [n, files, limit] = parsed.data;
+    if (n === 0) return;
+
+    const preview = files.slice(0, 5).join(", ");
+    const more = files.length > 5 ? ` (+${files.length - 5} more)` : "";
+    appState.update((s: AppState) => {
+      s.status_message = `Status: Skipped ${n} file(s) larger than ${limit} MB: ${preview}${more}`;
+      return s;
+    });
+
+    const tr = get(tStore);
+    toast.warning(tr("toast.large_files_skipped", { count: n, limit }));
+  };
+
+  window.showProfiles = (names: string[]) => {
+    const parsed = ProfileListSchema.safeParse(names);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid showProfiles() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    profiles.set(parsed.data);
+  };
+
+  window.showLogPath = (path: string | null) => {
+    const parsed = LogPathSchema.safeParse(path);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid showLogPath() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    const tr = get(tStore);
+    if (parsed.data === null) {
+      toast.warning(tr("toast.log_path_unavailable"));
+      return;
+    }
+    const message = tr("toast.log_path", { path: parsed.data });
+    appState.update((s: AppState) => {
+      s.status_message = `Status: ${message}`;
+      return s;
+    });
+    toast.info(message);
+  };
+
+  window.outputFilenameSet = (filename: string, corrected: boolean) => {
+    const parsed = OutputFilenameSetArgsSchema.safeParse([filename, corrected]);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid outputFilenameSet() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    const [name, wasCorrected] = parsed.data;
+    appState.update((s: AppState) => {
+      s.config.output_filename = name;
+      return s;
+    });
+    if (wasCorrected) {
+      toast.info(get(tStore)("toast.output_filename_corrected", { name }));
+    }
+  };
+
+  window.scrollToLine = (line: number) => {
+    const parsed = ScrollToLineSchema.safeParse(line);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid scrollToLine() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    scrollToLineImpl(parsed.data);
+  };
+
+  window.showTokenEstimate = (tokens: number, files: number) => {
+    const parsed = TokenEstimateArgsSchema.safeParse([tokens, files]);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid showTokenEstimate() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    const [n, count] = parsed.data;
+    const tr = get(tStore);
+    const message = tr("toast.token_estimate", {
+      tokens: n.toLocaleString(),
+      files: count,
+    });
+    appState.update((s: AppState) => {
+      s.status_message = `Status: ${message}`;
+      return s;
+    });
+    toast.info(message);
+  };
+
+  window.outputCopied = (tokenCount: number) => {
+    const parsed = OutputCopiedSchema.safeParse(tokenCount);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid outputCopied() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    const tr = get(tStore);
+    const message = tr("toast.output_copied", {
+      tokens: parsed.data.toLocaleString(),
+    });
+    appState.update((s: AppState) => {
+      s.status_message = `Status: ${message}`;
+      return s;
+    });
+    toast.success(message);
+  };
+
+  window.showContentSearchResults = (
+    query: string,
+    files: ContentSearchFile[]
+  ) => {
+    const parsed = ContentSearchResultsArgsSchema.safeParse([query, files]);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid showContentSearchResults() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    const [q, results] = parsed.data;
+    contentSearchResults.set(q ? results : []);
+  };
+
+  window.showIgnorePreview = (pattern: string, matches: string[]) => {
+    const parsed = IgnorePreviewArgsSchema.safeParse([pattern, matches]);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid showIgnorePreview() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    const [p, files] = parsed.data;
+    ignorePreview.set({ pattern: p, matches: files });
+  };
+
+  window.showHiddenExplanation = (path: string, reasons: string[]) => {
+    const parsed = HiddenExplanationArgsSchema.safeParse([path, reasons]);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid showHiddenExplanation() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    const [p, why] = parsed.data;
+    const tr = get(tStore);
+    if (why.length === 0) {
+      toast.info(tr("toast.path_shown", { path: p }));
+      return;
+    }
+    toast.warning(
+      tr("toast.path_hidden", { path: p, reasons: why.join("; ") })
+    );
+  };
+
+  window.confirmLargeGeneration = (bytes: number, files: number) => {
+    const parsed = ConfirmLargeGenerationArgsSchema.safeParse([bytes, files]);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid confirmLargeGeneration() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    const [size, count] = parsed.data;
+    pendingLargeGeneration.set({ bytes: size, files: count });
+  };
+
+  window.showGenerationDiff = (diff: GenerationDiff) => {
+    const parsed = GenerationDiffSchema.safeParse(diff);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid showGenerationDiff() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    const { added, removed, changed, token_delta } = parsed.data;
+    const list = (label: string, files: string[]) =>
+      files.length > 0 ? ` ${label}: ${files.join(", ")}.` : "";
+    const delta = token_delta > 0 ? `+${token_delta}` : `${token_delta}`;
+    appState.update((s: AppState) => {
+      s.status_message =
+        `Status: ${delta} tokens since the previous generation.` +
+        list("Added", added) +
+        list("Removed", removed) +
+        list("Changed", changed);
+      return s;
+    });
+
+    const tr = get(tStore);
+    toast.info(
+      tr("toast.generation_diff", {
+        added: added.length,
+        removed: removed.length,
+        changed: changed.length,
+        tokens: delta,
+      })
+    );
+  };
+
+  window.showGenerationPlan = (plan: GenerationPlan) => {
+    const parsed = GenerationPlanSchema.safeParse(plan);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid showGenerationPlan() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    const { files, total_size, tree_entries } = parsed.data;
+    const tree = tree_entries === null ? "" : ` Tree: ${tree_entries} entries.`;
+    appState.update((s: AppState) => {
+      s.status_message =
+        `Status: ${files.length} files (${formatFileSize(total_size)}) ` +
+        `would be generated.${tree}` +
+        (files.length > 0 ? ` Files: ${files.join(", ")}.` : "");
+      return s;
+    });
+
+    const tr = get(tStore);
+    toast.info(
+      tr("toast.generation_plan", {
+        files: files.length,
+        size: formatFileSize(total_size),
+      })
+    );
+  };
+
+  window.showTreeStats = (stats: TreeStats) => {
+    const parsed = TreeStatsSchema.safeParse(stats);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid showTreeStats() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    const { extensions, total_files, total_size, total_tokens, exact_tokens } =
+      parsed.data;
+    const approx = exact_tokens ? "" : "~";
+    const byExtension = extensions
+      .map(
+        (ext) =>
+          `${ext.extension ? `.${ext.extension}` : "(none)"} ${ext.files} ` +
+          `(${formatFileSize(ext.size)}, ${approx}${ext.tokens.toLocaleString()} tokens)`
+      )
+      .join(", ");
+    appState.update((s: AppState) => {
+      s.status_message =
+        `Status: ${total_files} files, ${formatFileSize(total_size)}, ` +
+        `${approx}${total_tokens.toLocaleString()} tokens.` +
+        (byExtension ? ` By extension: ${byExtension}.` : "");
+      return s;
+    });
+
+    const tr = get(tStore);
+    toast.info(
+      tr("toast.tree_stats", {
+        files: total_files,
+        size: formatFileSize(total_size),
+        tokens: `${approx}${total_tokens.toLocaleString()}`,
+      })
+    );
+  };
+
+  window.showAutoIncluded = (files: string[]) => {
+    const parsed = AutoIncludedSchema.safeParse(files);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid showAutoIncluded() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    appState.update((s: AppState) => {
+      s.status_message = `Status: Auto-included ${parsed.data.join(", ")}.`;
+      return s;
+    });
+
+    const tr = get(tStore);
+    toast.info(tr("toast.auto_included", { count: parsed.data.length }));
+  };
+
+  window.showConfigValidation = (path: string, issues: ConfigIssue[]) => {
+    const parsed = ConfigValidationArgsSchema.safeParse([path, issues]);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid showConfigValidation() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    const [file, found] = parsed.data;
+    const name = file.split(/[\\/]/).pop() || file;
+    appState.update((s: AppState) => {
+      s.status_message =
+        found.length === 0
+          ? `Status: ${name} is a valid config.`
+          : `Status: ${name}: ${found.map((i) => i.message).join(" ")}`;
+      return s;
+    });
+
+    const tr = get(tStore);
+    const count = found.length;
+    if (count === 0) {
+      toast.success(tr("toast.config_valid", { name }));
+    } else if (found.some((i) => i.severity === "error")) {
+      toast.error(tr("toast.config_invalid", { name, count }));
+    } else {
+      toast.warning(tr("toast.config_warnings", { name, count }));
+    }
+  };
+
+  window.showBrokenSymlinks = (paths: string[]) => {
+    const parsed = BrokenSymlinksSchema.safeParse(paths);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid showBrokenSymlinks() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    const links = parsed.data;
+    if (links.length === 0) return;
+
+    const preview = links.slice(0, 5).join(", ");
+    const more = links.length > 5 ? ` (+${links.length - 5} more)` : "";
+    appState.update((s: AppState) => {
+      s.status_message = `Status: Skipped ${links.length} broken symlink(s): ${preview}${more}`;
+      return s;
+    });
+
+    const tr = get(tStore);
+    toast.warning(tr("toast.broken_symlinks", { count: links.length }));
+  };
+
+  window.showGenerationHistory = (records: GenerationRecord[]) => {
+    const parsed = GenerationHistorySchema.safeParse(records);
+    if (!parsed.success) {
+      console.warn(
+        "[IPC] Ignored invalid showGenerationHistory() payload:",
+        parsed.error.flatten()
+      );
+      return;
+    }
+    generationHistory.set(parsed.data);
+  };
 }
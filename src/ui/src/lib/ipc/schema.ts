@@ -3,11 +3,20 @@
 
 import { z } from "zod";
 import type { TreeNode } from "../types";
+import { IGNORE_PRESETS, type IgnorePresetName } from "../config";
 
 /* ---------------------------------- Common --------------------------------- */
 export const PathString = z.string().min(1);
 /** (number, number) tuple */
 const Tuple2 = z.tuple([z.number(), z.number()]);
+/** Which files a content search reads. */
+const ContentSearchScopeSchema = z.enum([
+  "AllFiles",
+  "SelectedOnly",
+  "VisibleOnly",
+]);
+/** How the filename query is matched. */
+const FilenameMatchModeSchema = z.enum(["Substring", "Glob"]);
 
 /* --------------------------------- Config ---------------------------------- */
 export const ConfigSchema = z
@@ -19,13 +28,101 @@ export const ConfigSchema = z
     output_filename: z.string(),
     case_sensitive_search: z.boolean(),
     include_tree_by_default: z.boolean(),
+    tree_position: z.enum(["Top", "Bottom", "None"]).default("Top"),
     use_relative_paths: z.boolean(),
     remove_empty_directories: z.boolean(),
+    exclude_empty_files: z.boolean().default(false),
     window_size: Tuple2,
     window_position: Tuple2,
+    ui_zoom: z.number().positive().default(1),
+    theme: z.enum(["System", "Light", "Dark"]).default("System"),
     auto_load_last_directory: z.boolean(),
     max_file_size_mb: z.number(),
+    min_file_size_bytes: z.number().int().nonnegative().nullable().optional(),
+    max_file_size_bytes: z.number().int().nonnegative().nullable().optional(),
+    modified_within_days: z.number().int().nonnegative().nullable().optional(),
     scan_chunk_size: z.number(),
+    max_threads: z.number().int().positive().nullable().optional(),
+    strip_comments: z.boolean().default(false),
+    strip_blank_lines: z.boolean().default(false),
+    summarize_over_lines: z.number().int().positive().nullable().optional(),
+    summary_head_lines: z.number().int().nonnegative().default(50),
+    summary_tail_lines: z.number().int().nonnegative().default(20),
+    normalize_line_endings: z.enum(["AsIs", "Lf", "Crlf"]).default("AsIs"),
+    trim_trailing_whitespace: z.boolean().default(false),
+    number_lines_in_output: z.boolean().default(false),
+    include_file_mode: z.boolean().default(false),
+    include_checksums: z.boolean().default(false),
+    enable_redaction: z.boolean().default(false),
+    redaction_patterns: z.array(z.string()).default([]),
+    tab_policy: z
+      .union([
+        z.literal("AsIs"),
+        z.object({ TabsToSpaces: z.number().int().min(0).max(255) }),
+        z.object({ SpacesToTabs: z.number().int().min(0).max(255) }),
+      ])
+      .default("AsIs"),
+    sort_order: z
+      .enum(["NameAsc", "NameDesc", "SizeDesc", "ModifiedDesc"])
+      .default("NameAsc"),
+    concat_order: z
+      .enum(["TreeOrder", "ExtensionThenName", "SizeAsc", "SizeDesc"])
+      .default("TreeOrder"),
+    output_format: z.enum(["PlainText", "Html", "Xml"]).default("PlainText"),
+    include_manifest: z.boolean().default(false),
+    include_index: z.boolean().default(false),
+    stream_generated_content: z.boolean().default(true),
+    stream_generation: z.boolean().default(false),
+    skip_generated: z.boolean().default(true),
+    auto_include_imports: z.boolean().default(false),
+    skip_unreadable_files: z.boolean().default(false),
+    warn_over_bytes: z.number().int().nonnegative().nullable().optional(),
+    split_output_bytes: z.number().int().positive().nullable().optional(),
+    chunk_header_template: z
+      .string()
+      .default("// Part {part} of {total} — continues {root}"),
+    content_search_context_lines: z
+      .number()
+      .int()
+      .nonnegative()
+      .nullable()
+      .default(2),
+    search_read_buffer_kb: z.number().int().nonnegative().default(64),
+    max_search_results: z.number().int().nonnegative().default(300),
+    preview_max_lines: z.number().int().positive().default(1500),
+    max_children_displayed: z
+      .number()
+      .int()
+      .positive()
+      .nullable()
+      .default(1000),
+    preview_generated_head_lines: z
+      .number()
+      .int()
+      .positive()
+      .nullable()
+      .default(null),
+    filter_debounce_ms: z.number().int().nonnegative().default(120),
+    show_git_status: z.boolean().default(false),
+    only_git_tracked: z.boolean().default(false),
+    respect_export_ignore: z.boolean().default(false),
+    exclude_lockfiles: z.boolean().default(false),
+    exclude_vendored: z.boolean().default(false),
+    report_broken_symlinks: z.boolean().default(false),
+    auto_select_on_scan: z
+      .enum(["None", "AllText", "FromPreset"])
+      .default("None"),
+    auto_select_patterns: z.array(z.string()).default([]),
+    text_extensions: z.array(z.string()).default([]),
+    force_binary_extensions: z.array(z.string()).default([]),
+    language_overrides: z.record(z.string(), z.string()).default({}),
+    log_level: z
+      .enum(["error", "warn", "info", "debug", "trace"])
+      .default("info"),
+    tree_ascii: z.boolean().default(false),
+    include_hidden: z.boolean().default(false),
+    pinned_files: z.array(z.string()).default([]),
+    remember_selection: z.boolean().default(false),
   })
   .passthrough();
 
@@ -33,16 +130,34 @@ export const ConfigSchema = z
 export const TreeNodeSchema: z.ZodType<TreeNode> = z.lazy(() =>
   z
     .object({
+      id: z.number().int().nonnegative().nullable().optional(),
       path: z.string(),
       name: z.string(),
       is_directory: z.boolean(),
       is_expanded: z.boolean(),
       is_binary: z.boolean(),
+      likely_generated: z.boolean().optional(),
+      git_status: z
+        .enum([
+          "Modified",
+          "Added",
+          "Deleted",
+          "Renamed",
+          "Untracked",
+          "Conflicted",
+        ])
+        .nullable()
+        .optional(),
       is_match: z.boolean(),
       is_previewed: z.boolean(),
+      is_pinned: z.boolean().optional(),
       selection_state: z.enum(["none", "partial", "full"]),
       children: z.array(TreeNodeSchema),
       size: z.number(),
+      mtime: z.number().int().nonnegative().nullable().optional(),
+      content_match_count: z.number().int().nonnegative().optional(),
+      /** Entries left out, on the "… and N more" node only. */
+      hidden_children: z.number().int().nonnegative().optional(),
       // present in backend, not required by UI:
       children_loaded: z.boolean().optional(),
     })
@@ -68,12 +183,26 @@ export const UiStateSchema = z
     selected_files_count: z.number(),
     is_scanning: z.boolean(),
     is_generating: z.boolean(),
+    is_estimating: z.boolean().optional(),
     is_fully_scanned: z.boolean(),
+    generated_preview_truncated: z.boolean().optional(),
     status_message: z.string(),
     search_query: z.string(),
+    filename_match_mode: FilenameMatchModeSchema.optional(),
     extension_filter: z.string(),
     content_search_query: z.string(),
+    content_search_scope: ContentSearchScopeSchema.optional(),
+    content_search_threshold: z
+      .object({
+        min_matches: z.number().int().nonnegative(),
+        min_matches_per_kb: z.number().nonnegative(),
+      })
+      .optional(),
+    content_search_file_count: z.number().int().nonnegative().optional(),
+    content_search_match_count: z.number().int().nonnegative().optional(),
+    content_search_truncated: z.boolean().optional(),
     current_config_filename: z.string().nullable().optional(),
+    active_profile: z.string().nullable().optional(),
     scan_progress: ScanProgressSchema,
     active_ignore_patterns: z.array(z.string()),
     patterns_need_rescan: z.boolean(),
@@ -81,11 +210,17 @@ export const UiStateSchema = z
   .passthrough();
 
 /* ------------------------------ Incoming API ------------------------------- */
+export const PreviewTruncationSchema = z.object({
+  shown_lines: z.number().int().nonnegative(),
+  total_lines: z.number().int().nonnegative(),
+});
+
 export const ShowPreviewArgsSchema = z.tuple([
   z.string(), // content
   z.string(), // language
   z.string().nullable().optional(), // searchTerm
   z.string(), // path
+  PreviewTruncationSchema.nullable().optional(), // truncation
 ]);
 
 export const ShowGeneratedArgsSchema = z.tuple([
@@ -96,20 +231,143 @@ export const ShowGeneratedArgsSchema = z.tuple([
 export const StatusMessageSchema = z.string();
 export const FileSaveStatusArgsSchema = z.tuple([z.boolean(), z.string()]);
 export const DragStateSchema = z.boolean();
+export const LargeFilesSkippedArgsSchema = z.tuple([
+  z.number().int().nonnegative(), // count
+  z.array(z.string()), // names
+  z.number().int().nonnegative(), // maxFileSizeMb
+]);
+export const BrokenSymlinksSchema = z.array(z.string());
+
+export const GenerationChunkSchema = z.string();
+export const GenerationEndSchema = z.number().int().nonnegative(); // tokenCount
+export const OutputCopiedSchema = z.number().int().nonnegative(); // tokenCount
+export const TokenEstimateArgsSchema = z.tuple([
+  z.number().int().nonnegative(), // tokens
+  z.number().int().nonnegative(), // files
+]);
+export const ProfileListSchema = z.array(z.string());
+export const AutoIncludedSchema = z.array(z.string());
+export const LogPathSchema = z.string().nullable();
+export const ScrollToLineSchema = z.number().int().positive();
+export const OutputFilenameSetArgsSchema = z.tuple([
+  z.string(), // filename
+  z.boolean(), // corrected
+]);
+export const IgnorePreviewArgsSchema = z.tuple([
+  z.string(), // pattern
+  z.array(z.string()), // matches
+]);
+export const HiddenExplanationArgsSchema = z.tuple([
+  z.string(), // path
+  z.array(z.string()), // reasons
+]);
+export const ConfirmLargeGenerationArgsSchema = z.tuple([
+  z.number().int().nonnegative(), // bytes
+  z.number().int().nonnegative(), // files
+]);
+export const GenerationDiffSchema = z.object({
+  added: z.array(z.string()),
+  removed: z.array(z.string()),
+  changed: z.array(z.string()),
+  token_delta: z.number().int(),
+});
+export const GenerationPlanSchema = z.object({
+  files: z.array(z.string()),
+  total_size: z.number().int().nonnegative(),
+  tree_entries: z.number().int().nonnegative().nullable(),
+});
+export const TreeStatsSchema = z.object({
+  extensions: z.array(
+    z.object({
+      extension: z.string(),
+      files: z.number().int().nonnegative(),
+      size: z.number().int().nonnegative(),
+      tokens: z.number().int().nonnegative(),
+    })
+  ),
+  total_files: z.number().int().nonnegative(),
+  total_size: z.number().int().nonnegative(),
+  total_tokens: z.number().int().nonnegative(),
+  exact_tokens: z.boolean(),
+});
+export const GenerationHistorySchema = z.array(
+  z.object({
+    id: z.number().int().nonnegative(),
+    content_hash: z.string(),
+    token_count: z.number().int().nonnegative(),
+    file_count: z.number().int().nonnegative(),
+    size: z.number().int().nonnegative(),
+    generated_at: z.number().int().nonnegative(), // seconds since the epoch
+    saved_path: z.string().nullable(),
+  })
+);
+export const ConfigIssueSchema = z.object({
+  severity: z.enum(["warning", "error"]),
+  field: z.string().nullable(),
+  message: z.string(),
+});
+export const ConfigValidationArgsSchema = z.tuple([
+  z.string(), // path
+  z.array(ConfigIssueSchema), // issues
+]);
+export const MatchSnippetSchema = z.object({
+  line_number: z.number().int().positive(),
+  line: z.string(),
+  context_before: z.array(z.string()),
+  context_after: z.array(z.string()),
+});
+export const ContentSearchResultsArgsSchema = z.tuple([
+  z.string(), // query
+  z.array(
+    z.object({
+      id: z.number().int().nonnegative().nullable().optional(),
+      path: z.string(),
+      match_count: z.number().int().nonnegative(),
+      snippets: z.array(MatchSnippetSchema),
+    })
+  ),
+]);
 
 /* ------------------------------ Outgoing IPC ------------------------------- */
 const NullPayload = z.null();
 const UpdateFiltersPayload = z
   .object({
     searchQuery: z.string().optional().default(""),
+    filenameMatchMode: FilenameMatchModeSchema.optional().default("Substring"),
     extensionFilter: z.string().optional().default(""),
     contentSearchQuery: z.string().optional().default(""),
+    contentSearchScope: ContentSearchScopeSchema.optional().default("AllFiles"),
+    /** Numbers as strings, like every other filter value. */
+    contentSearchMinMatches: z.string().optional(),
+    contentSearchMinMatchesPerKb: z.string().optional(),
   })
   .passthrough();
-const PathPayload = PathString;
+/** A tree node id (preferred) or a plain path. */
+const PathRefPayload = z.union([z.number().int().nonnegative(), PathString]);
+const PathListPayload = z.array(PathRefPayload);
 const BoolPayload = z.boolean();
+const ProfileNamePayload = z.string().trim().min(1);
 const SaveFilePayload = z.string();
+const OutputFilenamePayload = z.string().trim().min(1);
+const ExtensionPayload = z.string().trim().min(1);
+const IgnorePatternPayload = z.string().trim().min(1);
+const IgnorePresetPayload = z.enum(
+  Object.keys(IGNORE_PRESETS) as [IgnorePresetName, ...IgnorePresetName[]]
+);
 const UpdateConfigPayload = ConfigSchema;
+const TreeStatsPayload = z.object({
+  exact: z.boolean().optional(),
+});
+const LoadMoreChildrenPayload = z.object({
+  path: PathString,
+  offset: z.number().int().nonnegative(),
+});
+
+/** Preferences left out keep their current value. */
+const UiPreferencesPayload = z.object({
+  ui_zoom: z.number().positive().optional(),
+  theme: z.enum(["System", "Light", "Dark"]).optional(),
+});
 
 /**
  * Single source of truth: command → payload schema.
@@ -118,29 +376,75 @@ const UpdateConfigPayload = ConfigSchema;
 export const CommandSchemas = {
   selectDirectory: NullPayload,
   rescanDirectory: NullPayload,
-  loadDirectoryLevel: PathPayload,
+  loadDirectoryLevel: PathRefPayload,
   generatePreview: NullPayload,
+  generateToClipboard: NullPayload,
+  confirmGeneration: NullPayload,
+  estimateTokens: NullPayload,
+  cancelEstimate: NullPayload,
   clearDirectory: NullPayload,
   cancelScan: NullPayload,
+  cancelAll: NullPayload,
   initialize: NullPayload,
-  loadFilePreview: PathPayload,
-  toggleSelection: PathPayload,
-  toggleDirectorySelection: PathPayload,
-  toggleExpansion: PathPayload,
+  loadFilePreview: PathRefPayload,
+  toggleSelection: PathRefPayload,
+  toggleDirectorySelection: PathRefPayload,
+  toggleExpansion: PathRefPayload,
+  revealPath: PathRefPayload,
+  rescanSubtree: PathRefPayload,
+  openInEditor: PathRefPayload,
+  pinFile: PathRefPayload,
+  unpinFile: PathRefPayload,
+  selectCurrent: PathRefPayload,
+  expandCurrent: PathRefPayload,
+  collapseCurrent: PathRefPayload,
   expandCollapseAll: BoolPayload,
+  includeTreeToggled: BoolPayload,
   selectAll: NullPayload,
   deselectAll: NullPayload,
+  selectByExtension: ExtensionPayload,
+  deselectByExtension: ExtensionPayload,
+  setGenerationOrder: PathListPayload.nullable(),
   expandAllFully: NullPayload,
   selectAllFully: NullPayload,
   cancelGeneration: NullPayload,
+  showGenerationDiff: NullPayload,
+  previewGenerationPlan: NullPayload,
+  listGenerationHistory: NullPayload,
+  showGenerationFromHistory: z.number().int().nonnegative(),
+  computeTreeStats: TreeStatsPayload,
   clearPreviewState: NullPayload,
+  nextMatch: NullPayload,
+  prevMatch: NullPayload,
+  collapseToPreview: NullPayload,
+  setUiPreferences: UiPreferencesPayload,
   saveFile: SaveFilePayload,
+  saveToConfiguredPath: SaveFilePayload,
   pickOutputDirectory: NullPayload,
   exportConfig: NullPayload,
+  exportTree: NullPayload,
+  copySelectedPaths: NullPayload,
+  copyGeneratedContent: NullPayload,
+  importSelection: NullPayload,
+  exportSelection: NullPayload,
   importConfig: NullPayload,
+  resetConfigToDefaults: NullPayload,
+  validateConfig: PathString,
+  saveProfile: ProfileNamePayload,
+  loadProfile: ProfileNamePayload,
+  listProfiles: NullPayload,
+  getLogPath: NullPayload,
+  setOutputFilename: OutputFilenamePayload,
+  deleteProfile: ProfileNamePayload,
   updateConfig: UpdateConfigPayload,
   updateFilters: UpdateFiltersPayload,
-  addIgnorePath: PathPayload,
+  loadMoreSearchResults: NullPayload,
+  loadMoreChildren: LoadMoreChildrenPayload,
+  addIgnorePath: PathRefPayload,
+  addIgnorePaths: PathListPayload,
+  applyIgnorePreset: IgnorePresetPayload,
+  previewIgnoreMatches: IgnorePatternPayload,
+  explainHidden: PathRefPayload,
 } as const satisfies Record<string, z.ZodTypeAny>;
 
 export type CommandName = keyof typeof CommandSchemas;
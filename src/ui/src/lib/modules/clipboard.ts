@@ -1,4 +1,5 @@
-import { editorInstance } from "../stores/app.js";
+import { appState, editorInstance } from "../stores/app.js";
+import { post } from "../services/backend.js";
 import { getUndoManagerForElement } from "./undo.js";
 import { elements } from "../dom.js";
 import type { FocusContext } from "../types.js";
@@ -66,6 +67,10 @@ export async function handleCopy(context: FocusContext) {
     if (selection && !selection.isEmpty()) {
       textToCopy = model.getValueInRange(selection);
       statusMessage = `✓ Copied selected text from editor.`;
+    } else if (get(appState).generated_preview_truncated) {
+      // The editor holds only the head; the backend copies the full output.
+      post("copyGeneratedContent");
+      return;
     } else {
       textToCopy = model.getValue();
       statusMessage = `✓ Copied entire editor content.`;
@@ -14,11 +14,16 @@ import {
 } from "../stores/app.js";
 import { post } from "../services/backend.js";
 import { get } from "svelte/store";
-import { previewMode, generatedTokenCount } from "../stores/preview.js";
+import {
+  previewMode,
+  generatedTokenCount,
+  previewTruncation,
+} from "../stores/preview.js";
 import { theme } from "../stores/theme.js";
 
 // Types only – no runtime cost
 import type * as MonacoTypes from "monaco-editor";
+import type { PreviewTruncation } from "../types.js";
 
 type MonacoNS = typeof import("monaco-editor");
 
@@ -187,7 +192,8 @@ export async function showPreviewContent(
   content: string,
   language: string,
   searchTerm: string,
-  path: string
+  path: string,
+  truncation: PreviewTruncation | null = null
 ): Promise<void> {
   await ensureEditor();
   const editor = get(editorInstance);
@@ -241,6 +247,7 @@ export async function showPreviewContent(
 
   previewMode.set("file");
   generatedTokenCount.set(null);
+  previewTruncation.set(truncation);
 
   layoutEditorSoon();
 }
@@ -272,6 +279,14 @@ export async function showGeneratedContent(
   layoutEditorSoon();
 }
 
+export function scrollToLine(line: number) {
+  const editor = get(editorInstance);
+  if (!editor) return;
+  editor.setPosition({ lineNumber: line, column: 1 });
+  editor.revealLineInCenter(line);
+  editor.focus();
+}
+
 export function clearPreview() {
   post("clearPreviewState");
   previewedPath.set(null);
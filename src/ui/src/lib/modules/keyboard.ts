@@ -1,4 +1,5 @@
 import { commands as allCommands } from "./commands.js";
+import { post } from "../services/backend.js";
 import type { FocusContext } from "../types.js";
 
 function getFocusContext(): FocusContext {
@@ -34,6 +35,13 @@ function globalKeydownHandler(e: KeyboardEvent) {
     return;
   }
 
+  // Escape is the panic button: it stops every running background task.
+  if (e.key === "Escape") {
+    e.preventDefault();
+    post("cancelAll");
+    return;
+  }
+
   if (context.isInNormalInputField) {
     const SAFE_KEYS_ALLOWLIST = [
       "ArrowUp",
@@ -1,5 +1,10 @@
 import { writable, derived, get } from "svelte/store";
-import type { AppState, Config } from "../types";
+import type {
+  AppState,
+  Config,
+  ContentSearchFile,
+  GenerationRecord,
+} from "../types";
 import type * as monaco from "monaco-editor/esm/vs/editor/editor.api";
 
 /**
@@ -40,15 +45,70 @@ export const appState = createAppStateStore({
   status_message: "Ready.",
   selected_files_count: 0,
   search_query: "",
+  filename_match_mode: "Substring",
   extension_filter: "",
   content_search_query: "",
+  content_search_scope: "AllFiles",
+  content_search_threshold: { min_matches: 1, min_matches_per_kb: 0 },
   active_ignore_patterns: [],
   config: {
     ignore_patterns: [],
     case_sensitive_search: false,
     include_tree_by_default: false,
+    tree_position: "Top",
     use_relative_paths: false,
     remove_empty_directories: false,
+    exclude_empty_files: false,
+    ui_zoom: 1,
+    theme: "System",
+    strip_comments: false,
+    strip_blank_lines: false,
+    summary_head_lines: 50,
+    summary_tail_lines: 20,
+    normalize_line_endings: "AsIs",
+    trim_trailing_whitespace: false,
+    number_lines_in_output: false,
+    include_file_mode: false,
+    include_checksums: false,
+    enable_redaction: false,
+    redaction_patterns: [],
+    tab_policy: "AsIs",
+    sort_order: "NameAsc",
+    concat_order: "TreeOrder",
+    output_format: "PlainText",
+    include_manifest: false,
+    include_index: false,
+    stream_generated_content: true,
+    stream_generation: false,
+    skip_generated: true,
+    auto_include_imports: false,
+    skip_unreadable_files: false,
+    warn_over_bytes: 50 * 1024 * 1024,
+    split_output_bytes: null,
+    chunk_header_template: "// Part {part} of {total} — continues {root}",
+    content_search_context_lines: 2,
+    search_read_buffer_kb: 64,
+    max_search_results: 300,
+    preview_max_lines: 1500,
+    max_children_displayed: 1000,
+    preview_generated_head_lines: null,
+    filter_debounce_ms: 120,
+    show_git_status: false,
+    only_git_tracked: false,
+    respect_export_ignore: false,
+    exclude_lockfiles: false,
+    exclude_vendored: false,
+    report_broken_symlinks: false,
+    auto_select_on_scan: "None",
+    auto_select_patterns: [],
+    text_extensions: [],
+    force_binary_extensions: [],
+    language_overrides: {},
+    log_level: "info",
+    tree_ascii: false,
+    include_hidden: false,
+    pinned_files: [],
+    remember_selection: false,
     output_directory: "",
     output_filename: "output.txt",
   },
@@ -78,6 +138,20 @@ export const editorInstance =
 export const editorDecorations = writable<string[]>([]);
 export const previewedPath = writable<string | null>(null);
 export const patternFilter = writable<string>("");
+export const profiles = writable<string[]>([]);
+export const contentSearchResults = writable<ContentSearchFile[]>([]);
+/** The files the pattern being typed would hide, as reported by the backend. */
+export const ignorePreview = writable<{
+  pattern: string;
+  matches: string[];
+} | null>(null);
+/** A generation held back by the size guard, until confirmed or cancelled. */
+export const pendingLargeGeneration = writable<{
+  bytes: number;
+  files: number;
+} | null>(null);
+/** The recent generations, newest first, while the history list is open. */
+export const generationHistory = writable<GenerationRecord[] | null>(null);
 
 // --- Helper function to easily access the current state ---
 export function getState(): AppState {
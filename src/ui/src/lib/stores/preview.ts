@@ -1,4 +1,5 @@
 import { writable } from "svelte/store";
+import type { PreviewTruncation } from "../types";
 
 export type PreviewMode = "idle" | "file" | "generated";
 
@@ -7,3 +8,6 @@ export const previewMode = writable<PreviewMode>("idle");
 
 /** For generated previews, holds the token count for stats display (nullable when unknown) */
 export const generatedTokenCount = writable<number | null>(null);
+
+/** For file previews, how many of the file's lines are shown (null when complete) */
+export const previewTruncation = writable<PreviewTruncation | null>(null);
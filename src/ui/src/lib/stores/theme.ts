@@ -1,4 +1,5 @@
 import { writable } from "svelte/store";
+import type { UiTheme } from "../types";
 
 export type Theme = "light" | "dark";
 
@@ -58,6 +59,16 @@ theme.subscribe((t) => {
   persistTheme(t);
 });
 
+/**
+ * Follow the theme preference saved in the app config. `System` picks the
+ * current OS appearance.
+ */
+export function applyThemePreference(pref: UiTheme) {
+  theme.set(
+    pref === "Light" ? "light" : pref === "Dark" ? "dark" : detectSystemTheme()
+  );
+}
+
 /** Public helpers */
 export function setTheme(t: Theme) {
   theme.set(t);
@@ -9,6 +9,7 @@ import { previewMode } from "./preview";
 
 export const isScanning = derived(appState, (s) => s.is_scanning);
 export const isGenerating = derived(appState, (s) => s.is_generating);
+export const isEstimating = derived(appState, (s) => !!s.is_estimating);
 export const isBusy = derived(
   [isScanning, isGenerating],
   ([scan, gen]) => scan || gen
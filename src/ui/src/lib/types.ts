@@ -1,20 +1,169 @@
+/** A file's working tree status as reported by `git status`. */
+export type GitStatus =
+  | "Modified"
+  | "Added"
+  | "Deleted"
+  | "Renamed"
+  | "Untracked"
+  | "Conflicted";
+
 /**
  * Defines the structure for a file or directory node in the tree.
  * This is the data contract between the Rust backend and the Svelte frontend.
  */
 export interface TreeNode {
+  /** Backend id used to refer to this node in commands (see `nodeRef`). */
+  id?: number | null;
   path: string;
   name: string;
   is_directory: boolean;
   is_expanded: boolean;
   is_binary: boolean;
+  /** The file looks minified or generated by a tool. */
+  likely_generated?: boolean;
+  /** How the file differs from the last commit; absent when unchanged. */
+  git_status?: GitStatus | null;
   is_match: boolean;
   is_previewed: boolean;
+  /** Pinned files are re-selected by every scan that finds them. */
+  is_pinned?: boolean;
   selection_state: "none" | "partial" | "full";
   children: TreeNode[];
   size: number;
+  /** Last modification time in Unix seconds; absent for directories. */
+  mtime?: number | null;
+  /** Content search occurrences in this file or below this directory. */
+  content_match_count?: number;
+  /**
+   * Set only on the "… and N more" node that ends a long directory listing: the
+   * entries left out. Its `path` is the directory's.
+   */
+  hidden_children?: number;
+}
+
+/** How much of a file the preview shows, if it is truncated. */
+export interface PreviewTruncation {
+  shown_lines: number;
+  total_lines: number;
+}
+
+/** A line matching the content search, with the lines around it. */
+export interface MatchSnippet {
+  line_number: number;
+  line: string;
+  context_before: string[];
+  context_after: string[];
+}
+
+/** The content search snippets of one matching file. */
+/** Files that differ between the last two generations, relative to the root. */
+export interface GenerationDiff {
+  added: string[];
+  removed: string[];
+  changed: string[];
+  token_delta: number;
+}
+
+/** What a generation of the current selection would include. */
+export interface GenerationPlan {
+  files: string[];
+  total_size: number;
+  /** Entries in the directory tree, or null if no tree is included. */
+  tree_entries: number | null;
+}
+
+/** A past generated output; only the newest ones can be reopened. */
+export interface GenerationRecord {
+  /** Passed to `showGenerationFromHistory`; never reused for another output. */
+  id: number;
+  content_hash: string;
+  token_count: number;
+  file_count: number;
+  size: number;
+  /** Seconds since the Unix epoch. */
+  generated_at: number;
+  saved_path: string | null;
+}
+
+/** Files, bytes and tokens of one file extension; "" for files without one. */
+export interface ExtensionStats {
+  extension: string;
+  files: number;
+  size: number;
+  tokens: number;
+}
+
+/** Aggregate statistics over all scanned files, largest extensions first. */
+export interface TreeStats {
+  extensions: ExtensionStats[];
+  total_files: number;
+  total_size: number;
+  total_tokens: number;
+  /** False if the token counts are estimated from the file sizes. */
+  exact_tokens: boolean;
 }
 
+/** A problem `validateConfig` found in a config file. */
+export interface ConfigIssue {
+  severity: "warning" | "error";
+  /** The config field concerned, or null for the file as a whole. */
+  field: string | null;
+  message: string;
+}
+
+export interface ContentSearchFile {
+  /** Backend id of the file, see `TreeNode.id`. */
+  id?: number | null;
+  path: string;
+  match_count: number;
+  snippets: MatchSnippet[];
+}
+
+/** Order of entries within each directory of the file tree. */
+export type SortOrder = "NameAsc" | "NameDesc" | "SizeDesc" | "ModifiedDesc";
+
+/** Order in which the selected files are concatenated into the output. */
+export type ConcatOrder =
+  | "TreeOrder"
+  | "ExtensionThenName"
+  | "SizeAsc"
+  | "SizeDesc";
+
+/** Color theme of the UI; `System` follows the OS appearance. */
+export type UiTheme = "System" | "Light" | "Dark";
+
+/** Line endings each file is converted to in the generated output. */
+export type LineEndings = "AsIs" | "Lf" | "Crlf";
+
+/** How leading indentation is converted; the number is the tab width. */
+export type TabPolicy =
+  | "AsIs"
+  | { TabsToSpaces: number }
+  | { SpacesToTabs: number };
+export type TreePosition = "Top" | "Bottom" | "None";
+
+/** Files selected automatically when a full scan completes. */
+export type AutoSelect = "None" | "AllText" | "FromPreset";
+
+/** Most verbose level written to the log file. */
+export type LogLevel = "error" | "warn" | "info" | "debug" | "trace";
+
+/** Whether the filename query is a substring of the name or a path glob. */
+export type FilenameMatchMode = "Substring" | "Glob";
+
+/** Which files a content search reads. */
+export type ContentSearchScope = "AllFiles" | "SelectedOnly" | "VisibleOnly";
+
+/** How relevant a file must be to appear in the content search results. */
+export interface ContentSearchThreshold {
+  min_matches: number;
+  /** Matches per KiB of content; 0 disables the check. */
+  min_matches_per_kb: number;
+}
+
+/** Document format of the generated output. */
+export type OutputFormat = "PlainText" | "Html" | "Xml";
+
 /**
  * Defines the configuration settings for the application.
  */
@@ -22,8 +171,94 @@ export interface Config {
   ignore_patterns: string[];
   case_sensitive_search: boolean;
   include_tree_by_default: boolean;
+  tree_position?: TreePosition;
   use_relative_paths: boolean;
   remove_empty_directories: boolean;
+  exclude_empty_files?: boolean;
+  /** Zoom factor of the UI, `1` being 100 %. */
+  ui_zoom?: number;
+  theme?: UiTheme;
+  strip_comments?: boolean;
+  strip_blank_lines?: boolean;
+  summarize_over_lines?: number | null;
+  summary_head_lines?: number;
+  summary_tail_lines?: number;
+  normalize_line_endings?: LineEndings;
+  trim_trailing_whitespace?: boolean;
+  /** Prefix each line of every file in the output with its line number. */
+  number_lines_in_output?: boolean;
+  /** Show each file's mode (or Windows attributes) in its output header. */
+  include_file_mode?: boolean;
+  /** Show each file's SHA-256 in its output header. */
+  include_checksums?: boolean;
+  /** Mask API keys, tokens and `.env` secrets in the output. */
+  enable_redaction?: boolean;
+  /** Regexes whose matches are replaced with `[REDACTED]`. */
+  redaction_patterns?: string[];
+  tab_policy?: TabPolicy;
+  sort_order?: SortOrder;
+  concat_order?: ConcatOrder;
+  min_file_size_bytes?: number | null;
+  max_file_size_bytes?: number | null;
+  /** Show only files modified within this many days; `null` shows all. */
+  modified_within_days?: number | null;
+  max_threads?: number | null;
+  output_format?: OutputFormat;
+  include_manifest?: boolean;
+  /** List the files with their bytes, lines and tokens before the first one. */
+  include_index?: boolean;
+  stream_generated_content?: boolean;
+  stream_generation?: boolean;
+  skip_generated?: boolean;
+  /** Add small files the selection names by a quoted path before generating. */
+  auto_include_imports?: boolean;
+  /** Unreadable files become a note in the output instead of failing it. */
+  skip_unreadable_files?: boolean;
+  /** Generation asks for confirmation above this many selected bytes. */
+  warn_over_bytes?: number | null;
+  /** Saves the output in parts of about this many bytes; `null` for one file. */
+  split_output_bytes?: number | null;
+  /** Header of each part of a split output; see `{part}`, `{total}`, `{root}`. */
+  chunk_header_template?: string;
+  /** Context lines around content search snippets; `null` disables snippets. */
+  content_search_context_lines?: number | null;
+  /** KiB read at a time when content-searching a file. */
+  search_read_buffer_kb?: number;
+  max_search_results?: number;
+  /** Lines shown when previewing a file. */
+  preview_max_lines?: number;
+  /** Entries shown per directory before an "… and N more" node; null for all. */
+  max_children_displayed?: number | null;
+  /** Lines of a generated output the preview shows; null for all of it. */
+  preview_generated_head_lines?: number | null;
+  /** Quiet period before the name and extension filters apply, in ms. */
+  filter_debounce_ms?: number;
+  /** Badge files in the tree with their git status. */
+  show_git_status?: boolean;
+  /** Only show files git tracks; ignored outside a git work tree. */
+  only_git_tracked?: boolean;
+  /** Hide paths `.gitattributes` marks `export-ignore`, as `git archive` would. */
+  respect_export_ignore?: boolean;
+  /** Hide lockfiles like `Cargo.lock` without adding ignore patterns. */
+  exclude_lockfiles?: boolean;
+  /** Hide vendored dependencies like `node_modules/` without adding ignore patterns. */
+  exclude_vendored?: boolean;
+  /** Tell the user which broken symlinks a full scan skipped. */
+  report_broken_symlinks?: boolean;
+  auto_select_on_scan?: AutoSelect;
+  auto_select_patterns?: string[];
+  text_extensions?: string[];
+  force_binary_extensions?: string[];
+  /** Language tags by extension, e.g. `{ tf: "terraform" }`; win over the built-ins. */
+  language_overrides?: Record<string, string>;
+  log_level?: LogLevel;
+  /** Draw directory trees with ASCII instead of box-drawing characters. */
+  tree_ascii?: boolean;
+  include_hidden?: boolean;
+  /** Absolute paths of files that every scan re-selects. */
+  pinned_files?: string[];
+  /** Restore the selection and expanded folders when a directory is reopened. */
+  remember_selection?: boolean;
   output_directory: string;
   output_filename: string;
 }
@@ -34,16 +269,26 @@ export interface Config {
 export interface AppState {
   is_scanning: boolean;
   is_generating: boolean;
+  is_estimating?: boolean;
   is_fully_scanned: boolean;
+  /** The preview shows only the head; save and copy use the full output. */
+  generated_preview_truncated?: boolean;
   patterns_need_rescan: boolean;
   tree: TreeNode[];
   current_path: string | null;
   current_config_filename: string | null;
+  active_profile?: string | null;
   status_message: string;
   selected_files_count: number;
   search_query: string;
+  filename_match_mode?: FilenameMatchMode;
   extension_filter: string;
   content_search_query: string;
+  content_search_scope?: ContentSearchScope;
+  content_search_threshold?: ContentSearchThreshold;
+  content_search_file_count?: number;
+  content_search_match_count?: number;
+  content_search_truncated?: boolean;
   active_ignore_patterns: string[];
   config: Config;
 }
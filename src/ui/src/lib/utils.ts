@@ -1,3 +1,5 @@
+import type { TreeNode } from "./types";
+
 export function formatFileSize(bytes: number): string {
   if (bytes === 0) return "0 B";
   const k = 1024;
@@ -6,6 +8,12 @@ export function formatFileSize(bytes: number): string {
   return parseFloat((bytes / Math.pow(k, i)).toFixed(1)) + " " + sizes[i];
 }
 
+/** Formats a Unix timestamp in seconds as a local date and time. */
+export function formatModified(mtime: number | null | undefined): string {
+  if (mtime == null) return "";
+  return new Date(mtime * 1000).toLocaleString();
+}
+
 function countWords(text: string): number {
   if (!text || text.trim() === "") return 0;
   const words = text.trim().split(/\s+/);
@@ -68,3 +76,12 @@ export function splitPathForDisplay(
   const pathPart = parts.slice(0, -1).join("/") + "/";
   return { pathPart, filename };
 }
+
+/**
+ * The reference sent to the backend for file operations on a tree node.
+ * Prefers the backend-assigned id, which resolves to the exact path even when
+ * the file name is not valid UTF-8; falls back to the display path.
+ */
+export function nodeRef(node: TreeNode): number | string {
+  return node.id ?? node.path;
+}
@@ -107,39 +107,103 @@ export function makeValidPayloadForCommand(name: CommandName): unknown {
     case "selectDirectory":
     case "rescanDirectory":
     case "generatePreview":
+    case "generateToClipboard":
+    case "confirmGeneration":
+    case "estimateTokens":
+    case "cancelEstimate":
     case "clearDirectory":
     case "cancelScan":
+    case "cancelAll":
     case "initialize":
     case "selectAll":
     case "deselectAll":
     case "expandAllFully":
     case "selectAllFully":
     case "cancelGeneration":
+    case "showGenerationDiff":
+    case "previewGenerationPlan":
+    case "listGenerationHistory":
+    case "loadMoreSearchResults":
     case "clearPreviewState":
+    case "nextMatch":
+    case "prevMatch":
+    case "collapseToPreview":
     case "pickOutputDirectory":
     case "exportConfig":
+    case "exportTree":
+    case "copySelectedPaths":
+    case "copyGeneratedContent":
+    case "importSelection":
+    case "exportSelection":
     case "importConfig":
+    case "resetConfigToDefaults":
+    case "listProfiles":
+    case "getLogPath":
       return null;
 
+    case "saveProfile":
+    case "loadProfile":
+    case "deleteProfile":
+      return "rust review";
+
+    case "selectByExtension":
+    case "deselectByExtension":
+      return "rs";
+
+    case "showGenerationFromHistory":
+      return 0;
+
+    case "previewIgnoreMatches":
+      return "*.log";
+
     case "loadDirectoryLevel":
     case "loadFilePreview":
     case "toggleSelection":
     case "toggleDirectorySelection":
     case "toggleExpansion":
+    case "revealPath":
+    case "rescanSubtree":
+    case "openInEditor":
+    case "explainHidden":
+    case "pinFile":
+    case "unpinFile":
+    case "selectCurrent":
+    case "expandCurrent":
+    case "collapseCurrent":
     case "addIgnorePath":
+    case "validateConfig":
       return VALID_PATH;
 
+    case "addIgnorePaths":
+    case "setGenerationOrder":
+      return [VALID_PATH];
+
+    case "applyIgnorePreset":
+      return "rust";
+
     case "expandCollapseAll":
+    case "includeTreeToggled":
       return true;
 
     case "saveFile":
+    case "saveToConfiguredPath":
       return "// content to save";
 
+    case "setOutputFilename":
+      return "review.txt";
+
     case "updateFilters":
       // Provide empty object to test default injection
       return {};
 
     case "updateConfig":
       return makeValidConfig();
+
+    case "setUiPreferences":
+      return { ui_zoom: 1.25, theme: "Dark" };
+    case "computeTreeStats":
+      return { exact: false };
+    case "loadMoreChildren":
+      return { path: "/tmp/project/migrations", offset: 1000 };
   }
 }
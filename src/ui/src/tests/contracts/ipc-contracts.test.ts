@@ -23,18 +23,38 @@ const NULLARY_COMMANDS: readonly NullaryCommandName[] = [
   "selectDirectory",
   "rescanDirectory",
   "generatePreview",
+  "generateToClipboard",
+  "confirmGeneration",
+  "estimateTokens",
+  "cancelEstimate",
   "clearDirectory",
   "cancelScan",
+  "cancelAll",
   "initialize",
   "selectAll",
   "deselectAll",
   "expandAllFully",
   "selectAllFully",
   "cancelGeneration",
+  "showGenerationDiff",
+  "previewGenerationPlan",
+  "listGenerationHistory",
+  "loadMoreSearchResults",
   "clearPreviewState",
+  "nextMatch",
+  "prevMatch",
+  "collapseToPreview",
   "pickOutputDirectory",
   "exportConfig",
+  "exportTree",
+  "copySelectedPaths",
+  "copyGeneratedContent",
+  "importSelection",
+  "exportSelection",
   "importConfig",
+  "resetConfigToDefaults",
+  "listProfiles",
+  "getLogPath",
 ] as const;
 
 describe("IPC contracts – outgoing commands (positive)", () => {
@@ -55,8 +75,20 @@ describe("IPC contracts – outgoing commands (positive)", () => {
       ["toggleSelection", "/repo/src/main.rs"],
       ["toggleDirectorySelection", "/repo/src"],
       ["toggleExpansion", "/repo/src"],
+      ["revealPath", "/repo/src/main.rs"],
+      ["rescanSubtree", "/repo/packages/api"],
+      ["openInEditor", "/repo/src/main.rs"],
+      ["explainHidden", "src/generated/schema.rs"],
+      ["pinFile", "/repo/README.md"],
+      ["unpinFile", "/repo/README.md"],
+      ["selectCurrent", "/repo/src/main.rs"],
+      ["expandCurrent", "/repo/src"],
+      ["collapseCurrent", "/repo/src"],
       ["addIgnorePath", "/repo/dist"],
+      ["validateConfig", "/home/me/review.json"],
       ["saveFile", "Hello world"], // content to save
+      ["saveToConfiguredPath", "Hello world"],
+      ["setOutputFilename", "review.txt"],
     ] as const;
 
     for (const [name, payload] of cases) {
@@ -65,6 +97,66 @@ describe("IPC contracts – outgoing commands (positive)", () => {
     }
   });
 
+  it("accepts a history id for showGenerationFromHistory", () => {
+    const parsed = validateCommand("showGenerationFromHistory", 1);
+    expect(parsed.command).toBe("showGenerationFromHistory");
+    expect(parsed.payload).toBe(1);
+  });
+
+  it("accepts tree node ids for path-based commands", () => {
+    const parsed = validateCommand("toggleSelection", 3);
+    expect(parsed.command).toBe("toggleSelection");
+    expect(validateCommand("addIgnorePaths", [0, "/repo/dist"]).command).toBe(
+      "addIgnorePaths"
+    );
+  });
+
+  it("accepts a path array for addIgnorePaths", () => {
+    const parsed = validateCommand("addIgnorePaths", [
+      "/repo/dist",
+      "/repo/a.log",
+    ]);
+    expect(parsed.command).toBe("addIgnorePaths");
+  });
+
+  it("accepts a profile name for profile commands", () => {
+    for (const name of [
+      "saveProfile",
+      "loadProfile",
+      "deleteProfile",
+    ] as const) {
+      const parsed = validateCommand(name, "rust review");
+      expect(parsed.command).toBe(name);
+    }
+  });
+
+  it("accepts an extension for selectByExtension/deselectByExtension", () => {
+    for (const name of ["selectByExtension", "deselectByExtension"] as const) {
+      const parsed = validateCommand(name, "rs");
+      expect(parsed.command).toBe(name);
+    }
+  });
+
+  it("accepts a pattern for previewIgnoreMatches", () => {
+    const parsed = validateCommand("previewIgnoreMatches", "*.log");
+    expect(parsed.payload).toBe("*.log");
+  });
+
+  it("accepts known preset names for applyIgnorePreset", () => {
+    for (const name of ["rust", "node", "python", "go"] as const) {
+      expect(validateCommand("applyIgnorePreset", name).command).toBe(
+        "applyIgnorePreset"
+      );
+    }
+  });
+
+  it("accepts an ordering or null for setGenerationOrder", () => {
+    expect(
+      validateCommand("setGenerationOrder", [2, "/repo/README.md"]).command
+    ).toBe("setGenerationOrder");
+    expect(validateCommand("setGenerationOrder", null).payload).toBeNull();
+  });
+
   it("accepts boolean payload for expandCollapseAll", () => {
     const pTrue = validateCommand("expandCollapseAll", true);
     const pFalse = validateCommand("expandCollapseAll", false);
@@ -72,6 +164,42 @@ describe("IPC contracts – outgoing commands (positive)", () => {
     expect(pFalse.command).toBe("expandCollapseAll");
   });
 
+  it("accepts boolean payload for includeTreeToggled", () => {
+    const p = validateCommand("includeTreeToggled", true);
+    expect(p.command).toBe("includeTreeToggled");
+  });
+
+  it("accepts partial UI preferences for setUiPreferences", () => {
+    expect(validateCommand("setUiPreferences", { ui_zoom: 1.5 }).command).toBe(
+      "setUiPreferences"
+    );
+    expect(
+      validateCommand("setUiPreferences", { theme: "Light" }).command
+    ).toBe("setUiPreferences");
+  });
+
+  it("accepts an optional exact flag for computeTreeStats", () => {
+    expect(validateCommand("computeTreeStats", {}).command).toBe(
+      "computeTreeStats"
+    );
+    expect(
+      validateCommand("computeTreeStats", { exact: true }).command
+    ).toBe("computeTreeStats");
+  });
+
+  it("requires a path and a non-negative offset for loadMoreChildren", () => {
+    expect(
+      validateCommand("loadMoreChildren", { path: "/tmp/dir", offset: 1000 })
+        .command
+    ).toBe("loadMoreChildren");
+    expect(
+      AnyCommandMessageSchema.safeParse({
+        command: "loadMoreChildren",
+        payload: { path: "/tmp/dir", offset: -1 },
+      }).success
+    ).toBe(false);
+  });
+
   it("accepts full wire config for updateConfig", () => {
     const cfg = makeWireConfig({
       output_filename: "cfc_output.txt",
@@ -86,7 +214,7 @@ describe("IPC contracts – outgoing commands (negative)", () => {
   it("rejects wrong payload type for path-based commands", () => {
     const invalid = AnyCommandMessageSchema.safeParse({
       command: "loadDirectoryLevel",
-      payload: 123, // invalid, must be string
+      payload: true, // invalid, must be a tree node id or a path string
     });
     expect(invalid.success).toBe(false);
   });
@@ -99,6 +227,38 @@ describe("IPC contracts – outgoing commands (negative)", () => {
     expect(invalid.success).toBe(false);
   });
 
+  it("rejects a negative id for showGenerationFromHistory", () => {
+    const invalid = AnyCommandMessageSchema.safeParse({
+      command: "showGenerationFromHistory",
+      payload: -1,
+    });
+    expect(invalid.success).toBe(false);
+  });
+
+  it("rejects blank profile names", () => {
+    const invalid = AnyCommandMessageSchema.safeParse({
+      command: "saveProfile",
+      payload: "   ",
+    });
+    expect(invalid.success).toBe(false);
+  });
+
+  it("rejects a blank extension for selectByExtension", () => {
+    const invalid = AnyCommandMessageSchema.safeParse({
+      command: "selectByExtension",
+      payload: "",
+    });
+    expect(invalid.success).toBe(false);
+  });
+
+  it("rejects unknown ignore presets", () => {
+    const invalid = AnyCommandMessageSchema.safeParse({
+      command: "applyIgnorePreset",
+      payload: "cobol",
+    });
+    expect(invalid.success).toBe(false);
+  });
+
   it("rejects incomplete config for updateConfig", () => {
     // Deliberately pass a *UI* style partial config to show that the wire schema is stricter.
     const invalid = AnyCommandMessageSchema.safeParse({
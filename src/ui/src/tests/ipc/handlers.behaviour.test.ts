@@ -2,7 +2,14 @@
 
 import { describe, it, expect, beforeEach, vi } from "vitest";
 import { get } from "svelte/store";
-import { appState } from "$lib/stores/app";
+import {
+  appState,
+  contentSearchResults,
+  generationHistory,
+  ignorePreview,
+  pendingLargeGeneration,
+  profiles,
+} from "$lib/stores/app";
 
 /**
  * Hoisted mocks (evaluated before Imports)
@@ -10,7 +17,12 @@ import { appState } from "$lib/stores/app";
 
 // toast: we check calls for success/info/error
 const { toast } = vi.hoisted(() => ({
-  toast: { success: vi.fn(), info: vi.fn(), error: vi.fn() },
+  toast: {
+    success: vi.fn(),
+    info: vi.fn(),
+    warning: vi.fn(),
+    error: vi.fn(),
+  },
 }));
 vi.mock("$lib/stores/toast", () => ({ toast }));
 
@@ -41,6 +53,7 @@ vi.mock("$lib/modules/editor", () => ({
   initEditor: vi.fn(),
   showPreviewContent: vi.fn(),
   showGeneratedContent: vi.fn(),
+  scrollToLine: vi.fn(),
   clearPreview: vi.fn(),
 }));
 
@@ -55,9 +68,29 @@ vi.mock("$lib/ipc/schema", () => {
     ScanProgressSchema: { safeParse: passthrough },
     ShowPreviewArgsSchema: { safeParse: passthrough },
     ShowGeneratedArgsSchema: { safeParse: passthrough },
+    GenerationChunkSchema: { safeParse: passthrough },
+    GenerationEndSchema: { safeParse: passthrough },
+    OutputCopiedSchema: { safeParse: passthrough },
     StatusMessageSchema: { safeParse: passthrough },
     FileSaveStatusArgsSchema: { safeParse: passthrough },
     DragStateSchema: { safeParse: passthrough },
+    LargeFilesSkippedArgsSchema: { safeParse: passthrough },
+    ProfileListSchema: { safeParse: passthrough },
+    LogPathSchema: { safeParse: passthrough },
+    OutputFilenameSetArgsSchema: { safeParse: passthrough },
+    ScrollToLineSchema: { safeParse: passthrough },
+    TokenEstimateArgsSchema: { safeParse: passthrough },
+    ContentSearchResultsArgsSchema: { safeParse: passthrough },
+    IgnorePreviewArgsSchema: { safeParse: passthrough },
+    HiddenExplanationArgsSchema: { safeParse: passthrough },
+    GenerationPlanSchema: { safeParse: passthrough },
+    TreeStatsSchema: { safeParse: passthrough },
+    AutoIncludedSchema: { safeParse: passthrough },
+    ConfigValidationArgsSchema: { safeParse: passthrough },
+    BrokenSymlinksSchema: { safeParse: passthrough },
+    ConfirmLargeGenerationArgsSchema: { safeParse: passthrough },
+    GenerationDiffSchema: { safeParse: passthrough },
+    GenerationHistorySchema: { safeParse: passthrough },
   };
 });
 
@@ -66,6 +99,7 @@ import { installWindowIPCHandlers } from "$lib/ipc/handlers";
 import {
   showPreviewContent as showPreviewContentImpl,
   showGeneratedContent as showGeneratedContentImpl,
+  scrollToLine as scrollToLineImpl,
   clearPreview as clearPreviewImpl,
 } from "$lib/modules/editor";
 
@@ -100,7 +134,26 @@ describe("ipc/handlers – behavior of installed window handlers", () => {
       "code",
       "javascript",
       "",
-      "/path/file.ts"
+      "/path/file.ts",
+      null
+    );
+  });
+
+  it("showPreviewContent forwards the truncation of long files", () => {
+    const truncation = { shown_lines: 2, total_lines: 5 };
+    window.showPreviewContent(
+      "1\n2\n",
+      "plaintext",
+      null,
+      "/a.txt",
+      truncation
+    );
+    expect(showPreviewContentImpl).toHaveBeenCalledWith(
+      "1\n2\n",
+      "plaintext",
+      "",
+      "/a.txt",
+      truncation
     );
   });
 
@@ -109,6 +162,22 @@ describe("ipc/handlers – behavior of installed window handlers", () => {
     expect(showGeneratedContentImpl).toHaveBeenCalledWith("output", 42);
   });
 
+  it("streamed generated content is joined and forwarded on end", () => {
+    window.beginGeneratedContent();
+    window.appendGeneratedContent("out");
+    window.appendGeneratedContent("put");
+    expect(showGeneratedContentImpl).not.toHaveBeenCalledWith("output", 7);
+    window.endGeneratedContent(7);
+    expect(showGeneratedContentImpl).toHaveBeenCalledWith("output", 7);
+  });
+
+  it("ignores stream chunks without a preceding begin", () => {
+    vi.mocked(showGeneratedContentImpl).mockClear();
+    window.appendGeneratedContent("stray");
+    window.endGeneratedContent(1);
+    expect(showGeneratedContentImpl).not.toHaveBeenCalled();
+  });
+
   it("showError updates status and emits toast.error", () => {
     window.showError("Boom");
     const state = get(appState);
@@ -147,6 +216,207 @@ describe("ipc/handlers – behavior of installed window handlers", () => {
     expect(container.classList.contains("drag-over")).toBe(false);
   });
 
+  it("showLargeFilesSkipped updates status and warns", () => {
+    window.showLargeFilesSkipped(2, ["a.bin", "b/c.iso"], 20);
+    expect(get(appState).status_message).toBe(
+      "Status: Skipped 2 file(s) larger than 20 MB: a.bin, b/c.iso"
+    );
+    expect(toast.warning).toHaveBeenCalledWith("toast.large_files_skipped");
+  });
+
+  it("showBrokenSymlinks updates status and warns", () => {
+    window.showBrokenSymlinks(["docs/old.md", "vendor/lib"]);
+    expect(get(appState).status_message).toBe(
+      "Status: Skipped 2 broken symlink(s): docs/old.md, vendor/lib"
+    );
+    expect(toast.warning).toHaveBeenCalledWith("toast.broken_symlinks");
+  });
+
+  it("showProfiles stores the profile names", () => {
+    window.showProfiles(["docs", "rust review"]);
+    expect(get(profiles)).toEqual(["docs", "rust review"]);
+  });
+
+  it("showLogPath reports the log directory or that there is none", () => {
+    window.showLogPath("/home/me/.local/share/cfc/logs");
+    expect(get(appState).status_message).toBe("Status: toast.log_path");
+    expect(toast.info).toHaveBeenCalledWith("toast.log_path");
+
+    window.showLogPath(null);
+    expect(toast.warning).toHaveBeenCalledWith("toast.log_path_unavailable");
+  });
+
+  it("outputFilenameSet stores the name and reports corrections", () => {
+    window.outputFilenameSet("review.txt", false);
+    expect(get(appState).config.output_filename).toBe("review.txt");
+    expect(toast.info).not.toHaveBeenCalled();
+
+    window.outputFilenameSet("my_output.txt", true);
+    expect(get(appState).config.output_filename).toBe("my_output.txt");
+    expect(toast.info).toHaveBeenCalledWith("toast.output_filename_corrected");
+  });
+
+  it("scrollToLine reveals the line in the editor", () => {
+    window.scrollToLine(42);
+    expect(scrollToLineImpl).toHaveBeenCalledWith(42);
+  });
+
+  it("showTokenEstimate updates status and informs", () => {
+    window.showTokenEstimate(1200, 3);
+    expect(get(appState).status_message).toBe("Status: toast.token_estimate");
+    expect(toast.info).toHaveBeenCalledWith("toast.token_estimate");
+  });
+
+  it("outputCopied updates status and confirms", () => {
+    window.outputCopied(1200);
+    expect(get(appState).status_message).toBe("Status: toast.output_copied");
+    expect(toast.success).toHaveBeenCalledWith("toast.output_copied");
+  });
+
+  it("showContentSearchResults stores snippets and clears on empty query", () => {
+    const files = [
+      {
+        id: 0,
+        path: "/repo/a.rs",
+        match_count: 1,
+        snippets: [
+          {
+            line_number: 3,
+            line: "let magic = 1;",
+            context_before: ["// setup"],
+            context_after: [],
+          },
+        ],
+      },
+    ];
+    window.showContentSearchResults("magic", files);
+    expect(get(contentSearchResults)).toEqual(files);
+
+    window.showContentSearchResults("", files);
+    expect(get(contentSearchResults)).toEqual([]);
+  });
+
+  it("confirmLargeGeneration stores the pending generation", () => {
+    window.confirmLargeGeneration(600_000_000, 1200);
+    expect(get(pendingLargeGeneration)).toEqual({
+      bytes: 600_000_000,
+      files: 1200,
+    });
+  });
+
+  it("showGenerationHistory stores the records", () => {
+    const records = [
+      {
+        id: 7,
+        content_hash: "00ff00ff00ff00ff",
+        token_count: 120,
+        file_count: 2,
+        size: 480,
+        generated_at: 1_700_000_000,
+        saved_path: "/home/me/Desktop/cfc_output.txt",
+      },
+    ];
+    window.showGenerationHistory(records);
+    expect(get(generationHistory)).toEqual(records);
+  });
+
+  it("showIgnorePreview stores the matches of the pattern", () => {
+    window.showIgnorePreview("*.log", ["/repo/a.log", "/repo/logs/b.log"]);
+    expect(get(ignorePreview)).toEqual({
+      pattern: "*.log",
+      matches: ["/repo/a.log", "/repo/logs/b.log"],
+    });
+  });
+
+  it("showHiddenExplanation reports why a path is hidden or shown", () => {
+    window.showHiddenExplanation("/repo/debug.log", [
+      "the ignore pattern `*.log` excludes it",
+    ]);
+    expect(toast.warning).toHaveBeenCalledWith("toast.path_hidden");
+
+    window.showHiddenExplanation("/repo/main.rs", []);
+    expect(toast.info).toHaveBeenCalledWith("toast.path_shown");
+  });
+
+  it("showGenerationDiff lists the changes in the status and informs", () => {
+    window.showGenerationDiff({
+      added: ["src/new.rs"],
+      removed: [],
+      changed: ["README.md", "src/main.rs"],
+      token_delta: 120,
+    });
+    expect(get(appState).status_message).toBe(
+      "Status: +120 tokens since the previous generation. Added: src/new.rs. Changed: README.md, src/main.rs."
+    );
+    expect(toast.info).toHaveBeenCalledWith("toast.generation_diff");
+  });
+
+  it("showGenerationPlan lists the planned files and informs", () => {
+    window.showGenerationPlan({
+      files: ["README.md", "src/main.rs"],
+      total_size: 2048,
+      tree_entries: 4,
+    });
+    expect(get(appState).status_message).toBe(
+      "Status: 2 files (2 KB) would be generated. Tree: 4 entries. Files: README.md, src/main.rs."
+    );
+    expect(toast.info).toHaveBeenCalledWith("toast.generation_plan");
+  });
+
+  it("showTreeStats summarises the tree per extension and informs", () => {
+    window.showTreeStats({
+      extensions: [
+        { extension: "rs", files: 2, size: 2048, tokens: 512 },
+        { extension: "", files: 1, size: 40, tokens: 10 },
+      ],
+      total_files: 3,
+      total_size: 2088,
+      total_tokens: 522,
+      exact_tokens: false,
+    });
+    expect(get(appState).status_message).toBe(
+      "Status: 3 files, 2 KB, ~522 tokens. By extension: .rs 2 (2 KB, ~512 tokens), (none) 1 (40 B, ~10 tokens)."
+    );
+    expect(toast.info).toHaveBeenCalledWith("toast.tree_stats");
+  });
+
+  it("showAutoIncluded lists the added files and informs", () => {
+    window.showAutoIncluded(["web/util.js", "web/api.js"]);
+    expect(get(appState).status_message).toBe(
+      "Status: Auto-included web/util.js, web/api.js."
+    );
+    expect(toast.info).toHaveBeenCalledWith("toast.auto_included");
+  });
+
+  it("showConfigValidation picks the toast by the worst issue", () => {
+    window.showConfigValidation("/home/me/review.json", []);
+    expect(get(appState).status_message).toBe(
+      "Status: review.json is a valid config."
+    );
+    expect(toast.success).toHaveBeenCalledWith("toast.config_valid");
+
+    const warning = {
+      severity: "warning" as const,
+      field: "last_directory",
+      message: "The directory /gone does not exist.",
+    };
+    window.showConfigValidation("/home/me/review.json", [warning]);
+    expect(toast.warning).toHaveBeenCalledWith("toast.config_warnings");
+
+    window.showConfigValidation("/home/me/review.json", [
+      {
+        severity: "error",
+        field: "output_filename",
+        message: "The output filename is empty.",
+      },
+      warning,
+    ]);
+    expect(get(appState).status_message).toBe(
+      "Status: review.json: The output filename is empty. The directory /gone does not exist."
+    );
+    expect(toast.error).toHaveBeenCalledWith("toast.config_invalid");
+  });
+
   it("updateScanProgress writes progress text and width when scanning", () => {
     el("div", { className: "scan-text" });
     el("div", { id: "scan-files-count" });
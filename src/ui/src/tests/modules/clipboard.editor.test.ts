@@ -1,8 +1,14 @@
 /* @vitest-environment jsdom */
 
 import { describe, it, expect, vi, beforeEach } from "vitest";
+
+vi.mock("$lib/services/backend", () => ({
+  post: vi.fn(),
+}));
+
 import { handleCopy, handlePaste, handleCut } from "$lib/modules/clipboard";
-import { editorInstance } from "$lib/stores/app";
+import { appState, editorInstance } from "$lib/stores/app";
+import { post } from "$lib/services/backend";
 import type { FocusContext } from "$lib/types";
 import type * as monaco from "monaco-editor/esm/vs/editor/editor.api";
 
@@ -116,6 +122,24 @@ describe("clipboard.* – editor-focused paths", () => {
     expect(clip.writeText).toHaveBeenCalledWith("CCC DDD");
   });
 
+  it("handleCopy lets the backend copy the full output behind a truncated preview", async () => {
+    const clip = mockClipboard({ writeOk: true });
+    vi.mocked(post).mockClear();
+    appState.update((s) => ({ ...s, generated_preview_truncated: true }));
+
+    // A selection is still copied from the editor.
+    setupEditor({ content: "HEAD", selectionText: "HE" });
+    await handleCopy(ctxEditor());
+    expect(clip.writeText).toHaveBeenCalledWith("HE");
+
+    setupEditor({ content: "HEAD", selectionText: null });
+    await handleCopy(ctxEditor());
+    expect(post).toHaveBeenCalledWith("copyGeneratedContent");
+    expect(clip.writeText).not.toHaveBeenCalledWith("HEAD");
+
+    appState.update((s) => ({ ...s, generated_preview_truncated: false }));
+  });
+
   it("handlePaste (editor) – cancelled via prompt when clipboard read fails", async () => {
     mockClipboard({ readOk: false });
     (
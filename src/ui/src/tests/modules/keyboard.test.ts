@@ -1,7 +1,21 @@
 /* @vitest-environment jsdom */
 
-import { describe, it, expect, beforeAll, beforeEach, afterEach } from "vitest";
+import {
+  describe,
+  it,
+  expect,
+  beforeAll,
+  beforeEach,
+  afterEach,
+  vi,
+} from "vitest";
+
+vi.mock("$lib/services/backend", () => ({
+  post: vi.fn(),
+}));
+
 import { setupGlobalKeyboardListeners } from "$lib/modules/keyboard";
+import { post } from "$lib/services/backend";
 import { commands } from "$lib/modules/commands";
 import type { FocusContext } from "$lib/types";
 
@@ -106,6 +120,35 @@ describe("keyboard.ts – global handler integration", () => {
     expect(allowed).toBe(false); // blocked by safety guard
   });
 
+  it("sends cancelAll on Escape outside the editor", () => {
+    focusInput();
+    commands.length = 0;
+    vi.mocked(post).mockClear();
+
+    const ev = new KeyboardEvent("keydown", {
+      key: "Escape",
+      cancelable: true,
+    });
+    const allowed = document.dispatchEvent(ev);
+
+    expect(allowed).toBe(false);
+    expect(post).toHaveBeenCalledWith("cancelAll");
+  });
+
+  it("leaves Escape to the editor when it has focus", () => {
+    focusEditor();
+    commands.length = 0;
+    vi.mocked(post).mockClear();
+
+    const ev = new KeyboardEvent("keydown", {
+      key: "Escape",
+      cancelable: true,
+    });
+    document.dispatchEvent(ev);
+
+    expect(post).not.toHaveBeenCalled();
+  });
+
   it("allows printable characters in inputs to pass through when no command matches", () => {
     focusInput();
     commands.length = 0;
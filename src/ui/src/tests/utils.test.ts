@@ -1,9 +1,12 @@
 import { describe, it, expect } from "vitest";
 import {
   formatFileSize,
+  formatModified,
   generateStatsString,
   splitPathForDisplay,
+  nodeRef,
 } from "$lib/utils";
+import type { TreeNode } from "$lib/types";
 
 describe("utils.formatFileSize", () => {
   it("formats 0 bytes", () => {
@@ -24,6 +27,18 @@ describe("utils.formatFileSize", () => {
   });
 });
 
+describe("utils.formatModified", () => {
+  it("returns an empty string for missing timestamps", () => {
+    expect(formatModified(null)).toBe("");
+    expect(formatModified(undefined)).toBe("");
+  });
+
+  it("formats Unix seconds as a local date", () => {
+    const expected = new Date(1_700_000_000 * 1000).toLocaleString();
+    expect(formatModified(1_700_000_000)).toBe(expected);
+  });
+});
+
 describe("utils.generateStatsString", () => {
   it("generates stats for plain content", () => {
     const s = generateStatsString("Hello world", "read-only");
@@ -77,3 +92,29 @@ describe("utils.splitPathForDisplay", () => {
     expect(filename).toBe("README.md");
   });
 });
+
+describe("utils.nodeRef", () => {
+  const node = (id?: number | null): TreeNode => ({
+    id,
+    path: "/repo/a.rs",
+    name: "a.rs",
+    is_directory: false,
+    is_expanded: false,
+    is_binary: false,
+    is_match: false,
+    is_previewed: false,
+    selection_state: "none",
+    children: [],
+    size: 0,
+  });
+
+  it("prefers the backend id", () => {
+    expect(nodeRef(node(0))).toBe(0);
+    expect(nodeRef(node(7))).toBe(7);
+  });
+
+  it("falls back to the path when no id is present", () => {
+    expect(nodeRef(node())).toBe("/repo/a.rs");
+    expect(nodeRef(node(null))).toBe("/repo/a.rs");
+  });
+});
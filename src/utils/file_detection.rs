@@ -1,8 +1,9 @@
 use anyhow::Result;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
-use std::sync::OnceLock;
+use std::sync::{Arc, OnceLock};
 
 const TEXT_EXTENSIONS: &[&str] = &[
     "txt",
@@ -340,8 +341,112 @@ const MAX_CONTENT_CHECK_SIZE: u64 = 20 * 1024 * 1024;
 
 const CONTENT_CHECK_BUFFER_SIZE: usize = 1024;
 
+/// Bytes read from the start of a file to guess whether it was generated.
+const GENERATED_CHECK_BUFFER_SIZE: u64 = 8 * 1024;
+
+/// Markers that code generators put into the header of their output.
+const GENERATED_MARKERS: &[&str] = &["@generated", "DO NOT EDIT"];
+
+/// Number of leading lines searched for a generated-file marker.
+const GENERATED_MARKER_LINES: usize = 5;
+
+/// Samples with a longer average line length are considered minified.
+const MINIFIED_AVG_LINE_LENGTH: usize = 300;
+
+/// Samples shorter than this are never considered minified, so a single long line in
+/// a small file is not enough.
+const MINIFIED_MIN_SAMPLE_SIZE: usize = 1024;
+
+/// User overrides of the built-in extension lists, from `AppConfig::text_extensions`
+/// and `AppConfig::force_binary_extensions`. Extensions are matched case-insensitively,
+/// with or without a leading dot.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtensionOverrides {
+    text: HashSet<String>,
+    binary: HashSet<String>,
+}
+
+impl ExtensionOverrides {
+    pub fn new<'a>(
+        text: impl IntoIterator<Item = &'a String>,
+        binary: impl IntoIterator<Item = &'a String>,
+    ) -> Self {
+        Self {
+            text: normalize_extensions(text),
+            binary: normalize_extensions(binary),
+        }
+    }
+
+    /// `Some(true)` if `path` has an extension forced to text, `Some(false)` if it has
+    /// one forced to binary. An extension listed as both is treated as binary.
+    pub fn classify(&self, path: &Path) -> Option<bool> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        if self.binary.contains(&extension) {
+            Some(false)
+        } else if self.text.contains(&extension) {
+            Some(true)
+        } else {
+            None
+        }
+    }
+}
+
+/// User mappings from extension to language tag, from `AppConfig::language_overrides`.
+/// They win over the built-in mapping of `get_language_from_path`. Extensions are
+/// matched case-insensitively, with or without a leading dot.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LanguageOverrides {
+    languages: BTreeMap<String, String>,
+}
+
+impl LanguageOverrides {
+    /// Returns the overrides for `languages`, shared so that a `ContentTransform` can
+    /// refer to them. `None` if no entry is left after dropping blank extensions and
+    /// languages.
+    pub fn build(languages: &HashMap<String, String>) -> Option<Arc<LanguageOverrides>> {
+        let languages: BTreeMap<String, String> = languages
+            .iter()
+            .map(|(ext, language)| {
+                (
+                    ext.trim().trim_start_matches('.').to_lowercase(),
+                    language.trim().to_string(),
+                )
+            })
+            .filter(|(ext, language)| !ext.is_empty() && !language.is_empty())
+            .collect();
+        if languages.is_empty() {
+            return None;
+        }
+        Some(Arc::new(LanguageOverrides { languages }))
+    }
+
+    /// The language configured for the extension of `path`, if any.
+    fn language(&self, path: &Path) -> Option<&str> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        self.languages.get(&extension).map(String::as_str)
+    }
+}
+
+/// Lowercases configured extensions and strips a leading dot. Blank entries are dropped.
+fn normalize_extensions<'a>(extensions: impl IntoIterator<Item = &'a String>) -> HashSet<String> {
+    extensions
+        .into_iter()
+        .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect()
+}
+
 /// Determines if a file is likely to be a text file.
 pub fn is_text_file(path: &Path) -> Result<bool> {
+    is_text_file_with(path, &ExtensionOverrides::default())
+}
+
+/// Like `is_text_file`, but extensions in `overrides` are classified without looking
+/// at the built-in lists or the file's content.
+pub fn is_text_file_with(path: &Path, overrides: &ExtensionOverrides) -> Result<bool> {
+    if let Some(is_text) = overrides.classify(path) {
+        return Ok(is_text);
+    }
     if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
         let ext_lower = extension.to_lowercase();
         if get_text_ext_set().contains(ext_lower.as_str()) {
@@ -368,6 +473,38 @@ pub fn is_text_file(path: &Path) -> Result<bool> {
     check_file_content_optimized(path)
 }
 
+/// Guesses whether a text file is minified or generated, e.g. `bundle.min.js` or a file
+/// with a `@generated` / `DO NOT EDIT` header. Only the start of the file is read.
+pub fn is_likely_generated(path: &Path) -> bool {
+    let mut sample = Vec::new();
+    match File::open(path) {
+        Ok(file) => {
+            if file
+                .take(GENERATED_CHECK_BUFFER_SIZE)
+                .read_to_end(&mut sample)
+                .is_err()
+            {
+                return false;
+            }
+        }
+        Err(_) => return false,
+    }
+    looks_generated(&String::from_utf8_lossy(&sample))
+}
+
+/// Applies the generated-file heuristics to the start of a file's content.
+fn looks_generated(sample: &str) -> bool {
+    let has_marker = sample
+        .lines()
+        .take(GENERATED_MARKER_LINES)
+        .any(|line| GENERATED_MARKERS.iter().any(|marker| line.contains(marker)));
+    if has_marker {
+        return true;
+    }
+    let line_count = sample.lines().count().max(1);
+    sample.len() >= MINIFIED_MIN_SAMPLE_SIZE && sample.len() / line_count > MINIFIED_AVG_LINE_LENGTH
+}
+
 /// Determines if a file is an image file.
 #[allow(dead_code)]
 pub fn is_image_file(path: &Path) -> bool {
@@ -379,6 +516,35 @@ pub fn is_image_file(path: &Path) -> bool {
     }
 }
 
+/// Determines the programming language from a file path for syntax highlighting.
+/// `overrides` are consulted before the built-in mapping.
+pub fn get_language_from_path(path: &Path, overrides: Option<&LanguageOverrides>) -> String {
+    if let Some(language) = overrides.and_then(|overrides| overrides.language(path)) {
+        return language.to_string();
+    }
+    match path.extension().and_then(|s| s.to_str()) {
+        Some("rs") => "rust",
+        Some("js") | Some("mjs") | Some("cjs") => "javascript",
+        Some("ts") | Some("tsx") => "typescript",
+        Some("py") => "python",
+        Some("html") | Some("htm") => "html",
+        Some("css") => "css",
+        Some("json") => "json",
+        Some("md") => "markdown",
+        Some("toml") => "toml",
+        Some("yaml") | Some("yml") => "yaml",
+        Some("sh") => "shell",
+        Some("sql") => "sql",
+        Some("lua") => "lua",
+        Some("go") => "go",
+        Some("java") => "java",
+        Some("c") | Some("h") => "c",
+        Some("cpp") | Some("hpp") | Some("cxx") | Some("hxx") => "cpp",
+        _ => "plaintext",
+    }
+    .to_string()
+}
+
 /// Checks the initial bytes of a file to guess if it's text.
 fn check_file_content_optimized(path: &Path) -> Result<bool> {
     let mut buffer = [0u8; CONTENT_CHECK_BUFFER_SIZE];
@@ -435,6 +601,24 @@ mod tests {
         path
     }
 
+    #[test]
+    fn extension_overrides_take_precedence_over_detection() {
+        let dir = TempDir::new().unwrap();
+        let custom = create_test_file(&dir, "schema.custom", &[0xFF, 0xFE, 0x00, 0x01]);
+        let pdf = create_test_file(&dir, "notes.PDF", b"plain text after all");
+        let readme = create_test_file(&dir, "README.md", b"# Title");
+        let overrides = ExtensionOverrides::new(
+            &[".Custom".to_string(), "md".to_string()],
+            &["pdf".to_string(), "md".to_string()],
+        );
+
+        assert!(!is_text_file(&custom).unwrap());
+        assert!(is_text_file_with(&custom, &overrides).unwrap());
+        assert!(!is_text_file_with(&pdf, &overrides).unwrap());
+        // Listed as both, binary wins.
+        assert!(!is_text_file_with(&readme, &overrides).unwrap());
+    }
+
     #[test]
     fn test_is_text_by_known_text_extension() {
         let dir = TempDir::new().unwrap();
@@ -563,4 +747,63 @@ mod tests {
         assert_eq!(results[2], (false, false));
         assert_eq!(results[3], (true, false));
     }
+
+    #[test]
+    fn test_is_likely_generated_detects_markers_and_minified_code() {
+        let dir = TempDir::new().unwrap();
+        let marked = create_test_file(
+            &dir,
+            "schema.generated.rs",
+            b"// @generated by build.rs\nfn main() {}\n",
+        );
+        let do_not_edit = create_test_file(&dir, "api.go", b"// Code generated. DO NOT EDIT.\n");
+        let minified = create_test_file(&dir, "bundle.min.js", "var a=1;".repeat(500).as_bytes());
+        let handwritten = create_test_file(
+            &dir,
+            "main.rs",
+            "fn main() {\n    println!(\"hi\");\n}\n"
+                .repeat(100)
+                .as_bytes(),
+        );
+        let late_marker = create_test_file(
+            &dir,
+            "notes.md",
+            format!("{}DO NOT EDIT\n", "line\n".repeat(10)).as_bytes(),
+        );
+
+        assert!(is_likely_generated(&marked));
+        assert!(is_likely_generated(&do_not_edit));
+        assert!(is_likely_generated(&minified));
+        assert!(!is_likely_generated(&handwritten));
+        assert!(!is_likely_generated(&late_marker));
+        assert!(!is_likely_generated(&dir.path().join("missing.js")));
+    }
+
+    #[test]
+    fn language_overrides_win_over_the_built_in_mapping() {
+        let overrides = LanguageOverrides::build(&HashMap::from([
+            (".TF".to_string(), "terraform".to_string()),
+            ("h".to_string(), "cpp".to_string()),
+            ("jinja".to_string(), " ".to_string()),
+        ]));
+
+        assert_eq!(
+            get_language_from_path(Path::new("main.tf"), overrides.as_deref()),
+            "terraform"
+        );
+        assert_eq!(
+            get_language_from_path(Path::new("util.h"), overrides.as_deref()),
+            "cpp"
+        );
+        assert_eq!(get_language_from_path(Path::new("util.h"), None), "c");
+        assert_eq!(
+            get_language_from_path(Path::new("page.jinja"), overrides.as_deref()),
+            "plaintext"
+        );
+        assert_eq!(
+            get_language_from_path(Path::new("main.rs"), overrides.as_deref()),
+            "rust"
+        );
+        assert!(LanguageOverrides::build(&HashMap::new()).is_none());
+    }
 }
@@ -0,0 +1,49 @@
+//! Sets up logging to a daily rolling file in the platform data directory and, in
+//! debug builds, to stderr.
+
+use crate::config::settings;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+/// Log files are named `cfc.log.<date>`, one per day.
+const LOG_FILE_PREFIX: &str = "cfc.log";
+
+/// Installs the global subscriber. `RUST_LOG` takes precedence over `log_level`; an
+/// invalid level falls back to `info`.
+///
+/// The returned guard flushes the file writer when dropped and must be kept alive for
+/// the lifetime of the program. It is `None` if no log directory is available, in
+/// which case only stderr is logged to (in debug builds).
+pub fn init(log_level: &str) -> Option<WorkerGuard> {
+    let filter = || {
+        EnvFilter::try_from_default_env()
+            .or_else(|_| EnvFilter::try_new(log_level))
+            .unwrap_or_else(|_| EnvFilter::new("info"))
+    };
+
+    let (file_layer, guard) = match settings::get_log_dir() {
+        Some(dir) => {
+            let appender = tracing_appender::rolling::daily(dir, LOG_FILE_PREFIX);
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let layer = fmt::layer()
+                .with_writer(writer)
+                .with_ansi(false)
+                .with_filter(filter());
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    #[cfg(debug_assertions)]
+    let stderr_layer = Some(fmt::layer().with_filter(filter()));
+    #[cfg(not(debug_assertions))]
+    let stderr_layer: Option<fmt::Layer<_>> = None;
+
+    tracing_subscriber::registry()
+        .with(file_layer)
+        .with(stderr_layer)
+        .init();
+    guard
+}
@@ -1,3 +1,6 @@
 pub mod file_detection;
+pub mod logging;
 #[cfg(test)]
 pub mod test_helpers;
+pub mod thread_pool;
+pub mod unicode;
@@ -0,0 +1,72 @@
+//! Runs parallel work on a size-limited rayon pool when a thread limit is configured.
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// The most recently built limited pool, reused while the limit stays the same.
+static LIMITED_POOL: OnceLock<Mutex<Option<(usize, Arc<ThreadPool>)>>> = OnceLock::new();
+
+/// Runs `op` with rayon's parallel iterators bounded by `max_threads`.
+///
+/// With `None` the global rayon pool is used, i.e. one thread per core. With
+/// `Some(n)`, `op` runs on a dedicated pool of `n` threads, which also bounds how
+/// many files are read into memory at the same time by parallel sections. If the
+/// pool cannot be built, `op` falls back to the global pool.
+pub fn install<R, F>(max_threads: Option<usize>, op: F) -> R
+where
+    R: Send,
+    F: FnOnce() -> R + Send,
+{
+    match max_threads.and_then(limited_pool) {
+        Some(pool) => pool.install(op),
+        None => op(),
+    }
+}
+
+/// Returns a pool with exactly `threads` threads, building it on first use.
+fn limited_pool(threads: usize) -> Option<Arc<ThreadPool>> {
+    let threads = threads.max(1);
+    let mut cached = LIMITED_POOL
+        .get_or_init(|| Mutex::new(None))
+        .lock()
+        .expect("Mutex was poisoned. This should not happen.");
+
+    if let Some((size, pool)) = cached.as_ref() {
+        if *size == threads {
+            return Some(pool.clone());
+        }
+    }
+
+    match ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("cfc-worker-{i}"))
+        .build()
+    {
+        Ok(pool) => {
+            let pool = Arc::new(pool);
+            *cached = Some((threads, pool.clone()));
+            Some(pool)
+        }
+        Err(e) => {
+            tracing::warn!("Failed to build a {}-thread pool: {}", threads, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limited_pool_bounds_rayon_parallelism() {
+        assert_eq!(install(Some(2), rayon::current_num_threads), 2);
+        assert_eq!(install(Some(0), rayon::current_num_threads), 1);
+    }
+
+    #[test]
+    fn no_limit_uses_the_global_pool() {
+        let global = rayon::current_num_threads();
+        assert_eq!(install(None, rayon::current_num_threads), global);
+    }
+}
@@ -0,0 +1,43 @@
+//! Unicode normalization for comparing user input with filesystem paths.
+//!
+//! macOS stores file names decomposed (NFD), while typed text is usually composed
+//! (NFC), so an `é` in a path and the same `é` in a query are different byte
+//! sequences. Both sides are brought to NFC before they are compared.
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+/// Returns `text` in NFC, borrowing it if it already is.
+pub fn nfc(text: &str) -> Cow<'_, str> {
+    if is_nfc_quick(text.chars()) == IsNormalized::Yes {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(text.nfc().collect())
+    }
+}
+
+/// Returns `path` in NFC, borrowing it if it already is. Paths that are not valid
+/// UTF-8 are returned unchanged.
+pub fn nfc_path(path: &Path) -> Cow<'_, Path> {
+    match path.to_str().map(nfc) {
+        Some(Cow::Owned(normalized)) => Cow::Owned(PathBuf::from(normalized)),
+        _ => Cow::Borrowed(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decomposed_text_is_composed_and_composed_text_is_borrowed() {
+        let decomposed = "Cafe\u{301}/re\u{301}sume\u{301}.txt";
+        assert_eq!(nfc(decomposed), "Caf\u{e9}/r\u{e9}sum\u{e9}.txt");
+        assert!(matches!(nfc("plain.rs"), Cow::Borrowed(_)));
+        assert_eq!(
+            nfc_path(Path::new(decomposed)),
+            Path::new("Caf\u{e9}/r\u{e9}sum\u{e9}.txt")
+        );
+    }
+}